@@ -0,0 +1,13 @@
+//! Generates the gRPC control API from `proto/control.proto` and the aggregator
+//! handshake from `proto/aggregator.proto`, and rebuilds on new task store migrations
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto");
+    // Embedded by `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=migrations");
+    // protox compiles the protos in pure Rust, so building doesn't need protoc
+    let file_descriptors =
+        protox::compile(["proto/control.proto", "proto/aggregator.proto"], ["proto"])?;
+    tonic_build::configure().compile_fds(file_descriptors)?;
+    Ok(())
+}
@@ -0,0 +1,20 @@
+-- Operators sharing the database each keep their own rows and cursor
+CREATE TABLE IF NOT EXISTS tasks (
+    operator TEXT NOT NULL,
+    task_index BIGINT NOT NULL,
+    task_name TEXT NOT NULL,
+    task_created_block BIGINT NOT NULL,
+    block_number BIGINT,
+    created_tx TEXT,
+    outcome TEXT,
+    response_tx TEXT,
+    updated_at BIGINT NOT NULL,
+    PRIMARY KEY (operator, task_index)
+);
+
+CREATE INDEX IF NOT EXISTS tasks_task_index ON tasks (task_index);
+
+CREATE TABLE IF NOT EXISTS cursors (
+    operator TEXT PRIMARY KEY,
+    next_block BIGINT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Block timestamps of the NewTaskCreated event and of the response, for latency SLOs
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS created_at BIGINT;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS responded_at BIGINT;
+CREATE INDEX IF NOT EXISTS tasks_created_at ON tasks (operator, created_at);
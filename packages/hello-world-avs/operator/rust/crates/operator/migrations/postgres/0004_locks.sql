@@ -0,0 +1,8 @@
+-- Expiring locks of the task claims and the leader lease, shared by the replicas of an operator
+CREATE TABLE IF NOT EXISTS locks (
+    operator TEXT NOT NULL,
+    name TEXT NOT NULL,
+    holder TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (operator, name)
+);
@@ -0,0 +1,5 @@
+-- Gas used by the response and the time the task was first seen, for `operator tasks export`
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS gas_used BIGINT;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS seen_at BIGINT;
+UPDATE tasks SET seen_at = updated_at WHERE seen_at IS NULL;
+CREATE INDEX IF NOT EXISTS tasks_seen_at ON tasks (operator, seen_at);
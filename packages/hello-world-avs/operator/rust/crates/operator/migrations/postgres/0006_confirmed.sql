@@ -0,0 +1,3 @@
+-- Responses buried under reorg.confirmations blocks, the others may still be reorged out
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS confirmed BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE tasks SET confirmed = TRUE WHERE response_block IS NOT NULL;
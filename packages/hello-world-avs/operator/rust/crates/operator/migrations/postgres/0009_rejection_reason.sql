@@ -0,0 +1,2 @@
+-- Why the operator refused to attest to a rejected task, a policy rule or the price check
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS rejection_reason TEXT;
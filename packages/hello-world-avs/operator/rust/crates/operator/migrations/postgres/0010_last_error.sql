@@ -0,0 +1,2 @@
+-- Message of the error the last failed response ran into, next to its failure_class
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS last_error TEXT;
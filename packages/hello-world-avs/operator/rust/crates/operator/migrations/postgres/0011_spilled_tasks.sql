@@ -0,0 +1,12 @@
+-- Tasks the full in-memory queue spilled over, read back in index order as it drains
+CREATE TABLE IF NOT EXISTS spilled_tasks (
+    operator TEXT NOT NULL,
+    task_index BIGINT NOT NULL,
+    task_name TEXT NOT NULL,
+    task_created_block BIGINT NOT NULL,
+    block_number BIGINT,
+    block_hash TEXT,
+    created_tx TEXT,
+    spilled_at BIGINT NOT NULL,
+    PRIMARY KEY (operator, task_index)
+);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS tasks (
+    task_index INTEGER PRIMARY KEY,
+    task_name TEXT NOT NULL,
+    task_created_block INTEGER NOT NULL,
+    block_number INTEGER,
+    created_tx TEXT,
+    outcome TEXT,
+    response_tx TEXT,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    next_block INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Lets reorg handling find the responses mined in dropped blocks
+ALTER TABLE tasks ADD COLUMN response_block INTEGER;
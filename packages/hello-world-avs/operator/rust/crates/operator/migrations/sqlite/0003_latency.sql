@@ -0,0 +1,3 @@
+-- Block timestamps of the NewTaskCreated event and of the response, for latency SLOs
+ALTER TABLE tasks ADD COLUMN created_at INTEGER;
+ALTER TABLE tasks ADD COLUMN responded_at INTEGER;
@@ -0,0 +1,6 @@
+-- Expiring locks of the task claims and the leader lease of replicated operators
+CREATE TABLE IF NOT EXISTS locks (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
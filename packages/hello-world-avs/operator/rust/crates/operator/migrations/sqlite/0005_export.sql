@@ -0,0 +1,4 @@
+-- Gas used by the response and the time the task was first seen, for `operator tasks export`
+ALTER TABLE tasks ADD COLUMN gas_used INTEGER;
+ALTER TABLE tasks ADD COLUMN seen_at INTEGER;
+UPDATE tasks SET seen_at = updated_at;
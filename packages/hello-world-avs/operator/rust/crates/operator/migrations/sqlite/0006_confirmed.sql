@@ -0,0 +1,3 @@
+-- Responses buried under reorg.confirmations blocks, the others may still be reorged out
+ALTER TABLE tasks ADD COLUMN confirmed INTEGER NOT NULL DEFAULT 0;
+UPDATE tasks SET confirmed = 1 WHERE response_block IS NOT NULL;
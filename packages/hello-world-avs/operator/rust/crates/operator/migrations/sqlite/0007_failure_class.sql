@@ -0,0 +1,2 @@
+-- Error class of the last failed response, for the failure breakdown of `spam-tasks report`
+ALTER TABLE tasks ADD COLUMN failure_class TEXT;
@@ -0,0 +1,2 @@
+-- Failed attempts at responding, a task is dead-lettered after dead_letter.max_attempts
+ALTER TABLE tasks ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+-- Tasks the full in-memory queue spilled over, read back in index order as it drains
+CREATE TABLE IF NOT EXISTS spilled_tasks (
+    task_index INTEGER PRIMARY KEY,
+    task_name TEXT NOT NULL,
+    task_created_block INTEGER NOT NULL,
+    block_number INTEGER,
+    block_hash TEXT,
+    created_tx TEXT,
+    spilled_at INTEGER NOT NULL
+);
@@ -0,0 +1,310 @@
+use std::{collections::BTreeSet, fmt, path::Path, process::Command};
+
+use alloy::{
+    json_abi::JsonAbi,
+    primitives::{hex, Selector, B256},
+};
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry::{
+    ECDSAStakeRegistryCalls, ECDSAStakeRegistryErrors, ECDSAStakeRegistryEvents,
+};
+use swap_manager_utils::SwapManager::SwapManager::{
+    SwapManagerCalls, SwapManagerErrors, SwapManagerEvents,
+};
+use tracing::info;
+
+use crate::error::AbiError;
+
+/// Foundry project of the contracts, relative to the repository root
+pub const DEFAULT_CONTRACTS_DIR: &str = "contracts";
+
+/// Bindings module of `swap_manager_utils`, relative to the repository root
+pub const DEFAULT_BINDINGS_DIR: &str = "operator/rust/crates/utils/src/bindings";
+
+/// `mod.rs` of the bindings, keeping the SwapManager bindings at the path the crates use
+const BINDINGS_MOD: &str = r#"#![allow(unused_imports, non_snake_case, clippy::all, rustdoc::all)]
+//! This module contains the sol! generated bindings for solidity contracts.
+//! This is autogenerated code.
+//! Do not manually edit these files.
+//! These files may be overwritten by the codegen system at any time.
+pub mod r#ecdsastakeregistry;
+#[path = "helloworldservicemanager.rs"]
+pub mod r#SwapManager;
+"#;
+
+/// Contract with bindings in `swap_manager_utils`, and the selectors they were generated with
+#[derive(Debug, Clone, Copy)]
+pub struct BoundContract {
+    /// Name of the contract, and of its Foundry artifact
+    pub name: &'static str,
+    /// File of the bindings in the bindings module
+    pub file: &'static str,
+    functions: &'static [[u8; 4]],
+    errors: &'static [[u8; 4]],
+    events: &'static [[u8; 32]],
+}
+
+/// Contracts of the bindings module
+pub const BOUND_CONTRACTS: &[BoundContract] = &[
+    BoundContract {
+        name: "HelloWorldServiceManager",
+        file: "helloworldservicemanager.rs",
+        functions: SwapManagerCalls::SELECTORS,
+        errors: SwapManagerErrors::SELECTORS,
+        events: SwapManagerEvents::SELECTORS,
+    },
+    BoundContract {
+        name: "ECDSAStakeRegistry",
+        file: "ecdsastakeregistry.rs",
+        functions: ECDSAStakeRegistryCalls::SELECTORS,
+        errors: ECDSAStakeRegistryErrors::SELECTORS,
+        events: ECDSAStakeRegistryEvents::SELECTORS,
+    },
+];
+
+/// Differences between the bindings of a contract and its compiled ABI
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AbiDrift {
+    /// Name of the contract
+    pub contract: &'static str,
+    /// Functions, errors and events of the ABI without bindings
+    pub unbound: Vec<String>,
+    /// Selectors of the bindings no longer in the ABI
+    pub stale: Vec<String>,
+}
+
+impl AbiDrift {
+    /// Whether the bindings match the ABI
+    pub fn is_empty(&self) -> bool {
+        self.unbound.is_empty() && self.stale.is_empty()
+    }
+}
+
+impl fmt::Display for AbiDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bindings drifted from the compiled ABI",
+            self.contract
+        )?;
+        for signature in &self.unbound {
+            write!(f, "\n  + {signature}")?;
+        }
+        for selector in &self.stale {
+            write!(f, "\n  - {selector}")?;
+        }
+        Ok(())
+    }
+}
+
+impl BoundContract {
+    /// Compare the bindings with the compiled `abi` of the contract
+    pub fn drift(&self, abi: &JsonAbi) -> AbiDrift {
+        let mut drift = AbiDrift {
+            contract: self.name,
+            ..AbiDrift::default()
+        };
+        compare(
+            &mut drift,
+            self.functions,
+            abi.functions()
+                .map(|function| (function.selector(), function.signature())),
+            |selector| Selector::from(*selector),
+            "function",
+        );
+        compare(
+            &mut drift,
+            self.errors,
+            abi.errors()
+                .map(|error| (error.selector(), error.signature())),
+            |selector| Selector::from(*selector),
+            "error",
+        );
+        compare(
+            &mut drift,
+            self.events,
+            abi.events()
+                .filter(|event| !event.anonymous)
+                .map(|event| (event.selector(), event.signature())),
+            |selector| B256::from(*selector),
+            "event",
+        );
+        drift
+    }
+}
+
+/// Record in `drift` the `compiled` items of a `kind` missing from the `bound` selectors,
+/// and the other way round
+fn compare<S, K>(
+    drift: &mut AbiDrift,
+    bound: &[S],
+    compiled: impl Iterator<Item = (K, String)>,
+    key: impl Fn(&S) -> K,
+    kind: &str,
+) where
+    K: Ord + AsRef<[u8]>,
+{
+    let bound: BTreeSet<K> = bound.iter().map(key).collect();
+    let mut seen = BTreeSet::new();
+    for (selector, signature) in compiled {
+        if !bound.contains(&selector) {
+            drift.unbound.push(format!("{kind} {signature}"));
+        }
+        seen.insert(selector);
+    }
+    drift.stale.extend(
+        bound
+            .iter()
+            .filter(|selector| !seen.contains(*selector))
+            .map(|selector| format!("{kind} {}", hex::encode_prefixed(selector))),
+    );
+}
+
+/// ABI of a Foundry artifact, or of a file holding the bare JSON ABI
+pub fn load_abi(path: &Path) -> Result<JsonAbi, AbiError> {
+    let data = std::fs::read_to_string(path).map_err(|source| AbiError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let parse = |source| AbiError::Parse {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut value: serde_json::Value = serde_json::from_str(&data).map_err(parse)?;
+    if let Some(abi) = value.get_mut("abi") {
+        value = abi.take();
+    }
+    serde_json::from_value(value).map_err(parse)
+}
+
+/// ABI of `contract` in `dir`, either a Foundry `out/` directory or one holding bare JSON
+/// ABIs such as `abis/`
+pub fn find_abi(dir: &Path, contract: &'static str) -> Result<JsonAbi, AbiError> {
+    let candidates = [
+        dir.join(format!("{contract}.sol"))
+            .join(format!("{contract}.json")),
+        dir.join(format!("{contract}.json")),
+    ];
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) => load_abi(path),
+        None => Err(AbiError::NotFound {
+            contract,
+            dir: dir.to_path_buf(),
+        }),
+    }
+}
+
+/// Drift of every bound contract whose bindings don't match its ABI in `dir`
+pub fn check_bindings(dir: &Path) -> Result<Vec<AbiDrift>, AbiError> {
+    let mut drifts = Vec::new();
+    for contract in BOUND_CONTRACTS {
+        let drift = contract.drift(&find_abi(dir, contract.name)?);
+        if !drift.is_empty() {
+            drifts.push(drift);
+        }
+    }
+    Ok(drifts)
+}
+
+/// Compile the Foundry project at `contracts_dir` and regenerate the bindings of
+/// [`BOUND_CONTRACTS`] into `bindings_dir`, replacing what it holds
+pub fn regenerate_bindings(contracts_dir: &Path, bindings_dir: &Path) -> Result<(), AbiError> {
+    forge(
+        "build",
+        Command::new("forge")
+            .args(["build", "--force", "--skip", "test", "--skip", "script"])
+            .current_dir(contracts_dir),
+    )?;
+    let io = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| AbiError::Io { path, source }
+    };
+    if bindings_dir.exists() {
+        std::fs::remove_dir_all(bindings_dir).map_err(io(bindings_dir))?;
+    }
+    let mut bind = Command::new("forge");
+    bind.args(["bind", "--alloy", "--skip-build", "--overwrite", "--module"])
+        .arg("--root")
+        .arg(contracts_dir)
+        .arg("--bindings-path")
+        .arg(bindings_dir);
+    for contract in BOUND_CONTRACTS {
+        bind.arg("--select").arg(format!("^{}$", contract.name));
+    }
+    forge("bind", &mut bind)?;
+
+    // forge names the files after the contracts, the crates expect the historical names
+    for contract in BOUND_CONTRACTS {
+        let generated = bindings_dir.join(format!("{}.rs", contract.name.to_lowercase()));
+        let file = bindings_dir.join(contract.file);
+        if generated != file {
+            std::fs::rename(&generated, &file).map_err(io(&generated))?;
+        }
+    }
+    let module = bindings_dir.join("mod.rs");
+    std::fs::write(&module, BINDINGS_MOD).map_err(io(&module))?;
+    info!(bindings = %bindings_dir.display(), "Bindings regenerated");
+    Ok(())
+}
+
+fn forge(step: &'static str, command: &mut Command) -> Result<(), AbiError> {
+    let status = command.status().map_err(|e| AbiError::Forge {
+        step,
+        reason: e.to_string(),
+    })?;
+    if !status.success() {
+        return Err(AbiError::Forge {
+            step,
+            reason: status.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_of_a_foundry_artifact() {
+        let artifact = serde_json::json!({
+            "abi": JsonAbi::parse([
+                "error OnlyStakeRegistry()",
+                "error TaskAlreadyResponded(uint32 taskIndex)",
+            ])
+            .unwrap(),
+            "bytecode": { "object": "0x" },
+        });
+        let dir = std::env::temp_dir().join(format!("abi-out-{}", std::process::id()));
+        let artifact_dir = dir.join("HelloWorldServiceManager.sol");
+        std::fs::create_dir_all(&artifact_dir).unwrap();
+        std::fs::write(
+            artifact_dir.join("HelloWorldServiceManager.json"),
+            artifact.to_string(),
+        )
+        .unwrap();
+
+        let contract = BOUND_CONTRACTS[0];
+        let drift = contract.drift(&find_abi(&dir, "HelloWorldServiceManager").unwrap());
+        assert_eq!(drift.unbound, ["error TaskAlreadyResponded(uint32)"]);
+        assert_eq!(
+            drift.stale.len(),
+            contract.functions.len() + contract.errors.len() + contract.events.len() - 1
+        );
+        assert!(!drift.stale.contains(&"error 0x46bf2281".to_string()));
+        assert!(matches!(
+            find_abi(&dir, "ECDSAStakeRegistry"),
+            Err(AbiError::NotFound { .. })
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_bindings_match_the_checked_in_abis() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../..");
+        let drifts = check_bindings(&root.join("abis")).unwrap();
+        assert!(drifts.is_empty(), "{drifts:?}");
+        let module =
+            std::fs::read_to_string(root.join(DEFAULT_BINDINGS_DIR).join("mod.rs")).unwrap();
+        assert_eq!(module, BINDINGS_MOD);
+    }
+}
@@ -0,0 +1,851 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use alloy::{
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    signers::Signature,
+    sol_types::SolValue,
+};
+use axum::{
+    body::Bytes as Body,
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use eigensdk::common::SdkSigner;
+use prost::Message;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
+use swap_manager_utils::SwapManager::{ISwapManager::Task, SwapManager};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::error::{AggregatorError, ChainError, ConfigError};
+use crate::handshake::{
+    proto::HelloReply, task_types_to_proto, Hello, SoftwareVersion, TaskType, HELLO_PATH,
+    PROTOBUF_CONTENT_TYPE, PROTOCOL_VERSION,
+};
+use crate::nonce_manager::NonceManager;
+use crate::pending_tx::PendingTxWatcher;
+use crate::prometheus::{
+    record_gas_used, record_task_responded, record_tx_failure, PendingTxGuard,
+};
+use crate::quoting::SignedQuote;
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::signer::OperatorSigner;
+use crate::socket::{SocketAnnouncement, SOCKETS_PATH};
+use crate::task_responder::{encode_signature_data, task_response_digest};
+use crate::webhook::WebhookEvent;
+
+const RESPOND_TO_TASK: &str = "respondToTask";
+
+/// Path operators post their [`SignedTaskResponse`] to
+pub const RESPONSES_PATH: &str = "/v1/responses";
+
+/// Aggregator settings, the `[aggregator]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AggregatorConfig {
+    /// Address the HTTP endpoint listens on (`AGGREGATOR_LISTEN_ADDR`)
+    pub listen_addr: SocketAddr,
+    /// Percentage of the total stake weight at the task's reference block the signers must
+    /// hold before the response is submitted, on top of the stake registry threshold
+    /// (`AGGREGATOR_QUORUM_THRESHOLD_PERCENT`)
+    pub quorum_threshold_percent: Option<f64>,
+    /// Oldest operator software version accepted in a hello, any if unset
+    /// (`AGGREGATOR_MIN_OPERATOR_VERSION`)
+    pub min_operator_version: Option<String>,
+    /// Turn down the responses of operators that never said hello (`AGGREGATOR_REQUIRE_HELLO`)
+    pub require_hello: bool,
+    /// Task types responses are aggregated for, operators only get the ones they declared
+    pub task_types: Vec<TaskType>,
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 8090)),
+            quorum_threshold_percent: None,
+            min_operator_version: None,
+            require_hello: false,
+            task_types: TaskType::ALL.to_vec(),
+        }
+    }
+}
+
+impl AggregatorConfig {
+    /// Check the settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(percent) = self.quorum_threshold_percent {
+            if !(percent > 0.0 && percent <= 100.0) {
+                return Err(ConfigError::Invalid(format!(
+                    "aggregator.quorum_threshold_percent must be in (0, 100], got {percent}"
+                )));
+            }
+        }
+        self.min_operator_version()?;
+        if self.task_types.is_empty() {
+            return Err(ConfigError::Invalid(
+                "aggregator.task_types needs at least one task type".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The parsed `min_operator_version`, if set
+    pub fn min_operator_version(&self) -> Result<Option<SoftwareVersion>, ConfigError> {
+        self.min_operator_version
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+    }
+}
+
+/// Weight the signers of a task must hold: the stake registry threshold at the task's
+/// reference block, raised to `percent` of the total weight at that block if set
+pub fn quorum_threshold(
+    registry_threshold: U256,
+    total_weight: U256,
+    percent: Option<f64>,
+) -> U256 {
+    let Some(percent) = percent else {
+        return registry_threshold;
+    };
+    let basis_points = U256::from((percent * 100.0).round() as u64);
+    let scale = U256::from(10_000);
+    let configured = (total_weight * basis_points + scale - U256::from(1)) / scale;
+    registry_threshold.max(configured)
+}
+
+/// Task response signed by one operator, as sent to the aggregator
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedTaskResponse {
+    /// Index of the task in the SwapManager
+    pub task_index: u32,
+    /// Name of the task
+    pub task_name: String,
+    /// Block the task was created in
+    pub task_created_block: u32,
+    /// Operator that signed the response
+    pub operator: Address,
+    /// 65 byte ECDSA signature over [`task_response_digest`]
+    pub signature: Bytes,
+    /// Quote of the task's swap, for tasks carrying a swap payload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<SignedQuote>,
+}
+
+impl SignedTaskResponse {
+    /// The task this response is for
+    pub fn task(&self) -> Task {
+        Task {
+            name: self.task_name.clone(),
+            taskCreatedBlock: self.task_created_block,
+        }
+    }
+
+    /// Check that the signature, and the one of the quote if any, recovers to `operator`
+    pub fn verify(&self) -> Result<(), AggregatorError> {
+        if let Some(quote) = &self.quote {
+            quote
+                .verify(self.task_index, self.operator)
+                .map_err(AggregatorError::InvalidSignature)?;
+        }
+        let signature = Signature::from_raw(&self.signature)
+            .map_err(|e| AggregatorError::InvalidSignature(format!("malformed signature: {e}")))?;
+        let signer = signature
+            .recover_address_from_prehash(&task_response_digest(&self.task_name))
+            .map_err(|e| {
+                AggregatorError::InvalidSignature(format!("unrecoverable signature: {e}"))
+            })?;
+        if signer != self.operator {
+            return Err(AggregatorError::InvalidSignature(format!(
+                "signature of {signer} does not match operator {}",
+                self.operator
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of submitting a response to the aggregator
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum AggregationStatus {
+    /// The quorum has not been reached yet
+    Pending {
+        /// Weight of the operators that signed so far
+        signed_weight: U256,
+        /// Weight needed to submit
+        threshold_weight: U256,
+    },
+    /// The aggregated response is being submitted by another request
+    Submitting,
+    /// The aggregated response was mined
+    Submitted {
+        /// Hash of the respondToTask transaction
+        tx_hash: B256,
+    },
+}
+
+/// Signatures collected for one task, keyed by operator so they come out sorted
+#[derive(Debug, Default)]
+pub struct QuorumTracker {
+    signatures: BTreeMap<Address, (Bytes, U256)>,
+}
+
+impl QuorumTracker {
+    /// Record the signature of an operator with stake `weight`, replacing an earlier one
+    pub fn insert(&mut self, operator: Address, signature: Bytes, weight: U256) {
+        self.signatures.insert(operator, (signature, weight));
+    }
+
+    /// Total weight of the operators that signed
+    pub fn signed_weight(&self) -> U256 {
+        self.signatures.values().map(|(_, weight)| *weight).sum()
+    }
+
+    /// Signers and signatures in ascending signer order, as the stake registry requires
+    pub fn signers(&self) -> (Vec<Address>, Vec<Bytes>) {
+        self.signatures
+            .iter()
+            .map(|(operator, (signature, _))| (*operator, signature.clone()))
+            .unzip()
+    }
+}
+
+#[derive(Debug)]
+enum TaskState {
+    Collecting(QuorumTracker),
+    Submitting,
+    Submitted(B256),
+}
+
+/// Collects operator signatures per task and submits respondToTask once the signed
+/// stake reaches the stake registry threshold.
+///
+/// The SwapManager checks responses with an `ECDSAStakeRegistry`, so the aggregate is the
+/// sorted list of operator ECDSA signatures rather than a single BLS signature.
+#[derive(Debug)]
+pub struct Aggregator {
+    provider: SdkSigner,
+    nonces: NonceManager,
+    swap_manager_address: Address,
+    stake_registry_address: Address,
+    watcher: PendingTxWatcher,
+    quorum_threshold_percent: Option<f64>,
+    min_operator_version: Option<SoftwareVersion>,
+    require_hello: bool,
+    task_types: Vec<TaskType>,
+    tasks: Mutex<HashMap<u32, TaskState>>,
+    sockets: Mutex<BTreeMap<Address, SocketAnnouncement>>,
+    /// Latest hello of every operator, its task types narrowed to the routed ones
+    hellos: Mutex<BTreeMap<Address, Hello>>,
+    services: Services,
+}
+
+impl Aggregator {
+    /// Create an aggregator submitting with `signer` through `rpc_url` and `watcher`,
+    /// with the RPC stack, audit log and notifications of `services`
+    pub async fn new(
+        rpc_url: &str,
+        services: &Services,
+        signer: &OperatorSigner,
+        swap_manager_address: Address,
+        watcher: PendingTxWatcher,
+    ) -> Result<Self, AggregatorError> {
+        let provider = signer
+            .clone()
+            .services(services.clone())
+            .provider(rpc_url)?;
+        let stake_registry_address = SwapManager::new(swap_manager_address, &provider)
+            .stakeRegistry()
+            .call()
+            .await?
+            ._0;
+        Ok(Self {
+            provider,
+            nonces: NonceManager::new(signer.address()),
+            swap_manager_address,
+            stake_registry_address,
+            watcher: watcher.services(services.clone()),
+            quorum_threshold_percent: None,
+            min_operator_version: None,
+            require_hello: false,
+            task_types: TaskType::ALL.to_vec(),
+            tasks: Mutex::new(HashMap::new()),
+            sockets: Mutex::new(BTreeMap::new()),
+            hellos: Mutex::new(BTreeMap::new()),
+            services: services.clone(),
+        })
+    }
+
+    /// Also require `percent` of the total stake weight to sign a task, see
+    /// [`AggregatorConfig::quorum_threshold_percent`]
+    pub fn quorum_threshold_percent(mut self, percent: Option<f64>) -> Self {
+        self.quorum_threshold_percent = percent;
+        self
+    }
+
+    /// Handshake settings: the oldest operator version, whether a hello is required and the
+    /// task types aggregated, see [`AggregatorConfig`]
+    pub fn handshake(mut self, config: &AggregatorConfig) -> Result<Self, ConfigError> {
+        self.min_operator_version = config.min_operator_version()?;
+        self.require_hello = config.require_hello;
+        self.task_types = config.task_types.clone();
+        Ok(self)
+    }
+
+    /// Verify `hello` and record the operator, replacing its older hello. The reply routes
+    /// it the task types it declared among the ones aggregated
+    pub async fn hello(&self, mut hello: Hello) -> Result<HelloReply, AggregatorError> {
+        hello.verify().map_err(AggregatorError::InvalidHello)?;
+        let operator = hello.operator;
+        if hello.protocol_version != PROTOCOL_VERSION {
+            return Err(AggregatorError::UnsupportedProtocol {
+                operator,
+                version: hello.protocol_version,
+                expected: PROTOCOL_VERSION,
+            });
+        }
+        if let Some(min) = self.min_operator_version {
+            // Checked by the verification
+            let version = hello
+                .software_version
+                .parse::<SoftwareVersion>()
+                .map_err(|e| AggregatorError::InvalidHello(e.to_string()))?;
+            if version < min {
+                return Err(AggregatorError::OutdatedOperator {
+                    operator,
+                    version: hello.software_version,
+                    min: min.to_string(),
+                });
+            }
+        }
+        hello
+            .task_types
+            .retain(|task_type| self.task_types.contains(task_type));
+        if hello.task_types.is_empty() {
+            return Err(AggregatorError::NoTaskType { operator });
+        }
+        let registered = ECDSAStakeRegistry::new(self.stake_registry_address, &self.provider)
+            .operatorRegistered(operator)
+            .call()
+            .await?
+            ._0;
+        if !registered {
+            return Err(AggregatorError::NoStake { operator });
+        }
+        let mut hellos = self.hellos.lock().await;
+        if hellos
+            .get(&operator)
+            .is_some_and(|previous| previous.timestamp >= hello.timestamp)
+        {
+            return Err(AggregatorError::StaleHello { operator });
+        }
+        info!(
+            %operator,
+            version = %hello.software_version,
+            bls_public_key = hello.bls_public_key.as_deref(),
+            task_types = ?hello.task_types.iter().map(|task_type| task_type.as_str()).collect::<Vec<_>>(),
+            "Operator said hello"
+        );
+        let reply = HelloReply {
+            protocol_version: PROTOCOL_VERSION,
+            min_software_version: self
+                .min_operator_version
+                .map(|min| min.to_string())
+                .unwrap_or_default(),
+            task_types: task_types_to_proto(&hello.task_types),
+        };
+        hellos.insert(operator, hello);
+        Ok(reply)
+    }
+
+    /// Operators that said hello and are routed tasks of `task_type`
+    pub async fn operators_for(&self, task_type: TaskType) -> Vec<Address> {
+        self.hellos
+            .lock()
+            .await
+            .values()
+            .filter(|hello| hello.task_types.contains(&task_type))
+            .map(|hello| hello.operator)
+            .collect()
+    }
+
+    /// Fail unless the task of `response` is of a type routed to its operator, operators
+    /// that never said hello pass unless a hello is required
+    async fn check_routed(&self, response: &SignedTaskResponse) -> Result<(), AggregatorError> {
+        let operator = response.operator;
+        let task_type = TaskType::of(&response.task_name);
+        match self.hellos.lock().await.get(&operator) {
+            Some(hello) if !hello.task_types.contains(&task_type) => {
+                Err(AggregatorError::TaskTypeNotRouted {
+                    operator,
+                    task_type: task_type.as_str(),
+                })
+            }
+            Some(_) => Ok(()),
+            None if self.require_hello => Err(AggregatorError::NoHello { operator }),
+            None if !self.task_types.contains(&task_type) => {
+                Err(AggregatorError::TaskTypeNotRouted {
+                    operator,
+                    task_type: task_type.as_str(),
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Verify `announcement` and record the socket of its operator, replacing an older one
+    pub async fn announce_socket(
+        &self,
+        announcement: SocketAnnouncement,
+    ) -> Result<(), AggregatorError> {
+        announcement
+            .verify()
+            .map_err(AggregatorError::InvalidSignature)?;
+        let registered = ECDSAStakeRegistry::new(self.stake_registry_address, &self.provider)
+            .operatorRegistered(announcement.operator)
+            .call()
+            .await?
+            ._0;
+        if !registered {
+            return Err(AggregatorError::NoStake {
+                operator: announcement.operator,
+            });
+        }
+        let mut sockets = self.sockets.lock().await;
+        let previous = sockets.get(&announcement.operator);
+        if previous.is_some_and(|previous| previous.timestamp >= announcement.timestamp) {
+            return Err(AggregatorError::StaleSocket {
+                operator: announcement.operator,
+            });
+        }
+        info!(
+            operator = %announcement.operator,
+            socket = %announcement.socket,
+            previous = previous.map(|previous| previous.socket.as_str()),
+            "Operator announced its socket"
+        );
+        sockets.insert(announcement.operator, announcement);
+        Ok(())
+    }
+
+    /// Latest announced socket of every operator
+    pub async fn sockets(&self) -> BTreeMap<Address, String> {
+        self.sockets
+            .lock()
+            .await
+            .iter()
+            .map(|(operator, announcement)| (*operator, announcement.socket.clone()))
+            .collect()
+    }
+
+    /// Verify `response` and add it to its task, submitting once the signers hold the
+    /// quorum threshold of stake weight at the task's reference block, the block it was
+    /// created in, which the stake registry checks the signatures against too
+    pub async fn add_response(
+        &self,
+        response: SignedTaskResponse,
+    ) -> Result<AggregationStatus, AggregatorError> {
+        response.verify()?;
+        self.check_routed(&response).await?;
+        let swap_manager = SwapManager::new(self.swap_manager_address, &self.provider);
+        let task_hash = swap_manager
+            .allTaskHashes(response.task_index)
+            .call()
+            .await?
+            ._0;
+        if task_hash != keccak256(response.task().abi_encode()) {
+            return Err(AggregatorError::TaskMismatch {
+                task_index: response.task_index,
+            });
+        }
+
+        let reference_block = response.task_created_block;
+        let stake_registry = ECDSAStakeRegistry::new(self.stake_registry_address, &self.provider);
+        let weight = stake_registry
+            .getOperatorWeightAtBlock(response.operator, reference_block)
+            .call()
+            .await?
+            ._0;
+        if weight.is_zero() {
+            return Err(AggregatorError::NoStake {
+                operator: response.operator,
+            });
+        }
+        let registry_threshold = stake_registry
+            .getLastCheckpointThresholdWeightAtBlock(reference_block)
+            .call()
+            .await?
+            ._0;
+        let total_weight = stake_registry
+            .getLastCheckpointTotalWeightAtBlock(reference_block)
+            .call()
+            .await?
+            ._0;
+        let threshold_weight = quorum_threshold(
+            registry_threshold,
+            total_weight,
+            self.quorum_threshold_percent,
+        );
+
+        let tracker = {
+            let mut tasks = self.tasks.lock().await;
+            let state = tasks
+                .entry(response.task_index)
+                .or_insert_with(|| TaskState::Collecting(QuorumTracker::default()));
+            let tracker = match state {
+                TaskState::Collecting(tracker) => tracker,
+                TaskState::Submitting => return Ok(AggregationStatus::Submitting),
+                TaskState::Submitted(tx_hash) => {
+                    return Ok(AggregationStatus::Submitted { tx_hash: *tx_hash })
+                }
+            };
+            tracker.insert(response.operator, response.signature.clone(), weight);
+            let signed_weight = tracker.signed_weight();
+            info!(
+                task_index = response.task_index,
+                operator = %response.operator,
+                %signed_weight,
+                %threshold_weight,
+                %total_weight,
+                reference_block,
+                "Collected task response"
+            );
+            if signed_weight < threshold_weight {
+                return Ok(AggregationStatus::Pending {
+                    signed_weight,
+                    threshold_weight,
+                });
+            }
+            match std::mem::replace(state, TaskState::Submitting) {
+                TaskState::Collecting(tracker) => tracker,
+                _ => unreachable!("state was collecting"),
+            }
+        };
+
+        let (operators, signatures) = tracker.signers();
+        let submitted = self.submit(&response, &operators, &signatures).await;
+        let mut tasks = self.tasks.lock().await;
+        match submitted {
+            Ok(tx_hash) => {
+                tasks.insert(response.task_index, TaskState::Submitted(tx_hash));
+                Ok(AggregationStatus::Submitted { tx_hash })
+            }
+            // Keep the signatures so the next response retries the submission
+            Err(e) => {
+                tasks.insert(response.task_index, TaskState::Collecting(tracker));
+                Err(e)
+            }
+        }
+    }
+
+    async fn submit(
+        &self,
+        response: &SignedTaskResponse,
+        operators: &[Address],
+        signatures: &[Bytes],
+    ) -> Result<B256, AggregatorError> {
+        let _pending = PendingTxGuard::new();
+        // The weights the quorum was counted with
+        let reference_block = u64::from(response.task_created_block);
+        let signature_data = encode_signature_data(operators, signatures, reference_block);
+        let swap_manager = SwapManager::new(self.swap_manager_address, &self.provider);
+        let nonce = self.nonces.next_nonce(&self.provider).await?;
+        let tx = swap_manager
+            .respondToTask(response.task(), response.task_index, signature_data)
+            .from(self.nonces.address())
+            .nonce(nonce)
+            .into_transaction_request();
+        let receipt = match self.watcher.send(&self.provider, RESPOND_TO_TASK, tx).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                record_tx_failure(RESPOND_TO_TASK);
+                self.services.notify_tx_failure(RESPOND_TO_TASK, &e);
+                self.nonces.resync(&self.provider).await?;
+                return Err(e.into());
+            }
+        };
+        if !receipt.status() {
+            record_tx_failure(RESPOND_TO_TASK);
+            let error = ChainError::Reverted {
+                method: RESPOND_TO_TASK,
+                tx_hash: receipt.transaction_hash,
+            };
+            self.services.notify_tx_failure(RESPOND_TO_TASK, &error);
+            return Err(error.into());
+        }
+        record_gas_used(RESPOND_TO_TASK, receipt.gas_used);
+        record_task_responded();
+        self.services.notify(WebhookEvent::ResponseSubmitted {
+            task_index: response.task_index,
+            tx_hash: receipt.transaction_hash,
+        });
+        info!(
+            task_index = response.task_index,
+            signers = operators.len(),
+            tx_hash = %receipt.transaction_hash,
+            "Submitted aggregated task response"
+        );
+        Ok(receipt.transaction_hash)
+    }
+}
+
+async fn post_response(
+    State(aggregator): State<Arc<Aggregator>>,
+    Json(response): Json<SignedTaskResponse>,
+) -> Result<Json<AggregationStatus>, (StatusCode, String)> {
+    let task_index = response.task_index;
+    let operator = response.operator;
+    aggregator
+        .add_response(response)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!(task_index, %operator, error = %e, "Rejected task response");
+            (StatusCode::BAD_REQUEST, e.to_string())
+        })
+}
+
+async fn post_socket(
+    State(aggregator): State<Arc<Aggregator>>,
+    Json(announcement): Json<SocketAnnouncement>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let operator = announcement.operator;
+    aggregator
+        .announce_socket(announcement)
+        .await
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(|e| {
+            warn!(%operator, error = %e, "Rejected socket announcement");
+            (StatusCode::BAD_REQUEST, e.to_string())
+        })
+}
+
+async fn post_hello(
+    State(aggregator): State<Arc<Aggregator>>,
+    body: Body,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let hello = Hello::decode(&body).map_err(|e| {
+        warn!(error = %e, "Rejected malformed hello");
+        (StatusCode::BAD_REQUEST, e)
+    })?;
+    let operator = hello.operator;
+    match aggregator.hello(hello).await {
+        Ok(reply) => Ok((
+            [(CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)],
+            reply.encode_to_vec(),
+        )),
+        Err(e) => {
+            warn!(%operator, error = %e, "Rejected hello");
+            let status = match e {
+                AggregatorError::UnsupportedProtocol { .. }
+                | AggregatorError::OutdatedOperator { .. } => StatusCode::UPGRADE_REQUIRED,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            Err((status, e.to_string()))
+        }
+    }
+}
+
+async fn get_sockets(State(aggregator): State<Arc<Aggregator>>) -> Json<BTreeMap<Address, String>> {
+    Json(aggregator.sockets().await)
+}
+
+/// Serve the aggregator HTTP endpoint on `addr` until `shutdown` triggers, letting the
+/// requests in flight finish
+pub async fn serve(
+    aggregator: Aggregator,
+    addr: SocketAddr,
+    shutdown: Shutdown,
+) -> std::io::Result<()> {
+    let app = Router::new()
+        .route(RESPONSES_PATH, post(post_response))
+        .route(SOCKETS_PATH, post(post_socket).get(get_sockets))
+        .route(HELLO_PATH, post(post_hello))
+        .with_state(Arc::new(aggregator));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(%addr, "Aggregator listening");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.triggered().await })
+        .await
+}
+
+/// HTTP client operators use to hand their responses, socket and hello to the aggregator
+#[derive(Debug, Clone)]
+pub struct AggregatorClient {
+    client: Client,
+    url: reqwest::Url,
+    sockets_url: reqwest::Url,
+    hello_url: reqwest::Url,
+}
+
+impl AggregatorClient {
+    /// Client of the aggregator at `url`
+    pub fn new(url: &str) -> Result<Self, AggregatorError> {
+        let join = |path| {
+            reqwest::Url::parse(url)
+                .and_then(|base| base.join(path))
+                .map_err(|e| AggregatorError::InvalidUrl {
+                    url: url.to_string(),
+                    source: e.into(),
+                })
+        };
+        Ok(Self {
+            client: Client::new(),
+            url: join(RESPONSES_PATH)?,
+            sockets_url: join(SOCKETS_PATH)?,
+            hello_url: join(HELLO_PATH)?,
+        })
+    }
+
+    /// Say `hello`, returning the reply of the aggregator
+    pub async fn hello(&self, hello: &Hello) -> Result<HelloReply, AggregatorError> {
+        let reply = self
+            .client
+            .post(self.hello_url.clone())
+            .header(CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+            .body(hello.encode())
+            .send()
+            .await?;
+        if !reply.status().is_success() {
+            return Err(AggregatorError::Rejected {
+                status: reply.status(),
+                body: reply.text().await.unwrap_or_default(),
+            });
+        }
+        HelloReply::decode(reply.bytes().await?)
+            .map_err(|e| AggregatorError::InvalidHello(format!("malformed hello reply: {e}")))
+    }
+
+    /// Announce the socket of an operator, rotating the one the aggregator knows
+    pub async fn announce_socket(
+        &self,
+        announcement: &SocketAnnouncement,
+    ) -> Result<(), AggregatorError> {
+        let reply = self
+            .client
+            .post(self.sockets_url.clone())
+            .json(announcement)
+            .send()
+            .await?;
+        if !reply.status().is_success() {
+            return Err(AggregatorError::Rejected {
+                status: reply.status(),
+                body: reply.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Send `response`, returning the aggregation status reported by the aggregator
+    pub async fn send(
+        &self,
+        response: &SignedTaskResponse,
+    ) -> Result<AggregationStatus, AggregatorError> {
+        let reply = self
+            .client
+            .post(self.url.clone())
+            .json(response)
+            .send()
+            .await?;
+        if !reply.status().is_success() {
+            return Err(AggregatorError::Rejected {
+                status: reply.status(),
+                body: reply.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(reply.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    use crate::task_responder::task_response_hash;
+
+    fn signed_response(signer: &PrivateKeySigner, name: &str) -> SignedTaskResponse {
+        let signature = signer
+            .sign_message_sync(task_response_hash(name).as_slice())
+            .unwrap();
+        SignedTaskResponse {
+            task_index: 3,
+            task_name: name.to_string(),
+            task_created_block: 10,
+            operator: signer.address(),
+            signature: signature.as_bytes().into(),
+            quote: None,
+        }
+    }
+
+    #[test]
+    fn test_responses_verify_against_their_operator() {
+        let signer = PrivateKeySigner::random();
+        let response = signed_response(&signer, "QuickFox1");
+        response.verify().unwrap();
+
+        let forged = SignedTaskResponse {
+            operator: PrivateKeySigner::random().address(),
+            ..response.clone()
+        };
+        assert!(forged.verify().is_err());
+        let renamed = SignedTaskResponse {
+            task_name: "LazyDog2".to_string(),
+            ..response
+        };
+        assert!(renamed.verify().is_err());
+    }
+
+    #[test]
+    fn test_quorum_tracker_sorts_signers_and_sums_weight() {
+        let mut tracker = QuorumTracker::default();
+        let high = Address::repeat_byte(0xee);
+        let low = Address::repeat_byte(0x11);
+        tracker.insert(high, Bytes::from_static(&[1]), U256::from(40));
+        tracker.insert(low, Bytes::from_static(&[2]), U256::from(30));
+        tracker.insert(high, Bytes::from_static(&[3]), U256::from(40));
+
+        assert_eq!(tracker.signed_weight(), U256::from(70));
+        let (operators, signatures) = tracker.signers();
+        assert_eq!(operators, vec![low, high]);
+        assert_eq!(
+            signatures,
+            vec![Bytes::from_static(&[2]), Bytes::from_static(&[3])]
+        );
+    }
+
+    #[test]
+    fn test_quorum_threshold_is_the_highest_of_registry_and_config() {
+        let registry = U256::from(6_000);
+        let total = U256::from(10_000);
+        assert_eq!(quorum_threshold(registry, total, None), registry);
+        assert_eq!(quorum_threshold(registry, total, Some(50.0)), registry);
+        assert_eq!(
+            quorum_threshold(registry, total, Some(66.67)),
+            U256::from(6_667)
+        );
+        // Rounded up, so a third of 100 can't be met with 33
+        assert_eq!(
+            quorum_threshold(U256::ZERO, U256::from(100), Some(33.34)),
+            U256::from(34)
+        );
+        assert!(AggregatorConfig {
+            quorum_threshold_percent: Some(0.0),
+            ..AggregatorConfig::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, error, warn};
+
+use crate::error::ConfigError;
+use crate::prometheus::record_alert;
+use crate::rate_limit::TokenBucket;
+use crate::retry::{retry, RetryConfig};
+use crate::shutdown::Shutdown;
+use crate::webhook::{Notifications, WebhookEvent};
+
+/// Longest message Discord accepts in `content`
+const DISCORD_MAX_CONTENT: usize = 2_000;
+
+/// How urgently an event needs a human, ordered from least to most urgent
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Normal progress, such as a task responded to
+    Info,
+    /// Something failed but the operator keeps going
+    #[default]
+    Warning,
+    /// The operator stopped making progress or is about to
+    Critical,
+}
+
+impl Severity {
+    /// Lowercase name, as written in the config
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// Chat service an alert channel posts to
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatKind {
+    /// Slack incoming webhook
+    Slack,
+    /// Discord channel webhook
+    Discord,
+}
+
+impl ChatKind {
+    /// Lowercase name, as written in the config
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Slack => "slack",
+            Self::Discord => "discord",
+        }
+    }
+}
+
+/// A chat channel alerts are posted to, an `[[alerts.channels]]` entry
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChatChannel {
+    /// Service behind `webhook_url`
+    pub kind: ChatKind,
+    /// Incoming webhook URL of the channel
+    pub webhook_url: String,
+    /// Slack channel posted to instead of the webhook's default one, such as `#avs-oncall`
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Least severity posted to this channel, `alerts.min_severity` if unset
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+}
+
+/// Chat alerts, the `[alerts]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// Channels alerted, `SLACK_WEBHOOK_URL` and `DISCORD_WEBHOOK_URL` each add one
+    pub channels: Vec<ChatChannel>,
+    /// Least severity posted to the channels not setting their own
+    pub min_severity: Severity,
+    /// Most alerts posted to a channel per minute, the others are dropped and counted
+    pub max_per_minute: u32,
+    /// Seconds a task may be handled without a response going out before the responder
+    /// counts as stalled, never if unset
+    pub stall_after_secs: Option<u64>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            min_severity: Severity::Warning,
+            max_per_minute: 10,
+            stall_after_secs: Some(300),
+        }
+    }
+}
+
+impl AlertsConfig {
+    /// Check that alerts can be posted with these settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_per_minute == 0 {
+            return Err(ConfigError::Invalid(
+                "alerts.max_per_minute must be greater than 0".to_string(),
+            ));
+        }
+        if self.stall_after_secs == Some(0) {
+            return Err(ConfigError::Invalid(
+                "alerts.stall_after_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self
+            .channels
+            .iter()
+            .any(|channel| channel.kind == ChatKind::Discord && channel.channel.is_some())
+        {
+            return Err(ConfigError::Invalid(
+                "alerts.channels.channel only applies to slack, discord webhooks post to their own channel"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Body posted to a `kind` webhook for `text`
+pub fn chat_payload(kind: ChatKind, channel: Option<&str>, text: &str) -> Value {
+    match kind {
+        ChatKind::Slack => match channel {
+            Some(channel) => json!({ "text": text, "channel": channel }),
+            None => json!({ "text": text }),
+        },
+        ChatKind::Discord => {
+            let content: String = text.chars().take(DISCORD_MAX_CONTENT).collect();
+            json!({ "content": content })
+        }
+    }
+}
+
+/// A channel and the rate limit of its alerts
+#[derive(Debug)]
+struct ChannelSink {
+    channel: ChatChannel,
+    min_severity: Severity,
+    limiter: TokenBucket,
+    suppressed: AtomicU64,
+}
+
+/// Posts events to Slack and Discord channels, filtered by severity and rate limited
+#[derive(Debug)]
+pub struct Alerter {
+    client: reqwest::Client,
+    sinks: Vec<ChannelSink>,
+    retry: RetryConfig,
+}
+
+impl Alerter {
+    /// Alerter for the channels of `config`, each post retried as set in `retry_config`
+    pub fn new(config: &AlertsConfig, retry_config: &RetryConfig) -> Self {
+        let per_minute = config.max_per_minute.max(1);
+        Self {
+            client: reqwest::Client::new(),
+            sinks: config
+                .channels
+                .iter()
+                .map(|channel| ChannelSink {
+                    channel: channel.clone(),
+                    min_severity: channel.min_severity.unwrap_or(config.min_severity),
+                    limiter: TokenBucket::new(f64::from(per_minute) / 60.0, per_minute),
+                    suppressed: AtomicU64::new(0),
+                })
+                .collect(),
+            retry: retry_config.clone(),
+        }
+    }
+
+    /// Post `event` to every channel alerted at its severity, failures are logged and counted
+    pub async fn send(&self, event: &WebhookEvent) {
+        let severity = event.severity();
+        for sink in self
+            .sinks
+            .iter()
+            .filter(|sink| severity >= sink.min_severity)
+        {
+            let kind = sink.channel.kind.as_str();
+            // A storm of failures would otherwise flood the channel and get the webhook banned
+            if sink.limiter.try_acquire().is_err() {
+                sink.suppressed.fetch_add(1, Ordering::Relaxed);
+                record_alert(kind, "suppressed");
+                continue;
+            }
+            let mut text = format!("[{}] {}", severity.as_str().to_uppercase(), event.summary());
+            let suppressed = sink.suppressed.swap(0, Ordering::Relaxed);
+            if suppressed > 0 {
+                text.push_str(&format!(
+                    " ({suppressed} more alerts dropped by the rate limit)"
+                ));
+            }
+            let body = chat_payload(sink.channel.kind, sink.channel.channel.as_deref(), &text);
+            let sent = retry(&self.retry, "alert", || async {
+                self.client
+                    .post(&sink.channel.webhook_url)
+                    .json(&body)
+                    .send()
+                    .await?
+                    .error_for_status()
+                    .map(|_| ())
+            })
+            .await;
+            match sent {
+                Ok(()) => {
+                    record_alert(kind, "sent");
+                    debug!(event = event.name(), kind, "Alert posted");
+                }
+                Err(e) => {
+                    record_alert(kind, "failed");
+                    warn!(event = event.name(), kind, error = %e, "Failed to post the alert");
+                }
+            }
+        }
+    }
+}
+
+/// Task being handled and whether its stall was already reported
+#[derive(Debug)]
+struct Busy {
+    task_index: u32,
+    since: Instant,
+    reported: bool,
+}
+
+/// Notices the responder spending too long on a task, as when retries keep failing or a
+/// transaction never gets mined
+#[derive(Debug)]
+pub struct StallWatchdog {
+    stall_after: Duration,
+    busy: Mutex<Option<Busy>>,
+}
+
+impl StallWatchdog {
+    /// Watchdog reporting tasks handled for longer than `stall_after`
+    pub fn new(stall_after: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            stall_after,
+            busy: Mutex::new(None),
+        })
+    }
+
+    /// Mark task `task_index` as being handled until the returned guard is dropped
+    pub fn busy(&self, task_index: u32) -> BusyGuard<'_> {
+        *self.busy.lock().expect("watchdog lock poisoned") = Some(Busy {
+            task_index,
+            since: Instant::now(),
+            reported: false,
+        });
+        BusyGuard(self)
+    }
+
+    /// Task handled for longer than the stall threshold and for how long, returned once per task
+    pub fn check(&self) -> Option<(u32, Duration)> {
+        let mut busy = self.busy.lock().expect("watchdog lock poisoned");
+        let busy = busy.as_mut()?;
+        let elapsed = busy.since.elapsed();
+        if busy.reported || elapsed < self.stall_after {
+            return None;
+        }
+        busy.reported = true;
+        Some((busy.task_index, elapsed))
+    }
+
+    /// Check for a stall a few times per threshold until `shutdown` is triggered, reporting
+    /// it to `notifications`
+    pub async fn run(self: Arc<Self>, notifications: Notifications, shutdown: Shutdown) {
+        let interval =
+            (self.stall_after / 4).clamp(Duration::from_secs(1), Duration::from_secs(30));
+        while !shutdown.is_triggered() {
+            if let Some((task_index, elapsed)) = self.check() {
+                error!(
+                    task_index,
+                    stalled_secs = elapsed.as_secs(),
+                    "Responder stalled, no response went out for the task"
+                );
+                notifications.notify(WebhookEvent::ResponderStalled {
+                    task_index,
+                    stalled_secs: elapsed.as_secs(),
+                });
+            }
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(interval) => {}
+            }
+        }
+    }
+}
+
+/// Clears the task being handled from the [`StallWatchdog`] on drop
+#[derive(Debug)]
+pub struct BusyGuard<'a>(&'a StallWatchdog);
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.busy.lock().expect("watchdog lock poisoned") = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalls_are_reported_once_per_task() {
+        let watchdog = StallWatchdog::new(Duration::ZERO);
+        assert_eq!(watchdog.check(), None);
+        {
+            let _busy = watchdog.busy(4);
+            assert_eq!(watchdog.check().map(|(task_index, _)| task_index), Some(4));
+            assert_eq!(watchdog.check(), None);
+        }
+        assert_eq!(watchdog.check(), None);
+
+        assert_eq!(
+            chat_payload(ChatKind::Slack, Some("#oncall"), "down"),
+            json!({ "text": "down", "channel": "#oncall" })
+        );
+        let long = "x".repeat(3 * DISCORD_MAX_CONTENT);
+        assert_eq!(
+            chat_payload(ChatKind::Discord, None, &long)["content"]
+                .as_str()
+                .unwrap()
+                .len(),
+            DISCORD_MAX_CONTENT
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use alloy::{
+    primitives::{keccak256, Address, Bytes, B256},
+    signers::Signature,
+    sol_types::SolValue,
+};
+use axum::{extract::State, routing::post, Json, Router};
+use eigensdk::common::SdkProvider;
+use serde::{Deserialize, Serialize};
+use swap_manager_utils::SwapManager::{ISwapManager::Task, SwapManager};
+use tracing::info;
+
+use crate::error::ConfigError;
+use crate::services::Services;
+use crate::signature_scheme::ecdsa_digest;
+use crate::task_handler::TaskHandlers;
+
+/// Path candidate responses are posted to, served along the health endpoints
+pub const VERIFY_PATH: &str = "/v1/verify";
+
+/// Response someone would like this operator to attest to, the body of `POST /v1/verify`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CandidateResponse {
+    /// Index of the task in the SwapManager
+    pub task_index: u32,
+    /// Name of the task
+    pub task_name: String,
+    /// Block the task was created in
+    pub task_created_block: u32,
+    /// Hash of the response, the one of this operator is checked against the signature
+    /// if unset
+    #[serde(default)]
+    pub response_hash: Option<B256>,
+    /// Operator that signed the response, `signature` must recover to it
+    #[serde(default)]
+    pub operator: Option<Address>,
+    /// 65 byte ECDSA signature over the EIP-191 digest of the response hash
+    #[serde(default)]
+    pub signature: Option<Bytes>,
+}
+
+/// Whether this operator would attest to a [`CandidateResponse`], and why not
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationVerdict {
+    /// Whether the operator would sign the candidate
+    pub attest: bool,
+    /// Why it wouldn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Hash of the response the operator computes for the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_hash: Option<B256>,
+}
+
+impl AttestationVerdict {
+    fn reject(reason: String, response_hash: Option<B256>) -> Self {
+        Self {
+            attest: false,
+            reason: Some(reason),
+            response_hash,
+        }
+    }
+}
+
+impl CandidateResponse {
+    /// The task the candidate responds to
+    pub fn task(&self) -> Task {
+        Task {
+            name: self.task_name.clone(),
+            taskCreatedBlock: self.task_created_block,
+        }
+    }
+
+    /// Verdict on the candidate, given the hash of task `task_index` stored on chain and
+    /// the response hash this operator computes for the task or why it couldn't
+    pub fn judge(
+        &self,
+        task_hash: B256,
+        response_hash: Result<B256, String>,
+    ) -> AttestationVerdict {
+        if task_hash != keccak256(self.task().abi_encode()) {
+            return AttestationVerdict::reject(
+                format!(
+                    "task {} does not match the task created on chain",
+                    self.task_index
+                ),
+                None,
+            );
+        }
+        let ours = match response_hash {
+            Ok(hash) => hash,
+            Err(e) => {
+                return AttestationVerdict::reject(format!("no response to the task: {e}"), None)
+            }
+        };
+        if let Some(theirs) = self.response_hash.filter(|theirs| *theirs != ours) {
+            return AttestationVerdict::reject(
+                format!("response hash {theirs} differs from {ours}"),
+                Some(ours),
+            );
+        }
+        match (&self.operator, &self.signature) {
+            (None, None) => {}
+            (Some(operator), Some(signature)) => {
+                if let Err(reason) = check_signature(*operator, signature, ours) {
+                    return AttestationVerdict::reject(reason, Some(ours));
+                }
+            }
+            _ => {
+                return AttestationVerdict::reject(
+                    "operator and signature must be given together".to_string(),
+                    Some(ours),
+                )
+            }
+        }
+        AttestationVerdict {
+            attest: true,
+            reason: None,
+            response_hash: Some(ours),
+        }
+    }
+}
+
+/// Check that `signature` is the one of `operator` over `response_hash`
+fn check_signature(operator: Address, signature: &[u8], response_hash: B256) -> Result<(), String> {
+    let signature =
+        Signature::from_raw(signature).map_err(|e| format!("malformed signature: {e}"))?;
+    let signer = signature
+        .recover_address_from_prehash(&ecdsa_digest(response_hash))
+        .map_err(|e| format!("unrecoverable signature: {e}"))?;
+    if signer != operator {
+        return Err(format!(
+            "signature of {signer} does not match operator {operator}"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks candidate responses against the task on chain and the responses this operator
+/// computes, without signing anything
+#[derive(Debug)]
+pub struct Attester {
+    provider: SdkProvider,
+    swap_manager_address: Address,
+    handlers: TaskHandlers,
+}
+
+impl Attester {
+    /// Attester reading the tasks of the SwapManager at `swap_manager_address` through
+    /// `rpc_url` and the RPC stack of `services`, computing the responses with `handlers`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        swap_manager_address: Address,
+        handlers: TaskHandlers,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            swap_manager_address,
+            handlers,
+        })
+    }
+
+    /// Whether this operator would attest to `candidate`
+    pub async fn verify(&self, candidate: &CandidateResponse) -> AttestationVerdict {
+        let swap_manager = SwapManager::new(self.swap_manager_address, &self.provider);
+        let task_hash = match swap_manager
+            .allTaskHashes(candidate.task_index)
+            .call()
+            .await
+        {
+            Ok(hash) => hash._0,
+            Err(e) => {
+                return AttestationVerdict::reject(format!("failed to read the task: {e}"), None)
+            }
+        };
+        let response_hash = self
+            .handlers
+            .response_hash(candidate.task_index, &candidate.task())
+            .await
+            .map_err(|e| e.to_string());
+        let verdict = candidate.judge(task_hash, response_hash);
+        info!(
+            task_index = candidate.task_index,
+            attest = verdict.attest,
+            reason = verdict.reason.as_deref(),
+            "Verified candidate response"
+        );
+        verdict
+    }
+}
+
+async fn verify(
+    State(attester): State<Arc<Attester>>,
+    Json(candidate): Json<CandidateResponse>,
+) -> Json<AttestationVerdict> {
+    Json(attester.verify(&candidate).await)
+}
+
+/// Routes serving [`VERIFY_PATH`] with `attester`
+pub fn router(attester: Arc<Attester>) -> Router {
+    Router::new()
+        .route(VERIFY_PATH, post(verify))
+        .with_state(attester)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    use crate::task_responder::task_response_hash;
+
+    #[test]
+    fn test_candidates_are_judged_against_the_chain_and_our_response() {
+        let signer = PrivateKeySigner::random();
+        let ours = task_response_hash("QuickFox1");
+        let candidate = CandidateResponse {
+            task_index: 3,
+            task_name: "QuickFox1".to_string(),
+            task_created_block: 10,
+            response_hash: Some(ours),
+            operator: Some(signer.address()),
+            signature: Some(
+                signer
+                    .sign_message_sync(ours.as_slice())
+                    .unwrap()
+                    .as_bytes()
+                    .into(),
+            ),
+        };
+        let task_hash = keccak256(candidate.task().abi_encode());
+        assert_eq!(
+            candidate.judge(task_hash, Ok(ours)),
+            AttestationVerdict {
+                attest: true,
+                reason: None,
+                response_hash: Some(ours),
+            }
+        );
+
+        let verdict = candidate.judge(B256::ZERO, Ok(ours));
+        assert!(!verdict.attest);
+        assert!(verdict.reason.unwrap().contains("does not match the task"));
+
+        let other = task_response_hash("LazyDog2");
+        let verdict = candidate.judge(task_hash, Ok(other));
+        assert!(verdict.reason.unwrap().contains("differs from"));
+
+        let forged = CandidateResponse {
+            operator: Some(PrivateKeySigner::random().address()),
+            ..candidate.clone()
+        };
+        let verdict = forged.judge(task_hash, Ok(ours));
+        assert!(verdict.reason.unwrap().contains("does not match operator"));
+
+        let failing = candidate.judge(task_hash, Err("malformed swap payload".to_string()));
+        assert_eq!(
+            failing.reason.as_deref(),
+            Some("no response to the task: malformed swap payload")
+        );
+    }
+}
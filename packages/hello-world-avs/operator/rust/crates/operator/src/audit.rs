@@ -0,0 +1,436 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{keccak256, Address, B256};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::error::AuditError;
+
+/// Audit log of the signed payloads and sent transactions, the `[audit]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Whether the log is kept (`AUDIT_ENABLED`)
+    pub enabled: bool,
+    /// JSON lines file the entries are appended to, one per process (`AUDIT_LOG_PATH`)
+    pub path: PathBuf,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("operator.audit.jsonl"),
+        }
+    }
+}
+
+/// Kind of payload a signature covers
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedPayload {
+    /// EIP-191 personal message, the digest is the prefixed hash
+    Message,
+    /// Raw 32 byte digest
+    Hash,
+    /// Transaction, the digest is its signing hash
+    Transaction,
+    /// BLS signature of a response hash, the digest is the hash
+    Bls,
+}
+
+/// What an [`AuditEntry`] records
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A payload was signed
+    Signature {
+        /// Kind of payload
+        payload: SignedPayload,
+        /// Task the signature responds to, if any
+        task_index: Option<u32>,
+        /// Digest that was signed
+        digest: B256,
+    },
+    /// A transaction was sent, each replacement of a stuck one included
+    Transaction {
+        /// Contract method called
+        method: String,
+        /// Nonce of the transaction, unset when the provider filled it in
+        nonce: Option<u64>,
+        /// Hash of the sent transaction
+        tx_hash: B256,
+    },
+}
+
+/// Line of the audit log, chained to the previous one by `prev_hash`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Position in the log, from 0
+    pub seq: u64,
+    /// Unix time in milliseconds the entry was recorded at
+    pub timestamp_ms: u64,
+    /// Address of the key that signed or sent
+    pub key_id: Address,
+    /// What happened
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    /// Hash of the previous entry, zero for the first one
+    pub prev_hash: B256,
+    /// Hash of this entry, see [`AuditEntry::compute_hash`]
+    pub hash: B256,
+}
+
+impl AuditEntry {
+    /// keccak256 of the JSON of the entry with `hash` zeroed
+    pub fn compute_hash(&self) -> B256 {
+        let unhashed = Self {
+            hash: B256::ZERO,
+            ..self.clone()
+        };
+        keccak256(serde_json::to_vec(&unhashed).expect("audit entries serialize"))
+    }
+}
+
+/// Entries and last hash of an audit log whose chain checked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditSummary {
+    /// Entries in the log
+    pub entries: u64,
+    /// Hash of the last entry, zero for an empty log
+    pub head: B256,
+}
+
+/// Append-only audit log, each entry hashes the one before so editing, reordering or
+/// dropping entries other than the last ones breaks the chain. The hashes are unkeyed:
+/// truncating the log, or rewriting it with recomputed hashes, only shows against a head
+/// hash kept elsewhere, such as the one logged when the log is opened
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    tail: Mutex<AuditTail>,
+}
+
+#[derive(Debug)]
+struct AuditTail {
+    file: File,
+    next_seq: u64,
+    head: B256,
+}
+
+impl AuditLog {
+    /// Open the log at `path`, creating it if missing. An existing log is verified first
+    /// and extended from its last entry, after dropping a last line torn by a crash in
+    /// [`Self::append`]
+    pub fn open(path: &Path) -> Result<Self, AuditError> {
+        let summary = if path.exists() {
+            truncate_torn_line(path)?;
+            verify_audit_log(path)?
+        } else {
+            AuditSummary {
+                entries: 0,
+                head: B256::ZERO,
+            }
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|source| AuditError::Write {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        }
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(|source| AuditError::Write {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            tail: Mutex::new(AuditTail {
+                file,
+                next_seq: summary.entries,
+                head: summary.head,
+            }),
+        })
+    }
+
+    /// Entries in the log and hash of the last one
+    pub fn summary(&self) -> AuditSummary {
+        let tail = self.tail.lock().expect("audit log lock poisoned");
+        AuditSummary {
+            entries: tail.next_seq,
+            head: tail.head,
+        }
+    }
+
+    /// Append `event` by `key_id`, synced to disk before returning
+    pub fn append(&self, key_id: Address, event: AuditEvent) -> Result<AuditEntry, AuditError> {
+        let mut tail = self.tail.lock().expect("audit log lock poisoned");
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut entry = AuditEntry {
+            seq: tail.next_seq,
+            timestamp_ms,
+            key_id,
+            event,
+            prev_hash: tail.head,
+            hash: B256::ZERO,
+        };
+        entry.hash = entry.compute_hash();
+        let mut line = serde_json::to_vec(&entry).expect("audit entries serialize");
+        line.push(b'\n');
+        tail.file
+            .write_all(&line)
+            .and_then(|()| tail.file.sync_data())
+            .map_err(|source| AuditError::Write {
+                path: self.path.clone(),
+                source,
+            })?;
+        tail.next_seq += 1;
+        tail.head = entry.hash;
+        Ok(entry)
+    }
+
+    /// Log at `config.path`, none unless `config.enabled`
+    pub fn from_config(config: &AuditConfig) -> Result<Option<Self>, AuditError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let log = Self::open(&config.path)?;
+        let summary = log.summary();
+        info!(
+            path = %config.path.display(),
+            entries = summary.entries,
+            head = %summary.head,
+            "Recording signatures and transactions in the audit log"
+        );
+        Ok(Some(log))
+    }
+
+    /// Record that `key_id` signed `digest`, on behalf of task `task_index` if set
+    pub(crate) fn record_signature(
+        &self,
+        key_id: Address,
+        payload: SignedPayload,
+        task_index: Option<u32>,
+        digest: B256,
+    ) {
+        self.record(
+            key_id,
+            AuditEvent::Signature {
+                payload,
+                task_index,
+                digest,
+            },
+        );
+    }
+
+    /// Record that `key_id` sent the `method` transaction `tx_hash`
+    pub(crate) fn record_transaction(
+        &self,
+        key_id: Address,
+        method: &str,
+        nonce: Option<u64>,
+        tx_hash: B256,
+    ) {
+        self.record(
+            key_id,
+            AuditEvent::Transaction {
+                method: method.to_string(),
+                nonce,
+                tx_hash,
+            },
+        );
+    }
+
+    fn record(&self, key_id: Address, event: AuditEvent) {
+        // The signature is already made, failing it now would only hide it from the log
+        if let Err(e) = self.append(key_id, event) {
+            error!(error = ?e, "Failed to append to the audit log");
+        }
+    }
+}
+
+/// Cut a last line without its newline off the log at `path`, what an append interrupted
+/// midway leaves behind
+fn truncate_torn_line(path: &Path) -> Result<(), AuditError> {
+    let read_error = |source| AuditError::Read {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut reader = BufReader::new(File::open(path).map_err(read_error)?);
+    let (mut complete, mut line) = (0u64, Vec::new());
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line).map_err(read_error)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if line.last() != Some(&b'\n') {
+            break;
+        }
+        complete += read as u64;
+    }
+    warn!(
+        path = %path.display(),
+        torn_bytes = line.len(),
+        "Dropping the torn last line of the audit log"
+    );
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_len(complete))
+        .map_err(|source| AuditError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Check every entry of the log at `path`: sequence numbers in order, each entry
+/// pointing at the hash of the previous one and hashing to its own `hash`
+pub fn verify_audit_log(path: &Path) -> Result<AuditSummary, AuditError> {
+    let file = File::open(path).map_err(|source| AuditError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut summary = AuditSummary {
+        entries: 0,
+        head: B256::ZERO,
+    };
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let broken = |reason: String| AuditError::Broken {
+            path: path.to_path_buf(),
+            line: number + 1,
+            reason,
+        };
+        let line = line.map_err(|source| AuditError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let entry: AuditEntry =
+            serde_json::from_str(&line).map_err(|e| broken(format!("unreadable entry: {e}")))?;
+        if entry.seq != summary.entries {
+            return Err(broken(format!(
+                "sequence number {} where {} was expected",
+                entry.seq, summary.entries
+            )));
+        }
+        if entry.prev_hash != summary.head {
+            return Err(broken(format!(
+                "previous hash {} where {} was expected",
+                entry.prev_hash, summary.head
+            )));
+        }
+        let hash = entry.compute_hash();
+        if entry.hash != hash {
+            return Err(broken(format!(
+                "entry hashes to {hash} but records {}",
+                entry.hash
+            )));
+        }
+        summary.entries += 1;
+        summary.head = entry.hash;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log_chains_entries_and_detects_tampering() {
+        let dir = std::env::temp_dir().join(format!("audit-{}", std::process::id()));
+        let path = dir.join("operator.audit.jsonl");
+        let key = Address::repeat_byte(0x11);
+        let signature = AuditEvent::Signature {
+            payload: SignedPayload::Message,
+            task_index: Some(7),
+            digest: B256::repeat_byte(0xaa),
+        };
+        let log = AuditLog::open(&path).unwrap();
+        let first = log.append(key, signature.clone()).unwrap();
+        assert_eq!(first.prev_hash, B256::ZERO);
+        drop(log);
+
+        // Reopening resumes the chain after the last entry
+        let log = AuditLog::open(&path).unwrap();
+        let second = log
+            .append(
+                key,
+                AuditEvent::Transaction {
+                    method: "respondToTask".to_string(),
+                    nonce: Some(3),
+                    tx_hash: B256::repeat_byte(0xbb),
+                },
+            )
+            .unwrap();
+        assert_eq!((second.seq, second.prev_hash), (1, first.hash));
+        assert_eq!(
+            verify_audit_log(&path).unwrap(),
+            AuditSummary {
+                entries: 2,
+                head: second.hash
+            }
+        );
+
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replace("\"task_index\":7", "\"task_index\":8")).unwrap();
+        assert!(matches!(
+            verify_audit_log(&path),
+            Err(AuditError::Broken { line: 1, .. })
+        ));
+        let second_line = data.lines().nth(1).unwrap();
+        std::fs::write(&path, format!("{second_line}\n")).unwrap();
+        assert!(matches!(
+            verify_audit_log(&path),
+            Err(AuditError::Broken { line: 1, .. })
+        ));
+        assert!(AuditLog::open(&path).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_a_torn_last_line_is_dropped_on_open() {
+        let dir = std::env::temp_dir().join(format!("audit-torn-{}", std::process::id()));
+        let path = dir.join("operator.audit.jsonl");
+        let key = Address::repeat_byte(0x11);
+        let event = AuditEvent::Signature {
+            payload: SignedPayload::Hash,
+            task_index: None,
+            digest: B256::repeat_byte(0xcc),
+        };
+        let log = AuditLog::open(&path).unwrap();
+        let first = log.append(key, event.clone()).unwrap();
+        drop(log);
+
+        // A crash midway through the second append
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"seq\":1,\"timestamp_ms\":17").unwrap();
+        drop(file);
+        assert!(verify_audit_log(&path).is_err());
+
+        let log = AuditLog::open(&path).unwrap();
+        assert_eq!(
+            log.summary(),
+            AuditSummary {
+                entries: 1,
+                head: first.hash
+            }
+        );
+        let second = log.append(key, event).unwrap();
+        assert_eq!((second.seq, second.prev_hash), (1, first.hash));
+        assert_eq!(verify_audit_log(&path).unwrap().entries, 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
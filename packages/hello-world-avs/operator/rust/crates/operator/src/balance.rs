@@ -0,0 +1,243 @@
+use std::{
+    future::IntoFuture,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{
+        utils::{format_ether, parse_ether},
+        Address, U256,
+    },
+    providers::Provider,
+};
+use eigensdk::common::SdkProvider;
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::error::{ChainError, ConfigError, FundingError};
+use crate::funding::{Funder, Funding, TopUpKind};
+use crate::prometheus::{record_top_up, set_signer_balance, time_rpc};
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::webhook::{Notifications, WebhookEvent};
+
+/// Signer balance watching, the `[balance]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BalanceConfig {
+    /// Watch the balance of the signer while sending transactions
+    pub enabled: bool,
+    /// Seconds between two balance checks
+    pub poll_interval_secs: u64,
+    /// Balance in ether under which the signer is low on funds
+    pub min_balance_eth: String,
+    /// What is done about a low balance
+    pub top_up: TopUpKind,
+    /// Faucet the `http` top up POSTs `{"address", "balance", "amount"}` to (`TOP_UP_URL`)
+    pub top_up_url: Option<String>,
+    /// Ether asked of the faucet, `anvil` sets the balance to `min_balance_eth` plus this
+    pub top_up_amount_eth: String,
+    /// Seconds between two top up requests for the same account, failed ones included
+    pub top_up_cooldown_secs: u64,
+}
+
+impl Default for BalanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_secs: 30,
+            min_balance_eth: "0.05".to_string(),
+            top_up: TopUpKind::None,
+            top_up_url: None,
+            top_up_amount_eth: "1".to_string(),
+            top_up_cooldown_secs: 600,
+        }
+    }
+}
+
+impl BalanceConfig {
+    /// Minimum balance in wei
+    pub fn min_balance(&self) -> Result<U256, ConfigError> {
+        parse_ether(&self.min_balance_eth).map_err(|e| {
+            ConfigError::Invalid(format!(
+                "invalid balance.min_balance_eth {}: {e}",
+                self.min_balance_eth
+            ))
+        })
+    }
+
+    /// Top up amount in wei
+    pub fn top_up_amount(&self) -> Result<U256, ConfigError> {
+        parse_ether(&self.top_up_amount_eth).map_err(|e| {
+            ConfigError::Invalid(format!(
+                "invalid balance.top_up_amount_eth {}: {e}",
+                self.top_up_amount_eth
+            ))
+        })
+    }
+
+    /// Check that the amounts parse and the chosen top up is usable
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "balance.poll_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        self.min_balance()?;
+        self.top_up_amount()?;
+        if self.top_up == TopUpKind::Http && self.top_up_url.is_none() {
+            return Err(ConfigError::Invalid(
+                "balance.top_up_url must be set for the http top up".to_string(),
+            ));
+        }
+        if self.top_up != TopUpKind::None && self.top_up_cooldown_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "balance.top_up_cooldown_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Checks the balance of an account at regular intervals and tops it up when low
+#[derive(Debug)]
+pub struct BalanceWatcher {
+    provider: SdkProvider,
+    funder: Funder,
+    address: Address,
+    min_balance: U256,
+    poll_interval: Duration,
+    /// Whether the last check found the balance low, so the webhook only fires once per drop
+    low: AtomicBool,
+    notifications: Notifications,
+}
+
+impl BalanceWatcher {
+    /// Watch the balance of `address` through `rpc_url` and the RPC stack of `services`,
+    /// notifying its drops to the notifications of `services`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        address: Address,
+        config: &BalanceConfig,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            funder: Funder::new(rpc_url, services, config)?,
+            address,
+            min_balance: config.min_balance()?,
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+            low: AtomicBool::new(false),
+            notifications: services.notifications().clone(),
+        })
+    }
+
+    /// Check the balance every poll interval until `shutdown` is triggered
+    pub async fn run(self, shutdown: Shutdown) {
+        info!(
+            address = %self.address,
+            min_balance = %format_ether(self.min_balance),
+            top_up = ?self.funder.kind(),
+            "Watching the signer balance"
+        );
+        while !shutdown.is_triggered() {
+            if let Err(e) = self.check().await {
+                warn!(error = %e, "Balance check failed");
+            }
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    /// Read and export the balance, topping it up if it is under the minimum
+    pub async fn check(&self) -> Result<U256, ChainError> {
+        let balance = time_rpc(
+            "eth_getBalance",
+            self.provider.get_balance(self.address).into_future(),
+        )
+        .await?;
+        let low = balance < self.min_balance;
+        set_signer_balance(self.address, f64::from(balance) / 1e18, low);
+        let was_low = self.low.swap(low, Ordering::Relaxed);
+        if !low {
+            return Ok(balance);
+        }
+        if !was_low {
+            self.notifications.notify(WebhookEvent::LowBalance {
+                address: self.address,
+                balance_eth: format_ether(balance),
+                min_balance_eth: format_ether(self.min_balance),
+            });
+        }
+        error!(
+            address = %self.address,
+            balance = %format_ether(balance),
+            min_balance = %format_ether(self.min_balance),
+            "Signer balance is low, transactions will start failing"
+        );
+        if self.funder.kind() == TopUpKind::None {
+            return Ok(balance);
+        }
+        let top_up = self.funder.kind();
+        match self.funder.fund(self.address, balance).await {
+            Ok(Funding::Requested) => {
+                record_top_up("requested");
+                info!(
+                    address = %self.address,
+                    amount = %format_ether(self.funder.amount()),
+                    ?top_up,
+                    "Requested a top up"
+                );
+            }
+            Ok(Funding::RateLimited(wait)) => {
+                record_top_up("rate_limited");
+                info!(
+                    address = %self.address,
+                    retry_in_secs = wait.as_secs(),
+                    ?top_up,
+                    "Top up requested recently, waiting for the cooldown"
+                );
+            }
+            Err(e @ FundingError::ProductionChain(_)) => {
+                record_top_up("refused");
+                error!(address = %self.address, error = %e, "Top up refused");
+            }
+            Err(e) => {
+                record_top_up("failed");
+                warn!(address = %self.address, error = %e, ?top_up, "Top up failed");
+            }
+        }
+        Ok(balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_amounts_parse_as_ether() {
+        let config = BalanceConfig::default();
+        config.validate().unwrap();
+        assert_eq!(
+            config.min_balance().unwrap(),
+            U256::from(50_000_000_000_000_000u64)
+        );
+        let http = BalanceConfig {
+            top_up: TopUpKind::Http,
+            ..Default::default()
+        };
+        assert!(http.validate().is_err());
+        let typo = BalanceConfig {
+            min_balance_eth: "0.o5".to_string(),
+            ..Default::default()
+        };
+        assert!(typo.validate().is_err());
+    }
+}
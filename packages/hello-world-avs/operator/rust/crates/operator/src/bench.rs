@@ -0,0 +1,441 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use alloy::{primitives::utils::parse_ether, signers::local::PrivateKeySigner};
+use clap::ValueEnum;
+use serde::Serialize;
+use tokio::{
+    task::JoinSet,
+    time::{self, Instant, MissedTickBehavior},
+};
+use tracing::{info, warn};
+
+use crate::config::OperatorConfig;
+use crate::control::{ControlState, TaskOutcome};
+use crate::error::{DevnetError, OperatorError};
+use crate::fleet::{fleet_key, Fleet};
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::signer::OperatorSigner;
+use crate::slo::percentile;
+use crate::task_creator::TaskCreator;
+use crate::testutils::{Devnet, DevnetConfig};
+
+/// Prefix of the names of the measured tasks
+const BENCH_TASK_PREFIX: &str = "Bench";
+/// Prefix of the names of the tasks sent until the operator responds to one
+const WARMUP_TASK_PREFIX: &str = "Warmup";
+/// How often the outcomes recorded by the operator are read
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Time a warm-up task gets to be responded to before another one is sent
+const WARMUP_RETRY: Duration = Duration::from_secs(2);
+/// Clock ticks per second of the CPU times in `/proc/self/stat`, `USER_HZ` on Linux
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+/// Size and pace of `operator bench`
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// Tasks created and measured
+    pub tasks: u32,
+    /// Tasks created per second
+    pub rate_per_sec: f64,
+    /// Time the tasks get to be responded to once the last one is created
+    pub timeout: Duration,
+}
+
+/// Output format of `operator bench`
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BenchFormat {
+    /// Aligned lines for a terminal
+    #[default]
+    Text,
+    /// A single JSON object, to compare runs in scripts
+    Json,
+}
+
+/// CPU time and memory of the process, read from procfs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessStats {
+    /// CPU seconds spent in user and kernel mode
+    pub cpu_secs: f64,
+    /// Resident memory in KiB
+    pub rss_kib: u64,
+    /// Peak resident memory in KiB
+    pub peak_rss_kib: u64,
+}
+
+impl ProcessStats {
+    /// Stats of the current process, `None` without procfs
+    pub fn read() -> Option<Self> {
+        let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        Self::parse(&stat, &status)
+    }
+
+    /// Stats from the contents of `/proc/<pid>/stat` and `/proc/<pid>/status`
+    fn parse(stat: &str, status: &str) -> Option<Self> {
+        // The command name may hold spaces, the fields after it are utime and stime 12th and 13th
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let ticks = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+        let kib = |key: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(key))?
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse()
+                .ok()
+        };
+        Some(Self {
+            cpu_secs: ticks as f64 / CLOCK_TICKS_PER_SEC,
+            rss_kib: kib("VmRSS:")?,
+            peak_rss_kib: kib("VmHWM:")?,
+        })
+    }
+}
+
+/// Throughput, latency and resource usage of a benchmark run
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    /// Tasks created
+    pub tasks: u32,
+    /// Tasks created per second that were asked for
+    pub target_rate_per_sec: f64,
+    /// Tasks responded to or handed to the aggregator
+    pub responded: usize,
+    /// Tasks that failed to be created or responded to
+    pub failed: usize,
+    /// Tasks without an outcome when the run timed out
+    pub unfinished: usize,
+    /// Seconds from the first task sent to the last outcome
+    pub elapsed_secs: f64,
+    /// Responded tasks per second
+    pub throughput_per_sec: f64,
+    /// Median latency from sending createNewTask to the response, in milliseconds
+    pub p50_ms: Option<u64>,
+    /// 99th percentile latency in milliseconds
+    pub p99_ms: Option<u64>,
+    /// Slowest response in milliseconds
+    pub max_ms: Option<u64>,
+    /// Average latency in milliseconds
+    pub mean_ms: Option<f64>,
+    /// CPU seconds the process spent during the run, the spammer included
+    pub cpu_secs: Option<f64>,
+    /// CPU usage during the run in percent of a core
+    pub cpu_percent: Option<f64>,
+    /// Resident memory at the end of the run in KiB
+    pub rss_kib: Option<u64>,
+    /// Peak resident memory of the process in KiB
+    pub peak_rss_kib: Option<u64>,
+}
+
+impl BenchReport {
+    /// Report of a run of `config` lasting `elapsed`, with the `latencies` of the responded
+    /// tasks and the process stats `before` and `after` it
+    pub fn new(
+        config: &BenchConfig,
+        latencies: &[Duration],
+        failed: usize,
+        elapsed: Duration,
+        before: Option<ProcessStats>,
+        after: Option<ProcessStats>,
+    ) -> Self {
+        let mut ms: Vec<u64> = latencies.iter().map(|l| l.as_millis() as u64).collect();
+        ms.sort_unstable();
+        let elapsed_secs = elapsed.as_secs_f64();
+        let per_sec = |value: f64| {
+            if elapsed_secs > 0.0 {
+                value / elapsed_secs
+            } else {
+                0.0
+            }
+        };
+        let cpu_secs = before
+            .zip(after)
+            .map(|(before, after)| after.cpu_secs - before.cpu_secs);
+        Self {
+            tasks: config.tasks,
+            target_rate_per_sec: config.rate_per_sec,
+            responded: ms.len(),
+            failed,
+            unfinished: (config.tasks as usize).saturating_sub(ms.len() + failed),
+            elapsed_secs,
+            throughput_per_sec: per_sec(ms.len() as f64),
+            p50_ms: percentile(&ms, 50.0),
+            p99_ms: percentile(&ms, 99.0),
+            max_ms: ms.last().copied(),
+            mean_ms: (!ms.is_empty()).then(|| ms.iter().sum::<u64>() as f64 / ms.len() as f64),
+            cpu_secs,
+            cpu_percent: cpu_secs.map(|cpu| per_sec(cpu) * 100.0),
+            rss_kib: after.map(|stats| stats.rss_kib),
+            peak_rss_kib: after.map(|stats| stats.peak_rss_kib),
+        }
+    }
+
+    /// The report rendered as `format`
+    pub fn render(&self, format: BenchFormat) -> Result<String, serde_json::Error> {
+        match format {
+            BenchFormat::Text => Ok(self.to_string()),
+            BenchFormat::Json => serde_json::to_string_pretty(self),
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opt = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        let ms = |value: Option<u64>| opt(value.map(|v| format!("{v}ms")));
+        writeln!(
+            f,
+            "Tasks        {} at {:.2}/s",
+            self.tasks, self.target_rate_per_sec
+        )?;
+        writeln!(
+            f,
+            "Outcomes     {} responded, {} failed, {} unfinished",
+            self.responded, self.failed, self.unfinished
+        )?;
+        writeln!(f, "Elapsed      {:.2}s", self.elapsed_secs)?;
+        writeln!(f, "Throughput   {:.2} tasks/s", self.throughput_per_sec)?;
+        writeln!(f, "p50          {}", ms(self.p50_ms))?;
+        writeln!(f, "p99          {}", ms(self.p99_ms))?;
+        writeln!(f, "max          {}", ms(self.max_ms))?;
+        writeln!(
+            f,
+            "mean         {}",
+            opt(self.mean_ms.map(|v| format!("{v:.0}ms")))
+        )?;
+        writeln!(
+            f,
+            "CPU          {}",
+            opt(self
+                .cpu_secs
+                .zip(self.cpu_percent)
+                .map(|(secs, percent)| format!("{secs:.2}s ({percent:.1}%)")))
+        )?;
+        writeln!(
+            f,
+            "Memory       {} RSS, {} peak",
+            opt(self.rss_kib.map(|v| format!("{}MiB", v / 1024))),
+            opt(self.peak_rss_kib.map(|v| format!("{}MiB", v / 1024)))
+        )
+    }
+}
+
+/// Start an anvil devnet, run an operator with the settings of `config` against it, create
+/// the tasks of `bench` at its rate and measure how the operator keeps up.
+///
+/// A warm-up task is responded to before the measured ones are created, so the time the
+/// operator takes to subscribe isn't counted.
+pub async fn run_bench(
+    config: &OperatorConfig,
+    bench: &BenchConfig,
+) -> Result<BenchReport, DevnetError> {
+    let spammer = OperatorSigner::new(PrivateKeySigner::random());
+    let devnet = Devnet::start(&DevnetConfig {
+        fund: vec![spammer.address()],
+        ..DevnetConfig::default()
+    })
+    .await?;
+    let report = bench_on(&devnet, config, bench, &spammer).await;
+    devnet.stop().await?;
+    report
+}
+
+async fn bench_on(
+    devnet: &Devnet,
+    config: &OperatorConfig,
+    bench: &BenchConfig,
+    spammer: &OperatorSigner,
+) -> Result<BenchReport, DevnetError> {
+    let mut config = config.clone();
+    config.rpc_url = devnet.http_url().to_string();
+    config.ws_url = devnet.ws_url().to_string();
+    config.dry_run = false;
+    let contracts = devnet.contract_addresses()?;
+    let shutdown = Shutdown::default();
+    let balance = parse_ether("10").expect("valid ether amount");
+    let fleet = Fleet::start(
+        &config,
+        &contracts,
+        vec![fleet_key(0, 0)],
+        balance,
+        shutdown.clone(),
+    )
+    .await?;
+    let control = fleet.members()[0].control.clone();
+    // The spammer isn't measured, so it gets no budget, rate limit or audit log
+    let creator = Arc::new(TaskCreator::new(
+        devnet.http_url(),
+        &Services::default(),
+        spammer,
+        contracts.swap_manager,
+        config.pending_tx_watcher().map_err(OperatorError::from)?,
+    )?);
+
+    warm_up(&creator, &control, bench.timeout).await?;
+    info!(
+        tasks = bench.tasks,
+        rate_per_sec = bench.rate_per_sec,
+        "Operator warmed up, benchmarking"
+    );
+    let before = ProcessStats::read();
+    let started = Instant::now();
+    let create_failures = Arc::new(AtomicU32::new(0));
+    let sending = tokio::spawn(send_tasks(creator, bench.clone(), create_failures.clone()));
+    let deadline = started
+        + Duration::from_secs_f64(f64::from(bench.tasks) / bench.rate_per_sec)
+        + bench.timeout;
+    let mut outcomes: HashMap<String, (TaskOutcome, SystemTime)> = HashMap::new();
+    let mut last_outcome = started;
+    while outcomes.len() + (create_failures.load(Ordering::Relaxed) as usize) < bench.tasks as usize
+        && Instant::now() < deadline
+    {
+        for record in control.recent_tasks(0) {
+            if record.task_name.starts_with(BENCH_TASK_PREFIX)
+                && record.outcome != TaskOutcome::Paused
+                && !outcomes.contains_key(&record.task_name)
+            {
+                outcomes.insert(record.task_name, (record.outcome, record.at));
+                last_outcome = Instant::now();
+            }
+        }
+        time::sleep(POLL_INTERVAL).await;
+    }
+    let sent = sending.await.unwrap_or_default();
+    let after = ProcessStats::read();
+    shutdown.trigger();
+    fleet.join().await?;
+
+    let mut latencies = Vec::new();
+    let mut failed = create_failures.load(Ordering::Relaxed) as usize;
+    for (name, (outcome, at)) in &outcomes {
+        match (outcome, sent.get(name)) {
+            (TaskOutcome::Responded | TaskOutcome::Aggregated, Some(sent_at)) => {
+                latencies.push(at.duration_since(*sent_at).unwrap_or_default());
+            }
+            _ => failed += 1,
+        }
+    }
+    Ok(BenchReport::new(
+        bench,
+        &latencies,
+        failed,
+        last_outcome - started,
+        before,
+        after,
+    ))
+}
+
+/// Send warm-up tasks until the operator responds to one, failing once `timeout` passes
+async fn warm_up(
+    creator: &TaskCreator,
+    control: &ControlState,
+    timeout: Duration,
+) -> Result<(), DevnetError> {
+    let deadline = Instant::now() + timeout;
+    for attempt in 0u32.. {
+        // The operator may not be subscribed yet when the first ones are created
+        creator
+            .create_task(&format!("{WARMUP_TASK_PREFIX}{attempt}"))
+            .await?;
+        let retry_at = (Instant::now() + WARMUP_RETRY).min(deadline);
+        while Instant::now() < retry_at {
+            if control
+                .recent_tasks(0)
+                .iter()
+                .any(|record| record.task_name.starts_with(WARMUP_TASK_PREFIX))
+            {
+                return Ok(());
+            }
+            time::sleep(POLL_INTERVAL).await;
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+    }
+    Err(DevnetError::WarmUpTimeout(timeout))
+}
+
+/// Create the tasks of `bench` at its rate, returning when each of them was sent
+async fn send_tasks(
+    creator: Arc<TaskCreator>,
+    bench: BenchConfig,
+    failures: Arc<AtomicU32>,
+) -> HashMap<String, SystemTime> {
+    let mut ticks = time::interval(Duration::from_secs_f64(1.0 / bench.rate_per_sec));
+    // A slow send delays the next ones instead of bursting to catch up
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut sent = HashMap::with_capacity(bench.tasks as usize);
+    let mut in_flight = JoinSet::new();
+    for task in 0..bench.tasks {
+        ticks.tick().await;
+        let task_name = format!("{BENCH_TASK_PREFIX}{task}");
+        sent.insert(task_name.clone(), SystemTime::now());
+        let (creator, failures) = (creator.clone(), failures.clone());
+        in_flight.spawn(async move {
+            if let Err(e) = creator.create_task(&task_name).await {
+                warn!(task_name, error = %e, "Failed to create a benchmark task");
+                failures.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+    while in_flight.join_next().await.is_some() {}
+    sent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_measures_latency_throughput_and_cpu() {
+        let stat = "4242 (operator bench) S 1 4242 4242 0 -1 4194560 9000 0 0 0 250 50 0 0 20 0 \
+                    12 0 100 1000000 5000";
+        let status = "Name:\toperator\nVmHWM:\t  204800 kB\nVmRSS:\t  102400 kB\n";
+        let after = ProcessStats::parse(stat, status).unwrap();
+        assert_eq!(after.cpu_secs, 3.0);
+        assert_eq!(after.rss_kib, 102_400);
+        assert_eq!(after.peak_rss_kib, 204_800);
+        assert_eq!(ProcessStats::parse("4242 (operator)", status), None);
+
+        let config = BenchConfig {
+            tasks: 102,
+            rate_per_sec: 10.0,
+            timeout: Duration::from_secs(60),
+        };
+        let latencies: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let before = ProcessStats {
+            cpu_secs: 1.0,
+            ..after
+        };
+        let report = BenchReport::new(
+            &config,
+            &latencies,
+            1,
+            Duration::from_secs(10),
+            Some(before),
+            Some(after),
+        );
+        assert_eq!(report.responded, 100);
+        assert_eq!(report.unfinished, 1);
+        assert_eq!(report.throughput_per_sec, 10.0);
+        assert_eq!(report.p50_ms, Some(50));
+        assert_eq!(report.p99_ms, Some(99));
+        assert_eq!(report.max_ms, Some(100));
+        assert_eq!(report.cpu_secs, Some(2.0));
+        assert_eq!(report.cpu_percent, Some(20.0));
+        let text = report.render(BenchFormat::Text).unwrap();
+        assert!(text.contains("Throughput   10.00 tasks/s"));
+        assert!(text.contains("Memory       100MiB RSS, 200MiB peak"));
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use alloy::primitives::keccak256;
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use eigensdk::crypto_bls::{BlsG1Point, BlsKeyPair};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::error::SignerError;
+use crate::signer::{read_password, KeystorePassword};
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// scrypt cost eigensdk uses for BLS keystores (`keystore.StandardScryptN`)
+const STANDARD_SCRYPT_LOG_N: u8 = 18;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const DKLEN: usize = 32;
+
+/// BN254 private key of the operator, kept so it can be written back to a keystore.
+///
+/// [`BlsKeyPair`] does not expose its private key once built, so this is the type to
+/// hold on to and [`BlsKey::key_pair`] hands out the signing pair.
+#[derive(Clone)]
+pub struct BlsKey {
+    private_key: Fr,
+}
+
+impl fmt::Debug for BlsKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlsKey")
+            .field("public_key", &format_g1(&self.public_key()))
+            .finish_non_exhaustive()
+    }
+}
+
+impl BlsKey {
+    /// Draw a new random key
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 64];
+        rand::rng().fill_bytes(&mut bytes);
+        // 512 bits reduced mod r, the bias is negligible
+        Self {
+            private_key: Fr::from_le_bytes_mod_order(&bytes),
+        }
+    }
+
+    /// Parse the decimal private key format used by eigensdk
+    pub fn from_decimal(key: &str) -> Result<Self, SignerError> {
+        let private_key = Fr::from_str(key.trim())
+            .map_err(|_| SignerError::Invalid("invalid BLS private key".to_string()))?;
+        Ok(Self { private_key })
+    }
+
+    /// Key pair signing with this key
+    pub fn key_pair(&self) -> BlsKeyPair {
+        BlsKeyPair::new(self.private_key.to_string()).expect("a valid Fr is a valid BLS key")
+    }
+
+    /// Public key on G1
+    pub fn public_key(&self) -> BlsG1Point {
+        self.key_pair().public_key()
+    }
+
+    /// Public key on G1 in eigensdk's `E([x,y])` notation
+    pub fn public_key_string(&self) -> String {
+        format_g1(&self.public_key())
+    }
+
+    /// Write the key to `path` as an eigensdk compatible encrypted keystore
+    pub fn save(&self, path: &Path, password: &str) -> Result<(), SignerError> {
+        self.save_with_cost(path, password, STANDARD_SCRYPT_LOG_N)
+    }
+
+    fn save_with_cost(&self, path: &Path, password: &str, log_n: u8) -> Result<(), SignerError> {
+        let keystore = BlsKeystoreJson {
+            pub_key: self.public_key_string(),
+            crypto: encrypt(self.private_key.to_string().as_bytes(), password, log_n)?,
+        };
+        let write_error = |source| SignerError::Write {
+            what: "BLS keystore",
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let json = serde_json::to_string_pretty(&keystore).expect("the keystore serializes");
+        std::fs::write(path, json).map_err(write_error)
+    }
+
+    /// Decrypt an eigensdk BLS keystore
+    pub fn load(path: &Path, password: &str) -> Result<Self, SignerError> {
+        let data = std::fs::read_to_string(path).map_err(|source| SignerError::Read {
+            what: "BLS keystore",
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let keystore: BlsKeystoreJson = serde_json::from_str(&data).map_err(|e| {
+            SignerError::Invalid(format!("invalid BLS keystore {}: {e}", path.display()))
+        })?;
+        let plaintext = decrypt(&keystore.crypto, password).map_err(|e| SignerError::Decrypt {
+            what: "BLS keystore",
+            path: path.to_path_buf(),
+            source: e.into(),
+        })?;
+        let key = Self::from_decimal(&String::from_utf8_lossy(&plaintext))?;
+        if !keystore.pub_key.is_empty() && keystore.pub_key != key.public_key_string() {
+            return Err(SignerError::Invalid(format!(
+                "BLS keystore {} public key does not match its private key",
+                path.display()
+            )));
+        }
+        Ok(key)
+    }
+}
+
+/// BLS keystore location and password, see [`crate::config::OperatorConfig::bls_keystore`]
+#[derive(Debug, Clone)]
+pub struct BlsKeystore {
+    /// Path of the keystore file
+    pub path: PathBuf,
+    /// Source of the decryption password
+    pub password: KeystorePassword,
+}
+
+impl BlsKeystore {
+    /// Unlock the key, prompting for the password if needed
+    pub fn load(&self) -> Result<BlsKey, SignerError> {
+        let password = read_password(&self.password, &self.path)?;
+        BlsKey::load(&self.path, &password)
+    }
+}
+
+fn format_g1(point: &BlsG1Point) -> String {
+    let g1 = point.g1();
+    format!("E([{},{}])", g1.x, g1.y)
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlsKeystoreJson {
+    #[serde(rename = "pubKey", default)]
+    pub_key: String,
+    crypto: CryptoJson,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CryptoJson {
+    cipher: String,
+    ciphertext: String,
+    cipherparams: CipherParams,
+    kdf: String,
+    kdfparams: ScryptParams,
+    mac: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScryptParams {
+    dklen: usize,
+    n: u64,
+    p: u32,
+    r: u32,
+    salt: String,
+}
+
+fn derive_key(password: &str, salt: &[u8], params: &ScryptParams) -> Result<Vec<u8>, SignerError> {
+    if !params.n.is_power_of_two() || params.n < 2 {
+        return Err(SignerError::Invalid(format!(
+            "scrypt n must be a power of two, got {}",
+            params.n
+        )));
+    }
+    if params.dklen < 32 {
+        return Err(SignerError::Invalid(format!(
+            "scrypt dklen must be at least 32, got {}",
+            params.dklen
+        )));
+    }
+    let log_n = params.n.trailing_zeros() as u8;
+    let scrypt_params = scrypt::Params::new(log_n, params.r, params.p, params.dklen)
+        .map_err(|e| SignerError::Invalid(format!("invalid scrypt params: {e}")))?;
+    let mut key = vec![0u8; params.dklen];
+    scrypt::scrypt(password.as_bytes(), salt, &scrypt_params, &mut key)
+        .map_err(|e| SignerError::Invalid(format!("scrypt failed: {e}")))?;
+    Ok(key)
+}
+
+fn encrypt(plaintext: &[u8], password: &str, log_n: u8) -> Result<CryptoJson, SignerError> {
+    let mut salt = [0u8; 32];
+    let mut iv = [0u8; 16];
+    rand::rng().fill_bytes(&mut salt);
+    rand::rng().fill_bytes(&mut iv);
+    let params = ScryptParams {
+        dklen: DKLEN,
+        n: 1 << log_n,
+        p: SCRYPT_P,
+        r: SCRYPT_R,
+        salt: hex::encode(salt),
+    };
+    let key = derive_key(password, &salt, &params)?;
+
+    let mut ciphertext = plaintext.to_vec();
+    Aes128Ctr::new(key[..16].into(), (&iv).into()).apply_keystream(&mut ciphertext);
+    let mac = keccak256([&key[16..32], &ciphertext[..]].concat());
+    Ok(CryptoJson {
+        cipher: "aes-128-ctr".to_string(),
+        ciphertext: hex::encode(ciphertext),
+        cipherparams: CipherParams {
+            iv: hex::encode(iv),
+        },
+        kdf: "scrypt".to_string(),
+        kdfparams: params,
+        mac: hex::encode(mac),
+    })
+}
+
+fn decrypt(crypto: &CryptoJson, password: &str) -> Result<Vec<u8>, SignerError> {
+    if crypto.kdf != "scrypt" {
+        return Err(SignerError::Invalid(format!(
+            "unsupported kdf {}",
+            crypto.kdf
+        )));
+    }
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(SignerError::Invalid(format!(
+            "unsupported cipher {}",
+            crypto.cipher
+        )));
+    }
+    let decode_hex = |field: &str, value: &str| {
+        hex::decode(value).map_err(|e| SignerError::Invalid(format!("invalid {field}: {e}")))
+    };
+    let salt = decode_hex("salt", &crypto.kdfparams.salt)?;
+    let iv: [u8; 16] = decode_hex("iv", &crypto.cipherparams.iv)?
+        .try_into()
+        .map_err(|_| SignerError::Invalid("iv must be 16 bytes".to_string()))?;
+    let mut ciphertext = decode_hex("ciphertext", &crypto.ciphertext)?;
+    let key = derive_key(password, &salt, &crypto.kdfparams)?;
+
+    let mac = keccak256([&key[16..32], &ciphertext[..]].concat());
+    if hex::encode(mac) != crypto.mac.trim_start_matches("0x") {
+        return Err(SignerError::Invalid("wrong password".to_string()));
+    }
+    Aes128Ctr::new(key[..16].into(), (&iv).into()).apply_keystream(&mut ciphertext);
+    Ok(ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bls_keystore_round_trip() {
+        let dir = std::env::temp_dir().join(format!("bls-keystore-{}", std::process::id()));
+        let path = dir.join("operator.bls.json");
+        let key = BlsKey::generate();
+        key.save_with_cost(&path, "hunter2", 4).unwrap();
+
+        let loaded = BlsKey::load(&path, "hunter2").unwrap();
+        assert_eq!(loaded.public_key(), key.public_key());
+        assert!(BlsKey::load(&path, "wrong").is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,607 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::utils::{format_ether, parse_ether};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::error::ConfigError;
+use crate::prometheus::set_budget_remaining;
+use crate::webhook::{Notifications, WebhookEvent};
+
+const HOUR_MS: u64 = 3_600_000;
+const DAY_MS: u64 = 86_400_000;
+
+/// Fee limits of a network, a `[budget.networks.<name>]` entry
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetLimits {
+    /// Ether the transactions may spend in fees over any hour, unlimited if unset
+    pub max_eth_per_hour: Option<String>,
+    /// Ether the transactions may spend in fees over any day, unlimited if unset
+    pub max_eth_per_day: Option<String>,
+}
+
+impl BudgetLimits {
+    /// Hourly and daily limits in wei
+    fn parse(&self, section: &str) -> Result<(Option<u128>, Option<u128>), ConfigError> {
+        let parse = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|eth| {
+                    parse_ether(eth)
+                        .ok()
+                        .and_then(|wei| u128::try_from(wei).ok())
+                        .filter(|&wei| wei > 0)
+                        .ok_or_else(|| {
+                            ConfigError::Invalid(format!(
+                                "{section}.{field} must be a positive amount of ether, got {eth}"
+                            ))
+                        })
+                })
+                .transpose()
+        };
+        Ok((
+            parse("max_eth_per_hour", &self.max_eth_per_hour)?,
+            parse("max_eth_per_day", &self.max_eth_per_day)?,
+        ))
+    }
+}
+
+/// Cap on the fees spent by the spammer and the responder, the `[budget]` config section
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    /// Ether the transactions may spend in fees over any hour, unlimited if unset
+    pub max_eth_per_hour: Option<String>,
+    /// Ether the transactions may spend in fees over any day, unlimited if unset
+    pub max_eth_per_day: Option<String>,
+    /// Limits replacing the ones above on the network `rpc.network` selects, keyed by the
+    /// names of `rpc.networks`
+    pub networks: BTreeMap<String, BudgetLimits>,
+    /// File the spends are appended to and read back from on start, so that the windows
+    /// survive a restart. The budget is per process if unset
+    pub state_file: Option<PathBuf>,
+}
+
+impl BudgetConfig {
+    /// Hourly and daily limits in wei on `network`
+    pub fn limits(
+        &self,
+        network: Option<&str>,
+    ) -> Result<(Option<u128>, Option<u128>), ConfigError> {
+        match network.and_then(|name| Some((name, self.networks.get(name)?))) {
+            Some((name, limits)) => limits.parse(&format!("budget.networks.{name}")),
+            None => BudgetLimits {
+                max_eth_per_hour: self.max_eth_per_hour.clone(),
+                max_eth_per_day: self.max_eth_per_day.clone(),
+            }
+            .parse("budget"),
+        }
+    }
+
+    /// Check that every limit is a positive amount of ether
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.limits(None)?;
+        for name in self.networks.keys() {
+            self.limits(Some(name))?;
+        }
+        Ok(())
+    }
+}
+
+/// A window whose fees reached its limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exhausted {
+    /// `hour` or `day`
+    pub window: &'static str,
+    /// Fees spent or reserved in the window, in wei
+    pub spent: u128,
+    /// Limit of the window, in wei
+    pub limit: u128,
+    /// Time until enough spending leaves the window to send again
+    pub wait: Duration,
+}
+
+/// Fees of a transaction counted against the budget, a line of the state file
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Spend {
+    /// Reservation the fees were counted under
+    id: u64,
+    /// Unix time in milliseconds the transaction was sent at
+    at_ms: u64,
+    /// Fees in wei, the most the transaction may pay until its receipt settles them
+    fees_wei: u128,
+}
+
+/// Fees spent over sliding hour and day windows, holding transactions back once a window
+/// reaches its limit.
+///
+/// A transaction reserves the most it may pay before it is sent and the reservation is
+/// settled to what it paid once mined, so concurrent sends can't overshoot a limit by more
+/// than one transaction. The spends live in memory unless `budget.state_file` is set, in
+/// which case they are appended to it and read back on start, so a restart doesn't reset
+/// the windows
+#[derive(Debug)]
+pub struct SpendBudget {
+    per_hour: Option<u128>,
+    per_day: Option<u128>,
+    state: Mutex<BudgetState>,
+    /// Where running out of budget is reported
+    notifications: Notifications,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    /// Spends of the transactions sent within the day, oldest first
+    spends: VecDeque<Spend>,
+    /// Id of the next reservation
+    next_id: u64,
+    /// Whether a send is held back, so the alert fires once per exhaustion
+    exhausted: bool,
+    /// State file the spends are appended to, if any
+    file: Option<(PathBuf, File)>,
+}
+
+/// Fees reserved for a transaction until [`BudgetReservation::settle`] replaces them with
+/// what it paid or [`BudgetReservation::release`] gives them back. Dropped otherwise, as when
+/// the transaction was sent but never mined, they stay counted
+#[derive(Debug)]
+#[must_use]
+pub struct BudgetReservation {
+    budget: Arc<SpendBudget>,
+    id: u64,
+}
+
+impl BudgetReservation {
+    /// Count the `fees_wei` the transaction paid instead of the reserved fees
+    pub fn settle(self, fees_wei: u128) {
+        self.budget.update(self.id, fees_wei);
+    }
+
+    /// Give the reserved fees back, the transaction not having been sent
+    pub fn release(self) {
+        self.budget.update(self.id, 0);
+    }
+}
+
+impl SpendBudget {
+    /// Budget of `per_hour` and `per_day` wei, unlimited where `None`
+    pub fn new(per_hour: Option<u128>, per_day: Option<u128>) -> Self {
+        Self {
+            per_hour,
+            per_day,
+            state: Mutex::new(BudgetState::default()),
+            notifications: Notifications::default(),
+        }
+    }
+
+    /// Budget capping the fees as set in `config` for `network`, `None` without a limit.
+    /// The spends survive a restart only with `config.state_file` set, the budget being per
+    /// process otherwise. Running out of it is reported to `notifications`
+    pub fn from_config(
+        config: &BudgetConfig,
+        network: Option<&str>,
+        notifications: Notifications,
+    ) -> Result<Option<Self>, ConfigError> {
+        let (per_hour, per_day) = config.limits(network)?;
+        if per_hour.is_none() && per_day.is_none() {
+            return Ok(None);
+        }
+        let budget = match &config.state_file {
+            Some(path) => Self::with_state_file(per_hour, per_day, path)?,
+            None => Self::new(per_hour, per_day),
+        };
+        budget.export(SystemTime::now());
+        info!(
+            max_eth_per_hour = per_hour.map(format_ether),
+            max_eth_per_day = per_day.map(format_ether),
+            state_file = config
+                .state_file
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            "Capping the fees spent by the transactions"
+        );
+        Ok(Some(Self {
+            notifications,
+            ..budget
+        }))
+    }
+
+    /// Budget of `per_hour` and `per_day` wei counting the spends of the day found in the
+    /// state file at `path` and appending the new ones to it. Unreadable lines, such as one
+    /// torn by a crash, are skipped and the file is rewritten without them or the spends
+    /// older than a day
+    pub fn with_state_file(
+        per_hour: Option<u128>,
+        per_day: Option<u128>,
+        path: &Path,
+    ) -> Result<Self, ConfigError> {
+        let read_error = |source| ConfigError::Read {
+            what: "budget state file",
+            path: path.to_path_buf(),
+            source,
+        };
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(read_error(e)),
+        };
+        // Later lines settle or release the spend of the same id
+        let mut spends: Vec<Spend> = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<Spend>(line) {
+                Ok(spend) => match spends.iter_mut().find(|known| known.id == spend.id) {
+                    Some(known) => known.fees_wei = spend.fees_wei,
+                    None => spends.push(spend),
+                },
+                Err(e) => warn!(path = %path.display(), error = %e, "Skipping a budget state line"),
+            }
+        }
+        let now = unix_ms(SystemTime::now());
+        spends.retain(|spend| spend.fees_wei > 0 && now.saturating_sub(spend.at_ms) < DAY_MS);
+        spends.sort_by_key(|spend| spend.at_ms);
+
+        let compacted: String = spends
+            .iter()
+            .map(|spend| format!("{}\n", serde_json::to_string(spend).unwrap_or_default()))
+            .collect();
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, compacted)
+            .and_then(|()| std::fs::rename(&tmp, path))
+            .map_err(read_error)?;
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(read_error)?;
+
+        let budget = Self::new(per_hour, per_day);
+        {
+            let mut state = budget.lock();
+            state.next_id = spends.iter().map(|spend| spend.id + 1).max().unwrap_or(0);
+            state.spends = spends.into();
+            state.file = Some((path.to_path_buf(), file));
+        }
+        Ok(budget)
+    }
+
+    #[cfg(test)]
+    fn record_at(&self, fees_wei: u128, now: SystemTime) -> u64 {
+        self.lock().push(fees_wei, now)
+    }
+
+    /// Count `fees_wei` instead of the fees of the spend `id`, if still within the day
+    fn update(&self, id: u64, fees_wei: u128) {
+        {
+            let mut state = self.lock();
+            let Some(spend) = state.spends.iter_mut().find(|spend| spend.id == id) else {
+                return;
+            };
+            spend.fees_wei = fees_wei;
+            let spend = *spend;
+            state.append(&spend);
+        }
+        self.export(SystemTime::now());
+    }
+
+    /// Whether a transaction may be sent, the window holding it back otherwise
+    pub fn try_spend(&self) -> Result<(), Exhausted> {
+        self.try_spend_at(SystemTime::now())
+    }
+
+    fn try_spend_at(&self, now: SystemTime) -> Result<(), Exhausted> {
+        let mut state = self.lock();
+        Self::check(self.per_hour, self.per_day, &mut state, unix_ms(now))
+    }
+
+    /// Reserve `fees_wei` for a transaction if the budget lets it through, in the same step
+    /// so that concurrent sends see each other's reservations
+    fn try_reserve_at(&self, fees_wei: u128, now: SystemTime) -> Result<u64, Exhausted> {
+        let mut state = self.lock();
+        Self::check(self.per_hour, self.per_day, &mut state, unix_ms(now))?;
+        Ok(state.push(fees_wei, now))
+    }
+
+    fn check(
+        per_hour: Option<u128>,
+        per_day: Option<u128>,
+        state: &mut BudgetState,
+        now: u64,
+    ) -> Result<(), Exhausted> {
+        while state
+            .spends
+            .front()
+            .is_some_and(|spend| now.saturating_sub(spend.at_ms) >= DAY_MS)
+        {
+            state.spends.pop_front();
+        }
+        [("hour", HOUR_MS, per_hour), ("day", DAY_MS, per_day)]
+            .into_iter()
+            .filter_map(|(window, length, limit)| {
+                let limit = limit?;
+                let spends: Vec<_> = state
+                    .spends
+                    .iter()
+                    .filter(|spend| now.saturating_sub(spend.at_ms) < length)
+                    .collect();
+                let mut spent: u128 = spends.iter().map(|spend| spend.fees_wei).sum();
+                if spent < limit {
+                    return None;
+                }
+                let total = spent;
+                // The oldest spends leave the window first
+                let mut wait = 0;
+                for spend in &spends {
+                    spent -= spend.fees_wei;
+                    wait = (spend.at_ms + length).saturating_sub(now);
+                    if spent < limit {
+                        break;
+                    }
+                }
+                Some(Exhausted {
+                    window,
+                    spent: total,
+                    limit,
+                    wait: Duration::from_millis(wait),
+                })
+            })
+            .max_by_key(|exhausted| exhausted.wait)
+            .map_or(Ok(()), Err)
+    }
+
+    /// Fees still allowed over the hour and over the day, `None` where unlimited
+    pub fn remaining(&self) -> (Option<u128>, Option<u128>) {
+        self.remaining_at(SystemTime::now())
+    }
+
+    fn remaining_at(&self, now: SystemTime) -> (Option<u128>, Option<u128>) {
+        let now = unix_ms(now);
+        let state = self.lock();
+        let spent = |length: u64| -> u128 {
+            state
+                .spends
+                .iter()
+                .filter(|spend| now.saturating_sub(spend.at_ms) < length)
+                .map(|spend| spend.fees_wei)
+                .sum()
+        };
+        (
+            self.per_hour
+                .map(|limit| limit.saturating_sub(spent(HOUR_MS))),
+            self.per_day
+                .map(|limit| limit.saturating_sub(spent(DAY_MS))),
+        )
+    }
+
+    fn export(&self, now: SystemTime) {
+        let (hour, day) = self.remaining_at(now);
+        if let Some(hour) = hour {
+            set_budget_remaining("hour", hour as f64 / 1e18);
+        }
+        if let Some(day) = day {
+            set_budget_remaining("day", day as f64 / 1e18);
+        }
+    }
+
+    /// Wait until the budget lets a `method` transaction through, alerting when it runs out
+    pub async fn acquire(&self, method: &'static str) {
+        self.wait_until(method, || self.try_spend()).await;
+    }
+
+    /// Wait until the budget lets a `method` transaction through and reserve the `fees_wei`
+    /// it may pay at most
+    pub async fn reserve(
+        self: &Arc<Self>,
+        method: &'static str,
+        fees_wei: u128,
+    ) -> BudgetReservation {
+        let id = self
+            .wait_until(method, || self.try_reserve_at(fees_wei, SystemTime::now()))
+            .await;
+        BudgetReservation {
+            budget: self.clone(),
+            id,
+        }
+    }
+
+    async fn wait_until<T>(
+        &self,
+        method: &'static str,
+        mut attempt: impl FnMut() -> Result<T, Exhausted>,
+    ) -> T {
+        loop {
+            self.export(SystemTime::now());
+            let exhausted = match attempt() {
+                Ok(admitted) => {
+                    if std::mem::take(&mut self.lock().exhausted) {
+                        info!(
+                            method,
+                            "Spend budget available again, resuming the transactions"
+                        );
+                    }
+                    self.export(SystemTime::now());
+                    return admitted;
+                }
+                Err(exhausted) => exhausted,
+            };
+            if !std::mem::replace(&mut self.lock().exhausted, true) {
+                error!(
+                    method,
+                    window = exhausted.window,
+                    spent = %format_ether(exhausted.spent),
+                    limit = %format_ether(exhausted.limit),
+                    resume_in_secs = exhausted.wait.as_secs(),
+                    "Spend budget exhausted, holding the transactions back"
+                );
+                self.notifications.notify(WebhookEvent::BudgetExhausted {
+                    window: exhausted.window,
+                    spent_eth: format_ether(exhausted.spent),
+                    limit_eth: format_ether(exhausted.limit),
+                });
+            }
+            tokio::time::sleep(exhausted.wait.max(Duration::from_secs(1))).await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BudgetState> {
+        self.state.lock().expect("spend budget lock poisoned")
+    }
+}
+
+impl BudgetState {
+    /// Count the `fees_wei` of a transaction sent at `now` under a new id
+    fn push(&mut self, fees_wei: u128, now: SystemTime) -> u64 {
+        let spend = Spend {
+            id: self.next_id,
+            at_ms: unix_ms(now),
+            fees_wei,
+        };
+        self.next_id += 1;
+        self.spends.push_back(spend);
+        self.append(&spend);
+        spend.id
+    }
+
+    /// Append `spend` to the state file, the budget carrying on in memory if that fails
+    fn append(&mut self, spend: &Spend) {
+        let Some((path, file)) = &mut self.file else {
+            return;
+        };
+        let line = serde_json::to_string(spend).unwrap_or_default();
+        if let Err(e) = writeln!(file, "{line}") {
+            warn!(path = %path.display(), error = %e, "Failed to persist a budget spend");
+        }
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETH: u128 = 1_000_000_000_000_000_000;
+
+    const HOUR: Duration = Duration::from_secs(3_600);
+    const DAY: Duration = Duration::from_secs(86_400);
+
+    #[test]
+    fn test_budget_holds_sends_back_until_spending_leaves_the_window() {
+        let budget = SpendBudget::new(Some(ETH), Some(3 * ETH));
+        let start = SystemTime::now();
+        budget.record_at(ETH / 2, start);
+        budget.try_spend_at(start).unwrap();
+        budget.record_at(ETH / 2, start + Duration::from_secs(600));
+
+        let exhausted = budget
+            .try_spend_at(start + Duration::from_secs(600))
+            .unwrap_err();
+        assert_eq!(exhausted.window, "hour");
+        assert_eq!(exhausted.spent, ETH);
+        // The first spend leaves the hour 50 minutes later
+        assert_eq!(exhausted.wait, Duration::from_secs(3_000));
+        budget.try_spend_at(start + HOUR).unwrap();
+        assert_eq!(
+            budget.remaining_at(start + HOUR),
+            (Some(ETH / 2), Some(2 * ETH))
+        );
+
+        // The day fills up over several hours
+        for hour in 2..6 {
+            budget.record_at(ETH / 2, start + HOUR * hour);
+        }
+        let exhausted = budget.try_spend_at(start + HOUR * 5).unwrap_err();
+        assert_eq!(exhausted.window, "day");
+        assert_eq!(exhausted.wait, DAY - HOUR * 5);
+        budget.try_spend_at(start + DAY).unwrap();
+    }
+
+    #[test]
+    fn test_reservations_count_until_settled() {
+        let budget = SpendBudget::new(Some(ETH), None);
+        let now = SystemTime::now();
+        // Sends in flight see each other's reservations, so the limit is overshot by one
+        // transaction at most
+        let first = budget.try_reserve_at(ETH * 4 / 10, now).unwrap();
+        let second = budget.try_reserve_at(ETH * 4 / 10, now).unwrap();
+        let third = budget.try_reserve_at(ETH * 4 / 10, now).unwrap();
+        let exhausted = budget.try_reserve_at(ETH * 4 / 10, now).unwrap_err();
+        assert_eq!(exhausted.spent, ETH * 12 / 10);
+
+        // Mined transactions pay less than their max fees, an unsent one nothing
+        budget.update(first, ETH / 10);
+        budget.update(second, ETH / 10);
+        budget.update(third, 0);
+        assert_eq!(budget.remaining_at(now), (Some(ETH * 8 / 10), None));
+        budget.try_reserve_at(ETH * 4 / 10, now).unwrap();
+    }
+
+    #[test]
+    fn test_state_file_keeps_the_spends_of_the_day_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("budget-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("budget.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let now = SystemTime::now();
+
+        let budget = SpendBudget::with_state_file(Some(ETH), Some(2 * ETH), &path).unwrap();
+        let settled = budget.try_reserve_at(ETH / 2, now).unwrap();
+        budget.update(settled, ETH / 4);
+        let released = budget.try_reserve_at(ETH / 2, now).unwrap();
+        budget.update(released, 0);
+        // Sent but never mined, so counted at its max fees
+        budget.try_reserve_at(ETH / 2, now).unwrap();
+        budget.record_at(ETH, now - DAY);
+        drop(budget);
+
+        // A crash tore the last line
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"id\":9,\"at_").unwrap();
+        drop(file);
+
+        let budget = SpendBudget::with_state_file(Some(ETH), Some(2 * ETH), &path).unwrap();
+        assert_eq!(budget.remaining_at(now), (Some(ETH / 4), Some(ETH * 5 / 4)));
+        // Compacted to the two spends still counted
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        let id = budget.try_reserve_at(ETH / 8, now).unwrap();
+        assert!(id > released);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_network_limits_replace_the_default_ones() {
+        let mut config = BudgetConfig {
+            max_eth_per_day: Some("2".to_string()),
+            ..Default::default()
+        };
+        config.networks.insert(
+            "holesky".to_string(),
+            BudgetLimits {
+                max_eth_per_hour: Some("0.1".to_string()),
+                max_eth_per_day: None,
+            },
+        );
+        config.validate().unwrap();
+        assert_eq!(config.limits(None).unwrap(), (None, Some(2 * ETH)));
+        assert_eq!(
+            config.limits(Some("sepolia")).unwrap(),
+            (None, Some(2 * ETH))
+        );
+        assert_eq!(
+            config.limits(Some("holesky")).unwrap(),
+            (Some(ETH / 10), None)
+        );
+
+        config.max_eth_per_hour = Some("0".to_string());
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,554 @@
+use std::{collections::BTreeMap, fmt, future::IntoFuture, time::Duration};
+
+use alloy::{
+    primitives::{Address, Bytes},
+    providers::Provider,
+    rpc::types::{BlockNumberOrTag, Filter},
+    signers::Signature,
+    sol_types::{SolEvent, SolValue},
+};
+use eigensdk::common::SdkSigner;
+use serde::Deserialize;
+use serde_json::json;
+use swap_manager_utils::SwapManager::{ISwapManager::Task, SwapManager};
+use tracing::{debug, error, info, warn};
+
+use crate::ens::AddressOrName;
+use crate::error::{ChainError, ConfigError, OperatorError};
+use crate::nonce_manager::NonceManager;
+use crate::pending_tx::PendingTxWatcher;
+use crate::prometheus::{
+    record_challenge, record_gas_used, record_invalid_response, record_response_checked,
+    record_tx_failure, time_rpc, PendingTxGuard,
+};
+use crate::retry::{retry, RetryConfig};
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::signer::OperatorSigner;
+use crate::task_responder::task_response_digest;
+use crate::task_source::{fetch_tasks, MAX_POLL_BLOCK_RANGE};
+use crate::webhook::WebhookEvent;
+
+/// Contract method challenges are sent through
+const SLASH_OPERATOR: &str = "slashOperator";
+
+/// The `[challenger]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChallengerConfig {
+    /// Blocks after a fault shows on chain, the response block or the end of the response
+    /// interval for missed responses, during which it is still challenged
+    pub window_blocks: u64,
+    /// Seconds between two polls for new responses
+    pub poll_interval_secs: u64,
+    /// First block whose responses are checked, the current head if unset
+    pub start_block: Option<u64>,
+    /// Operators expected to respond to every task, challenged when they miss the
+    /// response interval, as addresses or ENS names
+    pub expected_operators: Vec<AddressOrName>,
+    /// URL every fault is POSTed to as JSON, faults are only logged if unset
+    /// (`CHALLENGER_ALERT_URL`)
+    pub alert_url: Option<String>,
+}
+
+impl Default for ChallengerConfig {
+    fn default() -> Self {
+        Self {
+            window_blocks: 100,
+            poll_interval_secs: 4,
+            start_block: None,
+            expected_operators: Vec::new(),
+            alert_url: None,
+        }
+    }
+}
+
+impl ChallengerConfig {
+    /// Check that the challenger can poll and challenge with these settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.window_blocks == 0 {
+            return Err(ConfigError::Invalid(
+                "challenger.window_blocks must be greater than 0".to_string(),
+            ));
+        }
+        if self.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "challenger.poll_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What a response gets wrong compared to the one recomputed from its task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseFault {
+    /// No response was stored before the response interval ended
+    Missed,
+    /// The response doesn't decode as `(address[] operators, bytes[] signatures, uint32)`
+    Malformed,
+    /// The response doesn't hold exactly one signature per operator
+    SignatureCount {
+        /// Operators listed
+        operators: usize,
+        /// Signatures listed
+        signatures: usize,
+    },
+    /// A signature isn't the one its operator makes over the task
+    WrongSigner {
+        /// Operator listed for the signature
+        expected: Address,
+        /// Key the signature recovers to over the expected message, if any
+        recovered: Option<Address>,
+    },
+}
+
+impl ResponseFault {
+    /// Label of the fault in the metrics and alerts
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Missed => "missed",
+            Self::Malformed => "malformed",
+            Self::SignatureCount { .. } => "signature_count",
+            Self::WrongSigner { .. } => "wrong_signer",
+        }
+    }
+}
+
+impl fmt::Display for ResponseFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missed => write!(f, "no response within the response interval"),
+            Self::Malformed => write!(f, "response doesn't decode"),
+            Self::SignatureCount {
+                operators,
+                signatures,
+            } => write!(f, "{signatures} signatures for {operators} operators"),
+            Self::WrongSigner {
+                expected,
+                recovered: Some(recovered),
+            } => write!(f, "signature of {expected} recovers to {recovered}"),
+            Self::WrongSigner {
+                expected,
+                recovered: None,
+            } => write!(f, "signature of {expected} doesn't recover"),
+        }
+    }
+}
+
+/// Check `response`, as stored by respondToTask, against the signatures every listed
+/// operator would make over the task named `task_name`
+pub fn check_response(task_name: &str, response: &[u8]) -> Result<(), ResponseFault> {
+    let (operators, signatures, _reference_block) =
+        <(Vec<Address>, Vec<Bytes>, u32)>::abi_decode_params(response, true)
+            .map_err(|_| ResponseFault::Malformed)?;
+    if operators.is_empty() || operators.len() != signatures.len() {
+        return Err(ResponseFault::SignatureCount {
+            operators: operators.len(),
+            signatures: signatures.len(),
+        });
+    }
+    let digest = task_response_digest(task_name);
+    for (operator, signature) in operators.iter().zip(&signatures) {
+        let recovered = Signature::from_raw(signature)
+            .ok()
+            .and_then(|signature| signature.recover_address_from_prehash(&digest).ok());
+        if recovered != Some(*operator) {
+            return Err(ResponseFault::WrongSigner {
+                expected: *operator,
+                recovered,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Watches the responses of the other operators and challenges the faulty ones with
+/// slashOperator
+#[derive(Debug)]
+pub struct Challenger {
+    signer: OperatorSigner,
+    provider: SdkSigner,
+    nonces: NonceManager,
+    swap_manager_address: Address,
+    watcher: PendingTxWatcher,
+    retry: RetryConfig,
+    window_blocks: u64,
+    response_interval: u64,
+    poll_interval: Duration,
+    next_block: u64,
+    expected_operators: Vec<Address>,
+    open_tasks: BTreeMap<u32, Task>,
+    alerts: AlertSink,
+    services: Services,
+}
+
+impl Challenger {
+    /// Challenger reading through `rpc_url` and sending its challenges from `signer`
+    /// through `watcher`, with the RPC stack, budget, audit log, notifications and address
+    /// names of `services`
+    pub async fn new(
+        rpc_url: &str,
+        services: &Services,
+        signer: OperatorSigner,
+        swap_manager_address: Address,
+        watcher: PendingTxWatcher,
+        config: &ChallengerConfig,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, OperatorError> {
+        let signer = signer.services(services.clone());
+        let provider = signer.provider(rpc_url)?;
+        let swap_manager = SwapManager::new(swap_manager_address, &provider);
+        let response_interval = time_rpc(
+            "MAX_RESPONSE_INTERVAL_BLOCKS",
+            swap_manager
+                .MAX_RESPONSE_INTERVAL_BLOCKS()
+                .call()
+                .into_future(),
+        )
+        .await?
+        ._0;
+        let next_block = match config.start_block {
+            Some(start_block) => start_block,
+            None => time_rpc("eth_blockNumber", provider.get_block_number()).await?,
+        };
+        Ok(Self {
+            nonces: NonceManager::new(signer.address()),
+            provider,
+            signer,
+            swap_manager_address,
+            watcher: watcher.services(services.clone()),
+            retry: retry_config.clone(),
+            window_blocks: config.window_blocks,
+            response_interval: response_interval.into(),
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+            next_block,
+            expected_operators: config
+                .expected_operators
+                .iter()
+                .map(|operator| operator.address(services.names()))
+                .collect::<Result<_, _>>()?,
+            open_tasks: BTreeMap::new(),
+            alerts: AlertSink::new(config.alert_url.clone()),
+            services: services.clone(),
+        })
+    }
+
+    /// Poll for responses every poll interval until `shutdown` is triggered
+    pub async fn run(mut self, shutdown: Shutdown) {
+        info!(
+            swap_manager = %self.services.named(self.swap_manager_address),
+            from_block = self.next_block,
+            window_blocks = self.window_blocks,
+            expected_operators = self.expected_operators.len(),
+            "Watching task responses"
+        );
+        while !shutdown.is_triggered() {
+            if let Err(e) = self.poll().await {
+                warn!(error = %e, "Challenger poll failed");
+            }
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    /// Check the responses logged since the last poll, then the tasks whose response
+    /// interval ended without a response of an expected operator
+    pub async fn poll(&mut self) -> Result<(), OperatorError> {
+        let provider = &self.provider;
+        let head = retry(&self.retry, "eth_blockNumber", || {
+            time_rpc("eth_blockNumber", provider.get_block_number())
+        })
+        .await?;
+        while self.next_block <= head {
+            let to_block = head.min(self.next_block + MAX_POLL_BLOCK_RANGE - 1);
+            self.check_range(self.next_block, to_block, head).await?;
+            self.next_block = to_block + 1;
+        }
+        self.check_missed(head).await
+    }
+
+    async fn check_range(
+        &mut self,
+        from_block: u64,
+        to_block: u64,
+        head: u64,
+    ) -> Result<(), OperatorError> {
+        if !self.expected_operators.is_empty() {
+            let tasks = fetch_tasks(
+                &self.provider,
+                self.swap_manager_address,
+                from_block,
+                to_block,
+                &self.retry,
+            )
+            .await?;
+            self.open_tasks.extend(
+                tasks
+                    .into_iter()
+                    .map(|new_task| (new_task.index, new_task.task)),
+            );
+        }
+        let filter = Filter::new()
+            .address(self.swap_manager_address)
+            .event_signature(SwapManager::TaskResponded::SIGNATURE_HASH)
+            .from_block(BlockNumberOrTag::Number(from_block))
+            .to_block(BlockNumberOrTag::Number(to_block));
+        let provider = &self.provider;
+        let logs = retry(&self.retry, "eth_getLogs", || {
+            time_rpc("eth_getLogs", provider.get_logs(&filter))
+        })
+        .await?;
+        for log in logs {
+            let block_number = log.block_number.unwrap_or(head);
+            let event = match log.log_decode::<SwapManager::TaskResponded>() {
+                Ok(decoded) => decoded.inner.data,
+                Err(e) => {
+                    warn!(error = %e, "Failed to decode TaskResponded log");
+                    continue;
+                }
+            };
+            if event.operator == self.signer.address() {
+                continue;
+            }
+            let response = self
+                .stored_response(event.operator, event.taskIndex)
+                .await?;
+            record_response_checked();
+            match check_response(&event.task.name, &response) {
+                Ok(()) => debug!(
+                    task_index = event.taskIndex,
+                    operator = %self.services.named(event.operator),
+                    "Response checked"
+                ),
+                Err(fault) => {
+                    self.challenge(
+                        &event.task,
+                        event.taskIndex,
+                        event.operator,
+                        fault,
+                        block_number,
+                        head,
+                    )
+                    .await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_missed(&mut self, head: u64) -> Result<(), OperatorError> {
+        let response_interval = self.response_interval;
+        let expired: Vec<u32> = self
+            .open_tasks
+            .iter()
+            .filter(|(_, task)| u64::from(task.taskCreatedBlock) + response_interval < head)
+            .map(|(task_index, _)| *task_index)
+            .collect();
+        for task_index in expired {
+            let Some(task) = self.open_tasks.remove(&task_index) else {
+                continue;
+            };
+            let deadline = u64::from(task.taskCreatedBlock) + response_interval;
+            for operator in self.expected_operators.clone() {
+                if self.stored_response(operator, task_index).await?.is_empty() {
+                    self.challenge(
+                        &task,
+                        task_index,
+                        operator,
+                        ResponseFault::Missed,
+                        deadline,
+                        head,
+                    )
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn stored_response(
+        &self,
+        operator: Address,
+        task_index: u32,
+    ) -> Result<Bytes, OperatorError> {
+        let swap_manager = SwapManager::new(self.swap_manager_address, &self.provider);
+        let call = swap_manager.allTaskResponses(operator, task_index);
+        let response = retry(&self.retry, "allTaskResponses", || {
+            time_rpc("allTaskResponses", call.call().into_future())
+        })
+        .await?;
+        Ok(response._0)
+    }
+
+    /// Alert on `fault` and send slashOperator for it, unless the challenge window that
+    /// opened at `visible_block` closed by `head` or the SwapManager refuses the challenge
+    async fn challenge(
+        &self,
+        task: &Task,
+        task_index: u32,
+        operator: Address,
+        fault: ResponseFault,
+        visible_block: u64,
+        head: u64,
+    ) -> Result<(), OperatorError> {
+        record_invalid_response(fault.label());
+        error!(task_index, operator = %self.services.named(operator), %fault, "Faulty task response");
+        self.alerts.send(task_index, operator, &fault).await;
+        self.services.notify(WebhookEvent::ChallengeRaised {
+            task_index,
+            operator,
+            fault: fault.label(),
+        });
+        if head > visible_block + self.window_blocks {
+            warn!(
+                task_index,
+                %operator,
+                window_blocks = self.window_blocks,
+                "Challenge window closed, not challenging"
+            );
+            record_challenge("expired");
+            return Ok(());
+        }
+
+        let swap_manager = SwapManager::new(self.swap_manager_address, &self.provider);
+        let tx = swap_manager
+            .slashOperator(task.clone(), task_index, operator)
+            .from(self.signer.address())
+            .into_transaction_request();
+        // The SwapManager only slashes some faults, the others are left to the alert
+        let simulated = if self.watcher.is_dry_run() {
+            self.watcher
+                .simulate(&self.provider, SLASH_OPERATOR, tx.clone())
+                .await
+                .map(|_| ())
+        } else {
+            time_rpc("eth_call", self.provider.call(tx.clone()).into_future())
+                .await
+                .map(|_| ())
+                .map_err(ChainError::from)
+        };
+        if let Err(e) = simulated {
+            warn!(task_index, operator = %self.services.named(operator), error = %e, "SwapManager refuses the challenge");
+            record_challenge("refused");
+            return Ok(());
+        }
+        if self.watcher.is_dry_run() {
+            record_challenge("simulated");
+            return Ok(());
+        }
+
+        let _pending = PendingTxGuard::new();
+        let nonce = self.nonces.next_nonce(&self.provider).await?;
+        let receipt = match self
+            .watcher
+            .send(&self.provider, SLASH_OPERATOR, tx.nonce(nonce))
+            .await
+        {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                record_tx_failure(SLASH_OPERATOR);
+                self.services.notify_tx_failure(SLASH_OPERATOR, &e);
+                self.nonces.resync(&self.provider).await?;
+                return Err(e.into());
+            }
+        };
+        if !receipt.status() {
+            record_tx_failure(SLASH_OPERATOR);
+            record_challenge("reverted");
+            let error = ChainError::Reverted {
+                method: SLASH_OPERATOR,
+                tx_hash: receipt.transaction_hash,
+            };
+            self.services.notify_tx_failure(SLASH_OPERATOR, &error);
+            return Err(error.into());
+        }
+        record_gas_used(SLASH_OPERATOR, receipt.gas_used);
+        record_challenge("sent");
+        info!(
+            task_index,
+            operator = %self.services.named(operator),
+            tx_hash = %receipt.transaction_hash,
+            "Challenge sent"
+        );
+        Ok(())
+    }
+}
+
+/// Posts the faults found to a webhook
+#[derive(Debug)]
+struct AlertSink {
+    client: reqwest::Client,
+    url: Option<String>,
+}
+
+impl AlertSink {
+    fn new(url: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    /// Post `fault`, failures are logged as the fault itself already is
+    async fn send(&self, task_index: u32, operator: Address, fault: &ResponseFault) {
+        let Some(url) = &self.url else {
+            return;
+        };
+        let body = json!({
+            "text": format!("Task {task_index}: faulty response of {operator}: {fault}"),
+            "taskIndex": task_index,
+            "operator": operator,
+            "fault": fault.label(),
+        });
+        let sent = self
+            .client
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = sent {
+            warn!(error = %e, "Failed to send the challenger alert");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_responder::{encode_signature_data, task_response_hash};
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    #[test]
+    fn test_responses_are_checked_against_the_recomputed_signature() {
+        let signer = PrivateKeySigner::random();
+        let signature = signer
+            .sign_message_sync(task_response_hash("QuickFox12").as_slice())
+            .unwrap();
+        let response =
+            encode_signature_data(&[signer.address()], &[signature.as_bytes().into()], 42);
+
+        check_response("QuickFox12", &response).unwrap();
+        assert!(matches!(
+            check_response("SlowFox12", &response),
+            Err(ResponseFault::WrongSigner { expected, .. }) if expected == signer.address()
+        ));
+        assert_eq!(
+            check_response("QuickFox12", &response[..40]),
+            Err(ResponseFault::Malformed)
+        );
+        assert_eq!(
+            check_response(
+                "QuickFox12",
+                &encode_signature_data(&[signer.address()], &[], 42)
+            ),
+            Err(ResponseFault::SignatureCount {
+                operators: 1,
+                signatures: 0
+            })
+        );
+    }
+}
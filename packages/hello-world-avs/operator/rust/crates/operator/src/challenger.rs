@@ -1,218 +1,141 @@
 #![allow(missing_docs)]
-use std::{collections::HashMap, env, str::FromStr, sync::LazyLock};
-
-use alloy::{
-    eips::BlockNumberOrTag,
-    primitives::Address,
-    providers::Provider,
-    rpc::types::{Filter, Log},
-    signers::local::PrivateKeySigner,
-    sol_types::SolEvent,
-};
+use clap::Parser;
 use dotenv::dotenv;
-use eigensdk::{
-    common::{get_provider, get_signer, get_ws_provider},
-    logging::{get_logger, init_logger, log_level::LogLevel},
-};
-use eyre::{Ok, Result};
-use futures::StreamExt;
-use swap_manager_utils::{
-    get_swap_manager_service_manager,
-    SwapManager::{
-        SwapManager::{self},
-        ISwapManager::Task,
-    },
-};
-use tokio::signal::{self};
-
-static RPC_URL: LazyLock<String> =
-    LazyLock::new(|| env::var("RPC_URL").expect("failed to retrieve RPC URL"));
-
-static WS_URL: LazyLock<String> =
-    LazyLock::new(|| env::var("WS_URL").expect("failed to retrieve WS URL"));
-
-static KEY: LazyLock<String> =
-    LazyLock::new(|| env::var("PRIVATE_KEY").expect("failed to retrieve private key"));
-
-/// Challenger struct
-#[derive(Debug)]
-pub struct Challenger {
-    service_manager_address: Address,
-    rpc_url: String,
-    ws_url: String,
-    tasks: HashMap<u32, Task>,
-    max_response_interval_blocks: u32,
-    operator_address: Address,
+use eyre::{Result, WrapErr};
+use std::path::PathBuf;
+use swap_manager_avs_operator::challenge::Challenger;
+use swap_manager_avs_operator::config::{OperatorConfig, CONFIG_PATH_ENV};
+use swap_manager_avs_operator::log_file::LogFileArgs;
+use swap_manager_avs_operator::logging::{init_tracing, LogFormat};
+use swap_manager_avs_operator::preflight::{preflight, PreflightChecks};
+use swap_manager_avs_operator::prometheus::init_metrics;
+use swap_manager_avs_operator::services::Services;
+use swap_manager_avs_operator::shutdown::Shutdown;
+
+/// Command line arguments of the challenger
+#[derive(Parser, Debug)]
+#[command(
+    name = "challenger",
+    about = "Check the responses of other operators and challenge the faulty ones"
+)]
+pub struct ChallengerArgs {
+    /// Path of the operator config file
+    #[arg(long, env = CONFIG_PATH_ENV)]
+    pub config: Option<PathBuf>,
+
+    /// First block whose responses are checked, overrides `challenger.start_block` of the
+    /// config file
+    #[arg(long)]
+    pub start_block: Option<u64>,
+
+    /// Deployment of the connected chain, overrides `deployment_file` of the config file
+    #[arg(long)]
+    pub deployment_file: Option<PathBuf>,
+
+    /// Log output format, overrides `log_format` of the config file
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// OTLP/gRPC collector the spans are exported to, overrides `otlp_endpoint` of the config file
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    #[command(flatten)]
+    pub log_file: LogFileArgs,
+
+    /// Simulate slashOperator with eth_call instead of sending it
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
-/// Challenger implementation
-impl Challenger {
-    /// Create a new challenger
-    pub async fn new(rpc_url: String, ws_url: String, private_key: String) -> Result<Self> {
-        let signer = PrivateKeySigner::from_str(&private_key)?;
-        let operator_address = signer.address();
-
-        let service_manager_address = get_swap_manager_service_manager().unwrap();
-
-        let pr = get_provider(&rpc_url);
-        let service_manager_contract = SwapManager::new(service_manager_address, &pr);
-        let max_response_interval_blocks = service_manager_contract
-            .MAX_RESPONSE_INTERVAL_BLOCKS()
-            .call()
-            .await?
-            ._0;
-
-        Ok(Self {
-            service_manager_address,
-            rpc_url,
-            ws_url,
-            tasks: HashMap::new(),
-            max_response_interval_blocks,
-            operator_address,
-        })
-    }
-
-    pub async fn start_challenger(&mut self) -> Result<()> {
-        get_logger().info("Challenger started: monitoring tasks", "");
-
-        let ws_provider = get_ws_provider(&self.ws_url).await?;
-
-        // Subscribe to NewTaskCreated events
-        let new_task_filter = Filter::new()
-            .address(self.service_manager_address)
-            .event_signature(SwapManager::NewTaskCreated::SIGNATURE_HASH)
-            .from_block(BlockNumberOrTag::Latest);
-        let mut new_task_stream = ws_provider
-            .subscribe_logs(&new_task_filter)
-            .await?
-            .into_stream();
-
-        // Subscribe to TaskResponded events
-        let responded_filter = Filter::new()
-            .address(self.service_manager_address)
-            .event_signature(SwapManager::TaskResponded::SIGNATURE_HASH)
-            .from_block(BlockNumberOrTag::Latest);
-        let mut responded_stream = ws_provider
-            .subscribe_logs(&responded_filter)
-            .await?
-            .into_stream();
-
-        // Subscribe to new block events
-        let mut block_stream = ws_provider.subscribe_blocks().await?.into_stream();
-
-        loop {
-            tokio::select! {
-                Some(log) = new_task_stream.next() => {
-                    let decode = log.log_decode::<SwapManager::NewTaskCreated>().ok();
-                    if let Some(decoded) = decode {
-                        self.handle_task_creation(decoded);
-                    }
-                },
-                Some(log) = responded_stream.next() => {
-                    let decode = log.log_decode::<SwapManager::TaskResponded>().ok();
-                    if let Some(decoded) = decode {
-                        self.handle_task_response(decoded);
-                    }
-                },
-                Some(block) = block_stream.next() => {
-                    self.check_tasks_timeout(block.number).await?;
-                },
-                _ = signal::ctrl_c() => {
-                    get_logger().info("Received Ctrl+C, shutting down...", "");
-                    break;
-                }
-            }
+impl ChallengerArgs {
+    /// Load the config file and apply the command line overrides on top of it
+    pub fn into_config(self) -> Result<OperatorConfig> {
+        let mut config = OperatorConfig::read(self.config.as_deref())?;
+        if self.start_block.is_some() {
+            config.challenger.start_block = self.start_block;
         }
-
-        Ok(())
-    }
-
-    /// Handle a new task creation and handle the time that an operator has to respond to the task
-    fn handle_task_creation(&mut self, decoded: Log<SwapManager::NewTaskCreated>) {
-        let event = decoded.data().clone();
-        let task_index = event.taskIndex;
-        get_logger().info(
-            &format!(
-                "New task received: {} at block {}",
-                task_index, event.task.taskCreatedBlock
-            ),
-            "",
-        );
-
-        // Save the task and create a cancellation channel
-        self.tasks.insert(task_index, event.task.clone());
-    }
-
-    /// Handle a task response and cancel the timeout timer
-    fn handle_task_response(&mut self, decoded: Log<SwapManager::TaskResponded>) {
-        let event = decoded.data();
-        let task_index = event.taskIndex;
-
-        get_logger().info(&format!("Task {} responded", task_index), "");
-        self.tasks.remove(&task_index);
-    }
-
-    /// Handle the expiration of a task and slash the operator if so.
-    /// This function is called when a new block is received, iterates over all tasks and checks if they have expired
-    /// If so, it slashes the operator and removes the task from the list.
-    async fn check_tasks_timeout(&mut self, current_block: u64) -> Result<()> {
-        for (task_index, task) in self.tasks.clone() {
-            let task_created_block = task.taskCreatedBlock;
-            let expiration_block = (task_created_block + self.max_response_interval_blocks) as u64;
-
-            if current_block > expiration_block {
-                get_logger().info(
-                    &format!(
-                        "Task {} expired at {}, operator didn't respond",
-                        task_index, expiration_block
-                    ),
-                    "",
-                );
-                self.slash_operator(task.clone(), task_index).await?;
-                self.tasks.remove(&task_index);
-            }
+        if self.deployment_file.is_some() {
+            config.deployment_file = self.deployment_file;
         }
-
-        Ok(())
+        if let Some(log_format) = self.log_format {
+            config.log_format = log_format;
+        }
+        if self.otlp_endpoint.is_some() {
+            config.otlp_endpoint = self.otlp_endpoint;
+        }
+        self.log_file.apply(&mut config.log_file);
+        if self.dry_run {
+            config.dry_run = true;
+        }
+        config.validate()?;
+        Ok(config)
     }
+}
 
-    /// Execute the slashing of an operator
-    async fn slash_operator(&self, task: Task, task_index: u32) -> Result<()> {
-        let pr = get_signer(&KEY.to_string(), &self.rpc_url);
-        let swap_manager_contract = SwapManager::new(self.service_manager_address, &pr);
-
-        get_logger().info(
-            &format!(
-                "Slashing operator {} in task {}",
-                self.operator_address, task_index
-            ),
-            "",
-        );
-
-        let tx_result = swap_manager_contract
-            .slashOperator(task, task_index, self.operator_address)
-            .send()
-            .await?;
-
-        get_logger().info(
-            &format!("Slashing transaction sent: {}", tx_result.tx_hash()),
-            "",
-        );
-
-        Ok(())
-    }
+async fn run(config: &OperatorConfig, services: &Services) -> Result<()> {
+    let shutdown = Shutdown::on_signal();
+    tokio::spawn(services.rpc().clone().run_health_checks(shutdown.clone()));
+    let signer = config.signer_backend()?.load().await?;
+    let contracts = config.contract_addresses(services).await?;
+    let checks = PreflightChecks {
+        funded: (!config.dry_run).then(|| signer.address()),
+        ..PreflightChecks::default()
+    };
+    preflight(config, services, &contracts, checks)
+        .await
+        .wrap_err("Preflight checks failed")?;
+    let challenger = Challenger::new(
+        &config.rpc_url,
+        services,
+        signer,
+        contracts.swap_manager,
+        config.pending_tx_watcher()?,
+        &config.challenger,
+        &config.retry,
+    )
+    .await?;
+    challenger.run(shutdown).await;
+    services.tx_costs().log_summary();
+    Ok(())
 }
 
 #[tokio::main]
-pub async fn main() -> Result<()> {
+pub async fn main() {
     dotenv().ok();
-    init_logger(LogLevel::Info);
-
-    let mut challenger = Challenger::new(RPC_URL.to_string(), WS_URL.to_string(), KEY.to_string())
-        .await
-        .unwrap();
-
-    challenger.start_challenger().await?;
-
-    Ok(())
+    let mut config = match ChallengerArgs::parse().into_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    let _tracing = init_tracing(
+        config.log_level().unwrap_or(tracing::Level::INFO),
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+        &config.log_file,
+    );
+    if let Err(e) = config.resolve_secrets().await {
+        eprintln!("Failed to fetch the secrets: {:?}", e);
+        std::process::exit(1);
+    }
+    let services = match Services::from_config(&config) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("Failed to set up the challenger services: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = config.resolve_names(&services).await {
+        eprintln!("Failed to resolve the ENS names: {:?}", e);
+        std::process::exit(1);
+    }
+    if let Some(metrics_addr) = config.metrics_addr {
+        init_metrics(metrics_addr);
+    }
+    if let Err(e) = run(&config, &services).await {
+        eprintln!("Challenger failed: {:?}", e);
+        std::process::exit(1);
+    }
 }
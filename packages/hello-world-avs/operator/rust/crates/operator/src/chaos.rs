@@ -0,0 +1,347 @@
+use std::collections::VecDeque;
+
+use alloy::{
+    hex,
+    primitives::{Address, U256},
+};
+use rand::{distr::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::ConfigError;
+use crate::swap_payload::{SwapTaskPayload, SWAP_PAYLOAD_PREFIX};
+use crate::task_names::{draw_weighted, TaskNames};
+
+/// Names kept to be sent again as duplicates
+const RECENT_NAMES: usize = 64;
+
+/// Adversarial tasks of the `--chaos` mode, the `[spammer.chaos]` config section.
+///
+/// Weights are relative like the ones of `[spammer.mix]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChaosConfig {
+    /// Whether the spammer mixes adversarial tasks into the generated ones (`--chaos`)
+    pub enabled: bool,
+    /// Weight of the well formed tasks of `spammer.payload`
+    pub valid: f64,
+    /// Weight of the swap payloads that don't decode
+    pub malformed: f64,
+    /// Weight of the names of recent tasks sent again
+    pub duplicate: f64,
+    /// Weight of the empty names and names around `boundary_length`
+    pub boundary: f64,
+    /// Weight of the names submitted `rapid_repeats` times back to back
+    pub rapid: f64,
+    /// Length in bytes the boundary names are built around, the `max_name_length` under test
+    pub boundary_length: usize,
+    /// Submissions of each rapid duplicate
+    pub rapid_repeats: usize,
+    /// Seed making the adversarial tasks reproducible across runs
+    pub seed: Option<u64>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            valid: 1.0,
+            malformed: 1.0,
+            duplicate: 1.0,
+            boundary: 1.0,
+            rapid: 1.0,
+            boundary_length: 1_024,
+            rapid_repeats: 3,
+            seed: None,
+        }
+    }
+}
+
+impl ChaosConfig {
+    /// Check that the weights are non-negative and not all zero
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let weights = [
+            self.valid,
+            self.malformed,
+            self.duplicate,
+            self.boundary,
+            self.rapid,
+        ];
+        if weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(ConfigError::Invalid(
+                "spammer.chaos weights must be finite and at least 0".to_string(),
+            ));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(ConfigError::Invalid(
+                "spammer.chaos needs a weight greater than 0".to_string(),
+            ));
+        }
+        if self.boundary_length == 0 {
+            return Err(ConfigError::Invalid(
+                "spammer.chaos.boundary_length must be greater than 0".to_string(),
+            ));
+        }
+        if self.rapid_repeats < 2 {
+            return Err(ConfigError::Invalid(
+                "spammer.chaos.rapid_repeats must be at least 2".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Kind of task produced in chaos mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChaosCase {
+    /// Ordinary task
+    Valid,
+    /// Swap payload that doesn't decode
+    Malformed,
+    /// Name of a recent task
+    Duplicate,
+    /// Empty name or name around the length limit
+    Boundary,
+    /// Name submitted several times back to back
+    Rapid,
+}
+
+impl ChaosCase {
+    /// Name used in the logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Malformed => "malformed",
+            Self::Duplicate => "duplicate",
+            Self::Boundary => "boundary",
+            Self::Rapid => "rapid",
+        }
+    }
+}
+
+/// Mixes adversarial tasks into the names of another generator, to check that operators and
+/// challengers reject them rather than crash
+#[derive(Debug)]
+pub struct ChaosNames {
+    inner: TaskNames,
+    weights: [(ChaosCase, f64); 5],
+    boundary_length: usize,
+    rapid_repeats: usize,
+    recent: VecDeque<String>,
+    /// Rapid duplicate still to be sent and how many more times
+    repeat: Option<(String, usize)>,
+    rng: StdRng,
+}
+
+impl ChaosNames {
+    /// Wrap `inner` with the adversarial tasks of `config`
+    pub fn new(inner: TaskNames, config: &ChaosConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Ok(Self {
+            inner,
+            weights: [
+                (ChaosCase::Valid, config.valid),
+                (ChaosCase::Malformed, config.malformed),
+                (ChaosCase::Duplicate, config.duplicate),
+                (ChaosCase::Boundary, config.boundary),
+                (ChaosCase::Rapid, config.rapid),
+            ],
+            boundary_length: config.boundary_length,
+            rapid_repeats: config.rapid_repeats,
+            recent: VecDeque::with_capacity(RECENT_NAMES),
+            repeat: None,
+            rng,
+        })
+    }
+
+    /// Whether the next name repeats a rapid duplicate, to be sent without waiting
+    pub fn repeat_pending(&self) -> bool {
+        self.repeat.is_some()
+    }
+
+    /// Produce the next task name and the case it tests
+    pub fn next_task(&mut self) -> (ChaosCase, String) {
+        if let Some((name, left)) = self.repeat.take() {
+            if left > 1 {
+                self.repeat = Some((name.clone(), left - 1));
+            }
+            return (ChaosCase::Rapid, name);
+        }
+        let case = draw_weighted(&mut self.rng, &self.weights);
+        let name = match case {
+            ChaosCase::Valid => self.fresh_name(),
+            ChaosCase::Malformed => self.malformed_payload(),
+            ChaosCase::Duplicate => match self.recent.len() {
+                0 => self.fresh_name(),
+                len => self.recent[self.rng.random_range(0..len)].clone(),
+            },
+            ChaosCase::Boundary => self.boundary_name(),
+            ChaosCase::Rapid => {
+                let name = self.fresh_name();
+                self.repeat = Some((name.clone(), self.rapid_repeats - 1));
+                name
+            }
+        };
+        debug!(case = case.as_str(), length = name.len(), "Chaos task");
+        (case, name)
+    }
+
+    /// Produce the next task name
+    pub fn next_name(&mut self) -> String {
+        self.next_task().1
+    }
+
+    /// Name of the wrapped generator, remembered for the duplicates
+    fn fresh_name(&mut self) -> String {
+        let name = self.inner.next_name();
+        if self.recent.len() == RECENT_NAMES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(name.clone());
+        name
+    }
+
+    fn malformed_payload(&mut self) -> String {
+        let encoded = SwapTaskPayload {
+            token_in: Address::from(self.rng.random::<[u8; 20]>()),
+            token_out: Address::from(self.rng.random::<[u8; 20]>()),
+            amount_in: U256::from(self.rng.random::<u64>()),
+            min_amount_out: U256::from(self.rng.random::<u64>()),
+            slippage_bps: self.rng.random_range(0..10_000),
+            deadline: self.rng.random(),
+        }
+        .encode();
+        match self.rng.random_range(0..5) {
+            // Cut within the ABI words
+            0 => encoded[..encoded.len() / 2].to_string(),
+            // Odd number of hex digits
+            1 => format!("{encoded}0"),
+            // Not hex at all
+            2 => format!("{SWAP_PAYLOAD_PREFIX}{}", self.random_string(64)),
+            // Hex of the wrong length for a swap intent
+            3 => format!(
+                "{SWAP_PAYLOAD_PREFIX}{}",
+                hex::encode(self.rng.random::<[u8; 32]>())
+            ),
+            // The prefix alone
+            _ => SWAP_PAYLOAD_PREFIX.to_string(),
+        }
+    }
+
+    fn boundary_name(&mut self) -> String {
+        let limit = self.boundary_length;
+        match self.rng.random_range(0..5) {
+            0 => String::new(),
+            1 => self.random_string(limit),
+            2 => self.random_string(limit + 1),
+            3 => self.random_string(limit.saturating_sub(1).max(1)),
+            // Within the limit in characters but past it in bytes
+            _ => "é".repeat(limit / 2 + 1),
+        }
+    }
+
+    fn random_string(&mut self, length: usize) -> String {
+        (&mut self.rng)
+            .sample_iter(Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_payload::SwapPayloadConfig;
+    use crate::task_names::{PayloadKind, TaskMixConfig, TaskNameConfig};
+
+    fn chaos_names(config: &ChaosConfig) -> ChaosNames {
+        let names = TaskNameConfig {
+            template: "{adj}{noun}{uuid}".to_string(),
+            seed: Some(1),
+            ..Default::default()
+        };
+        let inner = TaskNames::new(
+            PayloadKind::Names,
+            &names,
+            &SwapPayloadConfig::default(),
+            &TaskMixConfig::default(),
+        )
+        .unwrap();
+        ChaosNames::new(inner, config).unwrap()
+    }
+
+    #[test]
+    fn test_chaos_mode_produces_every_adversarial_case() {
+        let config = ChaosConfig {
+            enabled: true,
+            boundary_length: 100,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut chaos = chaos_names(&config);
+        let mut previous = String::new();
+        let mut seen = Vec::new();
+        for _ in 0..500 {
+            let rapid_repeat = chaos.repeat_pending();
+            let (case, name) = chaos.next_task();
+            match case {
+                ChaosCase::Malformed => {
+                    assert!(name.starts_with(SWAP_PAYLOAD_PREFIX));
+                    assert!(SwapTaskPayload::decode(&name).is_err(), "{name}");
+                }
+                ChaosCase::Boundary => {
+                    assert!([0, 99, 100, 101, 102].contains(&name.len()), "{name}");
+                }
+                ChaosCase::Rapid if rapid_repeat => assert_eq!(name, previous),
+                ChaosCase::Duplicate => assert!(chaos.recent.contains(&name)),
+                _ => {}
+            }
+            if !seen.contains(&case) {
+                seen.push(case);
+            }
+            previous = name;
+        }
+        assert_eq!(seen.len(), 5, "{seen:?}");
+
+        let replayed: Vec<_> = {
+            let mut chaos = chaos_names(&config);
+            (0..50).map(|_| chaos.next_name()).collect()
+        };
+        let mut chaos = chaos_names(&config);
+        assert!(replayed.iter().all(|name| *name == chaos.next_name()));
+    }
+
+    #[test]
+    fn test_rapid_duplicates_repeat_back_to_back() {
+        let mut chaos = chaos_names(&ChaosConfig {
+            valid: 0.0,
+            malformed: 0.0,
+            duplicate: 0.0,
+            boundary: 0.0,
+            rapid_repeats: 4,
+            seed: Some(3),
+            ..Default::default()
+        });
+        let first = chaos.next_name();
+        for _ in 0..3 {
+            assert!(chaos.repeat_pending());
+            assert_eq!(chaos.next_name(), first);
+        }
+        assert!(!chaos.repeat_pending());
+        assert_ne!(chaos.next_name(), first);
+        assert!(ChaosConfig {
+            rapid_repeats: 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket, ResponsePayload},
+    transports::{TransportError, TransportFut, TransportResult},
+};
+use serde::Deserialize;
+use tower::Service;
+use tracing::{info, warn};
+
+use crate::error::ConfigError;
+use crate::prometheus::{record_rpc_circuit_transition, set_rpc_circuit_state};
+
+/// How often a call waiting for the half-open probe checks whether it's done
+const PROBE_POLL: Duration = Duration::from_millis(200);
+
+/// Pausing of the RPC calls while the endpoint fails, the `[circuit_breaker]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Whether failing RPC calls open the breaker
+    pub enabled: bool,
+    /// Seconds of calls the failure rate is computed over
+    pub window_secs: u64,
+    /// Percentage of failed calls in the window that opens the breaker
+    pub failure_rate_percent: f64,
+    /// Calls the window must hold before the failure rate is acted on
+    pub min_calls: u32,
+    /// Seconds the calls are paused once the breaker opens, before a probe call is let
+    /// through
+    pub cooldown_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 60,
+            failure_rate_percent: 50.0,
+            min_calls: 20,
+            cooldown_secs: 30,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Check the settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(self.failure_rate_percent > 0.0 && self.failure_rate_percent <= 100.0) {
+            return Err(ConfigError::Invalid(format!(
+                "circuit_breaker.failure_rate_percent must be in (0, 100], got {}",
+                self.failure_rate_percent
+            )));
+        }
+        if self.window_secs == 0 || self.cooldown_secs == 0 || self.min_calls == 0 {
+            return Err(ConfigError::Invalid(
+                "circuit_breaker.window_secs, cooldown_secs and min_calls must be at least 1"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Breaker of the RPC calls, none unless `enabled`
+    pub fn breaker(&self) -> Option<CircuitBreaker> {
+        if !self.enabled {
+            return None;
+        }
+        set_rpc_circuit_state(CircuitState::Closed);
+        Some(CircuitBreaker::new(self))
+    }
+}
+
+/// Where a [`CircuitBreaker`] stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through and their outcomes are counted
+    Closed,
+    /// Calls wait for the cooldown to end
+    Open,
+    /// A single probe call is let through, its outcome closes or opens the breaker again
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Label of the state in metrics and logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Breaker opening once the failure rate of the calls over a window passes a threshold,
+/// then probing with one call after a cooldown
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    window: Duration,
+    failure_rate: f64,
+    min_calls: usize,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    circuit: CircuitState,
+    /// When the breaker opened, or the probe was let through when half open
+    opened_at: Instant,
+    /// End time and success of the calls of the window
+    calls: VecDeque<(Instant, bool)>,
+}
+
+impl CircuitBreaker {
+    /// Closed breaker with the settings of `config`
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            window: Duration::from_secs(config.window_secs),
+            failure_rate: config.failure_rate_percent / 100.0,
+            min_calls: config.min_calls as usize,
+            cooldown: Duration::from_secs(config.cooldown_secs),
+            state: Mutex::new(BreakerState {
+                circuit: CircuitState::Closed,
+                opened_at: Instant::now(),
+                calls: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Current state
+    pub fn state(&self) -> CircuitState {
+        self.lock().circuit
+    }
+
+    /// Let a call through, or return how long to wait before asking again
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Wait until a call may go through
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Count the outcome of a call let through
+    pub fn record(&self, success: bool) {
+        self.record_at(success, Instant::now());
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> Result<(), Duration> {
+        let mut state = self.lock();
+        match state.circuit {
+            CircuitState::Closed => Ok(()),
+            // A probe dropped before its outcome was recorded is replaced after a cooldown
+            CircuitState::HalfOpen if now < state.opened_at + self.cooldown => Err(PROBE_POLL),
+            CircuitState::HalfOpen => {
+                state.opened_at = now;
+                Ok(())
+            }
+            CircuitState::Open => {
+                let reopens = state.opened_at + self.cooldown;
+                if now < reopens {
+                    return Err(reopens - now);
+                }
+                // The caller is the probe
+                state.opened_at = now;
+                transition(&mut state, CircuitState::HalfOpen);
+                Ok(())
+            }
+        }
+    }
+
+    fn record_at(&self, success: bool, now: Instant) {
+        let mut state = self.lock();
+        match state.circuit {
+            CircuitState::HalfOpen if success => {
+                state.calls.clear();
+                transition(&mut state, CircuitState::Closed);
+            }
+            CircuitState::HalfOpen => {
+                state.opened_at = now;
+                transition(&mut state, CircuitState::Open);
+            }
+            CircuitState::Closed => {
+                state.calls.push_back((now, success));
+                while state
+                    .calls
+                    .front()
+                    .is_some_and(|&(at, _)| now.duration_since(at) > self.window)
+                {
+                    state.calls.pop_front();
+                }
+                let calls = state.calls.len();
+                let failures = state.calls.iter().filter(|(_, success)| !success).count();
+                if calls >= self.min_calls && failures as f64 >= self.failure_rate * calls as f64 {
+                    warn!(
+                        calls,
+                        failures,
+                        cooldown_secs = self.cooldown.as_secs(),
+                        "Too many RPC calls failing, pausing them"
+                    );
+                    state.opened_at = now;
+                    transition(&mut state, CircuitState::Open);
+                }
+            }
+            // Calls sent before the breaker opened
+            CircuitState::Open => {}
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().expect("circuit breaker lock poisoned")
+    }
+}
+
+fn transition(state: &mut BreakerState, to: CircuitState) {
+    if state.circuit == to {
+        return;
+    }
+    info!(
+        from = state.circuit.as_str(),
+        to = to.as_str(),
+        "RPC circuit breaker changed state"
+    );
+    state.circuit = to;
+    record_rpc_circuit_transition(to.as_str());
+    set_rpc_circuit_state(to);
+}
+
+/// Whether `result` tells of a failing endpoint. Reverts are answers of a healthy
+/// endpoint, so only transport errors and the other JSON-RPC errors count
+fn is_failure(result: &TransportResult<ResponsePacket>) -> bool {
+    match result {
+        Ok(ResponsePacket::Single(response)) => match &response.payload {
+            ResponsePayload::Success(_) => false,
+            ResponsePayload::Failure(error) => {
+                error.code != 3 && !error.message.to_lowercase().contains("revert")
+            }
+        },
+        Ok(ResponsePacket::Batch(_)) => false,
+        Err(_) => true,
+    }
+}
+
+/// Transport holding the calls of a provider back while `breaker` is open, the clients
+/// sharing a breaker opening and closing together
+#[derive(Debug, Clone)]
+pub struct BreakerTransport<S> {
+    inner: S,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl<S> BreakerTransport<S> {
+    /// Calls of `inner` paused by `breaker`, never paused without one
+    pub fn new(inner: S, breaker: Option<Arc<CircuitBreaker>>) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+impl<S> Service<RequestPacket> for BreakerTransport<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let Some(breaker) = self.breaker.clone() else {
+            return self.inner.call(request);
+        };
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            breaker.acquire().await;
+            let result = inner.call(request).await;
+            breaker.record(!is_failure(&result));
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_on_failures_and_closes_after_a_probe() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig {
+            enabled: true,
+            window_secs: 60,
+            failure_rate_percent: 50.0,
+            min_calls: 4,
+            cooldown_secs: 30,
+        });
+        let start = Instant::now();
+        for success in [true, false, true] {
+            breaker.record_at(success, start);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_at(false, start);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let wait = breaker.try_acquire_at(start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(30));
+
+        // A failed probe opens it for another cooldown
+        let probe = start + Duration::from_secs(30);
+        breaker.try_acquire_at(probe).unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert_eq!(breaker.try_acquire_at(probe), Err(PROBE_POLL));
+        breaker.record_at(false, probe);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let probe = probe + Duration::from_secs(30);
+        breaker.try_acquire_at(probe).unwrap();
+        breaker.record_at(true, probe);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.try_acquire_at(probe).unwrap();
+
+        // Failures older than the window are forgotten
+        for _ in 0..3 {
+            breaker.record_at(false, probe);
+        }
+        breaker.record_at(true, probe + Duration::from_secs(61));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,1665 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy::{primitives::Address, providers::Provider, signers::ledger::HDPath};
+use clap::ValueEnum;
+use reqwest::Url;
+use serde::Deserialize;
+use swap_manager_utils::{Deployment, DeploymentError};
+use tracing::{info, Level};
+
+use crate::aggregator::AggregatorConfig;
+use crate::alerting::{AlertsConfig, ChatChannel, ChatKind};
+use crate::audit::AuditConfig;
+use crate::balance::BalanceConfig;
+use crate::bls::BlsKeystore;
+use crate::budget::BudgetConfig;
+use crate::challenge::ChallengerConfig;
+use crate::chaos::ChaosConfig;
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::dead_letter::DeadLetterConfig;
+use crate::ens::AddressOrName;
+use crate::error::ConfigError;
+use crate::event_bus::{EventBusBackend, EventBusConfig};
+use crate::funding::{is_production_chain, TopUpKind};
+use crate::gas::{fee_strategy, FeeStrategyKind, GasConfig};
+use crate::ha::HaConfig;
+use crate::handshake::TaskType;
+use crate::health::HealthConfig;
+use crate::key_pool::KeyPoolConfig;
+use crate::key_rotation::RotationConfig;
+use crate::log_file::{LogFileConfig, LogRotation};
+use crate::logging::LogFormat;
+use crate::oracle::OracleConfig;
+use crate::pending_tx::{PendingTxWatcher, MIN_FEE_BUMP_PERCENT};
+use crate::prometheus::time_rpc;
+use crate::quoting::QuotingConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::remote_signer::RemoteSignerTls;
+use crate::reorg::ReorgConfig;
+use crate::retry::{retry, RetryConfig};
+use crate::rewards::RewardsConfig;
+use crate::rpc_capture::RpcCaptureConfig;
+use crate::rpc_pool::RpcConfig;
+use crate::secrets::{secret_reference, SecretsBackend, SecretsConfig};
+use crate::services::Services;
+use crate::signature_scheme::SignatureScheme;
+use crate::signer::{KeystorePassword, SignerBackend, SignerConfig, SignerKind};
+use crate::socket::OperatorSocket;
+use crate::stake_monitor::StakeMonitorConfig;
+use crate::swap_payload::SwapPayloadConfig;
+use crate::task_listener::TASK_CHANNEL_CAPACITY;
+use crate::task_names::{PayloadKind, TaskMixConfig, TaskNameConfig};
+use crate::task_policy::ValidationConfig;
+use crate::task_source::TaskSourceConfig;
+use crate::task_store::{StoreBackend, StoreConfig};
+use crate::traffic::{SpamWindow, TrafficConfig, TrafficModel};
+use crate::webhook::WebhookConfig;
+use crate::work_queue::{WorkQueueConfig, WorkQueueRole};
+
+/// Config file looked up in the working directory when no path is given
+pub const DEFAULT_CONFIG_PATH: &str = "operator.toml";
+
+/// Env var holding the path of the config file
+pub const CONFIG_PATH_ENV: &str = "OPERATOR_CONFIG";
+
+/// Typed configuration shared by the operator, the spammer and the challenger.
+///
+/// Values are read from `operator.toml` (or a `.yaml`/`.yml` file), then
+/// overridden by the env vars documented on each field.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct OperatorConfig {
+    /// HTTP RPC endpoint (`RPC_URL`)
+    pub rpc_url: String,
+    /// WebSocket RPC endpoint (`WS_URL`)
+    pub ws_url: String,
+    /// Hex encoded private key (`PRIVATE_KEY`)
+    pub private_key: Option<String>,
+    /// File holding the hex encoded private key (`PRIVATE_KEY_FILE`)
+    pub private_key_file: Option<PathBuf>,
+    /// One of `error`, `warn`, `info`, `debug`, `trace` (`LOG_LEVEL`)
+    pub log_level: String,
+    /// `pretty` or `json` (`LOG_FORMAT`)
+    pub log_format: LogFormat,
+    /// Log file written instead of stdout, with its rotation and retention
+    pub log_file: LogFileConfig,
+    /// Address serving Prometheus `/metrics`, disabled if unset (`METRICS_ADDR`)
+    pub metrics_addr: Option<SocketAddr>,
+    /// OTLP/gRPC collector spans are exported to, such as Jaeger or Tempo on port 4317, not
+    /// exported if unset (`OTLP_ENDPOINT`)
+    pub otlp_endpoint: Option<String>,
+    /// Address serving the gRPC control API, disabled if unset (`CONTROL_ADDR`)
+    pub control_addr: Option<SocketAddr>,
+    /// Seconds the work in flight may take to finish on SIGINT/SIGTERM (`SHUTDOWN_TIMEOUT_SECS`)
+    pub shutdown_timeout_secs: u64,
+    /// Simulate createNewTask and respondToTask with `eth_call` instead of sending them (`DRY_RUN`)
+    pub dry_run: bool,
+    /// Chain the RPC endpoint must serve, any chain if unset (`CHAIN_ID`)
+    pub chain_id: Option<u64>,
+    /// Check the chain, the contracts, the signer balance and the registration at startup
+    /// (`PREFLIGHT`)
+    pub preflight: bool,
+    /// Which backend holds the signing key
+    pub signer: SignerConfig,
+    /// Funded keys sending transactions in turn with the signer
+    pub key_pool: KeyPoolConfig,
+    /// Keys, passwords and RPC endpoints fetched from a secrets backend
+    pub secrets: SecretsConfig,
+    /// Deployment of the connected chain, instead of the bundled one (`DEPLOYMENT_FILE`)
+    pub deployment_file: Option<PathBuf>,
+    /// Contract addresses, falling back to the deployment data
+    pub contracts: ContractsConfig,
+    /// Task spammer settings
+    pub spammer: SpammerConfig,
+    /// Task responder settings
+    pub operator: ResponderConfig,
+    /// How new tasks are discovered
+    pub source: TaskSourceConfig,
+    /// Backoff applied to RPC calls and transactions
+    pub retry: RetryConfig,
+    /// Giving up on the tasks whose response can't be landed
+    pub dead_letter: DeadLetterConfig,
+    /// Response aggregator settings
+    pub aggregator: AggregatorConfig,
+    /// Persistence of seen tasks and the last processed block
+    pub store: StoreConfig,
+    /// Reorg detection
+    pub reorg: ReorgConfig,
+    /// Fees of sent transactions
+    pub gas: GasConfig,
+    /// Liveness and readiness probes
+    pub health: HealthConfig,
+    /// Outgoing RPC rate
+    pub rate_limit: RateLimitConfig,
+    /// Pausing of the RPC calls while too many of them fail
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Fallback RPC endpoints and quorum reads
+    pub rpc: RpcConfig,
+    /// Recording and replay of the JSON-RPC traffic
+    pub rpc_capture: RpcCaptureConfig,
+    /// Coordination of replicas of the operator
+    pub ha: HaConfig,
+    /// Redis stream between a task listener and its responder workers
+    pub work_queue: WorkQueueConfig,
+    /// Price checks of swap tasks
+    pub oracle: OracleConfig,
+    /// Uniswap V3 quotes of swap tasks
+    pub quoting: QuotingConfig,
+    /// Checks of the task names and swap payloads before responding
+    pub validation: ValidationConfig,
+    /// Checks of the other operators' responses
+    pub challenger: ChallengerConfig,
+    /// Watching the operator's own stake
+    pub stake_monitor: StakeMonitorConfig,
+    /// Tracking and claiming the operator's rewards
+    pub rewards: RewardsConfig,
+    /// Watching the signer balance
+    pub balance: BalanceConfig,
+    /// Caps on the fees spent by the transactions
+    pub budget: BudgetConfig,
+    /// Webhook notifications of task lifecycle events
+    pub webhooks: WebhookConfig,
+    /// Kafka or NATS publishing of task lifecycle events
+    pub event_bus: EventBusConfig,
+    /// Slack and Discord alerts
+    pub alerts: AlertsConfig,
+    /// Audit log of the signed payloads and sent transactions
+    pub audit: AuditConfig,
+    /// Signing key rotation of `operator rotate-key`
+    pub rotation: RotationConfig,
+}
+
+/// Optional contract addresses or ENS names, unset entries are read from the deployment files
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContractsConfig {
+    /// SwapManager service manager (`SWAP_MANAGER_ADDRESS`)
+    pub swap_manager: Option<AddressOrName>,
+    /// ECDSA stake registry of the AVS (`STAKE_REGISTRY_ADDRESS`)
+    pub stake_registry: Option<AddressOrName>,
+    /// EigenLayer delegation manager (`DELEGATION_MANAGER_ADDRESS`)
+    pub delegation_manager: Option<AddressOrName>,
+    /// EigenLayer AVS directory (`AVS_DIRECTORY_ADDRESS`)
+    pub avs_directory: Option<AddressOrName>,
+    /// EigenLayer rewards coordinator, only read by the rewards tracker and
+    /// `operator rewards` (`REWARDS_COORDINATOR_ADDRESS`)
+    pub rewards_coordinator: Option<AddressOrName>,
+}
+
+impl ContractsConfig {
+    fn all_set(&self) -> bool {
+        self.swap_manager.is_some()
+            && self.stake_registry.is_some()
+            && self.delegation_manager.is_some()
+            && self.avs_directory.is_some()
+    }
+}
+
+/// Contract addresses in effect, overrides taking precedence over the deployment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractAddresses {
+    /// SwapManager service manager
+    pub swap_manager: Address,
+    /// ECDSA stake registry of the AVS
+    pub stake_registry: Address,
+    /// EigenLayer delegation manager
+    pub delegation_manager: Address,
+    /// EigenLayer AVS directory
+    pub avs_directory: Address,
+}
+
+impl ContractAddresses {
+    /// Addresses of `deployment`, without any override
+    pub fn from_deployment(deployment: &Deployment) -> Result<Self, DeploymentError> {
+        Ok(Self {
+            swap_manager: deployment.swap_manager_address()?,
+            stake_registry: deployment.stake_registry_address()?,
+            delegation_manager: deployment.delegation_manager_address()?,
+            avs_directory: deployment.avs_directory_address()?,
+        })
+    }
+}
+
+/// Settings of the `spam_tasks` binary
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpammerConfig {
+    /// Seconds between two task submissions
+    pub interval_secs: u64,
+    /// Milliseconds between two task submissions, overrides `interval_secs`
+    pub interval_ms: Option<u64>,
+    /// UTC time ranges the tasks are created in, the spammer pauses outside of them and
+    /// never pauses if there are none
+    pub windows: Vec<SpamWindow>,
+    /// Stop after this many tasks, loop forever if unset
+    pub count: Option<u64>,
+    /// Most createNewTask transactions awaiting their receipt at once
+    pub concurrency: usize,
+    /// Tasks created per transaction, more than 1 packs them into a Multicall3 `aggregate3`
+    pub batch_size: usize,
+    /// Multicall3 contract the batches go through, the canonical deployment if unset
+    pub multicall_address: Option<Address>,
+    /// Whether tasks carry plain names, swap payloads or a mix of both
+    pub payload: PayloadKind,
+    /// Word lists, template and seed of the generated task names
+    pub names: TaskNameConfig,
+    /// Token universe and amounts of the generated swap payloads
+    pub swaps: SwapPayloadConfig,
+    /// Weights of the task kinds of the `mixed` payload
+    pub mix: TaskMixConfig,
+    /// Adversarial tasks mixed into the generated ones
+    pub chaos: ChaosConfig,
+    /// Load curve the submissions follow
+    pub traffic: TrafficConfig,
+    /// Seeds the names, swaps, mix, chaos and traffic generators not setting their own seed, so two runs
+    /// with the same seed and `count` create the same tasks at the same pace
+    pub seed: Option<u64>,
+    /// Address serving `POST /tasks` for ad-hoc tasks, disabled if unset (`TASK_API_ADDR`)
+    pub api_addr: Option<SocketAddr>,
+    /// Ad-hoc tasks waiting to be created, further requests are refused until one is
+    pub api_queue_capacity: usize,
+}
+
+impl SpammerConfig {
+    /// Time between two task submissions of the fixed traffic model
+    pub fn interval(&self) -> Duration {
+        match self.interval_ms {
+            Some(interval_ms) => Duration::from_millis(interval_ms),
+            None => Duration::from_secs(self.interval_secs),
+        }
+    }
+
+    /// Give the generators without a seed one derived from `seed`, distinct for each of them
+    pub fn apply_seed(&mut self) {
+        let Some(seed) = self.seed else {
+            return;
+        };
+        self.names.seed.get_or_insert(seed);
+        self.swaps.seed.get_or_insert(seed.wrapping_add(1));
+        self.traffic.seed.get_or_insert(seed.wrapping_add(2));
+        self.mix.seed.get_or_insert(seed.wrapping_add(3));
+        self.chaos.seed.get_or_insert(seed.wrapping_add(4));
+    }
+}
+
+/// Settings of the task responder
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResponderConfig {
+    /// Chance in percent that the operator responds to a task (`OPERATOR_RESPONSE_PERCENTAGE`)
+    pub response_percentage: f64,
+    /// Metadata URI set on the EigenLayer operator when registering (`OPERATOR_METADATA_URI`)
+    pub metadata_uri: String,
+    /// Aggregator collecting the signed responses, submit them directly if unset (`AGGREGATOR_URL`)
+    pub aggregator_url: Option<String>,
+    /// Tasks buffered between the task source and the responder (`TASK_QUEUE_CAPACITY`)
+    pub queue_capacity: usize,
+    /// Park the tasks overflowing the queue in the task store instead of holding the task
+    /// source back, needs `store.path` or `store.url` (`TASK_QUEUE_SPILL`)
+    pub spill_to_store: bool,
+    /// `host:port` announced to the aggregator, the health endpoints are served on its
+    /// port unless `health.listen_addr` is set (`OPERATOR_SOCKET`)
+    pub socket: Option<String>,
+    /// Scheme the responses are signed with, the `signatureScheme` of the deployment or else
+    /// ECDSA if unset (`SIGNATURE_SCHEME`)
+    pub signature_scheme: Option<SignatureScheme>,
+    /// Task types the operator attests to and announces in its hello to the aggregator, the
+    /// other tasks are skipped (`OPERATOR_TASK_TYPES`, comma separated)
+    pub task_types: Vec<TaskType>,
+}
+
+impl Default for OperatorConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: "http://localhost:8545".to_string(),
+            ws_url: "ws://localhost:8545".to_string(),
+            private_key: None,
+            private_key_file: None,
+            log_level: "info".to_string(),
+            log_format: LogFormat::default(),
+            log_file: LogFileConfig::default(),
+            metrics_addr: None,
+            otlp_endpoint: None,
+            control_addr: None,
+            shutdown_timeout_secs: 30,
+            dry_run: false,
+            chain_id: None,
+            preflight: true,
+            signer: SignerConfig::default(),
+            key_pool: KeyPoolConfig::default(),
+            secrets: SecretsConfig::default(),
+            deployment_file: None,
+            contracts: ContractsConfig::default(),
+            spammer: SpammerConfig::default(),
+            operator: ResponderConfig::default(),
+            source: TaskSourceConfig::default(),
+            retry: RetryConfig::default(),
+            dead_letter: DeadLetterConfig::default(),
+            aggregator: AggregatorConfig::default(),
+            store: StoreConfig::default(),
+            reorg: ReorgConfig::default(),
+            gas: GasConfig::default(),
+            health: HealthConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            rpc: RpcConfig::default(),
+            rpc_capture: RpcCaptureConfig::default(),
+            ha: HaConfig::default(),
+            work_queue: WorkQueueConfig::default(),
+            oracle: OracleConfig::default(),
+            quoting: QuotingConfig::default(),
+            validation: ValidationConfig::default(),
+            challenger: ChallengerConfig::default(),
+            stake_monitor: StakeMonitorConfig::default(),
+            rewards: RewardsConfig::default(),
+            balance: BalanceConfig::default(),
+            budget: BudgetConfig::default(),
+            webhooks: WebhookConfig::default(),
+            event_bus: EventBusConfig::default(),
+            alerts: AlertsConfig::default(),
+            audit: AuditConfig::default(),
+            rotation: RotationConfig::default(),
+        }
+    }
+}
+
+impl Default for SpammerConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 6,
+            interval_ms: None,
+            windows: Vec::new(),
+            count: None,
+            concurrency: 1,
+            batch_size: 1,
+            multicall_address: None,
+            payload: PayloadKind::default(),
+            names: TaskNameConfig::default(),
+            swaps: SwapPayloadConfig::default(),
+            mix: TaskMixConfig::default(),
+            chaos: ChaosConfig::default(),
+            traffic: TrafficConfig::default(),
+            seed: None,
+            api_addr: None,
+            api_queue_capacity: 64,
+        }
+    }
+}
+
+impl Default for ResponderConfig {
+    fn default() -> Self {
+        Self {
+            response_percentage: 100.0,
+            metadata_uri: String::new(),
+            aggregator_url: None,
+            queue_capacity: TASK_CHANNEL_CAPACITY,
+            spill_to_store: false,
+            socket: None,
+            signature_scheme: None,
+            task_types: TaskType::ALL.to_vec(),
+        }
+    }
+}
+
+impl OperatorConfig {
+    /// [`OperatorConfig::read`] the config and validate it
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let config = Self::read(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read the config from `path`, `$OPERATOR_CONFIG` or `./operator.toml` (in that
+    /// order, a missing default file is not an error) and apply env overrides.
+    pub fn read(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let explicit = path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os(CONFIG_PATH_ENV).map(PathBuf::from));
+        let mut config = match explicit {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+        config.apply_env_overrides(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Parse a config file, picking the format from its extension
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let data = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            what: "config",
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&data).map_err(Into::into),
+            _ => toml::from_str(&data).map_err(Into::into),
+        };
+        parsed.map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Override fields with the values returned by `lookup` for their env var names
+    pub fn apply_env_overrides(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        if let Some(rpc_url) = lookup("RPC_URL") {
+            self.rpc_url = rpc_url;
+        }
+        if let Some(urls) = lookup("RPC_FALLBACK_URLS") {
+            self.rpc.fallback_urls = urls
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(network) = lookup("RPC_NETWORK") {
+            self.rpc.network = Some(network);
+        }
+        if let Some(relay_url) = lookup("PRIVATE_RELAY_URL") {
+            self.rpc.private_relay_url = Some(relay_url);
+        }
+        if let Some(replica_id) = lookup("HA_REPLICA_ID") {
+            self.ha.replica_id = Some(replica_id);
+        }
+        if let Some(role) = lookup("WORK_QUEUE_ROLE") {
+            self.work_queue.role = WorkQueueRole::from_str(&role, true).map_err(|e| {
+                ConfigError::Invalid(format!("invalid WORK_QUEUE_ROLE {role}: {e}"))
+            })?;
+        }
+        if let Some(url) = lookup("WORK_QUEUE_URL") {
+            self.work_queue.url = Some(url);
+        }
+        if let Some(consumer) = lookup("WORK_QUEUE_CONSUMER") {
+            self.work_queue.consumer = Some(consumer);
+        }
+        if let Some(ws_url) = lookup("WS_URL") {
+            self.ws_url = ws_url;
+        }
+        if let Some(private_key) = lookup("PRIVATE_KEY") {
+            self.private_key = Some(private_key);
+        }
+        if let Some(private_key_file) = lookup("PRIVATE_KEY_FILE") {
+            self.private_key_file = Some(private_key_file.into());
+        }
+        if let Some(log_level) = lookup("LOG_LEVEL") {
+            self.log_level = log_level;
+        }
+        if let Some(log_format) = lookup("LOG_FORMAT") {
+            self.log_format = LogFormat::from_str(&log_format, true).map_err(|e| {
+                ConfigError::Invalid(format!("invalid LOG_FORMAT {log_format}: {e}"))
+            })?;
+        }
+        if let Some(path) = lookup("LOG_FILE") {
+            self.log_file.path = Some(path.into());
+        }
+        if let Some(rotate) = lookup("LOG_ROTATE") {
+            self.log_file.rotate = LogRotation::from_str(&rotate, true)
+                .map_err(|e| ConfigError::Invalid(format!("invalid LOG_ROTATE {rotate}: {e}")))?;
+        }
+        if let Some(size) = lookup("LOG_MAX_SIZE_MB") {
+            self.log_file.max_size_mb = Some(size.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid LOG_MAX_SIZE_MB {size}: {e}"))
+            })?);
+        }
+        if let Some(keep) = lookup("LOG_KEEP") {
+            self.log_file.keep = keep
+                .parse()
+                .map_err(|e| ConfigError::Invalid(format!("invalid LOG_KEEP {keep}: {e}")))?;
+        }
+        if let Some(backend) = lookup("SIGNER_BACKEND") {
+            self.signer.backend = SignerKind::from_str(&backend, true).map_err(|e| {
+                ConfigError::Invalid(format!("invalid SIGNER_BACKEND {backend}: {e}"))
+            })?;
+        }
+        if let Some(keystore) = lookup("KEYSTORE_PATH") {
+            self.signer.keystore = Some(keystore.into());
+        }
+        if let Some(password_file) = lookup("KEYSTORE_PASSWORD_FILE") {
+            self.signer.password_file = Some(password_file.into());
+        }
+        if let Some(keystore) = lookup("BLS_KEYSTORE_PATH") {
+            self.signer.bls_keystore = Some(keystore.into());
+        }
+        if let Some(password_file) = lookup("BLS_KEYSTORE_PASSWORD_FILE") {
+            self.signer.bls_password_file = Some(password_file.into());
+        }
+        if let Some(key_id) = lookup("AWS_KMS_KEY_ID") {
+            self.signer.kms_key_id = Some(key_id);
+        }
+        if let Some(region) = lookup("AWS_REGION") {
+            self.signer.kms_region = Some(region);
+        }
+        if let Some(endpoint) = lookup("AWS_KMS_ENDPOINT") {
+            self.signer.kms_endpoint = Some(endpoint);
+        }
+        if let Some(url) = lookup("WEB3SIGNER_URL") {
+            self.signer.web3signer_url = Some(url);
+        }
+        if let Some(address) = lookup("WEB3SIGNER_ADDRESS") {
+            self.signer.web3signer_address = Some(address.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid WEB3SIGNER_ADDRESS {address}: {e}"))
+            })?);
+        }
+        if let Some(address) = lookup("OPERATOR_ADDRESS") {
+            self.signer.operator_address = Some(address.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid OPERATOR_ADDRESS {address}: {e}"))
+            })?);
+        }
+        if let Some(distribution) = lookup("REWARDS_DISTRIBUTION") {
+            self.rewards.distribution = Some(distribution);
+        }
+        if let Some(files) = lookup("KEY_POOL_FILES") {
+            self.key_pool.private_key_files = files
+                .split(',')
+                .map(str::trim)
+                .filter(|file| !file.is_empty())
+                .map(PathBuf::from)
+                .collect();
+        }
+        if let Some(backend) = lookup("SECRETS_BACKEND") {
+            self.secrets.backend = SecretsBackend::from_str(&backend, true).map_err(|e| {
+                ConfigError::Invalid(format!("invalid SECRETS_BACKEND {backend}: {e}"))
+            })?;
+        }
+        if let Some(addr) = lookup("VAULT_ADDR") {
+            self.secrets.vault.addr = Some(addr);
+        }
+        if let Some(namespace) = lookup("VAULT_NAMESPACE") {
+            self.secrets.vault.namespace = Some(namespace);
+        }
+        if let Some(token) = lookup("VAULT_TOKEN") {
+            self.secrets.vault.token = Some(token);
+        }
+        if let Some(role) = lookup("VAULT_ROLE") {
+            self.secrets.vault.role = Some(role);
+        }
+        if let Some(file) = lookup("SECRETS_FILE") {
+            self.secrets.file = file.into();
+        }
+        if let Some(password_file) = lookup("SECRETS_PASSWORD_FILE") {
+            self.secrets.password_file = Some(password_file.into());
+        }
+        if let Some(path) = lookup("LEDGER_HD_PATH") {
+            self.signer.ledger_hd_path = Some(path);
+        }
+        if let Some(account) = lookup("LEDGER_ACCOUNT") {
+            self.signer.ledger_account = account.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid LEDGER_ACCOUNT {account}: {e}"))
+            })?;
+        }
+        if let Some(service) = lookup("KEYCHAIN_SERVICE") {
+            self.signer.keychain_service = Some(service);
+        }
+        if let Some(account) = lookup("KEYCHAIN_ACCOUNT") {
+            self.signer.keychain_account = Some(account);
+        }
+        if let Some(url) = lookup("AGGREGATOR_URL") {
+            self.operator.aggregator_url = Some(url);
+        }
+        if let Some(listen_addr) = lookup("AGGREGATOR_LISTEN_ADDR") {
+            self.aggregator.listen_addr = listen_addr.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid AGGREGATOR_LISTEN_ADDR {listen_addr}: {e}"))
+            })?;
+        }
+        if let Some(percent) = lookup("AGGREGATOR_QUORUM_THRESHOLD_PERCENT") {
+            self.aggregator.quorum_threshold_percent = Some(percent.parse().map_err(|e| {
+                ConfigError::Invalid(format!(
+                    "invalid AGGREGATOR_QUORUM_THRESHOLD_PERCENT {percent}: {e}"
+                ))
+            })?);
+        }
+        if let Some(version) = lookup("AGGREGATOR_MIN_OPERATOR_VERSION") {
+            self.aggregator.min_operator_version = Some(version);
+        }
+        if let Some(require) = lookup("AGGREGATOR_REQUIRE_HELLO") {
+            self.aggregator.require_hello = require.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid AGGREGATOR_REQUIRE_HELLO {require}: {e}"))
+            })?;
+        }
+        if let Some(strategy) = lookup("GAS_STRATEGY") {
+            self.gas.strategy = match strategy.as_str() {
+                "eip1559" => FeeStrategyKind::Eip1559,
+                "legacy" => FeeStrategyKind::Legacy,
+                "fixed" => FeeStrategyKind::Fixed,
+                other => {
+                    return Err(ConfigError::Invalid(format!(
+                        "unknown GAS_STRATEGY {other}"
+                    )))
+                }
+            };
+        }
+        if let Some(max_fee) = lookup("MAX_FEE_PER_GAS") {
+            self.gas.max_fee_per_gas = Some(max_fee.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid MAX_FEE_PER_GAS {max_fee}: {e}"))
+            })?);
+        }
+        if let Some(multiplier) = lookup("GAS_LIMIT_MULTIPLIER") {
+            self.gas.gas_limit_multiplier = multiplier.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid GAS_LIMIT_MULTIPLIER {multiplier}: {e}"))
+            })?;
+        }
+        if let Some(backend) = lookup("STORE_BACKEND") {
+            self.store.backend = match backend.as_str() {
+                "sqlite" => StoreBackend::Sqlite,
+                "postgres" => StoreBackend::Postgres,
+                other => {
+                    return Err(ConfigError::Invalid(format!(
+                        "unknown STORE_BACKEND {other}"
+                    )))
+                }
+            };
+        }
+        if let Some(path) = lookup("STORE_PATH") {
+            self.store.path = Some(path.into());
+        }
+        if let Some(url) = lookup("STORE_URL") {
+            self.store.url = Some(url);
+        }
+        if let Some(api_addr) = lookup("TASK_API_ADDR") {
+            self.spammer.api_addr = Some(api_addr.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid TASK_API_ADDR {api_addr}: {e}"))
+            })?);
+        }
+        if let Some(control_addr) = lookup("CONTROL_ADDR") {
+            self.control_addr = Some(control_addr.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid CONTROL_ADDR {control_addr}: {e}"))
+            })?);
+        }
+        for (var, address) in [
+            ("SWAP_MANAGER_ADDRESS", &mut self.contracts.swap_manager),
+            ("STAKE_REGISTRY_ADDRESS", &mut self.contracts.stake_registry),
+            (
+                "DELEGATION_MANAGER_ADDRESS",
+                &mut self.contracts.delegation_manager,
+            ),
+            ("AVS_DIRECTORY_ADDRESS", &mut self.contracts.avs_directory),
+            (
+                "REWARDS_COORDINATOR_ADDRESS",
+                &mut self.contracts.rewards_coordinator,
+            ),
+        ] {
+            if let Some(value) = lookup(var) {
+                *address =
+                    Some(value.parse().map_err(|e| {
+                        ConfigError::Invalid(format!("invalid {var} {value}: {e}"))
+                    })?);
+            }
+        }
+        if let Some(deployment_file) = lookup("DEPLOYMENT_FILE") {
+            self.deployment_file = Some(deployment_file.into());
+        }
+        if let Some(health_addr) = lookup("HEALTH_ADDR") {
+            self.health.listen_addr = Some(health_addr.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid HEALTH_ADDR {health_addr}: {e}"))
+            })?);
+        }
+        if let Some(endpoint) = lookup("OTLP_ENDPOINT") {
+            self.otlp_endpoint = Some(endpoint);
+        }
+        if let Some(metrics_addr) = lookup("METRICS_ADDR") {
+            self.metrics_addr = Some(metrics_addr.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid METRICS_ADDR {metrics_addr}: {e}"))
+            })?);
+        }
+        if let Some(timeout) = lookup("SHUTDOWN_TIMEOUT_SECS") {
+            self.shutdown_timeout_secs = timeout.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid SHUTDOWN_TIMEOUT_SECS {timeout}: {e}"))
+            })?;
+        }
+        if let Some(dry_run) = lookup("DRY_RUN") {
+            self.dry_run = dry_run
+                .parse()
+                .map_err(|e| ConfigError::Invalid(format!("invalid DRY_RUN {dry_run}: {e}")))?;
+        }
+        if let Some(chain_id) = lookup("CHAIN_ID") {
+            self.chain_id =
+                Some(chain_id.parse().map_err(|e| {
+                    ConfigError::Invalid(format!("invalid CHAIN_ID {chain_id}: {e}"))
+                })?);
+        }
+        if let Some(preflight) = lookup("PREFLIGHT") {
+            self.preflight = preflight
+                .parse()
+                .map_err(|e| ConfigError::Invalid(format!("invalid PREFLIGHT {preflight}: {e}")))?;
+        }
+        if let Some(percentage) = lookup("OPERATOR_RESPONSE_PERCENTAGE") {
+            self.operator.response_percentage = percentage.parse().map_err(|e| {
+                ConfigError::Invalid(format!(
+                    "invalid OPERATOR_RESPONSE_PERCENTAGE {percentage}: {e}"
+                ))
+            })?;
+        }
+        if let Some(metadata_uri) = lookup("OPERATOR_METADATA_URI") {
+            self.operator.metadata_uri = metadata_uri;
+        }
+        if let Some(socket) = lookup("OPERATOR_SOCKET") {
+            self.operator.socket = Some(socket);
+        }
+        if let Some(scheme) = lookup("SIGNATURE_SCHEME") {
+            self.operator.signature_scheme =
+                Some(SignatureScheme::from_str(&scheme, true).map_err(|e| {
+                    ConfigError::Invalid(format!("invalid SIGNATURE_SCHEME {scheme}: {e}"))
+                })?);
+        }
+        if let Some(task_types) = lookup("OPERATOR_TASK_TYPES") {
+            self.operator.task_types = task_types
+                .split(',')
+                .map(|task_type| task_type.trim().parse())
+                .collect::<Result<_, ConfigError>>()
+                .map_err(|e| {
+                    ConfigError::Invalid(format!("invalid OPERATOR_TASK_TYPES {task_types}: {e}"))
+                })?;
+        }
+        if let Some(capacity) = lookup("TASK_QUEUE_CAPACITY") {
+            self.operator.queue_capacity = capacity.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid TASK_QUEUE_CAPACITY {capacity}: {e}"))
+            })?;
+        }
+        if let Some(spill) = lookup("TASK_QUEUE_SPILL") {
+            self.operator.spill_to_store = spill.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid TASK_QUEUE_SPILL {spill}: {e}"))
+            })?;
+        }
+        if let Some(api_key) = lookup("COINGECKO_API_KEY") {
+            self.oracle.coingecko_api_key = Some(api_key);
+        }
+        if let Some(enabled) = lookup("VALIDATION_ENABLED") {
+            self.validation.enabled = enabled.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid VALIDATION_ENABLED {enabled}: {e}"))
+            })?;
+        }
+        if let Some(policy_file) = lookup("VALIDATION_POLICY_FILE") {
+            self.validation.policy_file = Some(PathBuf::from(policy_file));
+        }
+        if let Some(alert_url) = lookup("CHALLENGER_ALERT_URL") {
+            self.challenger.alert_url = Some(alert_url);
+        }
+        if let Some(top_up_url) = lookup("TOP_UP_URL") {
+            self.balance.top_up_url = Some(top_up_url);
+        }
+        if let Some(urls) = lookup("WEBHOOK_URLS") {
+            self.webhooks.urls = urls
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(secret) = lookup("WEBHOOK_SECRET") {
+            self.webhooks.secret = Some(secret);
+        }
+        if let Some(backend) = lookup("EVENT_BUS_BACKEND") {
+            self.event_bus.backend = EventBusBackend::from_str(&backend, true).map_err(|e| {
+                ConfigError::Invalid(format!("invalid EVENT_BUS_BACKEND {backend}: {e}"))
+            })?;
+        }
+        if let Some(url) = lookup("EVENT_BUS_URL") {
+            self.event_bus.url = Some(url);
+        }
+        for (kind, var) in [
+            (ChatKind::Slack, "SLACK_WEBHOOK_URL"),
+            (ChatKind::Discord, "DISCORD_WEBHOOK_URL"),
+        ] {
+            if let Some(webhook_url) = lookup(var) {
+                self.alerts.channels.push(ChatChannel {
+                    kind,
+                    webhook_url,
+                    channel: None,
+                    min_severity: None,
+                });
+            }
+        }
+        if let Some(enabled) = lookup("AUDIT_ENABLED") {
+            self.audit.enabled = enabled.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid AUDIT_ENABLED {enabled}: {e}"))
+            })?;
+        }
+        if let Some(path) = lookup("AUDIT_LOG_PATH") {
+            self.audit.path = PathBuf::from(path);
+        }
+        if let Some(mode) = lookup("RPC_CAPTURE_MODE") {
+            self.rpc_capture.mode = mode.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid RPC_CAPTURE_MODE {mode}: {e}"))
+            })?;
+        }
+        if let Some(path) = lookup("RPC_CAPTURE_PATH") {
+            self.rpc_capture.path = PathBuf::from(path);
+        }
+        if let Some(rate) = lookup("RPC_RATE_LIMIT") {
+            self.rate_limit.requests_per_sec = Some(rate.parse().map_err(|e| {
+                ConfigError::Invalid(format!("invalid RPC_RATE_LIMIT {rate}: {e}"))
+            })?);
+        }
+        Ok(())
+    }
+
+    /// Check that every field holds a usable value
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_url(&self.rpc_url, &["http", "https"], "rpc_url")?;
+        self.rpc.validate(&self.rpc_url)?;
+        for url in &self.rpc.endpoints(&self.rpc_url)?[1..] {
+            check_url(url, &["http", "https"], "rpc.fallback_urls")?;
+        }
+        check_url(&self.ws_url, &["ws", "wss"], "ws_url")?;
+        if let Some(endpoint) = &self.otlp_endpoint {
+            check_url(endpoint, &["http", "https"], "otlp_endpoint")?;
+        }
+        if let Some(url) = &self.operator.aggregator_url {
+            check_url(url, &["http", "https"], "operator.aggregator_url")?;
+        }
+        if self.signer.operator_address.is_some() && self.operator.aggregator_url.is_some() {
+            return Err(ConfigError::Invalid(
+                "signer.operator_address can't be used with operator.aggregator_url, the \
+                 aggregator expects responses signed by the operator address"
+                    .to_string(),
+            ));
+        }
+        if self.operator.signature_scheme == Some(SignatureScheme::Bls) {
+            if self.signer.bls_keystore.is_none() {
+                return Err(ConfigError::Invalid(
+                    "signer.bls_keystore must be set to sign the responses with BLS".to_string(),
+                ));
+            }
+            if self.operator.aggregator_url.is_some() {
+                return Err(ConfigError::Invalid(
+                    "operator.aggregator_url only takes ECDSA signed responses".to_string(),
+                ));
+            }
+        }
+        self.log_level()?;
+        self.secrets.validate()?;
+        if self.secrets.backend == SecretsBackend::None {
+            if let Some(value) = self
+                .secret_values()
+                .find(|value| secret_reference(value).is_some())
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "{value} needs a secrets.backend to fetch it from"
+                )));
+            }
+        }
+        match self.signer.backend {
+            SignerKind::PrivateKey => {
+                if self.private_key.is_none()
+                    && self.private_key_file.is_none()
+                    && self.secrets.private_key.is_none()
+                {
+                    return Err(ConfigError::Invalid(
+                        "either private_key or private_key_file must be set".to_string(),
+                    ));
+                }
+            }
+            SignerKind::Keystore => {
+                if self.signer.keystore.is_none() {
+                    return Err(ConfigError::Invalid(
+                        "signer.keystore must be set for the keystore backend".to_string(),
+                    ));
+                }
+            }
+            SignerKind::AwsKms => {
+                if self.signer.kms_key_id.is_none() {
+                    return Err(ConfigError::Invalid(
+                        "signer.kms_key_id must be set for the aws_kms backend".to_string(),
+                    ));
+                }
+            }
+            SignerKind::Web3signer => {
+                let url = self.signer.web3signer_url.as_deref().ok_or_else(|| {
+                    ConfigError::Invalid(
+                        "signer.web3signer_url must be set for the web3signer backend".to_string(),
+                    )
+                })?;
+                check_url(url, &["http", "https"], "signer.web3signer_url")?;
+                if self.signer.web3signer_address.is_none() {
+                    return Err(ConfigError::Invalid(
+                        "signer.web3signer_address must be set for the web3signer backend"
+                            .to_string(),
+                    ));
+                }
+            }
+            SignerKind::Ledger => {
+                if self
+                    .signer
+                    .ledger_hd_path
+                    .as_deref()
+                    .is_some_and(|path| !path.starts_with("m/"))
+                {
+                    return Err(ConfigError::Invalid(
+                        "signer.ledger_hd_path must start with m/".to_string(),
+                    ));
+                }
+            }
+            SignerKind::OsKeychain => {
+                let entry = self.signer.keychain_entry();
+                if entry.service.is_empty() || entry.account.is_empty() {
+                    return Err(ConfigError::Invalid(
+                        "signer.keychain_service and signer.keychain_account can't be empty"
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+        if self.store.backend == StoreBackend::Postgres {
+            let url = self.store.url.as_deref().ok_or_else(|| {
+                ConfigError::Invalid("store.url must be set for the postgres backend".to_string())
+            })?;
+            check_url(url, &["postgres", "postgresql"], "store.url")?;
+            if self.store.max_connections == 0 {
+                return Err(ConfigError::Invalid(
+                    "store.max_connections must be at least 1".to_string(),
+                ));
+            }
+        }
+        if self.spammer.interval().is_zero() {
+            return Err(ConfigError::Invalid(
+                "spammer.interval_secs and interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        if self.spammer.concurrency == 0 {
+            return Err(ConfigError::Invalid(
+                "spammer.concurrency must be at least 1".to_string(),
+            ));
+        }
+        if self.spammer.batch_size == 0 {
+            return Err(ConfigError::Invalid(
+                "spammer.batch_size must be at least 1".to_string(),
+            ));
+        }
+        if self.spammer.api_queue_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "spammer.api_queue_capacity must be at least 1".to_string(),
+            ));
+        }
+        match self.spammer.payload {
+            PayloadKind::Names => {}
+            PayloadKind::Swaps => self.spammer.swaps.validate()?,
+            PayloadKind::Mixed => {
+                self.spammer.mix.validate()?;
+                if self.spammer.mix.swaps > 0.0 {
+                    self.spammer.swaps.validate()?;
+                }
+            }
+        }
+        if self.spammer.chaos.enabled {
+            self.spammer.chaos.validate()?;
+        }
+        let traffic = &self.spammer.traffic;
+        match traffic.model {
+            TrafficModel::Fixed => {}
+            TrafficModel::Poisson | TrafficModel::Burst if traffic.rate_per_min <= 0.0 => {
+                return Err(ConfigError::Invalid(
+                    "spammer.traffic.rate_per_min must be greater than 0".to_string(),
+                ));
+            }
+            TrafficModel::Poisson => {}
+            TrafficModel::Burst => {
+                if traffic.burst_on_secs == 0 {
+                    return Err(ConfigError::Invalid(
+                        "spammer.traffic.burst_on_secs must be greater than 0".to_string(),
+                    ));
+                }
+            }
+            TrafficModel::Ramp => {
+                if traffic.ramp_from_per_min <= 0.0 || traffic.ramp_to_per_min <= 0.0 {
+                    return Err(ConfigError::Invalid(
+                        "spammer.traffic ramp rates must be greater than 0".to_string(),
+                    ));
+                }
+                if traffic.ramp_secs == 0 {
+                    return Err(ConfigError::Invalid(
+                        "spammer.traffic.ramp_secs must be greater than 0".to_string(),
+                    ));
+                }
+            }
+        }
+        if self.source.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "source.poll_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.source.catch_up_concurrency == 0 {
+            return Err(ConfigError::Invalid(
+                "source.catch_up_concurrency must be greater than 0".to_string(),
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.gas.priority_fee_percentile) {
+            return Err(ConfigError::Invalid(format!(
+                "gas.priority_fee_percentile must be between 0 and 100, got {}",
+                self.gas.priority_fee_percentile
+            )));
+        }
+        if self.gas.stuck_timeout_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "gas.stuck_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.gas.fee_bump_percent < MIN_FEE_BUMP_PERCENT {
+            return Err(ConfigError::Invalid(format!(
+                "gas.fee_bump_percent must be at least {MIN_FEE_BUMP_PERCENT}, got {}",
+                self.gas.fee_bump_percent
+            )));
+        }
+        if !(self.gas.gas_limit_multiplier >= 1.0 && self.gas.gas_limit_multiplier.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "gas.gas_limit_multiplier must be at least 1, got {}",
+                self.gas.gas_limit_multiplier
+            )));
+        }
+        if self.gas.fee_history_blocks == 0 {
+            return Err(ConfigError::Invalid(
+                "gas.fee_history_blocks must be at least 1".to_string(),
+            ));
+        }
+        if self.gas.strategy == FeeStrategyKind::Fixed
+            && (self.gas.max_fee_per_gas.is_none() || self.gas.priority_fee_per_gas.is_none())
+        {
+            return Err(ConfigError::Invalid("gas.max_fee_per_gas and gas.priority_fee_per_gas must be set for the fixed strategy".to_string()));
+        }
+        if self.reorg.depth == 0 {
+            return Err(ConfigError::Invalid(
+                "reorg.depth must be at least 1".to_string(),
+            ));
+        }
+        if self.reorg.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "reorg.poll_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        // A reorg deeper than the window would undo confirmed responses unnoticed
+        if self.reorg.confirmations >= self.reorg.depth {
+            return Err(ConfigError::Invalid(
+                "reorg.confirmations must be lower than reorg.depth".to_string(),
+            ));
+        }
+        if self.retry.max_attempts == 0 {
+            return Err(ConfigError::Invalid(
+                "retry.max_attempts must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.operator.response_percentage) {
+            return Err(ConfigError::Invalid(format!(
+                "operator.response_percentage must be between 0 and 100, got {}",
+                self.operator.response_percentage
+            )));
+        }
+        if let Some(socket) = self.operator_socket()? {
+            if let Some(health_addr) = self
+                .health
+                .listen_addr
+                .filter(|addr| addr.port() != socket.port)
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "health.listen_addr {health_addr} must use the port of operator.socket {socket}"
+                )));
+            }
+        }
+        self.oracle.validate()?;
+        self.quoting.validate()?;
+        self.validation.validate()?;
+        self.challenger.validate()?;
+        self.stake_monitor.validate()?;
+        self.rewards.validate()?;
+        self.balance.validate()?;
+        self.budget.validate()?;
+        if let Some(chain_id) = self.chain_id.filter(|&id| is_production_chain(id)) {
+            if self.balance.enabled && self.balance.top_up != TopUpKind::None {
+                return Err(ConfigError::Invalid(format!(
+                    "balance.top_up must be none on production chain {chain_id}"
+                )));
+            }
+        }
+        self.key_pool.validate()?;
+        self.log_file.validate()?;
+        self.dead_letter.validate()?;
+        if let Some(url) = &self.balance.top_up_url {
+            check_url(url, &["http", "https"], "balance.top_up_url")?;
+        }
+        if let Some(url) = &self.challenger.alert_url {
+            check_url(url, &["http", "https"], "challenger.alert_url")?;
+        }
+        self.ha.validate(&self.store)?;
+        self.work_queue.validate()?;
+        self.webhooks.validate()?;
+        for url in &self.webhooks.urls {
+            check_url(url, &["http", "https"], "webhooks.urls")?;
+        }
+        self.event_bus.validate()?;
+        self.alerts.validate()?;
+        for channel in &self.alerts.channels {
+            check_url(
+                &channel.webhook_url,
+                &["http", "https"],
+                "alerts.channels.webhook_url",
+            )?;
+        }
+        if self.operator.queue_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "operator.queue_capacity must be at least 1".to_string(),
+            ));
+        }
+        if self.operator.task_types.is_empty() {
+            return Err(ConfigError::Invalid(
+                "operator.task_types needs at least one task type".to_string(),
+            ));
+        }
+        if let Some(rate) = self.rate_limit.requests_per_sec {
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(ConfigError::Invalid(format!(
+                    "rate_limit.requests_per_sec must be greater than 0, got {rate}"
+                )));
+            }
+        }
+        if self.rate_limit.burst == 0 {
+            return Err(ConfigError::Invalid(
+                "rate_limit.burst must be at least 1".to_string(),
+            ));
+        }
+        self.circuit_breaker.validate()?;
+        self.aggregator.validate()?;
+        Ok(())
+    }
+
+    /// Fetch the secrets referenced in `[secrets]` and by `secret://` values, replacing
+    /// the values they stand for, then validate the result. Called once at startup,
+    /// before anything reads them
+    pub async fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        let Some(provider) = self.secrets.provider().await? else {
+            return Ok(());
+        };
+        let backend = self.secrets.backend;
+        let secrets = &self.secrets;
+        if let Some(reference) = &secrets.private_key {
+            self.private_key = Some(provider.secret(reference).await?);
+            self.private_key_file = None;
+        }
+        if let Some(reference) = &secrets.keystore_password {
+            self.signer.password = Some(provider.secret(reference).await?);
+        }
+        if let Some(reference) = &secrets.bls_password {
+            self.signer.bls_password = Some(provider.secret(reference).await?);
+        }
+        if let Some(reference) = &secrets.rpc_url {
+            self.rpc_url = provider.secret(reference).await?;
+        }
+        if let Some(reference) = &secrets.ws_url {
+            self.ws_url = provider.secret(reference).await?;
+        }
+        for value in self.secret_values_mut() {
+            if let Some(reference) = secret_reference(value) {
+                *value = provider.secret(reference).await?;
+            }
+        }
+        self.validate()?;
+        info!(?backend, "Fetched the secrets");
+        Ok(())
+    }
+
+    /// Resolve the ENS names given in place of addresses through `rpc_url` into the names
+    /// of `services`, and name the configured tokens in the logs. Call once the secrets
+    /// are fetched
+    pub async fn resolve_names(&self, services: &Services) -> Result<(), ConfigError> {
+        let rules = self.validation.load_rules()?.unwrap_or_default();
+        let contracts = [
+            &self.contracts.swap_manager,
+            &self.contracts.stake_registry,
+            &self.contracts.delegation_manager,
+            &self.contracts.avs_directory,
+            &self.contracts.rewards_coordinator,
+            &self.rewards.recipient,
+        ];
+        services
+            .names()
+            .resolve_names(
+                services.rpc(),
+                &self.rpc_url,
+                contracts
+                    .into_iter()
+                    .flatten()
+                    .chain(&rules.allowed_tokens)
+                    .chain(&rules.banned_addresses)
+                    .chain(&self.challenger.expected_operators),
+            )
+            .await?;
+        for token in &self.spammer.swaps.tokens {
+            services
+                .names()
+                .name_address(token.address, token.symbol.clone());
+        }
+        for token in &self.oracle.tokens {
+            services
+                .names()
+                .name_address(token.address, token.symbol.clone());
+        }
+        Ok(())
+    }
+
+    /// Values that may be `secret://` references: the key, the RPC endpoints and the
+    /// store URL
+    fn secret_values(&self) -> impl Iterator<Item = &String> {
+        [&self.rpc_url, &self.ws_url]
+            .into_iter()
+            .chain(&self.private_key)
+            .chain(&self.store.url)
+            .chain(&self.rpc.fallback_urls)
+            .chain(
+                self.rpc
+                    .networks
+                    .values()
+                    .flat_map(|network| &network.fallback_urls),
+            )
+    }
+
+    fn secret_values_mut(&mut self) -> Vec<&mut String> {
+        let mut values = vec![&mut self.rpc_url, &mut self.ws_url];
+        values.extend(&mut self.private_key);
+        values.extend(&mut self.store.url);
+        values.extend(&mut self.rpc.fallback_urls);
+        values.extend(
+            self.rpc
+                .networks
+                .values_mut()
+                .flat_map(|network| &mut network.fallback_urls),
+        );
+        values
+    }
+
+    /// Resolve the signing key, preferring `private_key_file` over `private_key`
+    pub fn resolve_private_key(&self) -> Result<String, ConfigError> {
+        if let Some(path) = &self.private_key_file {
+            let key = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+                what: "key file",
+                path: path.clone(),
+                source,
+            })?;
+            return Ok(key.trim().to_string());
+        }
+        self.private_key.clone().ok_or_else(|| {
+            ConfigError::Invalid("either private_key or private_key_file must be set".to_string())
+        })
+    }
+
+    /// Signing key source selected by `signer.backend`
+    pub fn signer_backend(&self) -> Result<SignerBackend, ConfigError> {
+        match self.signer.backend {
+            SignerKind::PrivateKey => Ok(SignerBackend::PrivateKey(self.resolve_private_key()?)),
+            SignerKind::Keystore => Ok(SignerBackend::Keystore {
+                path: self.signer.keystore.clone().ok_or_else(|| {
+                    ConfigError::Invalid(
+                        "signer.keystore must be set for the keystore backend".to_string(),
+                    )
+                })?,
+                password: keystore_password(&self.signer.password, &self.signer.password_file),
+            }),
+            SignerKind::AwsKms => Ok(SignerBackend::AwsKms {
+                key_id: self.signer.kms_key_id.clone().ok_or_else(|| {
+                    ConfigError::Invalid(
+                        "signer.kms_key_id must be set for the aws_kms backend".to_string(),
+                    )
+                })?,
+                region: self.signer.kms_region.clone(),
+                endpoint: self.signer.kms_endpoint.clone(),
+            }),
+            SignerKind::Web3signer => {
+                let url = self.signer.web3signer_url.as_deref().ok_or_else(|| {
+                    ConfigError::Invalid(
+                        "signer.web3signer_url must be set for the web3signer backend".to_string(),
+                    )
+                })?;
+                Ok(SignerBackend::Web3signer {
+                    url: Url::parse(url).map_err(|e| {
+                        ConfigError::Invalid(format!("invalid signer.web3signer_url {url}: {e}"))
+                    })?,
+                    address: self.signer.web3signer_address.ok_or_else(|| {
+                        ConfigError::Invalid(
+                            "signer.web3signer_address must be set for the web3signer backend"
+                                .to_string(),
+                        )
+                    })?,
+                    tls: RemoteSignerTls {
+                        ca_cert: self.signer.tls_ca_cert.clone(),
+                        client_cert: self.signer.tls_client_cert.clone(),
+                        client_key: self.signer.tls_client_key.clone(),
+                    },
+                })
+            }
+            SignerKind::Ledger => Ok(SignerBackend::Ledger {
+                path: match &self.signer.ledger_hd_path {
+                    Some(path) => HDPath::Other(path.clone()),
+                    None => HDPath::LedgerLive(self.signer.ledger_account),
+                },
+            }),
+            SignerKind::OsKeychain => Ok(SignerBackend::OsKeychain(self.signer.keychain_entry())),
+        }
+    }
+
+    /// Watcher sending transactions with the fees and replacements set in `[gas]`
+    pub fn pending_tx_watcher(&self) -> Result<PendingTxWatcher, ConfigError> {
+        Ok(PendingTxWatcher::new(fee_strategy(&self.gas)?, &self.gas).dry_run(self.dry_run))
+    }
+
+    /// BLS keystore of the operator, if `signer.bls_keystore` is set
+    pub fn bls_keystore(&self) -> Option<BlsKeystore> {
+        Some(BlsKeystore {
+            path: self.signer.bls_keystore.clone()?,
+            password: keystore_password(&self.signer.bls_password, &self.signer.bls_password_file),
+        })
+    }
+
+    /// EigenLayer operator the key `signer` signs for, see `signer.operator_address`
+    pub fn operator_address(&self, signer: Address) -> Address {
+        self.signer.operator_address.unwrap_or(signer)
+    }
+
+    /// The parsed `operator.socket`, if set
+    pub fn operator_socket(&self) -> Result<Option<OperatorSocket>, ConfigError> {
+        self.operator.socket.as_deref().map(str::parse).transpose()
+    }
+
+    /// The configured log level
+    pub fn log_level(&self) -> Result<Level, ConfigError> {
+        self.log_level
+            .parse()
+            .map_err(|_| ConfigError::Invalid(format!("unknown log level {}", self.log_level)))
+    }
+
+    /// Deployment of the chain behind `rpc_url`, asked through the RPC stack of `services`,
+    /// read from `deployment_file` or the bundled deployments, failing if it was made for
+    /// another chain
+    pub async fn deployment(&self, services: &Services) -> Result<Deployment, ConfigError> {
+        let provider = services.http_provider(&self.rpc_url)?;
+        let chain_id = retry(&self.retry, "eth_chainId", || {
+            time_rpc("eth_chainId", provider.get_chain_id())
+        })
+        .await?;
+        let deployment = match &self.deployment_file {
+            Some(path) => Deployment::from_file(path)?,
+            None => Deployment::bundled(chain_id)?,
+        };
+        deployment.check_chain_id(chain_id)?;
+        info!(
+            chain_id,
+            network = deployment.network().map(|network| network.name()),
+            file = ?self.deployment_file,
+            "Loaded deployment"
+        );
+        Ok(deployment)
+    }
+
+    /// Scheme the responses are signed with, `operator.signature_scheme` or else the
+    /// `signatureScheme` of the deployment, which isn't loaded when every contract address
+    /// is overridden, ECDSA if neither names one
+    pub async fn signature_scheme(
+        &self,
+        services: &Services,
+    ) -> Result<SignatureScheme, ConfigError> {
+        if let Some(scheme) = self.operator.signature_scheme {
+            return Ok(scheme);
+        }
+        if self.contracts.all_set() {
+            return Ok(SignatureScheme::default());
+        }
+        match self.deployment(services).await?.signature_scheme() {
+            Some(scheme) => SignatureScheme::parse(scheme).map_err(ConfigError::Invalid),
+            None => Ok(SignatureScheme::default()),
+        }
+    }
+
+    /// Contract addresses in effect, each one from `[contracts]` or else from the
+    /// deployment, which isn't loaded when every address is overridden
+    pub async fn contract_addresses(
+        &self,
+        services: &Services,
+    ) -> Result<ContractAddresses, ConfigError> {
+        let deployment = if self.contracts.all_set() {
+            None
+        } else {
+            Some(self.deployment(services).await?)
+        };
+        let resolve = |name: &'static str,
+                       configured: &Option<AddressOrName>,
+                       from_deployment: fn(&Deployment) -> Result<Address, DeploymentError>|
+         -> Result<Address, ConfigError> {
+            let (address, source) = match (configured, &deployment) {
+                (Some(configured), _) => (configured.address(services.names())?, "config"),
+                (None, Some(deployment)) => (from_deployment(deployment)?, "deployment"),
+                (None, None) => unreachable!("the deployment is loaded unless all are set"),
+            };
+            info!(contract = name, %address, source, "Contract address in effect");
+            services.names().name_address(address, name);
+            Ok(address)
+        };
+        Ok(ContractAddresses {
+            swap_manager: resolve(
+                "SwapManager",
+                &self.contracts.swap_manager,
+                Deployment::swap_manager_address,
+            )?,
+            stake_registry: resolve(
+                "ECDSAStakeRegistry",
+                &self.contracts.stake_registry,
+                Deployment::stake_registry_address,
+            )?,
+            delegation_manager: resolve(
+                "DelegationManager",
+                &self.contracts.delegation_manager,
+                Deployment::delegation_manager_address,
+            )?,
+            avs_directory: resolve(
+                "AVSDirectory",
+                &self.contracts.avs_directory,
+                Deployment::avs_directory_address,
+            )?,
+        })
+    }
+
+    /// Address of the rewards coordinator, `contracts.rewards_coordinator` or else the
+    /// `rewardsCoordinator` of the deployment
+    pub async fn rewards_coordinator(&self, services: &Services) -> Result<Address, ConfigError> {
+        let (address, source) = match &self.contracts.rewards_coordinator {
+            Some(configured) => (configured.address(services.names())?, "config"),
+            None => (
+                self.deployment(services)
+                    .await?
+                    .rewards_coordinator_address()?,
+                "deployment",
+            ),
+        };
+        info!(contract = "RewardsCoordinator", %address, source, "Contract address in effect");
+        services.names().name_address(address, "RewardsCoordinator");
+        Ok(address)
+    }
+
+    /// First block the rewards tracker scans, `rewards.from_block` or else the block the
+    /// core contracts of the deployment were deployed in
+    pub async fn rewards_from_block(&self, services: &Services) -> Result<u64, ConfigError> {
+        if let Some(from_block) = self.rewards.from_block {
+            return Ok(from_block);
+        }
+        if self.contracts.rewards_coordinator.is_some() {
+            return Err(ConfigError::Invalid(
+                "rewards.from_block must be set when contracts.rewards_coordinator is".to_string(),
+            ));
+        }
+        let from_block = self.deployment(services).await?.core_block()?;
+        info!(from_block, "Scanning the rewards from the deployment block");
+        Ok(from_block)
+    }
+}
+
+fn check_url(url: &str, schemes: &[&str], field: &str) -> Result<(), ConfigError> {
+    // Checked once fetched by `resolve_secrets`
+    if secret_reference(url).is_some() {
+        return Ok(());
+    }
+    let parsed =
+        Url::parse(url).map_err(|e| ConfigError::Invalid(format!("invalid {field} {url}: {e}")))?;
+    if !schemes.contains(&parsed.scheme()) {
+        return Err(ConfigError::Invalid(format!(
+            "{field} must use one of {schemes:?}, got {}",
+            parsed.scheme()
+        )));
+    }
+    Ok(())
+}
+
+/// Source of a keystore password, a fetched secret before the password file
+fn keystore_password(secret: &Option<String>, file: &Option<PathBuf>) -> KeystorePassword {
+    match (secret, file) {
+        (Some(password), _) => KeystorePassword::Value(password.clone()),
+        (None, Some(file)) => KeystorePassword::File(file.clone()),
+        (None, None) => KeystorePassword::Prompt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os_keychain::KeychainEntry;
+
+    #[test]
+    fn test_parse_toml_with_env_overrides() {
+        let mut config: OperatorConfig = toml::from_str(
+            r#"
+            rpc_url = "http://node:8545"
+            private_key = "0x01"
+            log_level = "debug"
+
+            [spammer]
+            interval_secs = 2
+            interval_ms = 250
+            count = 10
+            seed = 42
+
+            [spammer.traffic]
+            seed = 7
+            "#,
+        )
+        .unwrap();
+        config
+            .apply_env_overrides(|name| match name {
+                "RPC_URL" => Some("https://rpc".to_string()),
+                "DRY_RUN" => Some("true".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.rpc_url, "https://rpc");
+        assert!(config.dry_run);
+        assert_eq!(config.ws_url, "ws://localhost:8545");
+        assert_eq!(config.spammer.count, Some(10));
+        assert_eq!(config.spammer.interval(), Duration::from_millis(250));
+        assert!(config.validate().is_ok());
+
+        config.spammer.apply_seed();
+        assert_eq!(config.spammer.names.seed, Some(42));
+        assert_eq!(config.spammer.swaps.seed, Some(43));
+        assert_eq!(config.spammer.traffic.seed, Some(7));
+        assert_eq!(config.spammer.mix.seed, Some(45));
+    }
+
+    #[tokio::test]
+    async fn test_contract_overrides_skip_the_deployment() {
+        let mut config: OperatorConfig = toml::from_str(
+            r#"
+            rpc_url = "http://127.0.0.1:1"
+
+            [contracts]
+            swap_manager = "0x0000000000000000000000000000000000000001"
+            stake_registry = "0x0000000000000000000000000000000000000002"
+            delegation_manager = "0x0000000000000000000000000000000000000003"
+            "#,
+        )
+        .unwrap();
+        config
+            .apply_env_overrides(|name| {
+                (name == "AVS_DIRECTORY_ADDRESS")
+                    .then(|| "0x0000000000000000000000000000000000000004".to_string())
+            })
+            .unwrap();
+
+        let contracts = config
+            .contract_addresses(&Services::default())
+            .await
+            .unwrap();
+        assert_eq!(contracts.stake_registry, Address::with_last_byte(2));
+        assert_eq!(contracts.avs_directory, Address::with_last_byte(4));
+        assert!(
+            config
+                .apply_env_overrides(
+                    |name| (name == "SWAP_MANAGER_ADDRESS").then(|| "0x12".to_string())
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_values() {
+        let mut config = OperatorConfig {
+            private_key: Some("0x01".to_string()),
+            ..Default::default()
+        };
+        config.operator.response_percentage = 120.0;
+        assert!(config.validate().is_err());
+
+        config.operator.response_percentage = 50.0;
+        config.ws_url = "http://localhost:8545".to_string();
+        assert!(config.validate().is_err());
+
+        config.ws_url = "ws://localhost:8545".to_string();
+        config.store.backend = StoreBackend::Postgres;
+        assert!(config.validate().is_err());
+
+        config.store.url = Some("postgres://operator@db/tasks".to_string());
+        assert!(config.validate().is_ok());
+
+        config.spammer.traffic.model = TrafficModel::Ramp;
+        config.spammer.traffic.ramp_from_per_min = 0.0;
+        assert!(config.validate().is_err());
+        config.spammer.traffic.ramp_from_per_min = 1.0;
+        assert!(config.validate().is_ok());
+
+        config.reorg.confirmations = config.reorg.depth;
+        assert!(config.validate().is_err());
+        config.reorg.confirmations = 12;
+        assert!(config.validate().is_ok());
+
+        config.signer.backend = SignerKind::Ledger;
+        config.signer.ledger_hd_path = Some("44'/60'/0'/0/0".to_string());
+        assert!(config.validate().is_err());
+        config.signer.ledger_hd_path = None;
+        config.signer.ledger_account = 2;
+        assert!(matches!(
+            config.signer_backend().unwrap(),
+            SignerBackend::Ledger { path } if path.to_string() == "m/44'/60'/2'/0/0"
+        ));
+        config.signer.backend = SignerKind::from_str("os-keychain", true).unwrap();
+        config.signer.keychain_account = Some(String::new());
+        assert!(config.validate().is_err());
+        config.signer.keychain_account = Some("devnet".to_string());
+        assert!(matches!(
+            config.signer_backend().unwrap(),
+            SignerBackend::OsKeychain(entry)
+                if entry == KeychainEntry::new("swap-manager-avs", "devnet")
+        ));
+        config.signer.backend = SignerKind::PrivateKey;
+
+        config.rpc_url = "secret://rpc_url".to_string();
+        assert!(config.validate().is_err());
+        config.secrets.backend = SecretsBackend::File;
+        assert!(config.validate().is_ok());
+
+        config.private_key = None;
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, B256};
+use tokio::sync::watch;
+use tonic::{Request, Response};
+use tracing::info;
+
+use crate::error::StoreError;
+use crate::services::Services;
+use proto::operator_control_server::{OperatorControl, OperatorControlServer};
+
+/// Types generated from `proto/control.proto`
+#[allow(missing_docs, unreachable_pub, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("swapmanager.control.v1");
+}
+
+/// Number of handled tasks kept for `ListRecentTasks`
+pub const RECENT_TASKS_CAPACITY: usize = 256;
+
+/// What happened to a task handled by the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskOutcome {
+    /// respondToTask was mined
+    Responded,
+    /// The signed response was handed to the aggregator
+    Aggregated,
+    /// The operator chose not to respond
+    Skipped,
+    /// The task arrived while responding was paused
+    Paused,
+    /// Responding or creating failed after all retries
+    Failed,
+    /// createNewTask was mined
+    Created,
+    /// The response or createNewTask was only simulated, in dry-run mode
+    Simulated,
+    /// The swap of the task failed the price check, so it wasn't attested to
+    Rejected,
+    /// No response landed within `dead_letter.max_attempts` or `dead_letter.ttl_secs`, the
+    /// task is left alone until `operator tasks retry-dead`
+    DeadLetter,
+    /// The swap deadline of the task passed before a response was sent
+    Expired,
+    /// `operator tasks cancel` withdrew the task before it was handled
+    Cancelled,
+}
+
+impl TaskOutcome {
+    fn completed(self) -> bool {
+        matches!(
+            self,
+            Self::Responded | Self::Aggregated | Self::Created | Self::Simulated
+        )
+    }
+
+    /// Lowercase name, as persisted by the task store
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Responded => "responded",
+            Self::Aggregated => "aggregated",
+            Self::Skipped => "skipped",
+            Self::Paused => "paused",
+            Self::Failed => "failed",
+            Self::Created => "created",
+            Self::Simulated => "simulated",
+            Self::Rejected => "rejected",
+            Self::DeadLetter => "dead_letter",
+            Self::Expired => "expired",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for TaskOutcome {
+    type Err = StoreError;
+
+    fn from_str(s: &str) -> Result<Self, StoreError> {
+        match s {
+            "responded" => Ok(Self::Responded),
+            "aggregated" => Ok(Self::Aggregated),
+            "skipped" => Ok(Self::Skipped),
+            "paused" => Ok(Self::Paused),
+            "failed" => Ok(Self::Failed),
+            "created" => Ok(Self::Created),
+            "simulated" => Ok(Self::Simulated),
+            "rejected" => Ok(Self::Rejected),
+            "dead_letter" => Ok(Self::DeadLetter),
+            "expired" => Ok(Self::Expired),
+            "cancelled" => Ok(Self::Cancelled),
+            other => Err(StoreError::Corrupt {
+                what: "task outcome",
+                value: other.to_string(),
+            }),
+        }
+    }
+}
+
+impl From<TaskOutcome> for proto::TaskOutcome {
+    fn from(outcome: TaskOutcome) -> Self {
+        match outcome {
+            TaskOutcome::Responded => Self::Responded,
+            TaskOutcome::Aggregated => Self::Aggregated,
+            TaskOutcome::Skipped => Self::Skipped,
+            TaskOutcome::Paused => Self::Paused,
+            TaskOutcome::Failed => Self::Failed,
+            TaskOutcome::Created => Self::Created,
+            TaskOutcome::Simulated => Self::Simulated,
+            TaskOutcome::Rejected => Self::Rejected,
+            TaskOutcome::DeadLetter => Self::DeadLetter,
+            TaskOutcome::Expired => Self::Expired,
+            TaskOutcome::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+/// A task handled by the process, as listed by `ListRecentTasks`
+#[derive(Debug, Clone)]
+pub struct TaskRecord {
+    /// Index of the task, unknown for tasks created by the spammer
+    pub task_index: Option<u32>,
+    /// Name of the task
+    pub task_name: String,
+    /// Block the task was created in
+    pub block_number: Option<u64>,
+    /// What happened to it
+    pub outcome: TaskOutcome,
+    /// Hash of the response or createNewTask transaction
+    pub tx_hash: Option<B256>,
+    /// When the outcome was recorded
+    pub at: SystemTime,
+}
+
+impl From<&TaskRecord> for proto::RecentTask {
+    fn from(record: &TaskRecord) -> Self {
+        Self {
+            task_index: record.task_index,
+            task_name: record.task_name.clone(),
+            block_number: record.block_number,
+            outcome: proto::TaskOutcome::from(record.outcome).into(),
+            tx_hash: record.tx_hash.map(|h| h.to_string()).unwrap_or_default(),
+            timestamp: record
+                .at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// State shared between the task loop of the process and its control service
+#[derive(Debug)]
+pub struct ControlState {
+    address: Address,
+    started: Instant,
+    paused: watch::Sender<bool>,
+    spam_interval: Option<watch::Sender<Duration>>,
+    recent: Mutex<VecDeque<TaskRecord>>,
+    tasks_seen: AtomicU64,
+    tasks_completed: AtomicU64,
+    outcomes: Mutex<Vec<(TaskOutcome, u64)>>,
+    services: Services,
+}
+
+impl ControlState {
+    /// State of an operator signing with `address`, publishing the handled tasks on the
+    /// event bus of `services`
+    pub fn new(address: Address, services: &Services) -> Arc<Self> {
+        Arc::new(Self::build(address, services, None))
+    }
+
+    /// State of a spammer creating a task every `interval`
+    pub fn with_spam_interval(
+        address: Address,
+        services: &Services,
+        interval: Duration,
+    ) -> Arc<Self> {
+        Arc::new(Self::build(
+            address,
+            services,
+            Some(watch::Sender::new(interval)),
+        ))
+    }
+
+    fn build(
+        address: Address,
+        services: &Services,
+        spam_interval: Option<watch::Sender<Duration>>,
+    ) -> Self {
+        Self {
+            address,
+            started: Instant::now(),
+            paused: watch::Sender::new(false),
+            spam_interval,
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_TASKS_CAPACITY)),
+            tasks_seen: AtomicU64::new(0),
+            tasks_completed: AtomicU64::new(0),
+            outcomes: Mutex::new(Vec::new()),
+            services: services.clone(),
+        }
+    }
+
+    /// Whether responding is paused
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Pause or resume responding
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.send_replace(paused);
+    }
+
+    /// Return once responding is not paused
+    pub async fn wait_until_resumed(&self) {
+        let mut paused = self.paused.subscribe();
+        // The sender lives as long as `self`, so this can't fail
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+
+    /// Subscribe to the spam interval, `None` outside of a spammer
+    pub fn spam_interval(&self) -> Option<watch::Receiver<Duration>> {
+        self.spam_interval.as_ref().map(watch::Sender::subscribe)
+    }
+
+    /// Remember a handled task, dropping the oldest one beyond [`RECENT_TASKS_CAPACITY`], and
+    /// publish its lifecycle event on the event bus
+    pub fn record(&self, record: TaskRecord) {
+        self.services.publish_record(self.address, &record);
+        self.tasks_seen.fetch_add(1, Ordering::Relaxed);
+        if record.outcome.completed() {
+            self.tasks_completed.fetch_add(1, Ordering::Relaxed);
+        }
+        {
+            let mut outcomes = self.outcomes.lock().expect("outcome counts lock poisoned");
+            match outcomes
+                .iter_mut()
+                .find(|(outcome, _)| *outcome == record.outcome)
+            {
+                Some((_, count)) => *count += 1,
+                None => outcomes.push((record.outcome, 1)),
+            }
+        }
+        let mut recent = self.recent.lock().expect("recent tasks lock poisoned");
+        if recent.len() == RECENT_TASKS_CAPACITY {
+            recent.pop_back();
+        }
+        recent.push_front(record);
+    }
+
+    /// Up to `limit` most recent tasks, newest first, all of them if `limit` is 0
+    pub fn recent_tasks(&self, limit: usize) -> Vec<TaskRecord> {
+        let recent = self.recent.lock().expect("recent tasks lock poisoned");
+        let limit = if limit == 0 { recent.len() } else { limit };
+        recent.iter().take(limit).cloned().collect()
+    }
+
+    /// Number of tasks recorded with each outcome, in the order they first occurred
+    pub fn outcome_counts(&self) -> Vec<(TaskOutcome, u64)> {
+        self.outcomes
+            .lock()
+            .expect("outcome counts lock poisoned")
+            .clone()
+    }
+
+    /// Snapshot returned by the control RPCs
+    pub fn status(&self) -> proto::Status {
+        proto::Status {
+            address: self.address.to_string(),
+            paused: self.is_paused(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            tasks_seen: self.tasks_seen.load(Ordering::Relaxed),
+            tasks_completed: self.tasks_completed.load(Ordering::Relaxed),
+            spam_interval_ms: self
+                .spam_interval
+                .as_ref()
+                .map(|interval| interval.borrow().as_millis() as u64),
+        }
+    }
+}
+
+/// gRPC `OperatorControl` service over a [`ControlState`]
+#[derive(Debug, Clone)]
+pub struct ControlService {
+    state: Arc<ControlState>,
+}
+
+impl ControlService {
+    /// Serve `state`
+    pub fn new(state: Arc<ControlState>) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl OperatorControl for ControlService {
+    async fn get_status(
+        &self,
+        _request: Request<proto::GetStatusRequest>,
+    ) -> Result<Response<proto::Status>, tonic::Status> {
+        Ok(Response::new(self.state.status()))
+    }
+
+    async fn pause_responding(
+        &self,
+        _request: Request<proto::PauseRespondingRequest>,
+    ) -> Result<Response<proto::Status>, tonic::Status> {
+        self.state.set_paused(true);
+        info!("Paused through the control API");
+        Ok(Response::new(self.state.status()))
+    }
+
+    async fn resume(
+        &self,
+        _request: Request<proto::ResumeRequest>,
+    ) -> Result<Response<proto::Status>, tonic::Status> {
+        self.state.set_paused(false);
+        info!("Resumed through the control API");
+        Ok(Response::new(self.state.status()))
+    }
+
+    async fn set_spam_rate(
+        &self,
+        request: Request<proto::SetSpamRateRequest>,
+    ) -> Result<Response<proto::Status>, tonic::Status> {
+        let interval_ms = request.into_inner().interval_ms;
+        if interval_ms == 0 {
+            return Err(tonic::Status::invalid_argument(
+                "interval_ms must be positive",
+            ));
+        }
+        let Some(spam_interval) = &self.state.spam_interval else {
+            return Err(tonic::Status::failed_precondition(
+                "this process does not create tasks",
+            ));
+        };
+        spam_interval.send_replace(Duration::from_millis(interval_ms));
+        info!(interval_ms, "Spam interval changed through the control API");
+        Ok(Response::new(self.state.status()))
+    }
+
+    async fn list_recent_tasks(
+        &self,
+        request: Request<proto::ListRecentTasksRequest>,
+    ) -> Result<Response<proto::ListRecentTasksResponse>, tonic::Status> {
+        let limit = request.into_inner().limit as usize;
+        Ok(Response::new(proto::ListRecentTasksResponse {
+            tasks: self
+                .state
+                .recent_tasks(limit)
+                .iter()
+                .map(proto::RecentTask::from)
+                .collect(),
+        }))
+    }
+}
+
+/// Serve the control API for `state` on `addr` until the process exits
+pub async fn serve(
+    state: Arc<ControlState>,
+    addr: SocketAddr,
+) -> Result<(), tonic::transport::Error> {
+    info!(%addr, "Control API listening");
+    tonic::transport::Server::builder()
+        .add_service(OperatorControlServer::new(ControlService::new(state)))
+        .serve(addr)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, outcome: TaskOutcome) -> TaskRecord {
+        TaskRecord {
+            task_index: None,
+            task_name: name.to_string(),
+            block_number: None,
+            outcome,
+            tx_hash: None,
+            at: SystemTime::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_control_service_pauses_and_lists_tasks() {
+        let state = ControlState::with_spam_interval(
+            Address::ZERO,
+            &Services::default(),
+            Duration::from_secs(6),
+        );
+        let service = ControlService::new(state.clone());
+        for i in 0..RECENT_TASKS_CAPACITY + 1 {
+            state.record(record(&format!("task{i}"), TaskOutcome::Created));
+        }
+        state.record(record("failed", TaskOutcome::Failed));
+
+        let status = service
+            .pause_responding(Request::new(proto::PauseRespondingRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(status.paused && state.is_paused());
+        assert_eq!(status.tasks_seen, RECENT_TASKS_CAPACITY as u64 + 2);
+        assert_eq!(status.tasks_completed, RECENT_TASKS_CAPACITY as u64 + 1);
+
+        let status = service
+            .set_spam_rate(Request::new(proto::SetSpamRateRequest { interval_ms: 250 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.spam_interval_ms, Some(250));
+
+        let tasks = service
+            .list_recent_tasks(Request::new(proto::ListRecentTasksRequest { limit: 2 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .tasks;
+        let names: Vec<_> = tasks.iter().map(|task| task.task_name.as_str()).collect();
+        assert_eq!(names, ["failed", &format!("task{RECENT_TASKS_CAPACITY}")]);
+        assert_eq!(state.recent_tasks(0).len(), RECENT_TASKS_CAPACITY);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use alloy::{primitives::utils::format_ether, rpc::types::TransactionReceipt};
+use tracing::info;
+
+use crate::prometheus::{add_tx_fees, record_gas_price};
+
+/// What the mined transactions of a method cost, reverted ones included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxCost {
+    /// Transactions mined
+    pub txs: u64,
+    /// Mined transactions that reverted
+    pub reverted: u64,
+    /// Total gas used
+    pub gas_used: u64,
+    /// Total fees paid in wei, gas used times the effective gas price
+    pub fees_wei: u128,
+}
+
+impl TxCost {
+    /// Add a mined transaction using `gas_used` at `effective_gas_price` wei per gas
+    pub fn add(&mut self, gas_used: u64, effective_gas_price: u128, reverted: bool) {
+        self.txs += 1;
+        self.reverted += u64::from(reverted);
+        self.gas_used = self.gas_used.saturating_add(gas_used);
+        self.fees_wei = self
+            .fees_wei
+            .saturating_add(u128::from(gas_used) * effective_gas_price);
+    }
+
+    /// Average price paid per gas in wei, `None` before any gas was used
+    pub fn average_gas_price(&self) -> Option<u128> {
+        (self.gas_used > 0).then(|| self.fees_wei / u128::from(self.gas_used))
+    }
+
+    fn merge(mut self, other: &Self) -> Self {
+        self.txs += other.txs;
+        self.reverted += other.reverted;
+        self.gas_used = self.gas_used.saturating_add(other.gas_used);
+        self.fees_wei = self.fees_wei.saturating_add(other.fees_wei);
+        self
+    }
+}
+
+/// Gas and fees of the transactions mined by an operator, by method
+#[derive(Debug, Default)]
+pub struct TxCosts {
+    by_method: Mutex<BTreeMap<&'static str, TxCost>>,
+}
+
+impl TxCosts {
+    /// Account for the mined `method` transaction of `receipt` and export its fees
+    pub fn record(&self, method: &'static str, receipt: &TransactionReceipt) {
+        self.by_method
+            .lock()
+            .expect("tx costs lock poisoned")
+            .entry(method)
+            .or_default()
+            .add(
+                receipt.gas_used,
+                receipt.effective_gas_price,
+                !receipt.status(),
+            );
+        record_gas_price(method, receipt.effective_gas_price);
+        add_tx_fees(
+            method,
+            wei_to_eth(u128::from(receipt.gas_used) * receipt.effective_gas_price),
+        );
+    }
+
+    /// Costs of the transactions mined so far, by method
+    pub fn by_method(&self) -> BTreeMap<&'static str, TxCost> {
+        self.by_method
+            .lock()
+            .expect("tx costs lock poisoned")
+            .clone()
+    }
+
+    /// Log what the transactions cost, by method and in total
+    pub fn log_summary(&self) {
+        let costs = self.by_method();
+        if costs.is_empty() {
+            return;
+        }
+        for (method, cost) in &costs {
+            info!(
+                method,
+                txs = cost.txs,
+                reverted = cost.reverted,
+                gas_used = cost.gas_used,
+                avg_gas_price_gwei = cost.average_gas_price().map(wei_to_gwei),
+                fees_eth = %format_ether(cost.fees_wei),
+                "Transaction costs"
+            );
+        }
+        let total = costs.values().fold(TxCost::default(), TxCost::merge);
+        info!(
+            txs = total.txs,
+            gas_used = total.gas_used,
+            fees_eth = %format_ether(total.fees_wei),
+            "Total spent on transactions"
+        );
+    }
+}
+
+fn wei_to_eth(wei: u128) -> f64 {
+    wei as f64 / 1e18
+}
+
+fn wei_to_gwei(wei: u128) -> f64 {
+    wei as f64 / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::Services;
+
+    #[test]
+    fn test_costs_add_up_fees_and_reverts() {
+        let mut cost = TxCost::default();
+        assert_eq!(cost.average_gas_price(), None);
+        cost.add(50_000, 2_000_000_000, false);
+        cost.add(30_000, 1_000_000_000, true);
+        assert_eq!(cost.txs, 2);
+        assert_eq!(cost.reverted, 1);
+        assert_eq!(cost.gas_used, 80_000);
+        assert_eq!(cost.fees_wei, 130_000_000_000_000);
+        assert_eq!(cost.average_gas_price(), Some(1_625_000_000));
+        assert_eq!(format_ether(cost.fees_wei), "0.000130000000000000");
+
+        let total = [cost, cost].iter().fold(TxCost::default(), TxCost::merge);
+        assert_eq!(total.txs, 4);
+        assert_eq!(total.fees_wei, 2 * cost.fees_wei);
+    }
+
+    fn receipt(gas_used: u64, effective_gas_price: u128, status: bool) -> TransactionReceipt {
+        serde_json::from_value(serde_json::json!({
+            "type": "0x2",
+            "status": if status { "0x1" } else { "0x0" },
+            "cumulativeGasUsed": format!("{gas_used:#x}"),
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "transactionHash": format!("0x{}", "11".repeat(32)),
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "22".repeat(32)),
+            "blockNumber": "0x1",
+            "gasUsed": format!("{gas_used:#x}"),
+            "effectiveGasPrice": format!("{effective_gas_price:#x}"),
+            "from": format!("0x{}", "33".repeat(20)),
+            "to": format!("0x{}", "44".repeat(20)),
+            "contractAddress": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_operators_add_up_their_own_costs() {
+        let (first, second) = (Services::default(), Services::default());
+        first
+            .tx_costs()
+            .record("respondToTask", &receipt(50_000, 2_000_000_000, true));
+        first
+            .tx_costs()
+            .record("respondToTask", &receipt(30_000, 1_000_000_000, false));
+        second.tx_costs().record(
+            "raiseAndResolveChallenge",
+            &receipt(70_000, 1_000_000_000, true),
+        );
+
+        let costs = first.tx_costs().by_method();
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs["respondToTask"].txs, 2);
+        assert_eq!(costs["respondToTask"].reverted, 1);
+        assert_eq!(costs["respondToTask"].fees_wei, 130_000_000_000_000);
+        let costs = second.tx_costs().by_method();
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs["raiseAndResolveChallenge"].gas_used, 70_000);
+        assert_eq!(first.clone().tx_costs().by_method().len(), 1);
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::ConfigError;
+
+/// Dead-lettering of the tasks whose response can't be landed, the `[dead_letter]` config
+/// section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeadLetterConfig {
+    /// Seconds an attempt at responding may take, `[retry]` backoff included, before it's
+    /// given up, 0 for no deadline
+    pub response_timeout_secs: u64,
+    /// Failed attempts after which the task is dead-lettered
+    pub max_attempts: u32,
+    /// Seconds after the task was first seen from which a failed attempt dead-letters it,
+    /// 0 to only count attempts
+    pub ttl_secs: u64,
+    /// Seconds before a failed task is attempted again
+    pub retry_delay_secs: u64,
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        Self {
+            response_timeout_secs: 600,
+            max_attempts: 3,
+            ttl_secs: 3_600,
+            retry_delay_secs: 60,
+        }
+    }
+}
+
+impl DeadLetterConfig {
+    /// Check the settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_attempts == 0 {
+            return Err(ConfigError::Invalid(
+                "dead_letter.max_attempts must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Deadline of an attempt, if any
+    pub fn response_timeout(&self) -> Option<Duration> {
+        (self.response_timeout_secs > 0).then(|| Duration::from_secs(self.response_timeout_secs))
+    }
+
+    /// Delay before a failed task is attempted again
+    pub fn retry_delay(&self) -> Duration {
+        Duration::from_secs(self.retry_delay_secs)
+    }
+
+    /// Whether a task first seen at unix time `seen_at` is dead-lettered at `now`, once its
+    /// `attempts`-th attempt failed
+    pub fn is_dead(&self, attempts: u32, seen_at: i64, now: i64) -> bool {
+        attempts >= self.max_attempts
+            || self.ttl_secs > 0 && now.saturating_sub(seen_at) >= self.ttl_secs as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_are_dead_lettered_after_max_attempts_or_ttl() {
+        let config = DeadLetterConfig {
+            max_attempts: 3,
+            ttl_secs: 600,
+            ..DeadLetterConfig::default()
+        };
+        assert!(!config.is_dead(1, 1_000, 1_060));
+        assert!(!config.is_dead(2, 1_000, 1_599));
+        assert!(config.is_dead(3, 1_000, 1_060));
+        assert!(config.is_dead(1, 1_000, 1_600));
+
+        let config = DeadLetterConfig {
+            ttl_secs: 0,
+            ..config
+        };
+        assert!(!config.is_dead(2, 0, i64::MAX));
+        assert_eq!(
+            DeadLetterConfig {
+                response_timeout_secs: 0,
+                ..config
+            }
+            .response_timeout(),
+            None
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::{collections::HashMap, fmt, future::IntoFuture, str::FromStr, sync::Mutex};
+
+use alloy::{
+    primitives::{address, keccak256, Address, B256},
+    providers::Provider,
+};
+use serde::{de, Deserialize, Deserializer};
+use tracing::info;
+
+use crate::error::{ChainError, ConfigError};
+use crate::prometheus::time_rpc;
+use crate::rpc_pool::RpcStack;
+
+/// ENS registry, at the same address on mainnet and the public testnets
+pub const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+alloy::sol! {
+    #[sol(rpc)]
+    interface EnsRegistry {
+        function resolver(bytes32 node) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface EnsResolver {
+        function addr(bytes32 node) external view returns (address);
+    }
+}
+
+/// An address of the config, given as hex or as an ENS name resolved at startup
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AddressOrName {
+    /// Hex address
+    Address(Address),
+    /// ENS name such as `swapmanager.eth`, lowercased
+    Name(String),
+}
+
+impl AddressOrName {
+    /// Address, that of the name once [`AddressBook::resolve_names`] resolved it in `book`
+    pub fn address(&self, book: &AddressBook) -> Result<Address, ConfigError> {
+        match self {
+            Self::Address(address) => Ok(*address),
+            Self::Name(name) => book
+                .resolved
+                .lock()
+                .expect("ens lock poisoned")
+                .get(name)
+                .copied()
+                .ok_or_else(|| ConfigError::Invalid(format!("ENS name {name} is not resolved"))),
+        }
+    }
+}
+
+impl From<Address> for AddressOrName {
+    fn from(address: Address) -> Self {
+        Self::Address(address)
+    }
+}
+
+impl FromStr for AddressOrName {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.starts_with("0x") {
+            return value
+                .parse()
+                .map(Self::Address)
+                .map_err(|e| ConfigError::Invalid(format!("invalid address {value}: {e}")));
+        }
+        let is_name = value.contains('.')
+            && value
+                .split('.')
+                .all(|label| !label.is_empty() && !label.contains(char::is_whitespace));
+        if !is_name {
+            return Err(ConfigError::Invalid(format!(
+                "{value} is neither a 0x address nor an ENS name"
+            )));
+        }
+        Ok(Self::Name(value.to_lowercase()))
+    }
+}
+
+impl fmt::Display for AddressOrName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(address) => address.fmt(f),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AddressOrName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// EIP-137 namehash of `name`
+pub fn namehash(name: &str) -> B256 {
+    if name.is_empty() {
+        return B256::ZERO;
+    }
+    name.rsplit('.').fold(B256::ZERO, |node, label| {
+        keccak256([node.as_slice(), keccak256(label.as_bytes()).as_slice()].concat())
+    })
+}
+
+/// Address `name` resolves to on the chain of `rpc_url`, called through `rpc`
+pub async fn resolve_name(
+    rpc: &RpcStack,
+    rpc_url: &str,
+    name: &str,
+) -> Result<Address, ConfigError> {
+    let provider = rpc.http_provider(rpc_url)?;
+    let unresolved = |reason: &str| ConfigError::Invalid(format!("ENS name {name} {reason}"));
+    let code = time_rpc(
+        "eth_getCode",
+        provider.get_code_at(ENS_REGISTRY).into_future(),
+    )
+    .await
+    .map_err(ChainError::from)?;
+    if code.is_empty() {
+        return Err(unresolved(
+            "can't be resolved, the chain has no ENS registry",
+        ));
+    }
+    let node = namehash(name);
+    let resolver = time_rpc(
+        "resolver",
+        EnsRegistry::new(ENS_REGISTRY, &provider)
+            .resolver(node)
+            .call()
+            .into_future(),
+    )
+    .await
+    .map_err(ChainError::from)?
+    ._0;
+    if resolver.is_zero() {
+        return Err(unresolved("has no resolver"));
+    }
+    let address = time_rpc(
+        "addr",
+        EnsResolver::new(resolver, &provider)
+            .addr(node)
+            .call()
+            .into_future(),
+    )
+    .await
+    .map_err(ChainError::from)?
+    ._0;
+    if address.is_zero() {
+        return Err(unresolved("resolves to no address"));
+    }
+    Ok(address)
+}
+
+/// ENS names resolved at startup and the names of the known contracts, tokens and ENS
+/// names shown in the logs
+#[derive(Debug, Default)]
+pub struct AddressBook {
+    resolved: Mutex<HashMap<String, Address>>,
+    names: Mutex<HashMap<Address, String>>,
+}
+
+impl AddressBook {
+    /// Resolve the ENS names among `addresses` through `rpc_url`, so that
+    /// [`AddressOrName::address`] returns them, and name their addresses in the logs
+    pub async fn resolve_names<'a>(
+        &self,
+        rpc: &RpcStack,
+        rpc_url: &str,
+        addresses: impl IntoIterator<Item = &'a AddressOrName>,
+    ) -> Result<(), ConfigError> {
+        for entry in addresses {
+            let AddressOrName::Name(name) = entry else {
+                continue;
+            };
+            if entry.address(self).is_ok() {
+                continue;
+            }
+            let address = resolve_name(rpc, rpc_url, name).await?;
+            info!(name, %address, "Resolved ENS name");
+            self.resolved
+                .lock()
+                .expect("ens lock poisoned")
+                .insert(name.clone(), address);
+            self.name_address(address, name.clone());
+        }
+        Ok(())
+    }
+
+    /// Show `address` as `name` in the logs, the first name given to an address is kept
+    pub fn name_address(&self, address: Address, name: impl Into<String>) {
+        self.names
+            .lock()
+            .expect("address book lock poisoned")
+            .entry(address)
+            .or_insert_with(|| name.into());
+    }
+
+    /// `address` as shown in the logs, its name and shortened hex if it's known
+    pub fn named(&self, address: Address) -> NamedAddress {
+        let name = self
+            .names
+            .lock()
+            .expect("address book lock poisoned")
+            .get(&address)
+            .cloned();
+        NamedAddress { address, name }
+    }
+}
+
+/// Display of an address by its name, see [`AddressBook::named`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedAddress {
+    address: Address,
+    name: Option<String>,
+}
+
+impl NamedAddress {
+    /// The address named
+    pub fn address(&self) -> Address {
+        self.address
+    }
+}
+
+impl fmt::Display for NamedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => {
+                let hex = self.address.to_checksum(None);
+                write!(f, "{name} ({}…{})", &hex[..6], &hex[hex.len() - 4..])
+            }
+            None => self.address.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+
+    #[test]
+    fn test_names_hash_and_parse_like_ens() {
+        assert_eq!(namehash(""), B256::ZERO);
+        assert_eq!(
+            namehash("eth"),
+            b256!("93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+        );
+        assert_eq!(
+            namehash("foo.eth"),
+            b256!("de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")
+        );
+
+        let hex: AddressOrName = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e"
+            .parse()
+            .unwrap();
+        let book = AddressBook::default();
+        assert_eq!(hex.address(&book).unwrap(), ENS_REGISTRY);
+        let name: AddressOrName = "SwapManager.ETH".parse().unwrap();
+        assert_eq!(name, AddressOrName::Name("swapmanager.eth".to_string()));
+        assert!(name.address(&book).is_err());
+        for invalid in ["0x12", "swapmanager", "swap..eth", "my name.eth"] {
+            assert!(invalid.parse::<AddressOrName>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_known_addresses_are_shown_by_name() {
+        let book = AddressBook::default();
+        let address = Address::repeat_byte(0xab);
+        assert_eq!(book.named(address).to_string(), address.to_string());
+        book.name_address(address, "SwapManager");
+        book.name_address(address, "other.eth");
+        assert_eq!(book.named(address).to_string(), "SwapManager (0xABaB…ABaB)");
+    }
+}
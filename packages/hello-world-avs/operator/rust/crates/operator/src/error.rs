@@ -0,0 +1,1098 @@
+use std::{io, num::TryFromIntError, path::PathBuf, time::Duration};
+
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::PendingTransactionError,
+    signers::{aws::AwsSignerError, ledger::LedgerError},
+    transports::TransportError,
+};
+use eigensdk::client_elcontracts::error::ElContractsError;
+use eigensdk::types::operator_metadata::OperatorMetadataError;
+use swap_manager_utils::DeploymentError;
+use thiserror::Error;
+
+use crate::ens::NamedAddress;
+
+/// Error of a dependency not worth a variant of its own
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Failure reading from or sending to the chain
+#[derive(Debug, Error)]
+pub enum ChainError {
+    /// The RPC endpoint failed or answered with an error
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+    /// A contract call failed or its result didn't decode
+    #[error(transparent)]
+    Contract(#[from] alloy::contract::Error),
+    /// Waiting for a transaction failed
+    #[error(transparent)]
+    PendingTransaction(#[from] PendingTransactionError),
+    /// An EigenLayer contract read failed
+    #[error(transparent)]
+    ElContracts(#[from] ElContractsError),
+    /// A log didn't decode as the expected event
+    #[error(transparent)]
+    Decode(#[from] alloy::sol_types::Error),
+    /// A transaction was mined but reverted
+    #[error("{method} reverted in tx {tx_hash}")]
+    Reverted {
+        /// Contract method of the transaction
+        method: &'static str,
+        /// Hash of the reverted transaction
+        tx_hash: B256,
+    },
+    /// A call or gas estimate reverted, with the reason decoded from the revert data
+    #[error("{method} reverted: {reason}")]
+    Revert {
+        /// Contract method of the call
+        method: &'static str,
+        /// Custom error, revert string or panic the contract reverted with
+        reason: String,
+    },
+    /// A transaction and all its replacements stayed in the mempool
+    #[error("{method} with nonce {nonce} not mined after {bumps} fee bumps, last tx {tx_hash}")]
+    NotMined {
+        /// Contract method of the transaction
+        method: &'static str,
+        /// Nonce shared by the transaction and its replacements
+        nonce: u64,
+        /// Replacements sent
+        bumps: u32,
+        /// Hash of the last replacement
+        tx_hash: B256,
+    },
+    /// A WebSocket subscription ended
+    #[error("{0} subscription closed")]
+    SubscriptionClosed(&'static str),
+    /// The RPC endpoint answered something unusable, or a request can't be sent as asked
+    #[error("{0}")]
+    Unexpected(String),
+}
+
+/// Failure loading a key or signing with it
+#[derive(Debug, Error)]
+pub enum SignerError {
+    /// A key, keystore or signer setting that can't be used
+    #[error("{0}")]
+    Invalid(String),
+    /// A key, certificate or password file couldn't be read
+    #[error("failed to read {what} {}", path.display())]
+    Read {
+        /// What the file holds
+        what: &'static str,
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// A keystore couldn't be written
+    #[error("failed to write {what} {}", path.display())]
+    Write {
+        /// What the file holds
+        what: &'static str,
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// A keystore didn't decrypt with the given password
+    #[error("failed to decrypt {what} {}", path.display())]
+    Decrypt {
+        /// What the file holds
+        what: &'static str,
+        /// Path of the keystore
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: BoxError,
+    },
+    /// The password prompt failed
+    #[error("failed to read the password")]
+    Prompt(#[source] io::Error),
+    /// The AWS KMS key couldn't be loaded
+    #[error("failed to load KMS key {key_id}")]
+    Kms {
+        /// Id, ARN or alias of the key
+        key_id: String,
+        /// Underlying error
+        #[source]
+        source: Box<AwsSignerError>,
+    },
+    /// The Ledger device couldn't be reached or didn't give the address of the account
+    #[error("failed to open the Ledger account {derivation}")]
+    Ledger {
+        /// HD derivation path of the account
+        derivation: String,
+        /// Underlying error
+        #[source]
+        source: Box<LedgerError>,
+    },
+    /// The key couldn't be read from or stored in the OS keychain
+    #[error("failed to {action} the key {account} of {service} in the OS keychain: {reason}")]
+    Keychain {
+        /// What was done with the key
+        action: &'static str,
+        /// Keychain service of the key
+        service: String,
+        /// Keychain account of the key
+        account: String,
+        /// Reason given by the keychain
+        reason: String,
+    },
+    /// The HTTP client of the remote signer couldn't be built
+    #[error("failed to set up the remote signer client")]
+    Client(#[from] reqwest::Error),
+    /// Signing failed
+    #[error(transparent)]
+    Signing(#[from] alloy::signers::Error),
+}
+
+/// Failure fetching the secrets of the config
+#[derive(Debug, Error)]
+pub enum SecretsError {
+    /// A file of the secrets backend couldn't be read
+    #[error("failed to read {what} {}", path.display())]
+    Read {
+        /// What the file holds
+        what: &'static str,
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The secrets file couldn't be written
+    #[error("failed to write {what} {}", path.display())]
+    Write {
+        /// What the file holds
+        what: &'static str,
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The secrets file didn't decrypt, the password is wrong or the file was altered
+    #[error("failed to decrypt secrets file {}", path.display())]
+    Decrypt {
+        /// Path of the secrets file
+        path: PathBuf,
+    },
+    /// The password prompt failed
+    #[error("failed to read the password")]
+    Prompt(#[source] io::Error),
+    /// The HTTP client of the secrets backend couldn't be built
+    #[error("failed to set up the secrets client")]
+    Client(#[from] reqwest::Error),
+    /// A Vault request failed or was denied
+    #[error("Vault request to {path} failed")]
+    Vault {
+        /// API path of the request
+        path: String,
+        /// Underlying error
+        #[source]
+        source: reqwest::Error,
+    },
+    /// A referenced secret or key doesn't exist
+    #[error("secret {reference} not found")]
+    Missing {
+        /// Reference of the secret
+        reference: String,
+    },
+    /// The backend settings can't be used
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Failure recording the JSON-RPC traffic or loading a recording to replay
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    /// The recording couldn't be read
+    #[error("failed to read RPC capture {}", path.display())]
+    Read {
+        /// Path of the recording
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The recording couldn't be opened for appending
+    #[error("failed to open RPC capture {}", path.display())]
+    Write {
+        /// Path of the recording
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// A recorded call doesn't parse
+    #[error("RPC capture {} is broken at line {line}: {reason}", path.display())]
+    Broken {
+        /// Path of the recording
+        path: PathBuf,
+        /// Line of the first bad call, from 1
+        line: usize,
+        /// What is wrong with it
+        reason: String,
+    },
+}
+
+/// Failure reading, extending or verifying the audit log
+#[derive(Debug, Error)]
+pub enum AuditError {
+    /// The audit log couldn't be read
+    #[error("failed to read audit log {}", path.display())]
+    Read {
+        /// Path of the log
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// An entry couldn't be appended
+    #[error("failed to append to audit log {}", path.display())]
+    Write {
+        /// Path of the log
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// An entry doesn't parse or breaks the hash chain
+    #[error("audit log {} is broken at line {line}: {reason}", path.display())]
+    Broken {
+        /// Path of the log
+        path: PathBuf,
+        /// Line of the first bad entry, from 1
+        line: usize,
+        /// What is wrong with it
+        reason: String,
+    },
+}
+
+/// Operator metadata that can't be published
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    /// The local metadata file couldn't be read
+    #[error("failed to read metadata file {}", path.display())]
+    Read {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The metadata URI couldn't be fetched
+    #[error("failed to fetch operator metadata from {uri}")]
+    Fetch {
+        /// URI of the metadata
+        uri: String,
+        /// Underlying error
+        #[source]
+        source: reqwest::Error,
+    },
+    /// The document doesn't follow the operator metadata schema
+    #[error("invalid operator metadata: {0}")]
+    Schema(String),
+    /// A field is rejected by the EigenLayer metadata rules
+    #[error("invalid operator metadata")]
+    Rejected(#[from] OperatorMetadataError),
+    /// The URI serves another document than the local metadata file
+    #[error("{uri} doesn't serve the metadata of {}", path.display())]
+    Mismatch {
+        /// URI of the metadata
+        uri: String,
+        /// Path of the local file
+        path: PathBuf,
+    },
+}
+
+/// Invalid or unreadable configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// A file named by the config couldn't be read
+    #[error("failed to read {what} {}", path.display())]
+    Read {
+        /// What the file holds
+        what: &'static str,
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The config file didn't parse
+    #[error("invalid config {}", path.display())]
+    Parse {
+        /// Path of the config file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: BoxError,
+    },
+    /// A value, env var or combination of settings that can't be used
+    #[error("{0}")]
+    Invalid(String),
+    /// The deployment couldn't be loaded or is for another chain
+    #[error(transparent)]
+    Deployment(#[from] DeploymentError),
+    /// The chain ID of the RPC endpoint couldn't be read
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    /// The secrets couldn't be fetched
+    #[error(transparent)]
+    Secrets(#[from] SecretsError),
+}
+
+/// Failure of the task store
+#[derive(Debug, Error)]
+pub enum StoreError {
+    /// The database failed
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    /// The schema migrations failed
+    #[error("task store migration failed")]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    /// The SQLite file couldn't be opened
+    #[error("failed to open task store {}", path.display())]
+    Open {
+        /// Path of the SQLite file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: sqlx::Error,
+    },
+    /// The Postgres database couldn't be reached
+    #[error("failed to connect to the task store")]
+    Connect(#[source] sqlx::Error),
+    /// A stored value doesn't parse back
+    #[error("invalid {what} {value} in task store")]
+    Corrupt {
+        /// What the value is
+        what: &'static str,
+        /// The stored value
+        value: String,
+    },
+    /// A stored number is out of range
+    #[error("number out of range in task store")]
+    OutOfRange(#[from] TryFromIntError),
+    /// The store settings can't be used
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Failure of the spammer
+#[derive(Debug, Error)]
+pub enum SpamError {
+    /// The task name template can't be used
+    #[error("{0}")]
+    Template(String),
+    /// The node rejected the nonce of a createNewTask
+    #[error("nonce {nonce} rejected")]
+    NonceRejected {
+        /// Nonce sent
+        nonce: u64,
+        /// Underlying error
+        #[source]
+        source: Box<ChainError>,
+    },
+    /// Batching was asked for but no contract is deployed at the Multicall3 address
+    #[error("no Multicall3 contract at {0}")]
+    NoMulticall(Address),
+    /// The config can't be used
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The signer failed
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    /// The chain failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// A task name holding a malformed swap payload
+#[derive(Debug, Error)]
+pub enum PayloadError {
+    /// The payload isn't valid hex
+    #[error("swap payload is not hex")]
+    Hex(#[from] alloy::hex::FromHexError),
+    /// The payload doesn't decode as a swap intent
+    #[error("swap payload doesn't decode")]
+    Abi(#[from] alloy::sol_types::Error),
+}
+
+/// Rule of the validation policy a task breaks
+#[derive(Debug, Error)]
+pub enum PolicyViolation {
+    /// The task name is longer than `max_name_length`
+    #[error("task name of {length} bytes exceeds {max}")]
+    NameTooLong {
+        /// Length of the name in bytes
+        length: usize,
+        /// Longest accepted name
+        max: usize,
+    },
+    /// The task name doesn't match `name_pattern`
+    #[error("task name doesn't match {0}")]
+    NameMismatch(String),
+    /// The swap payload trades a token outside `allowed_tokens`
+    #[error("token {0} is not allowed")]
+    TokenNotAllowed(NamedAddress),
+    /// The swap payload trades an address of `banned_addresses`
+    #[error("address {0} is banned")]
+    BannedAddress(NamedAddress),
+    /// The swap payload doesn't decode
+    #[error(transparent)]
+    Payload(#[from] PayloadError),
+}
+
+/// Failure pricing a swap task, or a task whose swap price is refused
+#[derive(Debug, Error)]
+pub enum OracleError {
+    /// The swap trades a token the oracle has no price for
+    #[error("no price configured for token {0}")]
+    UnknownToken(Address),
+    /// The source answered a stale or non-positive price
+    #[error("no usable price for {symbol}")]
+    BadPrice {
+        /// Symbol of the token
+        symbol: String,
+    },
+    /// The price implied by the swap is too far from the reference price
+    #[error(
+        "swap price deviates {deviation_bps} bps from the reference, over {max_deviation_bps}"
+    )]
+    Deviation {
+        /// Deviation from the reference price
+        deviation_bps: u64,
+        /// Largest accepted deviation
+        max_deviation_bps: u32,
+    },
+    /// The task name holds a malformed swap payload
+    #[error(transparent)]
+    Payload(#[from] PayloadError),
+    /// The price API request failed
+    #[error("price request failed")]
+    Http(#[from] reqwest::Error),
+    /// Reading a price feed failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure quoting the swap of a task
+#[derive(Debug, Error)]
+pub enum QuoteError {
+    /// No QuoterV2 is known for the chain and none is configured
+    #[error("no QuoterV2 known for chain {chain_id}, set quoting.quoter_address")]
+    NoQuoter {
+        /// Chain connected to
+        chain_id: u64,
+    },
+    /// No fee tier has a pool for the pair
+    #[error("no pool quotes {token_in} to {token_out}")]
+    NoPool {
+        /// Token sold
+        token_in: Address,
+        /// Token bought
+        token_out: Address,
+    },
+    /// The task name holds a malformed swap payload
+    #[error(transparent)]
+    Payload(#[from] PayloadError),
+    /// The quoter couldn't be reached
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure of the aggregator or of its client
+#[derive(Debug, Error)]
+pub enum AggregatorError {
+    /// A response signature doesn't recover to its operator
+    #[error("{0}")]
+    InvalidSignature(String),
+    /// A response is for a task that doesn't match the one created on chain
+    #[error("task {task_index} does not match the task created on chain")]
+    TaskMismatch {
+        /// Index of the task
+        task_index: u32,
+    },
+    /// A response is signed by an operator without stake
+    #[error("operator {operator} has no stake")]
+    NoStake {
+        /// Operator that signed the response
+        operator: Address,
+    },
+    /// A socket announcement is not newer than the one the aggregator holds
+    #[error("socket announcement of {operator} is older than the announced socket")]
+    StaleSocket {
+        /// Operator that announced the socket
+        operator: Address,
+    },
+    /// A hello doesn't decode or verify
+    #[error("{0}")]
+    InvalidHello(String),
+    /// A hello is not newer than the one the aggregator holds
+    #[error("hello of {operator} is older than the one it sent before")]
+    StaleHello {
+        /// Operator that said hello
+        operator: Address,
+    },
+    /// An operator speaks another version of the handshake protocol
+    #[error("operator {operator} speaks protocol version {version}, expected {expected}")]
+    UnsupportedProtocol {
+        /// Operator that said hello
+        operator: Address,
+        /// Protocol version of the operator
+        version: u32,
+        /// Protocol version of the aggregator
+        expected: u32,
+    },
+    /// An operator runs a software version older than `aggregator.min_operator_version`
+    #[error("operator {operator} runs version {version}, at least {min} is required")]
+    OutdatedOperator {
+        /// Operator that said hello
+        operator: Address,
+        /// Version the operator runs
+        version: String,
+        /// Oldest accepted version
+        min: String,
+    },
+    /// An operator handles none of the task types the aggregator takes
+    #[error("operator {operator} handles none of the task types of the aggregator")]
+    NoTaskType {
+        /// Operator that said hello
+        operator: Address,
+    },
+    /// A response comes from an operator that never said hello, with
+    /// `aggregator.require_hello` set
+    #[error("operator {operator} has not said hello")]
+    NoHello {
+        /// Operator that signed the response
+        operator: Address,
+    },
+    /// A response is for a task type that isn't routed to its operator
+    #[error("operator {operator} does not handle {task_type} tasks")]
+    TaskTypeNotRouted {
+        /// Operator that signed the response
+        operator: Address,
+        /// Type of the task
+        task_type: &'static str,
+    },
+    /// The aggregator URL doesn't parse
+    #[error("invalid aggregator url {url}")]
+    InvalidUrl {
+        /// The URL
+        url: String,
+        /// Underlying error
+        #[source]
+        source: BoxError,
+    },
+    /// The aggregator couldn't be reached
+    #[error("failed to reach the aggregator")]
+    Request(#[from] reqwest::Error),
+    /// The aggregator turned the response down
+    #[error("aggregator rejected the response with {status}: {body}")]
+    Rejected {
+        /// HTTP status of the reply
+        status: reqwest::StatusCode,
+        /// Body of the reply
+        body: String,
+    },
+    /// The signer failed
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    /// The chain failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure publishing task events on the event bus
+#[derive(Debug, Error)]
+pub enum EventBusError {
+    /// The NATS server couldn't be reached or the connection dropped
+    #[error("event bus connection failed")]
+    Io(#[from] io::Error),
+    /// The NATS server refused the connection or spoke out of protocol
+    #[error("NATS error: {0}")]
+    Nats(String),
+    /// The Kafka REST Proxy couldn't be reached
+    #[error("failed to reach the Kafka REST Proxy")]
+    Request(#[from] reqwest::Error),
+    /// The Kafka REST Proxy refused the records
+    #[error("Kafka REST Proxy rejected the records with {status}: {body}")]
+    Rejected {
+        /// HTTP status of the reply
+        status: reqwest::StatusCode,
+        /// Body of the reply
+        body: String,
+    },
+}
+
+/// Failure pushing tasks onto or pulling them from the Redis work queue
+#[derive(Debug, Error)]
+pub enum WorkQueueError {
+    /// Redis couldn't be reached or the connection dropped
+    #[error("work queue connection failed")]
+    Io(#[from] io::Error),
+    /// Redis answered a command with an error
+    #[error("Redis error: {0}")]
+    Redis(String),
+    /// Redis replied out of protocol
+    #[error("unexpected Redis reply: {0}")]
+    Protocol(String),
+}
+
+/// Misconfiguration found by the startup preflight checks
+#[derive(Debug, Error)]
+pub enum PreflightError {
+    /// The RPC endpoint serves another chain than the configured one
+    #[error("rpc_url is connected to chain {actual} but chain_id {expected} is configured, point rpc_url at the right network")]
+    ChainMismatch {
+        /// Configured chain ID
+        expected: u64,
+        /// Chain ID reported by the RPC endpoint
+        actual: u64,
+    },
+    /// No contract is deployed at a configured address
+    #[error("no {contract} contract at {address} on chain {chain_id}, check [contracts] and deployment_file")]
+    NoCode {
+        /// Name of the contract
+        contract: &'static str,
+        /// Address without code
+        address: Address,
+        /// Connected chain
+        chain_id: u64,
+    },
+    /// The signer can't pay for gas
+    #[error(
+        "signer {address} holds no ETH on chain {chain_id}, fund it before sending transactions"
+    )]
+    Unfunded {
+        /// Address of the signer
+        address: Address,
+        /// Connected chain
+        chain_id: u64,
+    },
+    /// The signer isn't an EigenLayer operator
+    #[error("{operator} is not an EigenLayer operator, run `operator register`")]
+    NotOperator {
+        /// Address of the signer
+        operator: Address,
+    },
+    /// The operator isn't registered with the AVS stake registry
+    #[error("{operator} is not registered with the AVS, run `operator register` or `operator quorum join`")]
+    NotRegistered {
+        /// Address of the operator
+        operator: Address,
+    },
+    /// The config can't be used, such as an rpc_url that isn't a URL
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The chain couldn't be queried
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure of `operator rotate-key`
+#[derive(Debug, Error)]
+pub enum RotationError {
+    /// The state file couldn't be read
+    #[error("failed to read the key rotation state {}: {source}", path.display())]
+    Read {
+        /// Path of the state file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The state file couldn't be written
+    #[error("failed to write the key rotation state {}: {source}", path.display())]
+    Write {
+        /// Path of the state file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The state file isn't a key rotation
+    #[error("{} is not a key rotation state: {source}", path.display())]
+    Parse {
+        /// Path of the state file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: serde_json::Error,
+    },
+    /// Only a registered operator can set its signing key
+    #[error("{operator} is not registered with the AVS, rotate the key with the operator key")]
+    NotRegistered {
+        /// Address of the signer
+        operator: Address,
+    },
+    /// The old key can't be retired before the overlap is over
+    #[error("the old key can be retired in {remaining_secs}s")]
+    Overlap {
+        /// Seconds left of the overlap
+        remaining_secs: u64,
+    },
+    /// The signing key in the stake registry isn't the one the rotation set
+    #[error("the signing key of {operator} is {actual}, not the rotated key {expected}")]
+    KeyMismatch {
+        /// Operator whose key was rotated
+        operator: Address,
+        /// Key set by the rotation
+        expected: Address,
+        /// Key held by the stake registry
+        actual: Address,
+    },
+    /// The new key hasn't responded to any task since the rotation
+    #[error("{new_key} responded to no task since the rotation, check the operator runs with it before retiring the old key")]
+    Unproven {
+        /// Key set by the rotation
+        new_key: Address,
+    },
+    /// The new key couldn't be created or unlocked
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    /// The task store couldn't be moved to the new key
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    /// The config can't be used, such as an rpc_url that isn't a URL
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The chain couldn't be queried or the transaction failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure reading the operator's rewards or claiming them
+#[derive(Debug, Error)]
+pub enum RewardsError {
+    /// The distribution file couldn't be read
+    #[error("failed to read the rewards distribution {}", path.display())]
+    Read {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The scan progress couldn't be saved to `rewards.state_file`
+    #[error("failed to write the rewards state file {}", path.display())]
+    State {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The distribution couldn't be fetched
+    #[error("failed to fetch the rewards distribution from {url}")]
+    Fetch {
+        /// URL of the distribution
+        url: String,
+        /// Underlying error
+        #[source]
+        source: reqwest::Error,
+    },
+    /// A line of the distribution isn't an earner, token and cumulative amount
+    #[error("invalid line {line} of the rewards distribution")]
+    Parse {
+        /// Line number, from 1
+        line: usize,
+        /// Underlying error
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The distribution isn't the one the posted root was computed from
+    #[error("the rewards distribution hashes to {computed}, not to the posted root {posted}")]
+    RootMismatch {
+        /// Root of the distribution
+        computed: B256,
+        /// Claimable root of the rewards coordinator
+        posted: B256,
+    },
+    /// No distribution root can be claimed against yet
+    #[error("the rewards coordinator has no claimable distribution root")]
+    NoRoot,
+    /// The earner has earned nothing more than it claimed already
+    #[error("{earner} has no unclaimed rewards in the claimable root")]
+    NothingToClaim {
+        /// Earner of the rewards
+        earner: Address,
+    },
+    /// The signer may not claim the rewards of the earner
+    #[error("{signer} can't claim for {earner}, its claimer is {claimer}")]
+    NotClaimer {
+        /// Address of the signer
+        signer: Address,
+        /// Earner of the rewards
+        earner: Address,
+        /// Claimer set for the earner, zero if none
+        claimer: Address,
+    },
+    /// The config can't be used
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The signer failed
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    /// The chain couldn't be queried or the transaction failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure of the operator
+#[derive(Debug, Error)]
+pub enum OperatorError {
+    /// The config can't be used
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The signer failed
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    /// The chain failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    /// The task store failed
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    /// Handing a response to the aggregator failed
+    #[error(transparent)]
+    Aggregator(#[from] AggregatorError),
+    /// The work queue failed
+    #[error(transparent)]
+    WorkQueue(#[from] WorkQueueError),
+    /// The audit log couldn't be opened
+    #[error(transparent)]
+    Audit(#[from] AuditError),
+    /// The RPC capture couldn't be set up
+    #[error(transparent)]
+    Capture(#[from] CaptureError),
+    /// A task handler couldn't compute the response to a task
+    #[error("the handler of {task_type} tasks failed")]
+    Handler {
+        /// Task type of the handler
+        task_type: &'static str,
+        /// Underlying error
+        #[source]
+        source: BoxError,
+    },
+    /// The operator's stake is too low for the stake registry to give it any weight
+    #[error("operator weight {weight} is below the minimum weight {minimum_weight} of the quorum")]
+    BelowMinimumWeight {
+        /// Current weight of the operator
+        weight: U256,
+        /// Minimum weight of the quorum
+        minimum_weight: U256,
+    },
+    /// Responding to a task took longer than `dead_letter.response_timeout_secs`
+    #[error("response to task {task_index} timed out after {secs}s")]
+    ResponseTimeout {
+        /// Index of the task
+        task_index: u32,
+        /// Deadline of the response
+        secs: u64,
+    },
+    /// A background task panicked or was cancelled
+    #[error("background task failed")]
+    Join(#[from] tokio::task::JoinError),
+    /// The block cursor file couldn't be read or written
+    #[error("failed to access cursor file {}", path.display())]
+    Cursor {
+        /// Path of the cursor file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: BoxError,
+    },
+}
+
+/// Failure topping up a low balance from a faucet or with anvil
+#[derive(Debug, Error)]
+pub enum FundingError {
+    /// `balance.top_up` is `none`, or `http` without a `top_up_url`
+    #[error("no top up is configured")]
+    Disabled,
+    /// The chain holds real value, funding it from a faucet or with cheat codes is refused
+    #[error("refusing to top up on production chain {0}, balance.top_up is for devnets and testnets only")]
+    ProductionChain(u64),
+    /// The faucet couldn't be reached or refused the request
+    #[error("faucet request failed")]
+    Faucet(#[from] reqwest::Error),
+    /// The chain failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+}
+
+/// Failure starting or driving a local anvil devnet
+#[derive(Debug, Error)]
+pub enum DevnetError {
+    /// The anvil container couldn't be started or removed
+    #[error("anvil container failed")]
+    Container(#[from] testcontainers::TestcontainersError),
+    /// No deployment matches the devnet's chain
+    #[error(transparent)]
+    Deployment(#[from] DeploymentError),
+    /// Anvil failed to answer
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    /// An operator run on the devnet failed to register or respond
+    #[error(transparent)]
+    Operator(#[from] OperatorError),
+    /// The tasks of a benchmark couldn't be created
+    #[error(transparent)]
+    Spam(#[from] SpamError),
+    /// The operator of a benchmark handled no warm-up task in time
+    #[error("no warm-up task handled within {0:?}")]
+    WarmUpTimeout(Duration),
+    /// Tasks were still unresponded when the deadline passed
+    #[error("tasks {tasks:?} not responded to in time")]
+    Unresponded {
+        /// Indexes of the unresponded tasks
+        tasks: Vec<u32>,
+    },
+}
+
+/// Failure loading a compiled ABI or regenerating the bindings from it
+#[derive(Debug, Error)]
+pub enum AbiError {
+    /// The ABI or a bindings file couldn't be read or written
+    #[error("failed to access {}", path.display())]
+    Io {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The file is neither a Foundry artifact nor a JSON ABI
+    #[error("{} is not a Foundry artifact nor a JSON ABI: {source}", path.display())]
+    Parse {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: serde_json::Error,
+    },
+    /// No compiled ABI of a bound contract was found
+    #[error("no ABI of {contract} in {}, build the contracts with forge build", dir.display())]
+    NotFound {
+        /// Name of the contract
+        contract: &'static str,
+        /// Directory searched
+        dir: PathBuf,
+    },
+    /// forge couldn't be run or failed
+    #[error("`forge {step}` failed: {reason}")]
+    Forge {
+        /// forge subcommand run
+        step: &'static str,
+        /// Exit status or spawn error
+        reason: String,
+    },
+}
+
+/// Failure taking or restoring a snapshot of the operator state
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    /// The tarball couldn't be read or written
+    #[error("failed to access snapshot {}", path.display())]
+    Io {
+        /// Path of the tarball
+        path: PathBuf,
+        /// Underlying error
+        #[source]
+        source: io::Error,
+    },
+    /// The file isn't a tarball of an operator snapshot
+    #[error("not an operator snapshot: {0}")]
+    Malformed(String),
+    /// The snapshot was taken by an incompatible version of the operator
+    #[error("snapshot version {0} is not supported")]
+    Version(u32),
+    /// An entry of the tarball doesn't parse
+    #[error("invalid snapshot entry")]
+    Json(#[from] serde_json::Error),
+    /// Persistence is disabled, so there is no state to snapshot or restore into
+    #[error("the task store is disabled, set store.path or store.url")]
+    NoStore,
+    /// The snapshot doesn't belong to the operator it's restored into
+    #[error("snapshot {what} {snapshot} differs from {current}")]
+    Mismatch {
+        /// What differs
+        what: &'static str,
+        /// Value in the snapshot
+        snapshot: String,
+        /// Value of the restoring operator
+        current: String,
+    },
+    /// Transactions sent before the snapshot may still land, responding again would
+    /// double-process their tasks
+    #[error("nonces up to {pending} were sent before the snapshot but only {mined} are mined")]
+    InFlight {
+        /// Mined nonce of the operator now
+        mined: u64,
+        /// Pending nonce of the operator in the snapshot
+        pending: u64,
+    },
+    /// The task store failed
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    /// The config can't be used, such as an rpc_url that isn't a URL
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The chain failed
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    /// The block cursor file couldn't be read or written
+    #[error(transparent)]
+    Cursor(#[from] Box<OperatorError>),
+}
+
+/// `From` the chain client errors through [`ChainError`], so `?` works on RPC calls
+macro_rules! from_chain_errors {
+    ($($error:ty),*) => {$(
+        impl From<TransportError> for $error {
+            fn from(error: TransportError) -> Self {
+                ChainError::from(error).into()
+            }
+        }
+
+        impl From<alloy::contract::Error> for $error {
+            fn from(error: alloy::contract::Error) -> Self {
+                ChainError::from(error).into()
+            }
+        }
+
+        impl From<PendingTransactionError> for $error {
+            fn from(error: PendingTransactionError) -> Self {
+                ChainError::from(error).into()
+            }
+        }
+
+        impl From<ElContractsError> for $error {
+            fn from(error: ElContractsError) -> Self {
+                ChainError::from(error).into()
+            }
+        }
+    )*};
+}
+
+from_chain_errors!(
+    ConfigError,
+    SpamError,
+    AggregatorError,
+    OperatorError,
+    PreflightError,
+    OracleError,
+    QuoteError,
+    RotationError,
+    RewardsError,
+    DevnetError,
+    FundingError,
+    SnapshotError
+);
@@ -0,0 +1,220 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy::{
+    primitives::{keccak256, U256},
+    signers::local::PrivateKeySigner,
+};
+use tokio::task::JoinSet;
+use tracing::{error, info};
+
+use crate::config::{ContractAddresses, OperatorConfig};
+use crate::control::ControlState;
+use crate::error::{DevnetError, OperatorError};
+use crate::ha::HaConfig;
+use crate::key_pool::KeyPoolConfig;
+use crate::logging::sdk_logger;
+use crate::registration::register_operator;
+use crate::rpc_capture::CaptureMode;
+use crate::service::Operator;
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::signer::OperatorSigner;
+use crate::task_store::connect_task_store;
+use crate::testutils::fund_account;
+
+/// Domain the fleet keys are derived in
+const FLEET_KEY_DOMAIN: &[u8] = b"swap-manager-avs fleet operator";
+
+/// Key of the `index`-th operator of a fleet, derived from `seed` so a fleet restarted
+/// against the same chain reuses the operators it registered
+pub fn fleet_key(seed: u64, index: u32) -> PrivateKeySigner {
+    let mut counter = 0u32;
+    loop {
+        let preimage = [
+            FLEET_KEY_DOMAIN,
+            &seed.to_be_bytes(),
+            &index.to_be_bytes(),
+            &counter.to_be_bytes(),
+        ]
+        .concat();
+        // Out of range for secp256k1 with a negligible probability
+        if let Ok(key) = PrivateKeySigner::from_bytes(&keccak256(preimage)) {
+            return key;
+        }
+        counter += 1;
+    }
+}
+
+/// Operator of a [`Fleet`]
+#[derive(Debug, Clone)]
+pub struct FleetMember {
+    /// Key the operator registered and signs with
+    pub signer: OperatorSigner,
+    /// Outcomes of the tasks it handled
+    pub control: Arc<ControlState>,
+}
+
+/// Operators with keys of their own run in a single process, all responding to the tasks
+/// of the same SwapManager, to exercise the quorum and aggregation paths locally
+#[derive(Debug)]
+pub struct Fleet {
+    members: Vec<FleetMember>,
+    running: JoinSet<Result<(), DevnetError>>,
+}
+
+impl Fleet {
+    /// Fund `keys` with `balance` wei on the anvil chain of `config`, register them as
+    /// operators and start one operator per key until `shutdown` triggers.
+    ///
+    /// The operators take their settings from `config`, except for what would clash
+    /// between them: the health endpoint, the announced socket and HA. Each one has
+    /// [`Services`] of its own, so they don't share a spend budget, a rate limit or a
+    /// circuit breaker, and writes its audit log, budget state, SQLite task store and RPC
+    /// recording next to the configured files, suffixed with its index. A Postgres task
+    /// store is shared, its rows are kept apart by operator.
+    pub async fn start(
+        config: &OperatorConfig,
+        contracts: &ContractAddresses,
+        keys: Vec<PrivateKeySigner>,
+        balance: U256,
+        shutdown: Shutdown,
+    ) -> Result<Self, DevnetError> {
+        let mut config = config.clone();
+        config.health.listen_addr = None;
+        config.operator.socket = None;
+        config.signer.operator_address = None;
+        // Each operator tracks the nonces of the keys it sends from
+        config.key_pool = KeyPoolConfig::default();
+        config.ha = HaConfig::default();
+        let log_level = config.log_level().map_err(OperatorError::from)?;
+
+        let mut members = Vec::with_capacity(keys.len());
+        let mut running = JoinSet::new();
+        for (index, key) in keys.into_iter().enumerate() {
+            let member_config = member_config(&config, index);
+            let services = Services::from_config(&member_config)?;
+            let signer = OperatorSigner::new(key).services(services.clone());
+            fund_account(&config.rpc_url, signer.address(), balance).await?;
+            register_operator(
+                sdk_logger(log_level),
+                &config.rpc_url,
+                &services,
+                &signer,
+                contracts,
+                &config.operator.metadata_uri,
+            )
+            .await?;
+            let mut builder = Operator::builder()
+                .services(services)
+                .config(&member_config)?
+                .signer(signer.clone())
+                .swap_manager_address(contracts.swap_manager)
+                .shutdown(shutdown.clone());
+            if let Some(store) = connect_task_store(&member_config.store, signer.address())
+                .await
+                .map_err(OperatorError::from)?
+            {
+                builder = builder.task_store(Arc::from(store));
+            }
+            let operator = builder.build()?;
+            let control = operator.control();
+            running.spawn(async move { Ok(operator.run().await?) });
+            info!(operator = %signer.address(), "Fleet operator started");
+            members.push(FleetMember { signer, control });
+        }
+        Ok(Self { members, running })
+    }
+
+    /// Operators of the fleet
+    pub fn members(&self) -> &[FleetMember] {
+        &self.members
+    }
+
+    /// Wait for every operator to stop, failing with the first error once they did
+    pub async fn join(mut self) -> Result<(), DevnetError> {
+        let mut result = Ok(());
+        while let Some(joined) = self.running.join_next().await {
+            match joined {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    error!(error = %e, "Fleet operator failed");
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+                Err(e) => error!(error = %e, "Fleet operator panicked"),
+            }
+        }
+        result
+    }
+}
+
+/// Settings of the `index`-th operator of a fleet started with `config`
+fn member_config(config: &OperatorConfig, index: usize) -> OperatorConfig {
+    let mut member_config = config.clone();
+    member_config.audit.path = member_path(&config.audit.path, index);
+    member_config.budget.state_file = config
+        .budget
+        .state_file
+        .as_deref()
+        .map(|path| member_path(path, index));
+    member_config.store.path = config
+        .store
+        .path
+        .as_deref()
+        .map(|path| member_path(path, index));
+    if config.rpc_capture.mode == CaptureMode::Record {
+        member_config.rpc_capture.path = member_path(&config.rpc_capture.path, index);
+    }
+    member_config
+}
+
+/// `path` of the `index`-th operator of a fleet, `audit.2.jsonl` for `audit.jsonl`
+fn member_path(path: &Path, index: usize) -> PathBuf {
+    match path.extension() {
+        Some(extension) => path.with_extension(format!("{index}.{}", extension.to_string_lossy())),
+        None => path.with_extension(index.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fleet_keys_are_distinct_and_reproducible() {
+        let keys: Vec<_> = (0..4).map(|index| fleet_key(7, index).address()).collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(*key, fleet_key(7, i as u32).address());
+            assert!(!keys[..i].contains(key));
+        }
+        assert_ne!(fleet_key(8, 0).address(), keys[0]);
+    }
+
+    #[test]
+    fn test_members_get_files_of_their_own() {
+        assert_eq!(
+            member_path(Path::new("data/audit.jsonl"), 2),
+            PathBuf::from("data/audit.2.jsonl")
+        );
+        assert_eq!(
+            member_path(Path::new("budget"), 0),
+            PathBuf::from("budget.0")
+        );
+
+        let mut config = OperatorConfig::default();
+        config.store.path = Some(PathBuf::from("data/tasks.db"));
+        config.operator.spill_to_store = true;
+        let member = member_config(&config, 1);
+        assert_eq!(member.store.path, Some(PathBuf::from("data/tasks.1.db")));
+        assert_eq!(member.audit.path, member_path(&config.audit.path, 1));
+        assert!(member.operator.spill_to_store);
+        assert_eq!(
+            member_config(&OperatorConfig::default(), 1).store.path,
+            None
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use eigensdk::common::SdkProvider;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::OnceCell;
+
+use crate::balance::BalanceConfig;
+use crate::error::{ConfigError, FundingError};
+use crate::prometheus::time_rpc;
+use crate::services::Services;
+
+/// Chains holding real value, never funded from a faucet or with cheat codes whatever the
+/// config says: Ethereum, Optimism, BNB Chain, Gnosis, Polygon, Base, Arbitrum One and Avalanche
+pub const PRODUCTION_CHAIN_IDS: [u64; 8] = [1, 10, 56, 100, 137, 8453, 42161, 43114];
+
+/// Whether `chain_id` is one of [`PRODUCTION_CHAIN_IDS`]
+pub fn is_production_chain(chain_id: u64) -> bool {
+    PRODUCTION_CHAIN_IDS.contains(&chain_id)
+}
+
+/// How a balance under the minimum is topped up
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TopUpKind {
+    /// Only log and export the low balance
+    #[default]
+    None,
+    /// Ask the faucet at `top_up_url`
+    Http,
+    /// Set the balance with `anvil_setBalance`, for devnets
+    Anvil,
+}
+
+/// What came of a [`Funder::fund`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Funding {
+    /// The faucet or anvil was asked for the funds
+    Requested,
+    /// An earlier request is too recent, funds may be asked again after the wait
+    RateLimited(Duration),
+}
+
+/// Tops up the balance of an account on a devnet or testnet, at most once per cooldown and
+/// never on a production chain
+#[derive(Debug)]
+pub struct Funder {
+    provider: SdkProvider,
+    client: reqwest::Client,
+    kind: TopUpKind,
+    url: Option<String>,
+    amount: U256,
+    /// Balance `anvil_setBalance` sets
+    target: U256,
+    cooldown: Duration,
+    chain_id: OnceCell<u64>,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl Funder {
+    /// Top up as set in the `[balance]` section through `rpc_url` and the RPC stack of
+    /// `services`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        config: &BalanceConfig,
+    ) -> Result<Self, ConfigError> {
+        let amount = config.top_up_amount()?;
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            client: reqwest::Client::new(),
+            kind: config.top_up,
+            url: config.top_up_url.clone(),
+            amount,
+            target: config.min_balance()? + amount,
+            cooldown: Duration::from_secs(config.top_up_cooldown_secs),
+            chain_id: OnceCell::new(),
+            last_request: Mutex::new(None),
+        })
+    }
+
+    /// How the balance is topped up
+    pub fn kind(&self) -> TopUpKind {
+        self.kind
+    }
+
+    /// Amount asked for in wei
+    pub fn amount(&self) -> U256 {
+        self.amount
+    }
+
+    /// Ask for funds for `address`, holding `balance`, unless the last request was less than
+    /// a cooldown ago. Fails without asking on a production chain
+    pub async fn fund(&self, address: Address, balance: U256) -> Result<Funding, FundingError> {
+        if self.kind == TopUpKind::None {
+            return Err(FundingError::Disabled);
+        }
+        let chain_id = *self
+            .chain_id
+            .get_or_try_init(|| time_rpc("eth_chainId", self.provider.get_chain_id()))
+            .await?;
+        if is_production_chain(chain_id) {
+            return Err(FundingError::ProductionChain(chain_id));
+        }
+        if let Some(wait) = self.reserve(Instant::now()) {
+            return Ok(Funding::RateLimited(wait));
+        }
+        match self.kind {
+            TopUpKind::None => return Err(FundingError::Disabled),
+            TopUpKind::Http => {
+                let url = self.url.as_deref().ok_or(FundingError::Disabled)?;
+                self.client
+                    .post(url)
+                    .json(&json!({
+                        "address": address,
+                        "balance": balance,
+                        "amount": self.amount,
+                    }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            TopUpKind::Anvil => {
+                time_rpc(
+                    "anvil_setBalance",
+                    self.provider
+                        .raw_request::<_, ()>("anvil_setBalance".into(), (address, self.target)),
+                )
+                .await?;
+            }
+        }
+        Ok(Funding::Requested)
+    }
+
+    /// Claim the request slot at `now`, or the wait left until the next one. A failed request
+    /// holds the slot too, so an unreachable faucet isn't hammered
+    fn reserve(&self, now: Instant) -> Option<Duration> {
+        let mut last = self.last_request.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(wait) = last
+            .map(|at| self.cooldown.saturating_sub(now.duration_since(at)))
+            .filter(|wait| !wait.is_zero())
+        {
+            return Some(wait);
+        }
+        *last = Some(now);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_spaced_by_the_cooldown() {
+        let config = BalanceConfig {
+            top_up: TopUpKind::Anvil,
+            top_up_cooldown_secs: 60,
+            ..Default::default()
+        };
+        let funder = Funder::new("http://localhost:8545", &Services::default(), &config).unwrap();
+        let start = Instant::now();
+        assert_eq!(funder.reserve(start), None);
+        assert_eq!(
+            funder.reserve(start + Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(funder.reserve(start + Duration::from_secs(60)), None);
+        assert!(funder.reserve(start + Duration::from_secs(61)).is_some());
+
+        assert!(is_production_chain(1));
+        assert!(is_production_chain(42161));
+        assert!(!is_production_chain(17000));
+        assert!(!is_production_chain(31337));
+    }
+}
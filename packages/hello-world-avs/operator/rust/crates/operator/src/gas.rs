@@ -0,0 +1,353 @@
+use std::{future::IntoFuture, sync::Arc};
+
+use alloy::{
+    contract::{CallBuilder, CallDecoder},
+    providers::Provider,
+    rpc::types::{BlockNumberOrTag, TransactionRequest},
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::error::{ChainError, ConfigError};
+use crate::prometheus::time_rpc;
+use crate::revert::decode_revert;
+
+/// Gas limit multiplier of [`GasConfig::default`]
+const DEFAULT_GAS_LIMIT_MULTIPLIER: f64 = 1.2;
+
+/// How the fees of sent transactions are chosen
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeStrategyKind {
+    /// EIP-1559 fees with a priority fee taken from recent blocks
+    #[default]
+    Eip1559,
+    /// Pre EIP-1559 `eth_gasPrice`
+    Legacy,
+    /// EIP-1559 fees fixed by `max_fee_per_gas` and `priority_fee_per_gas`
+    Fixed,
+}
+
+/// The `[gas]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GasConfig {
+    /// `eip1559`, `legacy` or `fixed` (`GAS_STRATEGY`)
+    pub strategy: FeeStrategyKind,
+    /// Percentile of the priority fees paid in recent blocks to pay, between 0 and 100
+    pub priority_fee_percentile: f64,
+    /// Number of recent blocks the priority fee is sampled from
+    pub fee_history_blocks: u64,
+    /// Priority fee in wei of the fixed strategy, lower bound of the eip1559 one
+    pub priority_fee_per_gas: Option<u128>,
+    /// Ceiling in wei on the fee per gas of every strategy (`MAX_FEE_PER_GAS`)
+    pub max_fee_per_gas: Option<u128>,
+    /// Seconds a transaction may stay pending before it is replaced with higher fees
+    pub stuck_timeout_secs: u64,
+    /// Percent the fees of a replacement transaction are raised by, at least 10
+    pub fee_bump_percent: u64,
+    /// Replacements sent before giving up on a transaction
+    pub max_fee_bumps: u32,
+    /// Factor the `eth_estimateGas` estimate is multiplied by to get the gas limit of a
+    /// transaction, at least 1 (`GAS_LIMIT_MULTIPLIER`)
+    pub gas_limit_multiplier: f64,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            strategy: FeeStrategyKind::default(),
+            priority_fee_percentile: 50.0,
+            fee_history_blocks: 10,
+            priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+            stuck_timeout_secs: 30,
+            fee_bump_percent: 12,
+            max_fee_bumps: 3,
+            gas_limit_multiplier: DEFAULT_GAS_LIMIT_MULTIPLIER,
+        }
+    }
+}
+
+/// Fees of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fees {
+    /// Legacy gas price
+    Legacy {
+        /// Price per gas in wei
+        gas_price: u128,
+    },
+    /// EIP-1559 fee caps
+    Eip1559 {
+        /// Most paid per gas in wei, base fee included
+        max_fee_per_gas: u128,
+        /// Most paid per gas in wei to the block producer
+        max_priority_fee_per_gas: u128,
+    },
+}
+
+impl Fees {
+    /// Highest fee per gas the transaction may pay
+    pub fn max_fee_per_gas(&self) -> u128 {
+        match *self {
+            Self::Legacy { gas_price } => gas_price,
+            Self::Eip1559 {
+                max_fee_per_gas, ..
+            } => max_fee_per_gas,
+        }
+    }
+
+    /// Lower the fees to `ceiling` if they exceed it
+    pub fn capped(self, ceiling: u128) -> Self {
+        match self {
+            Self::Legacy { gas_price } => Self::Legacy {
+                gas_price: gas_price.min(ceiling),
+            },
+            Self::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => Self::Eip1559 {
+                max_fee_per_gas: max_fee_per_gas.min(ceiling),
+                max_priority_fee_per_gas: max_priority_fee_per_gas.min(ceiling),
+            },
+        }
+    }
+
+    /// Set the fees on `tx`, replacing any it had
+    pub fn apply(self, mut tx: TransactionRequest) -> TransactionRequest {
+        match self {
+            Self::Legacy { gas_price } => {
+                tx.gas_price = Some(gas_price);
+                tx.max_fee_per_gas = None;
+                tx.max_priority_fee_per_gas = None;
+            }
+            Self::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                tx.gas_price = None;
+                tx.max_fee_per_gas = Some(max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+            }
+        }
+        tx
+    }
+}
+
+/// Chooses the fees of the transactions about to be sent
+#[async_trait]
+pub trait FeeStrategy: Send + Sync + std::fmt::Debug {
+    /// Fees for a transaction sent through `provider` now
+    async fn fees(&self, provider: &dyn Provider) -> Result<Fees, ChainError>;
+}
+
+/// `eth_gasPrice` as a legacy gas price
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LegacyFeeStrategy;
+
+#[async_trait]
+impl FeeStrategy for LegacyFeeStrategy {
+    async fn fees(&self, provider: &dyn Provider) -> Result<Fees, ChainError> {
+        let gas_price = time_rpc("eth_gasPrice", provider.get_gas_price()).await?;
+        Ok(Fees::Legacy { gas_price })
+    }
+}
+
+/// EIP-1559 fees paying a percentile of the priority fees of recent blocks
+#[derive(Debug, Clone)]
+pub struct Eip1559FeeStrategy {
+    /// Percentile of the recent priority fees to pay
+    pub percentile: f64,
+    /// Number of recent blocks sampled
+    pub blocks: u64,
+    /// Lowest priority fee paid in wei
+    pub min_priority_fee: u128,
+}
+
+#[async_trait]
+impl FeeStrategy for Eip1559FeeStrategy {
+    async fn fees(&self, provider: &dyn Provider) -> Result<Fees, ChainError> {
+        let history = time_rpc(
+            "eth_feeHistory",
+            provider.get_fee_history(self.blocks, BlockNumberOrTag::Latest, &[self.percentile]),
+        )
+        .await?;
+        let base_fee = history.next_block_base_fee().ok_or_else(|| {
+            ChainError::Unexpected("eth_feeHistory returned no base fee".to_string())
+        })?;
+        let rewards: Vec<u128> = history
+            .reward
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        Ok(eip1559_fees(base_fee, &rewards, self.min_priority_fee))
+    }
+}
+
+/// Fees paying the median of the sampled `rewards` on top of twice the `base_fee`, so
+/// the transaction stays includable through a few full blocks
+pub fn eip1559_fees(base_fee: u128, rewards: &[u128], min_priority_fee: u128) -> Fees {
+    let mut rewards = rewards.to_vec();
+    rewards.sort_unstable();
+    let priority_fee = rewards
+        .get(rewards.len() / 2)
+        .copied()
+        .unwrap_or_default()
+        .max(min_priority_fee);
+    Fees::Eip1559 {
+        max_fee_per_gas: base_fee.saturating_mul(2).saturating_add(priority_fee),
+        max_priority_fee_per_gas: priority_fee,
+    }
+}
+
+/// Always the same EIP-1559 fees
+#[derive(Debug, Clone, Copy)]
+pub struct FixedFeeStrategy {
+    /// Most paid per gas in wei, base fee included
+    pub max_fee_per_gas: u128,
+    /// Most paid per gas in wei to the block producer
+    pub max_priority_fee_per_gas: u128,
+}
+
+#[async_trait]
+impl FeeStrategy for FixedFeeStrategy {
+    async fn fees(&self, _provider: &dyn Provider) -> Result<Fees, ChainError> {
+        Ok(Fees::Eip1559 {
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+        })
+    }
+}
+
+/// Another strategy with its fees held below a ceiling, so a gas spike delays
+/// transactions instead of draining the wallet
+#[derive(Debug, Clone)]
+pub struct CappedFeeStrategy {
+    inner: Arc<dyn FeeStrategy>,
+    ceiling: u128,
+}
+
+impl CappedFeeStrategy {
+    /// Cap the fees of `inner` to `ceiling` wei per gas
+    pub fn new(inner: Arc<dyn FeeStrategy>, ceiling: u128) -> Self {
+        Self { inner, ceiling }
+    }
+}
+
+#[async_trait]
+impl FeeStrategy for CappedFeeStrategy {
+    async fn fees(&self, provider: &dyn Provider) -> Result<Fees, ChainError> {
+        let fees = self.inner.fees(provider).await?;
+        if fees.max_fee_per_gas() > self.ceiling {
+            warn!(
+                max_fee_per_gas = fees.max_fee_per_gas(),
+                ceiling = self.ceiling,
+                "Fees above the ceiling, capping them"
+            );
+        }
+        Ok(fees.capped(self.ceiling))
+    }
+}
+
+/// Gas limit of a transaction estimated to use `estimate` gas, padded by `multiplier`
+pub fn gas_limit(estimate: u64, multiplier: f64) -> u64 {
+    (estimate as f64 * multiplier).ceil() as u64
+}
+
+/// Gas limit of the `method` transaction `tx` padded by `multiplier`, a revert of the
+/// estimate is decoded into [`ChainError::Revert`]
+pub async fn estimate_gas_limit<P: Provider>(
+    provider: &P,
+    multiplier: f64,
+    method: &'static str,
+    tx: &TransactionRequest,
+) -> Result<u64, ChainError> {
+    let estimate = time_rpc(
+        "eth_estimateGas",
+        provider.estimate_gas(tx.clone()).into_future(),
+    )
+    .await
+    .map_err(|e| decode_revert(method, e.into()))?;
+    let limit = gas_limit(estimate, multiplier);
+    debug!(method, estimate, limit, "Gas estimated");
+    Ok(limit)
+}
+
+/// Gas limit of the `method` contract call `call`, as [`estimate_gas_limit`]
+pub async fn estimate_call_gas<T, P: Provider, D: CallDecoder>(
+    multiplier: f64,
+    method: &'static str,
+    call: &CallBuilder<T, P, D>,
+) -> Result<u64, ChainError> {
+    let estimate = time_rpc("eth_estimateGas", call.estimate_gas())
+        .await
+        .map_err(|e| decode_revert(method, e.into()))?;
+    let limit = gas_limit(estimate, multiplier);
+    debug!(method, estimate, limit, "Gas estimated");
+    Ok(limit)
+}
+
+/// Build the strategy selected by `config`
+pub fn fee_strategy(config: &GasConfig) -> Result<Arc<dyn FeeStrategy>, ConfigError> {
+    let strategy: Arc<dyn FeeStrategy> = match config.strategy {
+        FeeStrategyKind::Eip1559 => Arc::new(Eip1559FeeStrategy {
+            percentile: config.priority_fee_percentile,
+            blocks: config.fee_history_blocks,
+            min_priority_fee: config.priority_fee_per_gas.unwrap_or_default(),
+        }),
+        FeeStrategyKind::Legacy => Arc::new(LegacyFeeStrategy),
+        FeeStrategyKind::Fixed => Arc::new(FixedFeeStrategy {
+            max_fee_per_gas: config.max_fee_per_gas.ok_or_else(|| {
+                ConfigError::Invalid(
+                    "gas.max_fee_per_gas must be set for the fixed strategy".to_string(),
+                )
+            })?,
+            max_priority_fee_per_gas: config.priority_fee_per_gas.ok_or_else(|| {
+                ConfigError::Invalid(
+                    "gas.priority_fee_per_gas must be set for the fixed strategy".to_string(),
+                )
+            })?,
+        }),
+    };
+    Ok(match config.max_fee_per_gas {
+        Some(ceiling) => Arc::new(CappedFeeStrategy::new(strategy, ceiling)),
+        None => strategy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eip1559_fees_pay_the_median_reward_under_the_ceiling() {
+        let fees = eip1559_fees(10, &[7, 1, 3, 100, 2], 0);
+        assert_eq!(
+            fees,
+            Fees::Eip1559 {
+                max_fee_per_gas: 23,
+                max_priority_fee_per_gas: 3,
+            }
+        );
+        assert_eq!(
+            eip1559_fees(10, &[], 5),
+            Fees::Eip1559 {
+                max_fee_per_gas: 25,
+                max_priority_fee_per_gas: 5,
+            }
+        );
+        assert_eq!(
+            fees.capped(20),
+            Fees::Eip1559 {
+                max_fee_per_gas: 20,
+                max_priority_fee_per_gas: 3,
+            }
+        );
+        assert_eq!(
+            Fees::Legacy { gas_price: 50 }.capped(20),
+            Fees::Legacy { gas_price: 20 }
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    future::IntoFuture,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use alloy::{
+    consensus::Transaction as _,
+    eips::BlockNumberOrTag,
+    network::TransactionResponse,
+    primitives::{Address, B256},
+    providers::Provider,
+    sol_types::SolCall,
+};
+use serde::Deserialize;
+use swap_manager_utils::SwapManager::SwapManager::{self, respondToTaskCall};
+use tracing::{info, warn};
+
+use crate::error::{ChainError, ConfigError};
+use crate::prometheus::{record_task_deduplicated, set_ha_leader, time_rpc};
+use crate::shutdown::Shutdown;
+use crate::task_store::{StoreBackend, StoreConfig, TaskStore};
+
+/// Lock held by the replica allowed to respond
+pub const LEADER_LOCK: &str = "leader";
+
+/// Lock claiming task `task_index` for one replica
+pub fn task_lock(task_index: u32) -> String {
+    format!("task/{task_index}")
+}
+
+/// Replicas of one operator sharing a Postgres task store, the `[ha]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HaConfig {
+    /// Claim each task in the task store before handling it, so a single replica responds
+    pub enabled: bool,
+    /// Name of this replica in the locks, the host name and process ID if unset
+    /// (`HA_REPLICA_ID`)
+    pub replica_id: Option<String>,
+    /// Only the replica holding the leader lease responds, the others take over once it
+    /// stops renewing it
+    pub leader_election: bool,
+    /// Seconds the leader lease lasts without being renewed, renewed every third of it
+    pub lease_ttl_secs: u64,
+    /// Seconds a task claim lasts, after which a replica that died handling the task is
+    /// replaced by another
+    pub claim_ttl_secs: u64,
+    /// Skip the tasks a respondToTask of the operator is already pending for, even without
+    /// `enabled`
+    pub check_pending_txs: bool,
+}
+
+impl Default for HaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            replica_id: None,
+            leader_election: false,
+            lease_ttl_secs: 15,
+            claim_ttl_secs: 300,
+            check_pending_txs: false,
+        }
+    }
+}
+
+impl HaConfig {
+    /// Name of this replica, `replica_id` or `<host name>-<process ID>`
+    pub fn replica_id(&self) -> String {
+        self.replica_id.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "operator".to_string());
+            format!("{host}-{}", std::process::id())
+        })
+    }
+
+    /// Check that the locks can be shared with the other replicas through `store`
+    pub fn validate(&self, store: &StoreConfig) -> Result<(), ConfigError> {
+        if self.leader_election && !self.enabled {
+            return Err(ConfigError::Invalid(
+                "ha.leader_election needs ha.enabled".to_string(),
+            ));
+        }
+        if !self.enabled {
+            return Ok(());
+        }
+        if store.backend != StoreBackend::Postgres {
+            return Err(ConfigError::Invalid(
+                "ha.enabled needs the postgres store shared by the replicas".to_string(),
+            ));
+        }
+        if self.lease_ttl_secs < 3 {
+            return Err(ConfigError::Invalid(
+                "ha.lease_ttl_secs must be at least 3".to_string(),
+            ));
+        }
+        if self.claim_ttl_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "ha.claim_ttl_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the leader lease of this replica renewed, or tries to take it over
+#[derive(Debug)]
+pub struct LeaderElection {
+    store: Arc<dyn TaskStore>,
+    replica_id: String,
+    lease_ttl: Duration,
+    leader: AtomicBool,
+}
+
+impl LeaderElection {
+    /// Election of `replica_id` through the locks of `store`
+    pub fn new(store: Arc<dyn TaskStore>, replica_id: String, lease_ttl: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            store,
+            replica_id,
+            lease_ttl,
+            leader: AtomicBool::new(false),
+        })
+    }
+
+    /// Whether this replica held the lease at the last attempt
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    /// Take or renew the lease, losing it if the store can't be reached
+    pub async fn campaign(&self) -> bool {
+        let leader = match self
+            .store
+            .try_lock(LEADER_LOCK, &self.replica_id, self.lease_ttl.as_secs())
+            .await
+        {
+            Ok(leader) => leader,
+            Err(e) => {
+                warn!(error = %e, "Failed to renew the leader lease");
+                false
+            }
+        };
+        if self.leader.swap(leader, Ordering::Relaxed) != leader {
+            if leader {
+                info!(replica = %self.replica_id, "This replica is now the leader");
+            } else {
+                warn!(replica = %self.replica_id, "This replica is no longer the leader");
+            }
+        }
+        set_ha_leader(leader);
+        leader
+    }
+
+    /// Campaign every third of the lease until `shutdown` is triggered, then hand the lease
+    /// over to the next replica
+    pub async fn run(self: Arc<Self>, shutdown: Shutdown) {
+        while !shutdown.is_triggered() {
+            self.campaign().await;
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(self.lease_ttl / 3) => {}
+            }
+        }
+        if self.leader.swap(false, Ordering::Relaxed) {
+            if let Err(e) = self.store.unlock(LEADER_LOCK, &self.replica_id).await {
+                warn!(error = %e, "Failed to release the leader lease");
+            }
+        }
+    }
+}
+
+/// Decides which tasks this replica handles, see [`HaConfig`]
+#[derive(Debug)]
+pub struct Replica {
+    store: Arc<dyn TaskStore>,
+    replica_id: String,
+    claim_ttl_secs: u64,
+    election: Option<Arc<LeaderElection>>,
+}
+
+impl Replica {
+    /// Replica claiming tasks in `store` as set in `config`
+    pub fn new(store: Arc<dyn TaskStore>, config: &HaConfig) -> Self {
+        let replica_id = config.replica_id();
+        let election = config.leader_election.then(|| {
+            LeaderElection::new(
+                store.clone(),
+                replica_id.clone(),
+                Duration::from_secs(config.lease_ttl_secs),
+            )
+        });
+        Self {
+            store,
+            replica_id,
+            claim_ttl_secs: config.claim_ttl_secs,
+            election,
+        }
+    }
+
+    /// Election of the leader, if only the leader responds
+    pub fn election(&self) -> Option<&Arc<LeaderElection>> {
+        self.election.as_ref()
+    }
+
+    /// Whether this replica should handle task `task_index`: it is the leader, if there is
+    /// one, and no other replica claimed the task. The task stays claimed until
+    /// [`Self::release`], a store error lets the task through rather than drop it
+    pub async fn claim(&self, task_index: u32) -> bool {
+        if self.election.as_ref().is_some_and(|e| !e.is_leader()) {
+            info!(task_index, "Not the leader, leaving the task to it");
+            record_task_deduplicated("follower");
+            return false;
+        }
+        match self
+            .store
+            .try_lock(
+                &task_lock(task_index),
+                &self.replica_id,
+                self.claim_ttl_secs,
+            )
+            .await
+        {
+            Ok(true) => true,
+            Ok(false) => {
+                info!(task_index, "Task claimed by another replica, skipping");
+                record_task_deduplicated("claimed");
+                false
+            }
+            Err(e) => {
+                warn!(task_index, error = %e, "Failed to claim the task, handling it anyway");
+                true
+            }
+        }
+    }
+
+    /// Drop the claim on task `task_index`, once it was handled
+    pub async fn release(&self, task_index: u32) {
+        if let Err(e) = self
+            .store
+            .unlock(&task_lock(task_index), &self.replica_id)
+            .await
+        {
+            warn!(task_index, error = %e, "Failed to release the task claim");
+        }
+    }
+}
+
+/// Hash of a respondToTask of `operator` to task `task_index` waiting in the pending block
+pub async fn pending_response<P: Provider>(
+    provider: &P,
+    operator: Address,
+    swap_manager: Address,
+    task_index: u32,
+) -> Result<Option<B256>, ChainError> {
+    let block = time_rpc(
+        "eth_getBlockByNumber",
+        provider
+            .get_block_by_number(BlockNumberOrTag::Pending)
+            .full()
+            .into_future(),
+    )
+    .await?;
+    let Some(block) = block else {
+        return Ok(None);
+    };
+    Ok(block
+        .transactions
+        .into_transactions()
+        .find(|tx| {
+            tx.from() == operator
+                && tx.to() == Some(swap_manager)
+                && respondToTaskCall::abi_decode(tx.input(), false)
+                    .is_ok_and(|call| call.referenceTaskIndex == task_index)
+        })
+        .map(|tx| tx.tx_hash()))
+}
+
+/// Skip task `task_index` if a respondToTask of `operator` is already pending for it,
+/// a failed lookup lets the task through
+pub async fn already_pending<P: Provider>(
+    provider: &P,
+    operator: Address,
+    swap_manager: Address,
+    task_index: u32,
+) -> bool {
+    match pending_response(provider, operator, swap_manager, task_index).await {
+        Ok(Some(tx_hash)) => {
+            info!(task_index, %tx_hash, "A response to the task is already pending, skipping");
+            record_task_deduplicated("pending_tx");
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            warn!(task_index, error = %e, "Failed to look for a pending response");
+            false
+        }
+    }
+}
+
+/// Skip task `task_index` if the SwapManager already holds a response of `operator` to it,
+/// since responding again would only revert. A failed lookup lets the task through
+pub async fn already_responded<P: Provider>(
+    provider: &P,
+    operator: Address,
+    swap_manager: Address,
+    task_index: u32,
+) -> bool {
+    let swap_manager = SwapManager::new(swap_manager, provider);
+    let call = swap_manager.allTaskResponses(operator, task_index);
+    match time_rpc("allTaskResponses", call.call().into_future()).await {
+        Ok(response) if !response._0.is_empty() => {
+            info!(task_index, "Task already responded to, skipping");
+            record_task_deduplicated("responded");
+            true
+        }
+        Ok(_) => false,
+        Err(e) => {
+            warn!(task_index, error = %e, "Failed to look for an existing response");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replicas_need_a_shared_store() {
+        let mut config = HaConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut store = StoreConfig::default();
+        assert!(config.validate(&store).is_err());
+        store.backend = StoreBackend::Postgres;
+        config.validate(&store).unwrap();
+
+        config.enabled = false;
+        config.leader_election = true;
+        assert!(config.validate(&store).is_err());
+        assert_eq!(task_lock(7), "task/7");
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{eip191_hash_message, keccak256, Address, Bytes, B256},
+    signers::Signature,
+    sol_types::SolValue,
+};
+use prost::Message;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::aggregator::AggregatorClient;
+use crate::bls::BlsKey;
+use crate::error::{AggregatorError, ConfigError, SignerError};
+use crate::signer::OperatorSigner;
+use crate::swap_payload::SWAP_PAYLOAD_PREFIX;
+
+/// Types generated from `proto/aggregator.proto`
+#[allow(missing_docs, unreachable_pub, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("swapmanager.aggregator.v1");
+}
+
+/// Path operators post their [`Hello`] to when they connect to the aggregator
+pub const HELLO_PATH: &str = "/v1/hello";
+
+/// Content type of the protobuf encoded [`HELLO_PATH`] requests and replies
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// Version of the handshake protocol, bumped on changes the other side can't read
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Version of this software, announced in the [`Hello`]
+pub const SOFTWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Kind of task, telling the aggregator which responses an operator handles
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskType {
+    /// Tasks named with plain text
+    Plain,
+    /// Tasks carrying a swap payload
+    Swap,
+}
+
+impl TaskType {
+    /// Every task type
+    pub const ALL: [Self; 2] = [Self::Plain, Self::Swap];
+
+    /// Type of the task named `task_name`
+    pub fn of(task_name: &str) -> Self {
+        if task_name.starts_with(SWAP_PAYLOAD_PREFIX) {
+            Self::Swap
+        } else {
+            Self::Plain
+        }
+    }
+
+    /// Name used in the config and the logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Swap => "swap",
+        }
+    }
+}
+
+impl fmt::Display for TaskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TaskType {
+    type Err = ConfigError;
+
+    fn from_str(task_type: &str) -> Result<Self, Self::Err> {
+        match task_type {
+            "plain" => Ok(Self::Plain),
+            "swap" => Ok(Self::Swap),
+            other => Err(ConfigError::Invalid(format!("unknown task type {other}"))),
+        }
+    }
+}
+
+impl From<TaskType> for proto::TaskType {
+    fn from(task_type: TaskType) -> Self {
+        match task_type {
+            TaskType::Plain => Self::Plain,
+            TaskType::Swap => Self::Swap,
+        }
+    }
+}
+
+impl TryFrom<i32> for TaskType {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match proto::TaskType::try_from(value) {
+            Ok(proto::TaskType::Plain) => Ok(Self::Plain),
+            Ok(proto::TaskType::Swap) => Ok(Self::Swap),
+            _ => Err(format!("unknown task type {value}")),
+        }
+    }
+}
+
+/// Task types of the protobuf enum values `values`, failing on unknown ones
+pub fn task_types_from_proto(values: &[i32]) -> Result<Vec<TaskType>, String> {
+    values
+        .iter()
+        .map(|&value| TaskType::try_from(value))
+        .collect()
+}
+
+/// Protobuf enum values of `task_types`
+pub fn task_types_to_proto(task_types: &[TaskType]) -> Vec<i32> {
+    task_types
+        .iter()
+        .map(|&task_type| proto::TaskType::from(task_type) as i32)
+        .collect()
+}
+
+/// `major.minor.patch` version of the software, pre-release and build suffixes ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SoftwareVersion {
+    /// Incompatible changes
+    pub major: u64,
+    /// Compatible features
+    pub minor: u64,
+    /// Fixes
+    pub patch: u64,
+}
+
+impl FromStr for SoftwareVersion {
+    type Err = ConfigError;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConfigError::Invalid(format!("invalid version {version}, expected x.y.z"));
+        let core = version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default();
+        let mut parts = core.split('.').map(|part| part.parse::<u64>());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for SoftwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Identity of an operator signed by its key, sent when it connects to the aggregator.
+///
+/// The latest one by `timestamp` wins, so an operator restarted on a new version replaces
+/// what the aggregator knew of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hello {
+    /// Handshake protocol the operator speaks
+    pub protocol_version: u32,
+    /// Operator saying hello
+    pub operator: Address,
+    /// BLS public key of the operator in `E([x,y])` notation, if it has one
+    pub bls_public_key: Option<String>,
+    /// Version of the operator software
+    pub software_version: String,
+    /// Task types the operator attests to
+    pub task_types: Vec<TaskType>,
+    /// Unix time of the hello in seconds, so an older one can't be replayed
+    pub timestamp: u64,
+    /// 65 byte ECDSA signature over the EIP-191 digest of [`Hello::hash`]
+    pub signature: Bytes,
+}
+
+impl Hello {
+    /// Hello of the key of `signer` handling `task_types`, timestamped now
+    pub async fn sign(
+        signer: &OperatorSigner,
+        bls_key: Option<&BlsKey>,
+        task_types: &[TaskType],
+    ) -> Result<Self, SignerError> {
+        let mut hello = Self {
+            protocol_version: PROTOCOL_VERSION,
+            operator: signer.address(),
+            bls_public_key: bls_key.map(BlsKey::public_key_string),
+            software_version: SOFTWARE_VERSION.to_string(),
+            task_types: task_types.to_vec(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            signature: Bytes::new(),
+        };
+        let signature = signer.sign_message(hello.hash().as_slice()).await?;
+        hello.signature = signature.as_bytes().into();
+        Ok(hello)
+    }
+
+    /// Hash of the signed fields, signed with the EIP-191 prefix
+    pub fn hash(&self) -> B256 {
+        keccak256(
+            (
+                self.protocol_version,
+                self.operator,
+                self.bls_public_key.clone().unwrap_or_default(),
+                self.software_version.clone(),
+                task_types_to_proto(&self.task_types)
+                    .into_iter()
+                    .map(|value| value as u32)
+                    .collect::<Vec<u32>>(),
+                self.timestamp,
+            )
+                .abi_encode(),
+        )
+    }
+
+    /// Check that the version parses, task types are given and the signature recovers to
+    /// `operator`
+    pub fn verify(&self) -> Result<(), String> {
+        self.software_version
+            .parse::<SoftwareVersion>()
+            .map_err(|e| e.to_string())?;
+        if self.task_types.is_empty() {
+            return Err("hello declares no task type".to_string());
+        }
+        let signature = Signature::from_raw(&self.signature)
+            .map_err(|e| format!("malformed hello signature: {e}"))?;
+        let signer = signature
+            .recover_address_from_prehash(&eip191_hash_message(self.hash()))
+            .map_err(|e| format!("unrecoverable hello signature: {e}"))?;
+        if signer != self.operator {
+            return Err(format!(
+                "hello signature of {signer} does not match operator {}",
+                self.operator
+            ));
+        }
+        Ok(())
+    }
+
+    /// Protobuf encoding of the hello
+    pub fn encode(&self) -> Vec<u8> {
+        proto::Hello {
+            protocol_version: self.protocol_version,
+            operator: self.operator.to_string(),
+            bls_public_key: self.bls_public_key.clone().unwrap_or_default(),
+            software_version: self.software_version.clone(),
+            task_types: task_types_to_proto(&self.task_types),
+            timestamp: self.timestamp,
+            signature: self.signature.to_vec(),
+        }
+        .encode_to_vec()
+    }
+
+    /// Hello encoded in `bytes` by [`Self::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let hello = proto::Hello::decode(bytes).map_err(|e| format!("malformed hello: {e}"))?;
+        Ok(Self {
+            protocol_version: hello.protocol_version,
+            operator: hello
+                .operator
+                .parse()
+                .map_err(|e| format!("invalid hello operator {}: {e}", hello.operator))?,
+            bls_public_key: Some(hello.bls_public_key).filter(|key| !key.is_empty()),
+            software_version: hello.software_version,
+            task_types: task_types_from_proto(&hello.task_types)?,
+            timestamp: hello.timestamp,
+            signature: hello.signature.into(),
+        })
+    }
+}
+
+/// Say hello to the aggregator at `aggregator_url` as the key of `signer`, returning the task
+/// types the aggregator takes from the operator among `task_types`
+pub async fn say_hello(
+    aggregator_url: &str,
+    signer: &OperatorSigner,
+    bls_key: Option<&BlsKey>,
+    task_types: &[TaskType],
+) -> Result<Vec<TaskType>, AggregatorError> {
+    let hello = Hello::sign(signer, bls_key, task_types).await?;
+    let reply = AggregatorClient::new(aggregator_url)?.hello(&hello).await?;
+    let routed = task_types_from_proto(&reply.task_types).map_err(AggregatorError::InvalidHello)?;
+    let dropped: Vec<_> = task_types
+        .iter()
+        .filter(|task_type| !routed.contains(task_type))
+        .map(|task_type| task_type.as_str())
+        .collect();
+    if !dropped.is_empty() {
+        warn!(
+            ?dropped,
+            "The aggregator takes no responses of some task types, not handling them"
+        );
+    }
+    info!(
+        operator = %signer.address(),
+        version = SOFTWARE_VERSION,
+        task_types = ?routed.iter().map(|task_type| task_type.as_str()).collect::<Vec<_>>(),
+        "Said hello to the aggregator"
+    );
+    Ok(routed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+
+    #[tokio::test]
+    async fn test_hellos_round_trip_through_protobuf_and_verify() {
+        let signer = OperatorSigner::new(PrivateKeySigner::random());
+        let bls_key = BlsKey::generate();
+        let hello = Hello::sign(&signer, Some(&bls_key), &TaskType::ALL)
+            .await
+            .unwrap();
+        hello.verify().unwrap();
+        let decoded = Hello::decode(&hello.encode()).unwrap();
+        assert_eq!(decoded, hello);
+        decoded.verify().unwrap();
+
+        let downgraded = Hello {
+            software_version: "0.0.1".to_string(),
+            ..hello.clone()
+        };
+        assert!(downgraded.verify().is_err());
+        let narrowed = Hello {
+            task_types: vec![TaskType::Plain],
+            ..hello
+        };
+        assert!(narrowed.verify().is_err());
+        assert!(Hello::decode(b"\xff\xff").is_err());
+    }
+
+    #[test]
+    fn test_versions_compare_by_their_numbers() {
+        let version = |version: &str| version.parse::<SoftwareVersion>().unwrap();
+        assert!(version("0.10.0") > version("0.9.7"));
+        assert!(version("1.0.0-rc.1") == version("v1.0.0"));
+        assert_eq!(version("1.2.3").to_string(), "1.2.3");
+        SOFTWARE_VERSION.parse::<SoftwareVersion>().unwrap();
+        for invalid in ["1.2", "1.2.3.4", "a.b.c", ""] {
+            assert!(invalid.parse::<SoftwareVersion>().is_err(), "{invalid}");
+        }
+        assert_eq!(TaskType::of("QuickFox12"), TaskType::Plain);
+        assert_eq!(TaskType::of("swap:00"), TaskType::Swap);
+    }
+}
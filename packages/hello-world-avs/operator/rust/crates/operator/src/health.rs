@@ -0,0 +1,300 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use alloy::providers::Provider;
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use eigensdk::common::SdkProvider;
+use serde::{Deserialize, Serialize};
+use tokio::task::AbortHandle;
+use tracing::info;
+
+use crate::error::ConfigError;
+use crate::prometheus::{pending_txs, time_rpc};
+use crate::services::Services;
+use crate::signer::OperatorSigner;
+
+/// Path of the liveness probe
+pub const HEALTHZ_PATH: &str = "/healthz";
+/// Path of the readiness probe
+pub const READYZ_PATH: &str = "/readyz";
+
+/// How long an RPC or signer check may take before it counts as failed
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a signer check result is reused, so probes don't hit a remote signer each time
+const SIGNER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Message signed to check the signer, prefixed so it can't be a transaction
+const SIGNER_PROBE: &[u8] = b"swap manager operator health check";
+
+/// The `[health]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    /// Address serving `/healthz` and `/readyz`, disabled if unset (`HEALTH_ADDR`)
+    pub listen_addr: Option<SocketAddr>,
+    /// Most blocks the task source may be behind the head while ready
+    pub max_block_lag: u64,
+    /// Most transactions awaiting a receipt while ready
+    pub max_pending_txs: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: None,
+            max_block_lag: 32,
+            max_pending_txs: 16,
+        }
+    }
+}
+
+/// Progress a task source reports to the health endpoints
+#[derive(Debug, Default)]
+pub struct SourceHealth {
+    ws_listener: Option<AbortHandle>,
+    synced_block: AtomicU64,
+}
+
+impl SourceHealth {
+    /// Health of a WebSocket source forwarding the tasks of `listener`
+    pub fn ws(listener: AbortHandle) -> Self {
+        Self {
+            ws_listener: Some(listener),
+            synced_block: AtomicU64::new(0),
+        }
+    }
+
+    /// Record that every block up to `block` was scanned for tasks
+    pub fn set_synced_block(&self, block: u64) {
+        self.synced_block.fetch_max(block, Ordering::Relaxed);
+    }
+
+    /// Whether the WebSocket subscription still runs, `None` for other sources
+    pub fn ws_subscribed(&self) -> Option<bool> {
+        self.ws_listener
+            .as_ref()
+            .map(|listener| !listener.is_finished())
+    }
+
+    /// Last block scanned for tasks, `None` before the first scan
+    pub fn synced_block(&self) -> Option<u64> {
+        match self.synced_block.load(Ordering::Relaxed) {
+            0 => None,
+            block => Some(block),
+        }
+    }
+}
+
+/// Result of a health probe, served as JSON
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct HealthReport {
+    /// Whether every check passed
+    pub ok: bool,
+    /// Checks that failed
+    pub failures: Vec<String>,
+    /// Chain head seen through the RPC endpoint, unset if unreachable or not checked
+    pub head_block: Option<u64>,
+    /// Whether the WebSocket subscription is alive, unset for the polling source
+    pub ws_subscribed: Option<bool>,
+    /// Last block the task source scanned
+    pub synced_block: Option<u64>,
+    /// Blocks between the head and the last scanned block
+    pub block_lag: Option<u64>,
+    /// Block of the last task handled
+    pub processed_block: Option<u64>,
+    /// Transactions awaiting a receipt
+    pub pending_txs: u64,
+    /// Whether the signer answered, unset if not checked
+    pub signer_available: Option<bool>,
+}
+
+impl HealthReport {
+    fn fail(&mut self, failure: String) {
+        self.failures.push(failure);
+    }
+
+    fn finish(mut self) -> Self {
+        self.ok = self.failures.is_empty();
+        self
+    }
+}
+
+/// State behind the health endpoints of a running operator
+#[derive(Debug)]
+pub struct HealthState {
+    provider: SdkProvider,
+    signer: OperatorSigner,
+    source: Arc<SourceHealth>,
+    processed_block: AtomicU64,
+    signer_check: Mutex<Option<(Instant, bool)>>,
+    config: HealthConfig,
+}
+
+impl HealthState {
+    /// Health of an operator reading the chain at `rpc_url` through the RPC stack of
+    /// `services`, signing with `signer` and discovering tasks through a source reporting
+    /// to `source`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        signer: OperatorSigner,
+        source: Arc<SourceHealth>,
+        config: &HealthConfig,
+    ) -> Result<Arc<Self>, ConfigError> {
+        Ok(Arc::new(Self {
+            provider: services.http_provider(rpc_url)?,
+            signer,
+            source,
+            processed_block: AtomicU64::new(0),
+            signer_check: Mutex::new(None),
+            config: config.clone(),
+        }))
+    }
+
+    /// Record that the task created in `block` was handled
+    pub fn set_processed_block(&self, block: u64) {
+        self.processed_block.fetch_max(block, Ordering::Relaxed);
+        self.source.set_synced_block(block);
+    }
+
+    /// Liveness: the checks that need nothing outside the process
+    pub fn liveness(&self) -> HealthReport {
+        let mut report = HealthReport {
+            ws_subscribed: self.source.ws_subscribed(),
+            synced_block: self.source.synced_block(),
+            processed_block: match self.processed_block.load(Ordering::Relaxed) {
+                0 => None,
+                block => Some(block),
+            },
+            pending_txs: pending_txs(),
+            ..Default::default()
+        };
+        if report.ws_subscribed == Some(false) {
+            report.fail("WebSocket subscription closed".to_string());
+        }
+        report.finish()
+    }
+
+    /// Readiness: liveness plus the RPC endpoint, block lag, transaction backlog and signer
+    pub async fn readiness(&self) -> HealthReport {
+        let mut report = self.liveness();
+        match tokio::time::timeout(
+            CHECK_TIMEOUT,
+            time_rpc("eth_blockNumber", self.provider.get_block_number()),
+        )
+        .await
+        {
+            Ok(Ok(head)) => {
+                report.head_block = Some(head);
+                // A live subscription receives every task as it is mined
+                if report.ws_subscribed != Some(true) {
+                    let lag = head.saturating_sub(report.synced_block.unwrap_or_default());
+                    report.block_lag = Some(lag);
+                    if lag > self.config.max_block_lag {
+                        report.fail(format!(
+                            "task source {lag} blocks behind the head, at most {} allowed",
+                            self.config.max_block_lag
+                        ));
+                    }
+                }
+            }
+            Ok(Err(e)) => report.fail(format!("RPC endpoint unreachable: {e}")),
+            Err(_) => report.fail("RPC endpoint timed out".to_string()),
+        }
+        if report.pending_txs > self.config.max_pending_txs {
+            report.fail(format!(
+                "{} transactions awaiting a receipt, at most {} allowed",
+                report.pending_txs, self.config.max_pending_txs
+            ));
+        }
+        let signer_available = self.signer_available().await;
+        report.signer_available = Some(signer_available);
+        if !signer_available {
+            report.fail("signer unavailable".to_string());
+        }
+        report.finish()
+    }
+
+    /// Whether the signer signs a probe message, reusing a recent result
+    async fn signer_available(&self) -> bool {
+        let cached = *self
+            .signer_check
+            .lock()
+            .expect("signer check lock poisoned");
+        if let Some((checked_at, available)) = cached {
+            if checked_at.elapsed() < SIGNER_CHECK_INTERVAL {
+                return available;
+            }
+        }
+        let available = matches!(
+            tokio::time::timeout(CHECK_TIMEOUT, self.signer.sign_message(SIGNER_PROBE)).await,
+            Ok(Ok(_))
+        );
+        *self
+            .signer_check
+            .lock()
+            .expect("signer check lock poisoned") = Some((Instant::now(), available));
+        available
+    }
+}
+
+async fn healthz(State(state): State<Arc<HealthState>>) -> (StatusCode, Json<HealthReport>) {
+    respond(state.liveness())
+}
+
+async fn readyz(State(state): State<Arc<HealthState>>) -> (StatusCode, Json<HealthReport>) {
+    respond(state.readiness().await)
+}
+
+fn respond(report: HealthReport) -> (StatusCode, Json<HealthReport>) {
+    let status = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+/// Serve `/healthz` and `/readyz` on `addr`, along the other operator endpoints of `routes`
+pub async fn serve(
+    state: Arc<HealthState>,
+    addr: SocketAddr,
+    routes: Router,
+) -> std::io::Result<()> {
+    let app = Router::new()
+        .route(HEALTHZ_PATH, get(healthz))
+        .route(READYZ_PATH, get(readyz))
+        .with_state(state)
+        .merge(routes);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(%addr, "Health endpoints listening");
+    axum::serve(listener, app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_closed_subscription_fails_liveness() {
+        let listener = tokio::spawn(std::future::pending::<()>());
+        let source = Arc::new(SourceHealth::ws(listener.abort_handle()));
+        assert_eq!(source.ws_subscribed(), Some(true));
+
+        listener.abort();
+        let _ = listener.await;
+        assert_eq!(source.ws_subscribed(), Some(false));
+
+        let polling = SourceHealth::default();
+        assert_eq!(polling.ws_subscribed(), None);
+        assert_eq!(polling.synced_block(), None);
+        polling.set_synced_block(12);
+        polling.set_synced_block(7);
+        assert_eq!(polling.synced_block(), Some(12));
+    }
+}
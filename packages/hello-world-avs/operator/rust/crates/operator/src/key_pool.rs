@@ -0,0 +1,194 @@
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use eigensdk::common::SdkSigner;
+use serde::Deserialize;
+
+use crate::error::{ConfigError, SignerError};
+use crate::nonce_manager::NonceManager;
+use crate::signer::OperatorSigner;
+
+/// Funded keys sending transactions in turn with the signer, the `[key_pool]` config section
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyPoolConfig {
+    /// Files holding the hex encoded private keys of the pool, the signer sends every
+    /// transaction alone if empty (`KEY_POOL_FILES`, comma separated)
+    pub private_key_files: Vec<PathBuf>,
+}
+
+impl KeyPoolConfig {
+    /// Check that no key file is listed twice
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (i, file) in self.private_key_files.iter().enumerate() {
+            if self.private_key_files[..i].contains(file) {
+                return Err(ConfigError::Invalid(format!(
+                    "key_pool.private_key_files lists {} twice",
+                    file.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Signers of the keys of the pool, without the signer of the config
+    pub fn signers(&self) -> Result<Vec<OperatorSigner>, ConfigError> {
+        self.private_key_files
+            .iter()
+            .map(|path| {
+                let key = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+                    what: "key pool file",
+                    path: path.clone(),
+                    source,
+                })?;
+                let key = PrivateKeySigner::from_str(key.trim()).map_err(|e| {
+                    ConfigError::Invalid(format!("invalid key in {}: {e}", path.display()))
+                })?;
+                Ok(OperatorSigner::new(key))
+            })
+            .collect()
+    }
+}
+
+/// Private relay the transactions of a key are sent to, see
+/// [`PendingTxWatcher::send_private`](crate::pending_tx::PendingTxWatcher::send_private)
+#[derive(Debug)]
+pub struct KeyRelay {
+    /// Provider signing the transactions with the key and sending them to the relay
+    pub provider: SdkSigner,
+    /// Time a transaction gets to be mined through the relay before going public
+    pub fallback_after: Duration,
+}
+
+/// Key of a [`KeyPool`], with a provider signing its transactions and nonces of its own
+#[derive(Debug)]
+pub struct PoolKey {
+    signer: OperatorSigner,
+    provider: SdkSigner,
+    nonces: NonceManager,
+    relay: Option<KeyRelay>,
+}
+
+impl PoolKey {
+    /// Address sending the transactions
+    pub fn address(&self) -> Address {
+        self.nonces.address()
+    }
+
+    /// Provider signing the transactions with the key
+    pub fn provider(&self) -> &SdkSigner {
+        &self.provider
+    }
+
+    /// Nonces of the key
+    pub fn nonces(&self) -> &NonceManager {
+        &self.nonces
+    }
+
+    /// Private relay the transactions of the key go to, the public mempool if `None`
+    pub fn relay(&self) -> Option<&KeyRelay> {
+        self.relay.as_ref()
+    }
+}
+
+/// Keys handed out round-robin, so transactions aren't queued behind the nonce sequence of
+/// a single account
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Vec<PoolKey>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Pool of `signer` followed by `extra`, sending through `rpc_url`. Keys listed twice
+    /// are kept once, they would share a nonce sequence otherwise
+    pub fn new(
+        rpc_url: &str,
+        signer: &OperatorSigner,
+        extra: &[OperatorSigner],
+    ) -> Result<Self, SignerError> {
+        let mut keys: Vec<PoolKey> = Vec::with_capacity(extra.len() + 1);
+        for signer in std::iter::once(signer).chain(extra) {
+            if keys.iter().any(|key| key.address() == signer.address()) {
+                continue;
+            }
+            keys.push(PoolKey {
+                signer: signer.clone(),
+                provider: signer.provider(rpc_url)?,
+                nonces: NonceManager::new(signer.address()),
+                relay: None,
+            });
+        }
+        Ok(Self {
+            keys,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Pool of `signer` alone
+    pub fn single(rpc_url: &str, signer: &OperatorSigner) -> Result<Self, SignerError> {
+        Self::new(rpc_url, signer, &[])
+    }
+
+    /// Send the transactions of every key to the private relay `relay_url`, and to the
+    /// public mempool too once they weren't mined within `fallback_after`
+    pub fn private_relay(
+        mut self,
+        relay_url: &str,
+        fallback_after: Duration,
+    ) -> Result<Self, SignerError> {
+        for key in &mut self.keys {
+            key.relay = Some(KeyRelay {
+                provider: key.signer.provider(relay_url)?,
+                fallback_after,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Key sending the next transaction
+    pub fn next_key(&self) -> &PoolKey {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.keys.len();
+        &self.keys[index]
+    }
+
+    /// Key of the signer the pool was created with, for the calls needing no nonce
+    pub fn signer_key(&self) -> &PoolKey {
+        &self.keys[0]
+    }
+
+    /// Addresses of the keys, the signer first
+    pub fn addresses(&self) -> Vec<Address> {
+        self.keys.iter().map(PoolKey::address).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_rotates_through_distinct_keys() {
+        let signer = OperatorSigner::new(PrivateKeySigner::random());
+        let extra = [
+            OperatorSigner::new(PrivateKeySigner::random()),
+            signer.clone(),
+            OperatorSigner::new(PrivateKeySigner::random()),
+        ];
+        let pool = KeyPool::new("http://localhost:8545", &signer, &extra).unwrap();
+        let addresses = pool.addresses();
+        assert_eq!(
+            addresses,
+            [signer.address(), extra[0].address(), extra[2].address()]
+        );
+        let sent: Vec<_> = (0..6).map(|_| pool.next_key().address()).collect();
+        assert_eq!(sent[..3], addresses[..]);
+        assert_eq!(sent[3..], addresses[..]);
+        assert_eq!(pool.signer_key().address(), signer.address());
+    }
+}
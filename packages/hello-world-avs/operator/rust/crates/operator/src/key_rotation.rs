@@ -0,0 +1,329 @@
+use std::path::{Path, PathBuf};
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
+use tracing::info;
+
+use crate::config::OperatorConfig;
+use crate::control::TaskOutcome;
+use crate::error::{ChainError, RotationError, SignerError};
+use crate::gas::estimate_call_gas;
+use crate::revert::decode_revert;
+use crate::services::Services;
+use crate::signer::OperatorSigner;
+use crate::task_store::connect_task_store;
+
+/// Settings of `operator rotate-key`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RotationConfig {
+    /// Seconds after the rotation before the old key can be retired, long enough to see the
+    /// new key respond to tasks
+    pub overlap_secs: u64,
+    /// File recording the rotation in progress
+    pub state_file: PathBuf,
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            overlap_secs: 86_400,
+            state_file: PathBuf::from("key-rotation.json"),
+        }
+    }
+}
+
+/// Rotation of the signing key of an operator, as recorded in the state file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyRotation {
+    /// EigenLayer operator whose signing key is rotated
+    pub operator: Address,
+    /// Signing key replaced
+    pub old_key: Address,
+    /// Signing key taking over
+    pub new_key: Address,
+    /// Keystore of the new key
+    pub keystore: PathBuf,
+    /// updateOperatorSigningKey transaction
+    pub tx_hash: Option<B256>,
+    /// Tasks of the task store moved to the new key
+    pub moved_tasks: u64,
+    /// Unix time the new key took over
+    pub rotated_at: i64,
+    /// Unix time from which the old key can be retired
+    pub retire_after: i64,
+    /// Unix time the old key was retired
+    pub retired_at: Option<i64>,
+}
+
+/// Where a [`KeyRotation`] stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationPhase {
+    /// Both keys are kept until the overlap is over
+    Overlap {
+        /// Seconds left of the overlap
+        remaining_secs: u64,
+    },
+    /// The old key can be retired
+    Retirable,
+    /// The old key was retired, another rotation can start
+    Retired,
+}
+
+impl KeyRotation {
+    /// Rotation recorded at `path`, `None` if no rotation was started
+    pub fn load(path: &Path) -> Result<Option<Self>, RotationError> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(RotationError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|source| RotationError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Record the rotation at `path`
+    pub fn save(&self, path: &Path) -> Result<(), RotationError> {
+        let data = serde_json::to_string_pretty(self).map_err(|source| RotationError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        std::fs::write(path, data + "\n").map_err(|source| RotationError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Phase of the rotation at unix time `now`
+    pub fn phase(&self, now: i64) -> RotationPhase {
+        if self.retired_at.is_some() {
+            RotationPhase::Retired
+        } else if now < self.retire_after {
+            RotationPhase::Overlap {
+                remaining_secs: self.retire_after.abs_diff(now),
+            }
+        } else {
+            RotationPhase::Retirable
+        }
+    }
+}
+
+/// Key of the keystore at `path`, unlocked with `password`, or a new random key encrypted
+/// there with it if there is none
+pub fn load_or_create_key(path: &Path, password: &str) -> Result<PrivateKeySigner, RotationError> {
+    if path.exists() {
+        return PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+            SignerError::Decrypt {
+                what: "keystore",
+                path: path.to_path_buf(),
+                source: e.into(),
+            }
+            .into()
+        });
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().and_then(|name| name.to_str());
+    let (key, _) = PrivateKeySigner::new_keystore(dir, &mut rand08::thread_rng(), password, name)
+        .map_err(|e| SignerError::Write {
+        what: "keystore",
+        path: path.to_path_buf(),
+        source: std::io::Error::other(e),
+    })?;
+    info!(key = %key.address(), keystore = %path.display(), "New signing key generated");
+    Ok(key)
+}
+
+/// Make `new_key` the signing key of the operator `signer` in the stake registry and hand
+/// the task store rows of the old signing key over to it, retiring the old key is left to
+/// [`retire_old_key`] once `rotation.overlap_secs` have passed. A dry run only simulates
+/// updateOperatorSigningKey and leaves the task store untouched
+pub async fn rotate_signing_key(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: &OperatorSigner,
+    stake_registry: Address,
+    new_key: Address,
+    keystore: &Path,
+    dry_run: bool,
+) -> Result<KeyRotation, RotationError> {
+    let operator = signer.address();
+    let provider = services.http_provider(&config.rpc_url)?;
+    let registry = ECDSAStakeRegistry::new(stake_registry, &provider);
+    if !registry.operatorRegistered(operator).call().await?._0 {
+        return Err(RotationError::NotRegistered { operator });
+    }
+    let old_key = registry
+        .getLatestOperatorSigningKey(operator)
+        .call()
+        .await?
+        ._0;
+
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(&config.rpc_url)?;
+    let writer = ECDSAStakeRegistry::new(stake_registry, &pr);
+    let update = writer.updateOperatorSigningKey(new_key);
+    let tx_hash = if dry_run {
+        update
+            .call()
+            .await
+            .map_err(|e| decode_revert("updateOperatorSigningKey", e.into()))?;
+        info!(%operator, %old_key, %new_key, "Dry run, updateOperatorSigningKey would succeed");
+        None
+    } else {
+        let gas = estimate_call_gas(
+            services.gas_limit_multiplier(),
+            "updateOperatorSigningKey",
+            &update,
+        )
+        .await?;
+        let pending = update.gas(gas).send().await?;
+        services.audit_transaction(
+            operator,
+            "updateOperatorSigningKey",
+            None,
+            *pending.tx_hash(),
+        );
+        let receipt = pending.get_receipt().await?;
+        let tx_hash = receipt.transaction_hash;
+        if !receipt.status() {
+            return Err(ChainError::Reverted {
+                method: "updateOperatorSigningKey",
+                tx_hash,
+            }
+            .into());
+        }
+        info!(%operator, %old_key, %new_key, %tx_hash, "Operator signing key updated");
+        Some(tx_hash)
+    };
+
+    let now = Utc::now().timestamp();
+    let mut moved_tasks = 0;
+    if !dry_run {
+        if let Some(store) = connect_task_store(&config.store, old_key).await? {
+            moved_tasks = store.transfer(new_key).await?;
+            store.close().await;
+            info!(%old_key, %new_key, moved_tasks, "Task store handed over to the new key");
+        }
+    }
+    Ok(KeyRotation {
+        operator,
+        old_key,
+        new_key,
+        keystore: keystore.to_path_buf(),
+        tx_hash,
+        moved_tasks,
+        rotated_at: now,
+        retire_after: now.saturating_add_unsigned(config.rotation.overlap_secs),
+        retired_at: None,
+    })
+}
+
+/// Retire the old key of `rotation` at unix time `now`, once the overlap is over, the
+/// stake registry still holds the new key and, with a task store, the new key responded
+/// to a task since the rotation. A dry run runs the checks without recording the retirement
+pub async fn retire_old_key(
+    config: &OperatorConfig,
+    services: &Services,
+    rotation: &mut KeyRotation,
+    stake_registry: Address,
+    now: i64,
+    dry_run: bool,
+) -> Result<(), RotationError> {
+    if let RotationPhase::Overlap { remaining_secs } = rotation.phase(now) {
+        return Err(RotationError::Overlap { remaining_secs });
+    }
+    let provider = services.http_provider(&config.rpc_url)?;
+    let current = ECDSAStakeRegistry::new(stake_registry, &provider)
+        .getLatestOperatorSigningKey(rotation.operator)
+        .call()
+        .await?
+        ._0;
+    if current != rotation.new_key {
+        return Err(RotationError::KeyMismatch {
+            operator: rotation.operator,
+            expected: rotation.new_key,
+            actual: current,
+        });
+    }
+    if let Some(store) = connect_task_store(&config.store, rotation.new_key).await? {
+        let tasks = store
+            .export(rotation.rotated_at, now.saturating_add(1))
+            .await?;
+        store.close().await;
+        let responded = [TaskOutcome::Responded, TaskOutcome::Aggregated].map(TaskOutcome::as_str);
+        if !tasks.iter().any(|task| {
+            task.outcome
+                .is_some_and(|outcome| responded.contains(&outcome))
+        }) {
+            return Err(RotationError::Unproven {
+                new_key: rotation.new_key,
+            });
+        }
+    }
+    if !dry_run {
+        rotation.retired_at = Some(now);
+    }
+    info!(
+        operator = %rotation.operator,
+        old_key = %rotation.old_key,
+        new_key = %rotation.new_key,
+        dry_run,
+        "Old signing key retired"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_phases_and_state_file() {
+        let mut rotation = KeyRotation {
+            operator: Address::repeat_byte(1),
+            old_key: Address::repeat_byte(1),
+            new_key: Address::repeat_byte(2),
+            keystore: PathBuf::from("operator-2.json"),
+            tx_hash: Some(B256::repeat_byte(3)),
+            moved_tasks: 7,
+            rotated_at: 1_000,
+            retire_after: 1_600,
+            retired_at: None,
+        };
+        assert_eq!(
+            rotation.phase(1_100),
+            RotationPhase::Overlap {
+                remaining_secs: 500
+            }
+        );
+        assert_eq!(rotation.phase(1_600), RotationPhase::Retirable);
+
+        let path = std::env::temp_dir().join(format!("key-rotation-{}.json", std::process::id()));
+        assert_eq!(KeyRotation::load(&path).unwrap(), None);
+        rotation.retired_at = Some(1_700);
+        rotation.save(&path).unwrap();
+        let loaded = KeyRotation::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, rotation);
+        assert_eq!(loaded.phase(1_800), RotationPhase::Retired);
+        std::fs::remove_file(path).unwrap();
+    }
+}
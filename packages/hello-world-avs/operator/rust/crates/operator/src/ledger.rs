@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use alloy::{
+    consensus::SignableTransaction,
+    network::TxSigner,
+    primitives::{Address, ChainId, B256},
+    signers::{
+        ledger::{HDPath, LedgerSigner},
+        Result as SignerResult, Signature, Signer,
+    },
+};
+use async_trait::async_trait;
+
+use crate::error::SignerError;
+
+/// Signer holding the connection to a Ledger device, each signature is approved on it.
+///
+/// The device signs transactions and personal messages but not raw digests, so the
+/// registration signature needs another backend. Clones share the connection, and with
+/// it the device, which signs one payload at a time.
+#[derive(Debug, Clone)]
+pub struct LedgerDevice {
+    signer: Arc<LedgerSigner>,
+}
+
+impl LedgerDevice {
+    /// Connect to the first Ledger plugged in, with the Ethereum app open, and read the
+    /// address of `path`
+    pub async fn connect(path: HDPath) -> Result<Self, SignerError> {
+        let derivation = path.to_string();
+        // No chain id pinned, transactions are signed for the chain of the provider
+        let signer = LedgerSigner::new(path, None)
+            .await
+            .map_err(|source| SignerError::Ledger {
+                derivation,
+                source: Box::new(source),
+            })?;
+        Ok(Self {
+            signer: Arc::new(signer),
+        })
+    }
+}
+
+#[async_trait]
+impl TxSigner<Signature> for LedgerDevice {
+    fn address(&self) -> Address {
+        TxSigner::address(self.signer.as_ref())
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> SignerResult<Signature> {
+        self.signer.sign_transaction(tx).await
+    }
+}
+
+#[async_trait]
+impl Signer for LedgerDevice {
+    async fn sign_hash(&self, hash: &B256) -> SignerResult<Signature> {
+        self.signer.sign_hash(hash).await
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> SignerResult<Signature> {
+        self.signer.sign_message(message).await
+    }
+
+    fn address(&self) -> Address {
+        Signer::address(self.signer.as_ref())
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.signer.chain_id()
+    }
+
+    /// Only pins the chain id while the connection isn't shared with a clone yet
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        if let Some(signer) = Arc::get_mut(&mut self.signer) {
+            signer.set_chain_id(chain_id);
+        }
+    }
+}
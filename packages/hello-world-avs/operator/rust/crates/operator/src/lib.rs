@@ -1,21 +1,190 @@
 //! Start creating tasks and respond appropriately
 //! testing utils
-/// Challenger struct for monitoring task completions and performing slashing
+extern crate self as swap_manager_avs_operator;
+
+/// Drift checking and regeneration of the contract bindings from the compiled ABIs
+pub mod abi;
+/// Collects signed task responses over HTTP and submits them once the stake quorum is met
+pub mod aggregator;
+/// Rate limited Slack and Discord alerts and the responder stall watchdog
+pub mod alerting;
+/// Endpoint telling whether the operator would attest to a candidate task response
+pub mod attestation;
+/// Hash-chained audit log of the signed payloads and sent transactions
+pub mod audit;
+/// Signer balance watching with low balance alerts and top ups
+pub mod balance;
+/// Throughput, latency and resource baseline of the response pipeline for `operator bench`
+pub mod bench;
+/// BN254 BLS key generation and eigensdk compatible encrypted keystores
+pub mod bls;
+/// Hourly and daily caps on the fees spent by the transactions
+pub mod budget;
+/// Checks the responses of other operators and challenges the faulty ones
+pub mod challenge;
+/// Run the challenger
 pub mod challenger;
-/// Create createNewTask at regular intervals with random task names
+/// Adversarial tasks of the spammer, for negative testing of the operators and challengers
+pub mod chaos;
+/// Pausing of the RPC calls while too many of them fail
+pub mod circuit_breaker;
+/// Typed operator configuration loaded from a config file and env vars
+pub mod config;
+/// gRPC control API for pausing, inspecting and tuning a running process
+pub mod control;
+/// Gas and fee accounting of the mined transactions and their shutdown summary
+pub mod costs;
+/// Dead-lettering of the tasks whose response can't be landed
+pub mod dead_letter;
+/// ENS names in place of configured addresses and the names of known addresses in the logs
+pub mod ens;
+/// Typed errors of the library, one enum per component
+pub mod error;
+/// Kafka or NATS publishing of task lifecycle events in a stable JSON schema
+pub mod event_bus;
+/// Operators with keys of their own run in one process against a local chain
+pub mod fleet;
+/// Faucet and anvil top ups of low balances on devnets and testnets
+pub mod funding;
+/// Fee strategies choosing the gas price or EIP-1559 fees of sent transactions
+pub mod gas;
+/// Task claims, leader election and pending response checks of replicated operators
+pub mod ha;
+/// Signed operator hello announcing its version and task types to the aggregator
+pub mod handshake;
+/// `/healthz` and `/readyz` probes of the operator
+pub mod health;
+/// Funded keys sending transactions round-robin past the nonce sequence of one account
+pub mod key_pool;
+/// Signing key rotation of `operator rotate-key`
+pub mod key_rotation;
+/// Ledger hardware wallet signer
+pub mod ledger;
+/// Log file output with size or time based rotation and retention
+pub mod log_file;
+/// Tracing subscriber setup with pretty or JSON output
+pub mod logging;
+/// Validation of the EigenLayer operator metadata JSON published at the metadata URI
+pub mod metadata;
+/// Scripted JSON-RPC server for unit tests of the transaction flows without anvil
+pub mod mockrpc;
+/// Sequential nonce allocation shared by concurrent transaction senders
+pub mod nonce_manager;
+/// `operator register`, `operator deregister` and `operator keys` subcommands
+pub mod operator;
+/// Reference prices from Chainlink or Coingecko checking swap tasks before responding
+pub mod oracle;
+/// Signing keys kept in the macOS Keychain or the Windows Credential Manager
+pub mod os_keychain;
+/// Sending transactions and replacing the stuck ones with bumped fees
+pub mod pending_tx;
+/// Startup checks of the chain, the contracts, the signer balance and the registration
+pub mod preflight;
+/// Prometheus metrics endpoint and recording helpers
+pub mod prometheus;
+/// Uniswap V3 QuoterV2 quotes of swap tasks, signed along the task responses
+pub mod quoting;
+/// Token bucket limiting the RPC calls of the process
+pub mod rate_limit;
+/// Idempotent EigenLayer and AVS stake registry (de)registration
+pub mod registration;
+/// Web3Signer compatible remote signer with mutual TLS
+pub mod remote_signer;
+/// Reorg detection re-handling the tasks whose event or response was dropped
+pub mod reorg;
+/// Retry with exponential backoff and jitter for RPC calls and transactions
+pub mod retry;
+/// Decoding of the revert reasons and custom errors of failed calls
+pub mod revert;
+/// Tracking of the operator's EigenLayer rewards and `operator rewards claim`
+pub mod rewards;
+/// Recording of the JSON-RPC traffic to a file and replay of the recorded responses
+pub mod rpc_capture;
+/// Failover, health checks and quorum reads across several HTTP RPC endpoints
+pub mod rpc_pool;
+/// Secrets fetched at startup from HashiCorp Vault or an encrypted file
+pub mod secrets;
+/// Local secrets file encrypted with AES-256-GCM
+pub mod secrets_file;
+/// Embeddable operator configured through `Operator::builder()`
+pub mod service;
+/// Components an operator shares between its tasks, handed to `OperatorBuilder`
+pub mod services;
+/// SIGINT/SIGTERM handling and draining of the work in flight on shutdown
+pub mod shutdown;
+/// ECDSA and BLS signing of task responses
+pub mod signature_scheme;
+/// Signing key backends: raw private keys, encrypted keystores, AWS KMS and Web3Signer
+pub mod signer;
+/// Task latency measured from block timestamps and its SLO report
+pub mod slo;
+/// Tarball of the task store, cursor and nonces moving an operator between hosts
+pub mod snapshot;
+/// Markdown or HTML soak test report of the task store for `spam-tasks report`
+pub mod soak_report;
+/// Operator socket announced to the aggregator and its signed rotations
+pub mod socket;
+/// Create createNewTask at regular intervals with generated task names
 pub mod spam_tasks;
+/// Overflow of the task queue parked in the task store and queued again as it drains
+pub mod spill;
+/// Periodic checks of the operator's weight and ejection risk in the stake registry
+pub mod stake_monitor;
+/// Serve the response aggregator
+pub mod start_aggregator;
 /// Register Operator and monitor for NewTaskCreated event
 pub mod start_operator;
-
-/// Anvil container for testing
-#[cfg(test)]
-pub mod anvil;
+/// Structured swap payloads carried in task names and their generator
+pub mod swap_payload;
+/// `POST /tasks` endpoint queueing ad-hoc tasks for the spammer to create
+pub mod task_api;
+/// Long-lived createNewTask sender
+pub mod task_creator;
+/// CSV and JSON export of the task history kept by the task store
+pub mod task_export;
+/// TaskHandler registry computing the response of each task type, the name echo by default
+pub mod task_handler;
+/// WebSocket listener feeding NewTaskCreated events into a channel
+pub mod task_listener;
+/// Task name generation from configurable word lists and templates
+pub mod task_names;
+/// Validation of task names and swap payloads against a policy before responding
+pub mod task_policy;
+/// Lifecycle of a stored task, printed by `operator tasks show`
+pub mod task_receipt;
+/// TaskResponder trait submitting respondToTask or handing responses to an aggregator
+pub mod task_responder;
+/// TaskSource trait with WebSocket and eth_getLogs polling implementations
+pub mod task_source;
+/// SQLite or Postgres task store recording seen tasks, their responses and the last processed block
+pub mod task_store;
+/// Anvil devnet with the contracts deployed, for integration tests and local development
+pub mod testutils;
+/// Live terminal view of a running operator for `operator top`
+pub mod top;
+/// Traffic models spacing the spammer's task submissions
+pub mod traffic;
+/// HMAC signed webhook notifications of task lifecycle events
+pub mod webhook;
+/// Redis stream work queue splitting the task listener from horizontally scaled responders
+pub mod work_queue;
 
 #[cfg(test)]
 mod tests {
-    use crate::anvil::start_anvil_container;
+    use crate::config::ContractAddresses;
+    use crate::control::TaskOutcome;
+    use crate::gas::{fee_strategy, GasConfig};
+    use crate::pending_tx::PendingTxWatcher;
+    use crate::registration::register_operator;
+    use crate::service::Operator;
+    use crate::services::Services;
+    use crate::shutdown::Shutdown;
+    use crate::signer::OperatorSigner;
+    use crate::signer::SignerBackend;
     use crate::spam_tasks::create_new_task;
-    use crate::start_operator::register_operator;
+    use crate::task_creator::TaskCreator;
+    use crate::task_source::WsTaskSource;
+    use crate::testutils::{wait_for_responses, Devnet, DevnetConfig};
 
     use alloy::network::EthereumWallet;
     use alloy::primitives::Address;
@@ -23,20 +192,20 @@ mod tests {
     use alloy::signers::local::PrivateKeySigner;
     use dotenv::dotenv;
     use eigensdk::common::get_provider;
-    use eigensdk::logging::init_logger;
+    use eigensdk::logging::{get_logger, init_logger};
 
     use eigensdk::utils::slashing::core::delegationmanager::DelegationManager;
-    use swap_manager_utils::SwapManager::SwapManager::{
-        self, latestTaskNumReturn,
-    };
-    use swap_manager_utils::{
-        get_anvil_eigenlayer_deployment_data, get_anvil_swap_manager_deployment_data,
-    };
     use reqwest::Url;
     use serial_test::serial;
     use std::env;
     use std::str::FromStr;
     use std::sync::LazyLock;
+    use std::time::Duration;
+    use swap_manager_utils::SwapManager::SwapManager::{self, latestTaskNumReturn};
+    use swap_manager_utils::{
+        get_anvil_eigenlayer_deployment_data, get_anvil_swap_manager_deployment_data, Deployment,
+        Network,
+    };
 
     static KEY: LazyLock<String> =
         LazyLock::new(|| env::var("PRIVATE_KEY").expect("failed to retrieve private key"));
@@ -44,12 +213,28 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_register_operator() {
-        let (_container, anvil_http, _) = start_anvil_container().await;
+        let devnet = Devnet::start(&DevnetConfig::default()).await.unwrap();
+        let anvil_http = devnet.http_url().to_string();
 
         dotenv().ok();
         init_logger(eigensdk::logging::log_level::LogLevel::Info);
         let private_key = &KEY.clone();
-        register_operator(&anvil_http, private_key).await.unwrap();
+        let signer = SignerBackend::PrivateKey(private_key.to_string())
+            .load()
+            .await
+            .unwrap();
+        let deployment = Deployment::bundled(Network::Anvil.chain_id()).unwrap();
+        let contracts = ContractAddresses::from_deployment(&deployment).unwrap();
+        register_operator(
+            get_logger(),
+            &anvil_http,
+            &Services::default(),
+            &signer,
+            &contracts,
+            "",
+        )
+        .await
+        .unwrap();
 
         let signer = PrivateKeySigner::from_str(private_key).unwrap();
         let wallet = EthereumWallet::from(signer.clone());
@@ -74,7 +259,8 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_spam_tasks() {
-        let (_container, anvil_http, _) = start_anvil_container().await;
+        let devnet = Devnet::start(&DevnetConfig::default()).await.unwrap();
+        let anvil_http = devnet.http_url().to_string();
 
         dotenv().ok();
         init_logger(eigensdk::logging::log_level::LogLevel::Info);
@@ -86,13 +272,18 @@ mod tests {
             .parse()
             .unwrap();
         let provider = &get_provider(&anvil_http);
-        let swap_manager_contract =
-            SwapManager::new(swap_manager_contract_address, provider);
+        let swap_manager_contract = SwapManager::new(swap_manager_contract_address, provider);
 
         let latest_task_num = swap_manager_contract.latestTaskNum().call().await.unwrap();
 
         let latestTaskNumReturn { _0: task_num } = latest_task_num;
-        let _ = create_new_task(&anvil_http, "HelloEigen").await;
+        let _ = create_new_task(
+            &anvil_http,
+            &KEY,
+            swap_manager_contract_address,
+            "HelloEigen",
+        )
+        .await;
 
         let latest_task_num_after_creating_task =
             swap_manager_contract.latestTaskNum().call().await.unwrap();
@@ -102,4 +293,83 @@ mod tests {
 
         assert_eq!(task_num + 1, task_num_after_task);
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_operator_responds_to_every_spammed_task() {
+        const TASKS: u32 = 3;
+
+        let spammer_key = PrivateKeySigner::random();
+        let devnet = Devnet::start(&DevnetConfig {
+            fund: vec![spammer_key.address()],
+            ..DevnetConfig::default()
+        })
+        .await
+        .unwrap();
+        dotenv().ok();
+        let contracts = devnet.contract_addresses().unwrap();
+        let signer = SignerBackend::PrivateKey(KEY.clone()).load().await.unwrap();
+        register_operator(
+            get_logger(),
+            devnet.http_url(),
+            &Services::default(),
+            &signer,
+            &contracts,
+            "",
+        )
+        .await
+        .unwrap();
+
+        // Subscribe before the first task is created so none of them is missed
+        let source = WsTaskSource::connect(
+            devnet.ws_url(),
+            &Services::default(),
+            contracts.swap_manager,
+        )
+        .await
+        .unwrap();
+        let shutdown = Shutdown::default();
+        let operator = Operator::builder()
+            .rpc_url(devnet.http_url())
+            .signer(signer)
+            .swap_manager_address(contracts.swap_manager)
+            .task_source(source)
+            .shutdown(shutdown.clone())
+            .build()
+            .unwrap();
+        let control = operator.control();
+        let running = tokio::spawn(operator.run());
+
+        let swap_manager =
+            SwapManager::new(contracts.swap_manager, get_provider(devnet.http_url()));
+        let first_task = swap_manager.latestTaskNum().call().await.unwrap()._0;
+        let gas = GasConfig::default();
+        let creator = TaskCreator::new(
+            devnet.http_url(),
+            &Services::default(),
+            &OperatorSigner::new(spammer_key),
+            contracts.swap_manager,
+            PendingTxWatcher::new(fee_strategy(&gas).unwrap(), &gas),
+        )
+        .unwrap();
+        for task in 0..TASKS {
+            creator.create_task(&format!("E2E{task}")).await.unwrap();
+        }
+
+        wait_for_responses(
+            devnet.http_url(),
+            contracts.swap_manager,
+            first_task..first_task + TASKS,
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+        shutdown.trigger();
+        running.await.unwrap().unwrap();
+        assert_eq!(
+            control.outcome_counts(),
+            vec![(TaskOutcome::Responded, u64::from(TASKS))]
+        );
+        devnet.stop().await.unwrap();
+    }
 }
@@ -0,0 +1,330 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::error::ConfigError;
+
+/// Bytes in a MiB of `log_file.max_size_mb`
+const MIB: u64 = 1024 * 1024;
+
+/// Time boundary the log file is rotated at
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// At every UTC midnight
+    #[default]
+    Daily,
+    /// At the top of every UTC hour
+    Hourly,
+    /// Only past `log_file.max_size_mb`
+    Never,
+}
+
+impl LogRotation {
+    /// First rotation boundary after `time`, `None` without time based rotation
+    fn next_after(self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let period = match self {
+            Self::Daily => TimeDelta::days(1),
+            Self::Hourly => TimeDelta::hours(1),
+            Self::Never => return None,
+        };
+        Some(time.duration_trunc(period).ok()? + period)
+    }
+}
+
+/// Log file written instead of stdout, the `[log_file]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogFileConfig {
+    /// File the logs are appended to, stdout if unset (`LOG_FILE`)
+    pub path: Option<PathBuf>,
+    /// `daily`, `hourly` or `never` (`LOG_ROTATE`)
+    pub rotate: LogRotation,
+    /// Size in MiB past which the file is rotated before its time, unbounded if unset
+    /// (`LOG_MAX_SIZE_MB`)
+    pub max_size_mb: Option<u64>,
+    /// Rotated files kept next to the log file, the oldest are deleted beyond it, all are
+    /// kept if 0 (`LOG_KEEP`)
+    pub keep: usize,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            rotate: LogRotation::Daily,
+            max_size_mb: None,
+            keep: 7,
+        }
+    }
+}
+
+impl LogFileConfig {
+    /// Check that the file is named and its size limit holds at least a MiB
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self
+            .path
+            .as_ref()
+            .is_some_and(|path| path.file_name().is_none())
+        {
+            return Err(ConfigError::Invalid(
+                "log_file.path must name a file".to_string(),
+            ));
+        }
+        if self.max_size_mb == Some(0) {
+            return Err(ConfigError::Invalid(
+                "log_file.max_size_mb must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Command line overrides of the `[log_file]` section, shared by the binaries
+#[derive(Args, Debug, Clone, Default)]
+pub struct LogFileArgs {
+    /// File the logs are appended to instead of stdout, overrides `log_file.path`
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// When the log file is rotated, overrides `log_file.rotate`
+    #[arg(long, value_enum, global = true)]
+    pub log_rotate: Option<LogRotation>,
+
+    /// Rotated log files kept, overrides `log_file.keep`
+    #[arg(long, global = true)]
+    pub log_keep: Option<usize>,
+}
+
+impl LogFileArgs {
+    /// Apply the flags set on top of `config`
+    pub fn apply(self, config: &mut LogFileConfig) {
+        if self.log_file.is_some() {
+            config.path = self.log_file;
+        }
+        if let Some(rotate) = self.log_rotate {
+            config.rotate = rotate;
+        }
+        if let Some(keep) = self.log_keep {
+            config.keep = keep;
+        }
+    }
+}
+
+/// Log file rotated by time and size, the rotated files named after it with the time of
+/// their rotation appended and the oldest deleted beyond `keep`
+#[derive(Debug)]
+pub struct RollingFile {
+    inner: Mutex<RollingState>,
+}
+
+#[derive(Debug)]
+struct RollingState {
+    path: PathBuf,
+    rotation: LogRotation,
+    max_bytes: Option<u64>,
+    keep: usize,
+    file: File,
+    size: u64,
+    next_rotation: Option<DateTime<Utc>>,
+}
+
+impl RollingFile {
+    /// Open the log file of `config` for appending, `None` if it has no path
+    pub fn open(config: &LogFileConfig) -> io::Result<Option<Self>> {
+        config
+            .path
+            .as_deref()
+            .map(|path| {
+                Self::new(
+                    path,
+                    config.rotate,
+                    config.max_size_mb.map(|mb| mb * MIB),
+                    config.keep,
+                )
+            })
+            .transpose()
+    }
+
+    fn new(
+        path: &Path,
+        rotation: LogRotation,
+        max_bytes: Option<u64>,
+        keep: usize,
+    ) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        // A file left by an earlier run is rotated at the end of the period it was written in
+        let modified = metadata.modified().map(DateTime::<Utc>::from)?;
+        Ok(Self {
+            inner: Mutex::new(RollingState {
+                path: path.to_path_buf(),
+                rotation,
+                max_bytes,
+                keep,
+                file,
+                size: metadata.len(),
+                next_rotation: rotation.next_after(modified),
+            }),
+        })
+    }
+}
+
+impl RollingState {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Utc::now();
+        let due = self.next_rotation.is_some_and(|at| now >= at)
+            || self
+                .max_bytes
+                .is_some_and(|max| self.size > 0 && self.size + buf.len() as u64 > max);
+        if due {
+            // No subscriber to log through, and losing a rotation beats losing the logs
+            if let Err(e) = self.rotate(now) {
+                eprintln!("Failed to rotate the log file {}: {e}", self.path.display());
+                self.next_rotation = self.rotation.next_after(now);
+            }
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        self.file.flush()?;
+        let stamp = now.format("%Y%m%d-%H%M%S");
+        let mut rotated = self.sibling(&stamp.to_string());
+        for n in 1.. {
+            if !rotated.exists() {
+                break;
+            }
+            rotated = self.sibling(&format!("{stamp}.{n}"));
+        }
+        fs::rename(&self.path, &rotated)?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.next_rotation = self.rotation.next_after(now);
+        self.prune()
+    }
+
+    /// Path of the log file with `.suffix` appended
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(suffix);
+        self.path.with_file_name(name)
+    }
+
+    /// Delete the oldest rotated files beyond `keep`
+    fn prune(&self) -> io::Result<()> {
+        if self.keep == 0 {
+            return Ok(());
+        }
+        let prefix = self.sibling("");
+        let (Some(dir), Some(prefix)) = (prefix.parent(), prefix.file_name()) else {
+            return Ok(());
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let prefix = prefix.to_string_lossy();
+        let mut rotated: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(prefix.as_ref()))
+            })
+            .collect();
+        // The stamps sort chronologically
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.keep);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Exclusive access to a [`RollingFile`] while an event is written
+#[derive(Debug)]
+pub struct RollingWriter<'a>(MutexGuard<'a, RollingState>);
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingWriter(self.inner.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_rotates_past_its_size_and_keeps_the_newest() {
+        let dir = std::env::temp_dir().join(format!("log-file-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("operator.log");
+        let file = RollingFile::new(&path, LogRotation::Never, Some(16), 2).unwrap();
+        for line in [
+            "first line\n",
+            "second line\n",
+            "third line\n",
+            "fourth line\n",
+        ] {
+            file.make_writer().write_all(line.as_bytes()).unwrap();
+        }
+
+        let mut rotated: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name != "operator.log")
+            .collect();
+        rotated.sort();
+        assert_eq!(rotated.len(), 2);
+        assert!(rotated.iter().all(|name| name.starts_with("operator.log.")));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth line\n");
+        assert_eq!(
+            fs::read_to_string(dir.join(&rotated[1])).unwrap(),
+            "third line\n"
+        );
+
+        let time = "2026-10-15T13:45:10Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            LogRotation::Daily.next_after(time),
+            Some("2026-10-16T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            LogRotation::Hourly.next_after(time),
+            Some("2026-10-15T14:00:00Z".parse().unwrap())
+        );
+        assert_eq!(LogRotation::Never.next_after(time), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use std::{fmt, sync::Arc};
+
+use clap::ValueEnum;
+use eigensdk::logging::{log_level::LogLevel, logger::SharedLogger, tracing_logger::TracingLogger};
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use serde::Deserialize;
+use tracing::{level_filters::LevelFilter, Level, Subscriber};
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, Layer,
+};
+
+use crate::log_file::{LogFileConfig, RollingFile};
+
+/// Service name of the exported spans when `OTEL_SERVICE_NAME` is unset and the binary name
+/// can't be read
+const DEFAULT_SERVICE_NAME: &str = "swap-manager-avs-operator";
+
+/// Output format of the logs
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines, colored when printed to a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per line, carrying the span fields of every event
+    Json,
+}
+
+/// Flushes the spans still buffered for the OTLP collector when dropped
+#[must_use = "dropping the guard stops the span export"]
+pub struct TracingGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl fmt::Debug for TracingGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracingGuard")
+            .field("exporting", &self.provider.is_some())
+            .finish()
+    }
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush the exported spans: {e}");
+            }
+        }
+    }
+}
+
+/// Install the global tracing subscriber, must be called once before logging.
+///
+/// With an `otlp_endpoint` the spans are also exported over OTLP/gRPC, so the task pipeline
+/// shows up in Jaeger or Tempo. Must then be called from within the Tokio runtime, and the
+/// returned guard kept until exit.
+///
+/// With a `log_file` path the logs go to that file, rotated and pruned as configured,
+/// instead of stdout.
+pub fn init_tracing(
+    level: Level,
+    format: LogFormat,
+    otlp_endpoint: Option<&str>,
+    log_file: &LogFileConfig,
+) -> TracingGuard {
+    let provider = otlp_endpoint.and_then(|endpoint| match otlp_provider(endpoint) {
+        Ok(provider) => Some(provider),
+        // No subscriber is installed yet to log through
+        Err(e) => {
+            eprintln!(
+                "Failed to set up the OTLP exporter to {endpoint}, spans are not exported: {e}"
+            );
+            None
+        }
+    });
+    let otlp = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("swap-manager"))
+    });
+    let file = RollingFile::open(log_file).unwrap_or_else(|e| {
+        eprintln!("Failed to open the log file, logging to stdout: {e}");
+        None
+    });
+    let fmt = match file {
+        Some(file) => fmt_layer(format, file, false),
+        None => fmt_layer(format, std::io::stdout, true),
+    };
+    tracing_subscriber::registry()
+        .with(LevelFilter::from_level(level))
+        .with(fmt)
+        .with(otlp)
+        .init();
+    TracingGuard { provider }
+}
+
+fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    }
+}
+
+/// Tracer provider batching spans to the OTLP/gRPC collector at `endpoint`
+fn otlp_provider(endpoint: &str) -> Result<TracerProvider, opentelemetry::trace::TraceError> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let service_name = std::env::var("OTEL_SERVICE_NAME").ok().unwrap_or_else(|| {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string())
+    });
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name)]))
+        .build())
+}
+
+/// eigensdk logger for the SDK clients, writing through the subscriber set up by [`init_tracing`].
+///
+/// `eigensdk::logging::init_logger` installs its own subscriber and would clash with ours.
+pub fn sdk_logger(level: Level) -> SharedLogger {
+    let level = match level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Trace,
+    };
+    Arc::new(TracingLogger {
+        add_source: false,
+        level,
+        time_format: String::new(),
+    })
+}
@@ -0,0 +1,112 @@
+use std::{path::Path, time::Duration};
+
+use eigensdk::types::operator_metadata::OperatorMetadata;
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::error::MetadataError;
+
+/// Fields of the EigenLayer operator metadata JSON, `name`, `description` and `logo`
+/// are required
+pub const METADATA_FIELDS: [&str; 5] = ["name", "description", "logo", "website", "twitter"];
+
+/// Seconds fetching the metadata document may take
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Parse `json` as operator metadata, rejecting anything but an object of
+/// [`METADATA_FIELDS`] holding strings
+pub fn parse_operator_metadata(json: &str) -> Result<OperatorMetadata, MetadataError> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| MetadataError::Schema(format!("not valid JSON: {e}")))?;
+    let Value::Object(fields) = &value else {
+        return Err(MetadataError::Schema("expected a JSON object".to_string()));
+    };
+    for (name, field) in fields {
+        if !METADATA_FIELDS.contains(&name.as_str()) {
+            return Err(MetadataError::Schema(format!(
+                "unknown field {name}, expected one of {}",
+                METADATA_FIELDS.join(", ")
+            )));
+        }
+        if !field.is_string() && !field.is_null() {
+            return Err(MetadataError::Schema(format!("{name} must be a string")));
+        }
+    }
+    serde_json::from_value(value).map_err(|e| MetadataError::Schema(e.to_string()))
+}
+
+/// Fetch the metadata document served at `uri`
+pub async fn fetch_operator_metadata(uri: &str) -> Result<String, MetadataError> {
+    let url = Url::parse(uri)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| {
+            MetadataError::Schema(format!("metadata URI {uri} must be an http(s) URL"))
+        })?;
+    let fetch_error = |source| MetadataError::Fetch {
+        uri: uri.to_string(),
+        source,
+    };
+    reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(fetch_error)?
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_error)?
+        .text()
+        .await
+        .map_err(fetch_error)
+}
+
+/// Check the metadata served at `uri` before pointing the operator at it: it follows the
+/// schema, passes the EigenLayer rules, the logo included, and matches `file` if given
+pub async fn check_operator_metadata(uri: &str, file: Option<&Path>) -> Result<(), MetadataError> {
+    let served = fetch_operator_metadata(uri).await?;
+    if let Some(path) = file {
+        let local = std::fs::read_to_string(path).map_err(|source| MetadataError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        parse_operator_metadata(&local)?;
+        // Compared as JSON values, a hosting service may reformat the document
+        if serde_json::from_str::<Value>(&served).ok() != serde_json::from_str::<Value>(&local).ok()
+        {
+            return Err(MetadataError::Mismatch {
+                uri: uri.to_string(),
+                path: path.to_path_buf(),
+            });
+        }
+    }
+    parse_operator_metadata(&served)?.validate().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_metadata_schema() {
+        assert!(parse_operator_metadata(
+            r#"{"name": "Swap Operator", "description": "Quotes swaps", "logo": "https://example.com/logo.png", "website": null}"#
+        )
+        .is_ok());
+        for invalid in [
+            r#"["Swap Operator"]"#,
+            r#"{"description": "Quotes swaps", "logo": "https://example.com/logo.png"}"#,
+            r#"{"name": "Swap Operator", "description": "Quotes swaps", "logo": "https://example.com/logo.png", "discord": "swap"}"#,
+            r#"{"name": 7, "description": "Quotes swaps", "logo": "https://example.com/logo.png"}"#,
+        ] {
+            assert!(
+                matches!(
+                    parse_operator_metadata(invalid),
+                    Err(MetadataError::Schema(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+}
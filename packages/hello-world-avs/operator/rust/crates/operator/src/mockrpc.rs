@@ -0,0 +1,652 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use alloy::{
+    consensus::{Transaction, TxEnvelope},
+    eips::{eip2718::Decodable2718, Typed2718},
+    primitives::{keccak256, Address, Bytes, B256},
+};
+use axum::{extract::State, routing::post, Json, Router};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+
+/// JSON-RPC error code of a method the mock doesn't serve
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code nodes reject transactions with
+pub const SERVER_ERROR: i64 = -32000;
+
+/// Percentage a replacement must raise the fees of the transaction it replaces by, like geth
+const REPLACEMENT_BUMP_PERCENT: u128 = 10;
+
+/// Reply to a JSON-RPC call
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockReply {
+    /// The `result` of the call
+    Result(Value),
+    /// A JSON-RPC error
+    Error {
+        /// Error code, such as [`SERVER_ERROR`]
+        code: i64,
+        /// Error message, what the callers match on
+        message: String,
+    },
+}
+
+impl MockReply {
+    /// Error with `code` and `message`
+    pub fn error(code: i64, message: impl Into<String>) -> Self {
+        Self::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// A call the mock received
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    /// JSON-RPC method
+    pub method: String,
+    /// Parameters of the call
+    pub params: Value,
+}
+
+/// A transaction sent with `eth_sendRawTransaction`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentTx {
+    /// Hash of the transaction
+    pub hash: B256,
+    /// Recovered sender
+    pub from: Address,
+    /// Recipient, `None` for contract creations
+    pub to: Option<Address>,
+    /// Nonce of the sender
+    pub nonce: u64,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// Max fee per gas, the gas price of legacy transactions
+    pub max_fee_per_gas: u128,
+    /// Max priority fee per gas, the gas price of legacy transactions
+    pub max_priority_fee_per_gas: u128,
+    /// Calldata
+    pub input: Bytes,
+    /// EIP-2718 type
+    pub tx_type: u8,
+    /// Block it was mined in, `None` while pending or once replaced
+    pub block_number: Option<u64>,
+}
+
+/// Chain state and scripted replies of a [`MockRpc`]
+#[derive(Debug)]
+struct MockState {
+    chain_id: u64,
+    block_number: u64,
+    base_fee: u128,
+    priority_fee: u128,
+    gas_used: u64,
+    /// Least max fee per gas a transaction must pay to be mined, it stays pending otherwise
+    min_fee: u128,
+    automine: bool,
+    revert: bool,
+    /// Transactions mined per sender, the next nonce of the `latest` block
+    mined_nonces: HashMap<Address, u64>,
+    sent: Vec<SentTx>,
+    receipts: HashMap<B256, Value>,
+    replies: HashMap<String, MockReply>,
+    queued: HashMap<String, VecDeque<MockReply>>,
+    latency: HashMap<String, Duration>,
+    calls: Vec<MockCall>,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            chain_id: 31337,
+            block_number: 1,
+            base_fee: 1_000_000_000,
+            priority_fee: 100_000_000,
+            gas_used: 21_000,
+            min_fee: 0,
+            automine: true,
+            revert: false,
+            mined_nonces: HashMap::new(),
+            sent: Vec::new(),
+            receipts: HashMap::new(),
+            replies: HashMap::new(),
+            queued: HashMap::new(),
+            latency: HashMap::new(),
+            calls: Vec::new(),
+        }
+    }
+}
+
+/// Ethereum JSON-RPC server serving just enough of the API to send transactions, for unit
+/// tests of the transaction flows without anvil.
+///
+/// Transactions are decoded and mined on arrival, in nonce order per sender, unless they
+/// pay less than [`Self::set_min_fee`] or automining is off. Any method can be scripted
+/// with a fixed or one-off reply, an error or latency. The server stops once dropped.
+#[derive(Debug)]
+pub struct MockRpc {
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+impl Drop for MockRpc {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl MockRpc {
+    /// Serve a fresh chain on a free local port
+    pub async fn start() -> std::io::Result<Self> {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(Self { url, state, server })
+    }
+
+    /// HTTP URL of the server
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Set the chain id, 31337 by default
+    pub fn set_chain_id(&self, chain_id: u64) {
+        self.lock().chain_id = chain_id;
+    }
+
+    /// Set the base fee and the suggested priority fee, in wei
+    pub fn set_fees(&self, base_fee: u128, priority_fee: u128) {
+        let mut state = self.lock();
+        state.base_fee = base_fee;
+        state.priority_fee = priority_fee;
+    }
+
+    /// Gas `eth_estimateGas` answers and the mined transactions use, 21000 by default
+    pub fn set_gas_used(&self, gas: u64) {
+        self.lock().gas_used = gas;
+    }
+
+    /// Leave the transactions paying a max fee per gas under `min_fee` pending, like a
+    /// congested mempool. Lowering it mines the ones now paying enough
+    pub fn set_min_fee(&self, min_fee: u128) {
+        let mut state = self.lock();
+        state.min_fee = min_fee;
+        state.mine();
+    }
+
+    /// Mine the sent transactions on arrival, on by default. Turning it back on mines the
+    /// pending ones
+    pub fn set_automine(&self, automine: bool) {
+        let mut state = self.lock();
+        state.automine = automine;
+        state.mine();
+    }
+
+    /// Mine the transactions from now on with a failed status
+    pub fn set_revert(&self, revert: bool) {
+        self.lock().revert = revert;
+    }
+
+    /// Next nonce of `address` in the latest block
+    pub fn set_nonce(&self, address: Address, nonce: u64) {
+        self.lock().mined_nonces.insert(address, nonce);
+    }
+
+    /// Answer every `method` call with `reply`, instead of the built-in behaviour
+    pub fn reply(&self, method: &str, reply: MockReply) {
+        self.lock().replies.insert(method.to_string(), reply);
+    }
+
+    /// Answer the next `method` call with `reply`, before any fixed reply. Queued replies
+    /// are used in order
+    pub fn reply_once(&self, method: &str, reply: MockReply) {
+        self.lock()
+            .queued
+            .entry(method.to_string())
+            .or_default()
+            .push_back(reply);
+    }
+
+    /// Fail the next `method` call with a server error saying `message`
+    pub fn fail_once(&self, method: &str, message: &str) {
+        self.reply_once(method, MockReply::error(SERVER_ERROR, message));
+    }
+
+    /// Drop the fixed and queued replies of `method`, back to the built-in behaviour
+    pub fn reset(&self, method: &str) {
+        let mut state = self.lock();
+        state.replies.remove(method);
+        state.queued.remove(method);
+    }
+
+    /// Delay every reply to `method` by `latency`
+    pub fn set_latency(&self, method: &str, latency: Duration) {
+        self.lock().latency.insert(method.to_string(), latency);
+    }
+
+    /// Every call received, oldest first
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.lock().calls.clone()
+    }
+
+    /// Number of `method` calls received
+    pub fn call_count(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| call.method == method)
+            .count()
+    }
+
+    /// Every transaction sent, replaced ones included, oldest first
+    pub fn sent(&self) -> Vec<SentTx> {
+        self.lock().sent.clone()
+    }
+
+    /// Current block number
+    pub fn block_number(&self) -> u64 {
+        self.lock().block_number
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MockState {
+    fn call(&mut self, method: &str, params: &Value) -> MockReply {
+        self.calls.push(MockCall {
+            method: method.to_string(),
+            params: params.clone(),
+        });
+        if let Some(reply) = self.queued.get_mut(method).and_then(VecDeque::pop_front) {
+            return reply;
+        }
+        if let Some(reply) = self.replies.get(method) {
+            return reply.clone();
+        }
+        let param = |index: usize| params.get(index).cloned().unwrap_or(Value::Null);
+        match method {
+            "eth_chainId" => MockReply::Result(quantity(self.chain_id)),
+            "net_version" => MockReply::Result(json!(self.chain_id.to_string())),
+            "eth_blockNumber" => MockReply::Result(quantity(self.block_number)),
+            "eth_gasPrice" => MockReply::Result(quantity(self.base_fee + self.priority_fee)),
+            "eth_maxPriorityFeePerGas" => MockReply::Result(quantity(self.priority_fee)),
+            "eth_feeHistory" => MockReply::Result(self.fee_history(&param(0), &param(2))),
+            "eth_estimateGas" => MockReply::Result(quantity(self.gas_used)),
+            "eth_call" | "eth_getCode" => MockReply::Result(json!("0x")),
+            "eth_getBalance" => MockReply::Result(quantity(u128::MAX >> 1)),
+            "eth_getTransactionCount" => match serde_json::from_value::<Address>(param(0)) {
+                Ok(address) => MockReply::Result(quantity(
+                    self.nonce(address, param(1).as_str() == Some("pending")),
+                )),
+                Err(e) => MockReply::error(-32602, format!("invalid address: {e}")),
+            },
+            "eth_sendRawTransaction" => match serde_json::from_value::<Bytes>(param(0)) {
+                Ok(raw) => self.send(&raw),
+                Err(e) => MockReply::error(-32602, format!("invalid raw transaction: {e}")),
+            },
+            "eth_getTransactionReceipt" => match serde_json::from_value::<B256>(param(0)) {
+                Ok(hash) => {
+                    MockReply::Result(self.receipts.get(&hash).cloned().unwrap_or(Value::Null))
+                }
+                Err(e) => MockReply::error(-32602, format!("invalid hash: {e}")),
+            },
+            _ => MockReply::error(
+                METHOD_NOT_FOUND,
+                format!("the method {method} does not exist/is not available"),
+            ),
+        }
+    }
+
+    /// Next nonce of `address`, counting the pending transactions if `pending`
+    fn nonce(&self, address: Address, pending: bool) -> u64 {
+        let mined = self.mined_nonces.get(&address).copied().unwrap_or_default();
+        if !pending {
+            return mined;
+        }
+        self.sent
+            .iter()
+            .filter(|tx| tx.from == address && tx.block_number.is_none() && tx.nonce >= mined)
+            .map(|tx| tx.nonce + 1)
+            .max()
+            .unwrap_or(mined)
+            .max(mined)
+    }
+
+    fn fee_history(&self, block_count: &Value, percentiles: &Value) -> Value {
+        let blocks = block_count
+            .as_str()
+            .and_then(|count| u64::from_str_radix(count.trim_start_matches("0x"), 16).ok())
+            .or_else(|| block_count.as_u64())
+            .unwrap_or(1)
+            .clamp(1, self.block_number);
+        let rewards = percentiles.as_array().map_or(0, Vec::len);
+        json!({
+            "oldestBlock": quantity(self.block_number + 1 - blocks),
+            "baseFeePerGas": vec![quantity(self.base_fee); blocks as usize + 1],
+            "gasUsedRatio": vec![0.5; blocks as usize],
+            "reward": vec![vec![quantity(self.priority_fee); rewards]; blocks as usize],
+        })
+    }
+
+    fn send(&mut self, raw: &Bytes) -> MockReply {
+        let envelope = match TxEnvelope::decode_2718(&mut raw.as_ref()) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                return MockReply::error(-32602, format!("failed to decode transaction: {e}"))
+            }
+        };
+        let from = match envelope.recover_signer() {
+            Ok(from) => from,
+            Err(e) => return MockReply::error(-32602, format!("invalid sender: {e}")),
+        };
+        let hash = *envelope.tx_hash();
+        let tx = SentTx {
+            hash,
+            from,
+            to: envelope.to(),
+            nonce: envelope.nonce(),
+            gas_limit: envelope.gas_limit(),
+            max_fee_per_gas: envelope.max_fee_per_gas(),
+            max_priority_fee_per_gas: envelope.priority_fee_or_price(),
+            input: envelope.input().clone(),
+            tx_type: envelope.ty(),
+            block_number: None,
+        };
+        if envelope
+            .chain_id()
+            .is_some_and(|chain_id| chain_id != self.chain_id)
+        {
+            return MockReply::error(SERVER_ERROR, "invalid chain id");
+        }
+        if self.sent.iter().any(|sent| sent.hash == hash) {
+            return MockReply::error(SERVER_ERROR, "already known");
+        }
+        if tx.nonce < self.nonce(from, false) {
+            return MockReply::error(SERVER_ERROR, "nonce too low");
+        }
+        if let Some(pending) = self
+            .sent
+            .iter()
+            .filter(|sent| sent.from == from && sent.nonce == tx.nonce)
+            .max_by_key(|sent| sent.max_fee_per_gas)
+        {
+            let bumped = |fee: u128| fee + fee * REPLACEMENT_BUMP_PERCENT / 100;
+            if tx.max_fee_per_gas < bumped(pending.max_fee_per_gas)
+                || tx.max_priority_fee_per_gas < bumped(pending.max_priority_fee_per_gas)
+            {
+                return MockReply::error(SERVER_ERROR, "replacement transaction underpriced");
+            }
+        }
+        self.sent.push(tx);
+        self.mine();
+        MockReply::Result(json!(hash))
+    }
+
+    /// Mine the pending transactions paying enough, in nonce order per sender, the best
+    /// paying one of a nonce replacing the others. Each goes in a block of its own
+    fn mine(&mut self) {
+        if !self.automine {
+            return;
+        }
+        loop {
+            // Best paying transaction of the next nonce of each sender
+            let mut next: BTreeMap<Address, usize> = BTreeMap::new();
+            for (index, tx) in self.sent.iter().enumerate() {
+                let mined_nonce = self.mined_nonces.get(&tx.from).copied().unwrap_or_default();
+                if tx.block_number.is_some()
+                    || tx.nonce != mined_nonce
+                    || tx.max_fee_per_gas < self.min_fee.max(self.base_fee)
+                {
+                    continue;
+                }
+                let best = next.entry(tx.from).or_insert(index);
+                if tx.max_fee_per_gas > self.sent[*best].max_fee_per_gas {
+                    *best = index;
+                }
+            }
+            if next.is_empty() {
+                return;
+            }
+            for index in next.into_values() {
+                self.block_number += 1;
+                let effective_gas_price = self.sent[index]
+                    .max_fee_per_gas
+                    .min(self.base_fee + self.sent[index].max_priority_fee_per_gas);
+                let tx = &mut self.sent[index];
+                tx.block_number = Some(self.block_number);
+                self.mined_nonces.insert(tx.from, tx.nonce + 1);
+                let receipt = json!({
+                    "transactionHash": tx.hash,
+                    "transactionIndex": "0x0",
+                    "blockHash": keccak256(self.block_number.to_be_bytes()),
+                    "blockNumber": quantity(self.block_number),
+                    "from": tx.from,
+                    "to": tx.to,
+                    "contractAddress": Value::Null,
+                    "cumulativeGasUsed": quantity(self.gas_used),
+                    "gasUsed": quantity(self.gas_used),
+                    "effectiveGasPrice": quantity(effective_gas_price),
+                    "logs": [],
+                    "logsBloom": format!("0x{}", "0".repeat(512)),
+                    "type": quantity(tx.tx_type),
+                    "status": if self.revert { "0x0" } else { "0x1" },
+                });
+                self.receipts.insert(tx.hash, receipt);
+            }
+        }
+    }
+}
+
+/// Hex quantity of `value`
+fn quantity(value: impl Into<u128>) -> Value {
+    json!(format!("{:#x}", value.into()))
+}
+
+async fn handle(
+    State(state): State<Arc<Mutex<MockState>>>,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    match body {
+        Value::Array(requests) => {
+            let mut replies = Vec::with_capacity(requests.len());
+            for request in requests {
+                replies.push(handle_one(&state, request).await);
+            }
+            Json(Value::Array(replies))
+        }
+        request => Json(handle_one(&state, request).await),
+    }
+}
+
+async fn handle_one(state: &Mutex<MockState>, request: Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let params = request.get("params").cloned().unwrap_or(json!([]));
+    let (reply, latency) = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let latency = state.latency.get(&method).copied();
+        (state.call(&method, &params), latency)
+    };
+    if let Some(latency) = latency {
+        tokio::time::sleep(latency).await;
+    }
+    match reply {
+        MockReply::Result(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        MockReply::Error { code, message } => {
+            json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        providers::Provider, rpc::types::TransactionRequest, signers::local::PrivateKeySigner,
+    };
+    use std::time::Instant;
+
+    use crate::error::ChainError;
+    use crate::gas::{fee_strategy, GasConfig};
+    use crate::pending_tx::PendingTxWatcher;
+    use crate::retry::{retry, RetryConfig};
+    use crate::services::Services;
+    use crate::signer::OperatorSigner;
+    use crate::task_creator::TaskCreator;
+
+    fn watcher(gas: &GasConfig) -> PendingTxWatcher {
+        PendingTxWatcher::new(fee_strategy(gas).unwrap(), gas)
+    }
+
+    #[tokio::test]
+    async fn test_tasks_are_created_with_sequential_nonces() {
+        let mock = MockRpc::start().await.unwrap();
+        let signer = OperatorSigner::new(PrivateKeySigner::random());
+        mock.set_nonce(signer.address(), 7);
+        let swap_manager = Address::repeat_byte(0x11);
+        let creator = TaskCreator::new(
+            mock.url(),
+            &Services::default(),
+            &signer,
+            swap_manager,
+            watcher(&GasConfig::default()),
+        )
+        .unwrap();
+
+        let first = creator.create_task("QuickFox1").await.unwrap().unwrap();
+        let second = creator.create_task("LazyDog2").await.unwrap().unwrap();
+        let sent = mock.sent();
+        assert_eq!(
+            sent.iter()
+                .map(|tx| (tx.hash, tx.nonce))
+                .collect::<Vec<_>>(),
+            [(first, 7), (second, 8)]
+        );
+        assert!(sent
+            .iter()
+            .all(|tx| tx.from == signer.address() && tx.to == Some(swap_manager)));
+        assert_eq!(mock.block_number(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_injected_errors_are_retried_after_a_nonce_resync() {
+        let mock = MockRpc::start().await.unwrap();
+        let signer = OperatorSigner::new(PrivateKeySigner::random());
+        let creator = TaskCreator::new(
+            mock.url(),
+            &Services::default(),
+            &signer,
+            Address::repeat_byte(0x11),
+            watcher(&GasConfig::default()),
+        )
+        .unwrap();
+        mock.fail_once("eth_sendRawTransaction", "nonce too low");
+        let config = RetryConfig {
+            initial_delay_ms: 1,
+            jitter: 0.0,
+            ..Default::default()
+        };
+
+        let created = retry(&config, "createNewTask", || {
+            creator.create_task("QuickFox1")
+        })
+        .await
+        .unwrap();
+        assert!(created.is_some());
+        assert_eq!(mock.call_count("eth_sendRawTransaction"), 2);
+        assert_eq!(mock.sent().len(), 1);
+        assert_eq!(mock.sent()[0].nonce, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_transactions_are_replaced_with_bumped_fees() {
+        let mock = MockRpc::start().await.unwrap();
+        let signer = OperatorSigner::new(PrivateKeySigner::random());
+        let provider = signer.provider(mock.url()).unwrap();
+        let gas = GasConfig {
+            stuck_timeout_secs: 0,
+            max_fee_bumps: 3,
+            ..Default::default()
+        };
+        mock.set_min_fee(u128::MAX);
+        let tx = TransactionRequest::default()
+            .to(Address::repeat_byte(0x22))
+            .from(signer.address())
+            .nonce(0);
+
+        let error = watcher(&gas)
+            .send(&provider, "transfer", tx)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ChainError::NotMined { bumps: 3, .. }));
+        let sent = mock.sent();
+        assert_eq!(sent.len(), 4);
+        for pair in sent.windows(2) {
+            assert_eq!(pair[1].nonce, 0);
+            assert!(pair[1].max_fee_per_gas >= pair[0].max_fee_per_gas * 112 / 100);
+        }
+
+        // The best paying replacement is mined once the mempool clears
+        mock.set_min_fee(sent[1].max_fee_per_gas);
+        let mined = mock.sent();
+        assert_eq!(mined[3].block_number, Some(2));
+        assert!(mined[..3].iter().all(|tx| tx.block_number.is_none()));
+        let receipt = provider
+            .get_transaction_receipt(mined[3].hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(receipt.status());
+        assert_eq!(
+            provider
+                .get_transaction_count(signer.address())
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_latency_and_scripted_replies() {
+        let mock = MockRpc::start().await.unwrap();
+        let provider = OperatorSigner::new(PrivateKeySigner::random())
+            .provider(mock.url())
+            .unwrap();
+        mock.set_latency("eth_blockNumber", Duration::from_millis(200));
+        let start = Instant::now();
+        assert_eq!(provider.get_block_number().await.unwrap(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        mock.reply_once("eth_chainId", MockReply::Result(json!("0x4268")));
+        mock.reply("eth_chainId", MockReply::Result(json!("0x1")));
+        assert_eq!(provider.get_chain_id().await.unwrap(), 17000);
+        assert_eq!(provider.get_chain_id().await.unwrap(), 1);
+        mock.reset("eth_chainId");
+        mock.reply(
+            "eth_gasPrice",
+            MockReply::error(METHOD_NOT_FOUND, "the method eth_gasPrice does not exist"),
+        );
+        assert!(provider.get_gas_price().await.is_err());
+        assert_eq!(mock.call_count("eth_gasPrice"), 1);
+    }
+}
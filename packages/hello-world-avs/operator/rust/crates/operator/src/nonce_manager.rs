@@ -0,0 +1,147 @@
+use alloy::{primitives::Address, providers::Provider};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::error::ChainError;
+
+/// Hands out sequential nonces for one sender so concurrent submissions don't collide.
+///
+/// The first nonce is read from the pending transaction count. After any failed
+/// submission the cache must be [`resync`](NonceManager::resync)ed, which closes the
+/// gap left by the dropped nonce.
+#[derive(Debug)]
+pub struct NonceManager {
+    address: Address,
+    next: Mutex<Option<u64>>,
+}
+
+impl NonceManager {
+    /// Track nonces of `address`
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Address whose nonces are tracked
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Reserve the next nonce
+    pub async fn next_nonce<P: Provider>(&self, provider: &P) -> Result<u64, ChainError> {
+        let mut next = self.next.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => {
+                provider
+                    .get_transaction_count(self.address)
+                    .pending()
+                    .await?
+            }
+        };
+        *next = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Drop the cached nonce and reload it from the chain
+    pub async fn resync<P: Provider>(&self, provider: &P) -> Result<u64, ChainError> {
+        let mut next = self.next.lock().await;
+        let nonce = provider
+            .get_transaction_count(self.address)
+            .pending()
+            .await?;
+        if *next != Some(nonce) {
+            warn!(address = %self.address, cached = ?*next, nonce, "Nonce resynced");
+        }
+        *next = Some(nonce);
+        Ok(nonce)
+    }
+}
+
+/// Whether an RPC error message means the nonce we sent was wrong, used by a mined or
+/// pending transaction or past a gap. "already known" is not one, the node already holds
+/// this very transaction
+pub fn is_nonce_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "nonce too low",
+        "nonce too high",
+        "invalid nonce",
+        "replacement transaction underpriced",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::providers::ProviderBuilder;
+
+    use super::*;
+    use crate::mockrpc::MockRpc;
+
+    #[tokio::test]
+    async fn test_concurrent_reservations_are_sequential() {
+        let mock = MockRpc::start().await.unwrap();
+        let provider = ProviderBuilder::new().on_http(mock.url().parse().unwrap());
+        let address = Address::repeat_byte(0x42);
+        mock.set_nonce(address, 5);
+        let nonces = Arc::new(NonceManager::new(address));
+
+        let reservations: Vec<_> = (0..50)
+            .map(|_| {
+                let (nonces, provider) = (nonces.clone(), provider.clone());
+                tokio::spawn(async move { nonces.next_nonce(&provider).await.unwrap() })
+            })
+            .collect();
+        let mut reserved = Vec::new();
+        for reservation in reservations {
+            reserved.push(reservation.await.unwrap());
+        }
+        reserved.sort_unstable();
+
+        assert_eq!(reserved, (5..55).collect::<Vec<_>>());
+        assert_eq!(mock.call_count("eth_getTransactionCount"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resync_reuses_a_dropped_nonce() {
+        let mock = MockRpc::start().await.unwrap();
+        let provider = ProviderBuilder::new().on_http(mock.url().parse().unwrap());
+        let address = Address::repeat_byte(0x42);
+        mock.set_nonce(address, 3);
+        let nonces = NonceManager::new(address);
+
+        // Neither transaction reached the node, leaving a gap at 3
+        assert_eq!(nonces.next_nonce(&provider).await.unwrap(), 3);
+        assert_eq!(nonces.next_nonce(&provider).await.unwrap(), 4);
+
+        assert_eq!(nonces.resync(&provider).await.unwrap(), 3);
+        assert_eq!(nonces.next_nonce(&provider).await.unwrap(), 3);
+        assert_eq!(nonces.next_nonce(&provider).await.unwrap(), 4);
+    }
+
+    #[test]
+    fn test_nonce_errors_are_told_apart() {
+        for nonce_error in [
+            "nonce too low",
+            "Nonce too high: next nonce 7, tx nonce 9",
+            "invalid nonce",
+            "replacement transaction underpriced",
+        ] {
+            assert!(is_nonce_error(nonce_error), "{nonce_error}");
+        }
+        for other in [
+            "already known",
+            "insufficient funds for gas * price + value",
+            "execution reverted",
+            "transaction underpriced",
+        ] {
+            assert!(!is_nonce_error(other), "{other}");
+        }
+    }
+}
@@ -0,0 +1,1632 @@
+#![allow(missing_docs)]
+use alloy::primitives::{utils::parse_ether, Address};
+use alloy::providers::Provider;
+use alloy::signers::local::PrivateKeySigner;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dotenv::dotenv;
+use eyre::{eyre, Result};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use swap_manager_avs_operator::abi::{
+    check_bindings, regenerate_bindings, DEFAULT_BINDINGS_DIR, DEFAULT_CONTRACTS_DIR,
+};
+use swap_manager_avs_operator::audit::verify_audit_log;
+use swap_manager_avs_operator::bench::{run_bench, BenchConfig, BenchFormat};
+use swap_manager_avs_operator::bls::BlsKey;
+use swap_manager_avs_operator::config::{ContractAddresses, OperatorConfig, CONFIG_PATH_ENV};
+use swap_manager_avs_operator::fleet::{fleet_key, Fleet};
+use swap_manager_avs_operator::ha::HaConfig;
+use swap_manager_avs_operator::health::HealthConfig;
+use swap_manager_avs_operator::key_rotation::{
+    load_or_create_key, retire_old_key, rotate_signing_key, KeyRotation, RotationPhase,
+};
+use swap_manager_avs_operator::log_file::{LogFileArgs, LogFileConfig};
+use swap_manager_avs_operator::logging::{init_tracing, sdk_logger, LogFormat};
+use swap_manager_avs_operator::metadata::check_operator_metadata;
+use swap_manager_avs_operator::registration::{
+    deregister_operator, join_quorum, register_operator, stake_status, update_metadata_uri,
+    update_stake,
+};
+use swap_manager_avs_operator::reorg::ReorgConfig;
+use swap_manager_avs_operator::rewards::{claim_rewards, RewardsTracker};
+use swap_manager_avs_operator::rpc_pool::RpcStack;
+use swap_manager_avs_operator::secrets::SECRET_SCHEME;
+use swap_manager_avs_operator::secrets_file::{read_secrets_password, SecretsFile};
+use swap_manager_avs_operator::service::Operator;
+use swap_manager_avs_operator::services::Services;
+use swap_manager_avs_operator::shutdown::{log_summary, wait_for_signal, Shutdown};
+use swap_manager_avs_operator::signer::{OperatorSigner, SignerKind};
+use swap_manager_avs_operator::slo::{LatencyReport, ReportFormat};
+use swap_manager_avs_operator::snapshot::Snapshot;
+use swap_manager_avs_operator::socket::announce_socket;
+use swap_manager_avs_operator::task_export::{render_tasks, ExportFormat};
+use swap_manager_avs_operator::task_handler::TaskHandlers;
+use swap_manager_avs_operator::task_receipt::{ReceiptFormat, TaskReceipt};
+use swap_manager_avs_operator::task_source::{HistoricalTaskSource, StoredTaskSource, TaskSource};
+use swap_manager_avs_operator::task_store::connect_task_store;
+use swap_manager_avs_operator::testutils::{Devnet, DevnetConfig};
+use swap_manager_avs_operator::top::{self, TopSources};
+use tracing::{info, warn};
+
+/// Command line arguments of the operator management tool
+#[derive(Parser, Debug)]
+#[command(
+    name = "operator",
+    about = "Manage the operator's keys and its EigenLayer and AVS registration"
+)]
+pub struct OperatorCli {
+    /// Path of the operator config file
+    #[arg(long, env = CONFIG_PATH_ENV, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Log output format, overrides `log_format` of the config file
+    #[arg(long, value_enum, global = true)]
+    pub log_format: Option<LogFormat>,
+
+    #[command(flatten)]
+    pub log_file: LogFileArgs,
+
+    /// Encrypted JSON keystore of the operator, selects the keystore signer
+    #[arg(long, global = true)]
+    pub keystore: Option<PathBuf>,
+
+    /// File holding the keystore password, prompted if unset
+    #[arg(long, requires = "keystore", global = true)]
+    pub keystore_password_file: Option<PathBuf>,
+
+    /// Deployment of the connected chain, overrides `deployment_file` of the config file
+    #[arg(long, global = true)]
+    pub deployment_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: OperatorCommand,
+}
+
+/// Operator actions
+#[derive(Subcommand, Debug)]
+pub enum OperatorCommand {
+    /// Register with EigenLayer and the AVS stake registry, skipping steps already done
+    Register {
+        /// Operator metadata URI, overrides `operator.metadata_uri` of the config file
+        #[arg(long)]
+        metadata_uri: Option<String>,
+    },
+    /// Deregister from the AVS stake registry
+    Deregister,
+    /// Join or leave the quorum of the AVS stake registry and refresh the operator's weight
+    Quorum {
+        #[command(subcommand)]
+        command: QuorumCommand,
+    },
+    /// Announce the socket the aggregator reaches the operator at
+    Socket {
+        #[command(subcommand)]
+        command: SocketCommand,
+    },
+    /// Publish the operator metadata shown by the EigenLayer AVS directory
+    Metadata {
+        #[command(subcommand)]
+        command: MetadataCommand,
+    },
+    /// Rotate the operator's signing key in the stake registry. The first run makes a new
+    /// key the signing key and hands the task store over to it, a run once
+    /// `rotation.overlap_secs` have passed retires the old key
+    RotateKey(RotateKeyArgs),
+    /// Read the operator's EigenLayer rewards or claim them
+    Rewards {
+        #[command(subcommand)]
+        command: RewardsCommand,
+    },
+    /// Manage operator keys
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+    /// Manage the encrypted secrets file that `secret://<name>` config values are read
+    /// from with `secrets.backend = "file"`
+    Secrets {
+        #[command(flatten)]
+        file: SecretsFileArgs,
+
+        #[command(subcommand)]
+        command: SecretsCommand,
+    },
+    /// Inspect the audit log of the signed payloads and sent transactions
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+    /// Push the tasks created in a past block range through the response pipeline
+    Replay {
+        /// First block to read tasks from
+        #[arg(long)]
+        from_block: u64,
+
+        /// Last block to read tasks from, inclusive
+        #[arg(long)]
+        to_block: u64,
+
+        /// Simulate respondToTask with eth_call instead of sending it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the latency statistics of the tasks in the task store over a time window
+    SloReport(SloReportArgs),
+    /// Inspect the tasks recorded in the task store
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+    /// Move the operator between hosts: export the task store, the block cursor, the nonces
+    /// and the config fingerprint into a tarball, or restore them from one
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+    /// Watch a running operator in a live terminal view: block, task rate, latency,
+    /// pending transactions, balance and recent errors
+    Top(TopArgs),
+    /// Run several operators with keys of their own in this process until SIGINT/SIGTERM,
+    /// funded and registered on an anvil chain and all responding to the same tasks
+    Fleet(FleetArgs),
+    /// Measure the response pipeline on a fresh anvil devnet: create tasks at a target rate,
+    /// respond to them and print the throughput, latency percentiles and CPU and memory use
+    Bench(BenchArgs),
+    /// Check or regenerate the contract bindings of `swap_manager_utils` from the compiled
+    /// ABIs, run from the repository root
+    Abi {
+        #[command(subcommand)]
+        command: AbiCommand,
+    },
+    /// Run a local anvil devnet with the contracts deployed until SIGINT/SIGTERM
+    Devnet {
+        /// Chain to fork instead of loading the bundled anvil state
+        #[arg(long)]
+        fork_url: Option<String>,
+
+        /// Block to fork at, the latest if unset
+        #[arg(long, requires = "fork_url")]
+        fork_block_number: Option<u64>,
+
+        /// Host port anvil is published on
+        #[arg(long, default_value_t = 8545)]
+        port: u16,
+
+        /// Account to fund, repeatable
+        #[arg(long)]
+        fund: Vec<Address>,
+
+        /// Ether given to each funded account
+        #[arg(long, default_value = "100")]
+        balance_eth: String,
+    },
+}
+
+/// Window and output of `operator slo-report`
+#[derive(Args, Debug)]
+pub struct SloReportArgs {
+    /// Hours before `--until` the window starts at
+    #[arg(long, default_value_t = 24)]
+    pub hours: u64,
+
+    /// End of the window as an RFC 3339 time, now if unset
+    #[arg(long)]
+    pub until: Option<String>,
+
+    /// Latency objective in seconds, the report gives the share of responses within it
+    #[arg(long)]
+    pub target_secs: Option<u64>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+
+    /// File the report is written to instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// New key and overlap of `operator rotate-key`, which must run with the operator key
+#[derive(Args, Debug)]
+pub struct RotateKeyArgs {
+    /// Keystore of the new signing key, generated unless it exists
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// File holding the password of the new keystore, prompted if unset
+    #[arg(long)]
+    pub password_file: Option<PathBuf>,
+
+    /// Seconds before the old key can be retired, overrides `rotation.overlap_secs`
+    #[arg(long)]
+    pub overlap_secs: Option<u64>,
+
+    /// Run the checks and simulate updateOperatorSigningKey, without writing the keystore,
+    /// the task store or the state file
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Endpoints watched by `operator top`, those of the config file by default
+#[derive(Args, Debug)]
+pub struct TopArgs {
+    /// Prometheus endpoint of the operator, http://<metrics_addr>/metrics if unset
+    #[arg(long)]
+    pub metrics_url: Option<String>,
+
+    /// gRPC control API of the operator, http://<control_addr> if unset
+    #[arg(long)]
+    pub control_url: Option<String>,
+
+    /// Seconds between two refreshes
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
+/// Size and chain of `operator fleet`
+#[derive(Args, Debug)]
+pub struct FleetArgs {
+    /// Operators to run
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub count: u32,
+
+    /// Seed the operator keys are derived from, a fleet started again with the same seed
+    /// reuses the operators it registered
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Ether each operator is funded with
+    #[arg(long, default_value = "10")]
+    pub balance_eth: String,
+
+    /// Start a local anvil devnet for the fleet instead of using `rpc_url` and `ws_url` of
+    /// the config file
+    #[arg(long)]
+    pub devnet: bool,
+}
+
+/// Load and output of `operator bench`
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Tasks to create and measure
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    pub tasks: u32,
+
+    /// Tasks created per second
+    #[arg(long, default_value_t = 10.0, value_parser = parse_rate)]
+    pub rate: f64,
+
+    /// Seconds the tasks get to be responded to once the last one is created
+    #[arg(long, default_value_t = 60)]
+    pub timeout_secs: u64,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = BenchFormat::Text)]
+    pub format: BenchFormat,
+}
+
+/// Positive rate of `--rate`
+fn parse_rate(value: &str) -> std::result::Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(rate) => Err(format!("{rate} is not a positive rate")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Task store actions
+#[derive(Subcommand, Debug)]
+pub enum TasksCommand {
+    /// Withdraw a task not handled yet, so the operator skips it instead of responding.
+    /// The SwapManager can't cancel tasks, this only stops the operators sharing the store
+    Cancel {
+        /// Index of the task in the SwapManager
+        task_index: u32,
+    },
+    /// Dump the stored tasks with their outcome, response, gas used and latency
+    Export(ExportArgs),
+    /// Push the dead-lettered tasks through the response pipeline again
+    RetryDead(RetryDeadArgs),
+    /// Print the lifecycle of a task: creation, payload, computed response, response
+    /// transaction, gas, confirmations and errors
+    Show(ShowArgs),
+}
+
+/// Actions of `operator snapshot`
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommand {
+    /// Write the state of the operator to a tarball, stop the operator first so nothing
+    /// changes underneath
+    Create {
+        /// Tarball to write
+        file: PathBuf,
+    },
+    /// Restore the state of a tarball into the task store and cursor file of this host,
+    /// before starting the operator on it
+    Restore {
+        /// Tarball written by `operator snapshot create`
+        file: PathBuf,
+
+        /// Restore even if the config fingerprint differs or transactions sent before the
+        /// snapshot are not mined yet
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Window and output of `operator tasks export`
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+
+    /// Only the tasks first seen at or after this RFC 3339 time
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Only the tasks first seen before this RFC 3339 time
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// File the tasks are written to instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Task of `operator tasks show`
+#[derive(Args, Debug)]
+pub struct ShowArgs {
+    /// Index of the task in the SwapManager
+    pub task_index: u32,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ReceiptFormat::Text)]
+    pub format: ReceiptFormat,
+}
+
+/// Tasks of `operator tasks retry-dead`
+#[derive(Args, Debug)]
+pub struct RetryDeadArgs {
+    /// Index of a dead-lettered task to retry, repeatable, all of them if unset
+    #[arg(long)]
+    pub task: Vec<u32>,
+
+    /// Simulate respondToTask with eth_call instead of sending it, leaving the tasks
+    /// dead-lettered
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Quorum membership actions. The ECDSA stake registry of the AVS has a single quorum and
+/// keeps no operator socket, so there are no quorum numbers to pick nor socket to update
+#[derive(Subcommand, Debug)]
+pub enum QuorumCommand {
+    /// Print the registration, weight and minimum weight of the operator
+    Status,
+    /// Register with the quorum, once the operator's weight meets the minimum weight
+    Join,
+    /// Deregister from the quorum
+    Leave,
+    /// Refresh the weight the registry holds from the current delegated stake
+    UpdateStake,
+}
+
+/// Rewards actions, the rewards coordinator is `contracts.rewards_coordinator` or the one
+/// of the deployment
+#[derive(Subcommand, Debug)]
+pub enum RewardsCommand {
+    /// Print the operator directed rewards submitted per AVS and, with
+    /// `rewards.distribution` set, the amounts earned, claimed and claimable per token
+    Show,
+    /// Claim the rewards of the claimable root, building the proofs from
+    /// `rewards.distribution`
+    Claim {
+        /// Receiver of the tokens, overrides `rewards.recipient` of the config file
+        #[arg(long)]
+        recipient: Option<Address>,
+
+        /// Build and check the claim without sending processClaim
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Operator socket actions. The ECDSA stake registry keeps no socket, the aggregator
+/// holds the latest one each operator signed
+#[derive(Subcommand, Debug)]
+pub enum SocketCommand {
+    /// Sign the socket and announce it to the aggregator, replacing the one it holds
+    Announce {
+        /// `host:port` to announce, overrides `operator.socket` of the config file
+        #[arg(long)]
+        socket: Option<String>,
+    },
+}
+
+/// Operator metadata actions
+#[derive(Subcommand, Debug)]
+pub enum MetadataCommand {
+    /// Validate the metadata JSON served at a URI and point the operator at it on chain
+    Set(MetadataSetArgs),
+}
+
+/// Metadata published by `operator metadata set`
+#[derive(Args, Debug)]
+pub struct MetadataSetArgs {
+    /// URI serving the metadata JSON, overrides `operator.metadata_uri` of the config file
+    #[arg(long)]
+    pub uri: Option<String>,
+
+    /// Local copy of the metadata JSON, the URI must serve the same document
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+
+    /// Only validate the metadata, without sending updateOperatorMetadataURI
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Key management actions, these never read the config file
+#[derive(Subcommand, Debug)]
+pub enum KeysCommand {
+    /// Generate a new key and store it as an encrypted keystore
+    Generate {
+        /// Kind of key to generate
+        #[arg(long = "type", value_enum)]
+        key_type: KeyType,
+
+        /// Path of the keystore to write
+        #[arg(long)]
+        output: PathBuf,
+
+        /// File holding the keystore password, prompted if unset
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+    },
+    /// Store a hex private key in the macOS Keychain or the Windows Credential Manager
+    /// for `signer.backend = "os-keychain"`, replacing the key stored there before
+    Keychain {
+        /// File holding the hex private key, prompted if unset
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+
+        /// Keychain service, overrides `signer.keychain_service` of the config file
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Keychain account, overrides `signer.keychain_account` of the config file
+        #[arg(long)]
+        account: Option<String>,
+    },
+}
+
+/// Secrets file handled by `operator secrets`
+#[derive(Args, Debug)]
+pub struct SecretsFileArgs {
+    /// Path of the secrets file, overrides `secrets.file` of the config file
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+
+    /// File holding the password of the secrets file, prompted if unset
+    #[arg(long)]
+    pub password_file: Option<PathBuf>,
+}
+
+/// Actions on the secrets file, these only read the config file for its path
+#[derive(Subcommand, Debug)]
+pub enum SecretsCommand {
+    /// Create an empty secrets file locked with a new password
+    Init,
+    /// Store a secret, replacing the one of the same name
+    Set {
+        /// Name of the secret, referenced as `secret://<name>`
+        name: String,
+
+        /// Value of the secret, prompted without echo if unset so it stays out of the
+        /// shell history
+        #[arg(long)]
+        value: Option<String>,
+    },
+    /// Print a secret
+    Get {
+        /// Name of the secret
+        name: String,
+    },
+}
+
+/// Actions on the audit log, these only read the config file for its path
+#[derive(Subcommand, Debug)]
+pub enum AuditCommand {
+    /// Check that the entries of the log form an unbroken hash chain
+    Verify {
+        /// Path of the audit log, overrides `audit.path` of the config file
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+}
+
+/// Actions on the contract bindings, these never read the config file
+#[derive(Subcommand, Debug)]
+pub enum AbiCommand {
+    /// Fail if the bindings don't match the compiled ABIs
+    Check {
+        /// Foundry `out/` directory, or a directory of bare JSON ABIs such as `abis/`
+        #[arg(long, default_value = "contracts/out")]
+        out: PathBuf,
+    },
+    /// Compile the contracts with forge and regenerate the bindings from them
+    Generate {
+        /// Foundry project of the contracts
+        #[arg(long, default_value = DEFAULT_CONTRACTS_DIR)]
+        contracts: PathBuf,
+
+        /// Bindings module to replace
+        #[arg(long, default_value = DEFAULT_BINDINGS_DIR)]
+        bindings: PathBuf,
+    },
+}
+
+/// Kind of key handled by `operator keys`
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum KeyType {
+    /// BN254 key in eigensdk's BLS keystore format
+    Bls,
+}
+
+impl OperatorCli {
+    /// Load the config file and apply the command line overrides on top of it
+    pub fn into_config(self) -> Result<(OperatorConfig, OperatorCommand)> {
+        let mut config = OperatorConfig::read(self.config.as_deref())?;
+        if let Some(log_format) = self.log_format {
+            config.log_format = log_format;
+        }
+        self.log_file.apply(&mut config.log_file);
+        if self.keystore.is_some() {
+            config.signer.backend = SignerKind::Keystore;
+            config.signer.keystore = self.keystore;
+        }
+        if self.keystore_password_file.is_some() {
+            config.signer.password_file = self.keystore_password_file;
+        }
+        if self.deployment_file.is_some() {
+            config.deployment_file = self.deployment_file;
+        }
+        if let OperatorCommand::Register {
+            metadata_uri: Some(metadata_uri),
+        } = &self.command
+        {
+            config.operator.metadata_uri = metadata_uri.clone();
+        }
+        if let OperatorCommand::Metadata {
+            command:
+                MetadataCommand::Set(MetadataSetArgs {
+                    uri: Some(metadata_uri),
+                    ..
+                }),
+        } = &self.command
+        {
+            config.operator.metadata_uri = metadata_uri.clone();
+        }
+        if let OperatorCommand::Socket {
+            command: SocketCommand::Announce {
+                socket: Some(socket),
+            },
+        } = &self.command
+        {
+            config.operator.socket = Some(socket.clone());
+        }
+        if let OperatorCommand::RotateKey(RotateKeyArgs {
+            overlap_secs: Some(overlap_secs),
+            ..
+        }) = &self.command
+        {
+            config.rotation.overlap_secs = *overlap_secs;
+        }
+        if let OperatorCommand::Replay { dry_run: true, .. }
+        | OperatorCommand::Tasks {
+            command: TasksCommand::RetryDead(RetryDeadArgs { dry_run: true, .. }),
+        } = &self.command
+        {
+            config.dry_run = true;
+        }
+        config.validate()?;
+        Ok((config, self.command))
+    }
+}
+
+/// Password of a keystore, read from `password_file` or prompted, twice if `new`
+fn keystore_password(password_file: Option<&Path>, new: bool) -> Result<String> {
+    Ok(match password_file {
+        Some(file) => std::fs::read_to_string(file)?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        None if new => {
+            let password = rpassword::prompt_password("New keystore password: ")?;
+            if rpassword::prompt_password("Repeat the password: ")? != password {
+                return Err(eyre!("passwords do not match"));
+            }
+            password
+        }
+        None => rpassword::prompt_password("Keystore password: ")?,
+    })
+}
+
+fn generate_key(key_type: KeyType, output: &Path, password_file: Option<&Path>) -> Result<()> {
+    if output.exists() {
+        return Err(eyre!("{} already exists", output.display()));
+    }
+    let password = keystore_password(password_file, true)?;
+    match key_type {
+        KeyType::Bls => {
+            let key = BlsKey::generate();
+            key.save(output, &password)?;
+            println!("BLS keystore written to {}", output.display());
+            println!("Public key: {}", key.public_key_string());
+        }
+    }
+    Ok(())
+}
+
+fn store_keychain_key(
+    config_path: Option<&Path>,
+    key_file: Option<&Path>,
+    service: Option<&str>,
+    account: Option<&str>,
+) -> Result<()> {
+    let mut signer = OperatorConfig::read(config_path)?.signer;
+    if let Some(service) = service {
+        signer.keychain_service = Some(service.to_string());
+    }
+    if let Some(account) = account {
+        signer.keychain_account = Some(account.to_string());
+    }
+    let key = match key_file {
+        Some(file) => std::fs::read_to_string(file)?.trim().to_string(),
+        None => rpassword::prompt_password("Private key: ")?,
+    };
+    let address = key
+        .parse::<PrivateKeySigner>()
+        .map_err(|e| eyre!("invalid private key: {e}"))?
+        .address();
+    let entry = signer.keychain_entry();
+    entry.store(&key)?;
+    println!(
+        "Key of {address} stored in the OS keychain as account {} of {}",
+        entry.account, entry.service
+    );
+    Ok(())
+}
+
+fn manage_secrets(
+    config_path: Option<&Path>,
+    args: &SecretsFileArgs,
+    command: &SecretsCommand,
+) -> Result<()> {
+    let config = OperatorConfig::read(config_path)?;
+    let path = args.file.clone().unwrap_or(config.secrets.file);
+    let password_file = args
+        .password_file
+        .as_ref()
+        .or(config.secrets.password_file.as_ref());
+    match command {
+        SecretsCommand::Init => {
+            let password = match password_file {
+                Some(file) => read_secrets_password(Some(file), &path)?,
+                None => {
+                    let password = rpassword::prompt_password("New secrets file password: ")?;
+                    if rpassword::prompt_password("Repeat the password: ")? != password {
+                        return Err(eyre!("passwords do not match"));
+                    }
+                    password
+                }
+            };
+            SecretsFile::create(&path, &password)?;
+            println!("Secrets file written to {}", path.display());
+        }
+        SecretsCommand::Set { name, value } => {
+            let password = read_secrets_password(password_file.map(PathBuf::as_path), &path)?;
+            let mut file = SecretsFile::open(&path, &password)?;
+            let value = match value {
+                Some(value) => value.clone(),
+                None => rpassword::prompt_password(format!("Value of {name}: "))?,
+            };
+            file.set(name, &value);
+            file.save()?;
+            println!("Stored {name}, reference it as {SECRET_SCHEME}{name}");
+        }
+        SecretsCommand::Get { name } => {
+            let password = read_secrets_password(password_file.map(PathBuf::as_path), &path)?;
+            let file = SecretsFile::open(&path, &password)?;
+            let value = file
+                .get(name)
+                .ok_or_else(|| eyre!("no secret {name} in {}", path.display()))?;
+            println!("{value}");
+        }
+    }
+    Ok(())
+}
+
+fn manage_bindings(command: &AbiCommand) -> Result<()> {
+    match command {
+        AbiCommand::Check { out } => {
+            let drifts = check_bindings(out)?;
+            if !drifts.is_empty() {
+                for drift in &drifts {
+                    eprintln!("{drift}");
+                }
+                return Err(eyre!(
+                    "bindings don't match the ABIs in {}, run `operator abi generate`",
+                    out.display()
+                ));
+            }
+            println!("Bindings match the ABIs in {}", out.display());
+        }
+        AbiCommand::Generate {
+            contracts,
+            bindings,
+        } => {
+            regenerate_bindings(contracts, bindings)?;
+            println!("Bindings regenerated in {}", bindings.display());
+        }
+    }
+    Ok(())
+}
+
+fn verify_audit(config_path: Option<&Path>, file: Option<&Path>) -> Result<()> {
+    let path = match file {
+        Some(file) => file.to_path_buf(),
+        None => OperatorConfig::read(config_path)?.audit.path,
+    };
+    let summary = verify_audit_log(&path)?;
+    println!(
+        "Audit log {} is intact: {} entries, last hash {}",
+        path.display(),
+        summary.entries,
+        summary.head
+    );
+    Ok(())
+}
+
+async fn manage_quorum(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: &OperatorSigner,
+    contracts: &ContractAddresses,
+    command: QuorumCommand,
+) -> Result<()> {
+    match command {
+        QuorumCommand::Status => {
+            let status = stake_status(
+                &config.rpc_url,
+                services,
+                contracts.stake_registry,
+                signer.address(),
+            )
+            .await?;
+            println!("operator:         {}", signer.address());
+            println!("registered:       {}", status.registered);
+            println!("weight:           {}", status.weight);
+            println!("minimum weight:   {}", status.minimum_weight);
+            println!("total weight:     {}", status.total_weight);
+            println!("threshold weight: {}", status.threshold_weight);
+            println!("share:            {:.2}%", status.share() * 100.0);
+        }
+        QuorumCommand::Join => {
+            join_quorum(
+                sdk_logger(config.log_level()?),
+                &config.rpc_url,
+                services,
+                signer,
+                contracts,
+            )
+            .await?
+        }
+        QuorumCommand::Leave => {
+            deregister_operator(&config.rpc_url, services, signer, contracts.stake_registry).await?
+        }
+        QuorumCommand::UpdateStake => {
+            update_stake(&config.rpc_url, services, signer, contracts.stake_registry).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn set_metadata(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: &OperatorSigner,
+    delegation_manager: Address,
+    args: &MetadataSetArgs,
+) -> Result<()> {
+    let uri = &config.operator.metadata_uri;
+    if uri.is_empty() {
+        return Err(eyre!(
+            "no metadata URI, pass --uri or set operator.metadata_uri"
+        ));
+    }
+    check_operator_metadata(uri, args.file.as_deref()).await?;
+    info!(uri, "Operator metadata is valid");
+    if args.dry_run {
+        return Ok(());
+    }
+    update_metadata_uri(&config.rpc_url, services, signer, delegation_manager, uri).await?;
+    Ok(())
+}
+
+async fn run(config: &OperatorConfig, services: &Services, command: OperatorCommand) -> Result<()> {
+    // The fleet and the benchmark bring their own keys
+    if let OperatorCommand::Fleet(args) = command {
+        return run_fleet(config, services, &args).await;
+    }
+    if let OperatorCommand::Bench(args) = command {
+        return run_benchmark(config, &args).await;
+    }
+    let signer = config
+        .signer_backend()?
+        .load()
+        .await?
+        .services(services.clone());
+    if let OperatorCommand::SloReport(args) = command {
+        return slo_report(config, signer.address(), &args).await;
+    }
+    if let OperatorCommand::Tasks {
+        command: TasksCommand::Export(args),
+    } = command
+    {
+        return export_tasks(config, signer.address(), &args).await;
+    }
+    if let OperatorCommand::Tasks {
+        command: TasksCommand::Show(args),
+    } = command
+    {
+        return show_task(config, services, signer.address(), &args).await;
+    }
+    if let OperatorCommand::Tasks {
+        command: TasksCommand::Cancel { task_index },
+    } = command
+    {
+        return cancel_task(config, signer.address(), task_index).await;
+    }
+    if let OperatorCommand::Socket {
+        command: SocketCommand::Announce { .. },
+    } = command
+    {
+        let socket = config
+            .operator_socket()?
+            .ok_or_else(|| eyre!("no socket, pass --socket or set operator.socket"))?;
+        let aggregator_url = config
+            .operator
+            .aggregator_url
+            .as_deref()
+            .ok_or_else(|| eyre!("operator.aggregator_url must be set to announce a socket"))?;
+        announce_socket(aggregator_url, &signer, &socket).await?;
+        return Ok(());
+    }
+    if let OperatorCommand::Rewards { command } = command {
+        return manage_rewards(config, services, &signer, command).await;
+    }
+    let contracts = config.contract_addresses(services).await?;
+    match command {
+        OperatorCommand::Register { .. } => {
+            register_operator(
+                sdk_logger(config.log_level()?),
+                &config.rpc_url,
+                services,
+                &signer,
+                &contracts,
+                &config.operator.metadata_uri,
+            )
+            .await?
+        }
+        OperatorCommand::Deregister => {
+            deregister_operator(&config.rpc_url, services, &signer, contracts.stake_registry)
+                .await?
+        }
+        OperatorCommand::RotateKey(args) => {
+            rotate_key(config, services, &signer, contracts.stake_registry, &args).await?
+        }
+        OperatorCommand::Quorum { command } => {
+            manage_quorum(config, services, &signer, &contracts, command).await?
+        }
+        OperatorCommand::Metadata {
+            command: MetadataCommand::Set(args),
+        } => {
+            set_metadata(
+                config,
+                services,
+                &signer,
+                contracts.delegation_manager,
+                &args,
+            )
+            .await?
+        }
+        OperatorCommand::Replay {
+            from_block,
+            to_block,
+            ..
+        } => {
+            replay(
+                config,
+                services,
+                signer,
+                contracts.swap_manager,
+                from_block,
+                to_block,
+            )
+            .await?
+        }
+        OperatorCommand::Tasks {
+            command: TasksCommand::RetryDead(args),
+        } => retry_dead(config, services, signer, contracts.swap_manager, &args).await?,
+        OperatorCommand::Snapshot { command } => {
+            manage_snapshot(
+                config,
+                services,
+                signer.address(),
+                contracts.swap_manager,
+                command,
+            )
+            .await?
+        }
+        OperatorCommand::SloReport(_)
+        | OperatorCommand::Tasks {
+            command: TasksCommand::Export(_) | TasksCommand::Show(_) | TasksCommand::Cancel { .. },
+        }
+        | OperatorCommand::Socket { .. } => {
+            unreachable!("the task store and socket commands need no contracts")
+        }
+        OperatorCommand::Rewards { .. } => {
+            unreachable!("the rewards commands only need the rewards coordinator")
+        }
+        OperatorCommand::Top(_) => unreachable!("top runs without a signer"),
+        OperatorCommand::Fleet(_) | OperatorCommand::Bench(_) => {
+            unreachable!("the fleet and the benchmark run with keys of their own")
+        }
+        OperatorCommand::Keys { .. }
+        | OperatorCommand::Secrets { .. }
+        | OperatorCommand::Audit { .. }
+        | OperatorCommand::Abi { .. }
+        | OperatorCommand::Devnet { .. } => {
+            unreachable!(
+                "keys, secrets, audit, abi and devnet commands run without a validated config"
+            )
+        }
+    }
+    Ok(())
+}
+
+async fn manage_rewards(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: &OperatorSigner,
+    command: RewardsCommand,
+) -> Result<()> {
+    let rewards_coordinator = config.rewards_coordinator(services).await?;
+    match command {
+        RewardsCommand::Show => {
+            let earner = config.operator_address(signer.address());
+            let summary = RewardsTracker::new(
+                &config.rpc_url,
+                services,
+                rewards_coordinator,
+                earner,
+                config.rewards_from_block(services).await?,
+                &config.rewards,
+                &config.retry,
+            )?
+            .check()
+            .await?;
+            println!("earner: {earner}");
+            for (key, amount) in &summary.accrued {
+                println!(
+                    "accrued from {}: {amount} of {}",
+                    services.named(key.avs),
+                    services.named(key.token)
+                );
+            }
+            for (token, rewards) in &summary.tokens {
+                println!(
+                    "{}: earned {}, claimed {}, claimable {}",
+                    services.named(*token),
+                    rewards.earned,
+                    rewards.claimed,
+                    rewards.claimable()
+                );
+            }
+        }
+        RewardsCommand::Claim { recipient, dry_run } => {
+            if let Some(tx_hash) = claim_rewards(
+                config,
+                services,
+                signer,
+                rewards_coordinator,
+                recipient,
+                dry_run,
+            )
+            .await?
+            {
+                println!("Rewards claimed in {tx_hash}");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn rotate_key(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: &OperatorSigner,
+    stake_registry: Address,
+    args: &RotateKeyArgs,
+) -> Result<()> {
+    let state_file = &config.rotation.state_file;
+    let now = chrono::Utc::now().timestamp();
+    if let Some(mut rotation) = KeyRotation::load(state_file)? {
+        match rotation.phase(now) {
+            RotationPhase::Overlap { remaining_secs } => {
+                println!(
+                    "Signing key of {} rotated from {} to {}, the old key can be retired in {remaining_secs}s",
+                    rotation.operator, rotation.old_key, rotation.new_key
+                );
+                return Ok(());
+            }
+            RotationPhase::Retirable => {
+                retire_old_key(
+                    config,
+                    services,
+                    &mut rotation,
+                    stake_registry,
+                    now,
+                    args.dry_run,
+                )
+                .await?;
+                if !args.dry_run {
+                    rotation.save(state_file)?;
+                }
+                println!(
+                    "Retired {}, {} signs for {} from now on",
+                    rotation.old_key, rotation.new_key, rotation.operator
+                );
+                if rotation.old_key != rotation.operator {
+                    println!("The keystore of {} can be destroyed", rotation.old_key);
+                }
+                return Ok(());
+            }
+            RotationPhase::Retired => {}
+        }
+    }
+
+    let output = args
+        .output
+        .as_deref()
+        .ok_or_else(|| eyre!("pass --output, the keystore of the new signing key"))?;
+    let new_key = if args.dry_run && !output.exists() {
+        PrivateKeySigner::random().address()
+    } else {
+        let password = keystore_password(args.password_file.as_deref(), !output.exists())?;
+        load_or_create_key(output, &password)?.address()
+    };
+    let rotation = rotate_signing_key(
+        config,
+        services,
+        signer,
+        stake_registry,
+        new_key,
+        output,
+        args.dry_run,
+    )
+    .await?;
+    if args.dry_run {
+        println!(
+            "Dry run: the signing key of {} would be rotated from {} to {new_key}",
+            rotation.operator, rotation.old_key
+        );
+        return Ok(());
+    }
+    rotation.save(state_file)?;
+    println!(
+        "Signing key of {} rotated from {} to {new_key}, {} tasks handed over",
+        rotation.operator, rotation.old_key, rotation.moved_tasks
+    );
+    println!(
+        "Restart the operator with signer.keystore = {:?} and signer.operator_address = {:?}",
+        output.display().to_string(),
+        rotation.operator.to_string()
+    );
+    println!(
+        "Run `operator rotate-key` again in {}s to retire {}",
+        config.rotation.overlap_secs, rotation.old_key
+    );
+    Ok(())
+}
+
+async fn replay(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: OperatorSigner,
+    swap_manager_address: Address,
+    from_block: u64,
+    to_block: u64,
+) -> Result<()> {
+    if from_block > to_block {
+        return Err(eyre!(
+            "--from-block {from_block} is after --to-block {to_block}"
+        ));
+    }
+    let source = HistoricalTaskSource::new(
+        &config.rpc_url,
+        services,
+        swap_manager_address,
+        from_block,
+        to_block,
+        &config.retry,
+    )?;
+    run_tasks(config, services, signer, swap_manager_address, source).await
+}
+
+async fn retry_dead(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: OperatorSigner,
+    swap_manager_address: Address,
+    args: &RetryDeadArgs,
+) -> Result<()> {
+    let store = connect_task_store(&config.store, signer.address())
+        .await?
+        .ok_or_else(|| eyre!("the task store is disabled, set store.path or store.url"))?;
+    let mut tasks = store.dead_letters().await?;
+    if !args.task.is_empty() {
+        tasks.retain(|task| args.task.contains(&task.task_index));
+    }
+    // Revived first, or the store would skip them as handled
+    if !config.dry_run {
+        for task in &tasks {
+            store.revive(task.task_index).await?;
+        }
+    }
+    store.close().await;
+    if tasks.is_empty() {
+        info!("No dead-lettered task to retry");
+        return Ok(());
+    }
+    info!(tasks = tasks.len(), "Retrying the dead-lettered tasks");
+    run_tasks(
+        config,
+        services,
+        signer,
+        swap_manager_address,
+        StoredTaskSource::new(&tasks),
+    )
+    .await
+}
+
+async fn cancel_task(config: &OperatorConfig, operator: Address, task_index: u32) -> Result<()> {
+    let store = connect_task_store(&config.store, operator)
+        .await?
+        .ok_or_else(|| eyre!("the task store is disabled, set store.path or store.url"))?;
+    let cancelled = store.cancel(task_index).await?;
+    let stored = store.task(task_index).await?;
+    store.close().await;
+    if cancelled {
+        info!(task_index, "Task cancelled, the operator will skip it");
+        return Ok(());
+    }
+    match stored.and_then(|task| task.outcome) {
+        Some(outcome) => Err(eyre!(
+            "task {task_index} is already {}, it can't be cancelled",
+            outcome.as_str()
+        )),
+        None => Err(eyre!(
+            "task {task_index} is not in the task store, the operator hasn't seen it yet"
+        )),
+    }
+}
+
+/// Respond to the tasks of `source` once, next to a running operator
+async fn run_tasks(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: OperatorSigner,
+    swap_manager_address: Address,
+    source: impl TaskSource + 'static,
+) -> Result<()> {
+    // Every replayed task gets a response, and nothing is served or watched that a
+    // running operator could already be serving or watching
+    let mut builder = Operator::builder()
+        .services(services.clone())
+        .config(config)?
+        .signer(signer.clone())
+        .swap_manager_address(swap_manager_address)
+        .task_source(source)
+        .shutdown(Shutdown::on_signal())
+        .response_percentage(100.0)
+        .reorg(ReorgConfig {
+            enabled: false,
+            ..config.reorg.clone()
+        })
+        .health(HealthConfig {
+            listen_addr: None,
+            ..config.health.clone()
+        })
+        .ha(HaConfig {
+            leader_election: false,
+            ..config.ha.clone()
+        });
+    // The store skips the tasks already handled, dry runs leave it untouched
+    if !config.dry_run {
+        if let Some(store) = connect_task_store(&config.store, signer.address()).await? {
+            builder = builder.task_store(Arc::from(store));
+        }
+    }
+    let operator = builder.build()?;
+    let control = operator.control();
+    operator.run().await?;
+    log_summary(&control, services.tx_costs(), 0);
+    Ok(())
+}
+
+async fn slo_report(
+    config: &OperatorConfig,
+    operator: Address,
+    args: &SloReportArgs,
+) -> Result<()> {
+    let store = connect_task_store(&config.store, operator)
+        .await?
+        .ok_or_else(|| eyre!("the task store is disabled, set store.path or store.url"))?;
+    let to = match &args.until {
+        Some(until) => parse_time("--until", until)?,
+        None => chrono::Utc::now().timestamp(),
+    };
+    let from = to.saturating_sub(i64::try_from(args.hours.saturating_mul(3_600))?);
+    let latencies = store.latencies(from, to).await?;
+    store.close().await;
+    let report = LatencyReport::new(from, to, &latencies, args.target_secs).render(args.format)?;
+    match &args.output {
+        Some(output) => {
+            std::fs::write(output, report)?;
+            info!(output = %output.display(), "SLO report written");
+        }
+        None => print!("{report}"),
+    }
+    Ok(())
+}
+
+async fn export_tasks(config: &OperatorConfig, operator: Address, args: &ExportArgs) -> Result<()> {
+    let store = connect_task_store(&config.store, operator)
+        .await?
+        .ok_or_else(|| eyre!("the task store is disabled, set store.path or store.url"))?;
+    let from = args
+        .from
+        .as_deref()
+        .map(|from| parse_time("--from", from))
+        .transpose()?
+        .unwrap_or(0);
+    let to = args
+        .to
+        .as_deref()
+        .map(|to| parse_time("--to", to))
+        .transpose()?
+        .unwrap_or(i64::MAX);
+    let tasks = store.export(from, to).await?;
+    store.close().await;
+    let rendered = render_tasks(&tasks, args.format)?;
+    match &args.output {
+        Some(output) => {
+            std::fs::write(output, rendered)?;
+            info!(tasks = tasks.len(), output = %output.display(), "Tasks exported");
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
+
+async fn show_task(
+    config: &OperatorConfig,
+    services: &Services,
+    operator: Address,
+    args: &ShowArgs,
+) -> Result<()> {
+    let store = connect_task_store(&config.store, operator)
+        .await?
+        .ok_or_else(|| eyre!("the task store is disabled, set store.path or store.url"))?;
+    let task = store.receipt(args.task_index).await?;
+    store.close().await;
+    let task = task.ok_or_else(|| eyre!("task {} is not in the task store", args.task_index))?;
+    // Confirmations are counted against the head, the receipt stands without them
+    let head = match services
+        .http_provider(&config.rpc_url)?
+        .get_block_number()
+        .await
+    {
+        Ok(head) => Some(head),
+        Err(e) => {
+            warn!(error = %e, "Failed to read the chain head, confirmations unknown");
+            None
+        }
+    };
+    let receipt = TaskReceipt::new(task, &TaskHandlers::default(), head).await;
+    print!("{}", receipt.render(args.format)?);
+    Ok(())
+}
+
+async fn manage_snapshot(
+    config: &OperatorConfig,
+    services: &Services,
+    operator: Address,
+    swap_manager: Address,
+    command: SnapshotCommand,
+) -> Result<()> {
+    match command {
+        SnapshotCommand::Create { file } => {
+            let snapshot = Snapshot::take(config, services, operator, swap_manager).await?;
+            snapshot.save(&file)?;
+            println!(
+                "Snapshot of {operator} written to {}: {} tasks, next block {}, nonce {}",
+                file.display(),
+                snapshot.tasks.len(),
+                snapshot
+                    .manifest
+                    .next_block
+                    .map_or_else(|| "unset".to_string(), |block| block.to_string()),
+                snapshot.manifest.nonce.pending
+            );
+        }
+        SnapshotCommand::Restore { file, force } => {
+            let snapshot = Snapshot::load(&file)?;
+            let restored = snapshot
+                .restore(config, services, operator, swap_manager, force)
+                .await?;
+            println!(
+                "Restored {restored} tasks of {operator} from {}, taken at {}",
+                file.display(),
+                snapshot.manifest.created_at
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Endpoints of `args`, or those the config file serves on, reached through the loopback
+/// address when bound to every interface
+fn top_sources(config: &OperatorConfig, args: &TopArgs) -> TopSources {
+    let local = |addr: SocketAddr| {
+        if addr.ip().is_unspecified() {
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), addr.port())
+        } else {
+            addr
+        }
+    };
+    TopSources {
+        rpc_url: config.rpc_url.clone(),
+        metrics_url: args.metrics_url.clone().or_else(|| {
+            config
+                .metrics_addr
+                .map(|addr| format!("http://{}/metrics", local(addr)))
+        }),
+        control_url: args.control_url.clone().or_else(|| {
+            config
+                .control_addr
+                .map(|addr| format!("http://{}", local(addr)))
+        }),
+    }
+}
+
+/// Unix time of the RFC 3339 `value` of `flag`
+fn parse_time(flag: &str, value: &str) -> Result<i64> {
+    Ok(chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|e| eyre!("invalid {flag} {value}: {e}"))?
+        .timestamp())
+}
+
+async fn run_fleet(config: &OperatorConfig, services: &Services, args: &FleetArgs) -> Result<()> {
+    let balance = parse_ether(&args.balance_eth)?;
+    let mut config = config.clone();
+    let devnet = if args.devnet {
+        Some(Devnet::start(&DevnetConfig::default()).await?)
+    } else {
+        None
+    };
+    let contracts = match &devnet {
+        Some(devnet) => {
+            config.rpc_url = devnet.http_url().to_string();
+            config.ws_url = devnet.ws_url().to_string();
+            devnet.contract_addresses()?
+        }
+        None => config.contract_addresses(services).await?,
+    };
+    let keys = (0..args.count)
+        .map(|index| fleet_key(args.seed, index))
+        .collect();
+    let shutdown = Shutdown::on_signal();
+    let fleet = Fleet::start(&config, &contracts, keys, balance, shutdown).await?;
+    let members = fleet.members().to_vec();
+    info!(
+        operators = members.len(),
+        rpc_url = %config.rpc_url,
+        swap_manager = %contracts.swap_manager,
+        aggregator = config.operator.aggregator_url.as_deref().unwrap_or("none"),
+        "Fleet running, stop it with Ctrl-C"
+    );
+    let result = fleet.join().await;
+    for member in &members {
+        println!(
+            "{}: {:?}",
+            member.signer.address(),
+            member.control.outcome_counts()
+        );
+    }
+    if let Some(devnet) = devnet {
+        devnet.stop().await?;
+    }
+    Ok(result?)
+}
+
+async fn run_benchmark(config: &OperatorConfig, args: &BenchArgs) -> Result<()> {
+    let bench = BenchConfig {
+        tasks: args.tasks,
+        rate_per_sec: args.rate,
+        timeout: Duration::from_secs(args.timeout_secs),
+    };
+    let report = run_bench(config, &bench).await?;
+    print!("{}", report.render(args.format)?);
+    Ok(())
+}
+
+async fn run_devnet(mut config: DevnetConfig, balance_eth: &str) -> Result<()> {
+    config.balance = parse_ether(balance_eth)?;
+    let devnet = Devnet::start(&config).await?;
+    let contracts = devnet.contract_addresses()?;
+    info!(
+        rpc_url = devnet.http_url(),
+        ws_url = devnet.ws_url(),
+        swap_manager = %contracts.swap_manager,
+        stake_registry = %contracts.stake_registry,
+        delegation_manager = %contracts.delegation_manager,
+        avs_directory = %contracts.avs_directory,
+        "Devnet ready, stop it with Ctrl-C"
+    );
+    wait_for_signal().await;
+    devnet.stop().await?;
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn main() {
+    dotenv().ok();
+    let cli = OperatorCli::parse();
+    if let OperatorCommand::Keys {
+        command:
+            KeysCommand::Generate {
+                key_type,
+                output,
+                password_file,
+            },
+    } = &cli.command
+    {
+        if let Err(e) = generate_key(*key_type, output, password_file.as_deref()) {
+            eprintln!("Failed to generate key: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let OperatorCommand::Keys {
+        command:
+            KeysCommand::Keychain {
+                key_file,
+                service,
+                account,
+            },
+    } = &cli.command
+    {
+        if let Err(e) = store_keychain_key(
+            cli.config.as_deref(),
+            key_file.as_deref(),
+            service.as_deref(),
+            account.as_deref(),
+        ) {
+            eprintln!("Failed to store the key in the OS keychain: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let OperatorCommand::Secrets { file, command } = &cli.command {
+        if let Err(e) = manage_secrets(cli.config.as_deref(), file, command) {
+            eprintln!("Secrets command failed: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let OperatorCommand::Audit {
+        command: AuditCommand::Verify { file },
+    } = &cli.command
+    {
+        if let Err(e) = verify_audit(cli.config.as_deref(), file.as_deref()) {
+            eprintln!("Audit log verification failed: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let OperatorCommand::Abi { command } = &cli.command {
+        if let Err(e) = manage_bindings(command) {
+            eprintln!("Bindings command failed: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let OperatorCommand::Devnet {
+        fork_url,
+        fork_block_number,
+        port,
+        fund,
+        balance_eth,
+    } = &cli.command
+    {
+        let mut log_file = LogFileConfig::default();
+        cli.log_file.clone().apply(&mut log_file);
+        let _tracing = init_tracing(
+            tracing::Level::INFO,
+            cli.log_format.unwrap_or_default(),
+            None,
+            &log_file,
+        );
+        let config = DevnetConfig {
+            fork_url: fork_url.clone(),
+            fork_block_number: *fork_block_number,
+            host_port: Some(*port),
+            fund: fund.clone(),
+            ..DevnetConfig::default()
+        };
+        if let Err(e) = run_devnet(config, balance_eth).await {
+            eprintln!("Devnet failed: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let (mut config, command) = match cli.into_config() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = config.resolve_secrets().await {
+        eprintln!("Failed to fetch the secrets: {:?}", e);
+        std::process::exit(1);
+    }
+    // Logs would be drawn over the terminal view, so top runs without them
+    if let OperatorCommand::Top(args) = &command {
+        let services = Services::rpc_only(RpcStack::failover(&config.rpc_url, &config.rpc));
+        let sources = top_sources(&config, args);
+        if let Err(e) = top::run(sources, services, Duration::from_secs(args.interval)).await {
+            eprintln!("Operator top failed: {:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let _tracing = init_tracing(
+        config.log_level().unwrap_or(tracing::Level::INFO),
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+        &config.log_file,
+    );
+    let services = match Services::from_config(&config) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("Failed to set up the operator services: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = config.resolve_names(&services).await {
+        eprintln!("Failed to resolve the ENS names: {:?}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = run(&config, &services, command).await {
+        eprintln!("Operator command failed: {:?}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::HashMap,
+    future::IntoFuture,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, U256};
+use async_trait::async_trait;
+use eigensdk::common::SdkProvider;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::{ChainError, ConfigError, OracleError};
+use crate::prometheus::time_rpc;
+use crate::services::Services;
+use crate::swap_payload::SwapTaskPayload;
+
+/// Public Coingecko API
+pub const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3";
+
+alloy::sol! {
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+}
+
+/// Where reference prices come from
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OracleSourceKind {
+    /// Chainlink USD feeds read on chain through `rpc_url`
+    #[default]
+    Chainlink,
+    /// The Coingecko simple price API
+    Coingecko,
+}
+
+/// A token the oracle prices
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OracleToken {
+    /// Symbol shown in the logs
+    pub symbol: String,
+    /// ERC-20 contract, as found in the swap payloads
+    pub address: Address,
+    /// Decimals of the token
+    pub decimals: u8,
+    /// Chainlink `<token>/USD` feed, needed by the chainlink source
+    pub chainlink_feed: Option<Address>,
+    /// Coingecko coin id, needed by the coingecko source
+    pub coingecko_id: Option<String>,
+}
+
+/// Swap price validation, the `[oracle]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct OracleConfig {
+    /// Refuse to respond to swap tasks whose price deviates from the reference
+    pub enabled: bool,
+    /// Which source the reference prices come from
+    pub source: OracleSourceKind,
+    /// Largest accepted deviation of a swap's implied price, in basis points
+    pub max_deviation_bps: u32,
+    /// Oldest accepted Chainlink answer
+    pub max_price_age_secs: u64,
+    /// Seconds a fetched price is reused
+    pub cache_secs: u64,
+    /// Coingecko API base URL
+    pub coingecko_url: String,
+    /// Coingecko demo API key (`COINGECKO_API_KEY`)
+    pub coingecko_api_key: Option<String>,
+    /// Tokens swaps may trade, a swap of any other token is refused
+    pub tokens: Vec<OracleToken>,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: OracleSourceKind::default(),
+            max_deviation_bps: 300,
+            max_price_age_secs: 3_600,
+            cache_secs: 30,
+            coingecko_url: COINGECKO_URL.to_string(),
+            coingecko_api_key: None,
+            tokens: Vec::new(),
+        }
+    }
+}
+
+impl OracleConfig {
+    /// Check that every token can be priced by the selected source
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.max_deviation_bps == 0 {
+            return Err(ConfigError::Invalid(
+                "oracle.max_deviation_bps must be greater than 0".to_string(),
+            ));
+        }
+        for token in &self.tokens {
+            let priced = match self.source {
+                OracleSourceKind::Chainlink => token.chainlink_feed.is_some(),
+                OracleSourceKind::Coingecko => token.coingecko_id.is_some(),
+            };
+            if !priced {
+                return Err(ConfigError::Invalid(format!(
+                    "oracle.tokens {} has no price for the {:?} source",
+                    token.symbol, self.source
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Source of USD prices
+#[async_trait]
+pub trait PriceSource: Send + Sync + std::fmt::Debug {
+    /// Current USD price of one whole `token`
+    async fn usd_price(&self, token: &OracleToken) -> Result<f64, OracleError>;
+}
+
+/// Prices read from Chainlink feeds
+#[derive(Debug)]
+pub struct ChainlinkSource {
+    provider: SdkProvider,
+    max_age: Duration,
+}
+
+impl ChainlinkSource {
+    /// Read the feeds through `rpc_url` and the RPC stack of `services`, refusing answers
+    /// older than `max_age`
+    pub fn new(rpc_url: &str, services: &Services, max_age: Duration) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            max_age,
+        })
+    }
+}
+
+#[async_trait]
+impl PriceSource for ChainlinkSource {
+    async fn usd_price(&self, token: &OracleToken) -> Result<f64, OracleError> {
+        let feed = token
+            .chainlink_feed
+            .ok_or(OracleError::UnknownToken(token.address))?;
+        let feed = AggregatorV3Interface::new(feed, &self.provider);
+        let decimals = time_rpc("decimals", feed.decimals().call().into_future())
+            .await
+            .map_err(ChainError::from)?
+            ._0;
+        let round = time_rpc(
+            "latestRoundData",
+            feed.latestRoundData().call().into_future(),
+        )
+        .await
+        .map_err(ChainError::from)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let updated_at = u64::try_from(round.updatedAt).unwrap_or(u64::MAX);
+        if !round.answer.is_positive() || now.saturating_sub(updated_at) > self.max_age.as_secs() {
+            return Err(OracleError::BadPrice {
+                symbol: token.symbol.clone(),
+            });
+        }
+        let answer = f64::from(round.answer.into_raw());
+        Ok(answer / 10f64.powi(i32::from(decimals)))
+    }
+}
+
+/// Prices fetched from the Coingecko simple price API
+#[derive(Debug)]
+pub struct CoingeckoSource {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl CoingeckoSource {
+    /// Query the API at `base_url`, authenticating with `api_key` if set
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoingeckoSource {
+    async fn usd_price(&self, token: &OracleToken) -> Result<f64, OracleError> {
+        let id = token
+            .coingecko_id
+            .as_deref()
+            .ok_or(OracleError::UnknownToken(token.address))?;
+        let mut request = self
+            .client
+            .get(format!("{}/simple/price", self.base_url))
+            .query(&[("ids", id), ("vs_currencies", "usd")]);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-cg-demo-api-key", api_key);
+        }
+        let prices: HashMap<String, HashMap<String, f64>> =
+            request.send().await?.error_for_status()?.json().await?;
+        prices
+            .get(id)
+            .and_then(|price| price.get("usd"))
+            .copied()
+            .filter(|price| *price > 0.0)
+            .ok_or_else(|| OracleError::BadPrice {
+                symbol: token.symbol.clone(),
+            })
+    }
+}
+
+/// Checks swap tasks against reference prices before the operator attests to them
+#[derive(Debug)]
+pub struct PriceOracle {
+    source: Box<dyn PriceSource>,
+    tokens: HashMap<Address, OracleToken>,
+    max_deviation_bps: u32,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<Address, (f64, Instant)>>,
+}
+
+impl PriceOracle {
+    /// Oracle pricing `tokens` through `source`
+    pub fn new(
+        source: impl PriceSource + 'static,
+        tokens: Vec<OracleToken>,
+        max_deviation_bps: u32,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            source: Box::new(source),
+            tokens: tokens
+                .into_iter()
+                .map(|token| (token.address, token))
+                .collect(),
+            max_deviation_bps,
+            cache_ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Oracle set in `config`, `None` if disabled
+    pub fn from_config(
+        config: &OracleConfig,
+        rpc_url: &str,
+        services: &Services,
+    ) -> Result<Option<Self>, ConfigError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        config.validate()?;
+        let (tokens, max_deviation_bps, cache_ttl) = (
+            config.tokens.clone(),
+            config.max_deviation_bps,
+            Duration::from_secs(config.cache_secs),
+        );
+        Ok(Some(match config.source {
+            OracleSourceKind::Chainlink => Self::new(
+                ChainlinkSource::new(
+                    rpc_url,
+                    services,
+                    Duration::from_secs(config.max_price_age_secs),
+                )?,
+                tokens,
+                max_deviation_bps,
+                cache_ttl,
+            ),
+            OracleSourceKind::Coingecko => Self::new(
+                CoingeckoSource::new(&config.coingecko_url, config.coingecko_api_key.clone()),
+                tokens,
+                max_deviation_bps,
+                cache_ttl,
+            ),
+        }))
+    }
+
+    /// Check the swap carried by the task named `task_name`, tasks without a swap payload
+    /// always pass
+    pub async fn check_task(&self, task_name: &str) -> Result<(), OracleError> {
+        match SwapTaskPayload::decode(task_name)? {
+            Some(payload) => self.check(&payload).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Fail if the price implied by the `min_amount_out` of `payload` is further than
+    /// `max_deviation_bps` from the reference price
+    pub async fn check(&self, payload: &SwapTaskPayload) -> Result<(), OracleError> {
+        let token_in = self.token(payload.token_in)?;
+        let token_out = self.token(payload.token_out)?;
+        let reference = self.usd_price(token_in).await? / self.usd_price(token_out).await?;
+        let implied = whole_amount(payload.min_amount_out, token_out.decimals)
+            / whole_amount(payload.amount_in, token_in.decimals);
+        let deviation_bps = ((implied / reference - 1.0).abs() * 10_000.0).round() as u64;
+        debug!(
+            token_in = token_in.symbol,
+            token_out = token_out.symbol,
+            implied,
+            reference,
+            deviation_bps,
+            "Checked swap price"
+        );
+        if !implied.is_finite() || deviation_bps > u64::from(self.max_deviation_bps) {
+            return Err(OracleError::Deviation {
+                deviation_bps,
+                max_deviation_bps: self.max_deviation_bps,
+            });
+        }
+        Ok(())
+    }
+
+    fn token(&self, address: Address) -> Result<&OracleToken, OracleError> {
+        self.tokens
+            .get(&address)
+            .ok_or(OracleError::UnknownToken(address))
+    }
+
+    async fn usd_price(&self, token: &OracleToken) -> Result<f64, OracleError> {
+        if let Some((price, fetched_at)) = self.cached(token.address) {
+            if fetched_at.elapsed() < self.cache_ttl {
+                return Ok(price);
+            }
+        }
+        let price = self.source.usd_price(token).await?;
+        self.cache
+            .lock()
+            .expect("price cache lock poisoned")
+            .insert(token.address, (price, Instant::now()));
+        Ok(price)
+    }
+
+    fn cached(&self, address: Address) -> Option<(f64, Instant)> {
+        self.cache
+            .lock()
+            .expect("price cache lock poisoned")
+            .get(&address)
+            .copied()
+    }
+}
+
+fn whole_amount(amount: U256, decimals: u8) -> f64 {
+    f64::from(amount) / 10f64.powi(i32::from(decimals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct FixedPrices;
+
+    #[async_trait]
+    impl PriceSource for FixedPrices {
+        async fn usd_price(&self, token: &OracleToken) -> Result<f64, OracleError> {
+            Ok(if token.symbol == "WETH" { 3_000.0 } else { 1.0 })
+        }
+    }
+
+    fn token(symbol: &str, byte: u8, decimals: u8) -> OracleToken {
+        OracleToken {
+            symbol: symbol.to_string(),
+            address: Address::with_last_byte(byte),
+            decimals,
+            chainlink_feed: None,
+            coingecko_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_swaps_far_from_the_reference_price_are_refused() {
+        let oracle = PriceOracle::new(
+            FixedPrices,
+            vec![token("WETH", 1, 18), token("USDC", 2, 6)],
+            300,
+            Duration::from_secs(30),
+        );
+        let mut payload = SwapTaskPayload {
+            token_in: Address::with_last_byte(1),
+            token_out: Address::with_last_byte(2),
+            amount_in: U256::from(10u128.pow(18)),
+            // 1 WETH for at least 2950 USDC, 1.7% under the reference
+            min_amount_out: U256::from(2_950_000_000u64),
+            slippage_bps: 50,
+            deadline: 0,
+        };
+        oracle.check(&payload).await.unwrap();
+
+        // 1 WETH for at least 2000 USDC leaves room for a sandwich
+        payload.min_amount_out = U256::from(2_000_000_000u64);
+        assert!(matches!(
+            oracle.check(&payload).await,
+            Err(OracleError::Deviation {
+                deviation_bps: 3333,
+                ..
+            })
+        ));
+
+        payload.token_out = Address::with_last_byte(3);
+        assert!(matches!(
+            oracle.check(&payload).await,
+            Err(OracleError::UnknownToken(_))
+        ));
+        oracle.check_task("QuickFox1").await.unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::SignerError;
+
+/// Keychain service the key is stored under unless `signer.keychain_service` is set
+pub const DEFAULT_KEYCHAIN_SERVICE: &str = "swap-manager-avs";
+
+/// Keychain account the key is stored under unless `signer.keychain_account` is set
+pub const DEFAULT_KEYCHAIN_ACCOUNT: &str = "operator";
+
+/// Entry of the OS keychain holding the hex private key of the `os-keychain` backend,
+/// a generic password of the macOS Keychain or a generic credential of the Windows
+/// Credential Manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeychainEntry {
+    /// Service of the entry, the target of the credential on Windows
+    pub service: String,
+    /// Account of the entry, the user name of the credential on Windows
+    pub account: String,
+}
+
+impl KeychainEntry {
+    /// Entry of `account` under `service`
+    pub fn new(service: impl Into<String>, account: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            account: account.into(),
+        }
+    }
+
+    /// Read the key stored in the entry
+    pub fn read(&self) -> Result<String, SignerError> {
+        platform::read(&self.service, &self.account)
+            .map(|key| key.trim().to_string())
+            .map_err(|reason| self.error("read", reason))
+    }
+
+    /// Store `key` in the entry, replacing the key stored before
+    pub fn store(&self, key: &str) -> Result<(), SignerError> {
+        platform::store(&self.service, &self.account, key)
+            .map_err(|reason| self.error("store", reason))
+    }
+
+    fn error(&self, action: &'static str, reason: String) -> SignerError {
+        SignerError::Keychain {
+            action,
+            service: self.service.clone(),
+            account: self.account.clone(),
+            reason,
+        }
+    }
+}
+
+/// Security framework generic passwords
+#[cfg(target_os = "macos")]
+mod platform {
+    use security_framework::{
+        base::Error,
+        passwords::{get_generic_password, set_generic_password},
+    };
+
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+    fn status_error(error: Error) -> String {
+        match error.code() {
+            ERR_SEC_ITEM_NOT_FOUND => "no such keychain item".to_string(),
+            _ => error.to_string(),
+        }
+    }
+
+    pub(super) fn read(service: &str, account: &str) -> Result<String, String> {
+        let password = get_generic_password(service, account).map_err(status_error)?;
+        String::from_utf8(password).map_err(|_| "the keychain item is not UTF-8".to_string())
+    }
+
+    /// Adds the item or replaces the password of the existing one
+    pub(super) fn store(service: &str, account: &str, key: &str) -> Result<(), String> {
+        set_generic_password(service, account, key.as_bytes()).map_err(status_error)
+    }
+}
+
+/// Credential Manager generic credentials
+#[cfg(windows)]
+mod platform {
+    use std::{ffi::c_void, io, mem, ptr, slice};
+
+    use windows_sys::Win32::{
+        Foundation::ERROR_NOT_FOUND,
+        Security::Credentials::{
+            CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE,
+            CRED_TYPE_GENERIC,
+        },
+    };
+
+    /// Credentials are looked up by target alone, the account is part of it so that
+    /// several accounts of a service don't replace each other
+    pub(super) fn target(service: &str, account: &str) -> Vec<u16> {
+        wide(&format!("{service}:{account}"))
+    }
+
+    fn wide(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn last_error() -> String {
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(code) if code as u32 == ERROR_NOT_FOUND => "no such credential".to_string(),
+            _ => error.to_string(),
+        }
+    }
+
+    pub(super) fn read(service: &str, account: &str) -> Result<String, String> {
+        let target = target(service, account);
+        let mut credential = ptr::null_mut::<CREDENTIALW>();
+        // SAFETY: `target` is NUL terminated and outlives the call
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+            return Err(last_error());
+        }
+        // SAFETY: CredReadW succeeded, the blob is copied before the credential is freed
+        let blob = unsafe {
+            let blob = slice::from_raw_parts(
+                (*credential).CredentialBlob,
+                (*credential).CredentialBlobSize as usize,
+            )
+            .to_vec();
+            CredFree(credential as *const c_void);
+            blob
+        };
+        String::from_utf8(blob).map_err(|_| "the credential is not UTF-8".to_string())
+    }
+
+    /// Writes the credential, replacing the one of the same target
+    pub(super) fn store(service: &str, account: &str, key: &str) -> Result<(), String> {
+        let mut target = target(service, account);
+        let mut user_name = wide(account);
+        let mut blob = key.as_bytes().to_vec();
+        // SAFETY: CREDENTIALW is plain data, all null or zero is a valid empty credential
+        let mut credential: CREDENTIALW = unsafe { mem::zeroed() };
+        credential.Type = CRED_TYPE_GENERIC;
+        credential.TargetName = target.as_mut_ptr();
+        credential.CredentialBlobSize =
+            u32::try_from(blob.len()).map_err(|_| format!("{} bytes is too long", blob.len()))?;
+        credential.CredentialBlob = blob.as_mut_ptr();
+        credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+        credential.UserName = user_name.as_mut_ptr();
+        // SAFETY: the strings and the blob the credential points to outlive the call
+        if unsafe { CredWriteW(&credential, 0) } == 0 {
+            return Err(last_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    const UNSUPPORTED: &str = "the os-keychain backend is only available on macOS and Windows";
+
+    pub(super) fn read(_service: &str, _account: &str) -> Result<String, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub(super) fn store(_service: &str, _account: &str, _key: &str) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    #[test]
+    fn test_keychain_is_unavailable_elsewhere() {
+        let entry = KeychainEntry::new(DEFAULT_KEYCHAIN_SERVICE, DEFAULT_KEYCHAIN_ACCOUNT);
+        let error = entry.read().unwrap_err().to_string();
+        assert!(error.contains("operator of swap-manager-avs"), "{error}");
+        assert!(
+            error.contains("only available on macOS and Windows"),
+            "{error}"
+        );
+        assert!(entry.store("0x01").is_err());
+    }
+
+    /// Remove the entry the round trip test stored
+    #[cfg(target_os = "macos")]
+    fn delete(entry: &KeychainEntry) {
+        security_framework::passwords::delete_generic_password(&entry.service, &entry.account)
+            .unwrap();
+    }
+
+    /// Remove the entry the round trip test stored
+    #[cfg(windows)]
+    fn delete(entry: &KeychainEntry) {
+        use windows_sys::Win32::Security::Credentials::{CredDeleteW, CRED_TYPE_GENERIC};
+
+        let target = platform::target(&entry.service, &entry.account);
+        // SAFETY: `target` is NUL terminated and outlives the call
+        assert_ne!(
+            unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) },
+            0
+        );
+    }
+
+    #[cfg(any(target_os = "macos", windows))]
+    #[test]
+    fn test_keys_round_trip_through_the_keychain() {
+        let entry = KeychainEntry::new(
+            format!("{DEFAULT_KEYCHAIN_SERVICE}-test"),
+            format!("round-trip-{}", std::process::id()),
+        );
+        let error = entry.read().unwrap_err().to_string();
+        assert!(error.contains("no such"), "{error}");
+
+        entry.store("0x01").unwrap();
+        assert_eq!(entry.read().unwrap(), "0x01");
+        // Storing again replaces the key rather than failing on the existing entry
+        entry.store(" 0x02\n").unwrap();
+        assert_eq!(entry.read().unwrap(), "0x02");
+        delete(&entry);
+        assert!(entry.read().is_err());
+    }
+}
@@ -0,0 +1,415 @@
+use std::{future::IntoFuture, sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::B256,
+    providers::Provider,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use tokio::time::Instant;
+use tracing::{field, info, info_span, warn, Instrument};
+
+use crate::error::ChainError;
+use crate::gas::{estimate_gas_limit, FeeStrategy, Fees, GasConfig};
+use crate::prometheus::{record_private_tx, record_tx_replaced, time_rpc};
+use crate::revert::decode_revert;
+use crate::services::Services;
+
+/// How often the receipts of a pending transaction are polled
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Smallest fee increase nodes accept for a replacement transaction, in percent
+pub const MIN_FEE_BUMP_PERCENT: u64 = 10;
+
+/// Sends transactions with the fees of a [`FeeStrategy`] and replaces the ones stuck in
+/// the mempool by the same nonce with bumped fees
+#[derive(Debug, Clone)]
+pub struct PendingTxWatcher {
+    fees: Arc<dyn FeeStrategy>,
+    stuck_timeout: Duration,
+    bump_percent: u64,
+    max_bumps: u32,
+    ceiling: Option<u128>,
+    dry_run: bool,
+    services: Services,
+}
+
+impl PendingTxWatcher {
+    /// Watcher paying the fees chosen by `fees` and bumping them as set in `config`
+    pub fn new(fees: Arc<dyn FeeStrategy>, config: &GasConfig) -> Self {
+        Self {
+            fees,
+            stuck_timeout: Duration::from_secs(config.stuck_timeout_secs),
+            bump_percent: config.fee_bump_percent,
+            max_bumps: config.max_fee_bumps,
+            ceiling: config.max_fee_per_gas,
+            dry_run: false,
+            services: Services::default(),
+        }
+    }
+
+    /// Pad the gas estimates, cap the fees, audit the transactions and add up their costs as
+    /// set in `services`
+    pub fn services(mut self, services: Services) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Simulate the transactions instead of sending them when `dry_run` is set
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Whether transactions are simulated instead of sent, see [`Self::simulate`]
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Estimate and `eth_call` `tx` with the fees it would be sent with, sending nothing.
+    ///
+    /// `tx` must have its sender set. Returns the gas estimate, a revert fails both calls
+    /// with its decoded reason.
+    pub async fn simulate<P: Provider>(
+        &self,
+        provider: &P,
+        method: &'static str,
+        tx: TransactionRequest,
+    ) -> Result<u64, ChainError> {
+        let fees = self.fees.fees(provider).await?;
+        let tx = fees.apply(tx);
+        let gas = time_rpc(
+            "eth_estimateGas",
+            provider.estimate_gas(tx.clone()).into_future(),
+        )
+        .await
+        .map_err(|e| decode_revert(method, e.into()))?;
+        time_rpc("eth_call", provider.call(tx.clone()).into_future())
+            .await
+            .map_err(|e| decode_revert(method, e.into()))?;
+        info!(
+            method,
+            from = ?tx.from,
+            to = ?tx.to.and_then(|to| to.to().copied()),
+            gas,
+            max_fee_per_gas = fees.max_fee_per_gas(),
+            max_cost_wei = u128::from(gas) * fees.max_fee_per_gas(),
+            "Dry run, transaction not sent"
+        );
+        Ok(gas)
+    }
+
+    /// Send `tx`, whose nonce must be set, with the gas limit of its estimate and wait for
+    /// it or one of its replacements to be mined. A transaction whose estimate reverts isn't
+    /// sent
+    pub async fn send<P: Provider>(
+        &self,
+        provider: &P,
+        method: &'static str,
+        tx: TransactionRequest,
+    ) -> Result<TransactionReceipt, ChainError> {
+        self.send_through(provider, None::<(&P, Duration)>, method, tx)
+            .await
+    }
+
+    /// Send `tx` as [`Self::send`] does, but to the private `relay` rather than the public
+    /// mempool of `provider`, where it can't be front-run. If it isn't mined within
+    /// `fallback_after` it is sent to `provider` as well, and replaced there when stuck
+    pub async fn send_private<P: Provider, R: Provider>(
+        &self,
+        provider: &P,
+        relay: &R,
+        fallback_after: Duration,
+        method: &'static str,
+        tx: TransactionRequest,
+    ) -> Result<TransactionReceipt, ChainError> {
+        self.send_through(provider, Some((relay, fallback_after)), method, tx)
+            .await
+    }
+
+    async fn send_through<P: Provider, R: Provider>(
+        &self,
+        provider: &P,
+        relay: Option<(&R, Duration)>,
+        method: &'static str,
+        mut tx: TransactionRequest,
+    ) -> Result<TransactionReceipt, ChainError> {
+        let nonce = tx.nonce.ok_or_else(|| {
+            ChainError::Unexpected(format!(
+                "{method} must have its nonce set to be replaceable"
+            ))
+        })?;
+        self.services.wait_for_budget(method).await;
+        let fees = self.fees.fees(provider).await?;
+        let gas = estimate_gas_limit(
+            provider,
+            self.services.gas_limit_multiplier(),
+            method,
+            &fees.apply(tx.clone()),
+        )
+        .await?;
+        tx.gas = Some(gas);
+        // Counted at the most it may pay until its receipt tells what it paid
+        let reservation = self
+            .services
+            .reserve_budget(method, u128::from(gas) * fees.max_fee_per_gas())
+            .await;
+        let submit = info_span!(
+            "submit",
+            method,
+            nonce,
+            private = relay.is_some(),
+            tx_hash = field::Empty
+        );
+        let sent = match relay {
+            Some((relay, _)) => relay.send_transaction(fees.apply(tx.clone())),
+            None => provider.send_transaction(fees.apply(tx.clone())),
+        };
+        let pending = match time_rpc("eth_sendRawTransaction", sent)
+            .instrument(submit.clone())
+            .await
+        {
+            Ok(pending) => pending,
+            Err(e) => {
+                if let Some(reservation) = reservation {
+                    reservation.release();
+                }
+                return Err(e.into());
+            }
+        };
+        let original = *pending.tx_hash();
+        submit.record("tx_hash", field::display(original));
+        self.services
+            .audit_transaction(tx.from.unwrap_or_default(), method, Some(nonce), original);
+        let mut hashes = vec![original];
+
+        // Spans the wait for the receipt and the replacements of a stuck transaction
+        let confirm = info_span!(
+            "confirm",
+            method,
+            nonce,
+            tx_hash = field::Empty,
+            block_number = field::Empty,
+            replacements = field::Empty,
+        );
+        let confirmed = async {
+            if let Some((_, fallback_after)) = relay {
+                if let Some(receipt) = self
+                    .wait_private(
+                        provider,
+                        method,
+                        nonce,
+                        fees.apply(tx.clone()),
+                        original,
+                        fallback_after,
+                    )
+                    .await?
+                {
+                    return Ok(receipt);
+                }
+            }
+            self.confirm(provider, method, nonce, tx, fees, &mut hashes)
+                .await
+        }
+        .instrument(confirm.clone())
+        .await;
+        confirm.record("replacements", hashes.len() - 1);
+        if let Ok(receipt) = &confirmed {
+            // Reverted transactions pay for their gas too
+            self.services.tx_costs().record(method, receipt);
+            if let Some(reservation) = reservation {
+                reservation.settle(u128::from(receipt.gas_used) * receipt.effective_gas_price);
+            }
+            confirm.record("tx_hash", field::display(receipt.transaction_hash));
+            if let Some(block_number) = receipt.block_number {
+                confirm.record("block_number", block_number);
+            }
+        }
+        confirmed
+    }
+
+    /// Receipt of the transaction `hash` sent to a private relay if it gets mined within
+    /// `fallback_after`, else `None` once `tx` was sent to the public mempool of `provider`
+    async fn wait_private<P: Provider>(
+        &self,
+        provider: &P,
+        method: &'static str,
+        nonce: u64,
+        tx: TransactionRequest,
+        hash: B256,
+        fallback_after: Duration,
+    ) -> Result<Option<TransactionReceipt>, ChainError> {
+        if let Some(receipt) = self
+            .wait_for_receipt(provider, &[hash], fallback_after)
+            .await?
+        {
+            record_private_tx(method, "mined");
+            return Ok(Some(receipt));
+        }
+        record_private_tx(method, "fallback");
+        warn!(
+            method,
+            nonce,
+            tx_hash = %hash,
+            fallback_secs = fallback_after.as_secs(),
+            "Private transaction not mined in time, sending it to the public mempool"
+        );
+        // The same signed transaction, which the relay may have shared already
+        if let Err(e) = time_rpc("eth_sendRawTransaction", provider.send_transaction(tx)).await {
+            warn!(method, nonce, error = %e, "Failed to send the private transaction publicly");
+        }
+        Ok(None)
+    }
+
+    /// Wait for one of `hashes` to be mined, replacing the last one with bumped fees each
+    /// time the stuck timeout passes
+    async fn confirm<P: Provider>(
+        &self,
+        provider: &P,
+        method: &'static str,
+        nonce: u64,
+        tx: TransactionRequest,
+        mut fees: Fees,
+        hashes: &mut Vec<B256>,
+    ) -> Result<TransactionReceipt, ChainError> {
+        let original = hashes[0];
+        for bump in 1..=self.max_bumps + 1 {
+            if let Some(receipt) = self
+                .wait_for_receipt(provider, hashes, self.stuck_timeout)
+                .await?
+            {
+                return Ok(receipt);
+            }
+            if bump > self.max_bumps {
+                break;
+            }
+            let Some(bumped) = bump_fees(fees, self.bump_percent, self.ceiling) else {
+                warn!(
+                    method,
+                    nonce,
+                    ceiling = self.ceiling,
+                    "Transaction stuck but its fees can't be bumped under the ceiling"
+                );
+                continue;
+            };
+            let replaced = *hashes.last().expect("the original was sent");
+            match time_rpc(
+                "eth_sendRawTransaction",
+                provider.send_transaction(bumped.apply(tx.clone())),
+            )
+            .await
+            {
+                Ok(pending) => {
+                    record_tx_replaced(method);
+                    fees = bumped;
+                    let replacement = *pending.tx_hash();
+                    hashes.push(replacement);
+                    self.services.audit_transaction(
+                        tx.from.unwrap_or_default(),
+                        method,
+                        Some(nonce),
+                        replacement,
+                    );
+                    info!(
+                        method,
+                        nonce,
+                        %original,
+                        %replaced,
+                        %replacement,
+                        max_fee_per_gas = fees.max_fee_per_gas(),
+                        bump,
+                        "Replaced stuck transaction with bumped fees"
+                    );
+                }
+                Err(e) => {
+                    // Underpriced replacements are bumped further on the next round, and a
+                    // nonce already used means one of the sent ones got mined
+                    fees = bumped;
+                    warn!(method, nonce, %replaced, error = %e, "Failed to replace stuck transaction");
+                }
+            }
+        }
+        Err(ChainError::NotMined {
+            method,
+            nonce,
+            bumps: self.max_bumps,
+            tx_hash: *hashes.last().expect("the original was sent"),
+        })
+    }
+
+    /// Receipt of whichever of `hashes` gets mined within `timeout`
+    async fn wait_for_receipt<P: Provider>(
+        &self,
+        provider: &P,
+        hashes: &[B256],
+        timeout: Duration,
+    ) -> Result<Option<TransactionReceipt>, ChainError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            for hash in hashes {
+                if let Some(receipt) = time_rpc(
+                    "eth_getTransactionReceipt",
+                    provider.get_transaction_receipt(*hash),
+                )
+                .await?
+                {
+                    return Ok(Some(receipt));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL.min(timeout)).await;
+        }
+    }
+}
+
+/// Fees of a replacement for a transaction paying `fees`, raised by `percent` as the
+/// replacement rules require, `None` if that would cross `ceiling`
+pub fn bump_fees(fees: Fees, percent: u64, ceiling: Option<u128>) -> Option<Fees> {
+    let bump = |fee: u128| {
+        fee.saturating_mul(100 + u128::from(percent))
+            .div_ceil(100)
+            .max(fee + 1)
+    };
+    let bumped = match fees {
+        Fees::Legacy { gas_price } => Fees::Legacy {
+            gas_price: bump(gas_price),
+        },
+        Fees::Eip1559 {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        } => Fees::Eip1559 {
+            max_fee_per_gas: bump(max_fee_per_gas),
+            max_priority_fee_per_gas: bump(max_priority_fee_per_gas),
+        },
+    };
+    match ceiling {
+        Some(ceiling) if bumped.max_fee_per_gas() > ceiling => None,
+        _ => Some(bumped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bumped_fees_follow_the_replacement_rules() {
+        let fees = Fees::Eip1559 {
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 3,
+        };
+        assert_eq!(
+            bump_fees(fees, 12, None),
+            Some(Fees::Eip1559 {
+                max_fee_per_gas: 112,
+                max_priority_fee_per_gas: 4,
+            })
+        );
+        assert_eq!(
+            bump_fees(Fees::Legacy { gas_price: 0 }, 10, None),
+            Some(Fees::Legacy { gas_price: 1 })
+        );
+        assert_eq!(bump_fees(fees, 12, Some(111)), None);
+    }
+}
@@ -0,0 +1,124 @@
+use std::future::IntoFuture;
+
+use alloy::{primitives::Address, providers::Provider};
+use eigensdk::utils::slashing::core::delegationmanager::DelegationManager;
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
+use tracing::info;
+
+use crate::config::{ContractAddresses, OperatorConfig};
+use crate::error::PreflightError;
+use crate::prometheus::time_rpc;
+use crate::retry::retry;
+use crate::services::Services;
+
+/// Checks of [`preflight`] beyond the chain ID and the contract code
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreflightChecks {
+    /// Signer that must hold some ETH to pay for gas
+    pub funded: Option<Address>,
+    /// Operator that must be registered with EigenLayer and the AVS stake registry
+    pub registered: Option<Address>,
+}
+
+/// Fail if the chain behind `rpc_url` isn't the configured `chain_id`, if it has no code at
+/// one of `contracts`, or if the `checks` don't pass, so a misconfiguration is reported at
+/// startup rather than as reverts once tasks come in. Nothing is checked without
+/// `preflight` in `config`.
+pub async fn preflight(
+    config: &OperatorConfig,
+    services: &Services,
+    contracts: &ContractAddresses,
+    checks: PreflightChecks,
+) -> Result<(), PreflightError> {
+    if !config.preflight {
+        return Ok(());
+    }
+    let provider = services.http_provider(&config.rpc_url)?;
+    let retry_config = &config.retry;
+    let chain_id = retry(retry_config, "eth_chainId", || {
+        time_rpc("eth_chainId", provider.get_chain_id())
+    })
+    .await?;
+    check_chain_id(config.chain_id, chain_id)?;
+
+    for (contract, address) in [
+        ("SwapManager", contracts.swap_manager),
+        ("ECDSAStakeRegistry", contracts.stake_registry),
+        ("DelegationManager", contracts.delegation_manager),
+        ("AVSDirectory", contracts.avs_directory),
+    ] {
+        let code = retry(retry_config, "eth_getCode", || {
+            time_rpc("eth_getCode", provider.get_code_at(address).into_future())
+        })
+        .await?;
+        if code.is_empty() {
+            return Err(PreflightError::NoCode {
+                contract,
+                address,
+                chain_id,
+            });
+        }
+    }
+
+    if let Some(address) = checks.funded {
+        let balance = retry(retry_config, "eth_getBalance", || {
+            time_rpc(
+                "eth_getBalance",
+                provider.get_balance(address).into_future(),
+            )
+        })
+        .await?;
+        if balance.is_zero() {
+            return Err(PreflightError::Unfunded { address, chain_id });
+        }
+    }
+
+    if let Some(operator) = checks.registered {
+        let delegation_manager = DelegationManager::new(contracts.delegation_manager, &provider);
+        let is_operator = delegation_manager.isOperator(operator);
+        let is_operator = retry(retry_config, "isOperator", || {
+            time_rpc("isOperator", is_operator.call().into_future())
+        })
+        .await?
+        ._0;
+        if !is_operator {
+            return Err(PreflightError::NotOperator { operator });
+        }
+        let registry = ECDSAStakeRegistry::new(contracts.stake_registry, &provider);
+        let registered = registry.operatorRegistered(operator);
+        let registered = retry(retry_config, "operatorRegistered", || {
+            time_rpc("operatorRegistered", registered.call().into_future())
+        })
+        .await?
+        ._0;
+        if !registered {
+            return Err(PreflightError::NotRegistered { operator });
+        }
+    }
+    info!(chain_id, "Preflight checks passed");
+    Ok(())
+}
+
+/// Fail if the connected chain `actual` isn't the `expected` one, any chain passes if unset
+pub fn check_chain_id(expected: Option<u64>, actual: u64) -> Result<(), PreflightError> {
+    match expected {
+        Some(expected) if expected != actual => {
+            Err(PreflightError::ChainMismatch { expected, actual })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_ids_must_match_when_configured() {
+        check_chain_id(None, 17000).unwrap();
+        check_chain_id(Some(31337), 31337).unwrap();
+        let error = check_chain_id(Some(1), 17000).unwrap_err();
+        assert!(error.to_string().contains("chain 17000"), "{error}");
+        assert!(error.to_string().contains("chain_id 1"), "{error}");
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use alloy::primitives::Address;
+use eigensdk::metrics::prometheus::init_registry;
+use metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+
+use crate::circuit_breaker::CircuitState;
+
+/// Tasks created by the spammer
+pub const TASKS_CREATED: &str = "swap_manager_tasks_created_total";
+/// Tasks the operator responded to
+pub const TASKS_RESPONDED: &str = "swap_manager_tasks_responded_total";
+/// Failed transactions, labelled by `method`
+pub const TX_FAILURES: &str = "swap_manager_tx_failures_total";
+/// Gas used per mined transaction, labelled by `method`
+pub const TX_GAS_USED: &str = "swap_manager_tx_gas_used";
+/// Effective gas price in gwei per mined transaction, labelled by `method`
+pub const TX_GAS_PRICE: &str = "swap_manager_tx_gas_price_gwei";
+/// Ether spent on fees since start, reverted transactions included, labelled by `method`
+pub const TX_FEES: &str = "swap_manager_tx_fees_eth";
+/// RPC call latency, labelled by `method`
+pub const RPC_LATENCY: &str = "swap_manager_rpc_latency_seconds";
+/// Seconds from the block of NewTaskCreated to the block of the response, exported with
+/// its p50, p95 and p99 quantiles
+pub const TASK_LATENCY: &str = "swap_manager_task_latency_seconds";
+/// Transactions sent but not mined yet
+pub const PENDING_TXS: &str = "swap_manager_pending_txs";
+/// Stuck transactions replaced with bumped fees, labelled by `method`
+pub const TX_REPLACEMENTS: &str = "swap_manager_tx_replacements_total";
+/// Transactions sent to a private relay, labelled by `method` and `result`, `mined` or
+/// `fallback` to the public mempool
+pub const PRIVATE_TXS: &str = "swap_manager_private_txs_total";
+/// Chain reorganizations noticed by the operator
+pub const REORGS: &str = "swap_manager_reorgs_total";
+/// Responses that got `reorg.confirmations` blocks on top of them
+pub const TASKS_CONFIRMED: &str = "swap_manager_tasks_confirmed_total";
+/// Tasks waiting between the task source and the responder
+pub const TASK_QUEUE_DEPTH: &str = "swap_manager_task_queue_depth";
+/// Tasks held back at the source because the queue was full
+pub const TASKS_DEFERRED: &str = "swap_manager_tasks_deferred_total";
+/// Tasks written to the task store because the queue was full
+pub const TASKS_SPILLED: &str = "swap_manager_tasks_spilled_total";
+/// Spilled tasks read back from the task store into the queue
+pub const TASKS_UNSPILLED: &str = "swap_manager_tasks_unspilled_total";
+/// Tasks waiting in the task store for room in the queue
+pub const SPILLED_TASKS: &str = "swap_manager_spilled_tasks";
+/// Queued tasks discarded on shutdown before being handled
+pub const TASKS_DROPPED: &str = "swap_manager_tasks_dropped_total";
+/// RPC calls delayed by the rate limit
+pub const RPC_THROTTLED: &str = "swap_manager_rpc_throttled_total";
+/// Ether held by the signer
+pub const SIGNER_BALANCE: &str = "swap_manager_signer_balance_eth";
+/// Whether the signer balance is under `balance.min_balance_eth`, 1 or 0
+pub const LOW_BALANCE: &str = "swap_manager_signer_low_balance";
+/// Top ups of a low signer balance, labelled by `result`
+pub const TOP_UPS: &str = "swap_manager_balance_top_ups_total";
+/// Ether the transactions may still spend in fees, labelled by `window`
+pub const BUDGET_REMAINING: &str = "swap_manager_budget_remaining_eth";
+/// Whether the operator is registered in the stake registry, 1 or 0
+pub const OPERATOR_REGISTERED: &str = "swap_manager_operator_registered";
+/// Weight of the operator in the quorum
+pub const OPERATOR_WEIGHT: &str = "swap_manager_operator_weight";
+/// Share of the total quorum weight held by the operator
+pub const OPERATOR_WEIGHT_SHARE: &str = "swap_manager_operator_weight_share";
+/// Whether the operator is at risk of ejection from the quorum, 1 or 0
+pub const OPERATOR_AT_RISK: &str = "swap_manager_operator_at_risk";
+/// Operator directed rewards submitted for the operator in token base units, labelled by
+/// `avs` and `token`
+pub const REWARDS_ACCRUED: &str = "swap_manager_rewards_accrued";
+/// Cumulative earnings in the claimable root in token base units, labelled by `token`
+pub const REWARDS_EARNED: &str = "swap_manager_rewards_earned";
+/// Rewards claimed so far in token base units, labelled by `token`
+pub const REWARDS_CLAIMED: &str = "swap_manager_rewards_claimed";
+/// Rewards earned and not claimed yet in token base units, labelled by `token`
+pub const REWARDS_CLAIMABLE: &str = "swap_manager_rewards_claimable";
+/// Responses of other operators checked by the challenger
+pub const RESPONSES_CHECKED: &str = "swap_manager_responses_checked_total";
+/// Faulty responses found by the challenger, labelled by `fault`
+pub const INVALID_RESPONSES: &str = "swap_manager_invalid_responses_total";
+/// Challenges of faulty responses, labelled by `result`
+pub const CHALLENGES: &str = "swap_manager_challenges_total";
+/// Webhook deliveries, labelled by `event` and `result`
+pub const WEBHOOK_DELIVERIES: &str = "swap_manager_webhook_deliveries_total";
+/// Task events handed to the event bus, labelled by `event` and `result`
+pub const EVENT_BUS_PUBLISHES: &str = "swap_manager_event_bus_publishes_total";
+/// Chat alerts, labelled by `kind` and `result`
+pub const ALERTS: &str = "swap_manager_alerts_total";
+/// Calls moved to the next RPC endpoint after a transport error, labelled by the failed `endpoint`
+pub const RPC_FAILOVERS: &str = "swap_manager_rpc_failovers_total";
+/// Whether an RPC endpoint passes its health checks, 1 or 0, labelled by `endpoint`
+pub const RPC_ENDPOINT_HEALTHY: &str = "swap_manager_rpc_endpoint_healthy";
+/// Quorum reads the RPC endpoints did not agree on, labelled by `method`
+pub const RPC_QUORUM_FAILURES: &str = "swap_manager_rpc_quorum_failures_total";
+/// Whether this replica holds the leader lease, 1 or 0
+pub const HA_LEADER: &str = "swap_manager_ha_leader";
+/// Tasks left to another replica or skipped as duplicates of a pending, responded to or
+/// stored task, labelled by `reason`
+pub const TASKS_DEDUPLICATED: &str = "swap_manager_tasks_deduplicated_total";
+/// Tasks passing through the Redis work queue, labelled by `action`
+pub const WORK_QUEUE_TASKS: &str = "swap_manager_work_queue_tasks_total";
+/// Failed attempts of retried RPC calls and transactions, labelled by `operation` and `class`
+pub const CALL_FAILURES: &str = "swap_manager_call_failures_total";
+/// State of the RPC circuit breaker, 0 closed, 1 half open or 2 open
+pub const RPC_CIRCUIT_STATE: &str = "swap_manager_rpc_circuit_state";
+/// Changes of state of the RPC circuit breaker, labelled by the `state` entered
+pub const RPC_CIRCUIT_TRANSITIONS: &str = "swap_manager_rpc_circuit_transitions_total";
+/// Blocks between the chain head and the last block the task source scanned
+pub const SOURCE_BLOCK_LAG: &str = "swap_manager_source_block_lag";
+/// Whether the task source is catching up on a lag with concurrent log fetches, 1 or 0
+pub const SOURCE_CATCHING_UP: &str = "swap_manager_source_catching_up";
+
+/// Transactions awaiting a receipt, mirrored from [`PENDING_TXS`] as gauges can't be read back
+static PENDING_TX_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Serve the Prometheus `/metrics` endpoint on `addr` and describe the operator metrics
+pub fn init_metrics(addr: SocketAddr) {
+    init_registry(addr);
+    describe_counter!(TASKS_CREATED, "Tasks created through createNewTask");
+    describe_counter!(TASKS_RESPONDED, "Tasks responded to through respondToTask");
+    describe_counter!(
+        TX_FAILURES,
+        "Transactions that failed to be sent or reverted"
+    );
+    describe_histogram!(TX_GAS_USED, Unit::Count, "Gas used per mined transaction");
+    describe_histogram!(
+        TX_GAS_PRICE,
+        "Effective gas price in gwei per mined transaction"
+    );
+    describe_gauge!(TX_FEES, "Ether spent on transaction fees since start");
+    describe_histogram!(RPC_LATENCY, Unit::Seconds, "Latency of RPC calls");
+    describe_histogram!(
+        TASK_LATENCY,
+        Unit::Seconds,
+        "Time from the task creation block to the response block"
+    );
+    describe_gauge!(PENDING_TXS, "Transactions sent and waiting for a receipt");
+    describe_counter!(
+        TX_REPLACEMENTS,
+        "Stuck transactions replaced with bumped fees"
+    );
+    describe_counter!(
+        PRIVATE_TXS,
+        "Transactions sent to a private relay, by whether they were mined there"
+    );
+    describe_counter!(REORGS, "Chain reorganizations that dropped watched blocks");
+    describe_counter!(
+        TASKS_CONFIRMED,
+        "Responses buried under the configured number of confirmations"
+    );
+    describe_gauge!(
+        TASK_QUEUE_DEPTH,
+        "Tasks waiting between the task source and the responder"
+    );
+    describe_counter!(
+        TASKS_DEFERRED,
+        "Tasks held back at the source because the queue was full"
+    );
+    describe_counter!(
+        TASKS_SPILLED,
+        "Tasks written to the task store because the queue was full"
+    );
+    describe_counter!(
+        TASKS_UNSPILLED,
+        "Spilled tasks read back from the task store into the queue"
+    );
+    describe_gauge!(
+        SPILLED_TASKS,
+        "Tasks waiting in the task store for room in the queue"
+    );
+    describe_counter!(TASKS_DROPPED, "Queued tasks discarded on shutdown");
+    describe_counter!(RPC_THROTTLED, "RPC calls delayed by the rate limit");
+    describe_gauge!(SIGNER_BALANCE, "Ether held by the signer");
+    describe_gauge!(
+        LOW_BALANCE,
+        "Whether the signer balance is under the minimum balance"
+    );
+    describe_gauge!(
+        BUDGET_REMAINING,
+        "Ether the transactions may still spend in fees over the hour or day window"
+    );
+    describe_counter!(
+        TOP_UPS,
+        "Top ups of a low signer balance, by result: requested, rate_limited, refused or failed"
+    );
+    describe_gauge!(
+        OPERATOR_REGISTERED,
+        "Whether the operator is registered in the stake registry"
+    );
+    describe_gauge!(OPERATOR_WEIGHT, "Weight of the operator in the quorum");
+    describe_gauge!(
+        OPERATOR_WEIGHT_SHARE,
+        "Share of the total quorum weight held by the operator"
+    );
+    describe_gauge!(
+        OPERATOR_AT_RISK,
+        "Whether the operator weight is close to the minimum weight"
+    );
+    describe_gauge!(
+        REWARDS_ACCRUED,
+        "Operator directed rewards submitted for the operator, per AVS and token"
+    );
+    describe_gauge!(
+        REWARDS_EARNED,
+        "Cumulative earnings of the operator in the claimable root, per token"
+    );
+    describe_gauge!(REWARDS_CLAIMED, "Rewards claimed so far, per token");
+    describe_gauge!(
+        REWARDS_CLAIMABLE,
+        "Rewards earned and not claimed yet, per token"
+    );
+    describe_counter!(
+        RESPONSES_CHECKED,
+        "Responses of other operators checked by the challenger"
+    );
+    describe_counter!(
+        INVALID_RESPONSES,
+        "Faulty responses found by the challenger"
+    );
+    describe_counter!(
+        CHALLENGES,
+        "Challenges of faulty responses: sent, simulated, refused, expired or reverted"
+    );
+    describe_counter!(
+        WEBHOOK_DELIVERIES,
+        "Webhook notifications delivered or failed, per event"
+    );
+    describe_counter!(
+        EVENT_BUS_PUBLISHES,
+        "Task events published, failed or dropped on a full queue, per event"
+    );
+    describe_counter!(
+        ALERTS,
+        "Slack and Discord alerts sent, failed or suppressed by the rate limit"
+    );
+    describe_counter!(
+        RPC_FAILOVERS,
+        "RPC calls moved to the next endpoint after a transport error"
+    );
+    describe_gauge!(
+        RPC_ENDPOINT_HEALTHY,
+        "Whether an RPC endpoint passes its health checks"
+    );
+    describe_counter!(
+        RPC_QUORUM_FAILURES,
+        "Quorum reads the RPC endpoints did not agree on"
+    );
+    describe_gauge!(HA_LEADER, "Whether this replica holds the leader lease");
+    describe_counter!(
+        TASKS_DEDUPLICATED,
+        "Tasks left to another replica or skipped as already pending, responded to or handled"
+    );
+    describe_counter!(
+        WORK_QUEUE_TASKS,
+        "Tasks enqueued, delivered, claimed from a stale worker or acknowledged on the work queue"
+    );
+    describe_counter!(
+        CALL_FAILURES,
+        "Failed attempts of retried RPC calls and transactions, by error class"
+    );
+    describe_gauge!(
+        RPC_CIRCUIT_STATE,
+        "State of the RPC circuit breaker: 0 closed, 1 half open, 2 open"
+    );
+    describe_counter!(
+        RPC_CIRCUIT_TRANSITIONS,
+        "Changes of state of the RPC circuit breaker, by state entered"
+    );
+    describe_gauge!(
+        SOURCE_BLOCK_LAG,
+        "Blocks between the chain head and the last block scanned for tasks"
+    );
+    describe_gauge!(
+        SOURCE_CATCHING_UP,
+        "Whether the task source is catching up on a lag with concurrent log fetches"
+    );
+}
+
+/// Record the latency of a response, in seconds between the creation and response blocks
+pub fn record_task_latency(secs: u64) {
+    histogram!(TASK_LATENCY).record(secs as f64);
+}
+
+/// Count a created task
+pub fn record_task_created() {
+    counter!(TASKS_CREATED).increment(1);
+}
+
+/// Count a task response
+pub fn record_task_responded() {
+    counter!(TASKS_RESPONDED).increment(1);
+}
+
+/// Count a replacement of a stuck `method` transaction
+pub fn record_tx_replaced(method: &'static str) {
+    counter!(TX_REPLACEMENTS, "method" => method).increment(1);
+}
+
+/// Count a transaction sent to a private relay that ended with `result`
+pub fn record_private_tx(method: &'static str, result: &'static str) {
+    counter!(PRIVATE_TXS, "method" => method, "result" => result).increment(1);
+}
+
+/// Count a chain reorganization
+pub fn record_reorg() {
+    counter!(REORGS).increment(1);
+}
+
+/// Count `confirmed` responses that got enough confirmations
+pub fn record_tasks_confirmed(confirmed: usize) {
+    counter!(TASKS_CONFIRMED).increment(confirmed as u64);
+}
+
+/// Record how many tasks wait in the queue
+pub fn set_task_queue_depth(depth: usize) {
+    gauge!(TASK_QUEUE_DEPTH).set(depth as f64);
+}
+
+/// Record the balance in ether of the signer or key pool key `address` and whether it is
+/// under the minimum
+pub fn set_signer_balance(address: Address, balance_eth: f64, low: bool) {
+    let address = address.to_string();
+    gauge!(SIGNER_BALANCE, "address" => address.clone()).set(balance_eth);
+    gauge!(LOW_BALANCE, "address" => address).set(f64::from(u8::from(low)));
+}
+
+/// Count a top up that ended with `result`
+pub fn record_top_up(result: &'static str) {
+    counter!(TOP_UPS, "result" => result).increment(1);
+}
+
+/// Export the ether left to spend in fees over `window`, `hour` or `day`
+pub fn set_budget_remaining(window: &'static str, remaining_eth: f64) {
+    gauge!(BUDGET_REMAINING, "window" => window).set(remaining_eth);
+}
+
+/// Record the standing of the operator in the stake registry
+pub fn set_stake_status(registered: bool, weight: f64, share: f64, at_risk: bool) {
+    gauge!(OPERATOR_REGISTERED).set(f64::from(u8::from(registered)));
+    gauge!(OPERATOR_WEIGHT).set(weight);
+    gauge!(OPERATOR_WEIGHT_SHARE).set(share);
+    gauge!(OPERATOR_AT_RISK).set(f64::from(u8::from(at_risk)));
+}
+
+/// Record the operator directed rewards `avs` submitted for the operator in `token`
+pub fn set_rewards_accrued(avs: Address, token: Address, amount: f64) {
+    gauge!(REWARDS_ACCRUED, "avs" => avs.to_string(), "token" => token.to_string()).set(amount);
+}
+
+/// Record the rewards in `token` earned in the claimable root, claimed and left to claim
+pub fn set_token_rewards(token: Address, earned: f64, claimed: f64, claimable: f64) {
+    let token = token.to_string();
+    gauge!(REWARDS_EARNED, "token" => token.clone()).set(earned);
+    gauge!(REWARDS_CLAIMED, "token" => token.clone()).set(claimed);
+    gauge!(REWARDS_CLAIMABLE, "token" => token).set(claimable);
+}
+
+/// Count a response checked by the challenger
+pub fn record_response_checked() {
+    counter!(RESPONSES_CHECKED).increment(1);
+}
+
+/// Count a faulty response of kind `fault`
+pub fn record_invalid_response(fault: &'static str) {
+    counter!(INVALID_RESPONSES, "fault" => fault).increment(1);
+}
+
+/// Count a webhook delivery of `event` that ended with `result`
+pub fn record_webhook_delivery(event: &'static str, result: &'static str) {
+    counter!(WEBHOOK_DELIVERIES, "event" => event, "result" => result).increment(1);
+}
+
+/// Count a task `event` handed to the event bus that ended with `result`
+pub fn record_event_published(event: &'static str, result: &'static str) {
+    counter!(EVENT_BUS_PUBLISHES, "event" => event, "result" => result).increment(1);
+}
+
+/// Count a chat alert to a `kind` channel that ended with `result`
+pub fn record_alert(kind: &'static str, result: &'static str) {
+    counter!(ALERTS, "kind" => kind, "result" => result).increment(1);
+}
+
+/// Count a call moved away from the failed `endpoint`
+pub fn record_rpc_failover(endpoint: &str) {
+    counter!(RPC_FAILOVERS, "endpoint" => endpoint.to_string()).increment(1);
+}
+
+/// Record whether `endpoint` passes its health checks
+pub fn set_rpc_endpoint_health(endpoint: &str, healthy: bool) {
+    gauge!(RPC_ENDPOINT_HEALTHY, "endpoint" => endpoint.to_string())
+        .set(f64::from(u8::from(healthy)));
+}
+
+/// Count a `method` quorum read the endpoints did not agree on
+pub fn record_rpc_quorum_failure(method: &str) {
+    counter!(RPC_QUORUM_FAILURES, "method" => method.to_string()).increment(1);
+}
+
+/// Record whether this replica holds the leader lease
+pub fn set_ha_leader(leader: bool) {
+    gauge!(HA_LEADER).set(f64::from(u8::from(leader)));
+}
+
+/// Count a task this replica left alone for `reason`
+pub fn record_task_deduplicated(reason: &'static str) {
+    counter!(TASKS_DEDUPLICATED, "reason" => reason).increment(1);
+}
+
+/// Count a task the work queue saw `action` happen to
+pub fn record_work_queue_task(action: &'static str) {
+    counter!(WORK_QUEUE_TASKS, "action" => action).increment(1);
+}
+
+/// Count a failed attempt of the retried `operation`
+pub fn record_call_failure(operation: &str, class: &'static str) {
+    counter!(CALL_FAILURES, "operation" => operation.to_string(), "class" => class).increment(1);
+}
+
+/// Record the state the RPC circuit breaker is in
+pub fn set_rpc_circuit_state(state: CircuitState) {
+    let value = match state {
+        CircuitState::Closed => 0.0,
+        CircuitState::HalfOpen => 1.0,
+        CircuitState::Open => 2.0,
+    };
+    gauge!(RPC_CIRCUIT_STATE).set(value);
+}
+
+/// Count the RPC circuit breaker entering `state`
+pub fn record_rpc_circuit_transition(state: &'static str) {
+    counter!(RPC_CIRCUIT_TRANSITIONS, "state" => state).increment(1);
+}
+
+/// Count a challenge that ended with `result`
+pub fn record_challenge(result: &'static str) {
+    counter!(CHALLENGES, "result" => result).increment(1);
+}
+
+/// Count a task held back because the queue was full
+pub fn record_task_deferred() {
+    counter!(TASKS_DEFERRED).increment(1);
+}
+
+/// Count a task spilled to the task store, `pending` of them waiting there now
+pub fn record_task_spilled(pending: u64) {
+    counter!(TASKS_SPILLED).increment(1);
+    gauge!(SPILLED_TASKS).set(pending as f64);
+}
+
+/// Count a spilled task read back into the queue, `pending` of them still waiting
+pub fn record_task_unspilled(pending: u64) {
+    counter!(TASKS_UNSPILLED).increment(1);
+    gauge!(SPILLED_TASKS).set(pending as f64);
+}
+
+/// Count `count` queued tasks discarded on shutdown
+pub fn record_tasks_dropped(count: usize) {
+    counter!(TASKS_DROPPED).increment(count as u64);
+}
+
+/// Count an RPC call delayed by the rate limit
+pub fn record_rpc_throttled() {
+    counter!(RPC_THROTTLED).increment(1);
+}
+
+/// Count a failed `method` transaction
+pub fn record_tx_failure(method: &'static str) {
+    counter!(TX_FAILURES, "method" => method).increment(1);
+}
+
+/// Record the gas used by a mined `method` transaction
+pub fn record_gas_used(method: &'static str, gas_used: u64) {
+    histogram!(TX_GAS_USED, "method" => method).record(gas_used as f64);
+}
+
+/// Record the effective gas price in wei of a mined `method` transaction
+pub fn record_gas_price(method: &'static str, effective_gas_price: u128) {
+    histogram!(TX_GAS_PRICE, "method" => method).record(effective_gas_price as f64 / 1e9);
+}
+
+/// Add `fees_eth` ether to what the process spent on the fees of `method` transactions
+pub fn add_tx_fees(method: &'static str, fees_eth: f64) {
+    gauge!(TX_FEES, "method" => method).increment(fees_eth);
+}
+
+/// Record the block lag of the task source and whether it is catching up on it
+pub fn set_source_lag(lag: u64, catching_up: bool) {
+    gauge!(SOURCE_BLOCK_LAG).set(lag as f64);
+    gauge!(SOURCE_CATCHING_UP).set(f64::from(u8::from(catching_up)));
+}
+
+/// Transactions sent by this process and awaiting a receipt
+pub fn pending_txs() -> u64 {
+    PENDING_TX_COUNT.load(Ordering::Relaxed)
+}
+
+/// Await `future` and record how long it took as the latency of RPC `method`
+pub async fn time_rpc<T>(method: &'static str, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let output = future.await;
+    histogram!(RPC_LATENCY, "method" => method).record(start.elapsed().as_secs_f64());
+    output
+}
+
+/// Counts a transaction in [`PENDING_TXS`] for as long as the guard lives
+#[derive(Debug)]
+pub struct PendingTxGuard(());
+
+impl PendingTxGuard {
+    /// Increment the pending transaction gauge
+    pub fn new() -> Self {
+        gauge!(PENDING_TXS).increment(1);
+        PENDING_TX_COUNT.fetch_add(1, Ordering::Relaxed);
+        Self(())
+    }
+}
+
+impl Default for PendingTxGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PendingTxGuard {
+    fn drop(&mut self) {
+        gauge!(PENDING_TXS).decrement(1);
+        PENDING_TX_COUNT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,265 @@
+use std::future::IntoFuture;
+
+use alloy::{
+    primitives::{
+        address, aliases::U24, eip191_hash_message, keccak256, Address, Bytes, B256, U160, U256,
+    },
+    providers::Provider,
+    signers::Signature,
+    sol_types::SolValue,
+};
+use eigensdk::common::SdkProvider;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::error::{ChainError, ConfigError, QuoteError};
+use crate::prometheus::time_rpc;
+use crate::services::Services;
+use crate::swap_payload::SwapTaskPayload;
+
+alloy::sol! {
+    #[sol(rpc)]
+    interface IQuoterV2 {
+        struct QuoteExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint256 amountIn;
+            uint24 fee;
+            uint160 sqrtPriceLimitX96;
+        }
+
+        function quoteExactInputSingle(QuoteExactInputSingleParams memory params)
+            external
+            returns (uint256 amountOut, uint160 sqrtPriceX96After, uint32 initializedTicksCrossed, uint256 gasEstimate);
+    }
+}
+
+/// Uniswap V3 fee tiers, in hundredths of a basis point
+pub const DEFAULT_FEE_TIERS: [u32; 4] = [100, 500, 3_000, 10_000];
+
+/// Canonical QuoterV2 deployment of the chain `chain_id`, if Uniswap deployed one
+pub fn quoter_v2_address(chain_id: u64) -> Option<Address> {
+    match chain_id {
+        // Ethereum, Optimism, Polygon, Arbitrum
+        1 | 10 | 137 | 42161 => Some(address!("0x61fFE014bA17989E743c5F6cB21bF9697530B21e")),
+        8453 => Some(address!("0x3d4e44Eb1374240CE5F1B871ab261CD16335B76a")),
+        11155111 => Some(address!("0xEd1f6473345F45b75F8179591dd5bA1888cf2FB3")),
+        _ => None,
+    }
+}
+
+/// Swap quoting, the `[quoting]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotingConfig {
+    /// Quote swap tasks and include the quote in the responses sent to the aggregator
+    pub enabled: bool,
+    /// QuoterV2 contract, the canonical one of the connected chain if unset
+    pub quoter_address: Option<Address>,
+    /// Pool fee tiers quoted, the best output wins
+    pub fee_tiers: Vec<u32>,
+}
+
+impl Default for QuotingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quoter_address: None,
+            fee_tiers: DEFAULT_FEE_TIERS.to_vec(),
+        }
+    }
+}
+
+impl QuotingConfig {
+    /// Check that at least one valid fee tier is quoted
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.fee_tiers.is_empty() || self.fee_tiers.iter().any(|fee| *fee >= 1 << 24) {
+            return Err(ConfigError::Invalid(
+                "quoting.fee_tiers must hold at least one fee below 16777216".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Best output found for a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Amount of `token_out` the swap yields, in its smallest unit
+    pub amount_out: U256,
+    /// Fee tier of the pool quoted
+    pub fee: u32,
+    /// Gas the swap is estimated to use
+    pub gas_estimate: U256,
+}
+
+/// Quote of a task signed by the operator, sent along its task response
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedQuote {
+    /// Amount of `token_out` the swap yields, in its smallest unit
+    pub amount_out: U256,
+    /// Fee tier of the pool quoted
+    pub fee: u32,
+    /// 65 byte ECDSA signature over [`quote_digest`]
+    pub signature: Bytes,
+}
+
+impl SignedQuote {
+    /// Check that the signature over the quote of task `task_index` recovers to `operator`
+    pub fn verify(&self, task_index: u32, operator: Address) -> Result<(), String> {
+        let signature = Signature::from_raw(&self.signature)
+            .map_err(|e| format!("malformed quote signature: {e}"))?;
+        let signer = signature
+            .recover_address_from_prehash(&quote_digest(task_index, self.amount_out, self.fee))
+            .map_err(|e| format!("unrecoverable quote signature: {e}"))?;
+        if signer != operator {
+            return Err(format!(
+                "quote signature of {signer} does not match operator {operator}"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Hash of `(uint32 taskIndex, uint256 amountOut, uint24 fee)`, signed with the EIP-191 prefix
+pub fn quote_hash(task_index: u32, amount_out: U256, fee: u32) -> B256 {
+    keccak256((task_index, amount_out, U24::from(fee)).abi_encode())
+}
+
+/// EIP-191 digest of [`quote_hash`]
+pub fn quote_digest(task_index: u32, amount_out: U256, fee: u32) -> B256 {
+    eip191_hash_message(quote_hash(task_index, amount_out, fee))
+}
+
+/// Quotes swaps through a Uniswap V3 QuoterV2
+#[derive(Debug)]
+pub struct Quoter {
+    provider: SdkProvider,
+    quoter_address: OnceCell<Address>,
+    fee_tiers: Vec<u32>,
+}
+
+impl Quoter {
+    /// Quoter calling through `rpc_url` and the RPC stack of `services`, the QuoterV2
+    /// address is looked up on the first quote unless `config` sets it
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        config: &QuotingConfig,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            quoter_address: match config.quoter_address {
+                Some(address) => OnceCell::new_with(Some(address)),
+                None => OnceCell::new(),
+            },
+            fee_tiers: config.fee_tiers.clone(),
+        })
+    }
+
+    /// Quoter set in `config`, `None` if disabled
+    pub fn from_config(
+        config: &QuotingConfig,
+        rpc_url: &str,
+        services: &Services,
+    ) -> Result<Option<Self>, ConfigError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        config.validate()?;
+        Self::new(rpc_url, services, config).map(Some)
+    }
+
+    /// Quote the swap carried by the task named `task_name`, `None` for tasks without a
+    /// swap payload
+    pub async fn quote_task(&self, task_name: &str) -> Result<Option<SwapQuote>, QuoteError> {
+        match SwapTaskPayload::decode(task_name)? {
+            Some(payload) => Ok(Some(self.quote(&payload).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Best output of the swap of `payload` across the configured fee tiers
+    pub async fn quote(&self, payload: &SwapTaskPayload) -> Result<SwapQuote, QuoteError> {
+        let quoter = IQuoterV2::new(self.quoter_address().await?, &self.provider);
+        let mut best: Option<SwapQuote> = None;
+        for &fee in &self.fee_tiers {
+            let params = IQuoterV2::QuoteExactInputSingleParams {
+                tokenIn: payload.token_in,
+                tokenOut: payload.token_out,
+                amountIn: payload.amount_in,
+                fee: U24::from(fee),
+                sqrtPriceLimitX96: U160::ZERO,
+            };
+            // Tiers without a pool revert, they are simply not candidates
+            let quoted = match time_rpc(
+                "quoteExactInputSingle",
+                quoter.quoteExactInputSingle(params).call().into_future(),
+            )
+            .await
+            {
+                Ok(quoted) => quoted,
+                Err(e) => {
+                    debug!(fee, error = %e, "No quote for fee tier");
+                    continue;
+                }
+            };
+            if best.map_or(true, |best| quoted.amountOut > best.amount_out) {
+                best = Some(SwapQuote {
+                    amount_out: quoted.amountOut,
+                    fee,
+                    gas_estimate: quoted.gasEstimate,
+                });
+            }
+        }
+        best.ok_or(QuoteError::NoPool {
+            token_in: payload.token_in,
+            token_out: payload.token_out,
+        })
+    }
+
+    async fn quoter_address(&self) -> Result<Address, QuoteError> {
+        self.quoter_address
+            .get_or_try_init(|| async {
+                let chain_id = time_rpc("eth_chainId", self.provider.get_chain_id())
+                    .await
+                    .map_err(ChainError::from)?;
+                quoter_v2_address(chain_id).ok_or(QuoteError::NoQuoter { chain_id })
+            })
+            .await
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    #[test]
+    fn test_signed_quotes_verify_against_their_operator() {
+        let signer = PrivateKeySigner::random();
+        let amount_out = U256::from(2_950_000_000u64);
+        let signature = signer
+            .sign_message_sync(quote_hash(7, amount_out, 500).as_slice())
+            .unwrap();
+        let quote = SignedQuote {
+            amount_out,
+            fee: 500,
+            signature: signature.as_bytes().into(),
+        };
+
+        quote.verify(7, signer.address()).unwrap();
+        assert!(quote.verify(8, signer.address()).is_err());
+        let inflated = SignedQuote {
+            amount_out: amount_out * U256::from(2),
+            ..quote
+        };
+        assert!(inflated.verify(7, signer.address()).is_err());
+    }
+}
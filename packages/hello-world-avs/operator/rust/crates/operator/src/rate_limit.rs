@@ -0,0 +1,156 @@
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{TransportError, TransportFut},
+};
+use serde::Deserialize;
+use tower::Service;
+
+use crate::prometheus::record_rpc_throttled;
+
+/// Outgoing RPC rate, the `[rate_limit]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// RPC calls allowed per second on average, unlimited if unset (`RPC_RATE_LIMIT`)
+    pub requests_per_sec: Option<f64>,
+    /// Calls allowed in a burst above the average rate
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_sec: None,
+            burst: 10,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Limiter of the RPC calls, none without `requests_per_sec`
+    pub fn limiter(&self) -> Option<TokenBucket> {
+        self.requests_per_sec
+            .map(|rate| TokenBucket::new(rate, self.burst))
+    }
+}
+
+/// Token bucket refilled at a constant rate, each call taking one token
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Bucket holding up to `burst` tokens, refilled with `rate` tokens per second
+    pub fn new(rate: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            rate,
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token, or return how long until the next one is available
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().expect("token bucket lock poisoned");
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+        state.refilled_at = now;
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - state.tokens) / self.rate))
+        }
+    }
+
+    /// Wait until a token is available and take it
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Transport holding the calls of a provider back until `limiter` has a token for them,
+/// the clients sharing a limiter sharing its rate
+#[derive(Debug, Clone)]
+pub struct ThrottleTransport<S> {
+    inner: S,
+    limiter: Option<Arc<TokenBucket>>,
+}
+
+impl<S> ThrottleTransport<S> {
+    /// Calls of `inner` limited by `limiter`, unlimited without one
+    pub fn new(inner: S, limiter: Option<Arc<TokenBucket>>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<S> Service<RequestPacket> for ThrottleTransport<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let Some(limiter) = self.limiter.clone() else {
+            return self.inner.call(request);
+        };
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            if limiter.try_acquire().is_err() {
+                record_rpc_throttled();
+                limiter.acquire().await;
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_allows_a_burst_then_waits() {
+        let bucket = TokenBucket::new(1.0, 3);
+        for _ in 0..3 {
+            bucket.try_acquire().unwrap();
+        }
+        let wait = bucket.try_acquire().unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+    }
+}
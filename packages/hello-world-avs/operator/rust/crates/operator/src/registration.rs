@@ -0,0 +1,412 @@
+use alloy::primitives::{Address, FixedBytes, B256, U256};
+use chrono::Utc;
+use eigensdk::client_elcontracts::reader::ELChainReader;
+use eigensdk::logging::logger::SharedLogger;
+use eigensdk::utils::slashing::core::delegationmanager::DelegationManager;
+use rand::RngCore;
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
+use swap_manager_utils::ecdsastakeregistry::ISignatureUtilsMixinTypes::SignatureWithSaltAndExpiry;
+use tracing::info;
+
+use crate::config::ContractAddresses;
+use crate::error::{ChainError, OperatorError};
+use crate::gas::estimate_call_gas;
+use crate::retry::RetryConfig;
+use crate::services::Services;
+use crate::signer::OperatorSigner;
+use crate::stake_monitor::{StakeMonitor, StakeMonitorConfig, StakeStatus};
+
+/// Seconds the AVS registration signature stays valid
+const SIGNATURE_EXPIRY_SECS: i64 = 3600;
+
+/// Register `signer` as an EigenLayer operator and with the AVS stake registry.
+///
+/// Steps that are already done are skipped, so this is safe to run on every start. An
+/// EigenLayer operator that already exists only gets its metadata URI updated when
+/// `metadata_uri` is not empty. The operator address doubles as the ECDSA signing key
+/// registered with the stake registry, see [`join_quorum`].
+pub async fn register_operator(
+    logger: SharedLogger,
+    rpc_url: &str,
+    services: &Services,
+    signer: &OperatorSigner,
+    contracts: &ContractAddresses,
+    metadata_uri: &str,
+) -> Result<(), OperatorError> {
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(rpc_url)?;
+
+    let elcontracts_reader_instance = ELChainReader::new(
+        logger.clone(),
+        None,
+        contracts.delegation_manager,
+        Address::ZERO,
+        contracts.avs_directory,
+        None,
+        rpc_url.to_string(),
+    );
+
+    let is_registered = elcontracts_reader_instance
+        .is_operator_registered(signer.address())
+        .await?;
+    info!(is_registered, "Checked EigenLayer operator registration");
+    // Sent through our own provider rather than ELChainWriter, which only accepts a raw
+    // private key, so remote signers can register too
+    let delegation_manager = DelegationManager::new(contracts.delegation_manager, &pr);
+    if !is_registered {
+        let register =
+            delegation_manager.registerAsOperator(signer.address(), 0, metadata_uri.to_string());
+        let gas = estimate_call_gas(
+            services.gas_limit_multiplier(),
+            "registerAsOperator",
+            &register,
+        )
+        .await?;
+        let pending = register.gas(gas).send().await?;
+        services.audit_transaction(
+            signer.address(),
+            "registerAsOperator",
+            None,
+            *pending.tx_hash(),
+        );
+        let receipt = pending.get_receipt().await?;
+        let tx_hash = receipt.transaction_hash;
+        if !receipt.status() {
+            return Err(ChainError::Reverted {
+                method: "registerAsOperator",
+                tx_hash,
+            }
+            .into());
+        }
+        info!(%tx_hash, "Operator registered on EL successfully");
+    } else if !metadata_uri.is_empty() {
+        update_metadata_uri(
+            rpc_url,
+            services,
+            signer,
+            contracts.delegation_manager,
+            metadata_uri,
+        )
+        .await?;
+    }
+
+    join_quorum(logger, rpc_url, services, signer, contracts).await
+}
+
+/// Register the EigenLayer operator `signer` with the quorum of the AVS stake registry,
+/// a no-op when it is registered already.
+///
+/// Fails before signing anything when the operator's current weight is below the
+/// minimum weight of the quorum, as the registry would then record it with no weight.
+pub async fn join_quorum(
+    logger: SharedLogger,
+    rpc_url: &str,
+    services: &Services,
+    signer: &OperatorSigner,
+    contracts: &ContractAddresses,
+) -> Result<(), OperatorError> {
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(rpc_url)?;
+    let elcontracts_reader_instance = ELChainReader::new(
+        logger,
+        None,
+        contracts.delegation_manager,
+        Address::ZERO,
+        contracts.avs_directory,
+        None,
+        rpc_url.to_string(),
+    );
+    if !elcontracts_reader_instance
+        .is_operator_registered(signer.address())
+        .await?
+    {
+        return Err(not_an_operator(signer.address()));
+    }
+
+    let contract_ecdsa_stake_registry = ECDSAStakeRegistry::new(contracts.stake_registry, &pr);
+    let registered_with_avs = contract_ecdsa_stake_registry
+        .operatorRegistered(signer.address())
+        .call()
+        .await?
+        ._0;
+    if registered_with_avs {
+        info!(operator = %signer.address(), "Operator already registered on AVS");
+        return Ok(());
+    }
+    check_minimum_weight(
+        &stake_status(
+            rpc_url,
+            services,
+            contracts.stake_registry,
+            signer.address(),
+        )
+        .await?,
+    )?;
+
+    let mut salt = [0u8; 32];
+    rand::rng().fill_bytes(&mut salt);
+
+    let salt = FixedBytes::from_slice(&salt);
+    let now = Utc::now().timestamp();
+    let expiry: U256 = U256::from(now + SIGNATURE_EXPIRY_SECS);
+
+    let digest_hash = elcontracts_reader_instance
+        .calculate_operator_avs_registration_digest_hash(
+            signer.address(),
+            contracts.swap_manager,
+            salt,
+            expiry,
+        )
+        .await?;
+
+    let signature = signer.sign_hash(&digest_hash).await?;
+    let operator_signature = SignatureWithSaltAndExpiry {
+        signature: signature.as_bytes().into(),
+        salt,
+        expiry,
+    };
+    let registeroperator_details_call = contract_ecdsa_stake_registry
+        .registerOperatorWithSignature(operator_signature, signer.address());
+    let gas = estimate_call_gas(
+        services.gas_limit_multiplier(),
+        "registerOperatorWithSignature",
+        &registeroperator_details_call,
+    )
+    .await?;
+    let pending = registeroperator_details_call.gas(gas).send().await?;
+    services.audit_transaction(
+        signer.address(),
+        "registerOperatorWithSignature",
+        None,
+        *pending.tx_hash(),
+    );
+    let receipt = pending.get_receipt().await?;
+    let tx_hash = receipt.transaction_hash;
+    if !receipt.status() {
+        return Err(ChainError::Reverted {
+            method: "registerOperatorWithSignature",
+            tx_hash,
+        }
+        .into());
+    }
+
+    info!(
+        operator = %signer.address(),
+        %tx_hash,
+        "Operator registered on AVS successfully"
+    );
+
+    Ok(())
+}
+
+/// Point the EigenLayer operator `signer` at the metadata JSON served at `metadata_uri`
+pub async fn update_metadata_uri(
+    rpc_url: &str,
+    services: &Services,
+    signer: &OperatorSigner,
+    delegation_manager: Address,
+    metadata_uri: &str,
+) -> Result<B256, OperatorError> {
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(rpc_url)?;
+    let delegation_manager = DelegationManager::new(delegation_manager, &pr);
+    let is_operator = delegation_manager
+        .isOperator(signer.address())
+        .call()
+        .await?
+        ._0;
+    if !is_operator {
+        return Err(not_an_operator(signer.address()));
+    }
+    let update =
+        delegation_manager.updateOperatorMetadataURI(signer.address(), metadata_uri.to_string());
+    let gas = estimate_call_gas(
+        services.gas_limit_multiplier(),
+        "updateOperatorMetadataURI",
+        &update,
+    )
+    .await?;
+    let pending = update.gas(gas).send().await?;
+    services.audit_transaction(
+        signer.address(),
+        "updateOperatorMetadataURI",
+        None,
+        *pending.tx_hash(),
+    );
+    let receipt = pending.get_receipt().await?;
+    let tx_hash = receipt.transaction_hash;
+    if !receipt.status() {
+        return Err(ChainError::Reverted {
+            method: "updateOperatorMetadataURI",
+            tx_hash,
+        }
+        .into());
+    }
+    info!(%tx_hash, metadata_uri, "Operator metadata URI updated");
+    Ok(tx_hash)
+}
+
+/// Refresh the weight the stake registry at `stake_registry` holds for `signer` from its
+/// current delegated stake, refused when that stake is below the minimum weight
+pub async fn update_stake(
+    rpc_url: &str,
+    services: &Services,
+    signer: &OperatorSigner,
+    stake_registry: Address,
+) -> Result<B256, OperatorError> {
+    let status = stake_status(rpc_url, services, stake_registry, signer.address()).await?;
+    if !status.registered {
+        return Err(ChainError::Unexpected(format!(
+            "{} is not registered with the AVS, run `operator quorum join` first",
+            signer.address()
+        ))
+        .into());
+    }
+    check_minimum_weight(&status)?;
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(rpc_url)?;
+    let stake_registry = ECDSAStakeRegistry::new(stake_registry, &pr);
+    let update = stake_registry.updateOperators(vec![signer.address()]);
+    let gas =
+        estimate_call_gas(services.gas_limit_multiplier(), "updateOperators", &update).await?;
+    let pending = update.gas(gas).send().await?;
+    services.audit_transaction(
+        signer.address(),
+        "updateOperators",
+        None,
+        *pending.tx_hash(),
+    );
+    let receipt = pending.get_receipt().await?;
+    let tx_hash = receipt.transaction_hash;
+    if !receipt.status() {
+        return Err(ChainError::Reverted {
+            method: "updateOperators",
+            tx_hash,
+        }
+        .into());
+    }
+    info!(operator = %signer.address(), %tx_hash, weight = %status.weight, "Operator stake updated");
+    Ok(tx_hash)
+}
+
+/// Current standing of `operator` in the stake registry at `stake_registry`
+pub async fn stake_status(
+    rpc_url: &str,
+    services: &Services,
+    stake_registry: Address,
+    operator: Address,
+) -> Result<StakeStatus, OperatorError> {
+    let status = StakeMonitor::new(
+        rpc_url,
+        services,
+        stake_registry,
+        operator,
+        &StakeMonitorConfig::default(),
+        &RetryConfig::default(),
+    )?
+    .status()
+    .await?;
+    Ok(status)
+}
+
+fn check_minimum_weight(status: &StakeStatus) -> Result<(), OperatorError> {
+    if status.weight < status.minimum_weight {
+        return Err(OperatorError::BelowMinimumWeight {
+            weight: status.weight,
+            minimum_weight: status.minimum_weight,
+        });
+    }
+    Ok(())
+}
+
+fn not_an_operator(operator: Address) -> OperatorError {
+    ChainError::Unexpected(format!(
+        "{operator} is not an EigenLayer operator, run `operator register` first"
+    ))
+    .into()
+}
+
+/// Deregister `signer` from the AVS stake registry at `stake_registry_address`, a no-op when it is not registered.
+///
+/// The EigenLayer operator registration is left in place, it is shared with other AVSs.
+pub async fn deregister_operator(
+    rpc_url: &str,
+    services: &Services,
+    signer: &OperatorSigner,
+    stake_registry_address: Address,
+) -> Result<(), OperatorError> {
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(rpc_url)?;
+    let stake_registry = ECDSAStakeRegistry::new(stake_registry_address, &pr);
+
+    let registered = stake_registry
+        .operatorRegistered(signer.address())
+        .call()
+        .await?
+        ._0;
+    if !registered {
+        info!(operator = %signer.address(), "Operator is not registered on AVS");
+        return Ok(());
+    }
+
+    let deregister = stake_registry.deregisterOperator();
+    let gas = estimate_call_gas(
+        services.gas_limit_multiplier(),
+        "deregisterOperator",
+        &deregister,
+    )
+    .await?;
+    let pending = deregister.gas(gas).send().await?;
+    services.audit_transaction(
+        signer.address(),
+        "deregisterOperator",
+        None,
+        *pending.tx_hash(),
+    );
+    let receipt = pending.get_receipt().await?;
+    let tx_hash = receipt.transaction_hash;
+    if !receipt.status() {
+        return Err(ChainError::Reverted {
+            method: "deregisterOperator",
+            tx_hash,
+        }
+        .into());
+    }
+    info!(operator = %signer.address(), %tx_hash, "Operator deregistered from AVS");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_below_the_minimum_are_refused() {
+        let status = StakeStatus {
+            registered: false,
+            weight: U256::ZERO,
+            minimum_weight: U256::from(100),
+            total_weight: U256::from(420),
+            threshold_weight: U256::from(280),
+        };
+        assert!(matches!(
+            check_minimum_weight(&status),
+            Err(OperatorError::BelowMinimumWeight { .. })
+        ));
+        assert!(check_minimum_weight(&StakeStatus {
+            weight: U256::from(100),
+            ..status
+        })
+        .is_ok());
+    }
+}
@@ -0,0 +1,265 @@
+use std::path::PathBuf;
+
+use alloy::{
+    consensus::{SignableTransaction, TxEnvelope, TxType},
+    eips::eip2718::Decodable2718,
+    network::TxSigner,
+    primitives::{Address, Bytes, ChainId, B256},
+    signers::{Error as AlloySignerError, Result as SignerResult, Signature, Signer},
+};
+use async_trait::async_trait;
+use reqwest::{Certificate, Client, Identity, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::SignerError;
+
+/// TLS material used to reach the signing host
+#[derive(Debug, Clone, Default)]
+pub struct RemoteSignerTls {
+    /// PEM CA bundle trusted in addition to the system roots
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate presented for mutual TLS
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key of `client_cert`
+    pub client_key: Option<PathBuf>,
+}
+
+/// Signer delegating to a Web3Signer compatible host.
+///
+/// Transactions go through `eth_signTransaction`, messages through `eth_sign` and raw
+/// digests through the `/api/v1/eth1/sign/{address}` endpoint. Every returned signature
+/// is checked to recover to `address`, so a misbehaving host cannot make us send
+/// something we did not ask for.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: Client,
+    url: Url,
+    address: Address,
+    chain_id: Option<ChainId>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RemoteSigner {
+    /// Connect to the signer at `url` holding the key of `address`
+    pub fn new(url: Url, address: Address, tls: &RemoteSignerTls) -> Result<Self, SignerError> {
+        let read = |what, path: &PathBuf| {
+            std::fs::read(path).map_err(|source| SignerError::Read {
+                what,
+                path: path.clone(),
+                source,
+            })
+        };
+        let mut builder = Client::builder().use_rustls_tls();
+        if let Some(ca_cert) = &tls.ca_cert {
+            let pem = read("CA cert", ca_cert)?;
+            for cert in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        match (&tls.client_cert, &tls.client_key) {
+            (Some(cert), Some(key)) => {
+                let mut pem = read("client cert", cert)?;
+                pem.extend(read("client key", key)?);
+                builder = builder.identity(Identity::from_pem(&pem)?);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(SignerError::Invalid(
+                    "client_cert and client_key must be set together".to_string(),
+                ))
+            }
+        }
+        Ok(Self {
+            client: builder.build()?,
+            url,
+            address,
+            chain_id: None,
+        })
+    }
+
+    async fn rpc<T: DeserializeOwned>(&self, method: &str, params: Value) -> SignerResult<T> {
+        let response: RpcResponse<T> = self
+            .client
+            .post(self.url.clone())
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(AlloySignerError::other)?
+            .json()
+            .await
+            .map_err(AlloySignerError::other)?;
+        match (response.result, response.error) {
+            (Some(result), _) => Ok(result),
+            (None, Some(error)) => Err(AlloySignerError::message(format!(
+                "{method} failed with {}: {}",
+                error.code, error.message
+            ))),
+            (None, None) => Err(AlloySignerError::message(format!(
+                "{method} returned no result"
+            ))),
+        }
+    }
+
+    fn check_signer(
+        &self,
+        recovered: Result<Address, alloy::primitives::SignatureError>,
+    ) -> SignerResult<()> {
+        match recovered {
+            Ok(address) if address == self.address => Ok(()),
+            Ok(address) => Err(AlloySignerError::message(format!(
+                "remote signer returned a signature of {address}, expected {}",
+                self.address
+            ))),
+            Err(e) => Err(AlloySignerError::other(e)),
+        }
+    }
+}
+
+/// `eth_signTransaction` parameters of `tx` sent from `from`
+pub fn sign_transaction_params(
+    from: Address,
+    tx: &dyn SignableTransaction<Signature>,
+) -> Result<Value, SignerError> {
+    let mut params = json!({
+        "from": from,
+        "to": tx.to(),
+        "gas": format!("{:#x}", tx.gas_limit()),
+        "value": tx.value(),
+        "data": Bytes::copy_from_slice(tx.input()),
+        "nonce": format!("{:#x}", tx.nonce()),
+    });
+    if let Some(chain_id) = tx.chain_id() {
+        params["chainId"] = format!("{chain_id:#x}").into();
+    }
+    match tx.ty() {
+        ty if ty == TxType::Legacy as u8 => {
+            let gas_price = tx.gas_price().ok_or_else(|| {
+                SignerError::Invalid("legacy transaction without gas price".to_string())
+            })?;
+            params["gasPrice"] = format!("{gas_price:#x}").into();
+        }
+        ty if ty == TxType::Eip1559 as u8 => {
+            params["maxFeePerGas"] = format!("{:#x}", tx.max_fee_per_gas()).into();
+            params["maxPriorityFeePerGas"] =
+                format!("{:#x}", tx.max_priority_fee_per_gas().unwrap_or_default()).into();
+        }
+        ty => {
+            return Err(SignerError::Invalid(format!(
+                "remote signer does not support transaction type {ty}"
+            )))
+        }
+    }
+    Ok(params)
+}
+
+#[async_trait]
+impl TxSigner<Signature> for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> SignerResult<Signature> {
+        let params =
+            sign_transaction_params(self.address, tx).map_err(AlloySignerError::message)?;
+        let raw: Bytes = self.rpc("eth_signTransaction", json!([params])).await?;
+        let signed = TxEnvelope::decode_2718(&mut raw.as_ref()).map_err(AlloySignerError::other)?;
+        let signature = *signed.signature();
+        self.check_signer(signature.recover_address_from_prehash(&tx.signature_hash()))?;
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    async fn sign_hash(&self, hash: &B256) -> SignerResult<Signature> {
+        let url = self
+            .url
+            .join(&format!("api/v1/eth1/sign/{}", self.address))
+            .map_err(AlloySignerError::other)?;
+        let body = self
+            .client
+            .post(url)
+            .json(&json!({ "data": hash }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(AlloySignerError::other)?
+            .text()
+            .await
+            .map_err(AlloySignerError::other)?;
+        let bytes =
+            hex::decode(body.trim().trim_start_matches("0x")).map_err(AlloySignerError::other)?;
+        let signature = Signature::from_raw(&bytes).map_err(AlloySignerError::other)?;
+        self.check_signer(signature.recover_address_from_prehash(hash))?;
+        Ok(signature)
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> SignerResult<Signature> {
+        let raw: Bytes = self
+            .rpc(
+                "eth_sign",
+                json!([self.address, Bytes::copy_from_slice(message)]),
+            )
+            .await?;
+        let signature = Signature::from_raw(&raw).map_err(AlloySignerError::other)?;
+        self.check_signer(signature.recover_address_from_msg(message))?;
+        Ok(signature)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{consensus::TxEip1559, primitives::U256};
+
+    #[test]
+    fn test_eip1559_params_carry_fee_caps() {
+        let tx = TxEip1559 {
+            chain_id: 31337,
+            nonce: 7,
+            gas_limit: 500_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: Address::repeat_byte(0x11).into(),
+            value: U256::ZERO,
+            ..Default::default()
+        };
+        let params = sign_transaction_params(Address::repeat_byte(0x22), &tx).unwrap();
+
+        assert_eq!(params["nonce"], "0x7");
+        assert_eq!(params["gas"], "0x7a120");
+        assert_eq!(params["chainId"], "0x7a69");
+        assert_eq!(params["maxFeePerGas"], "0x77359400");
+        assert_eq!(params["maxPriorityFeePerGas"], "0x3b9aca00");
+        assert!(params.get("gasPrice").is_none());
+    }
+}
@@ -0,0 +1,359 @@
+use std::{collections::BTreeMap, future::IntoFuture, sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::BlockNumberOrTag,
+};
+use eigensdk::common::SdkProvider;
+use serde::Deserialize;
+use swap_manager_utils::SwapManager::ISwapManager::Task;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::control::TaskOutcome;
+use crate::error::{ChainError, ConfigError, OperatorError};
+use crate::prometheus::{record_reorg, record_tasks_confirmed, time_rpc};
+use crate::retry::{retry, RetryConfig};
+use crate::services::Services;
+use crate::task_listener::NewTask;
+use crate::task_source::{fetch_tasks, MAX_POLL_BLOCK_RANGE};
+use crate::task_store::{StoredTask, TaskStore};
+
+/// The `[reorg]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReorgConfig {
+    /// Watch for reorgs, only done when a task store is configured
+    pub enabled: bool,
+    /// Number of recent block hashes kept, reorgs deeper than this go unnoticed
+    pub depth: u64,
+    /// Seconds between two checks of the chain head
+    pub poll_interval_secs: u64,
+    /// Blocks mined on top of a response before it counts as confirmed, confirmed with its
+    /// receipt if 0 or when reorgs aren't watched
+    pub confirmations: u64,
+}
+
+impl Default for ReorgConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            depth: 64,
+            poll_interval_secs: 4,
+            confirmations: 0,
+        }
+    }
+}
+
+/// Hashes of the most recent blocks, as last seen on the canonical chain
+#[derive(Debug, Clone)]
+pub struct BlockWindow {
+    depth: u64,
+    hashes: BTreeMap<u64, B256>,
+}
+
+impl BlockWindow {
+    /// Window keeping the last `depth` blocks
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth,
+            hashes: BTreeMap::new(),
+        }
+    }
+
+    /// Highest block in the window
+    pub fn last(&self) -> Option<u64> {
+        self.hashes.keys().next_back().copied()
+    }
+
+    /// Hash recorded for block `number`
+    pub fn hash(&self, number: u64) -> Option<B256> {
+        self.hashes.get(&number).copied()
+    }
+
+    /// Whether block `number` with `parent_hash` builds on the recorded chain, which is
+    /// also the case when its parent isn't recorded
+    pub fn extends(&self, number: u64, parent_hash: B256) -> bool {
+        match number.checked_sub(1).and_then(|parent| self.hash(parent)) {
+            Some(hash) => hash == parent_hash,
+            None => true,
+        }
+    }
+
+    /// Record block `number` and drop the blocks that fell out of the window
+    pub fn insert(&mut self, number: u64, hash: B256) {
+        self.hashes.insert(number, hash);
+        let oldest = number.saturating_sub(self.depth.saturating_sub(1));
+        self.hashes = self.hashes.split_off(&oldest);
+    }
+
+    /// Drop block `from` and the ones after it
+    pub fn truncate(&mut self, from: u64) {
+        self.hashes.split_off(&from);
+    }
+}
+
+/// Watches the chain head for reorgs and sends the tasks they undid back to the task loop
+#[derive(Debug)]
+pub struct ReorgMonitor {
+    provider: SdkProvider,
+    swap_manager_address: Address,
+    store: Arc<dyn TaskStore>,
+    operator: Address,
+    window: BlockWindow,
+    poll_interval: Duration,
+    confirmations: u64,
+    retry: RetryConfig,
+    services: Services,
+}
+
+impl ReorgMonitor {
+    /// Monitor the chain at `rpc_url` through the RPC stack of `services`, invalidating
+    /// tasks in `store`. Confirmations are published on the event bus of `services` as
+    /// responses of `operator`.
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        swap_manager_address: Address,
+        store: Arc<dyn TaskStore>,
+        operator: Address,
+        config: &ReorgConfig,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            swap_manager_address,
+            store,
+            operator,
+            window: BlockWindow::new(config.depth),
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+            confirmations: config.confirmations,
+            retry: retry_config.clone(),
+            services: services.clone(),
+        })
+    }
+
+    /// Check the head every poll interval, sending the tasks to handle again to `replays`,
+    /// until the task loop goes away
+    pub async fn run(mut self, replays: mpsc::Sender<NewTask>) {
+        info!(depth = self.window.depth, "Watching for reorgs");
+        while !replays.is_closed() {
+            if let Err(e) = self.check(&replays).await {
+                warn!(error = %e, "Reorg check failed");
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Walk the blocks since the last check, handling a reorg if one no longer builds on
+    /// the recorded chain, then confirm the responses deep enough under the head. The tasks
+    /// the reorg undid are sent to `replays`
+    pub async fn check(&mut self, replays: &mpsc::Sender<NewTask>) -> Result<(), OperatorError> {
+        let provider = &self.provider;
+        let head = retry(&self.retry, "eth_blockNumber", || {
+            time_rpc("eth_blockNumber", provider.get_block_number())
+        })
+        .await?;
+        let oldest = head.saturating_sub(self.window.depth.saturating_sub(1));
+        let mut number = self.window.last().map_or(head, |last| last + 1).max(oldest);
+        while number <= head {
+            let (hash, parent_hash) = self.block_hashes(number).await?;
+            if !self.window.extends(number, parent_hash) {
+                let fork_block = self.fork_block(number - 1).await?;
+                self.window.truncate(fork_block);
+                self.handle_reorg(fork_block, head, replays).await?;
+                number = fork_block;
+                continue;
+            }
+            self.window.insert(number, hash);
+            number += 1;
+        }
+        self.confirm(head).await?;
+        Ok(())
+    }
+
+    /// Mark the responses with `confirmations` blocks on top of them under `head` as confirmed
+    async fn confirm(&self, head: u64) -> Result<(), OperatorError> {
+        let Some(block) = head.checked_sub(self.confirmations) else {
+            return Ok(());
+        };
+        let confirmed = self.store.confirm_responses(block).await?;
+        if !confirmed.is_empty() {
+            record_tasks_confirmed(confirmed.len());
+            self.services.publish_confirmed(self.operator, &confirmed);
+            info!(
+                ?confirmed,
+                confirmations = self.confirmations,
+                head,
+                "Responses confirmed"
+            );
+        }
+        Ok(())
+    }
+
+    async fn block_hashes(&self, number: u64) -> Result<(B256, B256), ChainError> {
+        let provider = &self.provider;
+        let block = retry(&self.retry, "eth_getBlockByNumber", || {
+            time_rpc(
+                "eth_getBlockByNumber",
+                provider
+                    .get_block_by_number(BlockNumberOrTag::Number(number))
+                    .into_future(),
+            )
+        })
+        .await?
+        .ok_or_else(|| ChainError::Unexpected(format!("block {number} not found")))?;
+        Ok((block.header.hash, block.header.parent_hash))
+    }
+
+    /// First block at or below `from` whose recorded hash is no longer canonical, walking
+    /// back until the chains agree again
+    async fn fork_block(&self, from: u64) -> Result<u64, ChainError> {
+        let mut number = from;
+        while let Some(recorded) = self.window.hash(number) {
+            let (canonical, _) = self.block_hashes(number).await?;
+            if canonical == recorded {
+                return Ok(number + 1);
+            }
+            if number == 0 {
+                return Ok(0);
+            }
+            number -= 1;
+        }
+        Ok(number + 1)
+    }
+
+    async fn handle_reorg(
+        &self,
+        fork_block: u64,
+        head: u64,
+        replays: &mpsc::Sender<NewTask>,
+    ) -> Result<(), OperatorError> {
+        record_reorg();
+        let invalidated = self.store.invalidate_from(fork_block).await?;
+        warn!(
+            fork_block,
+            head,
+            invalidated = invalidated.len(),
+            "Chain reorganized"
+        );
+
+        // Tasks created in the dropped blocks are handled again if they made it into the
+        // new chain, the task loop skips the ones that were never invalidated
+        let mut recreated = Vec::new();
+        let mut block = fork_block;
+        while block <= head {
+            let to_block = head.min(block + MAX_POLL_BLOCK_RANGE - 1);
+            recreated.extend(
+                fetch_tasks(
+                    &self.provider,
+                    self.swap_manager_address,
+                    block,
+                    to_block,
+                    &self.retry,
+                )
+                .await?,
+            );
+            block = to_block + 1;
+        }
+        for stored in &invalidated {
+            if stored.block_number.is_some_and(|block| block >= fork_block) {
+                if !recreated.iter().any(|task| task.index == stored.task_index) {
+                    warn!(
+                        task_index = stored.task_index,
+                        "Task dropped by the reorg, it no longer exists"
+                    );
+                }
+                continue;
+            }
+            // Only the response was dropped, unless it was mined again in the new chain
+            if let Some(response_block) = self.remined_block(stored.response_tx).await? {
+                info!(
+                    task_index = stored.task_index,
+                    response_block, "Response mined again after the reorg"
+                );
+                self.store
+                    .record_outcome(
+                        stored.task_index,
+                        TaskOutcome::Responded,
+                        stored.response_tx,
+                        Some(response_block),
+                        None,
+                    )
+                    .await?;
+                continue;
+            }
+            info!(
+                task_index = stored.task_index,
+                "Response dropped by the reorg, responding again"
+            );
+            replay(replays, stored_task(stored)).await;
+        }
+        for task in recreated {
+            replay(replays, task).await;
+        }
+        Ok(())
+    }
+
+    /// Block the `response_tx` is now mined in, if any
+    async fn remined_block(&self, response_tx: Option<B256>) -> Result<Option<u64>, ChainError> {
+        let Some(hash) = response_tx else {
+            return Ok(None);
+        };
+        let provider = &self.provider;
+        let receipt = retry(&self.retry, "eth_getTransactionReceipt", || {
+            time_rpc(
+                "eth_getTransactionReceipt",
+                provider.get_transaction_receipt(hash),
+            )
+        })
+        .await?;
+        Ok(receipt
+            .filter(|receipt| receipt.status())
+            .and_then(|receipt| receipt.block_number))
+    }
+}
+
+async fn replay(replays: &mpsc::Sender<NewTask>, task: NewTask) {
+    // Fails only once the task loop is gone, and `run` stops then
+    let _ = replays.send(task).await;
+}
+
+/// Rebuild the event of a stored task whose creation is still canonical
+fn stored_task(stored: &StoredTask) -> NewTask {
+    NewTask {
+        index: stored.task_index,
+        task: Task {
+            name: stored.task_name.clone(),
+            taskCreatedBlock: stored.task_created_block,
+        },
+        block_number: stored.block_number,
+        block_hash: None,
+        transaction_hash: stored.created_tx,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_window_detects_a_changed_parent() {
+        let mut window = BlockWindow::new(3);
+        for number in 10..=14 {
+            window.insert(number, B256::with_last_byte(number as u8));
+        }
+        assert_eq!(window.hash(11), None);
+        assert_eq!(window.hash(12), Some(B256::with_last_byte(12)));
+        assert_eq!(window.last(), Some(14));
+
+        assert!(window.extends(15, B256::with_last_byte(14)));
+        assert!(!window.extends(15, B256::with_last_byte(99)));
+        // Parents older than the window can't be checked
+        assert!(window.extends(11, B256::with_last_byte(99)));
+
+        window.truncate(13);
+        assert_eq!(window.last(), Some(12));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{error::Error, future::Future, time::Duration};
+
+use rand::Rng;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::prometheus::record_call_failure;
+
+/// Error messages that will fail again no matter how often the call is retried
+const FATAL_PATTERNS: [&str; 8] = [
+    "execution reverted",
+    "revert",
+    "insufficient funds",
+    "invalid signature",
+    "invalid argument",
+    "intrinsic gas too low",
+    "exceeds block gas limit",
+    "unknown function",
+];
+
+/// Error messages caused by the network or an overloaded provider
+const RETRYABLE_PATTERNS: [&str; 12] = [
+    "connection",
+    "timed out",
+    "timeout",
+    "reset",
+    "broken pipe",
+    "backend connection task has stopped",
+    "429",
+    "rate limit",
+    "too many requests",
+    "temporarily unavailable",
+    "502",
+    "503",
+];
+
+/// Backoff settings, the `[retry]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts including the first one, 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_delay_ms: u64,
+    /// Upper bound of a single delay
+    pub max_delay_ms: u64,
+    /// Factor applied to the delay after every attempt
+    pub multiplier: f64,
+    /// Fraction of the delay randomised in both directions, between 0 and 1
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay_ms: 500,
+            max_delay_ms: 30_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+/// Whether it is worth retrying after an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Transient failure such as a connection reset or rate limit
+    Retryable,
+    /// Deterministic failure such as a revert
+    Fatal,
+}
+
+/// Classify an error by looking at every message of its source chain.
+///
+/// Errors that match neither list are treated as retryable, since unknown
+/// failures are far more often transport hiccups than deterministic ones.
+pub fn classify_error(error: &(dyn Error + 'static)) -> ErrorClass {
+    let messages: Vec<String> = chain(error).map(|e| e.to_string().to_lowercase()).collect();
+    let matches = |patterns: &[&str]| {
+        messages
+            .iter()
+            .any(|message| patterns.iter().any(|pattern| message.contains(pattern)))
+    };
+    if matches(&FATAL_PATTERNS) {
+        ErrorClass::Fatal
+    } else {
+        ErrorClass::Retryable
+    }
+}
+
+/// Whether `error` looks like a network failure rather than an unknown one
+pub fn is_transient(error: &(dyn Error + 'static)) -> bool {
+    chain(error).any(|e| {
+        let message = e.to_string().to_lowercase();
+        RETRYABLE_PATTERNS
+            .iter()
+            .any(|pattern| message.contains(pattern))
+    })
+}
+
+/// Error classes of [`failure_class`] with the messages that identify them, the first
+/// matching class wins
+const FAILURE_CLASSES: [(&str, &[&str]); 7] = [
+    ("reverted", &["execution reverted", "revert"]),
+    ("insufficient_funds", &["insufficient funds"]),
+    (
+        "nonce",
+        &["nonce too low", "nonce too high", "invalid nonce"],
+    ),
+    (
+        "underpriced",
+        &[
+            "underpriced",
+            "fee cap",
+            "max fee per gas less than block base fee",
+        ],
+    ),
+    ("rate_limited", &["429", "rate limit", "too many requests"]),
+    ("timeout", &["timed out", "timeout"]),
+    (
+        "connection",
+        &[
+            "connection",
+            "reset",
+            "broken pipe",
+            "backend connection task has stopped",
+            "502",
+            "503",
+        ],
+    ),
+];
+
+/// Coarse class of `error` for breaking failures down, e.g. `reverted`, `nonce` or
+/// `timeout`, `other` if no message of its source chain is recognised
+pub fn failure_class(error: &(dyn Error + 'static)) -> &'static str {
+    let messages: Vec<String> = chain(error).map(|e| e.to_string().to_lowercase()).collect();
+    FAILURE_CLASSES
+        .iter()
+        .find(|(_, patterns)| {
+            messages
+                .iter()
+                .any(|message| patterns.iter().any(|pattern| message.contains(pattern)))
+        })
+        .map_or("other", |(class, _)| class)
+}
+
+/// `error` followed by its sources
+fn chain<'a>(error: &'a (dyn Error + 'static)) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+    std::iter::successors(Some(error), |&e| e.source())
+}
+
+impl RetryConfig {
+    /// Delay to wait after the `attempt`-th failure (1-based), before jitter
+    pub fn base_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let delay = self.initial_delay_ms as f64 * self.multiplier.powi(exponent);
+        Duration::from_millis(delay.min(self.max_delay_ms as f64) as u64)
+    }
+
+    /// Delay to wait after the `attempt`-th failure with jitter applied
+    pub fn delay(&self, attempt: u32, rng: &mut impl Rng) -> Duration {
+        let base = self.base_delay(attempt).as_millis() as f64;
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rng.random_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+        Duration::from_millis((base * factor) as u64)
+    }
+}
+
+/// Run `f` until it succeeds, fails with a fatal error, or `config.max_attempts` is reached
+pub async fn retry<T, E, F, Fut>(config: &RetryConfig, operation: &str, mut f: F) -> Result<T, E>
+where
+    E: Error + 'static,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                record_call_failure(operation, failure_class(&e));
+                if classify_error(&e) == ErrorClass::Fatal || attempt >= config.max_attempts {
+                    return Err(e);
+                }
+                let delay = config.delay(attempt, &mut rand::rng());
+                warn!(
+                    operation,
+                    attempt,
+                    max_attempts = config.max_attempts,
+                    ?delay,
+                    error = %e,
+                    "Call failed, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let config = RetryConfig {
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+            multiplier: 3.0,
+            jitter: 0.0,
+            ..Default::default()
+        };
+
+        assert_eq!(config.base_delay(1), Duration::from_millis(100));
+        assert_eq!(config.base_delay(2), Duration::from_millis(300));
+        assert_eq!(config.base_delay(3), Duration::from_millis(900));
+        assert_eq!(config.base_delay(4), Duration::from_millis(1_000));
+        assert_eq!(
+            config.delay(2, &mut rand::rng()),
+            Duration::from_millis(300)
+        );
+    }
+
+    use crate::error::{ChainError, SpamError};
+
+    #[test]
+    fn test_reverts_are_fatal() {
+        let revert = SpamError::NonceRejected {
+            nonce: 3,
+            source: Box::new(ChainError::Unexpected(
+                "server returned an error response: execution reverted: Task mismatch".to_string(),
+            )),
+        };
+        let reset = std::io::Error::other("connection reset by peer");
+
+        assert_eq!(classify_error(&revert), ErrorClass::Fatal);
+        assert_eq!(classify_error(&reset), ErrorClass::Retryable);
+        assert!(is_transient(&reset));
+        assert_eq!(failure_class(&revert), "reverted");
+        assert_eq!(failure_class(&reset), "connection");
+        assert_eq!(failure_class(&std::io::Error::other("boom")), "other");
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_on_fatal_errors() {
+        let config = RetryConfig {
+            initial_delay_ms: 1,
+            ..Default::default()
+        };
+        let mut calls = 0;
+        let result: Result<(), _> = retry(&config, "test", || {
+            calls += 1;
+            async { Err(std::io::Error::other("execution reverted")) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
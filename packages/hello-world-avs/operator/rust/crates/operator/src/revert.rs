@@ -0,0 +1,107 @@
+use alloy::{
+    primitives::{hex, Bytes},
+    sol_types::{decode_revert_reason, SolError},
+};
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
+use swap_manager_utils::SwapManager::SwapManager;
+use tracing::warn;
+
+use crate::error::ChainError;
+
+/// Selector and signature of a custom error of the contracts the operator sends to
+macro_rules! known_errors {
+    ($($error:ty),* $(,)?) => {
+        &[$((<$error as SolError>::SELECTOR, <$error as SolError>::SIGNATURE)),*]
+    };
+}
+
+/// Custom errors of the SwapManager, and of the stake registry it checks signatures with
+const KNOWN_ERRORS: &[([u8; 4], &str)] = known_errors!(
+    SwapManager::DelayPeriodNotPassed,
+    SwapManager::OnlyRegistryCoordinator,
+    SwapManager::OnlyRewardsInitiator,
+    SwapManager::OnlyStakeRegistry,
+    ECDSAStakeRegistry::InsufficientSignedStake,
+    ECDSAStakeRegistry::InsufficientWeight,
+    ECDSAStakeRegistry::InvalidLength,
+    ECDSAStakeRegistry::InvalidQuorum,
+    ECDSAStakeRegistry::InvalidReferenceBlock,
+    ECDSAStakeRegistry::InvalidSignature,
+    ECDSAStakeRegistry::InvalidSignedWeight,
+    ECDSAStakeRegistry::InvalidThreshold,
+    ECDSAStakeRegistry::LengthMismatch,
+    ECDSAStakeRegistry::MustUpdateAllOperators,
+    ECDSAStakeRegistry::NotSorted,
+    ECDSAStakeRegistry::OperatorAlreadyRegistered,
+    ECDSAStakeRegistry::OperatorNotRegistered,
+);
+
+/// Readable reason of the revert `data`: a known custom error, an `Error(string)` reason, a
+/// panic code, or the data in hex when none of them matches
+pub fn revert_reason(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "execution reverted without data".to_string();
+    }
+    let known = data.get(..4).and_then(|selector| {
+        KNOWN_ERRORS
+            .iter()
+            .find(|(known, _)| known.as_slice() == selector)
+    });
+    if let Some((_, signature)) = known {
+        return (*signature).to_string();
+    }
+    decode_revert_reason(data)
+        .unwrap_or_else(|| format!("unknown error {}", hex::encode_prefixed(data)))
+}
+
+/// Revert data carried by `error`
+fn revert_data(error: &ChainError) -> Option<Bytes> {
+    match error {
+        ChainError::Transport(e) => e.as_error_resp()?.as_revert_data(),
+        ChainError::Contract(e) => e.as_revert_data(),
+        _ => None,
+    }
+}
+
+/// `error` of a `method` call or gas estimate, turned into [`ChainError::Revert`] with the
+/// decoded reason when it's a revert
+pub fn decode_revert(method: &'static str, error: ChainError) -> ChainError {
+    match revert_data(&error) {
+        Some(data) => {
+            let reason = revert_reason(&data);
+            warn!(method, %reason, "Call reverted");
+            ChainError::Revert { method, reason }
+        }
+        None => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::sol_types::{Panic, PanicKind, Revert};
+
+    #[test]
+    fn test_revert_reasons_are_decoded() {
+        assert_eq!(
+            revert_reason(&SwapManager::OnlyStakeRegistry {}.abi_encode()),
+            "OnlyStakeRegistry()"
+        );
+        assert_eq!(
+            revert_reason(&ECDSAStakeRegistry::InvalidSignature {}.abi_encode()),
+            "InvalidSignature()"
+        );
+        assert_eq!(
+            revert_reason(&Revert::from("Task already responded").abi_encode()),
+            "revert: Task already responded"
+        );
+        assert!(
+            revert_reason(&Panic::from(PanicKind::DivisionByZero).abi_encode())
+                .contains("division or modulo by zero")
+        );
+        assert_eq!(
+            revert_reason(&[0xde, 0xad, 0xbe, 0xef, 0x01]),
+            "unknown error 0xdeadbeef01"
+        );
+    }
+}
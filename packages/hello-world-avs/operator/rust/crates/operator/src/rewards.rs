@@ -0,0 +1,919 @@
+use std::{
+    collections::BTreeMap,
+    future::IntoFuture,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{keccak256, Address, Bytes, B256, U256},
+    providers::Provider,
+    rpc::types::Filter,
+    sol_types::SolEvent,
+};
+use chrono::DateTime;
+use eigensdk::common::SdkProvider;
+use eigensdk::utils::slashing::core::irewardscoordinator::{
+    IRewardsCoordinator, IRewardsCoordinatorTypes,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::OperatorConfig;
+use crate::ens::AddressOrName;
+use crate::error::{ChainError, ConfigError, RewardsError};
+use crate::gas::estimate_call_gas;
+use crate::prometheus::{set_rewards_accrued, set_token_rewards, time_rpc};
+use crate::retry::{retry, RetryConfig};
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::signer::OperatorSigner;
+use crate::task_source::{block_ranges, write_atomically};
+
+/// Prefix of the earner leaves, `EARNER_LEAF_SALT` of the rewards coordinator
+const EARNER_LEAF_SALT: u8 = 0;
+
+/// Prefix of the token leaves, `TOKEN_LEAF_SALT` of the rewards coordinator
+const TOKEN_LEAF_SALT: u8 = 1;
+
+/// Time allowed to download the distribution
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The `[rewards]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RewardsConfig {
+    /// Track the operator's rewards while start_operator runs
+    pub enabled: bool,
+    /// Seconds between two reads of the rewards coordinator
+    pub poll_interval_secs: u64,
+    /// URL or path of the cumulative earnings the claimable root was computed from, JSON
+    /// lines of `earner`, `token` and `cumulative_amount`. `{snapshot}` is replaced by the
+    /// day the root's calculation ends on and `{root}` by the root (`REWARDS_DISTRIBUTION`)
+    pub distribution: Option<String>,
+    /// First block scanned for the operator directed rewards submissions, the block the
+    /// core contracts were deployed in if unset. Required when
+    /// `contracts.rewards_coordinator` overrides the deployment
+    pub from_block: Option<u64>,
+    /// File the scan progress and the accrued rewards are saved to after every scanned
+    /// range and resumed from on start, the scan starting over from `from_block` if unset
+    pub state_file: Option<PathBuf>,
+    /// Receiver of the claimed tokens, the earner if unset
+    pub recipient: Option<AddressOrName>,
+}
+
+impl Default for RewardsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_secs: 3_600,
+            distribution: None,
+            from_block: None,
+            state_file: None,
+            recipient: None,
+        }
+    }
+}
+
+impl RewardsConfig {
+    /// Check that the rewards can be polled with these settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.enabled && self.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "rewards.poll_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.distribution.as_deref().is_some_and(str::is_empty) {
+            return Err(ConfigError::Invalid(
+                "rewards.distribution can't be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Line of a distribution: tokens earned by an earner since the first root
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Earnings {
+    earner: Address,
+    token: Address,
+    cumulative_amount: U256,
+}
+
+/// Cumulative earnings of every earner and token behind a distribution root, the trees
+/// are built with the earners and their tokens in address order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Distribution {
+    earners: BTreeMap<Address, BTreeMap<Address, U256>>,
+}
+
+impl Distribution {
+    /// Parse the JSON lines of a distribution, blank lines are skipped
+    pub fn parse(text: &str) -> Result<Self, RewardsError> {
+        let mut distribution = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let earnings: Earnings =
+                serde_json::from_str(line).map_err(|source| RewardsError::Parse {
+                    line: number + 1,
+                    source,
+                })?;
+            distribution.insert(earnings.earner, earnings.token, earnings.cumulative_amount);
+        }
+        Ok(distribution)
+    }
+
+    /// Read the distribution `root` was computed from at `source`, a URL or a path
+    pub async fn load(
+        source: &str,
+        root: &IRewardsCoordinatorTypes::DistributionRoot,
+    ) -> Result<Self, RewardsError> {
+        let snapshot = DateTime::from_timestamp(i64::from(root.rewardsCalculationEndTimestamp), 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d")
+            .to_string();
+        let source = source
+            .replace("{snapshot}", &snapshot)
+            .replace("{root}", &root.root.to_string());
+        let text = match Url::parse(&source) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {
+                let fetch_error = |source| RewardsError::Fetch {
+                    url: url.to_string(),
+                    source,
+                };
+                reqwest::Client::builder()
+                    .timeout(FETCH_TIMEOUT)
+                    .build()
+                    .map_err(fetch_error)?
+                    .get(url.clone())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(fetch_error)?
+                    .text()
+                    .await
+                    .map_err(fetch_error)?
+            }
+            _ => std::fs::read_to_string(&source).map_err(|source_error| RewardsError::Read {
+                path: Path::new(&source).to_path_buf(),
+                source: source_error,
+            })?,
+        };
+        Self::parse(&text)
+    }
+
+    /// Record that `earner` earned `amount` of `token` in total
+    pub fn insert(&mut self, earner: Address, token: Address, amount: U256) {
+        self.earners
+            .entry(earner)
+            .or_default()
+            .insert(token, amount);
+    }
+
+    /// Cumulative earnings of `earner` per token
+    pub fn earnings(&self, earner: Address) -> Option<&BTreeMap<Address, U256>> {
+        self.earners.get(&earner)
+    }
+
+    /// Root of the earner tree, to compare with the posted root
+    pub fn root(&self) -> B256 {
+        merkle_root(&self.earner_leaves())
+    }
+
+    /// Claim of the tokens `earner` earned more of than `claimed` against the root at
+    /// `root_index`, none if there's nothing left to claim
+    pub fn claim(
+        &self,
+        root_index: u32,
+        earner: Address,
+        claimed: &BTreeMap<Address, U256>,
+    ) -> Option<IRewardsCoordinatorTypes::RewardsMerkleClaim> {
+        let earner_index = self.earners.keys().position(|known| *known == earner)?;
+        let tokens = &self.earners[&earner];
+        let token_leaves: Vec<_> = tokens
+            .iter()
+            .map(|(token, amount)| token_leaf_hash(*token, *amount))
+            .collect();
+        let mut claim = IRewardsCoordinatorTypes::RewardsMerkleClaim {
+            rootIndex: root_index,
+            earnerIndex: u32::try_from(earner_index).ok()?,
+            earnerTreeProof: merkle_proof(&self.earner_leaves(), earner_index),
+            earnerLeaf: IRewardsCoordinatorTypes::EarnerTreeMerkleLeaf {
+                earner,
+                earnerTokenRoot: merkle_root(&token_leaves),
+            },
+            ..Default::default()
+        };
+        for (index, (token, amount)) in tokens.iter().enumerate() {
+            // The coordinator reverts on tokens with nothing new to claim
+            if *amount <= claimed.get(token).copied().unwrap_or_default() {
+                continue;
+            }
+            claim.tokenIndices.push(u32::try_from(index).ok()?);
+            claim
+                .tokenTreeProofs
+                .push(merkle_proof(&token_leaves, index));
+            claim
+                .tokenLeaves
+                .push(IRewardsCoordinatorTypes::TokenTreeMerkleLeaf {
+                    token: *token,
+                    cumulativeEarnings: *amount,
+                });
+        }
+        (!claim.tokenLeaves.is_empty()).then_some(claim)
+    }
+
+    fn earner_leaves(&self) -> Vec<B256> {
+        self.earners
+            .iter()
+            .map(|(earner, tokens)| {
+                let token_leaves: Vec<_> = tokens
+                    .iter()
+                    .map(|(token, amount)| token_leaf_hash(*token, *amount))
+                    .collect();
+                earner_leaf_hash(*earner, merkle_root(&token_leaves))
+            })
+            .collect()
+    }
+}
+
+/// `calculateEarnerLeafHash` of the rewards coordinator
+pub fn earner_leaf_hash(earner: Address, earner_token_root: B256) -> B256 {
+    keccak256(
+        [
+            &[EARNER_LEAF_SALT][..],
+            earner.as_slice(),
+            earner_token_root.as_slice(),
+        ]
+        .concat(),
+    )
+}
+
+/// `calculateTokenLeafHash` of the rewards coordinator
+pub fn token_leaf_hash(token: Address, cumulative_earnings: U256) -> B256 {
+    keccak256(
+        [
+            &[TOKEN_LEAF_SALT][..],
+            token.as_slice(),
+            &cumulative_earnings.to_be_bytes::<32>()[..],
+        ]
+        .concat(),
+    )
+}
+
+/// Leaves padded with zero leaves to a power of 2, at least 2 so that every proof has a
+/// sibling as the coordinator requires
+fn padded(leaves: &[B256]) -> Vec<B256> {
+    let mut padded = leaves.to_vec();
+    padded.resize(leaves.len().next_power_of_two().max(2), B256::ZERO);
+    padded
+}
+
+fn parents(level: &[B256]) -> Vec<B256> {
+    level
+        .chunks(2)
+        .map(|pair| keccak256([pair[0].as_slice(), pair[1].as_slice()].concat()))
+        .collect()
+}
+
+/// Root of the keccak256 tree over `leaves`, built like the distribution roots
+pub fn merkle_root(leaves: &[B256]) -> B256 {
+    let mut level = padded(leaves);
+    while level.len() > 1 {
+        level = parents(&level);
+    }
+    level[0]
+}
+
+/// Siblings from the leaf at `index` up to the root, concatenated as `Merkle` verifies them
+pub fn merkle_proof(leaves: &[B256], mut index: usize) -> Bytes {
+    let mut level = padded(leaves);
+    let mut proof = Vec::new();
+    while level.len() > 1 {
+        proof.extend_from_slice(level[index ^ 1].as_slice());
+        level = parents(&level);
+        index /= 2;
+    }
+    proof.into()
+}
+
+/// Operator directed rewards the AVS `avs` submitted for the operator in `token`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AvsToken {
+    /// Service manager that submitted the rewards
+    pub avs: Address,
+    /// Token the rewards are paid in
+    pub token: Address,
+}
+
+/// Rewards of a token: earned in the claimable root and claimed so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenRewards {
+    /// Cumulative earnings in the claimable root
+    pub earned: U256,
+    /// Cumulative amount claimed
+    pub claimed: U256,
+}
+
+impl TokenRewards {
+    /// Earned and not claimed yet
+    pub fn claimable(&self) -> U256 {
+        self.earned.saturating_sub(self.claimed)
+    }
+}
+
+/// Rewards of the operator, as exported by [`RewardsTracker::check`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewardsSummary {
+    /// Operator directed rewards submitted for the operator per AVS and token
+    pub accrued: BTreeMap<AvsToken, U256>,
+    /// Earned and claimed amounts per token, empty unless `rewards.distribution` is set
+    pub tokens: BTreeMap<Address, TokenRewards>,
+}
+
+/// Reads the rewards of the operator from the rewards coordinator at regular intervals.
+///
+/// The AVSs pay operator directed rewards on chain, these are summed per AVS. Rewards by
+/// stake are only known once the off-chain calculation posts a root, so they show up in
+/// the earned amounts per token, which cover every AVS together
+#[derive(Debug)]
+pub struct RewardsTracker {
+    provider: SdkProvider,
+    rewards_coordinator: Address,
+    earner: Address,
+    config: RewardsConfig,
+    retry: RetryConfig,
+    state: ScanState,
+    services: Services,
+}
+
+/// Progress of the submissions scan, the contents of `rewards.state_file`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct ScanState {
+    rewards_coordinator: Address,
+    earner: Address,
+    /// First block not scanned yet
+    next_block: u64,
+    /// Rewards submitted for the earner up to `next_block`
+    accrued: Vec<AccruedRewards>,
+}
+
+/// Rewards an AVS submitted in a token, a line of [`ScanState`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct AccruedRewards {
+    avs: Address,
+    token: Address,
+    amount: U256,
+}
+
+impl ScanState {
+    /// State saved at `path` for `earner` in `rewards_coordinator`, if any
+    fn load(
+        path: &Path,
+        rewards_coordinator: Address,
+        earner: Address,
+    ) -> Result<Option<Self>, ConfigError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(ConfigError::Read {
+                    what: "rewards state file",
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        let state: Self = serde_json::from_str(&text).map_err(|e| {
+            ConfigError::Invalid(format!(
+                "invalid rewards state file {}: {e}",
+                path.display()
+            ))
+        })?;
+        if (state.rewards_coordinator, state.earner) != (rewards_coordinator, earner) {
+            return Err(ConfigError::Invalid(format!(
+                "rewards state file {} tracks {} in {}, not {earner} in {rewards_coordinator}",
+                path.display(),
+                state.earner,
+                state.rewards_coordinator
+            )));
+        }
+        Ok(Some(state))
+    }
+
+    fn accrued(&self) -> BTreeMap<AvsToken, U256> {
+        self.accrued
+            .iter()
+            .map(|rewards| {
+                (
+                    AvsToken {
+                        avs: rewards.avs,
+                        token: rewards.token,
+                    },
+                    rewards.amount,
+                )
+            })
+            .collect()
+    }
+}
+
+impl RewardsTracker {
+    /// Track the rewards of `earner` in the rewards coordinator at `rewards_coordinator`,
+    /// read through `rpc_url` and the RPC stack of `services`, scanning the submissions
+    /// from `from_block` unless `rewards.state_file` saved a later block
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        rewards_coordinator: Address,
+        earner: Address,
+        from_block: u64,
+        config: &RewardsConfig,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, ConfigError> {
+        let saved = match &config.state_file {
+            Some(path) => ScanState::load(path, rewards_coordinator, earner)?,
+            None => None,
+        };
+        if let Some(saved) = &saved {
+            info!(
+                next_block = saved.next_block,
+                "Resuming the rewards submissions scan"
+            );
+        }
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            rewards_coordinator,
+            earner,
+            config: config.clone(),
+            retry: retry_config.clone(),
+            state: saved.unwrap_or(ScanState {
+                rewards_coordinator,
+                earner,
+                next_block: from_block,
+                accrued: Vec::new(),
+            }),
+            services: services.clone(),
+        })
+    }
+
+    /// Check the rewards every poll interval until `shutdown` is triggered
+    pub async fn run(mut self, shutdown: Shutdown) {
+        info!(
+            earner = %self.earner,
+            rewards_coordinator = %self.services.named(self.rewards_coordinator),
+            "Tracking the operator rewards"
+        );
+        while !shutdown.is_triggered() {
+            if let Err(e) = self.check().await {
+                warn!(error = %e, "Rewards check failed");
+            }
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(Duration::from_secs(self.config.poll_interval_secs)) => {}
+            }
+        }
+    }
+
+    /// Read the rewards accrued since the last check and the claimable root, and export
+    /// them as gauges
+    pub async fn check(&mut self) -> Result<RewardsSummary, RewardsError> {
+        self.scan_submissions().await?;
+        let accrued = self.state.accrued();
+        for (key, amount) in &accrued {
+            set_rewards_accrued(key.avs, key.token, f64::from(*amount));
+        }
+        let mut tokens = BTreeMap::new();
+        if let Some(source) = &self.config.distribution {
+            let coordinator = IRewardsCoordinator::new(self.rewards_coordinator, &self.provider);
+            let root = coordinator.getCurrentClaimableDistributionRoot();
+            let root = retry(&self.retry, "getCurrentClaimableDistributionRoot", || {
+                time_rpc(
+                    "getCurrentClaimableDistributionRoot",
+                    root.call().into_future(),
+                )
+            })
+            .await
+            .map_err(ChainError::from)?
+            ._0;
+            if root.root.is_zero() {
+                return Err(RewardsError::NoRoot);
+            }
+            let distribution = Distribution::load(source, &root).await?;
+            check_root(&distribution, root.root)?;
+            let earned = distribution
+                .earnings(self.earner)
+                .cloned()
+                .unwrap_or_default();
+            let claimed = cumulative_claimed(
+                &self.provider,
+                self.rewards_coordinator,
+                self.earner,
+                earned.keys().copied(),
+                &self.retry,
+            )
+            .await?;
+            for (token, earned) in earned {
+                let rewards = TokenRewards {
+                    earned,
+                    claimed: claimed.get(&token).copied().unwrap_or_default(),
+                };
+                set_token_rewards(
+                    token,
+                    f64::from(rewards.earned),
+                    f64::from(rewards.claimed),
+                    f64::from(rewards.claimable()),
+                );
+                tokens.insert(token, rewards);
+            }
+        }
+        Ok(RewardsSummary { accrued, tokens })
+    }
+
+    /// Add up the operator directed rewards submitted for the earner since the last scan,
+    /// saving the progress after every range
+    async fn scan_submissions(&mut self) -> Result<(), RewardsError> {
+        let provider = &self.provider;
+        let head = retry(&self.retry, "eth_blockNumber", || {
+            time_rpc("eth_blockNumber", provider.get_block_number())
+        })
+        .await?;
+        if head < self.state.next_block {
+            return Ok(());
+        }
+        for (from_block, to_block) in block_ranges(self.state.next_block, head) {
+            let filter = Filter::new()
+                .address(self.rewards_coordinator)
+                .event_signature(
+                    IRewardsCoordinator::OperatorDirectedAVSRewardsSubmissionCreated::SIGNATURE_HASH,
+                )
+                .from_block(BlockNumberOrTag::Number(from_block))
+                .to_block(BlockNumberOrTag::Number(to_block));
+            let logs = retry(&self.retry, "eth_getLogs", || {
+                time_rpc("eth_getLogs", provider.get_logs(&filter))
+            })
+            .await?;
+            let mut accrued = self.state.accrued();
+            for log in logs {
+                let event = log
+                    .log_decode::<IRewardsCoordinator::OperatorDirectedAVSRewardsSubmissionCreated>(
+                    )
+                    .map_err(ChainError::from)?
+                    .inner
+                    .data;
+                let submission = event.operatorDirectedRewardsSubmission;
+                for reward in submission.operatorRewards {
+                    if reward.operator != self.earner {
+                        continue;
+                    }
+                    *accrued
+                        .entry(AvsToken {
+                            avs: event.avs,
+                            token: submission.token,
+                        })
+                        .or_default() += reward.amount;
+                }
+            }
+            self.state.accrued = accrued
+                .into_iter()
+                .map(|(key, amount)| AccruedRewards {
+                    avs: key.avs,
+                    token: key.token,
+                    amount,
+                })
+                .collect();
+            self.state.next_block = to_block + 1;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Write the scan progress to `rewards.state_file`, if set
+    fn save(&self) -> Result<(), RewardsError> {
+        let Some(path) = &self.config.state_file else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.state).unwrap_or_default();
+        write_atomically(path, &json).map_err(|source| RewardsError::State {
+            path: path.clone(),
+            source,
+        })
+    }
+}
+
+/// Fail unless `distribution` is the one behind the posted root `posted`
+fn check_root(distribution: &Distribution, posted: B256) -> Result<(), RewardsError> {
+    let computed = distribution.root();
+    if computed != posted {
+        return Err(RewardsError::RootMismatch { computed, posted });
+    }
+    Ok(())
+}
+
+/// Amounts of `tokens` `earner` claimed so far
+async fn cumulative_claimed<P: Provider>(
+    provider: &P,
+    rewards_coordinator: Address,
+    earner: Address,
+    tokens: impl IntoIterator<Item = Address>,
+    retry_config: &RetryConfig,
+) -> Result<BTreeMap<Address, U256>, ChainError> {
+    let coordinator = IRewardsCoordinator::new(rewards_coordinator, provider);
+    let mut claimed = BTreeMap::new();
+    for token in tokens {
+        let call = coordinator.cumulativeClaimed(earner, token);
+        let amount = retry(retry_config, "cumulativeClaimed", || {
+            time_rpc("cumulativeClaimed", call.call().into_future())
+        })
+        .await?
+        ._0;
+        claimed.insert(token, amount);
+    }
+    Ok(claimed)
+}
+
+/// Claim what the operator earned in the claimable root and didn't claim yet, sending the
+/// tokens to `recipient`, else `rewards.recipient`, else the operator.
+///
+/// The proofs are built from the distribution at `rewards.distribution`, checked against
+/// the posted root and by `checkClaim` before `processClaim` is sent, unless `dry_run`.
+/// Returns the hash of the claim transaction
+pub async fn claim_rewards(
+    config: &OperatorConfig,
+    services: &Services,
+    signer: &OperatorSigner,
+    rewards_coordinator: Address,
+    recipient: Option<Address>,
+    dry_run: bool,
+) -> Result<Option<B256>, RewardsError> {
+    let source = config.rewards.distribution.as_deref().ok_or_else(|| {
+        ConfigError::Invalid("rewards.distribution must be set to claim rewards".to_string())
+    })?;
+    let earner = config.operator_address(signer.address());
+    let recipient = match (recipient, &config.rewards.recipient) {
+        (Some(recipient), _) => recipient,
+        (None, Some(configured)) => configured.address(services.names())?,
+        (None, None) => earner,
+    };
+    let pr = signer
+        .clone()
+        .services(services.clone())
+        .provider(&config.rpc_url)?;
+    let coordinator = IRewardsCoordinator::new(rewards_coordinator, &pr);
+    if signer.address() != earner {
+        let claimer = coordinator.claimerFor(earner).call().await?._0;
+        if claimer != signer.address() {
+            return Err(RewardsError::NotClaimer {
+                signer: signer.address(),
+                earner,
+                claimer,
+            });
+        }
+    }
+    let root = coordinator
+        .getCurrentClaimableDistributionRoot()
+        .call()
+        .await?
+        ._0;
+    if root.root.is_zero() {
+        return Err(RewardsError::NoRoot);
+    }
+    let root_index = coordinator.getRootIndexFromHash(root.root).call().await?._0;
+    let distribution = Distribution::load(source, &root).await?;
+    check_root(&distribution, root.root)?;
+    let earned = distribution.earnings(earner).cloned().unwrap_or_default();
+    let claimed = cumulative_claimed(
+        &pr,
+        rewards_coordinator,
+        earner,
+        earned.keys().copied(),
+        &config.retry,
+    )
+    .await?;
+    let claim = distribution
+        .claim(root_index, earner, &claimed)
+        .ok_or(RewardsError::NothingToClaim { earner })?;
+    // Reverts with the reason if the coordinator would refuse the claim
+    coordinator.checkClaim(claim.clone()).call().await?;
+    for leaf in &claim.tokenLeaves {
+        let claimed = claimed.get(&leaf.token).copied().unwrap_or_default();
+        info!(
+            token = %services.named(leaf.token),
+            amount = %(leaf.cumulativeEarnings - claimed),
+            "Claimable rewards"
+        );
+    }
+    if dry_run {
+        info!(%earner, root = %root.root, root_index, "Claim checked, not sent");
+        return Ok(None);
+    }
+    let process = coordinator.processClaim(claim, recipient);
+    let gas = estimate_call_gas(services.gas_limit_multiplier(), "processClaim", &process).await?;
+    let pending = process.gas(gas).send().await?;
+    services.audit_transaction(signer.address(), "processClaim", None, *pending.tx_hash());
+    let receipt = pending.get_receipt().await?;
+    let tx_hash = receipt.transaction_hash;
+    if !receipt.status() {
+        return Err(ChainError::Reverted {
+            method: "processClaim",
+            tx_hash,
+        }
+        .into());
+    }
+    info!(%tx_hash, %earner, %recipient, root_index, "Rewards claimed");
+    Ok(Some(tx_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::LogData, rpc::types::Log};
+    use serde_json::json;
+
+    use super::*;
+    use crate::mockrpc::{MockReply, MockRpc};
+
+    /// `Merkle.verifyInclusionKeccak` of EigenLayer
+    fn verify(proof: &[u8], root: B256, leaf: B256, mut index: usize) -> bool {
+        let mut computed = leaf;
+        for sibling in proof.chunks(32) {
+            computed = if index % 2 == 0 {
+                keccak256([computed.as_slice(), sibling].concat())
+            } else {
+                keccak256([sibling, computed.as_slice()].concat())
+            };
+            index /= 2;
+        }
+        !proof.is_empty() && computed == root
+    }
+
+    #[test]
+    fn test_claims_prove_the_unclaimed_tokens_against_the_root() {
+        let operator = Address::with_last_byte(2);
+        let (token_a, token_b) = (Address::with_last_byte(0xa), Address::with_last_byte(0xb));
+        let lines = [
+            format!(
+                r#"{{"earner": "{}", "token": "{token_a}", "cumulative_amount": "500", "snapshot": 1}}"#,
+                Address::with_last_byte(1)
+            ),
+            String::new(),
+            format!(
+                r#"{{"earner": "{operator}", "token": "{token_a}", "cumulative_amount": "300"}}"#
+            ),
+            format!(
+                r#"{{"earner": "{operator}", "token": "{token_b}", "cumulative_amount": "0x64"}}"#
+            ),
+            format!(
+                r#"{{"earner": "{}", "token": "{token_b}", "cumulative_amount": "7"}}"#,
+                Address::with_last_byte(3)
+            ),
+        ];
+        let distribution = Distribution::parse(&lines.join("\n")).unwrap();
+        let root = distribution.root();
+
+        let claimed = BTreeMap::from([(token_a, U256::from(300))]);
+        let claim = distribution.claim(4, operator, &claimed).unwrap();
+        assert_eq!(claim.rootIndex, 4);
+        assert_eq!(claim.earnerIndex, 1);
+        assert_eq!(claim.tokenIndices, vec![1]);
+        assert_eq!(claim.tokenLeaves[0].token, token_b);
+        assert_eq!(claim.tokenLeaves[0].cumulativeEarnings, U256::from(100));
+        assert!(verify(
+            &claim.earnerTreeProof,
+            root,
+            earner_leaf_hash(operator, claim.earnerLeaf.earnerTokenRoot),
+            1,
+        ));
+        assert!(verify(
+            &claim.tokenTreeProofs[0],
+            claim.earnerLeaf.earnerTokenRoot,
+            token_leaf_hash(token_b, U256::from(100)),
+            1,
+        ));
+        assert!(distribution
+            .claim(
+                4,
+                operator,
+                &BTreeMap::from([(token_a, U256::from(300)), (token_b, U256::from(100))])
+            )
+            .is_none());
+        assert!(distribution.claim(4, Address::ZERO, &claimed).is_none());
+        assert!(matches!(
+            check_root(&distribution, B256::ZERO),
+            Err(RewardsError::RootMismatch { .. })
+        ));
+        assert!(matches!(
+            Distribution::parse("{\"earner\": \"0x01\"}"),
+            Err(RewardsError::Parse { line: 1, .. })
+        ));
+
+        // A lone leaf is paired with a zero leaf, like the roots of the distribution script
+        let leaf = B256::repeat_byte(1);
+        assert_eq!(
+            merkle_root(&[leaf]),
+            keccak256([leaf.as_slice(), B256::ZERO.as_slice()].concat())
+        );
+        assert_eq!(merkle_proof(&[leaf], 0).len(), 32);
+    }
+
+    fn submission_log(coordinator: Address, avs: Address, rewards: &[(Address, u64)]) -> Log {
+        let event = IRewardsCoordinator::OperatorDirectedAVSRewardsSubmissionCreated {
+            caller: avs,
+            avs,
+            operatorDirectedRewardsSubmissionHash: B256::ZERO,
+            submissionNonce: U256::ZERO,
+            operatorDirectedRewardsSubmission:
+                IRewardsCoordinatorTypes::OperatorDirectedRewardsSubmission {
+                    token: Address::with_last_byte(0xa),
+                    operatorRewards: rewards
+                        .iter()
+                        .map(
+                            |&(operator, amount)| IRewardsCoordinatorTypes::OperatorReward {
+                                operator,
+                                amount: U256::from(amount),
+                            },
+                        )
+                        .collect(),
+                    ..Default::default()
+                },
+        };
+        let data: LogData = event.encode_log_data();
+        Log {
+            inner: alloy::primitives::Log {
+                address: coordinator,
+                data,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_resumes_from_the_state_file() {
+        let dir = std::env::temp_dir().join(format!("rewards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = RewardsConfig {
+            state_file: Some(dir.join("rewards-state.json")),
+            ..RewardsConfig::default()
+        };
+        let coordinator = Address::repeat_byte(0x11);
+        let (operator, avs) = (Address::with_last_byte(2), Address::with_last_byte(3));
+        let key = AvsToken {
+            avs,
+            token: Address::with_last_byte(0xa),
+        };
+        let mock = MockRpc::start().await.unwrap();
+        mock.reply("eth_blockNumber", MockReply::Result(json!("0x10")));
+        mock.reply_once(
+            "eth_getLogs",
+            MockReply::Result(json!([submission_log(
+                coordinator,
+                avs,
+                &[(Address::with_last_byte(1), 100), (operator, 5)]
+            )])),
+        );
+        let new_tracker = || {
+            RewardsTracker::new(
+                mock.url(),
+                &Services::default(),
+                coordinator,
+                operator,
+                3,
+                &config,
+                &RetryConfig::default(),
+            )
+        };
+        let summary = new_tracker().unwrap().check().await.unwrap();
+        assert_eq!(summary.accrued, BTreeMap::from([(key, U256::from(5))]));
+
+        // A restart scans the new blocks only and adds to the saved sums
+        mock.reply("eth_blockNumber", MockReply::Result(json!("0x20")));
+        mock.reply_once(
+            "eth_getLogs",
+            MockReply::Result(json!([submission_log(coordinator, avs, &[(operator, 7)])])),
+        );
+        let summary = new_tracker().unwrap().check().await.unwrap();
+        assert_eq!(summary.accrued, BTreeMap::from([(key, U256::from(12))]));
+        let from_blocks: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.method == "eth_getLogs")
+            .map(|call| call.params[0]["fromBlock"].clone())
+            .collect();
+        assert_eq!(from_blocks, [json!("0x3"), json!("0x11")]);
+
+        // The saved sums belong to another earner
+        assert!(RewardsTracker::new(
+            mock.url(),
+            &Services::default(),
+            coordinator,
+            Address::with_last_byte(1),
+            3,
+            &config,
+            &RetryConfig::default(),
+        )
+        .is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,511 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    rpc::json_rpc::{Id, RequestPacket, Response, ResponsePacket, SerializedRequest},
+    transports::{TransportError, TransportErrorKind, TransportFut, TransportResult},
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower::Service;
+use tracing::{info, warn};
+
+use crate::error::CaptureError;
+
+/// Stand-in for the params of the methods carrying secrets and for the redacted URL parts
+const REDACTED: &str = "<redacted>";
+
+/// Methods whose params may hold passwords or keys, such as `personal_unlockAccount`
+const SECRET_METHOD_PREFIXES: [&str; 2] = ["personal_", "eth_sign"];
+
+/// What is done with the JSON-RPC traffic
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// Calls go to the endpoints untouched
+    #[default]
+    Off,
+    /// Every call and its response is appended to `path`
+    Record,
+    /// Calls are answered from the recording at `path`, no endpoint is contacted
+    Replay,
+}
+
+impl std::str::FromStr for CaptureMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "off" => Ok(Self::Off),
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            other => Err(format!(
+                "unknown mode {other}, expected off, record or replay"
+            )),
+        }
+    }
+}
+
+/// Recording and replay of the JSON-RPC traffic, the `[rpc_capture]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcCaptureConfig {
+    /// Whether the calls are recorded, replayed or left alone (`RPC_CAPTURE_MODE`)
+    pub mode: CaptureMode,
+    /// JSON lines file of the recorded calls (`RPC_CAPTURE_PATH`)
+    pub path: PathBuf,
+    /// Whether replayed responses wait as long as the recorded ones took
+    pub replay_latency: bool,
+}
+
+impl Default for RpcCaptureConfig {
+    fn default() -> Self {
+        Self {
+            mode: CaptureMode::Off,
+            path: PathBuf::from("rpc.capture.jsonl"),
+            replay_latency: false,
+        }
+    }
+}
+
+/// A call and its outcome, one line of the capture file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CapturedCall {
+    /// Milliseconds since the recording started
+    pub at_ms: u64,
+    /// Endpoint called, its credentials, path and query redacted as they may hold API keys
+    pub endpoint: String,
+    /// JSON-RPC method
+    pub method: String,
+    /// Params of the call, redacted for the methods that may carry secrets
+    pub params: Value,
+    /// Milliseconds the endpoint took to answer
+    pub elapsed_ms: u64,
+    /// JSON-RPC response object, unset when the transport failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    /// Transport error of the call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Params of `request` as recorded, redacted for the methods that may carry secrets
+fn recorded_params(request: &SerializedRequest) -> Value {
+    if SECRET_METHOD_PREFIXES
+        .iter()
+        .any(|prefix| request.method().starts_with(prefix))
+    {
+        return Value::String(REDACTED.to_string());
+    }
+    request
+        .params()
+        .and_then(|params| serde_json::from_str(params.get()).ok())
+        .unwrap_or(Value::Null)
+}
+
+/// `url` without the credentials, path and query API keys are usually passed in
+pub fn redact_url(url: &Url) -> String {
+    let mut redacted = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        redacted.push_str(&format!(":{port}"));
+    }
+    if url.path() != "/" || url.query().is_some() {
+        redacted.push('/');
+        redacted.push_str(REDACTED);
+    }
+    redacted
+}
+
+/// Appends the calls to the capture file
+#[derive(Debug)]
+struct Recorder {
+    path: PathBuf,
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    fn open(path: &Path) -> Result<Self, CaptureError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|source| CaptureError::Write {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(
+        &self,
+        endpoint: &str,
+        request: &RequestPacket,
+        result: &TransportResult<ResponsePacket>,
+        sent: Instant,
+    ) {
+        let requests = match request {
+            RequestPacket::Single(request) => std::slice::from_ref(request),
+            RequestPacket::Batch(requests) => requests.as_slice(),
+        };
+        let mut lines = String::new();
+        for request in requests {
+            let (response, error) = match result {
+                Ok(packet) => (
+                    response_for(packet, request.id())
+                        .and_then(|response| serde_json::to_value(response).ok()),
+                    None,
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            let call = CapturedCall {
+                at_ms: sent.duration_since(self.started).as_millis() as u64,
+                endpoint: endpoint.to_string(),
+                method: request.method().to_string(),
+                params: recorded_params(request),
+                elapsed_ms: sent.elapsed().as_millis() as u64,
+                response,
+                error,
+            };
+            match serde_json::to_string(&call) {
+                Ok(line) => {
+                    lines.push_str(&line);
+                    lines.push('\n');
+                }
+                Err(e) => warn!(error = %e, method = call.method, "Failed to record an RPC call"),
+            }
+        }
+        let mut file = self.file.lock().expect("rpc capture lock poisoned");
+        if let Err(e) = file.write_all(lines.as_bytes()) {
+            warn!(error = %e, path = %self.path.display(), "Failed to record RPC calls");
+        }
+    }
+}
+
+/// Response to the request `id` within `packet`
+fn response_for<'a>(packet: &'a ResponsePacket, id: &Id) -> Option<&'a Response> {
+    match packet {
+        ResponsePacket::Single(response) => Some(response),
+        ResponsePacket::Batch(responses) => responses.iter().find(|response| response.id == *id),
+    }
+}
+
+/// Answers the calls from a capture file.
+///
+/// A call gets the next unused recording of the same method and params, or failing that of
+/// the same method, so a replay whose params drift (new block numbers, hashes) still runs.
+/// Once every matching recording was used, the last one is answered again
+#[derive(Debug)]
+pub struct Replay {
+    calls: Vec<CapturedCall>,
+    state: Mutex<ReplayState>,
+    latency: bool,
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    by_params: HashMap<(String, String), VecDeque<usize>>,
+    by_method: HashMap<String, VecDeque<usize>>,
+    used: Vec<bool>,
+    last: HashMap<String, usize>,
+}
+
+impl Replay {
+    /// Replay of `calls` in their recorded order
+    pub fn new(calls: Vec<CapturedCall>, latency: bool) -> Self {
+        let mut state = ReplayState {
+            used: vec![false; calls.len()],
+            ..Default::default()
+        };
+        for (index, call) in calls.iter().enumerate() {
+            state
+                .by_params
+                .entry((call.method.clone(), call.params.to_string()))
+                .or_default()
+                .push_back(index);
+            state
+                .by_method
+                .entry(call.method.clone())
+                .or_default()
+                .push_back(index);
+        }
+        Self {
+            calls,
+            state: Mutex::new(state),
+            latency,
+        }
+    }
+
+    /// Replay of the capture file at `path`
+    pub fn load(path: &Path, latency: bool) -> Result<Self, CaptureError> {
+        let contents = fs::read_to_string(path).map_err(|source| CaptureError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let calls = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| CaptureError::Broken {
+                    path: path.to_path_buf(),
+                    line: index + 1,
+                    reason: e.to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(calls, latency))
+    }
+
+    /// Recording answering `request`
+    fn next_call(&self, request: &SerializedRequest) -> Option<&CapturedCall> {
+        let mut state = self.state.lock().expect("rpc replay lock poisoned");
+        let method = request.method().to_string();
+        let key = (method.clone(), recorded_params(request).to_string());
+        let ReplayState {
+            by_params,
+            by_method,
+            used,
+            last,
+        } = &mut *state;
+        let mut take = |queue: Option<&mut VecDeque<usize>>| {
+            let queue = queue?;
+            while let Some(index) = queue.pop_front() {
+                if !std::mem::replace(&mut used[index], true) {
+                    return Some(index);
+                }
+            }
+            None
+        };
+        let index = take(by_params.get_mut(&key))
+            .or_else(|| take(by_method.get_mut(&method)))
+            .or_else(|| last.get(&method).copied())?;
+        last.insert(method, index);
+        Some(&self.calls[index])
+    }
+
+    /// Recorded response to `request`, its id set to the one of `request`
+    fn respond(&self, request: &SerializedRequest) -> TransportResult<(Response, Duration)> {
+        let call = self.next_call(request).ok_or_else(|| {
+            TransportErrorKind::custom_str(&format!("no recorded response to {}", request.method()))
+        })?;
+        let latency = Duration::from_millis(if self.latency { call.elapsed_ms } else { 0 });
+        if let Some(error) = &call.error {
+            return Err(TransportErrorKind::custom_str(error));
+        }
+        let response = call.response.as_ref().ok_or_else(|| {
+            TransportErrorKind::custom_str(&format!(
+                "recorded {} call has no response",
+                call.method
+            ))
+        })?;
+        // Raw JSON payloads only deserialize from text
+        let mut response: Response = serde_json::from_str(&response.to_string())
+            .map_err(|e| TransportErrorKind::custom_str(&format!("bad recorded response: {e}")))?;
+        response.id = request.id().clone();
+        Ok((response, latency))
+    }
+
+    /// Recorded responses to `request`
+    pub async fn replay(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let (packet, latency) = match request {
+            RequestPacket::Single(request) => {
+                let (response, latency) = self.respond(&request)?;
+                (ResponsePacket::Single(response), latency)
+            }
+            RequestPacket::Batch(requests) => {
+                let mut responses = Vec::with_capacity(requests.len());
+                let mut latency = Duration::ZERO;
+                for request in &requests {
+                    let (response, elapsed) = self.respond(request)?;
+                    responses.push(response);
+                    latency = latency.max(elapsed);
+                }
+                (ResponsePacket::Batch(responses), latency)
+            }
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        Ok(packet)
+    }
+}
+
+/// Recording or replay of the JSON-RPC traffic, shared by the clients capturing to the
+/// same file
+#[derive(Debug)]
+pub struct RpcCapture(Capturing);
+
+#[derive(Debug)]
+enum Capturing {
+    Record(Recorder),
+    Replay(Replay),
+}
+
+impl RpcCapture {
+    /// Record or replay the traffic as set in `config`, none when the mode is `off`
+    pub fn open(config: &RpcCaptureConfig) -> Result<Option<Self>, CaptureError> {
+        let capturing = match config.mode {
+            CaptureMode::Off => return Ok(None),
+            CaptureMode::Record => {
+                info!(path = %config.path.display(), "Recording the JSON-RPC traffic");
+                Capturing::Record(Recorder::open(&config.path)?)
+            }
+            CaptureMode::Replay => {
+                let replay = Replay::load(&config.path, config.replay_latency)?;
+                warn!(
+                    path = %config.path.display(),
+                    calls = replay.calls.len(),
+                    "Answering the JSON-RPC calls from a recording, no endpoint is contacted"
+                );
+                Capturing::Replay(replay)
+            }
+        };
+        Ok(Some(Self(capturing)))
+    }
+}
+
+/// Transport recording the calls of a provider to the capture file, or answering them from
+/// it in replay mode
+#[derive(Debug, Clone)]
+pub struct CaptureTransport<S> {
+    inner: S,
+    endpoint: String,
+    capture: Option<Arc<RpcCapture>>,
+}
+
+impl<S> CaptureTransport<S> {
+    /// Capture of the calls `inner` sends to `url` by `capture`, passed through without one
+    pub fn new(inner: S, url: &Url, capture: Option<Arc<RpcCapture>>) -> Self {
+        Self {
+            inner,
+            endpoint: redact_url(url),
+            capture,
+        }
+    }
+}
+
+impl<S> Service<RequestPacket> for CaptureTransport<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let Some(capture) = self.capture.clone() else {
+            return self.inner.call(request);
+        };
+        let mut inner = self.inner.clone();
+        let endpoint = self.endpoint.clone();
+        Box::pin(async move {
+            match &capture.0 {
+                Capturing::Replay(replay) => replay.replay(request).await,
+                Capturing::Record(recorder) => {
+                    let sent = Instant::now();
+                    let result = inner.call(request.clone()).await;
+                    recorder.record(&endpoint, &request, &result, sent);
+                    result
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::rpc::json_rpc::{Request, ResponsePayload};
+    use serde_json::json;
+
+    fn request(id: u64, method: &'static str, params: Value) -> SerializedRequest {
+        Request::new(method, Id::Number(id), params)
+            .serialize()
+            .unwrap()
+    }
+
+    fn result(packet: &ResponsePacket) -> String {
+        match packet {
+            ResponsePacket::Single(Response {
+                payload: ResponsePayload::Success(result),
+                ..
+            }) => result.get().to_string(),
+            other => panic!("unexpected response {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorded_calls_replay_with_the_ids_of_the_new_requests() {
+        let path = std::env::temp_dir().join(format!("rpc-capture-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let recorder = Recorder::open(&path).unwrap();
+        let endpoint = redact_url(&"https://eth.example.com/v2/secret-key".parse().unwrap());
+        assert_eq!(endpoint, "https://eth.example.com/<redacted>");
+        for (block, id) in [("0x10", 1), ("0x11", 2)] {
+            let request = RequestPacket::Single(request(id, "eth_blockNumber", json!([])));
+            let response: Response = serde_json::from_str(&format!(
+                r#"{{"jsonrpc":"2.0","id":{id},"result":"{block}"}}"#
+            ))
+            .unwrap();
+            recorder.record(
+                &endpoint,
+                &request,
+                &Ok(ResponsePacket::Single(response)),
+                Instant::now(),
+            );
+        }
+        let unlock = RequestPacket::Single(request(
+            3,
+            "personal_unlockAccount",
+            json!(["0x0000000000000000000000000000000000000001", "hunter2"]),
+        ));
+        recorder.record(
+            &endpoint,
+            &unlock,
+            &Err(TransportErrorKind::custom_str("connection reset")),
+            Instant::now(),
+        );
+        let recorded = fs::read_to_string(&path).unwrap();
+        assert!(!recorded.contains("hunter2") && !recorded.contains("secret-key"));
+
+        let replay = Replay::load(&path, false).unwrap();
+        fs::remove_file(&path).unwrap();
+        let next = |id| RequestPacket::Single(request(id, "eth_blockNumber", json!([])));
+        let first = replay.replay(next(40)).await.unwrap();
+        assert_eq!(result(&first), r#""0x10""#);
+        let ResponsePacket::Single(first) = first else {
+            unreachable!()
+        };
+        assert_eq!(first.id, Id::Number(40));
+        assert_eq!(result(&replay.replay(next(41)).await.unwrap()), r#""0x11""#);
+        // The last recording answers once they are used up
+        assert_eq!(result(&replay.replay(next(42)).await.unwrap()), r#""0x11""#);
+        assert!(replay.replay(unlock).await.is_err());
+        let unknown = RequestPacket::Single(request(5, "eth_chainId", json!([])));
+        assert!(replay.replay(unknown).await.is_err());
+    }
+}
@@ -0,0 +1,526 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::U64,
+    providers::ProviderBuilder,
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, ResponsePacket, ResponsePayload},
+    },
+    transports::{
+        http::Http, utils::guess_local_url, RpcError, TransportError, TransportErrorKind,
+        TransportFut, TransportResult,
+    },
+};
+use eigensdk::common::SdkProvider;
+use futures::future::join_all;
+use reqwest::Url;
+use serde::Deserialize;
+use tower::Service;
+use tracing::{debug, info, warn};
+
+use crate::circuit_breaker::{BreakerTransport, CircuitBreaker};
+use crate::config::OperatorConfig;
+use crate::error::{CaptureError, ConfigError};
+use crate::prometheus::{record_rpc_failover, record_rpc_quorum_failure, set_rpc_endpoint_health};
+use crate::rate_limit::{ThrottleTransport, TokenBucket};
+use crate::rpc_capture::{CaptureTransport, RpcCapture};
+use crate::shutdown::Shutdown;
+
+/// Endpoints of one network, an `[rpc.networks.<name>]` entry
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcNetworkConfig {
+    /// Endpoints tried in order when `rpc_url` fails
+    pub fallback_urls: Vec<String>,
+    /// Endpoints that must agree on a quorum read, `rpc.quorum` if unset
+    pub quorum: Option<usize>,
+    /// Private relay of the network, `rpc.private_relay_url` if unset
+    pub private_relay_url: Option<String>,
+}
+
+/// RPC endpoint failover, the `[rpc]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    /// Endpoints tried in order when `rpc_url` fails (`RPC_FALLBACK_URLS`, comma separated)
+    pub fallback_urls: Vec<String>,
+    /// Entry of `networks` used instead of `fallback_urls` and `quorum` (`RPC_NETWORK`)
+    pub network: Option<String>,
+    /// Endpoints of each network, keyed by a name such as `holesky`
+    pub networks: BTreeMap<String, RpcNetworkConfig>,
+    /// Seconds between two reads of the head of every endpoint
+    pub health_check_interval_secs: u64,
+    /// Blocks an endpoint may lag behind the highest head before it counts as unhealthy
+    pub max_block_lag: u64,
+    /// Endpoints that must return the same result for the calls in `quorum_methods`, 1
+    /// reads a single endpoint
+    pub quorum: usize,
+    /// Methods read from every endpoint and compared when `quorum` is above 1
+    pub quorum_methods: Vec<String>,
+    /// Flashbots Protect style RPC the responses are sent to instead of the public mempool,
+    /// keeping them from being front-run (`PRIVATE_RELAY_URL`)
+    pub private_relay_url: Option<String>,
+    /// Seconds a response sent to the private relay gets to be mined before it is sent to
+    /// the public mempool as well
+    pub private_fallback_secs: u64,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            fallback_urls: Vec::new(),
+            network: None,
+            networks: BTreeMap::new(),
+            health_check_interval_secs: 15,
+            max_block_lag: 5,
+            quorum: 1,
+            quorum_methods: vec!["eth_getLogs".to_string()],
+            private_relay_url: None,
+            private_fallback_secs: 120,
+        }
+    }
+}
+
+impl RpcConfig {
+    /// Entry of `networks` selected by `network`
+    pub fn selected_network(&self) -> Result<Option<&RpcNetworkConfig>, ConfigError> {
+        let Some(name) = &self.network else {
+            return Ok(None);
+        };
+        self.networks.get(name).map(Some).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "rpc.network {name} has no [rpc.networks.{name}] entry"
+            ))
+        })
+    }
+
+    /// `rpc_url` followed by the fallback endpoints of the selected network or of `[rpc]`
+    pub fn endpoints(&self, rpc_url: &str) -> Result<Vec<String>, ConfigError> {
+        let fallback_urls = match self.selected_network()? {
+            Some(network) => &network.fallback_urls,
+            None => &self.fallback_urls,
+        };
+        Ok(std::iter::once(rpc_url.to_string())
+            .chain(fallback_urls.iter().cloned())
+            .collect())
+    }
+
+    /// Quorum of the selected network, else of `[rpc]`
+    pub fn effective_quorum(&self) -> Result<usize, ConfigError> {
+        Ok(self
+            .selected_network()?
+            .and_then(|network| network.quorum)
+            .unwrap_or(self.quorum))
+    }
+
+    /// Private relay of the selected network, else of `[rpc]`
+    pub fn private_relay(&self) -> Result<Option<&str>, ConfigError> {
+        Ok(self
+            .selected_network()?
+            .and_then(|network| network.private_relay_url.as_deref())
+            .or(self.private_relay_url.as_deref()))
+    }
+
+    /// Time a response sent to the private relay gets before going to the public mempool
+    pub fn private_fallback(&self) -> Duration {
+        Duration::from_secs(self.private_fallback_secs)
+    }
+
+    /// Check that the endpoints of `rpc_url` can reach the quorum and be health checked
+    pub fn validate(&self, rpc_url: &str) -> Result<(), ConfigError> {
+        let endpoints = self.endpoints(rpc_url)?.len();
+        let quorum = self.effective_quorum()?;
+        if quorum == 0 || quorum > endpoints {
+            return Err(ConfigError::Invalid(format!(
+                "rpc.quorum must be between 1 and the {endpoints} configured endpoints, got {quorum}"
+            )));
+        }
+        if self.health_check_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "rpc.health_check_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(relay) = self.private_relay()? {
+            Url::parse(relay).map_err(|e| {
+                ConfigError::Invalid(format!("invalid private relay url {relay}: {e}"))
+            })?;
+            if self.private_fallback_secs == 0 {
+                return Err(ConfigError::Invalid(
+                    "rpc.private_fallback_secs must be greater than 0".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Host and port of `url`, naming the endpoint in logs and metrics without its API key
+fn endpoint_label(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        (None, _) => url.scheme().to_string(),
+    }
+}
+
+/// One endpoint of the pool and whether it is usable
+#[derive(Debug)]
+struct Endpoint {
+    label: String,
+    transport: Http<reqwest::Client>,
+    healthy: AtomicBool,
+}
+
+impl Endpoint {
+    async fn head(&self) -> TransportResult<u64> {
+        let client = RpcClient::new(self.transport.clone(), false);
+        let head: U64 = client.request_noparams("eth_blockNumber").await?;
+        Ok(head.to::<u64>())
+    }
+
+    fn set_healthy(&self, healthy: bool) {
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                info!(endpoint = %self.label, "RPC endpoint is healthy again");
+            } else {
+                warn!(endpoint = %self.label, "RPC endpoint is unhealthy");
+            }
+        }
+        set_rpc_endpoint_health(&self.label, healthy);
+    }
+}
+
+/// HTTP endpoints sharing the calls of a provider: each call goes to the first healthy one
+/// and fails over to the next on a transport error, the quorum methods are read from all
+/// of them and only returned once `quorum` endpoints agree
+#[derive(Debug)]
+pub struct RpcPool {
+    primary: Url,
+    endpoints: Vec<Endpoint>,
+    quorum: usize,
+    quorum_methods: Vec<String>,
+    health_check_interval: Duration,
+    max_block_lag: u64,
+}
+
+impl RpcPool {
+    /// Pool of `rpc_url` and its fallback endpoints in `config`
+    pub fn new(rpc_url: &str, config: &RpcConfig) -> Result<Self, ConfigError> {
+        let parse = |url: &str| {
+            Url::parse(url).map_err(|e| ConfigError::Invalid(format!("invalid rpc url {url}: {e}")))
+        };
+        let endpoints = config
+            .endpoints(rpc_url)?
+            .iter()
+            .map(|url| {
+                let url = parse(url)?;
+                Ok(Endpoint {
+                    label: endpoint_label(&url),
+                    transport: Http::new(url),
+                    healthy: AtomicBool::new(true),
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        Ok(Self {
+            primary: parse(rpc_url)?,
+            endpoints,
+            quorum: config.effective_quorum()?,
+            quorum_methods: config.quorum_methods.clone(),
+            health_check_interval: Duration::from_secs(config.health_check_interval_secs),
+            max_block_lag: config.max_block_lag,
+        })
+    }
+
+    /// Indices of the endpoints in the order they are tried, the unhealthy ones last
+    fn order(&self) -> Vec<usize> {
+        let (mut healthy, unhealthy): (Vec<_>, Vec<_>) = (0..self.endpoints.len())
+            .partition(|&i| self.endpoints[i].healthy.load(Ordering::Relaxed));
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    /// Send `request` to the endpoints of the pool
+    pub async fn request(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        if let RequestPacket::Single(single) = &request {
+            if self.quorum > 1 && self.quorum_methods.iter().any(|m| m == single.method()) {
+                let method = single.method().to_string();
+                return self.quorum_request(&method, request).await;
+            }
+        }
+        let mut last_error = None;
+        for i in self.order() {
+            let endpoint = &self.endpoints[i];
+            match endpoint.transport.clone().call(request.clone()).await {
+                Ok(response) => return Ok(response),
+                // JSON-RPC errors such as reverts would be the same on every endpoint
+                Err(e @ RpcError::Transport(_)) => {
+                    endpoint.set_healthy(false);
+                    record_rpc_failover(&endpoint.label);
+                    warn!(endpoint = %endpoint.label, error = %e, "RPC endpoint failed, trying the next one");
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| TransportErrorKind::custom_str("no RPC endpoint")))
+    }
+
+    /// Send `request` to every endpoint and return the response `quorum` of them agree on
+    async fn quorum_request(
+        &self,
+        method: &str,
+        request: RequestPacket,
+    ) -> TransportResult<ResponsePacket> {
+        let results = join_all(
+            self.endpoints
+                .iter()
+                .map(|endpoint| endpoint.transport.clone().call(request.clone())),
+        )
+        .await;
+        let mut tallies: Vec<(String, ResponsePacket, usize)> = Vec::new();
+        for (endpoint, result) in self.endpoints.iter().zip(results) {
+            let response = match result {
+                Ok(response) => response,
+                Err(e) => {
+                    endpoint.set_healthy(false);
+                    debug!(endpoint = %endpoint.label, method, error = %e, "Quorum read failed");
+                    continue;
+                }
+            };
+            let key = response_key(&response);
+            match tallies.iter_mut().find(|(k, _, _)| *k == key) {
+                Some((_, _, count)) => *count += 1,
+                None => tallies.push((key, response, 1)),
+            }
+        }
+        let best = tallies.into_iter().max_by_key(|(_, _, count)| *count);
+        match best {
+            Some((_, response, count)) if count >= self.quorum => Ok(response),
+            best => {
+                let agreed = best.map_or(0, |(_, _, count)| count);
+                record_rpc_quorum_failure(method);
+                warn!(
+                    method,
+                    agreed,
+                    quorum = self.quorum,
+                    "RPC endpoints disagree"
+                );
+                Err(TransportErrorKind::custom_str(&format!(
+                    "{method}: only {agreed} of the {} required endpoints agreed",
+                    self.quorum
+                )))
+            }
+        }
+    }
+
+    /// Read the head of every endpoint, those failing or lagging more than `max_block_lag`
+    /// blocks behind the highest head become unhealthy
+    pub async fn check_health(&self) {
+        let heads = join_all(self.endpoints.iter().map(Endpoint::head)).await;
+        let highest = heads
+            .iter()
+            .filter_map(|head| head.as_ref().ok())
+            .max()
+            .copied();
+        for (endpoint, head) in self.endpoints.iter().zip(heads) {
+            let healthy = match (head, highest) {
+                (Ok(head), Some(highest)) => highest - head <= self.max_block_lag,
+                (Ok(_), None) => true,
+                (Err(e), _) => {
+                    debug!(endpoint = %endpoint.label, error = %e, "RPC health check failed");
+                    false
+                }
+            };
+            endpoint.set_healthy(healthy);
+        }
+    }
+
+    /// Check the health of the endpoints every interval until `shutdown` is triggered
+    pub async fn run(self: Arc<Self>, shutdown: Shutdown) {
+        info!(
+            endpoints = ?self.endpoints.iter().map(|e| &e.label).collect::<Vec<_>>(),
+            quorum = self.quorum,
+            "Checking the health of the RPC endpoints"
+        );
+        while !shutdown.is_triggered() {
+            self.check_health().await;
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(self.health_check_interval) => {}
+            }
+        }
+    }
+}
+
+/// What two endpoints must return alike to agree: the result, or the error code and message
+fn response_key(response: &ResponsePacket) -> String {
+    match response {
+        ResponsePacket::Single(response) => match &response.payload {
+            ResponsePayload::Success(result) => result.get().to_string(),
+            ResponsePayload::Failure(error) => format!("error {}: {}", error.code, error.message),
+        },
+        ResponsePacket::Batch(_) => String::new(),
+    }
+}
+
+/// Transport handing the calls of a provider to an [`RpcPool`]
+#[derive(Debug, Clone)]
+pub struct PoolTransport(Arc<RpcPool>);
+
+impl Service<RequestPacket> for PoolTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let pool = self.0.clone();
+        Box::pin(async move { pool.request(request).await })
+    }
+}
+
+/// Layers the HTTP RPC clients go through: the pool spreading the calls to its primary
+/// endpoint, the rate limit, the circuit breaker and the capture. The clients of one stack
+/// share its limits and its breaker, the clients of another stack don't
+#[derive(Debug, Clone, Default)]
+pub struct RpcStack {
+    pool: Option<Arc<RpcPool>>,
+    limiter: Option<Arc<TokenBucket>>,
+    breaker: Option<Arc<CircuitBreaker>>,
+    capture: Option<Arc<RpcCapture>>,
+}
+
+impl RpcStack {
+    /// Layers set in `[rpc]`, `[rate_limit]`, `[circuit_breaker]` and `[rpc_capture]`
+    pub fn from_config(config: &OperatorConfig) -> Result<Self, CaptureError> {
+        Ok(Self {
+            pool: Self::failover(&config.rpc_url, &config.rpc).pool,
+            limiter: config.rate_limit.limiter().map(Arc::new),
+            breaker: config.circuit_breaker.breaker().map(Arc::new),
+            capture: RpcCapture::open(&config.rpc_capture)?.map(Arc::new),
+        })
+    }
+
+    /// Stack failing over between the endpoints of `config` and nothing else, for the
+    /// commands only reading the chain
+    pub fn failover(rpc_url: &str, config: &RpcConfig) -> Self {
+        let pool = match RpcPool::new(rpc_url, config) {
+            Ok(pool) if pool.endpoints.len() > 1 => Some(Arc::new(pool)),
+            Ok(_) => None,
+            Err(e) => {
+                warn!(error = %e, "RPC failover is disabled");
+                None
+            }
+        };
+        Self {
+            pool,
+            ..Self::default()
+        }
+    }
+
+    /// Client sending to `url`, through the pool when `url` is its primary endpoint, held
+    /// back by the rate limit and while the circuit breaker is open, and recorded or
+    /// replayed as `[rpc_capture]` sets
+    pub fn client(&self, url: Url) -> RpcClient {
+        let is_local = guess_local_url(&url);
+        match self.pool.as_ref().filter(|pool| pool.primary == url) {
+            Some(pool) => RpcClient::new(self.layer(PoolTransport(pool.clone()), &url), is_local),
+            None => RpcClient::new(self.layer(Http::new(url.clone()), &url), is_local),
+        }
+    }
+
+    fn layer<S>(
+        &self,
+        transport: S,
+        url: &Url,
+    ) -> ThrottleTransport<CaptureTransport<BreakerTransport<S>>> {
+        ThrottleTransport::new(
+            CaptureTransport::new(
+                BreakerTransport::new(transport, self.breaker.clone()),
+                url,
+                self.capture.clone(),
+            ),
+            self.limiter.clone(),
+        )
+    }
+
+    /// Read only provider of `rpc_url`, a drop in for `eigensdk::common::get_provider` that
+    /// goes through the stack, failing if `rpc_url` isn't a URL
+    pub fn http_provider(&self, rpc_url: &str) -> Result<SdkProvider, ConfigError> {
+        let url = Url::parse(rpc_url)
+            .map_err(|e| ConfigError::Invalid(format!("invalid rpc url {rpc_url}: {e}")))?;
+        Ok(ProviderBuilder::new().on_client(self.client(url)))
+    }
+
+    /// Health check the endpoints of the pool until `shutdown` is triggered, returns at once
+    /// without a pool
+    pub async fn run_health_checks(self, shutdown: Shutdown) {
+        if let Some(pool) = self.pool {
+            pool.run(shutdown).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_come_from_the_selected_network() {
+        let mut config = RpcConfig {
+            fallback_urls: vec!["http://b:8545".to_string()],
+            quorum: 2,
+            ..Default::default()
+        };
+        config.validate("http://a:8545").unwrap();
+        assert_eq!(
+            config.endpoints("http://a:8545").unwrap(),
+            ["http://a:8545", "http://b:8545"]
+        );
+
+        config.network = Some("holesky".to_string());
+        assert!(config.validate("http://a:8545").is_err());
+        config.networks.insert(
+            "holesky".to_string(),
+            RpcNetworkConfig {
+                fallback_urls: vec!["http://c:8545".to_string(), "http://d:8545".to_string()],
+                quorum: Some(3),
+                private_relay_url: None,
+            },
+        );
+        config.validate("http://a:8545").unwrap();
+        assert_eq!(config.endpoints("http://a:8545").unwrap().len(), 3);
+        config.networks.get_mut("holesky").unwrap().quorum = Some(4);
+        assert!(config.validate("http://a:8545").is_err());
+        config.networks.get_mut("holesky").unwrap().quorum = Some(3);
+
+        config.private_relay_url = Some("https://relay.example.com".to_string());
+        assert_eq!(
+            config.private_relay().unwrap(),
+            Some("https://relay.example.com")
+        );
+        config
+            .networks
+            .get_mut("holesky")
+            .unwrap()
+            .private_relay_url = Some("not a url".to_string());
+        assert!(config.validate("http://a:8545").is_err());
+
+        assert_eq!(
+            endpoint_label(&Url::parse("https://eth.example.com/v2/secret-key").unwrap()),
+            "eth.example.com"
+        );
+    }
+}
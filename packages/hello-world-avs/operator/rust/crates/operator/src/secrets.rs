@@ -0,0 +1,375 @@
+use std::{fmt, path::PathBuf};
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+use reqwest::{Certificate, Client, Method, RequestBuilder, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{ConfigError, SecretsError};
+use crate::secrets_file::{read_secrets_password, SecretsFile};
+
+/// Key read from a secret whose reference names none
+pub const DEFAULT_SECRET_KEY: &str = "value";
+
+/// Prefix of a config value standing for the secret named after it
+pub const SECRET_SCHEME: &str = "secret://";
+
+/// Secret named by `value` if it is a `secret://<reference>`
+pub fn secret_reference(value: &str) -> Option<&str> {
+    value.strip_prefix(SECRET_SCHEME)
+}
+
+/// Where the secrets referenced in `[secrets]` are fetched from
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretsBackend {
+    /// Secrets come from the config and the env only
+    #[default]
+    None,
+    /// KV version 2 engine of HashiCorp Vault
+    Vault,
+    /// Local file encrypted by `operator secrets`
+    File,
+}
+
+/// How the operator logs into Vault
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultAuth {
+    /// A token from `token` or `token_file`
+    #[default]
+    Token,
+    /// The service account token of the pod, exchanged for a Vault token of `role`
+    Kubernetes,
+}
+
+/// Vault server and login, the `[secrets.vault]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct VaultConfig {
+    /// Address of the Vault server (`VAULT_ADDR`)
+    pub addr: Option<String>,
+    /// Vault Enterprise namespace (`VAULT_NAMESPACE`)
+    pub namespace: Option<String>,
+    /// Login method
+    pub auth: VaultAuth,
+    /// Token of the `token` login (`VAULT_TOKEN`)
+    pub token: Option<String>,
+    /// File holding the token of the `token` login, read if `token` is unset
+    pub token_file: Option<PathBuf>,
+    /// Role of the `kubernetes` login (`VAULT_ROLE`)
+    pub role: Option<String>,
+    /// Mount of the Kubernetes auth method
+    pub auth_mount: String,
+    /// Service account token presented by the `kubernetes` login
+    pub jwt_file: PathBuf,
+    /// Mount of the KV version 2 engine holding the secrets
+    pub mount: String,
+    /// PEM CA bundle of the Vault server
+    pub ca_cert: Option<PathBuf>,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            addr: None,
+            namespace: None,
+            auth: VaultAuth::Token,
+            token: None,
+            token_file: None,
+            role: None,
+            auth_mount: "kubernetes".to_string(),
+            jwt_file: "/var/run/secrets/kubernetes.io/serviceaccount/token".into(),
+            mount: "secret".to_string(),
+            ca_cert: None,
+        }
+    }
+}
+
+/// Secrets fetched at startup, the `[secrets]` config section.
+///
+/// A Vault reference is `<path>#<key>`, the key `value` of the secret at `path` if
+/// `#<key>` is left out, a file reference the name of the secret. A fetched value
+/// replaces the one of the config and the env. Config values of the form
+/// `secret://<reference>` are fetched the same way.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretsConfig {
+    /// Backend holding the secrets (`SECRETS_BACKEND`)
+    pub backend: SecretsBackend,
+    /// Secret holding the hex encoded private key
+    pub private_key: Option<String>,
+    /// Secret holding the password of the JSON keystore
+    pub keystore_password: Option<String>,
+    /// Secret holding the password of the BLS keystore
+    pub bls_password: Option<String>,
+    /// Secret holding the HTTP RPC endpoint, credentials included
+    pub rpc_url: Option<String>,
+    /// Secret holding the WebSocket RPC endpoint, credentials included
+    pub ws_url: Option<String>,
+    /// Vault server, for the `vault` backend
+    pub vault: VaultConfig,
+    /// Encrypted secrets file, for the `file` backend (`SECRETS_FILE`)
+    pub file: PathBuf,
+    /// File holding the password of the secrets file, prompted if unset
+    /// (`SECRETS_PASSWORD_FILE`)
+    pub password_file: Option<PathBuf>,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            backend: SecretsBackend::None,
+            private_key: None,
+            keystore_password: None,
+            bls_password: None,
+            rpc_url: None,
+            ws_url: None,
+            vault: VaultConfig::default(),
+            file: "operator.secrets".into(),
+            password_file: None,
+        }
+    }
+}
+
+impl SecretsConfig {
+    fn references(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("secrets.private_key", &self.private_key),
+            ("secrets.keystore_password", &self.keystore_password),
+            ("secrets.bls_password", &self.bls_password),
+            ("secrets.rpc_url", &self.rpc_url),
+            ("secrets.ws_url", &self.ws_url),
+        ]
+        .into_iter()
+        .filter_map(|(name, reference)| Some((name, reference.as_deref()?)))
+    }
+
+    /// Check that the references can be fetched from the backend
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (name, reference) in self.references() {
+            if self.backend == SecretsBackend::None {
+                return Err(ConfigError::Invalid(format!(
+                    "{name} needs a secrets.backend to fetch it from"
+                )));
+            }
+            if split_reference(reference).0.is_empty() {
+                return Err(ConfigError::Invalid(format!(
+                    "{name} must be a <path>#<key> reference, got {reference}"
+                )));
+            }
+        }
+        if self.backend != SecretsBackend::Vault {
+            return Ok(());
+        }
+        let vault = &self.vault;
+        let addr = vault.addr.as_deref().ok_or_else(|| {
+            ConfigError::Invalid("secrets.vault.addr must be set for the vault backend".to_string())
+        })?;
+        if !Url::parse(addr).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+            return Err(ConfigError::Invalid(format!(
+                "secrets.vault.addr must be an http(s) URL, got {addr}"
+            )));
+        }
+        match vault.auth {
+            VaultAuth::Token if vault.token.is_none() && vault.token_file.is_none() => {
+                Err(ConfigError::Invalid(
+                    "the token login to Vault needs VAULT_TOKEN or secrets.vault.token_file"
+                        .to_string(),
+                ))
+            }
+            VaultAuth::Kubernetes if vault.role.is_none() => Err(ConfigError::Invalid(
+                "secrets.vault.role must be set for the kubernetes login".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Log into the backend, `None` if the secrets aren't fetched from anywhere
+    pub async fn provider(&self) -> Result<Option<Box<dyn SecretsProvider>>, SecretsError> {
+        match self.backend {
+            SecretsBackend::None => Ok(None),
+            SecretsBackend::Vault => Ok(Some(Box::new(VaultSecrets::login(&self.vault).await?))),
+            SecretsBackend::File => {
+                let password = read_secrets_password(self.password_file.as_deref(), &self.file)?;
+                Ok(Some(Box::new(SecretsFile::open(&self.file, &password)?)))
+            }
+        }
+    }
+}
+
+/// Source of the secrets named by the references of [`SecretsConfig`]
+#[async_trait]
+pub trait SecretsProvider: Send + Sync + fmt::Debug {
+    /// Value of the secret `reference`
+    async fn secret(&self, reference: &str) -> Result<String, SecretsError>;
+}
+
+/// Path and key of `reference`, `<path>#<key>` or a bare path
+pub fn split_reference(reference: &str) -> (&str, &str) {
+    let (path, key) = reference
+        .split_once('#')
+        .unwrap_or((reference, DEFAULT_SECRET_KEY));
+    (path.trim_matches('/'), key)
+}
+
+/// Secrets read from the KV version 2 engine of a Vault server
+pub struct VaultSecrets {
+    client: Client,
+    addr: Url,
+    namespace: Option<String>,
+    token: String,
+    mount: String,
+}
+
+impl fmt::Debug for VaultSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultSecrets")
+            .field("addr", &self.addr.as_str())
+            .field("namespace", &self.namespace)
+            .field("mount", &self.mount)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VaultSecrets {
+    /// Log into the Vault server of `config`
+    pub async fn login(config: &VaultConfig) -> Result<Self, SecretsError> {
+        let addr = config.addr.as_deref().unwrap_or_default();
+        let addr = Url::parse(addr)
+            .map_err(|e| SecretsError::Invalid(format!("invalid Vault address {addr}: {e}")))?;
+        let mut builder = Client::builder().use_rustls_tls();
+        if let Some(ca_cert) = &config.ca_cert {
+            let pem = read("Vault CA cert", ca_cert)?;
+            for cert in Certificate::from_pem_bundle(pem.as_bytes())? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        let mut vault = Self {
+            client: builder.build()?,
+            addr,
+            namespace: config.namespace.clone(),
+            token: String::new(),
+            mount: config.mount.trim_matches('/').to_string(),
+        };
+        vault.token = match config.auth {
+            VaultAuth::Token => match (&config.token, &config.token_file) {
+                (Some(token), _) => token.clone(),
+                (None, Some(file)) => read("Vault token", file)?.trim().to_string(),
+                (None, None) => {
+                    return Err(SecretsError::Invalid(
+                        "no Vault token to log in with".to_string(),
+                    ))
+                }
+            },
+            VaultAuth::Kubernetes => {
+                let jwt = read("service account token", &config.jwt_file)?;
+                let path = format!("auth/{}/login", config.auth_mount.trim_matches('/'));
+                let body = vault
+                    .send(
+                        &path,
+                        vault.request(Method::POST, &path)?.json(&json!({
+                            "jwt": jwt.trim(),
+                            "role": config.role,
+                        })),
+                    )
+                    .await?;
+                body["auth"]["client_token"]
+                    .as_str()
+                    .ok_or_else(|| SecretsError::Invalid(format!("no client token from {path}")))?
+                    .to_string()
+            }
+        };
+        Ok(vault)
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, SecretsError> {
+        let url = self
+            .addr
+            .join(&format!("v1/{path}"))
+            .map_err(|e| SecretsError::Invalid(format!("invalid Vault path {path}: {e}")))?;
+        let mut request = self.client.request(method, url);
+        if !self.token.is_empty() {
+            request = request.header("X-Vault-Token", &self.token);
+        }
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        Ok(request)
+    }
+
+    async fn send(&self, path: &str, request: RequestBuilder) -> Result<Value, SecretsError> {
+        let vault_error = |source| SecretsError::Vault {
+            path: path.to_string(),
+            source,
+        };
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(vault_error)?
+            .json()
+            .await
+            .map_err(vault_error)
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for VaultSecrets {
+    async fn secret(&self, reference: &str) -> Result<String, SecretsError> {
+        let (path, key) = split_reference(reference);
+        let path = format!("{}/data/{path}", self.mount);
+        let body = self.send(&path, self.request(Method::GET, &path)?).await?;
+        kv_value(&body, key).ok_or_else(|| SecretsError::Missing {
+            reference: reference.to_string(),
+        })
+    }
+}
+
+/// Value of `key` in a KV version 2 read response
+fn kv_value(body: &Value, key: &str) -> Option<String> {
+    match &body["data"]["data"][key] {
+        Value::String(value) => Some(value.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn read(what: &'static str, path: &PathBuf) -> Result<String, SecretsError> {
+    std::fs::read_to_string(path).map_err(|source| SecretsError::Read {
+        what,
+        path: path.clone(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references_name_a_key_of_a_kv_secret() {
+        assert_eq!(
+            split_reference("/operator/keys#private_key"),
+            ("operator/keys", "private_key")
+        );
+        assert_eq!(split_reference("operator/rpc"), ("operator/rpc", "value"));
+
+        let body = json!({ "data": { "data": { "private_key": "0x01", "port": 8545 } } });
+        assert_eq!(kv_value(&body, "private_key").as_deref(), Some("0x01"));
+        assert_eq!(kv_value(&body, "port").as_deref(), Some("8545"));
+        assert_eq!(kv_value(&body, "missing"), None);
+
+        let mut config = SecretsConfig {
+            private_key: Some("operator/keys#private_key".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        config.backend = SecretsBackend::Vault;
+        config.vault.addr = Some("https://vault:8200".to_string());
+        assert!(config.validate().is_err());
+        config.vault.token = Some("s.token".to_string());
+        config.validate().unwrap();
+    }
+}
@@ -0,0 +1,1311 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::Address;
+use rand::Rng;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{error, field, info, info_span, warn, Instrument};
+
+use crate::aggregator::AggregatorClient;
+use crate::alerting::StallWatchdog;
+use crate::attestation::{self, Attester};
+use crate::bls::BlsKey;
+use crate::config::OperatorConfig;
+use crate::control::{ControlState, TaskOutcome, TaskRecord};
+use crate::dead_letter::DeadLetterConfig;
+use crate::error::{ConfigError, OperatorError};
+use crate::gas::{fee_strategy, GasConfig};
+use crate::ha::{already_pending, already_responded, HaConfig, Replica};
+use crate::handshake::TaskType;
+use crate::health::{self, HealthConfig, HealthState};
+use crate::key_pool::KeyPool;
+use crate::oracle::PriceOracle;
+use crate::pending_tx::PendingTxWatcher;
+use crate::prometheus::{
+    record_task_deduplicated, record_task_deferred, record_task_latency, record_tasks_dropped,
+    set_task_queue_depth,
+};
+use crate::quoting::Quoter;
+use crate::reorg::{ReorgConfig, ReorgMonitor};
+use crate::retry::{failure_class, retry, RetryConfig};
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+use crate::signer::OperatorSigner;
+use crate::slo::block_timestamps;
+use crate::spill::TaskSpill;
+use crate::swap_payload::SwapTaskPayload;
+use crate::task_handler::{TaskHandler, TaskHandlers};
+use crate::task_listener::{NewTask, TASK_CHANNEL_CAPACITY};
+use crate::task_policy::TaskPolicy;
+use crate::task_responder::{AggregatorResponder, SwapManagerResponder, TaskResponder};
+use crate::task_source::{connect_task_source, TaskSource, TaskSourceConfig};
+use crate::task_store::TaskStore;
+
+/// Components and settings of an [`Operator`], see [`Operator::builder`]
+#[derive(Debug, Default)]
+pub struct OperatorBuilder {
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    services: Services,
+    signer: Option<OperatorSigner>,
+    key_pool: Vec<OperatorSigner>,
+    private_relay: Option<(String, Duration)>,
+    operator_address: Option<Address>,
+    swap_manager_address: Option<Address>,
+    task_source: Option<Box<dyn TaskSource>>,
+    task_store: Option<Arc<dyn TaskStore>>,
+    task_responder: Option<Box<dyn TaskResponder>>,
+    aggregator: Option<AggregatorClient>,
+    oracle: Option<PriceOracle>,
+    policy: Option<TaskPolicy>,
+    quoter: Option<Quoter>,
+    handlers: TaskHandlers,
+    bls_key: Option<BlsKey>,
+    watcher: Option<PendingTxWatcher>,
+    control: Option<Arc<ControlState>>,
+    shutdown: Shutdown,
+    response_percentage: Option<f64>,
+    task_types: Option<Vec<TaskType>>,
+    queue_capacity: Option<usize>,
+    spill_to_store: bool,
+    source_config: TaskSourceConfig,
+    retry: RetryConfig,
+    dead_letter: DeadLetterConfig,
+    reorg: ReorgConfig,
+    health: HealthConfig,
+    stall_after: Option<Duration>,
+    ha: HaConfig,
+}
+
+impl OperatorBuilder {
+    /// Take the endpoints, fees, aggregator and section settings from `config`.
+    ///
+    /// The signer, contract address and task store are left to the caller. The components
+    /// built from `config` call the chain through the [`Self::services`] set before.
+    pub fn config(mut self, config: &OperatorConfig) -> Result<Self, OperatorError> {
+        self.rpc_url = Some(config.rpc_url.clone());
+        self.ws_url = Some(config.ws_url.clone());
+        self.operator_address = config.signer.operator_address;
+        self.key_pool = config.key_pool.signers()?;
+        self.private_relay = config
+            .rpc
+            .private_relay()?
+            .map(|url| (url.to_string(), config.rpc.private_fallback()));
+        self.watcher = Some(config.pending_tx_watcher()?);
+        self.aggregator = config
+            .operator
+            .aggregator_url
+            .as_deref()
+            .map(AggregatorClient::new)
+            .transpose()?;
+        self.oracle = PriceOracle::from_config(&config.oracle, &config.rpc_url, &self.services)?;
+        self.policy = TaskPolicy::from_config(&config.validation, &self.services)?;
+        self.quoter = Quoter::from_config(&config.quoting, &config.rpc_url, &self.services)?;
+        self.response_percentage = Some(config.operator.response_percentage);
+        self.task_types = Some(config.operator.task_types.clone());
+        self.queue_capacity = Some(config.operator.queue_capacity);
+        self.spill_to_store = config.operator.spill_to_store;
+        self.source_config = config.source.clone();
+        self.retry = config.retry.clone();
+        self.dead_letter = config.dead_letter.clone();
+        self.reorg = config.reorg.clone();
+        self.health = config.health.clone();
+        // The announced socket is where the aggregator looks for the operator
+        if let Some(socket) = config.operator_socket()? {
+            self.health.listen_addr.get_or_insert(socket.bind_addr());
+        }
+        self.stall_after = config.alerts.stall_after_secs.map(Duration::from_secs);
+        self.ha = config.ha.clone();
+        Ok(self)
+    }
+
+    /// HTTP RPC endpoint transactions are sent to
+    pub fn rpc_url(mut self, rpc_url: impl Into<String>) -> Self {
+        self.rpc_url = Some(rpc_url.into());
+        self
+    }
+
+    /// WebSocket RPC endpoint of the default task source, unused with [`Self::task_source`]
+    pub fn ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.ws_url = Some(ws_url.into());
+        self
+    }
+
+    /// Components the operator shares between its tasks, see [`Services`]. Set before
+    /// [`Self::config`]
+    pub fn services(mut self, services: Services) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Key signing the responses and their transactions
+    pub fn signer(mut self, signer: OperatorSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Keys submitting the response transactions in turn with the signer, which still signs
+    /// the responses
+    pub fn key_pool(mut self, keys: Vec<OperatorSigner>) -> Self {
+        self.key_pool = keys;
+        self
+    }
+
+    /// Send the responses to the Flashbots Protect style RPC `relay_url` rather than the
+    /// public mempool, falling back to it once they weren't mined within `fallback_after`
+    pub fn private_relay(mut self, relay_url: impl Into<String>, fallback_after: Duration) -> Self {
+        self.private_relay = Some((relay_url.into(), fallback_after));
+        self
+    }
+
+    /// Operator the signer signs for once it was made the operator's signing key, the
+    /// signer's own address by default
+    pub fn operator_address(mut self, operator: Address) -> Self {
+        self.operator_address = Some(operator);
+        self
+    }
+
+    /// SwapManager the tasks are read from and responded to
+    pub fn swap_manager_address(mut self, address: Address) -> Self {
+        self.swap_manager_address = Some(address);
+        self
+    }
+
+    /// Source of the tasks, instead of the one selected by [`Self::source_config`]
+    pub fn task_source(mut self, source: impl TaskSource + 'static) -> Self {
+        self.task_source = Some(Box::new(source));
+        self
+    }
+
+    /// Store skipping handled tasks and recording outcomes, none by default
+    pub fn task_store(mut self, store: Arc<dyn TaskStore>) -> Self {
+        self.task_store = Some(store);
+        self
+    }
+
+    /// Responder of the tasks, instead of submitting respondToTask or using [`Self::aggregator`]
+    pub fn task_responder(mut self, responder: impl TaskResponder + 'static) -> Self {
+        self.task_responder = Some(Box::new(responder));
+        self
+    }
+
+    /// Hand the signed responses to this aggregator instead of submitting them
+    pub fn aggregator(mut self, aggregator: AggregatorClient) -> Self {
+        self.aggregator = Some(aggregator);
+        self
+    }
+
+    /// Check swap tasks against reference prices before responding
+    pub fn oracle(mut self, oracle: PriceOracle) -> Self {
+        self.oracle = Some(oracle);
+        self
+    }
+
+    /// Reject the tasks breaking `policy` instead of responding to them
+    pub fn policy(mut self, policy: TaskPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Quote swap tasks through a Uniswap V3 QuoterV2, ignored with a custom task responder
+    pub fn quoter(mut self, quoter: Quoter) -> Self {
+        self.quoter = Some(quoter);
+        self
+    }
+
+    /// Compute the responses to the tasks of its type with `handler`, tasks of the types
+    /// without a handler are echoed by [`NameEchoHandler`](crate::task_handler::NameEchoHandler).
+    /// Ignored with a custom task responder
+    pub fn task_handler(mut self, handler: impl TaskHandler + 'static) -> Self {
+        self.handlers = self.handlers.register(handler);
+        self
+    }
+
+    /// Sign the responses with the BLS `key` for a SwapManager checking BLS signatures.
+    /// The aggregator only takes ECDSA signatures, so this can't be combined with
+    /// [`Self::aggregator`], and it's ignored with a custom task responder
+    pub fn bls_signing(mut self, key: BlsKey) -> Self {
+        self.bls_key = Some(key);
+        self
+    }
+
+    /// Fees and replacement of the response transactions, the default gas settings if unset
+    pub fn pending_tx_watcher(mut self, watcher: PendingTxWatcher) -> Self {
+        self.watcher = Some(watcher);
+        self
+    }
+
+    /// State shared with the control API, a fresh one if unset
+    pub fn control(mut self, control: Arc<ControlState>) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Stop once `shutdown` triggers, after the task being handled
+    pub fn shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Chance in percent of responding to a task, 100 by default
+    pub fn response_percentage(mut self, percentage: f64) -> Self {
+        self.response_percentage = Some(percentage);
+        self
+    }
+
+    /// Task types responded to, the other tasks are skipped. Every type by default
+    pub fn task_types(mut self, task_types: Vec<TaskType>) -> Self {
+        self.task_types = Some(task_types);
+        self
+    }
+
+    /// Tasks buffered between the task source and the responder, the source waits while
+    /// the queue is full
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Park the tasks overflowing the queue in the task store instead of holding the
+    /// source back, and park the queued tasks there on shutdown. Needs a task store
+    pub fn spill_to_store(mut self, spill: bool) -> Self {
+        self.spill_to_store = spill;
+        self
+    }
+
+    /// Settings of the default task source
+    pub fn source_config(mut self, config: TaskSourceConfig) -> Self {
+        self.source_config = config;
+        self
+    }
+
+    /// Backoff applied to RPC calls and transactions
+    pub fn retry(mut self, config: RetryConfig) -> Self {
+        self.retry = config;
+        self
+    }
+
+    /// Deadline of the responses and when failed tasks are given up on
+    pub fn dead_letter(mut self, config: DeadLetterConfig) -> Self {
+        self.dead_letter = config;
+        self
+    }
+
+    /// Reorg detection, active only with a task store
+    pub fn reorg(mut self, config: ReorgConfig) -> Self {
+        self.reorg = config;
+        self
+    }
+
+    /// Liveness and readiness probes
+    pub fn health(mut self, config: HealthConfig) -> Self {
+        self.health = config;
+        self
+    }
+
+    /// Report the responder as stalled once a task is handled for longer than `stall_after`
+    pub fn stall_after(mut self, stall_after: Duration) -> Self {
+        self.stall_after = Some(stall_after);
+        self
+    }
+
+    /// Coordination with the other replicas of the operator, through the task store
+    pub fn ha(mut self, config: HaConfig) -> Self {
+        self.ha = config;
+        self
+    }
+
+    /// Check the settings and build the operator
+    pub fn build(self) -> Result<Operator, OperatorError> {
+        let rpc_url = self.rpc_url.ok_or_else(|| missing("rpc_url"))?;
+        let signer = self.signer.ok_or_else(|| missing("signer"))?;
+        let swap_manager_address = self
+            .swap_manager_address
+            .ok_or_else(|| missing("swap_manager_address"))?;
+        let source = match self.task_source {
+            Some(source) => DeferredSource::Ready(source),
+            None => DeferredSource::Connect(self.ws_url.ok_or_else(|| missing("ws_url"))?),
+        };
+        let response_percentage = self.response_percentage.unwrap_or(100.0);
+        if !(0.0..=100.0).contains(&response_percentage) {
+            return Err(ConfigError::Invalid(format!(
+                "response_percentage must be between 0 and 100, got {response_percentage}"
+            ))
+            .into());
+        }
+        let task_types = self.task_types.unwrap_or_else(|| TaskType::ALL.to_vec());
+        if task_types.is_empty() {
+            return Err(ConfigError::Invalid(
+                "task_types needs at least one task type".to_string(),
+            )
+            .into());
+        }
+        let queue_capacity = self.queue_capacity.unwrap_or(TASK_CHANNEL_CAPACITY);
+        if queue_capacity == 0 {
+            return Err(
+                ConfigError::Invalid("queue_capacity must be at least 1".to_string()).into(),
+            );
+        }
+        let control = self
+            .control
+            .unwrap_or_else(|| ControlState::new(signer.address(), &self.services));
+        // Candidate responses are checked against the responses the handlers compute
+        let attester = Arc::new(Attester::new(
+            &rpc_url,
+            &self.services,
+            swap_manager_address,
+            self.handlers.clone(),
+        )?);
+        let responder: Box<dyn TaskResponder> = match self.task_responder {
+            Some(responder) => responder,
+            None => {
+                let watcher = match self.watcher {
+                    Some(watcher) => watcher,
+                    None => {
+                        let gas = GasConfig::default();
+                        PendingTxWatcher::new(fee_strategy(&gas)?, &gas)
+                    }
+                };
+                let mut responder = SwapManagerResponder::new(
+                    &rpc_url,
+                    &self.services,
+                    signer.clone(),
+                    swap_manager_address,
+                    watcher,
+                )?
+                .handlers(self.handlers);
+                if !self.key_pool.is_empty() || self.private_relay.is_some() {
+                    let signer = signer.clone().services(self.services.clone());
+                    let extra: Vec<_> = self
+                        .key_pool
+                        .into_iter()
+                        .map(|key| key.services(self.services.clone()))
+                        .collect();
+                    let mut keys = KeyPool::new(&rpc_url, &signer, &extra)?;
+                    if let Some((relay_url, fallback_after)) = &self.private_relay {
+                        keys = keys.private_relay(relay_url, *fallback_after)?;
+                    }
+                    responder = responder.key_pool(keys);
+                }
+                if let Some(operator) = self.operator_address {
+                    responder = responder.operator(operator);
+                }
+                if let Some(quoter) = self.quoter {
+                    responder = responder.quoter(quoter);
+                }
+                if let Some(key) = self.bls_key {
+                    if self.aggregator.is_some() {
+                        return Err(ConfigError::Invalid(
+                            "BLS response signing can't be used with an aggregator".to_string(),
+                        )
+                        .into());
+                    }
+                    responder = responder.bls_signing(key);
+                }
+                match self.aggregator {
+                    Some(client) => Box::new(AggregatorResponder::new(responder, client)),
+                    None => Box::new(responder),
+                }
+            }
+        };
+        Ok(Operator {
+            rpc_url,
+            services: self.services,
+            signer,
+            swap_manager_address,
+            source,
+            store: self.task_store,
+            responder,
+            oracle: self.oracle,
+            policy: self.policy,
+            control,
+            shutdown: self.shutdown,
+            response_percentage,
+            task_types,
+            queue_capacity,
+            spill_to_store: self.spill_to_store,
+            source_config: self.source_config,
+            retry: self.retry,
+            dead_letter: self.dead_letter,
+            reorg: self.reorg,
+            health: self.health,
+            stall_after: self.stall_after,
+            ha: self.ha,
+            attester,
+        })
+    }
+}
+
+fn missing(setting: &str) -> OperatorError {
+    ConfigError::Invalid(format!("{setting} is required to build an operator")).into()
+}
+
+/// Task source given to the builder, or the WebSocket endpoint to connect the default one to
+#[derive(Debug)]
+enum DeferredSource {
+    Ready(Box<dyn TaskSource>),
+    Connect(String),
+}
+
+/// Operator responding to the tasks of a SwapManager, embeddable in other services
+#[derive(Debug)]
+pub struct Operator {
+    rpc_url: String,
+    services: Services,
+    signer: OperatorSigner,
+    swap_manager_address: Address,
+    source: DeferredSource,
+    store: Option<Arc<dyn TaskStore>>,
+    responder: Box<dyn TaskResponder>,
+    oracle: Option<PriceOracle>,
+    policy: Option<TaskPolicy>,
+    control: Arc<ControlState>,
+    shutdown: Shutdown,
+    response_percentage: f64,
+    task_types: Vec<TaskType>,
+    queue_capacity: usize,
+    spill_to_store: bool,
+    source_config: TaskSourceConfig,
+    retry: RetryConfig,
+    dead_letter: DeadLetterConfig,
+    reorg: ReorgConfig,
+    health: HealthConfig,
+    stall_after: Option<Duration>,
+    ha: HaConfig,
+    attester: Arc<Attester>,
+}
+
+impl Operator {
+    /// Start configuring an operator
+    pub fn builder() -> OperatorBuilder {
+        OperatorBuilder::default()
+    }
+
+    /// State shared with the control API
+    pub fn control(&self) -> Arc<ControlState> {
+        self.control.clone()
+    }
+
+    /// Handle stopping [`Self::run`]
+    pub fn shutdown(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Respond to tasks until the shutdown triggers or the task source is exhausted,
+    /// finishing the task being handled first
+    pub async fn run(self) -> Result<(), OperatorError> {
+        let store = self.store;
+        let start_block = match &store {
+            Some(store) => store.next_block().await?,
+            None => None,
+        };
+        if let Some(start_block) = start_block {
+            info!(start_block, "Resuming from the task store");
+        }
+        let replica = match &store {
+            Some(store) if self.ha.enabled => Some(Replica::new(store.clone(), &self.ha)),
+            None if self.ha.enabled => {
+                return Err(
+                    ConfigError::Invalid("ha.enabled needs a task store".to_string()).into(),
+                )
+            }
+            _ => None,
+        };
+        let spill = match &store {
+            Some(store) if self.spill_to_store => Some(TaskSpill::new(store.clone()).await?),
+            None if self.spill_to_store => {
+                return Err(ConfigError::Invalid(
+                    "operator.spill_to_store needs a task store".to_string(),
+                )
+                .into())
+            }
+            _ => None,
+        };
+        if let Some(election) = replica.as_ref().and_then(Replica::election) {
+            election.campaign().await;
+            tokio::spawn(election.clone().run(self.shutdown.clone()));
+        }
+
+        let source = match self.source {
+            DeferredSource::Ready(source) => source,
+            DeferredSource::Connect(ws_url) => {
+                connect_task_source(
+                    &self.rpc_url,
+                    &ws_url,
+                    &self.services,
+                    self.swap_manager_address,
+                    &self.source_config,
+                    &self.retry,
+                    start_block,
+                )
+                .await?
+            }
+        };
+
+        // Tasks of a work queue are delivered again until acknowledged, once handled
+        let acks = source.acks();
+        // The persisted block cursor only moves past the tasks once handled
+        let cursor = source.cursor();
+        let operator = self.signer.address();
+        let health_state = HealthState::new(
+            &self.rpc_url,
+            &self.services,
+            self.signer,
+            source.health(),
+            &self.health,
+        )?;
+        if let Some(health_addr) = self.health.listen_addr {
+            let health_state = health_state.clone();
+            let routes = attestation::router(self.attester.clone());
+            tokio::spawn(async move {
+                if let Err(e) = health::serve(health_state, health_addr, routes).await {
+                    error!(error = %e, "Health endpoints stopped");
+                }
+            });
+        }
+
+        let watchdog = self.stall_after.map(StallWatchdog::new);
+        if let Some(watchdog) = &watchdog {
+            tokio::spawn(
+                watchdog
+                    .clone()
+                    .run(self.services.notifications().clone(), self.shutdown.clone()),
+            );
+        }
+
+        // Tasks a reorg undid, the sender is dropped right away when nothing watches for reorgs
+        let (replay_sender, mut replays) = mpsc::channel(TASK_CHANNEL_CAPACITY);
+        match &store {
+            Some(store) if self.reorg.enabled => {
+                let monitor = ReorgMonitor::new(
+                    &self.rpc_url,
+                    &self.services,
+                    self.swap_manager_address,
+                    store.clone(),
+                    operator,
+                    &self.reorg,
+                    &self.retry,
+                )?;
+                tokio::spawn(monitor.run(replay_sender));
+            }
+            _ => drop(replay_sender),
+        }
+
+        // A slow responder fills the queue and holds the source back, or spills over to the
+        // task store, instead of letting tasks pile up in memory
+        let (queue_sender, mut queue) = mpsc::channel(self.queue_capacity);
+        let mut feeder = tokio::spawn(feed_queue(source, queue_sender, spill.clone()));
+        // Failed tasks come back here after `dead_letter.retry_delay_secs`, the run only ends
+        // once none is pending
+        let (retry_sender, mut retries) = mpsc::channel(TASK_CHANNEL_CAPACITY);
+        let mut pending_retries = 0usize;
+        let mut source_done = false;
+        // Failed attempts and first sighting of the tasks, when there is no task store
+        let mut attempts: HashMap<u32, (u32, i64)> = HashMap::new();
+        // Retries pending when the last run stopped are attempted again, the cursors having
+        // possibly moved past them with the tasks after
+        if let Some(store) = &store {
+            let failed = store.failed_tasks().await?;
+            if !failed.is_empty() {
+                info!(tasks = failed.len(), "Attempting the failed tasks again");
+                pending_retries += failed.len();
+                let retry_sender = retry_sender.clone();
+                tokio::spawn(async move {
+                    for stored in failed {
+                        let _ = retry_sender.send(stored.new_task()).await;
+                    }
+                });
+            }
+        }
+
+        let (responder, control) = (&self.responder, &self.control);
+        let provider = self.services.http_provider(&self.rpc_url)?;
+        // Process tasks when a new event is detected
+        loop {
+            if source_done && pending_retries == 0 {
+                break;
+            }
+            let new_task = tokio::select! {
+                biased;
+                _ = self.shutdown.triggered() => break,
+                Some(new_task) = replays.recv() => new_task,
+                Some(new_task) = retries.recv() => {
+                    pending_retries -= 1;
+                    new_task
+                }
+                new_task = queue.recv(), if !source_done => match new_task {
+                    Some(new_task) => {
+                        set_task_queue_depth(queue.len());
+                        new_task
+                    }
+                    // The source is exhausted or failed
+                    None => {
+                        (&mut feeder).await??;
+                        source_done = true;
+                        continue;
+                    }
+                },
+            };
+            // Claimed before looking the task up, so a replica finishing it in between is seen
+            if let Some(replica) = &replica {
+                if !replica.claim(new_task.index).await {
+                    continue;
+                }
+            }
+            if let Some(store) = &store {
+                match store.task(new_task.index).await {
+                    Ok(Some(stored)) if stored.is_done() => {
+                        info!(
+                            task_index = new_task.index,
+                            "Task already handled, skipping"
+                        );
+                        record_task_deduplicated("stored");
+                        if let Some(replica) = &replica {
+                            replica.release(new_task.index).await;
+                        }
+                        if let Some(acks) = &acks {
+                            acks.ack(new_task.index).await;
+                        }
+                        if let Some(cursor) = &cursor {
+                            cursor.handled(&new_task);
+                        }
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(error = %e, "Failed to look the task up in the task store"),
+                }
+                if let Err(e) = store.record_seen(&new_task).await {
+                    warn!(error = %e, "Failed to record the task in the task store");
+                }
+            }
+            // A duplicate response only burns gas on a revert. Tasks of a work queue may also
+            // be redelivered while a response of a dead worker is still pending
+            let handled = already_responded(
+                &provider,
+                operator,
+                self.swap_manager_address,
+                new_task.index,
+            )
+            .await
+                || (self.ha.check_pending_txs || acks.is_some())
+                    && already_pending(
+                        &provider,
+                        operator,
+                        self.swap_manager_address,
+                        new_task.index,
+                    )
+                    .await;
+            if handled {
+                if let Some(replica) = &replica {
+                    replica.release(new_task.index).await;
+                }
+                if let Some(acks) = &acks {
+                    acks.ack(new_task.index).await;
+                }
+                if let Some(cursor) = &cursor {
+                    cursor.handled(&new_task);
+                }
+                continue;
+            }
+            let _busy = watchdog
+                .as_ref()
+                .map(|watchdog| watchdog.busy(new_task.index));
+            let span = info_span!(
+                "task",
+                task_index = new_task.index,
+                task_name = %new_task.task.name,
+                block_number = new_task.block_number,
+                created_tx = ?new_task.transaction_hash,
+                tx_hash = field::Empty,
+            );
+            let (outcome, tx_hash, response_block, gas_used, failure, rejection) = async {
+                info!("New task detected");
+                if control.is_paused() {
+                    info!("Responding is paused, ignoring task");
+                    return (TaskOutcome::Paused, None, None, None, None, None);
+                }
+                let task_type = TaskType::of(&new_task.task.name);
+                if !self.task_types.contains(&task_type) {
+                    info!(%task_type, "Task type not handled, not responding");
+                    return (TaskOutcome::Skipped, None, None, None, None, None);
+                }
+                // Malformed payloads are left to the validation policy
+                if let Ok(Some(payload)) = SwapTaskPayload::decode(&new_task.task.name) {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |now| now.as_secs());
+                    if payload.is_expired(now) {
+                        info!(
+                            deadline = payload.deadline,
+                            "Swap deadline passed, not responding"
+                        );
+                        return (TaskOutcome::Expired, None, None, None, None, None);
+                    }
+                }
+
+                // There is a `response_percentage` chance that the operator will respond to the task.
+                // If the operator does not respond, the operator will be slashed.
+                let should_respond = rand::rng().random_bool(self.response_percentage / 100.0);
+
+                if !should_respond {
+                    info!("Operator did not respond to task");
+                    return (TaskOutcome::Skipped, None, None, None, None, None);
+                }
+                let policy_check = match &self.policy {
+                    Some(policy) => policy.check(&new_task.task.name),
+                    None => Ok(()),
+                };
+                if let Err(e) = policy_check {
+                    warn!(error = %e, "Task breaks the validation policy, refusing to attest");
+                    return (
+                        TaskOutcome::Rejected,
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some(e.to_string()),
+                    );
+                }
+                let price_check = match &self.oracle {
+                    Some(oracle) => oracle.check_task(&new_task.task.name).await,
+                    None => Ok(()),
+                };
+                if let Err(e) = price_check {
+                    warn!(error = %e, "Refusing to attest to the swap");
+                    (
+                        TaskOutcome::Rejected,
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some(e.to_string()),
+                    )
+                } else {
+                    let respond = retry(&self.retry, responder.name(), || {
+                        responder.respond(&new_task)
+                    });
+                    let response = match self.dead_letter.response_timeout() {
+                        Some(deadline) => tokio::time::timeout(deadline, respond)
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(OperatorError::ResponseTimeout {
+                                    task_index: new_task.index,
+                                    secs: deadline.as_secs(),
+                                })
+                            }),
+                        None => respond.await,
+                    };
+                    match response {
+                        Ok(response) => {
+                            if let Some(tx_hash) = response.tx_hash {
+                                tracing::Span::current().record("tx_hash", field::display(tx_hash));
+                            }
+                            (
+                                response.outcome,
+                                response.tx_hash,
+                                response.block_number,
+                                response.gas_used,
+                                None,
+                                None,
+                            )
+                        }
+                        Err(e) => {
+                            error!(error = %e, "Failed to respond to task");
+                            (
+                                TaskOutcome::Failed,
+                                None,
+                                None,
+                                None,
+                                Some((failure_class(&e), e.to_string())),
+                                None,
+                            )
+                        }
+                    }
+                }
+            }
+            .instrument(span)
+            .await;
+            // A task attempted again later holds the block cursor back until then
+            let mut retrying = false;
+            let outcome = if outcome == TaskOutcome::Failed {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or_default();
+                let counted = match &store {
+                    Some(store) => store.record_attempt(new_task.index).await,
+                    None => {
+                        let entry = attempts.entry(new_task.index).or_insert((0, now));
+                        entry.0 += 1;
+                        Ok(*entry)
+                    }
+                };
+                let (attempt, seen_at) = counted.unwrap_or_else(|e| {
+                    warn!(error = %e, "Failed to count the attempt in the task store");
+                    (1, now)
+                });
+                if self.dead_letter.is_dead(attempt, seen_at, now) {
+                    error!(
+                        task_index = new_task.index,
+                        attempts = attempt,
+                        "Giving up on the task, dead-lettered"
+                    );
+                    attempts.remove(&new_task.index);
+                    TaskOutcome::DeadLetter
+                } else {
+                    info!(
+                        task_index = new_task.index,
+                        attempts = attempt,
+                        retry_in_secs = self.dead_letter.retry_delay_secs,
+                        "Attempting the task again later"
+                    );
+                    pending_retries += 1;
+                    retrying = true;
+                    let (retry_sender, new_task, delay) = (
+                        retry_sender.clone(),
+                        new_task.clone(),
+                        self.dead_letter.retry_delay(),
+                    );
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        let _ = retry_sender.send(new_task).await;
+                    });
+                    outcome
+                }
+            } else {
+                attempts.remove(&new_task.index);
+                outcome
+            };
+            if let Some(block_number) = new_task.block_number {
+                health_state.set_processed_block(block_number);
+            }
+            let latency = match (new_task.block_number, response_block) {
+                (Some(created_block), Some(response_block)) => {
+                    match block_timestamps(&provider, created_block, response_block).await {
+                        Ok((created_at, responded_at)) => {
+                            record_task_latency(responded_at.saturating_sub(created_at));
+                            Some((created_at, responded_at))
+                        }
+                        Err(e) => {
+                            warn!(task_index = new_task.index, error = %e, "Failed to measure the task latency");
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            control.record(TaskRecord {
+                task_index: Some(new_task.index),
+                task_name: new_task.task.name.clone(),
+                block_number: new_task.block_number,
+                outcome,
+                tx_hash,
+                at: SystemTime::now(),
+            });
+            // The reorg monitor publishes the confirmations it waits for
+            if response_block.is_some()
+                && (store.is_none() || !self.reorg.enabled || self.reorg.confirmations == 0)
+            {
+                self.services.publish_confirmed(operator, &[new_task.index]);
+            }
+            if let Some(store) = &store {
+                if let Err(e) = store
+                    .record_outcome(new_task.index, outcome, tx_hash, response_block, gas_used)
+                    .await
+                {
+                    warn!(error = %e, "Failed to record the task outcome in the task store");
+                }
+                if let Some((class, error)) = &failure {
+                    if let Err(e) = store.record_failure(new_task.index, class, error).await {
+                        warn!(error = %e, "Failed to record the task failure in the task store");
+                    }
+                }
+                if let Some(reason) = &rejection {
+                    if let Err(e) = store.record_rejection(new_task.index, reason).await {
+                        warn!(error = %e, "Failed to record the task rejection in the task store");
+                    }
+                }
+                // Without confirmations to wait for, the receipt is as final as it gets
+                if let Some(response_block) = response_block {
+                    if !self.reorg.enabled || self.reorg.confirmations == 0 {
+                        if let Err(e) = store.confirm_responses(response_block).await {
+                            warn!(error = %e, "Failed to confirm the response in the task store");
+                        }
+                    }
+                }
+                if let Some((created_at, responded_at)) = latency {
+                    if let Err(e) = store
+                        .record_latency(new_task.index, created_at, responded_at)
+                        .await
+                    {
+                        warn!(error = %e, "Failed to record the task latency in the task store");
+                    }
+                }
+                // Tasks of the same block may follow, so only the blocks before it are done
+                if let Some(block_number) = new_task.block_number.filter(|_| !retrying) {
+                    if let Err(e) = store.set_next_block(block_number).await {
+                        warn!(error = %e, "Failed to advance the task store cursor");
+                    }
+                }
+            }
+            if let Some(replica) = &replica {
+                replica.release(new_task.index).await;
+            }
+            if let Some(acks) = &acks {
+                acks.ack(new_task.index).await;
+            }
+            if let Some(cursor) = cursor.as_ref().filter(|_| !retrying) {
+                cursor.handled(&new_task);
+            }
+        }
+        feeder.abort();
+        // The next run queues the parked tasks before any new one
+        if let Some(spill) = &spill {
+            queue.close();
+            let mut parked = 0;
+            while let Ok(new_task) = queue.try_recv() {
+                if let Err(e) = spill.park(&new_task).await {
+                    warn!(error = %e, "Failed to spill the queued tasks to the task store");
+                    break;
+                }
+                parked += 1;
+            }
+            if parked > 0 {
+                info!(parked, "Spilled the queued tasks to the task store");
+                set_task_queue_depth(queue.len());
+            }
+        }
+        let dropped = queue.len();
+        if dropped > 0 {
+            warn!(dropped, "Discarding queued tasks on shutdown");
+            record_tasks_dropped(dropped);
+            set_task_queue_depth(0);
+        }
+        if let Some(store) = &store {
+            store.close().await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Move the tasks of `source` into `queue`, spilling them over to `spill` or else waiting
+/// while it is full
+async fn feed_queue(
+    mut source: Box<dyn TaskSource>,
+    queue: mpsc::Sender<NewTask>,
+    spill: Option<Arc<TaskSpill>>,
+) -> Result<(), OperatorError> {
+    if let Some(spill) = spill {
+        let feed = async {
+            let fed = async {
+                while let Some(new_task) = source.next_task().await? {
+                    if !spill.offer(&queue, new_task).await? {
+                        break;
+                    }
+                    set_task_queue_depth(queue.max_capacity() - queue.capacity());
+                }
+                Ok::<_, OperatorError>(())
+            }
+            .await;
+            spill.finish();
+            fed
+        };
+        let refill = async { Ok(spill.refill(&queue).await?) };
+        tokio::try_join!(feed, refill)?;
+        return Ok(());
+    }
+    while let Some(new_task) = source.next_task().await? {
+        let new_task = match queue.try_send(new_task) {
+            Ok(()) => None,
+            Err(TrySendError::Full(new_task)) => {
+                record_task_deferred();
+                Some(new_task)
+            }
+            Err(TrySendError::Closed(_)) => return Ok(()),
+        };
+        if let Some(new_task) = new_task {
+            if queue.send(new_task).await.is_err() {
+                return Ok(());
+            }
+        }
+        set_task_queue_depth(queue.max_capacity() - queue.capacity());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use alloy::signers::local::PrivateKeySigner;
+    use async_trait::async_trait;
+    use swap_manager_utils::SwapManager::ISwapManager::Task;
+
+    use super::*;
+    use crate::health::SourceHealth;
+    use crate::task_listener::NewTask;
+    use crate::task_responder::TaskResponse;
+    use crate::task_store::SqliteTaskStore;
+
+    #[derive(Debug, Default)]
+    struct ReplaySource {
+        tasks: VecDeque<NewTask>,
+        health: Arc<SourceHealth>,
+    }
+
+    #[async_trait]
+    impl TaskSource for ReplaySource {
+        async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+            Ok(self.tasks.pop_front())
+        }
+
+        fn health(&self) -> Arc<SourceHealth> {
+            self.health.clone()
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingResponder {
+        responded: Arc<Mutex<Vec<u32>>>,
+    }
+
+    #[async_trait]
+    impl TaskResponder for RecordingResponder {
+        fn name(&self) -> &'static str {
+            "record"
+        }
+
+        async fn respond(&self, new_task: &NewTask) -> Result<TaskResponse, OperatorError> {
+            self.responded.lock().unwrap().push(new_task.index);
+            Ok(TaskResponse {
+                outcome: TaskOutcome::Responded,
+                tx_hash: None,
+                block_number: None,
+                gas_used: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_hands_every_task_to_the_responder() {
+        let tasks = (0..3)
+            .map(|index| NewTask {
+                index,
+                task: Task {
+                    name: format!("Task{index}"),
+                    taskCreatedBlock: 1,
+                },
+                block_number: Some(1),
+                block_hash: None,
+                transaction_hash: None,
+            })
+            .collect();
+        let responder = RecordingResponder::default();
+        let responded = responder.responded.clone();
+        let operator = Operator::builder()
+            .rpc_url("http://localhost:8545")
+            .signer(OperatorSigner::new(PrivateKeySigner::random()))
+            .swap_manager_address(Address::ZERO)
+            .task_source(ReplaySource {
+                tasks,
+                ..Default::default()
+            })
+            .task_responder(responder)
+            .build()
+            .unwrap();
+        let control = operator.control();
+
+        operator.run().await.unwrap();
+        assert_eq!(*responded.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(control.outcome_counts(), vec![(TaskOutcome::Responded, 3)]);
+    }
+
+    #[derive(Debug, Default)]
+    struct FailingResponder {
+        attempts: Arc<Mutex<Vec<u32>>>,
+    }
+
+    #[async_trait]
+    impl TaskResponder for FailingResponder {
+        fn name(&self) -> &'static str {
+            "fail"
+        }
+
+        async fn respond(&self, new_task: &NewTask) -> Result<TaskResponse, OperatorError> {
+            self.attempts.lock().unwrap().push(new_task.index);
+            Err(ConfigError::Invalid("always fails".to_string()).into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_dead_letters_a_task_after_max_attempts() {
+        let responder = FailingResponder::default();
+        let attempts = responder.attempts.clone();
+        let operator = Operator::builder()
+            .rpc_url("http://localhost:8545")
+            .signer(OperatorSigner::new(PrivateKeySigner::random()))
+            .swap_manager_address(Address::ZERO)
+            .task_source(ReplaySource {
+                tasks: VecDeque::from([NewTask {
+                    index: 7,
+                    task: Task {
+                        name: "Task7".to_string(),
+                        taskCreatedBlock: 1,
+                    },
+                    block_number: None,
+                    block_hash: None,
+                    transaction_hash: None,
+                }]),
+                ..Default::default()
+            })
+            .task_responder(responder)
+            .retry(RetryConfig {
+                max_attempts: 1,
+                ..RetryConfig::default()
+            })
+            .dead_letter(DeadLetterConfig {
+                max_attempts: 2,
+                retry_delay_secs: 0,
+                ..DeadLetterConfig::default()
+            })
+            .build()
+            .unwrap();
+        let control = operator.control();
+
+        operator.run().await.unwrap();
+        assert_eq!(*attempts.lock().unwrap(), vec![7, 7]);
+        assert_eq!(
+            control.outcome_counts(),
+            vec![(TaskOutcome::Failed, 1), (TaskOutcome::DeadLetter, 1)]
+        );
+    }
+
+    /// Fails at task `failing` and stops the run once task `last` is answered
+    #[derive(Debug)]
+    struct FlakyResponder {
+        failing: Option<u32>,
+        last: u32,
+        shutdown: Shutdown,
+        responded: Arc<Mutex<Vec<u32>>>,
+    }
+
+    #[async_trait]
+    impl TaskResponder for FlakyResponder {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn respond(&self, new_task: &NewTask) -> Result<TaskResponse, OperatorError> {
+            if self.failing == Some(new_task.index) {
+                return Err(ConfigError::Invalid("fails".to_string()).into());
+            }
+            self.responded.lock().unwrap().push(new_task.index);
+            if new_task.index == self.last {
+                self.shutdown.trigger();
+            }
+            Ok(TaskResponse {
+                outcome: TaskOutcome::Responded,
+                tx_hash: None,
+                block_number: None,
+                gas_used: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_answers_a_task_retrying_when_stopped_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("service-retry-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let task = |index: u32, block_number: u64| NewTask {
+            index,
+            task: Task {
+                name: format!("Task{index}"),
+                taskCreatedBlock: block_number as u32,
+            },
+            block_number: Some(block_number),
+            block_hash: None,
+            transaction_hash: None,
+        };
+        let responded = Arc::new(Mutex::new(Vec::new()));
+        let run = |tasks: Vec<NewTask>, failing: Option<u32>, last: u32| {
+            let responded = responded.clone();
+            let path = path.clone();
+            async move {
+                let store = SqliteTaskStore::open(&path).await.unwrap();
+                let shutdown = Shutdown::default();
+                let operator = Operator::builder()
+                    .rpc_url("http://localhost:8545")
+                    .signer(OperatorSigner::new(PrivateKeySigner::random()))
+                    .swap_manager_address(Address::ZERO)
+                    .task_store(Arc::new(store.clone()))
+                    .task_source(ReplaySource {
+                        tasks: tasks.into(),
+                        ..Default::default()
+                    })
+                    .task_responder(FlakyResponder {
+                        failing,
+                        last,
+                        shutdown: shutdown.clone(),
+                        responded,
+                    })
+                    .retry(RetryConfig {
+                        max_attempts: 1,
+                        ..RetryConfig::default()
+                    })
+                    .dead_letter(DeadLetterConfig {
+                        retry_delay_secs: 3_600,
+                        ..DeadLetterConfig::default()
+                    })
+                    .shutdown(shutdown)
+                    .build()
+                    .unwrap();
+                operator.run().await.unwrap();
+            }
+        };
+
+        // Task 7 fails and waits an hour for its retry, the run stops after task 8
+        run(vec![task(7, 5), task(8, 6)], Some(7), 8).await;
+        assert_eq!(*responded.lock().unwrap(), vec![8]);
+        let store = SqliteTaskStore::open(&path).await.unwrap();
+        let stored = store.task(7).await.unwrap().unwrap();
+        assert_eq!(stored.outcome, Some(TaskOutcome::Failed));
+        store.close().await;
+
+        // The restarted operator attempts it again although no source delivers it
+        run(Vec::new(), None, 7).await;
+        assert_eq!(*responded.lock().unwrap(), vec![8, 7]);
+        let store = SqliteTaskStore::open(&path).await.unwrap();
+        let stored = store.task(7).await.unwrap().unwrap();
+        assert_eq!(stored.outcome, Some(TaskOutcome::Responded));
+        store.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_build_requires_the_signer() {
+        let error = Operator::builder()
+            .rpc_url("http://localhost:8545")
+            .ws_url("ws://localhost:8545")
+            .swap_manager_address(Address::ZERO)
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("signer is required"), "{error}");
+    }
+
+    #[test]
+    fn test_build_rejects_an_invalid_rpc_url() {
+        let error = Operator::builder()
+            .rpc_url("not a url")
+            .ws_url("ws://localhost:8545")
+            .signer(OperatorSigner::new(PrivateKeySigner::random()))
+            .swap_manager_address(Address::ZERO)
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid rpc url"), "{error}");
+    }
+}
@@ -0,0 +1,200 @@
+use std::{fmt, sync::Arc};
+
+use alloy::{
+    primitives::{Address, B256},
+    rpc::client::RpcClient,
+};
+use eigensdk::common::SdkProvider;
+use reqwest::Url;
+
+use crate::audit::{AuditLog, SignedPayload};
+use crate::budget::{BudgetReservation, SpendBudget};
+use crate::config::OperatorConfig;
+use crate::control::TaskRecord;
+use crate::costs::TxCosts;
+use crate::ens::{AddressBook, NamedAddress};
+use crate::error::{ConfigError, OperatorError};
+use crate::event_bus::EventPublisher;
+use crate::gas::GasConfig;
+use crate::rpc_pool::RpcStack;
+use crate::webhook::{Notifications, WebhookEvent};
+
+/// Components an operator shares between its tasks: the RPC stack, the notifications, the
+/// audit log, the event bus, the spend budget, the gas limit multiplier, the costs of the
+/// mined transactions and the names of the known addresses.
+///
+/// An operator gets them through [`OperatorBuilder::services`](crate::service::OperatorBuilder::services),
+/// so that operators embedded in one process each have their own unless they are handed
+/// clones of the same. The default one calls the RPC endpoints directly and records,
+/// publishes, notifies and caps nothing
+#[derive(Debug, Clone)]
+pub struct Services {
+    rpc: RpcStack,
+    notifications: Notifications,
+    audit_log: Option<Arc<AuditLog>>,
+    events: Option<EventPublisher>,
+    spend_budget: Option<Arc<SpendBudget>>,
+    gas_limit_multiplier: f64,
+    tx_costs: Arc<TxCosts>,
+    names: Arc<AddressBook>,
+}
+
+impl Default for Services {
+    fn default() -> Self {
+        Self {
+            rpc: RpcStack::default(),
+            notifications: Notifications::default(),
+            audit_log: None,
+            events: None,
+            spend_budget: None,
+            gas_limit_multiplier: GasConfig::default().gas_limit_multiplier,
+            tx_costs: Arc::default(),
+            names: Arc::default(),
+        }
+    }
+}
+
+impl Services {
+    /// Components set in `config`. Opens the audit log, the RPC capture and the budget state
+    /// file, and starts publishing on the event bus, so it must be called on a runtime
+    pub fn from_config(config: &OperatorConfig) -> Result<Self, OperatorError> {
+        let notifications = Notifications::new(&config.webhooks, &config.alerts, &config.retry);
+        let spend_budget = SpendBudget::from_config(
+            &config.budget,
+            config.rpc.network.as_deref(),
+            notifications.clone(),
+        )?;
+        Ok(Self {
+            rpc: RpcStack::from_config(config)?,
+            notifications,
+            audit_log: AuditLog::from_config(&config.audit)?.map(Arc::new),
+            events: EventPublisher::start(&config.event_bus, &config.retry),
+            spend_budget: spend_budget.map(Arc::new),
+            gas_limit_multiplier: config.gas.gas_limit_multiplier,
+            tx_costs: Arc::default(),
+            names: Arc::default(),
+        })
+    }
+
+    /// Components calling the chain through `rpc` and recording, publishing, notifying and
+    /// capping nothing
+    pub fn rpc_only(rpc: RpcStack) -> Self {
+        Self {
+            rpc,
+            ..Self::default()
+        }
+    }
+
+    /// Layers the RPC clients go through
+    pub fn rpc(&self) -> &RpcStack {
+        &self.rpc
+    }
+
+    /// Client sending to `url` through the RPC stack
+    pub fn rpc_client(&self, url: Url) -> RpcClient {
+        self.rpc.client(url)
+    }
+
+    /// Read only provider of `rpc_url` going through the RPC stack
+    pub fn http_provider(&self, rpc_url: &str) -> Result<SdkProvider, ConfigError> {
+        self.rpc.http_provider(rpc_url)
+    }
+
+    /// Webhooks and chat channels the events are sent to
+    pub fn notifications(&self) -> &Notifications {
+        &self.notifications
+    }
+
+    /// Deliver `event` in the background, see [`Notifications::notify`]
+    pub(crate) fn notify(&self, event: WebhookEvent) {
+        self.notifications.notify(event);
+    }
+
+    /// Notify that a transaction calling `method` failed with `error`
+    pub(crate) fn notify_tx_failure(&self, method: &'static str, error: &impl fmt::Display) {
+        self.notifications.notify_tx_failure(method, error);
+    }
+
+    /// Record in the audit log that `key_id` signed `digest`, on behalf of task `task_index`
+    /// if set
+    pub(crate) fn audit_signature(
+        &self,
+        key_id: Address,
+        payload: SignedPayload,
+        task_index: Option<u32>,
+        digest: B256,
+    ) {
+        if let Some(log) = &self.audit_log {
+            log.record_signature(key_id, payload, task_index, digest);
+        }
+    }
+
+    /// Record in the audit log that `key_id` sent the `method` transaction `tx_hash`
+    pub(crate) fn audit_transaction(
+        &self,
+        key_id: Address,
+        method: &str,
+        nonce: Option<u64>,
+        tx_hash: B256,
+    ) {
+        if let Some(log) = &self.audit_log {
+            log.record_transaction(key_id, method, nonce, tx_hash);
+        }
+    }
+
+    /// Publish the lifecycle event of `record` handled by `source` on the event bus, if it
+    /// is one
+    pub(crate) fn publish_record(&self, source: Address, record: &TaskRecord) {
+        if let Some(events) = &self.events {
+            events.publish_record(source, record);
+        }
+    }
+
+    /// Publish on the event bus that the responses of `source` to `task_indices` are
+    /// confirmed
+    pub(crate) fn publish_confirmed(&self, source: Address, task_indices: &[u32]) {
+        if let Some(events) = &self.events {
+            events.publish_confirmed(source, task_indices);
+        }
+    }
+
+    /// Multiplier padding the gas estimates
+    pub fn gas_limit_multiplier(&self) -> f64 {
+        self.gas_limit_multiplier
+    }
+
+    /// Wait until the spend budget lets a `method` transaction through
+    pub async fn wait_for_budget(&self, method: &'static str) {
+        if let Some(budget) = &self.spend_budget {
+            budget.acquire(method).await;
+        }
+    }
+
+    /// Wait until the spend budget lets a `method` transaction through and reserve the
+    /// `fees_wei` it may pay at most, `None` when nothing is capped
+    pub async fn reserve_budget(
+        &self,
+        method: &'static str,
+        fees_wei: u128,
+    ) -> Option<BudgetReservation> {
+        match &self.spend_budget {
+            Some(budget) => Some(budget.reserve(method, fees_wei).await),
+            None => None,
+        }
+    }
+
+    /// Gas and fees of the transactions mined so far
+    pub fn tx_costs(&self) -> &TxCosts {
+        &self.tx_costs
+    }
+
+    /// ENS names resolved at startup and names of the known addresses
+    pub fn names(&self) -> &AddressBook {
+        &self.names
+    }
+
+    /// `address` as shown in the logs, see [`AddressBook::named`]
+    pub fn named(&self, address: Address) -> NamedAddress {
+        self.names.named(address)
+    }
+}
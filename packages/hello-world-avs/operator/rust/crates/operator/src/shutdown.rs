@@ -0,0 +1,149 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::watch, task::JoinSet, time};
+use tracing::{info, warn};
+
+use crate::control::ControlState;
+use crate::costs::TxCosts;
+
+/// Set once the process is asked to stop, by SIGINT, SIGTERM or [`Shutdown::trigger`]
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    requested: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            requested: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Shutdown triggered by the first SIGINT or SIGTERM the process receives
+    pub fn on_signal() -> Self {
+        let shutdown = Self::default();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            info!(signal, "Shutting down, no new work is accepted");
+            trigger.trigger();
+        });
+        shutdown
+    }
+
+    /// Ask everything watching this shutdown to stop
+    pub fn trigger(&self) {
+        self.requested.send_replace(true);
+    }
+
+    /// Whether the shutdown was triggered
+    pub fn is_triggered(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Return once the shutdown is triggered
+    pub async fn triggered(&self) {
+        let mut requested = self.requested.subscribe();
+        // The sender lives as long as `self`, so this can't fail
+        let _ = requested.wait_for(|requested| *requested).await;
+    }
+}
+
+/// Name of the first SIGINT or SIGTERM received
+pub async fn wait_for_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                warn!(error = %e, "Failed to listen for SIGTERM, only SIGINT stops the process");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "ctrl-c"
+    }
+}
+
+/// Wait for the tasks of `in_flight` to finish, for at most `timeout` once `shutdown` is
+/// triggered, then abort the remaining ones and return how many were abandoned
+pub async fn drain<T: 'static>(
+    in_flight: &mut JoinSet<T>,
+    shutdown: &Shutdown,
+    timeout: Duration,
+) -> usize {
+    let finished = async { while in_flight.join_next().await.is_some() {} };
+    let deadline = async {
+        shutdown.triggered().await;
+        time::sleep(timeout).await;
+    };
+    tokio::select! {
+        _ = finished => return 0,
+        _ = deadline => {}
+    }
+    let abandoned = in_flight.len();
+    warn!(
+        abandoned,
+        timeout_secs = timeout.as_secs(),
+        "Work still in flight after the shutdown timeout, abandoning it"
+    );
+    in_flight.abort_all();
+    while in_flight.join_next().await.is_some() {}
+    abandoned
+}
+
+/// Log what the process handled before exiting
+pub fn log_summary(control: &ControlState, tx_costs: &TxCosts, abandoned: usize) {
+    let status = control.status();
+    let outcomes = control
+        .outcome_counts()
+        .iter()
+        .map(|(outcome, count)| format!("{}={count}", outcome.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    info!(
+        uptime_secs = status.uptime_secs,
+        tasks_seen = status.tasks_seen,
+        tasks_completed = status.tasks_completed,
+        abandoned,
+        %outcomes,
+        "Shutdown complete"
+    );
+    tx_costs.log_summary();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_abandons_work_past_the_timeout() {
+        let shutdown = Shutdown::default();
+        let mut in_flight = JoinSet::new();
+        in_flight.spawn(async {});
+        assert_eq!(
+            drain(&mut in_flight, &shutdown, Duration::from_millis(10)).await,
+            0
+        );
+
+        in_flight.spawn(async {});
+        in_flight.spawn(std::future::pending::<()>());
+        shutdown.trigger();
+        assert!(shutdown.is_triggered());
+        assert_eq!(
+            drain(&mut in_flight, &shutdown, Duration::from_millis(10)).await,
+            1
+        );
+        assert!(in_flight.is_empty());
+    }
+}
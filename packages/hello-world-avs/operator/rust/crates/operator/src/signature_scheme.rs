@@ -0,0 +1,146 @@
+use alloy::primitives::{eip191_hash_message, Bytes, B256, U256};
+use ark_bn254::{Fq, G1Affine};
+use ark_ff::{BigInt, PrimeField};
+use clap::ValueEnum;
+use eigensdk::crypto_bls::BlsG2Point;
+use eigensdk::crypto_bn254::utils::{map_to_curve, verify_message};
+use serde::Deserialize;
+
+use crate::bls::BlsKey;
+
+/// Bytes of an encoded BLS signature, the `(uint256 X, uint256 Y)` G1 point
+pub const BLS_SIGNATURE_LEN: usize = 64;
+
+/// How the operator signs its task responses, picked to match what the service manager
+/// verifies
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// secp256k1 signature of the EIP-191 digest of the response hash, checked by an ECDSA
+    /// stake registry
+    #[default]
+    Ecdsa,
+    /// BN254 signature of the response hash mapped to G1, checked by a BLS signature checker
+    Bls,
+}
+
+impl SignatureScheme {
+    /// Parse the `signatureScheme` of a deployment
+    pub fn parse(value: &str) -> Result<Self, String> {
+        <Self as ValueEnum>::from_str(value, true)
+            .map_err(|_| format!("unknown signature scheme {value:?}, expected ecdsa or bls"))
+    }
+}
+
+/// Digest an ECDSA response signature is made over, the EIP-191 hash of `response_hash`
+pub fn ecdsa_digest(response_hash: B256) -> B256 {
+    eip191_hash_message(response_hash)
+}
+
+/// Point of G1 a BLS response signature is made over, `response_hash` mapped to the curve
+pub fn bls_message_point(response_hash: B256) -> G1Affine {
+    map_to_curve(&response_hash.0)
+}
+
+/// Sign `response_hash` with the BLS `key`, encoded as [`encode_bls_signature`] does
+pub fn sign_bls(key: &BlsKey, response_hash: B256) -> Bytes {
+    let signature = key.key_pair().sign_message(&response_hash.0);
+    encode_bls_signature(&signature.g1_point().g1())
+}
+
+/// The G1 point `signature` ABI encoded as `(uint256 X, uint256 Y)`
+pub fn encode_bls_signature(signature: &G1Affine) -> Bytes {
+    let coordinate = |value: Fq| U256::from_limbs(value.into_bigint().0);
+    let mut encoded = Vec::with_capacity(BLS_SIGNATURE_LEN);
+    encoded.extend_from_slice(&coordinate(signature.x).to_be_bytes::<32>());
+    encoded.extend_from_slice(&coordinate(signature.y).to_be_bytes::<32>());
+    encoded.into()
+}
+
+/// The G1 point of an encoded BLS signature, `None` unless it is a point of the subgroup
+pub fn decode_bls_signature(signature: &[u8]) -> Option<G1Affine> {
+    if signature.len() != BLS_SIGNATURE_LEN {
+        return None;
+    }
+    let coordinate = |bytes: &[u8]| {
+        let value = U256::from_be_slice(bytes);
+        Fq::from_bigint(BigInt(value.into_limbs()))
+    };
+    let point =
+        G1Affine::new_unchecked(coordinate(&signature[..32])?, coordinate(&signature[32..])?);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+/// Whether the encoded BLS `signature` of `response_hash` was made by `public_key`
+pub fn verify_bls(public_key: &BlsG2Point, response_hash: B256, signature: &[u8]) -> bool {
+    decode_bls_signature(signature)
+        .is_some_and(|signature| verify_message(public_key.g2(), &response_hash.0, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    use super::*;
+    use crate::task_responder::task_response_hash;
+
+    /// First anvil dev account
+    const ECDSA_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ECDSA_RESPONSE_HASH: &str =
+        "0x8e8fcc4b9a6bfef262eb097095d8cedd1fb4409c16b4e39b5e1db78d285566e3";
+    const ECDSA_DIGEST: &str = "0xc727f504e21eed4bc09aebb39e1c369669f803b89d2128209248b2714ba5ea7d";
+    const ECDSA_SIGNATURE: &str = "0x3b9cab04b3286cd4bf7d92d1fd841adebc25877c0193e91b33946e465cd6fb9c06ccd3743739495d6f58198ecab32a207fa26fa8f2cdbb80f120b760d3d8d8461b";
+    const BLS_SIGNATURE: &str = "0x06a682e2a36ff4eaa9791701ea09c380e281357e67334b8c67a457b914e65dff21976740864974651089ea8c5ac5ebd09361202f63cb01f95a52d06d15e85f0f";
+
+    #[test]
+    fn test_ecdsa_golden_vector() {
+        let response_hash = task_response_hash("HelloEigen");
+        assert_eq!(response_hash, B256::from_str(ECDSA_RESPONSE_HASH).unwrap());
+        assert_eq!(
+            ecdsa_digest(response_hash),
+            B256::from_str(ECDSA_DIGEST).unwrap()
+        );
+        let signer = PrivateKeySigner::from_str(ECDSA_KEY).unwrap();
+        let signature = signer.sign_message_sync(response_hash.as_slice()).unwrap();
+        assert_eq!(
+            Bytes::from(signature.as_bytes()),
+            Bytes::from_str(ECDSA_SIGNATURE).unwrap()
+        );
+        assert_eq!(
+            signature
+                .recover_address_from_prehash(&ecdsa_digest(response_hash))
+                .unwrap(),
+            signer.address()
+        );
+    }
+
+    #[test]
+    fn test_bls_golden_vector() {
+        let key = BlsKey::from_decimal("1337").unwrap();
+        let response_hash = task_response_hash("HelloEigen");
+        let signature = sign_bls(&key, response_hash);
+        assert_eq!(signature, Bytes::from_str(BLS_SIGNATURE).unwrap());
+        assert_eq!(
+            decode_bls_signature(&signature),
+            Some(
+                key.key_pair()
+                    .sign_message(&response_hash.0)
+                    .g1_point()
+                    .g1()
+            )
+        );
+
+        let public_key = key.key_pair().public_key_g2();
+        assert!(verify_bls(&public_key, response_hash, &signature));
+        assert!(!verify_bls(
+            &public_key,
+            task_response_hash("HelloEigen2"),
+            &signature
+        ));
+        assert!(!verify_bls(&public_key, response_hash, &signature[1..]));
+        assert_eq!(SignatureScheme::parse("BLS"), Ok(SignatureScheme::Bls));
+        assert!(SignatureScheme::parse("schnorr").is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use alloy::{
+    consensus::SignableTransaction,
+    network::{EthereumWallet, TxSigner},
+    primitives::{eip191_hash_message, Address, B256},
+    providers::ProviderBuilder,
+    signers::{
+        aws::AwsSigner, ledger::HDPath, local::PrivateKeySigner, Result as SignerResult, Signature,
+        Signer,
+    },
+};
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region};
+use clap::ValueEnum;
+use eigensdk::common::SdkSigner;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::audit::SignedPayload;
+use crate::error::SignerError;
+use crate::ledger::LedgerDevice;
+use crate::os_keychain::{KeychainEntry, DEFAULT_KEYCHAIN_ACCOUNT, DEFAULT_KEYCHAIN_SERVICE};
+use crate::remote_signer::{RemoteSigner, RemoteSignerTls};
+use crate::services::Services;
+
+/// Kind of key store the operator signs with
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerKind {
+    /// Hex key from `private_key` or `private_key_file`
+    #[default]
+    PrivateKey,
+    /// Encrypted web3 JSON keystore
+    Keystore,
+    /// secp256k1 key held in AWS KMS
+    AwsKms,
+    /// Key held by a Web3Signer compatible signing host
+    Web3signer,
+    /// Key on a Ledger device, every signature is approved on the device
+    Ledger,
+    /// Hex key in the macOS Keychain or the Windows Credential Manager
+    #[serde(rename = "os-keychain", alias = "os_keychain")]
+    OsKeychain,
+}
+
+impl SignerKind {
+    /// Whether each signature waits for a person at the device
+    pub fn is_hardware(self) -> bool {
+        self == Self::Ledger
+    }
+}
+
+/// Signer settings, the `[signer]` config section
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignerConfig {
+    /// Backend holding the key (`SIGNER_BACKEND`)
+    pub backend: SignerKind,
+    /// Path of the JSON keystore (`KEYSTORE_PATH`)
+    pub keystore: Option<PathBuf>,
+    /// File holding the keystore password, prompted on the terminal if unset (`KEYSTORE_PASSWORD_FILE`)
+    pub password_file: Option<PathBuf>,
+    /// Id, ARN or alias of the KMS key (`AWS_KMS_KEY_ID`)
+    pub kms_key_id: Option<String>,
+    /// Region of the KMS key, defaults to the AWS SDK region chain (`AWS_REGION`)
+    pub kms_region: Option<String>,
+    /// Custom KMS endpoint, e.g. LocalStack (`AWS_KMS_ENDPOINT`)
+    pub kms_endpoint: Option<String>,
+    /// URL of the Web3Signer host (`WEB3SIGNER_URL`)
+    pub web3signer_url: Option<String>,
+    /// Address of the key on the Web3Signer host (`WEB3SIGNER_ADDRESS`)
+    pub web3signer_address: Option<Address>,
+    /// PEM CA bundle of the Web3Signer host
+    pub tls_ca_cert: Option<PathBuf>,
+    /// PEM client certificate for mutual TLS with the Web3Signer host
+    pub tls_client_cert: Option<PathBuf>,
+    /// PEM private key of `tls_client_cert`
+    pub tls_client_key: Option<PathBuf>,
+    /// HD derivation path of the Ledger account, overrides `ledger_account`
+    /// (`LEDGER_HD_PATH`)
+    pub ledger_hd_path: Option<String>,
+    /// Index of the Ledger Live account `m/44'/60'/<index>'/0/0` (`LEDGER_ACCOUNT`)
+    pub ledger_account: usize,
+    /// Keychain service holding the key of the `os-keychain` backend, `swap-manager-avs`
+    /// if unset (`KEYCHAIN_SERVICE`)
+    pub keychain_service: Option<String>,
+    /// Keychain account holding the key of the `os-keychain` backend, `operator` if unset
+    /// (`KEYCHAIN_ACCOUNT`)
+    pub keychain_account: Option<String>,
+    /// eigensdk encrypted BLS keystore (`BLS_KEYSTORE_PATH`)
+    pub bls_keystore: Option<PathBuf>,
+    /// File holding the BLS keystore password, prompted if unset (`BLS_KEYSTORE_PASSWORD_FILE`)
+    pub bls_password_file: Option<PathBuf>,
+    /// EigenLayer operator the key signs for once `operator rotate-key` made it the
+    /// operator's signing key, the key's own address if unset (`OPERATOR_ADDRESS`)
+    pub operator_address: Option<Address>,
+    /// Keystore password fetched by `secrets.keystore_password`
+    #[serde(skip)]
+    pub password: Option<String>,
+    /// BLS keystore password fetched by `secrets.bls_password`
+    #[serde(skip)]
+    pub bls_password: Option<String>,
+}
+
+impl SignerConfig {
+    /// Keychain entry of the `os-keychain` backend
+    pub fn keychain_entry(&self) -> KeychainEntry {
+        KeychainEntry::new(
+            self.keychain_service
+                .as_deref()
+                .unwrap_or(DEFAULT_KEYCHAIN_SERVICE),
+            self.keychain_account
+                .as_deref()
+                .unwrap_or(DEFAULT_KEYCHAIN_ACCOUNT),
+        )
+    }
+}
+
+/// Where the keystore password comes from
+#[derive(Clone, PartialEq, Eq)]
+pub enum KeystorePassword {
+    /// Ask on the terminal without echoing
+    Prompt,
+    /// Read the first line of a file
+    File(PathBuf),
+    /// Fetched from the secrets backend
+    Value(String),
+}
+
+impl fmt::Debug for KeystorePassword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prompt => f.write_str("Prompt"),
+            Self::File(path) => f.debug_tuple("File").field(path).finish(),
+            Self::Value(_) => f.write_str("Value(<redacted>)"),
+        }
+    }
+}
+
+/// Resolved signing key source, see [`crate::config::OperatorConfig::signer_backend`]
+#[derive(Clone)]
+pub enum SignerBackend {
+    /// Hex encoded private key
+    PrivateKey(String),
+    /// Encrypted web3 JSON keystore
+    Keystore {
+        /// Path of the keystore file
+        path: PathBuf,
+        /// Source of the decryption password
+        password: KeystorePassword,
+    },
+    /// Asymmetric `ECC_SECG_P256K1` key in AWS KMS, the key never leaves KMS
+    AwsKms {
+        /// Id, ARN or alias of the key
+        key_id: String,
+        /// Region override
+        region: Option<String>,
+        /// Endpoint override
+        endpoint: Option<String>,
+    },
+    /// Web3Signer compatible host, the key never leaves it
+    Web3signer {
+        /// Base URL of the host
+        url: Url,
+        /// Address of the key to sign with
+        address: Address,
+        /// TLS material for the connection
+        tls: RemoteSignerTls,
+    },
+    /// Ledger device plugged into this host, the key never leaves it
+    Ledger {
+        /// HD derivation path of the account
+        path: HDPath,
+    },
+    /// Hex encoded private key kept in the OS keychain
+    OsKeychain(KeychainEntry),
+}
+
+impl fmt::Debug for SignerBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PrivateKey(_) => f.write_str("PrivateKey(<redacted>)"),
+            Self::Keystore { path, password } => f
+                .debug_struct("Keystore")
+                .field("path", path)
+                .field("password", password)
+                .finish(),
+            Self::AwsKms {
+                key_id,
+                region,
+                endpoint,
+            } => f
+                .debug_struct("AwsKms")
+                .field("key_id", key_id)
+                .field("region", region)
+                .field("endpoint", endpoint)
+                .finish(),
+            Self::Web3signer { url, address, tls } => f
+                .debug_struct("Web3signer")
+                .field("url", &url.as_str())
+                .field("address", address)
+                .field("tls", tls)
+                .finish(),
+            Self::Ledger { path } => f
+                .debug_struct("Ledger")
+                .field("path", &path.to_string())
+                .finish(),
+            Self::OsKeychain(entry) => f.debug_tuple("OsKeychain").field(entry).finish(),
+        }
+    }
+}
+
+impl SignerBackend {
+    /// Unlock the key, prompting for the keystore password if needed
+    pub async fn load(&self) -> Result<OperatorSigner, SignerError> {
+        let signer = match self {
+            Self::PrivateKey(key) => PrivateKeySigner::from_str(key)
+                .map_err(|e| SignerError::Invalid(format!("invalid private key: {e}")))?,
+            Self::AwsKms {
+                key_id,
+                region,
+                endpoint,
+            } => {
+                let mut loader = aws_config::defaults(BehaviorVersion::latest());
+                if let Some(region) = region {
+                    loader = loader.region(Region::new(region.clone()));
+                }
+                if let Some(endpoint) = endpoint {
+                    loader = loader.endpoint_url(endpoint);
+                }
+                let client = aws_sdk_kms::Client::new(&loader.load().await);
+                // No chain id pinned, transactions are signed for the chain of the provider
+                let signer =
+                    AwsSigner::new(client, key_id.clone(), None)
+                        .await
+                        .map_err(|source| SignerError::Kms {
+                            key_id: key_id.clone(),
+                            source: Box::new(source),
+                        })?;
+                return Ok(OperatorSigner::new(signer));
+            }
+            Self::Web3signer { url, address, tls } => {
+                return Ok(OperatorSigner::new(RemoteSigner::new(
+                    url.clone(),
+                    *address,
+                    tls,
+                )?));
+            }
+            Self::Ledger { path } => {
+                return Ok(OperatorSigner::new(
+                    LedgerDevice::connect(path.clone()).await?,
+                ));
+            }
+            Self::OsKeychain(entry) => PrivateKeySigner::from_str(&entry.read()?).map_err(|e| {
+                SignerError::Invalid(format!(
+                    "invalid private key in the keychain account {} of {}: {e}",
+                    entry.account, entry.service
+                ))
+            })?,
+            Self::Keystore { path, password } => {
+                let password = read_password(password, path)?;
+                PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+                    SignerError::Decrypt {
+                        what: "keystore",
+                        path: path.clone(),
+                        source: e.into(),
+                    }
+                })?
+            }
+        };
+        Ok(OperatorSigner::new(signer))
+    }
+}
+
+/// Key loaded from a [`SignerBackend`], used for transactions and task signatures
+#[derive(Clone)]
+pub struct OperatorSigner {
+    signer: Arc<dyn Signer + Send + Sync>,
+    tx_signer: Arc<dyn TxSigner<Signature> + Send + Sync>,
+    services: Services,
+}
+
+impl fmt::Debug for OperatorSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperatorSigner")
+            .field("address", &self.address())
+            .finish_non_exhaustive()
+    }
+}
+
+impl OperatorSigner {
+    /// Wrap any signer that can sign both transactions and digests
+    pub fn new<S>(signer: S) -> Self
+    where
+        S: Signer + TxSigner<Signature> + Clone + Send + Sync + 'static,
+    {
+        Self {
+            signer: Arc::new(signer.clone()),
+            tx_signer: Arc::new(signer),
+            services: Services::default(),
+        }
+    }
+
+    /// Audit the signatures in the audit log of `services` and send through its RPC stack
+    pub fn services(mut self, services: Services) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Address of the key
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Wallet signing transactions with this key
+    pub fn wallet(&self) -> EthereumWallet {
+        EthereumWallet::from(AuditedTxSigner {
+            inner: self.tx_signer.clone(),
+            services: self.services.clone(),
+        })
+    }
+
+    /// Sign a 32 byte digest
+    pub async fn sign_hash(&self, hash: &B256) -> Result<Signature, SignerError> {
+        let signature = self.signer.sign_hash(hash).await?;
+        self.services
+            .audit_signature(self.address(), SignedPayload::Hash, None, *hash);
+        Ok(signature)
+    }
+
+    /// Sign `message` with the EIP-191 personal message prefix
+    pub async fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.sign_message_for(None, message).await
+    }
+
+    /// Sign `message` like [`Self::sign_message`], audited as signed for task `task_index`
+    pub async fn sign_task_message(
+        &self,
+        task_index: u32,
+        message: &[u8],
+    ) -> Result<Signature, SignerError> {
+        self.sign_message_for(Some(task_index), message).await
+    }
+
+    async fn sign_message_for(
+        &self,
+        task_index: Option<u32>,
+        message: &[u8],
+    ) -> Result<Signature, SignerError> {
+        let signature = self.signer.sign_message(message).await?;
+        let digest = eip191_hash_message(message);
+        self.services
+            .audit_signature(self.address(), SignedPayload::Message, task_index, digest);
+        Ok(signature)
+    }
+
+    /// HTTP provider sending transactions signed by this key
+    pub fn provider(&self, rpc_url: &str) -> Result<SdkSigner, SignerError> {
+        let url = Url::parse(rpc_url)
+            .map_err(|e| SignerError::Invalid(format!("invalid rpc url {rpc_url}: {e}")))?;
+        Ok(ProviderBuilder::new()
+            .wallet(self.wallet())
+            .on_client(self.services.rpc_client(url)))
+    }
+}
+
+/// Transaction signer of the wallet, recording the signing hash of every transaction in the
+/// audit log of `services`
+struct AuditedTxSigner {
+    inner: Arc<dyn TxSigner<Signature> + Send + Sync>,
+    services: Services,
+}
+
+#[async_trait]
+impl TxSigner<Signature> for AuditedTxSigner {
+    fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> SignerResult<Signature> {
+        let signature = self.inner.sign_transaction(tx).await?;
+        self.services.audit_signature(
+            self.address(),
+            SignedPayload::Transaction,
+            None,
+            tx.signature_hash(),
+        );
+        Ok(signature)
+    }
+}
+
+/// Resolve the password unlocking the keystore at `keystore`
+pub(crate) fn read_password(
+    password: &KeystorePassword,
+    keystore: &Path,
+) -> Result<String, SignerError> {
+    match password {
+        KeystorePassword::Prompt => {
+            rpassword::prompt_password(format!("Password for keystore {}: ", keystore.display()))
+                .map_err(SignerError::Prompt)
+        }
+        KeystorePassword::File(file) => read_password_file(file),
+        KeystorePassword::Value(password) => Ok(password.clone()),
+    }
+}
+
+fn read_password_file(path: &Path) -> Result<String, SignerError> {
+    let password = std::fs::read_to_string(path).map_err(|source| SignerError::Read {
+        what: "password file",
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(password.lines().next().unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keystore_backend_unlocks_with_password_file() {
+        let dir = std::env::temp_dir().join(format!("keystore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = PrivateKeySigner::random();
+        PrivateKeySigner::encrypt_keystore(
+            &dir,
+            &mut rand08::thread_rng(),
+            key.to_bytes(),
+            "hunter2",
+            Some("operator.json"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("password"), "hunter2\n").unwrap();
+
+        let backend = SignerBackend::Keystore {
+            path: dir.join("operator.json"),
+            password: KeystorePassword::File(dir.join("password")),
+        };
+        let signer = backend.load().await.unwrap();
+
+        assert_eq!(signer.address(), key.address());
+        assert!(format!("{:?}", SignerBackend::PrivateKey("0x01".into())).contains("redacted"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
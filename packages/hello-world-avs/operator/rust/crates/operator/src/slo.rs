@@ -0,0 +1,200 @@
+use std::{fmt, future::IntoFuture};
+
+use alloy::{eips::BlockNumberOrTag, providers::Provider};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::ChainError;
+use crate::prometheus::time_rpc;
+
+/// Latency of a task, from the block of its NewTaskCreated event to the block of its response
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskLatency {
+    /// Index of the task in the SwapManager
+    pub task_index: u32,
+    /// Unix timestamp of the block the task was created in
+    pub created_at: i64,
+    /// Seconds between the creation block and the response block
+    pub latency_secs: u64,
+}
+
+/// Unix timestamps of the blocks `created_block` and `response_block`
+pub async fn block_timestamps<P: Provider>(
+    provider: &P,
+    created_block: u64,
+    response_block: u64,
+) -> Result<(u64, u64), ChainError> {
+    let mut timestamps = [0; 2];
+    for (timestamp, block) in timestamps.iter_mut().zip([created_block, response_block]) {
+        *timestamp = time_rpc(
+            "eth_getBlockByNumber",
+            provider
+                .get_block_by_number(BlockNumberOrTag::Number(block))
+                .into_future(),
+        )
+        .await?
+        .ok_or_else(|| ChainError::Unexpected(format!("block {block} not found")))?
+        .header
+        .timestamp;
+    }
+    Ok((timestamps[0], timestamps[1]))
+}
+
+/// Output format of `operator slo-report`
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Aligned lines for a terminal
+    #[default]
+    Text,
+    /// A single JSON object
+    Json,
+    /// A header line and a line of values
+    Csv,
+}
+
+/// Latency statistics of the tasks created in a time window
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyReport {
+    /// Start of the window, unix seconds
+    pub from: i64,
+    /// End of the window, unix seconds, excluded
+    pub to: i64,
+    /// Tasks whose latency was measured
+    pub tasks: usize,
+    /// Median latency in seconds
+    pub p50_secs: Option<u64>,
+    /// 95th percentile latency in seconds
+    pub p95_secs: Option<u64>,
+    /// 99th percentile latency in seconds
+    pub p99_secs: Option<u64>,
+    /// Slowest response in seconds
+    pub max_secs: Option<u64>,
+    /// Average latency in seconds
+    pub mean_secs: Option<f64>,
+    /// Latency objective the responses are compared to
+    pub target_secs: Option<u64>,
+    /// Share of the responses within `target_secs`, in percent
+    pub within_target_percent: Option<f64>,
+}
+
+impl LatencyReport {
+    /// Statistics of `latencies` measured from `from` to `to`, compared to `target_secs` if set
+    pub fn new(from: i64, to: i64, latencies: &[TaskLatency], target_secs: Option<u64>) -> Self {
+        let mut secs: Vec<u64> = latencies.iter().map(|l| l.latency_secs).collect();
+        secs.sort_unstable();
+        let mean_secs =
+            (!secs.is_empty()).then(|| secs.iter().sum::<u64>() as f64 / secs.len() as f64);
+        let within_target_percent = target_secs.filter(|_| !secs.is_empty()).map(|target| {
+            let within = secs.iter().filter(|&&s| s <= target).count();
+            within as f64 * 100.0 / secs.len() as f64
+        });
+        Self {
+            from,
+            to,
+            tasks: secs.len(),
+            p50_secs: percentile(&secs, 50.0),
+            p95_secs: percentile(&secs, 95.0),
+            p99_secs: percentile(&secs, 99.0),
+            max_secs: secs.last().copied(),
+            mean_secs,
+            target_secs,
+            within_target_percent,
+        }
+    }
+
+    /// The report rendered as `format`
+    pub fn render(&self, format: ReportFormat) -> Result<String, serde_json::Error> {
+        match format {
+            ReportFormat::Text => Ok(self.to_string()),
+            ReportFormat::Json => serde_json::to_string_pretty(self),
+            ReportFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let opt = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        let float = |value: Option<f64>| value.map(|v| format!("{v:.2}")).unwrap_or_default();
+        format!(
+            "from,to,tasks,p50_secs,p95_secs,p99_secs,max_secs,mean_secs,target_secs,within_target_percent\n\
+             {},{},{},{},{},{},{},{},{},{}\n",
+            self.from,
+            self.to,
+            self.tasks,
+            opt(self.p50_secs),
+            opt(self.p95_secs),
+            opt(self.p99_secs),
+            opt(self.max_secs),
+            float(self.mean_secs),
+            opt(self.target_secs),
+            float(self.within_target_percent),
+        )
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = |secs: i64| {
+            chrono::DateTime::from_timestamp(secs, 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| secs.to_string())
+        };
+        writeln!(f, "Window   {} to {}", time(self.from), time(self.to))?;
+        writeln!(f, "Tasks    {}", self.tasks)?;
+        let Some(mean) = self.mean_secs else {
+            return writeln!(f, "No responded task with a measured latency in the window");
+        };
+        let secs = |value: Option<u64>| value.map(|v| format!("{v}s")).unwrap_or_default();
+        writeln!(f, "p50      {}", secs(self.p50_secs))?;
+        writeln!(f, "p95      {}", secs(self.p95_secs))?;
+        writeln!(f, "p99      {}", secs(self.p99_secs))?;
+        writeln!(f, "max      {}", secs(self.max_secs))?;
+        writeln!(f, "mean     {mean:.2}s")?;
+        if let (Some(target), Some(within)) = (self.target_secs, self.within_target_percent) {
+            writeln!(f, "Target   {within:.2}% within {target}s")?;
+        }
+        Ok(())
+    }
+}
+
+/// Nearest rank `p`-th percentile of the ascending `sorted`
+pub(crate) fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_percentiles_use_the_nearest_rank() {
+        let latencies: Vec<TaskLatency> = (1..=100)
+            .rev()
+            .map(|secs| TaskLatency {
+                task_index: secs as u32,
+                created_at: 1_000,
+                latency_secs: secs,
+            })
+            .collect();
+        let report = LatencyReport::new(0, 2_000, &latencies, Some(90));
+        assert_eq!(report.tasks, 100);
+        assert_eq!(report.p50_secs, Some(50));
+        assert_eq!(report.p95_secs, Some(95));
+        assert_eq!(report.p99_secs, Some(99));
+        assert_eq!(report.max_secs, Some(100));
+        assert_eq!(report.within_target_percent, Some(90.0));
+        assert!(report
+            .render(ReportFormat::Csv)
+            .unwrap()
+            .ends_with("0,2000,100,50,95,99,100,50.50,90,90.00\n"));
+
+        let empty = LatencyReport::new(0, 2_000, &[], Some(90));
+        assert_eq!(empty.p50_secs, None);
+        assert_eq!(empty.within_target_percent, None);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, B256},
+    providers::Provider,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::config::OperatorConfig;
+use crate::error::SnapshotError;
+use crate::services::Services;
+use crate::task_export::ExportedTask;
+use crate::task_source::BlockCursor;
+use crate::task_store::connect_task_store;
+
+/// Version of the snapshot layout, bumped when a restore couldn't read older tarballs
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Tarball entry holding the [`SnapshotManifest`]
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// Tarball entry holding the tasks of the task store
+const TASKS_ENTRY: &str = "tasks.json";
+
+/// Size of the header and data blocks of a tar archive
+const TAR_BLOCK: usize = 512;
+
+/// Nonces of the operator when the snapshot was taken
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceState {
+    /// Nonce of the next transaction once the mined ones are counted
+    pub mined: u64,
+    /// Nonce of the next transaction once the pending ones are counted too
+    pub pending: u64,
+}
+
+/// Everything a snapshot holds besides the tasks, `manifest.json` of the tarball
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotManifest {
+    /// Layout version, [`SNAPSHOT_VERSION`] when taken by this build
+    pub version: u32,
+    /// Unix time the snapshot was taken
+    pub created_at: i64,
+    /// Operator whose state it is
+    pub operator: Address,
+    /// SwapManager whose tasks the operator handled
+    pub swap_manager: Address,
+    /// [`config_fingerprint`] of the operator
+    pub config_fingerprint: B256,
+    /// Cursor of the task store, the first block whose tasks may not all be handled
+    pub next_block: Option<u64>,
+    /// Next block of `source.cursor_file`, if the polling source persisted one
+    pub cursor_block: Option<u64>,
+    /// Nonces of the operator
+    pub nonce: NonceState,
+}
+
+/// State of an operator, moved between hosts by `operator snapshot create` and `restore`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Where the operator stood
+    pub manifest: SnapshotManifest,
+    /// Every task of the task store
+    pub tasks: Vec<ExportedTask>,
+}
+
+/// Digest of the settings deciding which tasks the operator responds to and with what.
+///
+/// Paths, endpoints and listen addresses are left out since they're expected to change
+/// with the host.
+pub fn config_fingerprint(config: &OperatorConfig) -> B256 {
+    let settings = format!(
+        "chain_id={:?}\ndry_run={}\nresponse_percentage={}\nsignature_scheme={:?}\n\
+         dead_letter={:?}\nreorg={:?}\noracle={}:{}\nquoting={}\n",
+        config.chain_id,
+        config.dry_run,
+        config.operator.response_percentage,
+        config.operator.signature_scheme,
+        config.dead_letter,
+        config.reorg,
+        config.oracle.enabled,
+        config.oracle.max_deviation_bps,
+        config.quoting.enabled,
+    );
+    B256::from_slice(&Sha256::digest(settings.as_bytes()))
+}
+
+/// Mined and pending nonces of `operator`, read through `rpc_url` and the RPC stack of
+/// `services`
+pub async fn nonce_state(
+    rpc_url: &str,
+    services: &Services,
+    operator: Address,
+) -> Result<NonceState, SnapshotError> {
+    let provider = services.http_provider(rpc_url)?;
+    Ok(NonceState {
+        mined: provider.get_transaction_count(operator).latest().await?,
+        pending: provider.get_transaction_count(operator).pending().await?,
+    })
+}
+
+impl Snapshot {
+    /// Snapshot of the state `config` persists for `operator`, which should be stopped
+    /// so nothing changes underneath
+    pub async fn take(
+        config: &OperatorConfig,
+        services: &Services,
+        operator: Address,
+        swap_manager: Address,
+    ) -> Result<Self, SnapshotError> {
+        let store = connect_task_store(&config.store, operator)
+            .await?
+            .ok_or(SnapshotError::NoStore)?;
+        let tasks = store.export(i64::MIN, i64::MAX).await?;
+        let next_block = store.next_block().await?;
+        store.close().await;
+        let cursor_block = match &config.source.cursor_file {
+            Some(path) if path.exists() => Some(
+                BlockCursor::load(Some(path.clone()), 0)
+                    .map_err(Box::new)?
+                    .next_block(),
+            ),
+            _ => None,
+        };
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_VERSION,
+            created_at: now(),
+            operator,
+            swap_manager,
+            config_fingerprint: config_fingerprint(config),
+            next_block,
+            cursor_block,
+            nonce: nonce_state(&config.rpc_url, services, operator).await?,
+        };
+        Ok(Self { manifest, tasks })
+    }
+
+    /// Write the state into the task store and the cursor file of `config`, after
+    /// checking that it's the state of `operator` and that its transactions all landed.
+    ///
+    /// `force` restores despite a different config fingerprint or transactions still
+    /// pending. Returns the number of tasks restored.
+    pub async fn restore(
+        &self,
+        config: &OperatorConfig,
+        services: &Services,
+        operator: Address,
+        swap_manager: Address,
+        force: bool,
+    ) -> Result<u64, SnapshotError> {
+        let manifest = &self.manifest;
+        check_same("operator", manifest.operator, operator)?;
+        check_same("SwapManager", manifest.swap_manager, swap_manager)?;
+        if let Err(e) = check_same(
+            "config fingerprint",
+            manifest.config_fingerprint,
+            config_fingerprint(config),
+        ) {
+            if !force {
+                return Err(e);
+            }
+            warn!(error = %e, "Restoring a snapshot taken with other settings");
+        }
+        let nonce = nonce_state(&config.rpc_url, services, operator).await?;
+        if nonce.mined < manifest.nonce.pending {
+            let e = SnapshotError::InFlight {
+                mined: nonce.mined,
+                pending: manifest.nonce.pending,
+            };
+            if !force {
+                return Err(e);
+            }
+            warn!(error = %e, "Restoring a snapshot with transactions in flight");
+        }
+
+        let store = connect_task_store(&config.store, operator)
+            .await?
+            .ok_or(SnapshotError::NoStore)?;
+        let restored = store.import(&self.tasks).await?;
+        if let Some(next_block) = manifest.next_block {
+            store.set_next_block(next_block).await?;
+        }
+        store.close().await;
+        if let (Some(block), Some(path)) = (manifest.cursor_block, &config.source.cursor_file) {
+            let mut cursor = BlockCursor::load(Some(path.clone()), block).map_err(Box::new)?;
+            if cursor.next_block() <= block {
+                cursor.advance(block).map_err(Box::new)?;
+            }
+        }
+        info!(
+            tasks = restored,
+            next_block = manifest.next_block,
+            taken_at = manifest.created_at,
+            "Snapshot restored"
+        );
+        Ok(restored)
+    }
+
+    /// The snapshot as an uncompressed tarball of `manifest.json` and `tasks.json`
+    pub fn to_tar(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mtime = self.manifest.created_at.max(0) as u64;
+        let mut tar = Vec::new();
+        append_entry(
+            &mut tar,
+            MANIFEST_ENTRY,
+            &serde_json::to_vec_pretty(&self.manifest)?,
+            mtime,
+        );
+        append_entry(
+            &mut tar,
+            TASKS_ENTRY,
+            &serde_json::to_vec_pretty(&self.tasks)?,
+            mtime,
+        );
+        // Two zero blocks end the archive
+        tar.resize(tar.len() + 2 * TAR_BLOCK, 0);
+        Ok(tar)
+    }
+
+    /// Snapshot read back from a tarball written by [`Self::to_tar`]
+    pub fn from_tar(tar: &[u8]) -> Result<Self, SnapshotError> {
+        let mut entries = read_entries(tar)?;
+        let mut entry = |name: &str| {
+            entries
+                .remove(name)
+                .ok_or_else(|| SnapshotError::Malformed(format!("no {name} in the tarball")))
+        };
+        let manifest: SnapshotManifest = serde_json::from_slice(&entry(MANIFEST_ENTRY)?)?;
+        if manifest.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(manifest.version));
+        }
+        let tasks = serde_json::from_slice(&entry(TASKS_ENTRY)?)?;
+        Ok(Self { manifest, tasks })
+    }
+
+    /// Write the tarball to `path`
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        std::fs::write(path, self.to_tar()?).map_err(|source| SnapshotError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Read the tarball at `path`
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let tar = std::fs::read(path).map_err(|source| SnapshotError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_tar(&tar)
+    }
+}
+
+fn check_same<T: PartialEq + ToString>(
+    what: &'static str,
+    snapshot: T,
+    current: T,
+) -> Result<(), SnapshotError> {
+    if snapshot != current {
+        return Err(SnapshotError::Mismatch {
+            what,
+            snapshot: snapshot.to_string(),
+            current: current.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Append a regular file to a ustar archive
+fn append_entry(tar: &mut Vec<u8>, name: &str, data: &[u8], mtime: u64) {
+    let mut header = [0u8; TAR_BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o600);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], data.len() as u64);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|byte| u64::from(*byte)).sum();
+    write_octal(&mut header[148..155], checksum);
+    tar.extend_from_slice(&header);
+    tar.extend_from_slice(data);
+    tar.resize(tar.len().next_multiple_of(TAR_BLOCK), 0);
+}
+
+/// Zero padded octal number ending with a NUL, filling `field`
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    field[..digits].copy_from_slice(format!("{value:0digits$o}").as_bytes());
+    field[digits] = 0;
+}
+
+fn read_octal(field: &[u8]) -> Result<u64, SnapshotError> {
+    let digits = std::str::from_utf8(field)
+        .map_err(|_| SnapshotError::Malformed("non-ASCII tar header".to_string()))?
+        .trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(digits, 8)
+        .map_err(|_| SnapshotError::Malformed(format!("invalid tar number {digits:?}")))
+}
+
+/// Regular files of a ustar archive, by name
+fn read_entries(tar: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, SnapshotError> {
+    let mut entries = BTreeMap::new();
+    let mut offset = 0;
+    while let Some(header) = tar.get(offset..offset + TAR_BLOCK) {
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(entries);
+        }
+        let checksum = read_octal(&header[148..156])?;
+        let computed: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| match i {
+                148..=155 => u64::from(b' '),
+                _ => u64::from(*byte),
+            })
+            .sum();
+        if checksum != computed {
+            return Err(SnapshotError::Malformed(
+                "tar header checksum mismatch".to_string(),
+            ));
+        }
+        let name_len = header[..100]
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(100);
+        let name = String::from_utf8_lossy(&header[..name_len]).into_owned();
+        let size = read_octal(&header[124..136])? as usize;
+        let start = offset + TAR_BLOCK;
+        let data = tar
+            .get(start..start + size)
+            .ok_or_else(|| SnapshotError::Malformed(format!("{name} is truncated")))?;
+        if matches!(header[156], b'0' | 0) {
+            entries.insert(name, data.to_vec());
+        }
+        offset = start + size.next_multiple_of(TAR_BLOCK);
+    }
+    Err(SnapshotError::Malformed(
+        "the tarball ends without its end of archive blocks".to_string(),
+    ))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::task_store::{SqliteTaskStore, TaskStore};
+
+    #[tokio::test]
+    async fn test_snapshot_tarball_round_trips_into_a_store() {
+        let task = ExportedTask {
+            task_index: 7,
+            task_name: "QuickFox7".to_string(),
+            task_created_block: 40,
+            block_number: Some(41),
+            created_tx: Some(B256::repeat_byte(1)),
+            outcome: Some("responded"),
+            response_tx: Some(B256::repeat_byte(2)),
+            response_block: Some(42),
+            confirmed: true,
+            gas_used: Some(61_234),
+            failure_class: Some("nonce".to_string()),
+            rejection_reason: None,
+            last_error: Some("nonce too low".to_string()),
+            attempts: 1,
+            seen_at: 1_000,
+            created_at: Some(990),
+            responded_at: Some(1_002),
+            latency_secs: Some(12),
+            updated_at: 1_003,
+        };
+        let snapshot = Snapshot {
+            manifest: SnapshotManifest {
+                version: SNAPSHOT_VERSION,
+                created_at: 1_100,
+                operator: Address::repeat_byte(3),
+                swap_manager: Address::repeat_byte(4),
+                config_fingerprint: config_fingerprint(&OperatorConfig::default()),
+                next_block: Some(43),
+                cursor_block: None,
+                nonce: NonceState {
+                    mined: 5,
+                    pending: 5,
+                },
+            },
+            tasks: vec![task.clone()],
+        };
+        let tar = snapshot.to_tar().unwrap();
+        assert_eq!(tar.len() % TAR_BLOCK, 0);
+        assert_eq!(Snapshot::from_tar(&tar).unwrap(), snapshot);
+        assert!(matches!(
+            Snapshot::from_tar(&tar[..TAR_BLOCK]),
+            Err(SnapshotError::Malformed(_))
+        ));
+
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        assert_eq!(store.import(&snapshot.tasks).await.unwrap(), 1);
+        assert_eq!(store.export(0, i64::MAX).await.unwrap(), vec![task]);
+        assert!(store.task(7).await.unwrap().unwrap().is_done());
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+
+use crate::prometheus::{CALL_FAILURES, RPC_LATENCY};
+use crate::slo::{LatencyReport, TaskLatency};
+use crate::task_export::ExportedTask;
+use crate::top::Metrics;
+
+/// Widest throughput bar, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Output format of `spam-tasks report`
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SoakReportFormat {
+    /// Markdown tables, for a PR or an issue
+    #[default]
+    Markdown,
+    /// A standalone HTML page
+    Html,
+}
+
+/// Tasks first seen during one bucket of the report window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThroughputBucket {
+    /// Start of the bucket, unix seconds
+    pub start: i64,
+    /// Tasks seen
+    pub seen: usize,
+    /// Tasks responded to, directly or through the aggregator
+    pub responded: usize,
+    /// Tasks whose response failed
+    pub failed: usize,
+}
+
+/// Failed attempts of a retried RPC call or transaction out of its calls
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallErrors {
+    /// RPC method or transaction the attempts were made for
+    pub operation: String,
+    /// Calls timed as this RPC method, `None` for transactions and unknown methods
+    pub calls: Option<u64>,
+    /// Failed attempts by error class
+    pub failures: BTreeMap<String, u64>,
+}
+
+impl CallErrors {
+    /// Failed attempts of every class
+    pub fn total(&self) -> u64 {
+        self.failures.values().sum()
+    }
+
+    /// Share of the calls that failed, in percent
+    pub fn rate_percent(&self) -> Option<f64> {
+        self.calls
+            .filter(|&calls| calls > 0)
+            .map(|calls| self.total() as f64 * 100.0 / calls as f64)
+    }
+}
+
+/// Everything `spam-tasks report` tells about a soak test, from the task store export and,
+/// if one was scraped, the metrics of the operator
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoakReport {
+    /// First time a task was seen, unix seconds
+    pub from: i64,
+    /// Last time a task was seen, unix seconds, included
+    pub to: i64,
+    /// Tasks in the report
+    pub tasks: usize,
+    /// Seconds covered by each throughput bucket
+    pub bucket_secs: u64,
+    /// Tasks seen, responded to and failed over time
+    pub throughput: Vec<ThroughputBucket>,
+    /// Tasks by outcome, `pending` for those without one
+    pub outcomes: BTreeMap<String, usize>,
+    /// Failed tasks by error class, `unknown` for those stored without one
+    pub failures: BTreeMap<String, usize>,
+    /// Responses whose gas used is known
+    pub gas_responses: usize,
+    /// Gas used by all the responses
+    pub gas_used: u64,
+    /// Most gas used by a response
+    pub max_gas_used: Option<u64>,
+    /// Latency percentiles of the measured responses
+    pub latency: LatencyReport,
+    /// Failed attempts by operation, `None` without a metrics scrape
+    pub call_errors: Option<Vec<CallErrors>>,
+}
+
+impl SoakReport {
+    /// Report on `tasks`, counting their throughput in buckets of `bucket_secs` and reading
+    /// the RPC error rates from `metrics` if given
+    pub fn new(tasks: &[ExportedTask], bucket_secs: u64, metrics: Option<&Metrics>) -> Self {
+        let bucket_secs = bucket_secs.max(1);
+        let from = tasks.iter().map(|task| task.seen_at).min().unwrap_or(0);
+        let to = tasks.iter().map(|task| task.seen_at).max().unwrap_or(from);
+        let buckets = if tasks.is_empty() {
+            0
+        } else {
+            ((to - from) as u64 / bucket_secs + 1) as usize
+        };
+        let mut throughput: Vec<ThroughputBucket> = (0..buckets)
+            .map(|bucket| ThroughputBucket {
+                start: from + (bucket as u64 * bucket_secs) as i64,
+                seen: 0,
+                responded: 0,
+                failed: 0,
+            })
+            .collect();
+        let mut outcomes = BTreeMap::new();
+        let mut failures = BTreeMap::new();
+        for task in tasks {
+            let bucket = &mut throughput[((task.seen_at - from) as u64 / bucket_secs) as usize];
+            bucket.seen += 1;
+            match task.outcome {
+                Some("responded" | "aggregated") => bucket.responded += 1,
+                Some("failed") => {
+                    bucket.failed += 1;
+                    let class = task.failure_class.as_deref().unwrap_or("unknown");
+                    *failures.entry(class.to_string()).or_insert(0) += 1;
+                }
+                _ => {}
+            }
+            *outcomes
+                .entry(task.outcome.unwrap_or("pending").to_string())
+                .or_insert(0) += 1;
+        }
+        let gas: Vec<u64> = tasks.iter().filter_map(|task| task.gas_used).collect();
+        let latencies: Vec<TaskLatency> = tasks
+            .iter()
+            .filter_map(|task| {
+                Some(TaskLatency {
+                    task_index: task.task_index,
+                    created_at: task.created_at?,
+                    latency_secs: task.latency_secs?,
+                })
+            })
+            .collect();
+        Self {
+            from,
+            to,
+            tasks: tasks.len(),
+            bucket_secs,
+            throughput,
+            outcomes,
+            failures,
+            gas_responses: gas.len(),
+            gas_used: gas.iter().sum(),
+            max_gas_used: gas.iter().max().copied(),
+            latency: LatencyReport::new(from, to + 1, &latencies, None),
+            call_errors: metrics.map(call_errors),
+        }
+    }
+
+    /// The report rendered as `format`
+    pub fn render(&self, format: SoakReportFormat) -> String {
+        let sections = self.sections();
+        match format {
+            SoakReportFormat::Markdown => to_markdown(&self.summary(), &sections),
+            SoakReportFormat::Html => to_html(&self.summary(), &sections),
+        }
+    }
+
+    fn summary(&self) -> String {
+        if self.tasks == 0 {
+            return "No task in the task store".to_string();
+        }
+        format!(
+            "{} tasks seen from {} to {}",
+            self.tasks,
+            time(self.from),
+            time(self.to)
+        )
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let most_seen = self
+            .throughput
+            .iter()
+            .map(|bucket| bucket.seen)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let per_min = 60.0 / self.bucket_secs as f64;
+        let throughput = Section {
+            title: format!("Throughput per {}s", self.bucket_secs),
+            headers: vec!["Start", "Seen", "Responded", "Failed", "Tasks/min", ""],
+            rows: self
+                .throughput
+                .iter()
+                .map(|bucket| {
+                    vec![
+                        time(bucket.start),
+                        bucket.seen.to_string(),
+                        bucket.responded.to_string(),
+                        bucket.failed.to_string(),
+                        format!("{:.2}", bucket.seen as f64 * per_min),
+                        "█".repeat(bucket.seen * BAR_WIDTH / most_seen),
+                    ]
+                })
+                .collect(),
+            empty: "No task in the task store",
+        };
+        let share =
+            |count: usize, total: usize| format!("{:.2}%", count as f64 * 100.0 / total as f64);
+        let outcomes = Section {
+            title: "Outcomes".to_string(),
+            headers: vec!["Outcome", "Tasks", "Share"],
+            rows: self
+                .outcomes
+                .iter()
+                .map(|(outcome, &count)| {
+                    vec![outcome.clone(), count.to_string(), share(count, self.tasks)]
+                })
+                .collect(),
+            empty: "No task in the task store",
+        };
+        let failed: usize = self.failures.values().sum();
+        let failures = Section {
+            title: "Failures by error class".to_string(),
+            headers: vec!["Class", "Tasks", "Share"],
+            rows: self
+                .failures
+                .iter()
+                .map(|(class, &count)| vec![class.clone(), count.to_string(), share(count, failed)])
+                .collect(),
+            empty: "No failed task",
+        };
+        let gas = Section {
+            title: "Gas spend".to_string(),
+            headers: vec!["Responses", "Total gas", "Mean gas", "Max gas"],
+            rows: (self.gas_responses > 0)
+                .then(|| {
+                    vec![
+                        self.gas_responses.to_string(),
+                        self.gas_used.to_string(),
+                        (self.gas_used / self.gas_responses as u64).to_string(),
+                        opt(self.max_gas_used),
+                    ]
+                })
+                .into_iter()
+                .collect(),
+            empty: "No response with a known gas used",
+        };
+        let latency = &self.latency;
+        let latency = Section {
+            title: "Latency".to_string(),
+            headers: vec!["Responses", "p50", "p95", "p99", "Max", "Mean"],
+            rows: latency
+                .mean_secs
+                .map(|mean| {
+                    let secs =
+                        |value: Option<u64>| value.map(|v| format!("{v}s")).unwrap_or_default();
+                    vec![
+                        latency.tasks.to_string(),
+                        secs(latency.p50_secs),
+                        secs(latency.p95_secs),
+                        secs(latency.p99_secs),
+                        secs(latency.max_secs),
+                        format!("{mean:.2}s"),
+                    ]
+                })
+                .into_iter()
+                .collect(),
+            empty: "No response with a measured latency",
+        };
+        let calls = Section {
+            title: "RPC errors".to_string(),
+            headers: vec![
+                "Operation",
+                "Calls",
+                "Failed attempts",
+                "Error rate",
+                "Classes",
+            ],
+            rows: self
+                .call_errors
+                .iter()
+                .flatten()
+                .map(|errors| {
+                    let classes: Vec<String> = errors
+                        .failures
+                        .iter()
+                        .map(|(class, count)| format!("{class} {count}"))
+                        .collect();
+                    vec![
+                        errors.operation.clone(),
+                        opt(errors.calls),
+                        errors.total().to_string(),
+                        errors
+                            .rate_percent()
+                            .map(|rate| format!("{rate:.2}%"))
+                            .unwrap_or_default(),
+                        classes.join(", "),
+                    ]
+                })
+                .collect(),
+            empty: if self.call_errors.is_some() {
+                "No failed call"
+            } else {
+                "No metrics scraped, pass --metrics-url to include the RPC error rates"
+            },
+        };
+        vec![throughput, outcomes, failures, gas, latency, calls]
+    }
+}
+
+/// Failed attempts by operation from the metrics of a run, most failures first
+fn call_errors(metrics: &Metrics) -> Vec<CallErrors> {
+    let call_counts = format!("{RPC_LATENCY}_count");
+    let calls: BTreeMap<&str, f64> = metrics.by_label(&call_counts, "method").collect();
+    let mut errors: BTreeMap<&str, CallErrors> = BTreeMap::new();
+    for sample in metrics.series(CALL_FAILURES) {
+        let (Some(operation), Some(class)) = (sample.label("operation"), sample.label("class"))
+        else {
+            continue;
+        };
+        let errors = errors.entry(operation).or_insert_with(|| CallErrors {
+            operation: operation.to_string(),
+            calls: calls.get(operation).map(|&calls| calls as u64),
+            failures: BTreeMap::new(),
+        });
+        *errors.failures.entry(class.to_string()).or_insert(0) += sample.value as u64;
+    }
+    let mut errors: Vec<CallErrors> = errors.into_values().collect();
+    errors.sort_by_key(|errors| std::cmp::Reverse(errors.total()));
+    errors
+}
+
+/// A titled table of the report
+struct Section {
+    title: String,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    /// Shown instead of the table when it has no row
+    empty: &'static str,
+}
+
+fn to_markdown(summary: &str, sections: &[Section]) -> String {
+    let mut markdown = format!("# Soak test report\n\n{summary}\n");
+    for section in sections {
+        markdown.push_str(&format!("\n## {}\n\n", section.title));
+        if section.rows.is_empty() {
+            markdown.push_str(&format!("{}\n", section.empty));
+            continue;
+        }
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        markdown.push_str(&line(
+            section.headers.iter().map(|h| h.to_string()).collect(),
+        ));
+        markdown.push_str(&line(
+            section.headers.iter().map(|_| "---".to_string()).collect(),
+        ));
+        for row in &section.rows {
+            markdown.push_str(&line(
+                row.iter().map(|cell| cell.replace('|', "\\|")).collect(),
+            ));
+        }
+    }
+    markdown
+}
+
+fn to_html(summary: &str, sections: &[Section]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Soak test report</title>\n\
+         <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}\
+         th,td{{border:1px solid #ccc;padding:4px 8px;text-align:left}}</style>\n</head>\n<body>\n\
+         <h1>Soak test report</h1>\n<p>{}</p>\n",
+        escape(summary)
+    );
+    for section in sections {
+        html.push_str(&format!("<h2>{}</h2>\n", escape(&section.title)));
+        if section.rows.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", escape(section.empty)));
+            continue;
+        }
+        html.push_str("<table>\n<tr>");
+        for header in &section.headers {
+            html.push_str(&format!("<th>{}</th>", escape(header)));
+        }
+        html.push_str("</tr>\n");
+        for row in &section.rows {
+            html.push_str("<tr>");
+            for cell in row {
+                html.push_str(&format!("<td>{}</td>", escape(cell)));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn time(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| secs.to_string())
+}
+
+fn opt(value: Option<impl ToString>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(task_index: u32, seen_at: i64, outcome: &'static str) -> ExportedTask {
+        ExportedTask {
+            task_index,
+            task_name: format!("Task{task_index}"),
+            task_created_block: 10,
+            block_number: Some(11),
+            created_tx: None,
+            outcome: Some(outcome),
+            response_tx: None,
+            response_block: None,
+            confirmed: false,
+            gas_used: (outcome == "responded").then_some(60_000),
+            failure_class: (outcome == "failed").then(|| "nonce".to_string()),
+            rejection_reason: None,
+            last_error: None,
+            attempts: 0,
+            seen_at,
+            created_at: Some(seen_at),
+            responded_at: None,
+            latency_secs: (outcome == "responded").then_some(task_index as u64),
+            updated_at: seen_at,
+        }
+    }
+
+    #[test]
+    fn test_report_buckets_tasks_and_breaks_failures_down() {
+        let tasks = [
+            task(1, 1_000, "responded"),
+            task(2, 1_030, "responded"),
+            task(3, 1_070, "failed"),
+            task(4, 1_190, "responded"),
+        ];
+        let metrics = Metrics::parse(&format!(
+            "{RPC_LATENCY}_count{{method=\"eth_call\"}} 200\n\
+             {CALL_FAILURES}{{operation=\"eth_call\",class=\"timeout\"}} 3\n\
+             {CALL_FAILURES}{{operation=\"eth_call\",class=\"rate_limited\"}} 1\n"
+        ));
+        let report = SoakReport::new(&tasks, 60, Some(&metrics));
+
+        assert_eq!(report.tasks, 4);
+        let seen: Vec<usize> = report.throughput.iter().map(|b| b.seen).collect();
+        assert_eq!(seen, [2, 1, 0, 1]);
+        assert_eq!(report.throughput[1].failed, 1);
+        assert_eq!(report.outcomes["responded"], 3);
+        assert_eq!(report.failures["nonce"], 1);
+        assert_eq!(report.gas_used, 180_000);
+        assert_eq!(report.latency.p50_secs, Some(2));
+        let call_errors = report.call_errors.as_ref().unwrap();
+        assert_eq!(call_errors[0].total(), 4);
+        assert_eq!(call_errors[0].rate_percent(), Some(2.0));
+
+        let markdown = report.render(SoakReportFormat::Markdown);
+        assert!(markdown.contains("| nonce | 1 | 100.00% |"), "{markdown}");
+        assert!(markdown.contains("| eth_call | 200 | 4 | 2.00% | rate_limited 1, timeout 3 |"));
+        let html = report.render(SoakReportFormat::Html);
+        assert!(html.contains("<td>nonce</td><td>1</td>"), "{html}");
+
+        let empty = SoakReport::new(&[], 60, None).render(SoakReportFormat::Markdown);
+        assert!(empty.contains("pass --metrics-url"));
+    }
+}
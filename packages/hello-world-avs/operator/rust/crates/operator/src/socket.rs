@@ -0,0 +1,202 @@
+use std::{
+    fmt,
+    net::SocketAddr,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{eip191_hash_message, keccak256, Address, Bytes, B256},
+    signers::Signature,
+    sol_types::SolValue,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::aggregator::AggregatorClient;
+use crate::error::{AggregatorError, ConfigError, SignerError};
+use crate::signer::OperatorSigner;
+
+/// Path operators post their [`SocketAnnouncement`] to, and the announced sockets are read from
+pub const SOCKETS_PATH: &str = "/v1/sockets";
+
+/// `host:port` the aggregator reaches the operator at.
+///
+/// The ECDSA stake registry of the AVS keeps no operator socket, so the socket is announced
+/// to the aggregator with a [`SocketAnnouncement`] signed by the operator key instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorSocket {
+    /// Host name or IP address, without brackets for IPv6
+    pub host: String,
+    /// TCP port, never 0
+    pub port: u16,
+}
+
+impl OperatorSocket {
+    /// Address the local HTTP server binds to so it serves the announced port
+    pub fn bind_addr(&self) -> SocketAddr {
+        SocketAddr::from(([0, 0, 0, 0], self.port))
+    }
+}
+
+impl FromStr for OperatorSocket {
+    type Err = ConfigError;
+
+    fn from_str(socket: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| ConfigError::Invalid(format!("invalid socket {socket}: {reason}"));
+        let (host, port) = socket
+            .rsplit_once(':')
+            .ok_or_else(|| invalid("expected host:port"))?;
+        let host = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        if host.is_empty() || host.contains(['/', ' ']) {
+            return Err(invalid("missing or malformed host"));
+        }
+        let port = port
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| invalid("port must be between 1 and 65535"))?;
+        Ok(Self {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for OperatorSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Socket of an operator signed by its key, the latest one by `timestamp` wins
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SocketAnnouncement {
+    /// Operator reachable at `socket`
+    pub operator: Address,
+    /// Announced `host:port`
+    pub socket: String,
+    /// Unix time of the announcement in seconds, so an older one can't be replayed
+    pub timestamp: u64,
+    /// 65 byte ECDSA signature over [`socket_digest`]
+    pub signature: Bytes,
+}
+
+impl SocketAnnouncement {
+    /// Announce `socket` for the key of `signer`, timestamped now
+    pub async fn sign(
+        signer: &OperatorSigner,
+        socket: &OperatorSocket,
+    ) -> Result<Self, SignerError> {
+        let socket = socket.to_string();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let signature = signer
+            .sign_message(socket_hash(&socket, timestamp).as_slice())
+            .await?;
+        Ok(Self {
+            operator: signer.address(),
+            socket,
+            timestamp,
+            signature: signature.as_bytes().into(),
+        })
+    }
+
+    /// Check that the socket parses and the signature recovers to `operator`
+    pub fn verify(&self) -> Result<(), String> {
+        self.socket
+            .parse::<OperatorSocket>()
+            .map_err(|e| e.to_string())?;
+        let signature = Signature::from_raw(&self.signature)
+            .map_err(|e| format!("malformed socket signature: {e}"))?;
+        let signer = signature
+            .recover_address_from_prehash(&socket_digest(&self.socket, self.timestamp))
+            .map_err(|e| format!("unrecoverable socket signature: {e}"))?;
+        if signer != self.operator {
+            return Err(format!(
+                "socket signature of {signer} does not match operator {}",
+                self.operator
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Sign `socket` with `signer` and announce it to the aggregator at `aggregator_url`
+pub async fn announce_socket(
+    aggregator_url: &str,
+    signer: &OperatorSigner,
+    socket: &OperatorSocket,
+) -> Result<(), AggregatorError> {
+    let announcement = SocketAnnouncement::sign(signer, socket).await?;
+    AggregatorClient::new(aggregator_url)?
+        .announce_socket(&announcement)
+        .await?;
+    info!(operator = %signer.address(), %socket, "Announced the operator socket");
+    Ok(())
+}
+
+/// Hash of `(string socket, uint64 timestamp)`, signed with the EIP-191 prefix
+pub fn socket_hash(socket: &str, timestamp: u64) -> B256 {
+    keccak256((socket.to_string(), timestamp).abi_encode())
+}
+
+/// EIP-191 digest of [`socket_hash`]
+pub fn socket_digest(socket: &str, timestamp: u64) -> B256 {
+    eip191_hash_message(socket_hash(socket, timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+
+    #[test]
+    fn test_sockets_parse_and_display() {
+        let socket: OperatorSocket = "operator.example.com:9010".parse().unwrap();
+        assert_eq!(socket.port, 9010);
+        assert_eq!(socket.bind_addr(), SocketAddr::from(([0, 0, 0, 0], 9010)));
+        assert_eq!(socket.to_string(), "operator.example.com:9010");
+        let ipv6: OperatorSocket = "[::1]:9010".parse().unwrap();
+        assert_eq!(ipv6.host, "::1");
+        assert_eq!(ipv6.to_string(), "[::1]:9010");
+        for invalid in [
+            "operator",
+            ":9010",
+            "operator:0",
+            "operator:port",
+            "http://a:1",
+        ] {
+            assert!(invalid.parse::<OperatorSocket>().is_err(), "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socket_announcements_verify_against_their_operator() {
+        let signer = OperatorSigner::new(PrivateKeySigner::random());
+        let socket = "10.0.0.7:9010".parse().unwrap();
+        let announcement = SocketAnnouncement::sign(&signer, &socket).await.unwrap();
+        announcement.verify().unwrap();
+
+        let redirected = SocketAnnouncement {
+            socket: "10.0.0.8:9010".to_string(),
+            ..announcement.clone()
+        };
+        assert!(redirected.verify().is_err());
+        let replayed = SocketAnnouncement {
+            timestamp: announcement.timestamp - 1,
+            ..announcement
+        };
+        assert!(replayed.verify().is_err());
+    }
+}
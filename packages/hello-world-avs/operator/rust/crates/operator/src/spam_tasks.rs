@@ -1,77 +1,680 @@
 #![allow(missing_docs)]
-use alloy::primitives::Address;
+use alloy::primitives::{Address, B256};
+use clap::{Args, Parser, Subcommand};
 use dotenv::dotenv;
-use eigensdk::common::get_signer;
-use eigensdk::logging::{get_logger, init_logger, log_level::LogLevel};
-use eyre::Result;
-use swap_manager_utils::get_anvil_swap_manager_deployment_data;
-use swap_manager_utils::SwapManager::SwapManager;
-use rand::Rng;
-use std::env;
-use std::sync::LazyLock;
+use eyre::{eyre, Result, WrapErr};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use swap_manager_avs_operator::balance::BalanceWatcher;
+use swap_manager_avs_operator::chaos::ChaosNames;
+use swap_manager_avs_operator::config::{OperatorConfig, CONFIG_PATH_ENV};
+use swap_manager_avs_operator::control::{self, ControlState, TaskOutcome, TaskRecord};
+use swap_manager_avs_operator::gas::{fee_strategy, GasConfig};
+use swap_manager_avs_operator::key_pool::KeyPool;
+use swap_manager_avs_operator::log_file::LogFileArgs;
+use swap_manager_avs_operator::logging::{init_tracing, LogFormat};
+use swap_manager_avs_operator::pending_tx::PendingTxWatcher;
+use swap_manager_avs_operator::preflight::{preflight, PreflightChecks};
+use swap_manager_avs_operator::prometheus::init_metrics;
+use swap_manager_avs_operator::retry::{retry, RetryConfig};
+use swap_manager_avs_operator::services::Services;
+use swap_manager_avs_operator::shutdown::{drain, log_summary, Shutdown};
+use swap_manager_avs_operator::signer::{SignerBackend, SignerKind};
+use swap_manager_avs_operator::soak_report::{SoakReport, SoakReportFormat};
+use swap_manager_avs_operator::task_api::{self, QueuedTask};
+use swap_manager_avs_operator::task_creator::TaskCreator;
+use swap_manager_avs_operator::task_names::{PayloadKind, TaskNames};
+use swap_manager_avs_operator::task_store::connect_task_store;
+use swap_manager_avs_operator::top::Metrics;
+use swap_manager_avs_operator::traffic::{
+    parse_interval, until_open, SpamWindow, TrafficModel, TrafficSchedule,
+};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
 use tokio::time::{self, Duration};
+use tracing::{error, field, info, info_span, warn, Instrument};
 
-static RPC_URL: LazyLock<String> =
-    LazyLock::new(|| env::var("RPC_URL").expect("failed to retrieve RPC URL"));
+/// Command line arguments of the task spammer, each one overrides the config file
+#[derive(Parser, Debug)]
+#[command(
+    name = "spam-tasks",
+    about = "Create SwapManager tasks at a regular interval"
+)]
+pub struct SpamArgs {
+    /// Path of the operator config file
+    #[arg(long, global = true, env = CONFIG_PATH_ENV)]
+    pub config: Option<PathBuf>,
 
-static KEY: LazyLock<String> =
-    LazyLock::new(|| env::var("PRIVATE_KEY").expect("failed to retrieve private key"));
+    /// HTTP RPC endpoint used to send the createNewTask transactions
+    #[arg(long)]
+    pub rpc_url: Option<String>,
 
-/// Generate random task names from the given adjectives and nouns
-fn generate_random_name() -> String {
-    let adjectives = ["Quick", "Lazy", "Sleepy", "Noisy", "Hungry"];
-    let nouns = ["Fox", "Dog", "Cat", "Mouse", "Bear"];
+    /// Time between two task submissions, e.g. 250ms, 1.5s or 6 (seconds)
+    #[arg(long, value_parser = parse_interval)]
+    pub interval: Option<Duration>,
 
-    let mut rng = rand::rng();
+    /// Exit after creating this many tasks instead of looping forever
+    #[arg(long)]
+    pub count: Option<u64>,
 
-    let adjective = adjectives[rng.random_range(0..adjectives.len())];
-    let noun = nouns[rng.random_range(0..nouns.len())];
-    let number: u16 = rng.random_range(0..1000);
+    /// Seed of the task names, swap payloads and traffic model, for a reproducible run
+    #[arg(long)]
+    pub seed: Option<u64>,
 
-    format!("{}{}{}", adjective, noun, number)
+    /// Traffic model spacing the submissions
+    #[arg(long, value_enum)]
+    pub traffic: Option<TrafficModel>,
+
+    /// Tasks per minute of the poisson and burst traffic models
+    #[arg(long)]
+    pub rate: Option<f64>,
+
+    /// Most createNewTask transactions awaiting their receipt at once
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub concurrency: Option<u64>,
+
+    /// What the created tasks carry, plain names, swap payloads or the `[spammer.mix]` of them
+    #[arg(long, value_enum)]
+    pub payload: Option<PayloadKind>,
+
+    /// Mix malformed payloads, duplicate names, boundary lengths and rapid duplicate
+    /// submissions into the tasks, as weighted in `[spammer.chaos]`
+    #[arg(long)]
+    pub chaos: bool,
+
+    /// Tasks packed into a single Multicall3 transaction
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub batch: Option<u64>,
+
+    /// File holding the hex encoded private key of the task creator
+    #[arg(long)]
+    pub private_key_file: Option<PathBuf>,
+
+    /// Encrypted JSON keystore of the task creator, selects the keystore signer
+    #[arg(long)]
+    pub keystore: Option<PathBuf>,
+
+    /// File holding the keystore password, prompted if unset
+    #[arg(long, requires = "keystore")]
+    pub keystore_password_file: Option<PathBuf>,
+
+    /// Address serving Prometheus metrics, e.g. 0.0.0.0:9091
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Address serving the gRPC control API, e.g. 0.0.0.0:50051
+    #[arg(long)]
+    pub control_addr: Option<SocketAddr>,
+
+    /// Address serving `POST /tasks` for ad-hoc tasks, e.g. 127.0.0.1:8088
+    #[arg(long)]
+    pub api_addr: Option<SocketAddr>,
+
+    /// Deployment of the connected chain, overrides `deployment_file` of the config file
+    #[arg(long)]
+    pub deployment_file: Option<PathBuf>,
+
+    /// Ceiling in wei on the fee per gas paid for createNewTask
+    #[arg(long)]
+    pub max_fee_per_gas: Option<u128>,
+
+    /// Simulate createNewTask with eth_call instead of sending it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Log output format, overrides `log_format` of the config file
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// OTLP/gRPC collector the spans are exported to, overrides `otlp_endpoint` of the config file
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    #[command(flatten)]
+    pub log_file: LogFileArgs,
+
+    #[command(subcommand)]
+    pub command: Option<SpamCommand>,
+}
+
+/// Subcommands of the spammer, it creates tasks without one
+#[derive(Subcommand, Debug)]
+pub enum SpamCommand {
+    /// Write a report of a soak test from the task store: throughput over time, failures by
+    /// error class, gas spend, latency percentiles and, with --metrics-url, RPC error rates
+    Report(ReportArgs),
+}
+
+/// Window and output of `spam-tasks report`
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = SoakReportFormat::Markdown)]
+    pub format: SoakReportFormat,
+
+    /// Only the tasks first seen at or after this RFC 3339 time
+    #[arg(long, value_parser = parse_time)]
+    pub from: Option<i64>,
+
+    /// Only the tasks first seen before this RFC 3339 time
+    #[arg(long, value_parser = parse_time)]
+    pub to: Option<i64>,
+
+    /// Seconds of each throughput bucket
+    #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    pub bucket_secs: u64,
+
+    /// Operator whose tasks are read, the signer of the config file if unset
+    #[arg(long)]
+    pub operator: Option<Address>,
+
+    /// Prometheus endpoint of the operator the RPC error rates are read from
+    #[arg(long)]
+    pub metrics_url: Option<String>,
+
+    /// File the report is written to instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Unix time of an RFC 3339 time
+fn parse_time(value: &str) -> std::result::Result<i64, String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|time| time.timestamp())
+        .map_err(|e| e.to_string())
+}
+
+impl SpamArgs {
+    /// Load the config file and apply the command line overrides on top of it
+    pub fn into_config(self) -> Result<OperatorConfig> {
+        let mut config = OperatorConfig::read(self.config.as_deref())?;
+        if let Some(rpc_url) = self.rpc_url {
+            config.rpc_url = rpc_url;
+        }
+        if let Some(interval) = self.interval {
+            config.spammer.interval_ms = Some(interval.as_millis() as u64);
+        }
+        if self.count.is_some() {
+            config.spammer.count = self.count;
+        }
+        if self.seed.is_some() {
+            config.spammer.seed = self.seed;
+        }
+        if let Some(traffic) = self.traffic {
+            config.spammer.traffic.model = traffic;
+        }
+        if let Some(rate) = self.rate {
+            config.spammer.traffic.rate_per_min = rate;
+        }
+        if let Some(concurrency) = self.concurrency {
+            config.spammer.concurrency = concurrency as usize;
+        }
+        if let Some(payload) = self.payload {
+            config.spammer.payload = payload;
+        }
+        if self.chaos {
+            config.spammer.chaos.enabled = true;
+        }
+        if let Some(batch) = self.batch {
+            config.spammer.batch_size = batch as usize;
+        }
+        if self.private_key_file.is_some() {
+            config.private_key_file = self.private_key_file;
+        }
+        if self.keystore.is_some() {
+            config.signer.backend = SignerKind::Keystore;
+            config.signer.keystore = self.keystore;
+        }
+        if self.keystore_password_file.is_some() {
+            config.signer.password_file = self.keystore_password_file;
+        }
+        if self.metrics_addr.is_some() {
+            config.metrics_addr = self.metrics_addr;
+        }
+        if self.control_addr.is_some() {
+            config.control_addr = self.control_addr;
+        }
+        if self.api_addr.is_some() {
+            config.spammer.api_addr = self.api_addr;
+        }
+        if self.deployment_file.is_some() {
+            config.deployment_file = self.deployment_file;
+        }
+        if self.max_fee_per_gas.is_some() {
+            config.gas.max_fee_per_gas = self.max_fee_per_gas;
+        }
+        if self.dry_run {
+            config.dry_run = true;
+        }
+        if let Some(log_format) = self.log_format {
+            config.log_format = log_format;
+        }
+        if self.otlp_endpoint.is_some() {
+            config.otlp_endpoint = self.otlp_endpoint;
+        }
+        self.log_file.apply(&mut config.log_file);
+        config.spammer.apply_seed();
+        config.validate()?;
+        Ok(config)
+    }
 }
 
 /// Calls CreateNewTask function of the Hello world service manager contract
-pub async fn create_new_task(rpc_url: &str, task_name: &str) -> Result<()> {
-    let hw_data = get_anvil_swap_manager_deployment_data()?;
-    let swap_manager_contract_address: Address =
-        hw_data.addresses.swap_manager_service_manager.parse()?;
-    let pr = get_signer(&KEY.clone(), rpc_url);
-    let swap_manager_contract = SwapManager::new(swap_manager_contract_address, pr);
-
-    let tx = swap_manager_contract
-        .createNewTask(task_name.to_string())
-        .send()
-        .await?
-        .get_receipt()
+pub async fn create_new_task(
+    rpc_url: &str,
+    private_key: &str,
+    swap_manager_contract_address: Address,
+    task_name: &str,
+) -> Result<()> {
+    let signer = SignerBackend::PrivateKey(private_key.to_string())
+        .load()
         .await?;
+    let gas = GasConfig::default();
+    let creator = TaskCreator::new(
+        rpc_url,
+        &Services::default(),
+        &signer,
+        swap_manager_contract_address,
+        PendingTxWatcher::new(fee_strategy(&gas)?, &gas),
+    )?;
+    let tx_hash = creator.create_task(task_name).await?;
 
-    println!(
-        "Transaction successfull with tx : {:?}",
-        tx.transaction_hash
-    );
+    info!(task_name, ?tx_hash, "Transaction successful");
 
     Ok(())
 }
 
-/// Start creating tasks at every 15 seconds
-async fn start_creating_tasks() {
-    let mut interval = time::interval(Duration::from_secs(6));
-    init_logger(LogLevel::Info);
-    loop {
-        interval.tick().await;
-        let random_name = generate_random_name();
-        get_logger().info(
-            &format!("Creating new task with name: {random_name}"),
-            "start_creating_tasks",
+/// Create a task named `task_name`, retrying as set in `retry_config`, and record the outcome.
+///
+/// Returns the transaction hash, `None` in dry-run mode, or why the task wasn't created.
+async fn create_task(
+    creator: &TaskCreator,
+    retry_config: &RetryConfig,
+    control: &ControlState,
+    task_name: String,
+) -> Result<Option<B256>, String> {
+    let span = info_span!("create_task", task_name = %task_name, tx_hash = field::Empty);
+    let (outcome, created) = async {
+        info!("Creating new task");
+        match retry(retry_config, "createNewTask", || {
+            creator.create_task(&task_name)
+        })
+        .await
+        {
+            Ok(Some(tx_hash)) => {
+                tracing::Span::current().record("tx_hash", field::display(tx_hash));
+                info!("Created task");
+                (TaskOutcome::Created, Ok(Some(tx_hash)))
+            }
+            Ok(None) => (TaskOutcome::Simulated, Ok(None)),
+            Err(e) => {
+                error!(error = %e, "Failed to create task");
+                (TaskOutcome::Failed, Err(e.to_string()))
+            }
+        }
+    }
+    .instrument(span)
+    .await;
+    control.record(TaskRecord {
+        task_index: None,
+        task_name,
+        block_number: None,
+        outcome,
+        tx_hash: created.clone().ok().flatten(),
+        at: SystemTime::now(),
+    });
+    created
+}
+
+/// Write the soak test report of `args` from the task store of `config`
+pub async fn write_report(config: &OperatorConfig, args: &ReportArgs) -> Result<()> {
+    let operator = match args.operator {
+        Some(operator) => operator,
+        None => config.signer_backend()?.load().await?.address(),
+    };
+    let store = connect_task_store(&config.store, operator)
+        .await?
+        .ok_or_else(|| eyre!("the task store is disabled, set store.path or store.url"))?;
+    let tasks = store
+        .export(args.from.unwrap_or(0), args.to.unwrap_or(i64::MAX))
+        .await?;
+    store.close().await;
+    let metrics = match &args.metrics_url {
+        Some(url) => {
+            let text = reqwest::get(url)
+                .await
+                .and_then(|response| response.error_for_status())
+                .wrap_err_with(|| format!("failed to scrape {url}"))?
+                .text()
+                .await?;
+            Some(Metrics::parse(&text))
+        }
+        None => None,
+    };
+    let report = SoakReport::new(&tasks, args.bucket_secs, metrics.as_ref()).render(args.format);
+    match &args.output {
+        Some(output) => {
+            std::fs::write(output, report)?;
+            info!(tasks = tasks.len(), output = %output.display(), "Soak test report written");
+        }
+        None => print!("{report}"),
+    }
+    Ok(())
+}
+
+/// Sleep until one of the `spammer.windows` is open, returning at once if one is
+async fn wait_for_window(windows: &[SpamWindow]) {
+    let Some(wait) = until_open(windows, chrono::Utc::now()) else {
+        return;
+    };
+    let reopens = chrono::Utc::now() + wait;
+    info!(%reopens, "Outside of the spam windows, pausing");
+    time::sleep(wait).await;
+    info!("Spam window opened, resuming");
+}
+
+/// Create the ad-hoc tasks of the task API as they are queued, sharing the `permits` and
+/// pauses of the timed tasks, until the API stops queueing
+async fn create_queued_tasks(
+    creator: Arc<TaskCreator>,
+    retry_config: RetryConfig,
+    control: Arc<ControlState>,
+    permits: Arc<Semaphore>,
+    mut queue: mpsc::Receiver<QueuedTask>,
+) {
+    let mut in_flight = JoinSet::new();
+    while let Some(QueuedTask { task_name, reply }) = queue.recv().await {
+        control.wait_until_resumed().await;
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        while in_flight.try_join_next().is_some() {}
+        let (creator, retry_config, control) =
+            (creator.clone(), retry_config.clone(), control.clone());
+        in_flight.spawn(async move {
+            let created = create_task(&creator, &retry_config, &control, task_name).await;
+            // The client may have hung up, the task is created all the same
+            let _ = reply.send(created);
+            drop(permit);
+        });
+    }
+    while in_flight.join_next().await.is_some() {}
+}
+
+/// Create a task for each of `task_names` in one Multicall3 transaction, retrying as set
+/// in `retry_config`, and record the outcome of every task
+async fn create_batch(
+    creator: &TaskCreator,
+    retry_config: &RetryConfig,
+    control: &ControlState,
+    task_names: Vec<String>,
+) {
+    let span = info_span!(
+        "create_batch",
+        tasks = task_names.len(),
+        tx_hash = field::Empty
+    );
+    let (outcome, tx_hash) = async {
+        info!("Creating a batch of tasks");
+        match retry(retry_config, "aggregate3", || {
+            creator.create_tasks(&task_names)
+        })
+        .await
+        {
+            Ok(Some(tx_hash)) => {
+                tracing::Span::current().record("tx_hash", field::display(tx_hash));
+                info!("Created batch");
+                (TaskOutcome::Created, Some(tx_hash))
+            }
+            Ok(None) => (TaskOutcome::Simulated, None),
+            Err(e) => {
+                error!(error = %e, "Failed to create batch");
+                (TaskOutcome::Failed, None)
+            }
+        }
+    }
+    .instrument(span)
+    .await;
+    for task_name in task_names {
+        control.record(TaskRecord {
+            task_index: None,
+            task_name,
+            block_number: None,
+            outcome,
+            tx_hash,
+            at: SystemTime::now(),
+        });
+    }
+}
+
+/// Start creating tasks along the `spammer.traffic` model, stopping after `spammer.count` tasks if set.
+///
+/// Up to `spammer.concurrency` transactions wait for their receipt at once, a tick finding
+/// all of them busy waits for one to finish. Each tick creates `spammer.batch_size` tasks,
+/// several of them in a single Multicall3 transaction. Once `shutdown` triggers no task is created
+/// anymore and the ones in flight get `shutdown_timeout_secs` to be mined.
+async fn start_creating_tasks(
+    config: &OperatorConfig,
+    services: &Services,
+    shutdown: Shutdown,
+) -> Result<()> {
+    tokio::spawn(services.rpc().clone().run_health_checks(shutdown.clone()));
+    let signer = config.signer_backend()?.load().await?;
+    if config.signer.backend.is_hardware() {
+        warn!(
+            interval_secs = config.spammer.interval_secs,
+            concurrency = config.spammer.concurrency,
+            "Every createNewTask waits for approval on the hardware wallet, which signs one \
+             at a time, so tasks are created no faster than they are approved"
         );
-        let _ = create_new_task(&RPC_URL, &random_name).await;
     }
+    let contracts = config.contract_addresses(services).await?;
+    let checks = PreflightChecks {
+        funded: (!config.dry_run).then(|| signer.address()),
+        ..PreflightChecks::default()
+    };
+    preflight(config, services, &contracts, checks)
+        .await
+        .wrap_err("Preflight checks failed")?;
+    let pool = config.key_pool.signers()?;
+    let mut creator = TaskCreator::new(
+        &config.rpc_url,
+        services,
+        &signer,
+        contracts.swap_manager,
+        config.pending_tx_watcher()?,
+    )?;
+    if !pool.is_empty() {
+        let signer = signer.clone().services(services.clone());
+        let pool: Vec<_> = pool
+            .iter()
+            .map(|key| key.clone().services(services.clone()))
+            .collect();
+        let keys = KeyPool::new(&config.rpc_url, &signer, &pool)?;
+        info!(keys = ?keys.addresses(), "Rotating createNewTask through the key pool");
+        creator = creator.key_pool(keys);
+    }
+    let batch_size = config.spammer.batch_size;
+    if batch_size > 1 {
+        if let Some(address) = config.spammer.multicall_address {
+            creator = creator.multicall_address(address);
+        }
+        creator.check_multicall().await?;
+    }
+    let creator = Arc::new(creator);
+    if config.balance.enabled {
+        for address in std::iter::once(&signer)
+            .chain(&pool)
+            .map(|key| key.address())
+        {
+            let watcher = BalanceWatcher::new(&config.rpc_url, services, address, &config.balance)?;
+            tokio::spawn(watcher.run(shutdown.clone()));
+        }
+    }
+    let mut names = TaskNames::new(
+        config.spammer.payload,
+        &config.spammer.names,
+        &config.spammer.swaps,
+        &config.spammer.mix,
+    )?;
+    if config.spammer.chaos.enabled {
+        warn!("Chaos mode, creating malformed, duplicate and boundary length tasks on purpose");
+        names = TaskNames::Chaos(Box::new(ChaosNames::new(names, &config.spammer.chaos)?));
+    }
+    let control =
+        ControlState::with_spam_interval(signer.address(), services, config.spammer.interval());
+    if let Some(control_addr) = config.control_addr {
+        let control = control.clone();
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(control, control_addr).await {
+                error!(error = %e, "Control API stopped");
+            }
+        });
+    }
+    let spam_interval = control
+        .spam_interval()
+        .expect("spammer control state has an interval");
+    let mut schedule = TrafficSchedule::new(&config.spammer.traffic);
+    info!(
+        model = ?config.spammer.traffic.model,
+        payload = ?config.spammer.payload,
+        batch_size,
+        "Spamming tasks"
+    );
+    let started = time::Instant::now();
+    let mut next_task = started;
+    let permits = Arc::new(Semaphore::new(config.spammer.concurrency));
+    let mut in_flight = JoinSet::new();
+    if let Some(api_addr) = config.spammer.api_addr {
+        let (queue_sender, queue) = mpsc::channel(config.spammer.api_queue_capacity);
+        let deadline_secs = config.spammer.swaps.deadline_secs;
+        let api_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                task_api::serve(queue_sender, deadline_secs, api_addr, api_shutdown).await
+            {
+                error!(error = %e, "Task API stopped");
+            }
+        });
+        in_flight.spawn(create_queued_tasks(
+            creator.clone(),
+            config.retry.clone(),
+            control.clone(),
+            permits.clone(),
+            queue,
+        ));
+    }
+    let mut created: u64 = 0;
+    loop {
+        if config.spammer.count.is_some_and(|count| created >= count) {
+            if config.spammer.api_addr.is_some() {
+                info!(
+                    created,
+                    "Created all tasks, serving the task API until shutdown"
+                );
+                shutdown.triggered().await;
+            } else {
+                info!(created, "Created all tasks, exiting");
+            }
+            break;
+        }
+        let permit = tokio::select! {
+            biased;
+            _ = shutdown.triggered() => break,
+            permit = async {
+                time::sleep_until(next_task).await;
+                wait_for_window(&config.spammer.windows).await;
+                control.wait_until_resumed().await;
+                permits.clone().acquire_owned().await
+            } => permit?,
+        };
+        // A new interval set through the control API applies from the next task on
+        let delay = schedule.next_delay(next_task - started, *spam_interval.borrow());
+        // Time lost paused or behind is not made up with a burst of tasks
+        next_task = (next_task + delay).max(time::Instant::now());
+        while in_flight.try_join_next().is_some() {}
+
+        let (creator, retry_config, control) =
+            (creator.clone(), config.retry.clone(), control.clone());
+        // The last batch stops at `spammer.count`
+        let batch = config.spammer.count.map_or(batch_size as u64, |count| {
+            (count - created).min(batch_size as u64)
+        });
+        if batch == 1 {
+            let task_name = names.next_name();
+            in_flight.spawn(async move {
+                let _ = create_task(&creator, &retry_config, &control, task_name).await;
+                drop(permit);
+            });
+        } else {
+            let task_names = (0..batch).map(|_| names.next_name()).collect();
+            in_flight.spawn(async move {
+                create_batch(&creator, &retry_config, &control, task_names).await;
+                drop(permit);
+            });
+        }
+        // Rapid duplicates of the chaos mode go out as soon as a permit frees up
+        if names.repeat_pending() {
+            next_task = time::Instant::now();
+        }
+        created += batch;
+    }
+    let abandoned = drain(
+        &mut in_flight,
+        &shutdown,
+        Duration::from_secs(config.shutdown_timeout_secs),
+    )
+    .await;
+    log_summary(&control, services.tx_costs(), abandoned);
+    Ok(())
 }
 
 #[allow(dead_code)]
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    start_creating_tasks().await;
+    let mut args = SpamArgs::parse();
+    let command = args.command.take();
+    let mut config = match args.into_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:?}", e);
+            return;
+        }
+    };
+    let _tracing = init_tracing(
+        config.log_level().unwrap_or(tracing::Level::INFO),
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+        &config.log_file,
+    );
+    if let Err(e) = config.resolve_secrets().await {
+        eprintln!("Failed to fetch the secrets: {:?}", e);
+        return;
+    }
+    if let Some(SpamCommand::Report(args)) = command {
+        if let Err(e) = write_report(&config, &args).await {
+            eprintln!("Failed to write the report: {:?}", e);
+        }
+        return;
+    }
+    let services = match Services::from_config(&config) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("Failed to set up the spammer services: {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = config.resolve_names(&services).await {
+        eprintln!("Failed to resolve the ENS names: {:?}", e);
+        return;
+    }
+    if let Some(metrics_addr) = config.metrics_addr {
+        init_metrics(metrics_addr);
+    }
+    if let Err(e) = start_creating_tasks(&config, &services, Shutdown::on_signal()).await {
+        eprintln!("Failed to create tasks: {:?}", e);
+    }
 }
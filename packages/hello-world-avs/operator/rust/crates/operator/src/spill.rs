@@ -0,0 +1,171 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    Notify,
+};
+use tracing::info;
+
+use crate::error::StoreError;
+use crate::prometheus::{record_task_spilled, record_task_unspilled};
+use crate::task_listener::NewTask;
+use crate::task_store::TaskStore;
+
+/// Tasks overflowing the in-memory queue, parked in the task store and queued again as the
+/// responder frees room, so a burst or a catch up neither holds the task source back nor
+/// piles up in memory
+#[derive(Debug)]
+pub struct TaskSpill {
+    store: Arc<dyn TaskStore>,
+    pending: AtomicU64,
+    finished: AtomicBool,
+    wake: Notify,
+}
+
+impl TaskSpill {
+    /// Spill into `store`, starting with the tasks an earlier run parked there
+    pub async fn new(store: Arc<dyn TaskStore>) -> Result<Arc<Self>, StoreError> {
+        let pending = store.spilled().await?;
+        if pending > 0 {
+            info!(
+                pending,
+                "Queueing the tasks spilled by an earlier run first"
+            );
+        }
+        Ok(Arc::new(Self {
+            store,
+            pending: AtomicU64::new(pending),
+            finished: AtomicBool::new(false),
+            wake: Notify::new(),
+        }))
+    }
+
+    /// Tasks parked in the task store
+    pub fn pending(&self) -> u64 {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    /// Queue `task` if `queue` has room and no parked task is ahead of it, park it
+    /// otherwise. Returns `false` once the queue is closed
+    pub async fn offer(
+        &self,
+        queue: &mpsc::Sender<NewTask>,
+        task: NewTask,
+    ) -> Result<bool, StoreError> {
+        // Parked tasks have lower indexes, a task queued ahead of them would break the order
+        if self.pending() == 0 {
+            match queue.try_send(task) {
+                Ok(()) => return Ok(true),
+                Err(TrySendError::Full(task)) => self.park(&task).await?,
+                Err(TrySendError::Closed(_)) => return Ok(false),
+            }
+        } else {
+            self.park(&task).await?;
+        }
+        Ok(true)
+    }
+
+    /// Park `task` in the task store until [`Self::refill`] queues it
+    pub async fn park(&self, task: &NewTask) -> Result<(), StoreError> {
+        if self.store.spill(task).await? {
+            let pending = self.pending.fetch_add(1, Ordering::AcqRel) + 1;
+            record_task_spilled(pending);
+            self.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Mark the end of the offered tasks, [`Self::refill`] returns once the parked ones
+    /// are queued
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+        self.wake.notify_one();
+    }
+
+    /// Queue the parked tasks, lowest index first, as `queue` frees room, until the spill
+    /// is finished and empty or the queue is closed
+    pub async fn refill(&self, queue: &mpsc::Sender<NewTask>) -> Result<(), StoreError> {
+        loop {
+            if self.pending() == 0 {
+                if self.finished.load(Ordering::Acquire) {
+                    return Ok(());
+                }
+                self.wake.notified().await;
+                continue;
+            }
+            let Ok(permit) = queue.reserve().await else {
+                return Ok(());
+            };
+            match self.store.unspill().await? {
+                Some(task) => {
+                    permit.send(task);
+                    let pending = self.pending.fetch_sub(1, Ordering::AcqRel) - 1;
+                    record_task_unspilled(pending);
+                }
+                // A replica sharing the store took them
+                None => self
+                    .pending
+                    .store(self.store.spilled().await?, Ordering::Release),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swap_manager_utils::SwapManager::ISwapManager::Task;
+
+    use crate::task_store::SqliteTaskStore;
+
+    fn new_task(index: u32) -> NewTask {
+        NewTask {
+            index,
+            task: Task {
+                name: format!("QuickFox{index}"),
+                taskCreatedBlock: 10 + index,
+            },
+            block_number: Some(11 + u64::from(index)),
+            block_hash: None,
+            transaction_hash: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spill_parks_the_overflow_and_queues_it_in_order() {
+        let store: Arc<dyn TaskStore> = Arc::new(SqliteTaskStore::in_memory().await.unwrap());
+        let spill = TaskSpill::new(store.clone()).await.unwrap();
+        let (queue, mut tasks) = mpsc::channel(2);
+
+        for index in 0..5 {
+            assert!(spill.offer(&queue, new_task(index)).await.unwrap());
+        }
+        assert_eq!(spill.pending(), 3);
+        assert_eq!(store.spilled().await.unwrap(), 3);
+        // Already parked, so not counted twice
+        spill.park(&new_task(3)).await.unwrap();
+        assert_eq!(spill.pending(), 3);
+
+        spill.finish();
+        let refill = {
+            let (spill, queue) = (spill.clone(), queue.clone());
+            tokio::spawn(async move { spill.refill(&queue).await })
+        };
+        let mut received = Vec::new();
+        for _ in 0..5 {
+            received.push(tasks.recv().await.unwrap());
+        }
+        refill.await.unwrap().unwrap();
+        assert_eq!(
+            received.iter().map(|task| task.index).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(received[4].task.name, "QuickFox4");
+        assert_eq!(received[4].block_number, Some(15));
+        assert_eq!(spill.pending(), 0);
+        assert!(store.unspill().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use std::{future::IntoFuture, time::Duration};
+
+use alloy::primitives::{Address, U256};
+use eigensdk::common::SdkProvider;
+use serde::Deserialize;
+use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
+use tracing::{info, warn};
+
+use crate::error::{ChainError, ConfigError};
+use crate::prometheus::{set_stake_status, time_rpc};
+use crate::retry::{retry, RetryConfig};
+use crate::services::Services;
+use crate::shutdown::Shutdown;
+
+/// The `[stake_monitor]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StakeMonitorConfig {
+    /// Watch the operator's stake while responding to tasks
+    pub enabled: bool,
+    /// Seconds between two reads of the stake registry
+    pub poll_interval_secs: u64,
+    /// Weight under which a warning is logged, only the ejection risk is watched if unset
+    pub min_weight: Option<U256>,
+    /// The operator is at risk of ejection while its weight is less than this many percent
+    /// above the registry's minimum weight
+    pub ejection_margin_percent: u64,
+}
+
+impl Default for StakeMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_secs: 60,
+            min_weight: None,
+            ejection_margin_percent: 10,
+        }
+    }
+}
+
+impl StakeMonitorConfig {
+    /// Check that the registry can be polled with these settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.enabled && self.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "stake_monitor.poll_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Standing of an operator in the stake registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeStatus {
+    /// Whether the operator is registered, the quorum only counts registered operators
+    pub registered: bool,
+    /// Weight of the operator in the quorum
+    pub weight: U256,
+    /// Weight every operator needs to keep a non zero weight
+    pub minimum_weight: U256,
+    /// Weight of all the registered operators
+    pub total_weight: U256,
+    /// Signed weight a response needs to be accepted
+    pub threshold_weight: U256,
+}
+
+impl StakeStatus {
+    /// Share of the total weight held by the operator, between 0 and 1
+    pub fn share(&self) -> f64 {
+        if self.total_weight.is_zero() {
+            return 0.0;
+        }
+        f64::from(self.weight) / f64::from(self.total_weight)
+    }
+
+    /// Whether the weight is less than `margin_percent` above the minimum weight, so a
+    /// stake update could drop the operator from the quorum
+    pub fn at_risk(&self, margin_percent: u64) -> bool {
+        let floor = self.minimum_weight * U256::from(100 + margin_percent) / U256::from(100);
+        !self.registered || self.weight < floor
+    }
+}
+
+/// Reads the operator's standing in the stake registry at regular intervals
+#[derive(Debug)]
+pub struct StakeMonitor {
+    provider: SdkProvider,
+    stake_registry: Address,
+    operator: Address,
+    config: StakeMonitorConfig,
+    retry: RetryConfig,
+    services: Services,
+}
+
+impl StakeMonitor {
+    /// Monitor `operator` in the stake registry at `stake_registry`, read through `rpc_url`
+    /// and the RPC stack of `services`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        stake_registry: Address,
+        operator: Address,
+        config: &StakeMonitorConfig,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            stake_registry,
+            operator,
+            config: config.clone(),
+            retry: retry_config.clone(),
+            services: services.clone(),
+        })
+    }
+
+    /// Check the stake every poll interval until `shutdown` is triggered
+    pub async fn run(self, shutdown: Shutdown) {
+        info!(
+            operator = %self.operator,
+            stake_registry = %self.services.named(self.stake_registry),
+            "Monitoring the operator stake"
+        );
+        while !shutdown.is_triggered() {
+            if let Err(e) = self.check().await {
+                warn!(error = %e, "Stake check failed");
+            }
+            tokio::select! {
+                _ = shutdown.triggered() => {}
+                _ = tokio::time::sleep(Duration::from_secs(self.config.poll_interval_secs)) => {}
+            }
+        }
+    }
+
+    /// Read the current standing, export it as gauges and warn if it needs attention
+    pub async fn check(&self) -> Result<StakeStatus, ChainError> {
+        let status = self.status().await?;
+        let at_risk = status.at_risk(self.config.ejection_margin_percent);
+        set_stake_status(
+            status.registered,
+            f64::from(status.weight),
+            status.share(),
+            at_risk,
+        );
+        if !status.registered {
+            warn!(operator = %self.operator, "Operator is not registered in the stake registry");
+        } else if at_risk {
+            warn!(
+                weight = %status.weight,
+                minimum_weight = %status.minimum_weight,
+                margin_percent = self.config.ejection_margin_percent,
+                "Operator weight is close to the minimum, at risk of ejection"
+            );
+        }
+        if let Some(min_weight) = self.config.min_weight {
+            if status.registered && status.weight < min_weight {
+                warn!(
+                    weight = %status.weight,
+                    %min_weight,
+                    "Operator weight dropped below stake_monitor.min_weight"
+                );
+            }
+        }
+        Ok(status)
+    }
+
+    /// Current standing of the operator
+    pub async fn status(&self) -> Result<StakeStatus, ChainError> {
+        let registry = ECDSAStakeRegistry::new(self.stake_registry, &self.provider);
+        let registered = registry.operatorRegistered(self.operator);
+        let weight = registry.getOperatorWeight(self.operator);
+        let minimum_weight = registry.minimumWeight();
+        let total_weight = registry.getLastCheckpointTotalWeight();
+        let threshold_weight = registry.getLastCheckpointThresholdWeight();
+        Ok(StakeStatus {
+            registered: retry(&self.retry, "operatorRegistered", || {
+                time_rpc("operatorRegistered", registered.call().into_future())
+            })
+            .await?
+            ._0,
+            weight: retry(&self.retry, "getOperatorWeight", || {
+                time_rpc("getOperatorWeight", weight.call().into_future())
+            })
+            .await?
+            ._0,
+            minimum_weight: retry(&self.retry, "minimumWeight", || {
+                time_rpc("minimumWeight", minimum_weight.call().into_future())
+            })
+            .await?
+            ._0,
+            total_weight: retry(&self.retry, "getLastCheckpointTotalWeight", || {
+                time_rpc(
+                    "getLastCheckpointTotalWeight",
+                    total_weight.call().into_future(),
+                )
+            })
+            .await?
+            ._0,
+            threshold_weight: retry(&self.retry, "getLastCheckpointThresholdWeight", || {
+                time_rpc(
+                    "getLastCheckpointThresholdWeight",
+                    threshold_weight.call().into_future(),
+                )
+            })
+            .await?
+            ._0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_close_to_the_minimum_are_at_risk() {
+        let status = StakeStatus {
+            registered: true,
+            weight: U256::from(105),
+            minimum_weight: U256::from(100),
+            total_weight: U256::from(420),
+            threshold_weight: U256::from(280),
+        };
+        assert!(status.at_risk(10));
+        assert!(!status.at_risk(5));
+        assert_eq!(status.share(), 0.25);
+        assert!(StakeStatus {
+            registered: false,
+            ..status
+        }
+        .at_risk(0));
+    }
+}
@@ -0,0 +1,134 @@
+#![allow(missing_docs)]
+use clap::Parser;
+use dotenv::dotenv;
+use eyre::{Result, WrapErr};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use swap_manager_avs_operator::aggregator::{serve, Aggregator};
+use swap_manager_avs_operator::config::{OperatorConfig, CONFIG_PATH_ENV};
+use swap_manager_avs_operator::log_file::LogFileArgs;
+use swap_manager_avs_operator::logging::{init_tracing, LogFormat};
+use swap_manager_avs_operator::preflight::{preflight, PreflightChecks};
+use swap_manager_avs_operator::prometheus::init_metrics;
+use swap_manager_avs_operator::services::Services;
+use swap_manager_avs_operator::shutdown::Shutdown;
+
+/// Command line arguments of the aggregator
+#[derive(Parser, Debug)]
+#[command(
+    name = "start-aggregator",
+    about = "Collect signed task responses and submit them once the quorum is met"
+)]
+pub struct AggregatorArgs {
+    /// Path of the operator config file
+    #[arg(long, env = CONFIG_PATH_ENV)]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on, overrides `aggregator.listen_addr` of the config file
+    #[arg(long)]
+    pub listen_addr: Option<SocketAddr>,
+
+    /// Deployment of the connected chain, overrides `deployment_file` of the config file
+    #[arg(long)]
+    pub deployment_file: Option<PathBuf>,
+
+    /// Log output format, overrides `log_format` of the config file
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// OTLP/gRPC collector the spans are exported to, overrides `otlp_endpoint` of the config file
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    #[command(flatten)]
+    pub log_file: LogFileArgs,
+}
+
+impl AggregatorArgs {
+    /// Load the config file and apply the command line overrides on top of it
+    pub fn into_config(self) -> Result<OperatorConfig> {
+        let mut config = OperatorConfig::read(self.config.as_deref())?;
+        if let Some(listen_addr) = self.listen_addr {
+            config.aggregator.listen_addr = listen_addr;
+        }
+        if self.deployment_file.is_some() {
+            config.deployment_file = self.deployment_file;
+        }
+        if let Some(log_format) = self.log_format {
+            config.log_format = log_format;
+        }
+        if self.otlp_endpoint.is_some() {
+            config.otlp_endpoint = self.otlp_endpoint;
+        }
+        self.log_file.apply(&mut config.log_file);
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+async fn run(config: &OperatorConfig, services: &Services) -> Result<()> {
+    let shutdown = Shutdown::on_signal();
+    tokio::spawn(services.rpc().clone().run_health_checks(shutdown.clone()));
+    let signer = config.signer_backend()?.load().await?;
+    let contracts = config.contract_addresses(services).await?;
+    let checks = PreflightChecks {
+        funded: (!config.dry_run).then(|| signer.address()),
+        ..PreflightChecks::default()
+    };
+    preflight(config, services, &contracts, checks)
+        .await
+        .wrap_err("Preflight checks failed")?;
+    let aggregator = Aggregator::new(
+        &config.rpc_url,
+        services,
+        &signer,
+        contracts.swap_manager,
+        config.pending_tx_watcher()?,
+    )
+    .await?
+    .quorum_threshold_percent(config.aggregator.quorum_threshold_percent)
+    .handshake(&config.aggregator)?;
+    serve(aggregator, config.aggregator.listen_addr, shutdown).await?;
+    services.tx_costs().log_summary();
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn main() {
+    dotenv().ok();
+    let mut config = match AggregatorArgs::parse().into_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    let _tracing = init_tracing(
+        config.log_level().unwrap_or(tracing::Level::INFO),
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+        &config.log_file,
+    );
+    if let Err(e) = config.resolve_secrets().await {
+        eprintln!("Failed to fetch the secrets: {:?}", e);
+        std::process::exit(1);
+    }
+    let services = match Services::from_config(&config) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("Failed to set up the aggregator services: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = config.resolve_names(&services).await {
+        eprintln!("Failed to resolve the ENS names: {:?}", e);
+        std::process::exit(1);
+    }
+    if let Some(metrics_addr) = config.metrics_addr {
+        init_metrics(metrics_addr);
+    }
+    if let Err(e) = run(&config, &services).await {
+        eprintln!("Aggregator failed: {:?}", e);
+        std::process::exit(1);
+    }
+}
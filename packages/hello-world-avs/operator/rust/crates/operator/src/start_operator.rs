@@ -1,336 +1,335 @@
 #![allow(missing_docs)]
-use alloy::dyn_abi::DynSolValue;
-use alloy::{
-    primitives::{eip191_hash_message, keccak256, Address, FixedBytes, U256},
-    providers::Provider,
-    rpc::types::{BlockNumberOrTag, Filter},
-    signers::{local::PrivateKeySigner, SignerSync},
-    sol_types::{SolEvent, SolValue},
-};
-use chrono::Utc;
+use clap::Parser;
 use dotenv::dotenv;
-use eigensdk::client_elcontracts::{
-    reader::ELChainReader,
-    writer::{ELChainWriter, Operator},
-};
-use eigensdk::common::{get_provider, get_signer, get_ws_provider};
-use eigensdk::logging::{get_logger, init_logger, log_level::LogLevel};
-use eyre::Result;
-use futures::StreamExt;
-use swap_manager_utils::ecdsastakeregistry::ECDSAStakeRegistry;
-use swap_manager_utils::{
-    ecdsastakeregistry::ISignatureUtilsMixinTypes::SignatureWithSaltAndExpiry,
-    SwapManager::{SwapManager, ISwapManager::Task},
-};
-use swap_manager_utils::{
-    get_anvil_eigenlayer_deployment_data, get_swap_manager_service_manager,
-    get_stake_registry_address,
-};
-use rand::{Rng, TryRngCore};
-use std::sync::LazyLock;
-use std::{env, str::FromStr};
-
-static RPC_URL: LazyLock<String> =
-    LazyLock::new(|| env::var("RPC_URL").expect("failed to retrieve RPC URL"));
-
-static WS_URL: LazyLock<String> =
-    LazyLock::new(|| env::var("WS_URL").expect("failed to retrieve WS URL"));
-
-static KEY: LazyLock<String> =
-    LazyLock::new(|| env::var("PRIVATE_KEY").expect("failed to retrieve private key"));
-
-static OPERATOR_RESPONSE_PERCENTAGE: LazyLock<f64> = LazyLock::new(|| {
-    env::var("OPERATOR_RESPONSE_PERCENTAGE")
-        .expect("failed to retrieve operator response percentage")
-        .parse::<f64>()
-        .expect("failed to parse operator response percentage")
-});
-
-async fn sign_and_respond_to_task(
-    rpc_url: &str,
-    private_key: &str,
-    task_index: u32,
-    task_created_block: u32,
-    name: String,
-) -> Result<()> {
-    let pr = get_signer(private_key, rpc_url);
-    let signer = PrivateKeySigner::from_str(private_key)?;
-
-    let message = format!("Hello, {}", name);
-    let m_hash = eip191_hash_message(keccak256(message.abi_encode_packed()));
-    let operators: Vec<DynSolValue> = vec![DynSolValue::Address(signer.address())];
-    let signature: Vec<DynSolValue> =
-        vec![DynSolValue::Bytes(signer.sign_hash_sync(&m_hash)?.into())];
-    let current_block = U256::from(get_provider(rpc_url).get_block_number().await?);
-    let signature_data = DynSolValue::Tuple(vec![
-        DynSolValue::Array(operators.clone()),
-        DynSolValue::Array(signature.clone()),
-        DynSolValue::Uint(current_block, 32),
-    ])
-    .abi_encode_params();
-
-    get_logger().info(
-        &format!("Signing and responding to task: {task_index:?}"),
-        "",
-    );
-    let swap_manager_contract_address: Address = get_swap_manager_service_manager()?;
-    let swap_manager_contract = SwapManager::new(swap_manager_contract_address, &pr);
-
-    let task = Task {
-        name,
-        taskCreatedBlock: task_created_block,
-    };
-    let response_hash = swap_manager_contract
-        .respondToTask(task, task_index, signature_data.into())
-        .gas(500000)
-        .send()
-        .await?
-        .get_receipt()
-        .await?
-        .transaction_hash;
-    get_logger().info(
-        &format!("Responded to task with tx hash {}", response_hash),
-        "",
-    );
-    Ok(())
+use eyre::{Result, WrapErr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use swap_manager_avs_operator::balance::BalanceWatcher;
+use swap_manager_avs_operator::config::{OperatorConfig, CONFIG_PATH_ENV};
+use swap_manager_avs_operator::control::{self, ControlState};
+use swap_manager_avs_operator::error::AggregatorError;
+use swap_manager_avs_operator::handshake::say_hello;
+use swap_manager_avs_operator::log_file::LogFileArgs;
+use swap_manager_avs_operator::logging::{init_tracing, sdk_logger, LogFormat};
+use swap_manager_avs_operator::preflight::{preflight, PreflightChecks};
+use swap_manager_avs_operator::prometheus::init_metrics;
+use swap_manager_avs_operator::registration::register_operator;
+use swap_manager_avs_operator::rewards::RewardsTracker;
+use swap_manager_avs_operator::service::Operator;
+use swap_manager_avs_operator::services::Services;
+use swap_manager_avs_operator::shutdown::{drain, log_summary, Shutdown};
+use swap_manager_avs_operator::signature_scheme::SignatureScheme;
+use swap_manager_avs_operator::signer::SignerKind;
+use swap_manager_avs_operator::socket::announce_socket;
+use swap_manager_avs_operator::stake_monitor::StakeMonitor;
+use swap_manager_avs_operator::task_source::connect_task_source;
+use swap_manager_avs_operator::task_store::connect_task_store;
+use swap_manager_avs_operator::work_queue::{publish_tasks, WorkQueueRole, WorkQueueSource};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+/// Command line arguments of the operator
+#[derive(Parser, Debug)]
+#[command(
+    name = "start-operator",
+    about = "Register the operator and respond to tasks"
+)]
+pub struct OperatorArgs {
+    /// Path of the operator config file
+    #[arg(long, env = CONFIG_PATH_ENV)]
+    pub config: Option<PathBuf>,
+
+    /// Log output format, overrides `log_format` of the config file
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// OTLP/gRPC collector the spans are exported to, overrides `otlp_endpoint` of the config file
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    #[command(flatten)]
+    pub log_file: LogFileArgs,
+
+    /// Encrypted JSON keystore of the operator, selects the keystore signer
+    #[arg(long)]
+    pub keystore: Option<PathBuf>,
+
+    /// File holding the keystore password, prompted if unset
+    #[arg(long, requires = "keystore")]
+    pub keystore_password_file: Option<PathBuf>,
+
+    /// Deployment of the connected chain, overrides `deployment_file` of the config file
+    #[arg(long)]
+    pub deployment_file: Option<PathBuf>,
+
+    /// `host:port` announced to the aggregator, overrides `operator.socket` of the config file
+    #[arg(long)]
+    pub socket: Option<String>,
+
+    /// Skip registration and simulate respondToTask with eth_call instead of sending it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Only listen for tasks or only respond to them, overrides `work_queue.role` of the
+    /// config file
+    #[arg(long, value_enum)]
+    pub work_queue_role: Option<WorkQueueRole>,
 }
 
-/// Monitor new tasks
-async fn monitor_new_tasks(rpc_url: &str, private_key: &str) -> Result<()> {
-    let swap_manager_contract_address: Address = get_swap_manager_service_manager()?;
-
-    let ws_provider = get_ws_provider(&WS_URL).await?;
-
-    // Subscribe to NewTaskCreated events
-    let filter = Filter::new()
-        .address(swap_manager_contract_address)
-        .event_signature(SwapManager::NewTaskCreated::SIGNATURE_HASH)
-        .from_block(BlockNumberOrTag::Latest);
-    let mut new_task_stream = ws_provider.subscribe_logs(&filter).await?.into_stream();
-
-    // Process tasks when a new event is detected
-    while let Some(log) = new_task_stream.next().await {
-        if let Ok(decoded) = log.log_decode::<SwapManager::NewTaskCreated>() {
-            let SwapManager::NewTaskCreated { taskIndex, task } = decoded.inner.data;
-            get_logger().info(
-                &format!(
-                    "New task {} detected at block {}",
-                    taskIndex, task.taskCreatedBlock
-                ),
-                "",
-            );
+/// Push the new tasks onto the work queue until SIGINT/SIGTERM, leaving them to the workers
+async fn run_listener(config: &OperatorConfig, services: &Services) -> Result<()> {
+    let contracts = config
+        .contract_addresses(services)
+        .await
+        .wrap_err("Failed to resolve the contract addresses")?;
+    preflight(config, services, &contracts, PreflightChecks::default())
+        .await
+        .wrap_err("Preflight checks failed")?;
+    let shutdown = Shutdown::on_signal();
+    tokio::spawn(services.rpc().clone().run_health_checks(shutdown.clone()));
+    let source = connect_task_source(
+        &config.rpc_url,
+        &config.ws_url,
+        services,
+        contracts.swap_manager,
+        &config.source,
+        &config.retry,
+        None,
+    )
+    .await?;
+    publish_tasks(source, &config.work_queue, &config.retry, shutdown)
+        .await
+        .wrap_err("Failed to push tasks onto the work queue")
+}
 
-            // There is a `OPERATOR_RESPONSE_PERCENTAGE` chance that the operator will respond to the task.
-            // If the operator does not respond, the operator will be slashed.
-            let should_respond = rand::rng().random_bool(*OPERATOR_RESPONSE_PERCENTAGE / 100.0);
+async fn run(config: OperatorConfig, services: Services) -> Result<()> {
+    if config.work_queue.role == WorkQueueRole::Listener {
+        return run_listener(&config, &services).await;
+    }
+    let log_level = config.log_level().unwrap_or(tracing::Level::INFO);
+    let signer = config
+        .signer_backend()?
+        .load()
+        .await
+        .wrap_err("Failed to load signer")?
+        .services(services.clone());
+    let contracts = config
+        .contract_addresses(&services)
+        .await
+        .wrap_err("Failed to resolve the contract addresses")?;
+    // Responses handed to the aggregator cost the operator no gas, and registering an
+    // operator that isn't registered yet is left to the registration below
+    let operator = config.operator_address(signer.address());
+    let rotated = operator != signer.address();
+    let checks = PreflightChecks {
+        funded: (!config.dry_run && config.operator.aggregator_url.is_none())
+            .then(|| signer.address()),
+        registered: (config.dry_run || rotated).then_some(operator),
+    };
+    preflight(&config, &services, &contracts, checks)
+        .await
+        .wrap_err("Preflight checks failed")?;
+    if config.dry_run {
+        info!("Dry run, skipping registration");
+    } else if rotated {
+        // Only the operator key can register, the rotated key just signs for it
+        info!(%operator, signing_key = %signer.address(), "Signing for a registered operator");
+    } else {
+        register_operator(
+            sdk_logger(log_level),
+            &config.rpc_url,
+            &services,
+            &signer,
+            &contracts,
+            &config.operator.metadata_uri,
+        )
+        .await
+        .wrap_err("Failed to register operator")?;
+    }
 
-            if should_respond {
-                sign_and_respond_to_task(
-                    rpc_url,
-                    private_key,
-                    taskIndex,
-                    task.taskCreatedBlock,
-                    task.name,
-                )
-                .await?;
-            } else {
-                get_logger().info(
-                    &format!("Operator did not respond to task {}", taskIndex),
-                    "",
-                );
-            }
+    if let (Some(socket), Some(aggregator_url)) =
+        (config.operator_socket()?, &config.operator.aggregator_url)
+    {
+        // The aggregator keeps the socket it knew, so a failed rotation isn't fatal
+        if let Err(e) = announce_socket(aggregator_url, &signer, &socket).await {
+            warn!(error = %e, %socket, "Failed to announce the operator socket");
         }
     }
-
-    Ok(())
-}
-
-#[allow(dead_code)]
-/// Monitor new tasks using polling
-async fn monitor_new_tasks_polling(rpc_url: &str, private_key: &str) -> Result<()> {
-    let pr = get_signer(private_key, rpc_url);
-    let swap_manager_contract_address: Address = get_swap_manager_service_manager()?;
-    let mut latest_processed_block = pr.get_block_number().await?;
-
-    loop {
-        let current_block = pr.get_block_number().await?;
-        get_logger().info(
-            &format!(
-                "Monitoring for new tasks from block {} to {}",
-                latest_processed_block, current_block
-            ),
-            "",
-        );
-
-        let filter = Filter::new()
-            .address(swap_manager_contract_address)
-            .from_block(BlockNumberOrTag::Number(latest_processed_block))
-            .to_block(BlockNumberOrTag::Number(current_block));
-
-        let logs = pr.get_logs(&filter).await?;
-
-        for log in logs {
-            if let Some(&SwapManager::NewTaskCreated::SIGNATURE_HASH) = log.topic0() {
-                let SwapManager::NewTaskCreated { taskIndex, task } = log
-                    .log_decode()
-                    .expect("Failed to decode log new task created")
-                    .inner
-                    .data;
-                get_logger().info(
-                    &format!("New task {} detected: Hello, {}", taskIndex, task.name),
-                    "",
-                );
-
-                // There is a `OPERATOR_RESPONSE_PERCENTAGE` chance that the operator will respond to the task.
-                // If the operator does not respond, the operator will be slashed.
-                let should_respond = rand::rng().random_bool(*OPERATOR_RESPONSE_PERCENTAGE / 100.0);
-
-                if should_respond {
-                    let _ = sign_and_respond_to_task(
-                        rpc_url,
-                        private_key,
-                        taskIndex,
-                        task.taskCreatedBlock,
-                        task.name,
-                    )
-                    .await;
-                } else {
-                    get_logger().info(
-                        &format!("Operator did not respond to task {}", taskIndex),
-                        "",
-                    );
-                }
+    let mut task_types = config.operator.task_types.clone();
+    if let Some(aggregator_url) = &config.operator.aggregator_url {
+        // Only announced, the responses handed to the aggregator are ECDSA signed
+        let bls_key = config
+            .bls_keystore()
+            .map(|keystore| keystore.load())
+            .transpose()
+            .wrap_err("Failed to load the BLS key")?;
+        match say_hello(aggregator_url, &signer, bls_key.as_ref(), &task_types).await {
+            Ok(routed) => task_types = routed,
+            // The responses of an operator the aggregator turned down would be rejected too
+            Err(e @ AggregatorError::Rejected { .. }) => {
+                return Err(e).wrap_err("The aggregator refused the handshake");
             }
+            Err(e) => warn!(error = %e, "Failed to say hello to the aggregator"),
         }
-
-        tokio::time::sleep(tokio::time::Duration::from_secs(12)).await;
-        latest_processed_block = current_block + 1;
     }
-}
-
-pub async fn register_operator(rpc_url: &str, private_key: &str) -> Result<()> {
-    let pr = get_signer(private_key, rpc_url);
-    let signer = PrivateKeySigner::from_str(private_key)?;
-
-    let el_data = get_anvil_eigenlayer_deployment_data()?;
-    let delegation_manager_address: Address = el_data.addresses.delegation_manager.parse()?;
-    let avs_directory_address: Address = el_data.addresses.avs_directory.parse()?;
-
-    let elcontracts_reader_instance = ELChainReader::new(
-        get_logger().clone(),
-        None,
-        delegation_manager_address,
-        Address::ZERO,
-        avs_directory_address,
-        None,
-        rpc_url.to_string(),
-    );
-    let elcontracts_writer_instance = ELChainWriter::new(
-        Address::ZERO,
-        Address::ZERO,
-        None,
-        None,
-        Address::ZERO,
-        elcontracts_reader_instance.clone(),
-        rpc_url.to_string(),
-        private_key.to_string(),
-    );
-
-    let operator = Operator {
-        address: signer.address(),
-        delegation_approver_address: Address::ZERO,
-        staker_opt_out_window_blocks: Some(0),
-        metadata_url: Default::default(),
-        allocation_delay: Some(0),
-        _deprecated_earnings_receiver_address: None,
-    };
 
-    let is_registered = elcontracts_reader_instance
-        .is_operator_registered(signer.address())
-        .await
-        .unwrap();
-    get_logger().info(&format!("is registered {}", is_registered), "");
-    let tx_hash = elcontracts_writer_instance
-        .register_as_operator(operator)
-        .await?;
-    let receipt = pr.get_transaction_receipt(tx_hash).await?;
-    if !receipt.is_some_and(|r| r.inner.is_success()) {
-        get_logger().error("Operator registration failed", "");
-        return Err(eyre::eyre!("Operator registration failed"));
+    let control = ControlState::new(signer.address(), &services);
+    if let Some(control_addr) = config.control_addr {
+        let control = control.clone();
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(control, control_addr).await {
+                error!(error = %e, "Control API stopped");
+            }
+        });
     }
-    get_logger().info(
-        &format!(
-            "Operator registered on EL successfully tx_hash {:?}",
-            tx_hash
-        ),
-        "",
-    );
-    let mut salt = [0u8; 32];
-    rand::rngs::OsRng.try_fill_bytes(&mut salt).unwrap();
-
-    let salt = FixedBytes::from_slice(&salt);
-    let now = Utc::now().timestamp();
-    let expiry: U256 = U256::from(now + 3600);
-
-    let swap_manager_contract_address: Address = get_swap_manager_service_manager()?;
-    let digest_hash = elcontracts_reader_instance
-        .calculate_operator_avs_registration_digest_hash(
-            signer.address(),
-            swap_manager_contract_address,
-            salt,
-            expiry,
-        )
-        .await?;
-
-    let signature = signer.sign_hash_sync(&digest_hash)?;
-    let operator_signature = SignatureWithSaltAndExpiry {
-        signature: signature.as_bytes().into(),
-        salt,
-        expiry,
-    };
-    let stake_registry_address = get_stake_registry_address()?;
-    let contract_ecdsa_stake_registry = ECDSAStakeRegistry::new(stake_registry_address, &pr);
-    let registeroperator_details_call = contract_ecdsa_stake_registry
-        .registerOperatorWithSignature(operator_signature, signer.clone().address())
-        .gas(500000);
-    let register_swap_manager_hash = registeroperator_details_call
-        .send()
-        .await?
-        .get_receipt()
-        .await?
-        .transaction_hash;
 
-    get_logger().info(
-        &format!(
-            "Operator registered on AVS successfully :{} , tx_hash :{}",
+    // Monitor tasks until SIGINT/SIGTERM, then give the response in flight some time
+    let shutdown = Shutdown::on_signal();
+    tokio::spawn(services.rpc().clone().run_health_checks(shutdown.clone()));
+    if config.balance.enabled {
+        let pool = config.key_pool.signers()?;
+        for address in std::iter::once(&signer)
+            .chain(&pool)
+            .map(|key| key.address())
+        {
+            let watcher =
+                BalanceWatcher::new(&config.rpc_url, &services, address, &config.balance)?;
+            tokio::spawn(watcher.run(shutdown.clone()));
+        }
+    }
+    if config.stake_monitor.enabled {
+        let monitor = StakeMonitor::new(
+            &config.rpc_url,
+            &services,
+            contracts.stake_registry,
             signer.address(),
-            register_swap_manager_hash
-        ),
-        "",
-    );
-
+            &config.stake_monitor,
+            &config.retry,
+        )?;
+        tokio::spawn(monitor.run(shutdown.clone()));
+    }
+    if config.rewards.enabled {
+        let tracker = RewardsTracker::new(
+            &config.rpc_url,
+            &services,
+            config.rewards_coordinator(&services).await?,
+            config.operator_address(signer.address()),
+            config.rewards_from_block(&services).await?,
+            &config.rewards,
+            &config.retry,
+        )?;
+        tokio::spawn(tracker.run(shutdown.clone()));
+    }
+    let mut builder = Operator::builder()
+        .services(services.clone())
+        .config(&config)?
+        .task_types(task_types)
+        .signer(signer.clone())
+        .swap_manager_address(contracts.swap_manager)
+        .control(control.clone())
+        .shutdown(shutdown.clone());
+    let scheme = config
+        .signature_scheme(&services)
+        .await
+        .wrap_err("Failed to resolve the signature scheme")?;
+    if scheme == SignatureScheme::Bls {
+        let key = config
+            .bls_keystore()
+            .ok_or_else(|| eyre::eyre!("signer.bls_keystore is required to sign with BLS"))?
+            .load()
+            .wrap_err("Failed to load the BLS key")?;
+        builder = builder.bls_signing(key);
+    }
+    info!(?scheme, "Signing task responses");
+    if let Some(store) = connect_task_store(&config.store, signer.address()).await? {
+        builder = builder.task_store(Arc::from(store));
+    }
+    if config.work_queue.role == WorkQueueRole::Worker {
+        let source = WorkQueueSource::connect(&config.work_queue, &config.retry)
+            .await
+            .wrap_err("Failed to join the work queue")?;
+        builder = builder.task_source(source);
+    }
+    let operator = builder.build()?;
+    let mut monitor = JoinSet::new();
+    monitor.spawn(async move {
+        if let Err(e) = operator.run().await {
+            eprintln!("Failed to monitor new tasks: {:?}", e);
+        }
+    });
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let abandoned = drain(&mut monitor, &shutdown, shutdown_timeout).await;
+    log_summary(&control, services.tx_costs(), abandoned);
     Ok(())
 }
 
 #[tokio::main]
 pub async fn main() {
-    use tokio::signal;
     dotenv().ok();
-    init_logger(LogLevel::Info);
-    let rpc_url = &RPC_URL;
-    if let Err(e) = register_operator(rpc_url, &KEY).await {
-        eprintln!("Failed to register operator: {:?}", e);
+    let args = OperatorArgs::parse();
+    let mut config = match OperatorConfig::read(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:?}", e);
+            return;
+        }
+    };
+    if let Some(log_format) = args.log_format {
+        config.log_format = log_format;
+    }
+    if args.otlp_endpoint.is_some() {
+        config.otlp_endpoint = args.otlp_endpoint;
+    }
+    args.log_file.apply(&mut config.log_file);
+    if args.keystore.is_some() {
+        config.signer.backend = SignerKind::Keystore;
+        config.signer.keystore = args.keystore;
+    }
+    if args.keystore_password_file.is_some() {
+        config.signer.password_file = args.keystore_password_file;
+    }
+    if args.deployment_file.is_some() {
+        config.deployment_file = args.deployment_file;
+    }
+    if args.socket.is_some() {
+        config.operator.socket = args.socket;
+    }
+    if args.dry_run {
+        config.dry_run = true;
+    }
+    if let Some(role) = args.work_queue_role {
+        config.work_queue.role = role;
+    }
+    if let Err(e) = config.validate() {
+        eprintln!("Invalid configuration: {:?}", e);
         return;
     }
-
-    // Start the task monitoring as a separate async task to keep the process running
-    tokio::spawn(async {
-        if let Err(e) = monitor_new_tasks(rpc_url, &KEY).await {
-            eprintln!("Failed to monitor new tasks: {:?}", e);
+    let _tracing = init_tracing(
+        config.log_level().unwrap_or(tracing::Level::INFO),
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+        &config.log_file,
+    );
+    if let Err(e) = config.resolve_secrets().await {
+        eprintln!("Failed to fetch the secrets: {:?}", e);
+        return;
+    }
+    let services = match Services::from_config(&config) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("Failed to set up the operator services: {:?}", e);
+            return;
         }
-    });
-
-    // Wait for a Ctrl+C signal to gracefully shut down
-    let _ = signal::ctrl_c().await;
-    get_logger().info("Received Ctrl+C, shutting down...", "");
+    };
+    if let Err(e) = config.resolve_names(&services).await {
+        eprintln!("Failed to resolve the ENS names: {:?}", e);
+        return;
+    }
+    if let Some(metrics_addr) = config.metrics_addr {
+        init_metrics(metrics_addr);
+    }
+    if let Err(e) = run(config, services).await {
+        eprintln!("{:?}", e);
+    }
 }
@@ -0,0 +1,395 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alloy::{
+    hex,
+    primitives::{address, Address, U256},
+    sol,
+    sol_types::SolValue,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ConfigError, PayloadError};
+
+/// Prefix telling swap payloads apart from plain task names
+pub const SWAP_PAYLOAD_PREFIX: &str = "swap:";
+
+sol! {
+    /// ABI layout of a swap payload, hex encoded after [`SWAP_PAYLOAD_PREFIX`] in the task name
+    #[derive(Debug, PartialEq, Eq)]
+    struct SwapIntent {
+        address tokenIn;
+        address tokenOut;
+        uint256 amountIn;
+        uint256 minAmountOut;
+        uint16 slippageBps;
+        uint64 deadline;
+    }
+}
+
+/// A swap a task asks the operators to attest to
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapTaskPayload {
+    /// Token sold
+    pub token_in: Address,
+    /// Token bought
+    pub token_out: Address,
+    /// Amount of `token_in` sold, in its smallest unit
+    pub amount_in: U256,
+    /// Least amount of `token_out` accepted, in its smallest unit
+    pub min_amount_out: U256,
+    /// Largest accepted deviation from the quoted output, in basis points
+    pub slippage_bps: u16,
+    /// Unix time after which the swap must not execute
+    pub deadline: u64,
+}
+
+impl SwapTaskPayload {
+    /// Task name carrying the payload, what `createNewTask` is called with
+    pub fn encode(&self) -> String {
+        let intent = SwapIntent {
+            tokenIn: self.token_in,
+            tokenOut: self.token_out,
+            amountIn: self.amount_in,
+            minAmountOut: self.min_amount_out,
+            slippageBps: self.slippage_bps,
+            deadline: self.deadline,
+        };
+        format!("{SWAP_PAYLOAD_PREFIX}{}", hex::encode(intent.abi_encode()))
+    }
+
+    /// Payload of the task named `name`, `None` for names that aren't swap payloads
+    pub fn decode(name: &str) -> Result<Option<Self>, PayloadError> {
+        let Some(encoded) = name.strip_prefix(SWAP_PAYLOAD_PREFIX) else {
+            return Ok(None);
+        };
+        let intent = SwapIntent::abi_decode(&hex::decode(encoded)?, true)?;
+        Ok(Some(Self {
+            token_in: intent.tokenIn,
+            token_out: intent.tokenOut,
+            amount_in: intent.amountIn,
+            min_amount_out: intent.minAmountOut,
+            slippage_bps: intent.slippageBps,
+            deadline: intent.deadline,
+        }))
+    }
+
+    /// Whether the deadline passed at the Unix time `now`, a response would be wasted gas
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.deadline
+    }
+}
+
+/// How the generated amounts spread between a token's `min_amount` and `max_amount`
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountDistribution {
+    /// Every amount equally likely
+    Uniform,
+    /// Every order of magnitude equally likely, so small swaps dominate like on a real DEX
+    #[default]
+    LogUniform,
+}
+
+/// A token swaps are generated for
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SwapToken {
+    /// Symbol shown in the logs
+    pub symbol: String,
+    /// ERC-20 contract
+    pub address: Address,
+    /// Decimals of the token
+    pub decimals: u8,
+    /// Smallest amount sold, in whole tokens
+    pub min_amount: f64,
+    /// Largest amount sold, in whole tokens
+    pub max_amount: f64,
+    /// Rough USD price deriving the `min_amount_out` of the swaps
+    pub price_usd: f64,
+}
+
+/// Swap payload generation settings, the `[spammer.swaps]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwapPayloadConfig {
+    /// Tokens the pairs are drawn from, at least two
+    pub tokens: Vec<SwapToken>,
+    /// Spread of the amounts sold
+    pub amount_distribution: AmountDistribution,
+    /// Smallest slippage tolerance, in basis points
+    pub min_slippage_bps: u16,
+    /// Largest slippage tolerance, in basis points
+    pub max_slippage_bps: u16,
+    /// Seconds from creation until the deadline of a swap
+    pub deadline_secs: u64,
+    /// Seed making the generated payloads reproducible across runs, deadlines included
+    pub seed: Option<u64>,
+}
+
+impl Default for SwapPayloadConfig {
+    fn default() -> Self {
+        Self {
+            tokens: vec![
+                SwapToken {
+                    symbol: "WETH".to_string(),
+                    address: address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                    decimals: 18,
+                    min_amount: 0.01,
+                    max_amount: 50.0,
+                    price_usd: 3_000.0,
+                },
+                SwapToken {
+                    symbol: "USDC".to_string(),
+                    address: address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+                    decimals: 6,
+                    min_amount: 10.0,
+                    max_amount: 100_000.0,
+                    price_usd: 1.0,
+                },
+                SwapToken {
+                    symbol: "DAI".to_string(),
+                    address: address!("0x6B175474E89094C44Da98b954EedeAC495271d0F"),
+                    decimals: 18,
+                    min_amount: 10.0,
+                    max_amount: 100_000.0,
+                    price_usd: 1.0,
+                },
+                SwapToken {
+                    symbol: "WBTC".to_string(),
+                    address: address!("0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599"),
+                    decimals: 8,
+                    min_amount: 0.001,
+                    max_amount: 5.0,
+                    price_usd: 60_000.0,
+                },
+            ],
+            amount_distribution: AmountDistribution::default(),
+            min_slippage_bps: 10,
+            max_slippage_bps: 100,
+            deadline_secs: 600,
+            seed: None,
+        }
+    }
+}
+
+impl SwapPayloadConfig {
+    /// Check that payloads can be generated from these settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.tokens.len() < 2 {
+            return Err(ConfigError::Invalid(
+                "spammer.swaps.tokens needs at least two tokens".to_string(),
+            ));
+        }
+        for token in &self.tokens {
+            if !(token.min_amount > 0.0
+                && token.min_amount <= token.max_amount
+                && token.max_amount.is_finite())
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "spammer.swaps.tokens {}: min_amount must be greater than 0 and at most a finite max_amount",
+                    token.symbol
+                )));
+            }
+            if !(token.price_usd.is_finite() && token.price_usd > 0.0) {
+                return Err(ConfigError::Invalid(format!(
+                    "spammer.swaps.tokens {}: price_usd must be greater than 0",
+                    token.symbol
+                )));
+            }
+        }
+        if self.min_slippage_bps > self.max_slippage_bps || self.max_slippage_bps > 10_000 {
+            return Err(ConfigError::Invalid(
+                "spammer.swaps slippage must satisfy min_slippage_bps <= max_slippage_bps <= 10000"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Unix time the deadlines of seeded payloads count from in place of the clock, 2100-01-01,
+/// so two seeded runs create the same payloads and their tasks don't expire
+pub const SEEDED_DEADLINE_BASE: u64 = 4_102_444_800;
+
+/// Generates random swap payloads over a token universe
+#[derive(Debug)]
+pub struct SwapPayloadGenerator {
+    config: SwapPayloadConfig,
+    rng: StdRng,
+    /// Time the deadlines count from if seeded, the clock otherwise
+    base: Option<SystemTime>,
+}
+
+impl SwapPayloadGenerator {
+    /// Build a generator, failing on settings [`SwapPayloadConfig::validate`] rejects
+    pub fn new(config: &SwapPayloadConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Ok(Self {
+            config: config.clone(),
+            rng,
+            base: config
+                .seed
+                .map(|_| UNIX_EPOCH + Duration::from_secs(SEEDED_DEADLINE_BASE)),
+        })
+    }
+
+    /// Produce the next payload, expiring `deadline_secs` after `now`
+    pub fn next_payload(&mut self, now: SystemTime) -> SwapTaskPayload {
+        let tokens = &self.config.tokens;
+        let token_in = self.rng.random_range(0..tokens.len());
+        // Drawn among the other tokens so a pair never swaps a token for itself
+        let token_out = (token_in + self.rng.random_range(1..tokens.len())) % tokens.len();
+        let (token_in, token_out) = (&tokens[token_in], &tokens[token_out]);
+
+        let (min, max) = (token_in.min_amount, token_in.max_amount);
+        let amount = match self.config.amount_distribution {
+            AmountDistribution::Uniform => self.rng.random_range(min..=max),
+            AmountDistribution::LogUniform => self.rng.random_range(min.ln()..=max.ln()).exp(),
+        };
+        let amount_in = amount * 10f64.powi(i32::from(token_in.decimals));
+        let slippage_bps = self
+            .rng
+            .random_range(self.config.min_slippage_bps..=self.config.max_slippage_bps);
+        let amount_out = amount * token_in.price_usd / token_out.price_usd
+            * (1.0 - f64::from(slippage_bps) / 10_000.0);
+        let min_amount_out = amount_out * 10f64.powi(i32::from(token_out.decimals));
+
+        let deadline = (now + Duration::from_secs(self.config.deadline_secs))
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        SwapTaskPayload {
+            token_in: token_in.address,
+            token_out: token_out.address,
+            amount_in: U256::from(amount_in as u128),
+            min_amount_out: U256::from(min_amount_out as u128),
+            slippage_bps,
+            deadline,
+        }
+    }
+
+    /// Produce the task name of the next payload, expiring `deadline_secs` after
+    /// [`SEEDED_DEADLINE_BASE`] if seeded or after now otherwise
+    pub fn next_name(&mut self) -> String {
+        let now = self.base.unwrap_or_else(SystemTime::now);
+        self.next_payload(now).encode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn amount() -> impl Strategy<Value = U256> {
+        prop_oneof![
+            Just(U256::ZERO),
+            Just(U256::MAX),
+            any::<[u8; 32]>().prop_map(U256::from_be_bytes),
+        ]
+    }
+
+    prop_compose! {
+        fn payload()(
+            token_in in any::<[u8; 20]>(),
+            token_out in any::<[u8; 20]>(),
+            amount_in in amount(),
+            min_amount_out in amount(),
+            slippage_bps in any::<u16>(),
+            deadline in any::<u64>(),
+        ) -> SwapTaskPayload {
+            SwapTaskPayload {
+                token_in: Address::from(token_in),
+                token_out: Address::from(token_out),
+                amount_in,
+                min_amount_out,
+                slippage_bps,
+                deadline,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_any_payload_round_trips(payload in payload()) {
+            let name = payload.encode();
+            prop_assert!(name.starts_with(SWAP_PAYLOAD_PREFIX));
+            prop_assert_eq!(SwapTaskPayload::decode(&name).unwrap(), Some(payload));
+        }
+
+        #[test]
+        fn test_truncated_payloads_fail_to_decode(payload in payload(), cut in 0usize..384) {
+            let name = payload.encode();
+            let truncated = &name[..SWAP_PAYLOAD_PREFIX.len() + cut];
+            prop_assert!(SwapTaskPayload::decode(truncated).is_err());
+        }
+
+        #[test]
+        fn test_other_names_are_not_payloads(name in any::<String>()) {
+            prop_assume!(!name.starts_with(SWAP_PAYLOAD_PREFIX));
+            prop_assert_eq!(SwapTaskPayload::decode(&name).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_payloads_round_trip_through_the_task_name() {
+        let config = SwapPayloadConfig {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut generator = SwapPayloadGenerator::new(&config).unwrap();
+
+        for _ in 0..20 {
+            let payload = generator.next_payload(UNIX_EPOCH);
+            assert_ne!(payload.token_in, payload.token_out);
+            assert!((10..=100).contains(&payload.slippage_bps));
+            assert_eq!(payload.deadline, 600);
+            assert!(!payload.is_expired(600));
+            assert!(payload.is_expired(601));
+            assert_eq!(
+                SwapTaskPayload::decode(&payload.encode()).unwrap(),
+                Some(payload)
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeded_names_are_reproducible() {
+        let config = SwapPayloadConfig {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut first = SwapPayloadGenerator::new(&config).unwrap();
+        let mut second = SwapPayloadGenerator::new(&config).unwrap();
+
+        for _ in 0..20 {
+            let name = first.next_name();
+            assert_eq!(name, second.next_name());
+            let payload = SwapTaskPayload::decode(&name).unwrap().unwrap();
+            assert_eq!(payload.deadline, SEEDED_DEADLINE_BASE + 600);
+        }
+    }
+
+    #[test]
+    fn test_unbounded_amounts_are_rejected() {
+        let mut config = SwapPayloadConfig::default();
+        assert!(config.validate().is_ok());
+
+        config.tokens[0].max_amount = f64::INFINITY;
+        assert!(config.validate().is_err());
+        config.tokens[0].max_amount = f64::NAN;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_plain_names_are_not_payloads() {
+        assert_eq!(SwapTaskPayload::decode("QuickFox12").unwrap(), None);
+        assert!(SwapTaskPayload::decode("swap:0xzz").is_err());
+    }
+}
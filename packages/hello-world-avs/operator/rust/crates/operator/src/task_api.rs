@@ -0,0 +1,219 @@
+use std::{
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, B256, U256};
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot,
+};
+use tracing::info;
+
+use crate::shutdown::Shutdown;
+use crate::swap_payload::{SwapTaskPayload, SWAP_PAYLOAD_PREFIX};
+
+/// Path ad-hoc tasks are posted to
+pub const TASKS_PATH: &str = "/tasks";
+
+/// Longest task name accepted, longer names make createNewTask needlessly expensive
+const MAX_TASK_NAME_LEN: usize = 1024;
+
+/// Swap of an ad-hoc task, encoded as its task name like the generated swap payloads
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SwapRequest {
+    /// Token sold
+    pub token_in: Address,
+    /// Token bought
+    pub token_out: Address,
+    /// Amount of `token_in` sold, in its smallest unit
+    pub amount_in: U256,
+    /// Least amount of `token_out` accepted, in its smallest unit
+    pub min_amount_out: U256,
+    /// Largest accepted deviation from the quoted output, in basis points
+    pub slippage_bps: u16,
+    /// Unix time after which the swap must not execute, `deadline_secs` from now if unset
+    pub deadline: Option<u64>,
+}
+
+/// Body of `POST /tasks`, either a plain task name or a swap payload
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TaskRequest {
+    /// Name createNewTask is called with
+    pub name: Option<String>,
+    /// Swap the task carries instead of a name
+    pub payload: Option<SwapRequest>,
+}
+
+impl TaskRequest {
+    /// Name of the task to create, swap deadlines defaulting to `deadline_secs` from now
+    pub fn task_name(&self, deadline_secs: u64) -> Result<String, String> {
+        match (&self.name, &self.payload) {
+            (Some(name), None) => {
+                if name.is_empty() || name.len() > MAX_TASK_NAME_LEN {
+                    return Err(format!(
+                        "name must be between 1 and {MAX_TASK_NAME_LEN} bytes"
+                    ));
+                }
+                // A name carrying a malformed swap would only be refused by the operators
+                if name.starts_with(SWAP_PAYLOAD_PREFIX) {
+                    SwapTaskPayload::decode(name).map_err(|e| e.to_string())?;
+                }
+                Ok(name.clone())
+            }
+            (None, Some(swap)) => {
+                if swap.token_in == swap.token_out {
+                    return Err("tokenIn and tokenOut must differ".to_string());
+                }
+                if swap.amount_in.is_zero() {
+                    return Err("amountIn must be greater than 0".to_string());
+                }
+                if swap.slippage_bps > 10_000 {
+                    return Err("slippageBps must be at most 10000".to_string());
+                }
+                let deadline = swap.deadline.unwrap_or_else(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |now| now.as_secs())
+                        + deadline_secs
+                });
+                Ok(SwapTaskPayload {
+                    token_in: swap.token_in,
+                    token_out: swap.token_out,
+                    amount_in: swap.amount_in,
+                    min_amount_out: swap.min_amount_out,
+                    slippage_bps: swap.slippage_bps,
+                    deadline,
+                }
+                .encode())
+            }
+            _ => Err("give either a name or a payload".to_string()),
+        }
+    }
+}
+
+/// Reply of `POST /tasks` once the task was created
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskCreated {
+    /// Name createNewTask was called with
+    pub task_name: String,
+    /// Hash of the mined createNewTask transaction, `None` in dry-run mode
+    pub tx_hash: Option<B256>,
+}
+
+/// Ad-hoc task waiting for its turn to be created, the creator answers on `reply`
+#[derive(Debug)]
+pub struct QueuedTask {
+    /// Name createNewTask is called with
+    pub task_name: String,
+    /// Transaction hash, `None` in dry-run mode, or why creating the task failed
+    pub reply: oneshot::Sender<Result<Option<B256>, String>>,
+}
+
+#[derive(Debug, Clone)]
+struct ApiState {
+    queue: mpsc::Sender<QueuedTask>,
+    deadline_secs: u64,
+}
+
+async fn post_task(
+    State(state): State<ApiState>,
+    Json(request): Json<TaskRequest>,
+) -> Result<Json<TaskCreated>, (StatusCode, String)> {
+    let task_name = request
+        .task_name(state.deadline_secs)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let (reply, created) = oneshot::channel();
+    let task = QueuedTask {
+        task_name: task_name.clone(),
+        reply,
+    };
+    match state.queue.try_send(task) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "task queue is full".to_string(),
+            ))
+        }
+        Err(TrySendError::Closed(_)) => {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "the spammer is shutting down".to_string(),
+            ))
+        }
+    }
+    match created.await {
+        Ok(Ok(tx_hash)) => Ok(Json(TaskCreated { task_name, tx_hash })),
+        Ok(Err(e)) => Err((StatusCode::BAD_GATEWAY, e)),
+        Err(_) => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "the spammer stopped before creating the task".to_string(),
+        )),
+    }
+}
+
+/// Serve `POST /tasks` on `addr` until `shutdown` triggers, queueing each task on `queue`
+/// and replying with its transaction hash once the creator mined it. Swap payloads without
+/// a deadline expire `deadline_secs` after they are queued.
+pub async fn serve(
+    queue: mpsc::Sender<QueuedTask>,
+    deadline_secs: u64,
+    addr: SocketAddr,
+    shutdown: Shutdown,
+) -> std::io::Result<()> {
+    let app = Router::new()
+        .route(TASKS_PATH, post(post_task))
+        .with_state(ApiState {
+            queue,
+            deadline_secs,
+        });
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(%addr, "Task API listening");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.triggered().await })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_requests_name_the_task() {
+        let named: TaskRequest = serde_json::from_str(r#"{"name": "DemoTask1"}"#).unwrap();
+        assert_eq!(named.task_name(600).unwrap(), "DemoTask1");
+
+        let swap: TaskRequest = serde_json::from_str(
+            r#"{"payload": {
+                "tokenIn": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "tokenOut": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                "amountIn": "1000000000000000000",
+                "minAmountOut": "2900000000",
+                "slippageBps": 50,
+                "deadline": 1700000000
+            }}"#,
+        )
+        .unwrap();
+        let payload = SwapTaskPayload::decode(&swap.task_name(600).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(payload.amount_in, U256::from(10).pow(U256::from(18)));
+        assert_eq!(payload.deadline, 1_700_000_000);
+
+        for invalid in [
+            r#"{}"#,
+            r#"{"name": ""}"#,
+            r#"{"name": "swap:zz"}"#,
+            r#"{"name": "DemoTask1", "payload": {"tokenIn": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "tokenOut": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "amountIn": "1", "minAmountOut": "0", "slippageBps": 50}}"#,
+        ] {
+            let request: TaskRequest = serde_json::from_str(invalid).unwrap();
+            assert!(request.task_name(600).is_err(), "{invalid}");
+        }
+    }
+}
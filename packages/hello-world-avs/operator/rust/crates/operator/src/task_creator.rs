@@ -0,0 +1,242 @@
+use std::future::IntoFuture;
+
+use alloy::{
+    primitives::{Address, B256},
+    providers::{
+        bindings::IMulticall3::{aggregate3Call, Call3},
+        Provider, MULTICALL3_ADDRESS,
+    },
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    sol_types::SolCall,
+};
+use swap_manager_utils::SwapManager::SwapManager::{self, createNewTaskCall};
+
+use crate::error::{ChainError, SpamError};
+use crate::key_pool::KeyPool;
+use crate::nonce_manager::is_nonce_error;
+use crate::pending_tx::PendingTxWatcher;
+use crate::prometheus::{
+    record_gas_used, record_task_created, record_tx_failure, time_rpc, PendingTxGuard,
+};
+use crate::services::Services;
+use crate::signer::OperatorSigner;
+use crate::webhook::WebhookEvent;
+
+const CREATE_NEW_TASK: &str = "createNewTask";
+const AGGREGATE3: &str = "aggregate3";
+
+/// Sends createNewTask transactions through long-lived providers and nonce managers, one
+/// per key of its pool
+#[derive(Debug)]
+pub struct TaskCreator {
+    keys: KeyPool,
+    swap_manager_address: Address,
+    multicall_address: Address,
+    watcher: PendingTxWatcher,
+    services: Services,
+}
+
+impl TaskCreator {
+    /// Create a task creator signing with `signer` and sending through `watcher`, with the
+    /// RPC stack, budget, audit log and notifications of `services`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        signer: &OperatorSigner,
+        swap_manager_address: Address,
+        watcher: PendingTxWatcher,
+    ) -> Result<Self, SpamError> {
+        Ok(Self {
+            keys: KeyPool::single(rpc_url, &signer.clone().services(services.clone()))?,
+            swap_manager_address,
+            multicall_address: MULTICALL3_ADDRESS,
+            watcher: watcher.services(services.clone()),
+            services: services.clone(),
+        })
+    }
+
+    /// Send the transactions from the keys of `keys` in turn instead of the signer alone
+    pub fn key_pool(mut self, keys: KeyPool) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Send the batches of [`Self::create_tasks`] through the Multicall3 contract at
+    /// `address` instead of the canonical deployment
+    pub fn multicall_address(mut self, address: Address) -> Self {
+        self.multicall_address = address;
+        self
+    }
+
+    /// Fail unless a contract is deployed at the Multicall3 address, which devnets lack
+    pub async fn check_multicall(&self) -> Result<(), SpamError> {
+        let code = time_rpc(
+            "eth_getCode",
+            self.keys
+                .signer_key()
+                .provider()
+                .get_code_at(self.multicall_address)
+                .into_future(),
+        )
+        .await
+        .map_err(ChainError::from)?;
+        if code.is_empty() {
+            return Err(SpamError::NoMulticall(self.multicall_address));
+        }
+        Ok(())
+    }
+
+    /// Create a task named `task_name`, returning the transaction hash once mined, `None`
+    /// if the watcher only simulates transactions
+    pub async fn create_task(&self, task_name: &str) -> Result<Option<B256>, SpamError> {
+        if self.watcher.is_dry_run() {
+            self.simulate_create_task(task_name).await?;
+            return Ok(None);
+        }
+        let _pending = PendingTxGuard::new();
+        match self.send_create_task(task_name).await {
+            Ok(receipt) => {
+                record_gas_used(CREATE_NEW_TASK, receipt.gas_used);
+                record_task_created();
+                self.services.notify(WebhookEvent::TaskCreated {
+                    task_names: vec![task_name.to_string()],
+                    tx_hash: receipt.transaction_hash,
+                });
+                Ok(Some(receipt.transaction_hash))
+            }
+            Err(e) => {
+                record_tx_failure(CREATE_NEW_TASK);
+                self.services.notify_tx_failure(CREATE_NEW_TASK, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Create a task for each of `task_names` in a single Multicall3 transaction, all of
+    /// them reverting if one does. Returns the transaction hash once mined, `None` if the
+    /// watcher only simulates transactions
+    pub async fn create_tasks(&self, task_names: &[String]) -> Result<Option<B256>, SpamError> {
+        let tx = TransactionRequest::default()
+            .to(self.multicall_address)
+            .input(batch_calldata(self.swap_manager_address, task_names).into());
+        if self.watcher.is_dry_run() {
+            let key = self.keys.signer_key();
+            let tx = tx.from(key.address());
+            self.watcher
+                .simulate(key.provider(), AGGREGATE3, tx)
+                .await?;
+            return Ok(None);
+        }
+        let _pending = PendingTxGuard::new();
+        match self.send(AGGREGATE3, tx).await {
+            Ok(receipt) => {
+                record_gas_used(AGGREGATE3, receipt.gas_used);
+                for _ in task_names {
+                    record_task_created();
+                }
+                self.services.notify(WebhookEvent::TaskCreated {
+                    task_names: task_names.to_vec(),
+                    tx_hash: receipt.transaction_hash,
+                });
+                Ok(Some(receipt.transaction_hash))
+            }
+            Err(e) => {
+                record_tx_failure(AGGREGATE3);
+                self.services.notify_tx_failure(AGGREGATE3, &e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn simulate_create_task(&self, task_name: &str) -> Result<u64, SpamError> {
+        let key = self.keys.signer_key();
+        let swap_manager_contract = SwapManager::new(self.swap_manager_address, key.provider());
+        let tx = swap_manager_contract
+            .createNewTask(task_name.to_string())
+            .from(key.address())
+            .into_transaction_request();
+        Ok(self
+            .watcher
+            .simulate(key.provider(), CREATE_NEW_TASK, tx)
+            .await?)
+    }
+
+    async fn send_create_task(&self, task_name: &str) -> Result<TransactionReceipt, SpamError> {
+        let swap_manager_contract =
+            SwapManager::new(self.swap_manager_address, self.keys.signer_key().provider());
+        let tx = swap_manager_contract
+            .createNewTask(task_name.to_string())
+            .into_transaction_request();
+        self.send(CREATE_NEW_TASK, tx).await
+    }
+
+    /// Send `tx` from the next key of the pool with its next nonce and wait for it to be
+    /// mined successfully
+    async fn send(
+        &self,
+        method: &'static str,
+        tx: TransactionRequest,
+    ) -> Result<TransactionReceipt, SpamError> {
+        let key = self.keys.next_key();
+        let nonce = key.nonces().next_nonce(key.provider()).await?;
+        let tx = tx.from(key.address()).nonce(nonce);
+        let receipt = match self.watcher.send(key.provider(), method, tx).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                // The reserved nonce was dropped, reload it so later submissions don't stall
+                key.nonces().resync(key.provider()).await?;
+                if is_nonce_error(&e.to_string()) {
+                    return Err(SpamError::NonceRejected {
+                        nonce,
+                        source: Box::new(e),
+                    });
+                }
+                return Err(e.into());
+            }
+        };
+        if !receipt.status() {
+            return Err(ChainError::Reverted {
+                method,
+                tx_hash: receipt.transaction_hash,
+            }
+            .into());
+        }
+        Ok(receipt)
+    }
+}
+
+/// `aggregate3` calldata calling `createNewTask` on `swap_manager_address` once per name
+fn batch_calldata(swap_manager_address: Address, task_names: &[String]) -> Vec<u8> {
+    let calls = task_names
+        .iter()
+        .map(|name| Call3 {
+            target: swap_manager_address,
+            allowFailure: false,
+            callData: createNewTaskCall { name: name.clone() }.abi_encode().into(),
+        })
+        .collect();
+    aggregate3Call { calls }.abi_encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_calldata_wraps_every_task() {
+        let names = ["QuickFox1".to_string(), "LazyDog2".to_string()];
+        let calldata = batch_calldata(Address::with_last_byte(1), &names);
+
+        let decoded = aggregate3Call::abi_decode(&calldata, true).unwrap();
+        assert_eq!(decoded.calls.len(), 2);
+        let call = &decoded.calls[1];
+        assert_eq!(call.target, Address::with_last_byte(1));
+        assert!(!call.allowFailure);
+        assert_eq!(
+            createNewTaskCall::abi_decode(&call.callData, true)
+                .unwrap()
+                .name,
+            "LazyDog2"
+        );
+    }
+}
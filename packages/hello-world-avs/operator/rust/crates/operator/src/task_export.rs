@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+use alloy::primitives::B256;
+use clap::ValueEnum;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+
+use crate::control::TaskOutcome;
+
+/// Output format of `operator tasks export`
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A header line and a line per task, for spreadsheets
+    #[default]
+    Csv,
+    /// A JSON array of task objects, for notebooks
+    Json,
+}
+
+/// Name of a [`TaskOutcome`], as given by [`TaskOutcome::as_str`]. Spelled out as `&str`,
+/// serde would only deserialize tasks from `'static` input, taking it for a borrowed string
+pub type OutcomeName = &'static str;
+
+/// A task of the task store with everything known about its handling
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedTask {
+    /// Index of the task in the SwapManager
+    pub task_index: u32,
+    /// Name of the task, the encoded payload of swap tasks
+    pub task_name: String,
+    /// Block the task was created in, from the task itself
+    pub task_created_block: u32,
+    /// Block the NewTaskCreated event was emitted in
+    pub block_number: Option<u64>,
+    /// Hash of the createNewTask transaction
+    pub created_tx: Option<B256>,
+    /// What happened to the task, `None` while it is being handled
+    #[serde(deserialize_with = "outcome_name")]
+    pub outcome: Option<OutcomeName>,
+    /// Hash of the response transaction
+    pub response_tx: Option<B256>,
+    /// Block the response transaction was mined in
+    pub response_block: Option<u64>,
+    /// Whether the response got `reorg.confirmations` blocks on top of it
+    pub confirmed: bool,
+    /// Gas used by the response transaction
+    pub gas_used: Option<u64>,
+    /// Error class of the last failed response
+    pub failure_class: Option<String>,
+    /// Why the operator refused to attest to a rejected task
+    #[serde(default)]
+    pub rejection_reason: Option<String>,
+    /// Message of the error the last failed response ran into
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// Failed attempts at responding since the task was seen or revived
+    #[serde(default)]
+    pub attempts: u32,
+    /// Unix time the operator first saw the task
+    pub seen_at: i64,
+    /// Unix timestamp of the block the task was created in
+    pub created_at: Option<i64>,
+    /// Unix timestamp of the block the response was mined in
+    pub responded_at: Option<i64>,
+    /// Seconds between the creation block and the response block
+    pub latency_secs: Option<u64>,
+    /// Unix time of the last update
+    pub updated_at: i64,
+}
+
+const CSV_HEADER: &str = "task_index,task_name,task_created_block,block_number,created_tx,outcome,\
+    response_tx,response_block,confirmed,gas_used,failure_class,seen_at,created_at,responded_at,latency_secs,updated_at";
+
+/// Outcome of a deserialized task, as named by [`TaskOutcome::as_str`]
+fn outcome_name<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<OutcomeName>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|name| {
+            TaskOutcome::from_str(&name)
+                .map(TaskOutcome::as_str)
+                .map_err(D::Error::custom)
+        })
+        .transpose()
+}
+
+/// `tasks` rendered as `format`
+pub fn render_tasks(
+    tasks: &[ExportedTask],
+    format: ExportFormat,
+) -> Result<String, serde_json::Error> {
+    match format {
+        ExportFormat::Csv => Ok(to_csv(tasks)),
+        ExportFormat::Json => serde_json::to_string_pretty(tasks),
+    }
+}
+
+fn to_csv(tasks: &[ExportedTask]) -> String {
+    fn opt(value: Option<impl ToString>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_default()
+    }
+    let mut csv = format!("{CSV_HEADER}\n");
+    for task in tasks {
+        let fields = [
+            task.task_index.to_string(),
+            csv_field(&task.task_name),
+            task.task_created_block.to_string(),
+            opt(task.block_number),
+            opt(task.created_tx),
+            opt(task.outcome),
+            opt(task.response_tx),
+            opt(task.response_block),
+            task.confirmed.to_string(),
+            opt(task.gas_used),
+            opt(task.failure_class.as_deref()),
+            task.seen_at.to_string(),
+            opt(task.created_at),
+            opt(task.responded_at),
+            opt(task.latency_secs),
+            task.updated_at.to_string(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// `value` quoted as RFC 4180 requires if it holds a comma, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_quotes_names_and_leaves_unknowns_empty() {
+        let task = ExportedTask {
+            task_index: 3,
+            task_name: "Quick, \"Fox\"".to_string(),
+            task_created_block: 10,
+            block_number: Some(11),
+            created_tx: None,
+            outcome: Some("responded"),
+            response_tx: None,
+            response_block: Some(12),
+            confirmed: true,
+            gas_used: Some(61_234),
+            failure_class: None,
+            rejection_reason: None,
+            last_error: None,
+            attempts: 0,
+            seen_at: 1_000,
+            created_at: Some(990),
+            responded_at: Some(1_002),
+            latency_secs: Some(12),
+            updated_at: 1_003,
+        };
+        let csv = render_tasks(std::slice::from_ref(&task), ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "3,\"Quick, \"\"Fox\"\"\",10,11,,responded,,12,true,61234,,1000,990,1002,12,1003"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_tasks(&[task], ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[0]["gasUsed"], 61_234);
+        assert!(json[0]["createdTx"].is_null());
+    }
+}
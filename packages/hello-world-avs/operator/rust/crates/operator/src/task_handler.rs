@@ -0,0 +1,159 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    primitives::{keccak256, Bytes, B256},
+    sol_types::SolValue,
+};
+use async_trait::async_trait;
+use swap_manager_utils::SwapManager::ISwapManager::Task;
+
+use crate::error::OperatorError;
+use crate::task_responder::task_response_message;
+
+/// Task type of the names without a selector
+pub const NAME_TASK_TYPE: &str = "name";
+
+/// Longest selector a task name can start with
+const MAX_SELECTOR_LEN: usize = 32;
+
+/// Type of the task named `name`, the selector before the first `:` such as `swap` for
+/// swap payloads, [`NAME_TASK_TYPE`] for plain names
+pub fn task_type(name: &str) -> &str {
+    match name.split_once(':') {
+        Some((selector, _))
+            if !selector.is_empty()
+                && selector.len() <= MAX_SELECTOR_LEN
+                && selector
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_') =>
+        {
+            selector
+        }
+        _ => NAME_TASK_TYPE,
+    }
+}
+
+/// Computes the response to one type of task.
+///
+/// The operator signs the keccak256 hash of the response with the EIP-191 prefix. The
+/// SwapManager, the aggregator and the challenger verify signatures over the name echo, so a
+/// handler producing another payload is only of use against a SwapManager verifying it.
+#[async_trait]
+pub trait TaskHandler: Send + Sync + std::fmt::Debug {
+    /// [`task_type`] of the tasks the handler is registered for
+    fn task_type(&self) -> &'static str;
+
+    /// Encoded response to task `task_index`
+    async fn response(&self, task_index: u32, task: &Task) -> Result<Bytes, OperatorError>;
+}
+
+/// Echoes `Hello, <name>`, the response the SwapManager verifies for every task
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NameEchoHandler;
+
+#[async_trait]
+impl TaskHandler for NameEchoHandler {
+    fn task_type(&self) -> &'static str {
+        NAME_TASK_TYPE
+    }
+
+    async fn response(&self, _task_index: u32, task: &Task) -> Result<Bytes, OperatorError> {
+        Ok(task_response_message(&task.name).abi_encode_packed().into())
+    }
+}
+
+/// Handlers by task type, the tasks of a type without a handler go to the fallback,
+/// [`NameEchoHandler`] unless set with [`Self::fallback`]
+#[derive(Debug, Clone)]
+pub struct TaskHandlers {
+    handlers: HashMap<&'static str, Arc<dyn TaskHandler>>,
+    default: Arc<dyn TaskHandler>,
+}
+
+impl Default for TaskHandlers {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            default: Arc::new(NameEchoHandler),
+        }
+    }
+}
+
+impl TaskHandlers {
+    /// Route the tasks of the type of `handler` to it, replacing the previous handler
+    pub fn register(mut self, handler: impl TaskHandler + 'static) -> Self {
+        self.handlers.insert(handler.task_type(), Arc::new(handler));
+        self
+    }
+
+    /// Handle the tasks of the types without a handler with `handler`
+    pub fn fallback(mut self, handler: impl TaskHandler + 'static) -> Self {
+        self.default = Arc::new(handler);
+        self
+    }
+
+    /// Handler of the task named `name`
+    pub fn handler(&self, name: &str) -> &dyn TaskHandler {
+        self.handlers
+            .get(task_type(name))
+            .unwrap_or(&self.default)
+            .as_ref()
+    }
+
+    /// Hash of the response to task `task_index` that the operator signs
+    pub async fn response_hash(&self, task_index: u32, task: &Task) -> Result<B256, OperatorError> {
+        let response = self.handler(&task.name).response(task_index, task).await?;
+        Ok(keccak256(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_responder::task_response_hash;
+
+    #[derive(Debug)]
+    struct SwapHandler;
+
+    #[async_trait]
+    impl TaskHandler for SwapHandler {
+        fn task_type(&self) -> &'static str {
+            "swap"
+        }
+
+        async fn response(&self, task_index: u32, _task: &Task) -> Result<Bytes, OperatorError> {
+            Ok(task_index.abi_encode().into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tasks_are_routed_by_selector() {
+        assert_eq!(task_type("QuickFox1"), NAME_TASK_TYPE);
+        assert_eq!(task_type("swap:00ff"), "swap");
+        assert_eq!(task_type("Quick:Fox"), NAME_TASK_TYPE);
+        assert_eq!(task_type(":Fox"), NAME_TASK_TYPE);
+
+        let task = |name: &str| Task {
+            name: name.to_string(),
+            taskCreatedBlock: 1,
+        };
+        let handlers = TaskHandlers::default();
+        for name in ["QuickFox1", "swap:00ff"] {
+            assert_eq!(
+                handlers.response_hash(3, &task(name)).await.unwrap(),
+                task_response_hash(name)
+            );
+        }
+
+        let handlers = handlers.register(SwapHandler);
+        assert_eq!(handlers.handler("swap:00ff").task_type(), "swap");
+        assert_eq!(
+            handlers.response_hash(3, &task("swap:00ff")).await.unwrap(),
+            keccak256(3u32.abi_encode())
+        );
+        assert_eq!(
+            handlers.response_hash(3, &task("QuickFox1")).await.unwrap(),
+            task_response_hash("QuickFox1")
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use alloy::{
+    primitives::{Address, B256},
+    providers::{Provider, ProviderBuilder, RootProvider, WsConnect},
+    pubsub::Subscription,
+    rpc::types::{BlockNumberOrTag, Filter, Log},
+    sol_types::SolEvent,
+};
+use futures::StreamExt;
+use swap_manager_utils::SwapManager::{ISwapManager::Task, SwapManager};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::error::ChainError;
+use crate::services::Services;
+
+/// Default capacity of the channel between the listener and its consumer
+pub const TASK_CHANNEL_CAPACITY: usize = 256;
+
+/// A `NewTaskCreated` event decoded from the SwapManager logs
+#[derive(Debug, Clone)]
+pub struct NewTask {
+    /// Index of the task in the SwapManager
+    pub index: u32,
+    /// Task as stored on chain
+    pub task: Task,
+    /// Block the event was emitted in
+    pub block_number: Option<u64>,
+    /// Hash of the block the event was emitted in
+    pub block_hash: Option<B256>,
+    /// Hash of the createNewTask transaction
+    pub transaction_hash: Option<B256>,
+}
+
+impl NewTask {
+    /// Decode a `NewTaskCreated` log
+    pub fn from_log(log: &Log) -> Result<Self, ChainError> {
+        let decoded = log.log_decode::<SwapManager::NewTaskCreated>()?;
+        let SwapManager::NewTaskCreated { taskIndex, task } = decoded.inner.data;
+        Ok(Self {
+            index: taskIndex,
+            task,
+            block_number: log.block_number,
+            block_hash: log.block_hash,
+            transaction_hash: log.transaction_hash,
+        })
+    }
+}
+
+/// Filter matching `NewTaskCreated` events of the given SwapManager
+pub fn new_task_filter(swap_manager_address: Address) -> Filter {
+    Filter::new()
+        .address(swap_manager_address)
+        .event_signature(SwapManager::NewTaskCreated::SIGNATURE_HASH)
+}
+
+/// Subscribes to `NewTaskCreated` events over WebSocket and forwards them to a channel
+#[derive(Debug, Clone)]
+pub struct TaskListener {
+    ws_url: String,
+    swap_manager_address: Address,
+    services: Services,
+}
+
+impl TaskListener {
+    /// Create a listener for the SwapManager deployed at `swap_manager_address`, logging
+    /// it by its name in `services`
+    pub fn new(
+        ws_url: impl Into<String>,
+        services: &Services,
+        swap_manager_address: Address,
+    ) -> Self {
+        Self {
+            ws_url: ws_url.into(),
+            swap_manager_address,
+            services: services.clone(),
+        }
+    }
+
+    /// Forward every new task to `sender` until the subscription closes or the receiver is dropped
+    pub async fn run(&self, sender: mpsc::Sender<NewTask>) -> Result<(), ChainError> {
+        let (ws_provider, subscription) = self.subscribe().await?;
+        forward_tasks(ws_provider, subscription, sender).await
+    }
+
+    /// Connect and subscribe, then forward tasks from a dedicated tokio task.
+    ///
+    /// Connection errors are returned here rather than through the join handle, so
+    /// callers can fall back to another source when WebSocket is unavailable.
+    pub async fn spawn(
+        self,
+        capacity: usize,
+    ) -> Result<(mpsc::Receiver<NewTask>, JoinHandle<Result<(), ChainError>>), ChainError> {
+        let (ws_provider, subscription) = self.subscribe().await?;
+        let (sender, receiver) = mpsc::channel(capacity);
+        let handle = tokio::spawn(forward_tasks(ws_provider, subscription, sender));
+        Ok((receiver, handle))
+    }
+
+    async fn subscribe(&self) -> Result<(RootProvider, Subscription<Log>), ChainError> {
+        let ws_provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .on_ws(WsConnect::new(self.ws_url.clone()))
+            .await?;
+        let filter =
+            new_task_filter(self.swap_manager_address).from_block(BlockNumberOrTag::Latest);
+        let subscription = ws_provider.subscribe_logs(&filter).await?;
+        info!(
+            swap_manager = %self.services.named(self.swap_manager_address),
+            ws_url = %self.ws_url,
+            "Listening for new tasks"
+        );
+        Ok((ws_provider, subscription))
+    }
+}
+
+/// Keeps `_ws_provider` alive for as long as the subscription is consumed
+async fn forward_tasks(
+    _ws_provider: RootProvider,
+    subscription: Subscription<Log>,
+    sender: mpsc::Sender<NewTask>,
+) -> Result<(), ChainError> {
+    let mut new_task_stream = subscription.into_stream();
+    while let Some(log) = new_task_stream.next().await {
+        match NewTask::from_log(&log) {
+            Ok(new_task) => {
+                if sender.send(new_task).await.is_err() {
+                    info!("Task receiver dropped, stopping listener");
+                    return Ok(());
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to decode NewTaskCreated log"),
+        }
+    }
+
+    Err(ChainError::SubscriptionClosed("NewTaskCreated"))
+}
@@ -0,0 +1,449 @@
+use clap::ValueEnum;
+use rand::{distr::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::chaos::ChaosNames;
+use crate::error::{ConfigError, SpamError};
+use crate::swap_payload::{SwapPayloadConfig, SwapPayloadGenerator};
+
+/// Template reproducing the historical `QuickFox123` style names
+pub const DEFAULT_TEMPLATE: &str = "{adj}{noun}{number}";
+
+const DEFAULT_ADJECTIVES: [&str; 5] = ["Quick", "Lazy", "Sleepy", "Noisy", "Hungry"];
+const DEFAULT_NOUNS: [&str; 5] = ["Fox", "Dog", "Cat", "Mouse", "Bear"];
+
+/// Task name generation settings, the `[spammer.names]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskNameConfig {
+    /// Words substituted for `{adj}`
+    pub adjectives: Vec<String>,
+    /// Words substituted for `{noun}`
+    pub nouns: Vec<String>,
+    /// Name template, supports `{adj}`, `{noun}`, `{number}` (0..1000) and `{uuid}`
+    pub template: String,
+    /// Seed making the generated names reproducible across runs
+    pub seed: Option<u64>,
+}
+
+impl Default for TaskNameConfig {
+    fn default() -> Self {
+        Self {
+            adjectives: DEFAULT_ADJECTIVES.map(String::from).to_vec(),
+            nouns: DEFAULT_NOUNS.map(String::from).to_vec(),
+            template: DEFAULT_TEMPLATE.to_string(),
+            seed: None,
+        }
+    }
+}
+
+/// Share of each kind of task in a mixed workload, the `[spammer.mix]` config section.
+///
+/// Weights are relative, `70/20/10` and `7/2/1` give the same mix
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskMixConfig {
+    /// Weight of the plain names generated from `[spammer.names]`
+    pub names: f64,
+    /// Weight of the swap payloads generated from `[spammer.swaps]`
+    pub swaps: f64,
+    /// Weight of the plain names padded to `oversized_bytes`
+    pub oversized: f64,
+    /// Length in bytes of the oversized names
+    pub oversized_bytes: usize,
+    /// Seed making the drawn kinds reproducible across runs
+    pub seed: Option<u64>,
+}
+
+impl Default for TaskMixConfig {
+    fn default() -> Self {
+        Self {
+            names: 70.0,
+            swaps: 20.0,
+            oversized: 10.0,
+            oversized_bytes: 4096,
+            seed: None,
+        }
+    }
+}
+
+impl TaskMixConfig {
+    /// Check that the weights are non-negative and not all zero
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let weights = [self.names, self.swaps, self.oversized];
+        if weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(ConfigError::Invalid(
+                "spammer.mix weights must be finite and at least 0".to_string(),
+            ));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(ConfigError::Invalid(
+                "spammer.mix needs a weight greater than 0".to_string(),
+            ));
+        }
+        if self.oversized > 0.0 && self.oversized_bytes == 0 {
+            return Err(ConfigError::Invalid(
+                "spammer.mix.oversized_bytes must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What the spammer passes to `createNewTask`
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadKind {
+    /// Names generated from `[spammer.names]`
+    #[default]
+    Names,
+    /// Swap payloads generated from `[spammer.swaps]`
+    Swaps,
+    /// Names, swap payloads and oversized names drawn as weighted in `[spammer.mix]`
+    Mixed,
+}
+
+/// Produces the names of the created tasks, plain or carrying a swap payload
+#[derive(Debug)]
+pub enum TaskNames {
+    /// Plain names
+    Words(TaskNameGenerator),
+    /// Encoded swap payloads
+    Swaps(SwapPayloadGenerator),
+    /// A weighted mix of both, boxed as it holds both generators
+    Mixed(Box<TaskMix>),
+    /// Any of the above with adversarial tasks mixed in
+    Chaos(Box<ChaosNames>),
+}
+
+impl TaskNames {
+    /// Generator of the payloads of `kind`
+    pub fn new(
+        kind: PayloadKind,
+        names: &TaskNameConfig,
+        swaps: &SwapPayloadConfig,
+        mix: &TaskMixConfig,
+    ) -> Result<Self, SpamError> {
+        Ok(match kind {
+            PayloadKind::Names => Self::Words(TaskNameGenerator::new(names)?),
+            PayloadKind::Swaps => Self::Swaps(SwapPayloadGenerator::new(swaps)?),
+            PayloadKind::Mixed => Self::Mixed(Box::new(TaskMix::new(names, swaps, mix)?)),
+        })
+    }
+
+    /// Produce the next task name
+    pub fn next_name(&mut self) -> String {
+        match self {
+            Self::Words(generator) => generator.next_name(),
+            Self::Swaps(generator) => generator.next_name(),
+            Self::Mixed(generator) => generator.next_name(),
+            Self::Chaos(generator) => generator.next_name(),
+        }
+    }
+
+    /// Whether the next name repeats a chaos mode rapid duplicate, to be sent without waiting
+    pub fn repeat_pending(&self) -> bool {
+        match self {
+            Self::Chaos(generator) => generator.repeat_pending(),
+            _ => false,
+        }
+    }
+}
+
+/// Kind of task drawn by a [`TaskMix`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixedKind {
+    /// Plain name
+    Name,
+    /// Swap payload
+    Swap,
+    /// Plain name padded past the usual lengths
+    Oversized,
+}
+
+/// Draws the kind of each task by the weights of `[spammer.mix]`
+#[derive(Debug)]
+pub struct TaskMix {
+    names: TaskNameGenerator,
+    swaps: Option<SwapPayloadGenerator>,
+    weights: [(MixedKind, f64); 3],
+    oversized_bytes: usize,
+    rng: StdRng,
+}
+
+impl TaskMix {
+    /// Build the mix, the swap generator only if swaps have a weight
+    pub fn new(
+        names: &TaskNameConfig,
+        swaps: &SwapPayloadConfig,
+        mix: &TaskMixConfig,
+    ) -> Result<Self, SpamError> {
+        mix.validate()?;
+        let rng = match mix.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Ok(Self {
+            names: TaskNameGenerator::new(names)?,
+            swaps: (mix.swaps > 0.0)
+                .then(|| SwapPayloadGenerator::new(swaps))
+                .transpose()?,
+            weights: [
+                (MixedKind::Name, mix.names),
+                (MixedKind::Swap, mix.swaps),
+                (MixedKind::Oversized, mix.oversized),
+            ],
+            oversized_bytes: mix.oversized_bytes,
+            rng,
+        })
+    }
+
+    /// Draw the kind of the next task
+    pub fn next_kind(&mut self) -> MixedKind {
+        draw_weighted(&mut self.rng, &self.weights)
+    }
+
+    /// Produce the next task name, of a kind drawn by weight
+    pub fn next_name(&mut self) -> String {
+        match self.next_kind() {
+            MixedKind::Name => self.names.next_name(),
+            MixedKind::Swap => match &mut self.swaps {
+                Some(swaps) => swaps.next_name(),
+                None => self.names.next_name(),
+            },
+            MixedKind::Oversized => {
+                let mut name = self.names.next_name();
+                name.push('-');
+                let padding = self.oversized_bytes.saturating_sub(name.len());
+                name.extend(
+                    (&mut self.rng)
+                        .sample_iter(Alphanumeric)
+                        .take(padding)
+                        .map(char::from),
+                );
+                name
+            }
+        }
+    }
+}
+
+/// One of `choices` drawn with a chance proportional to its weight, which must not all be 0
+pub(crate) fn draw_weighted<K: Copy>(rng: &mut StdRng, choices: &[(K, f64)]) -> K {
+    let total: f64 = choices.iter().map(|(_, weight)| weight).sum();
+    let mut draw = rng.random_range(0.0..total);
+    for &(choice, weight) in choices {
+        if draw < weight {
+            return choice;
+        }
+        draw -= weight;
+    }
+    // Rounding left the draw past the last weight
+    choices
+        .iter()
+        .rev()
+        .find(|(_, weight)| *weight > 0.0)
+        .map(|&(choice, _)| choice)
+        .expect("a weight is greater than 0")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Adjective,
+    Noun,
+    Number,
+    Uuid,
+}
+
+/// Generates task names by filling a template with random words
+#[derive(Debug)]
+pub struct TaskNameGenerator {
+    adjectives: Vec<String>,
+    nouns: Vec<String>,
+    segments: Vec<Segment>,
+    rng: StdRng,
+}
+
+impl TaskNameGenerator {
+    /// Build a generator, failing on empty word lists or unknown placeholders
+    pub fn new(config: &TaskNameConfig) -> Result<Self, SpamError> {
+        let segments = parse_template(&config.template)?;
+        if segments.contains(&Segment::Adjective) && config.adjectives.is_empty() {
+            return Err(SpamError::Template(
+                "template uses {adj} but no adjectives are configured".to_string(),
+            ));
+        }
+        if segments.contains(&Segment::Noun) && config.nouns.is_empty() {
+            return Err(SpamError::Template(
+                "template uses {noun} but no nouns are configured".to_string(),
+            ));
+        }
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Ok(Self {
+            adjectives: config.adjectives.clone(),
+            nouns: config.nouns.clone(),
+            segments,
+            rng,
+        })
+    }
+
+    /// Produce the next task name
+    pub fn next_name(&mut self) -> String {
+        let mut name = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => name.push_str(text),
+                Segment::Adjective => {
+                    name.push_str(&self.adjectives[self.rng.random_range(0..self.adjectives.len())])
+                }
+                Segment::Noun => {
+                    name.push_str(&self.nouns[self.rng.random_range(0..self.nouns.len())])
+                }
+                Segment::Number => name.push_str(&self.rng.random_range(0..1000u16).to_string()),
+                Segment::Uuid => {
+                    let uuid = uuid::Builder::from_random_bytes(self.rng.random()).into_uuid();
+                    name.push_str(&uuid.to_string())
+                }
+            }
+        }
+        name
+    }
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>, SpamError> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..].find('}').ok_or_else(|| {
+            SpamError::Template(format!("unclosed placeholder in template {template}"))
+        })?;
+        let segment = match &rest[start + 1..start + end] {
+            "adj" => Segment::Adjective,
+            "noun" => Segment::Noun,
+            "number" => Segment::Number,
+            "uuid" => Segment::Uuid,
+            other => {
+                return Err(SpamError::Template(format!(
+                    "unknown placeholder {{{other}}} in template {template}"
+                )))
+            }
+        };
+        segments.push(segment);
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_payload::SWAP_PAYLOAD_PREFIX;
+
+    #[test]
+    fn test_seeded_generators_replay_the_same_names() {
+        let config = TaskNameConfig {
+            template: "{adj}-{noun}-{uuid}".to_string(),
+            seed: Some(42),
+            ..Default::default()
+        };
+        let mut first = TaskNameGenerator::new(&config).unwrap();
+        let mut second = TaskNameGenerator::new(&config).unwrap();
+
+        for _ in 0..10 {
+            assert_eq!(first.next_name(), second.next_name());
+        }
+    }
+
+    #[test]
+    fn test_template_uses_custom_words() {
+        let config = TaskNameConfig {
+            adjectives: vec!["Red".to_string()],
+            nouns: vec!["Swap".to_string()],
+            template: "task_{adj}{noun}".to_string(),
+            seed: Some(1),
+        };
+        let mut generator = TaskNameGenerator::new(&config).unwrap();
+
+        assert_eq!(generator.next_name(), "task_RedSwap");
+    }
+
+    #[test]
+    fn test_mixed_workloads_follow_the_weights() {
+        let names = TaskNameConfig {
+            seed: Some(1),
+            ..Default::default()
+        };
+        let swaps = SwapPayloadConfig {
+            seed: Some(2),
+            ..Default::default()
+        };
+        let mix = TaskMixConfig {
+            oversized_bytes: 512,
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut first = TaskMix::new(&names, &swaps, &mix).unwrap();
+        let mut second = TaskMix::new(&names, &swaps, &mix).unwrap();
+        let generated: Vec<String> = (0..1000).map(|_| first.next_name()).collect();
+        for name in &generated {
+            assert_eq!(*name, second.next_name());
+        }
+
+        let swap_count = generated
+            .iter()
+            .filter(|name| name.starts_with(SWAP_PAYLOAD_PREFIX))
+            .count();
+        let oversized_count = generated.iter().filter(|name| name.len() == 512).count();
+        let name_count = generated.len() - swap_count - oversized_count;
+        assert!((620..780).contains(&name_count), "{name_count} names");
+        assert!((140..260).contains(&swap_count), "{swap_count} swaps");
+        assert!(
+            (50..150).contains(&oversized_count),
+            "{oversized_count} oversized"
+        );
+
+        let only_names = TaskMixConfig {
+            swaps: 0.0,
+            oversized: 0.0,
+            ..mix
+        };
+        let mut mixed = TaskMix::new(&names, &swaps, &only_names).unwrap();
+        assert!((0..100).all(|_| mixed.next_kind() == MixedKind::Name));
+        for invalid in [
+            TaskMixConfig {
+                names: 0.0,
+                ..only_names.clone()
+            },
+            TaskMixConfig {
+                names: -1.0,
+                ..Default::default()
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        for template in ["{colour}", "{adj", "{noun}"] {
+            let config = TaskNameConfig {
+                nouns: vec![],
+                template: template.to_string(),
+                ..Default::default()
+            };
+            assert!(TaskNameGenerator::new(&config).is_err(), "{template}");
+        }
+    }
+}
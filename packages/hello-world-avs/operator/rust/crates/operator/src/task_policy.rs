@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use alloy::primitives::Address;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::ens::AddressOrName;
+use crate::error::{ConfigError, PolicyViolation};
+use crate::services::Services;
+use crate::swap_payload::SwapTaskPayload;
+
+/// Rules a task must pass before the operator responds to it
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskPolicyRules {
+    /// Longest task name in bytes, the encoded payload of swap tasks included
+    pub max_name_length: Option<usize>,
+    /// Regex task names must match
+    pub name_pattern: Option<String>,
+    /// Tokens swap payloads may trade, any if empty, as addresses or ENS names
+    pub allowed_tokens: Vec<AddressOrName>,
+    /// Addresses swap payloads must not trade, as addresses or ENS names
+    pub banned_addresses: Vec<AddressOrName>,
+}
+
+/// The `[validation]` config section
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationConfig {
+    /// Check the tasks against the rules and reject those failing them (`VALIDATION_ENABLED`)
+    pub enabled: bool,
+    /// TOML policy file holding the rules instead of `rules` (`VALIDATION_POLICY_FILE`)
+    pub policy_file: Option<PathBuf>,
+    /// Rules applied without a policy file
+    pub rules: TaskPolicyRules,
+}
+
+impl ValidationConfig {
+    /// Check that the rules load and their pattern compiles, their ENS names are resolved
+    /// later
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(rules) = self.load_rules()? {
+            compile_pattern(&rules)?;
+        }
+        Ok(())
+    }
+
+    /// Rules in effect, read from the policy file if set, `None` if validation is disabled
+    pub fn load_rules(&self) -> Result<Option<TaskPolicyRules>, ConfigError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let rules = match &self.policy_file {
+            Some(path) => {
+                let policy = std::fs::read_to_string(path).map_err(|e| {
+                    ConfigError::Invalid(format!(
+                        "failed to read policy file {}: {e}",
+                        path.display()
+                    ))
+                })?;
+                toml::from_str(&policy).map_err(|e| {
+                    ConfigError::Invalid(format!("invalid policy file {}: {e}", path.display()))
+                })?
+            }
+            None => self.rules.clone(),
+        };
+        Ok(Some(rules))
+    }
+}
+
+fn compile_pattern(rules: &TaskPolicyRules) -> Result<Option<Regex>, ConfigError> {
+    rules
+        .name_pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| ConfigError::Invalid(format!("invalid validation name_pattern: {e}")))
+}
+
+/// Compiled [`TaskPolicyRules`]
+#[derive(Debug, Clone)]
+pub struct TaskPolicy {
+    rules: TaskPolicyRules,
+    name_pattern: Option<Regex>,
+    allowed_tokens: Vec<Address>,
+    banned_addresses: Vec<Address>,
+    services: Services,
+}
+
+impl TaskPolicy {
+    /// Policy enforcing `rules`, failing on ENS names that weren't resolved into the
+    /// names of `services`
+    pub fn new(rules: TaskPolicyRules, services: &Services) -> Result<Self, ConfigError> {
+        let resolve = |addresses: &[AddressOrName]| {
+            addresses
+                .iter()
+                .map(|address| address.address(services.names()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            name_pattern: compile_pattern(&rules)?,
+            allowed_tokens: resolve(&rules.allowed_tokens)?,
+            banned_addresses: resolve(&rules.banned_addresses)?,
+            rules,
+            services: services.clone(),
+        })
+    }
+
+    /// Policy of `config`, read from its policy file if set, `None` if validation is disabled
+    pub fn from_config(
+        config: &ValidationConfig,
+        services: &Services,
+    ) -> Result<Option<Self>, ConfigError> {
+        config
+            .load_rules()?
+            .map(|rules| Self::new(rules, services))
+            .transpose()
+    }
+
+    /// Fail with the first rule the task named `task_name` breaks
+    pub fn check(&self, task_name: &str) -> Result<(), PolicyViolation> {
+        if let Some(max) = self.rules.max_name_length {
+            if task_name.len() > max {
+                return Err(PolicyViolation::NameTooLong {
+                    length: task_name.len(),
+                    max,
+                });
+            }
+        }
+        if let Some(pattern) = &self.name_pattern {
+            if !pattern.is_match(task_name) {
+                return Err(PolicyViolation::NameMismatch(pattern.to_string()));
+            }
+        }
+        let Some(payload) = SwapTaskPayload::decode(task_name)? else {
+            return Ok(());
+        };
+        for token in [payload.token_in, payload.token_out] {
+            if self.banned_addresses.contains(&token) {
+                return Err(PolicyViolation::BannedAddress(self.services.named(token)));
+            }
+            if !self.allowed_tokens.is_empty() && !self.allowed_tokens.contains(&token) {
+                return Err(PolicyViolation::TokenNotAllowed(self.services.named(token)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_policy_rejects_tasks_breaking_a_rule() {
+        let (weth, usdc, banned) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let swap = |token_out| {
+            SwapTaskPayload {
+                token_in: weth,
+                token_out,
+                amount_in: U256::from(10u64).pow(U256::from(18u64)),
+                min_amount_out: U256::from(3_000_000_000u64),
+                slippage_bps: 50,
+                deadline: 1_700_000_000,
+            }
+            .encode()
+        };
+        let policy = TaskPolicy::new(
+            TaskPolicyRules {
+                max_name_length: Some(1_024),
+                name_pattern: Some("^(swap:[0-9a-f]+|[A-Za-z]+[0-9]+)$".to_string()),
+                allowed_tokens: vec![weth.into(), usdc.into()],
+                banned_addresses: vec![banned.into()],
+            },
+            &Services::default(),
+        )
+        .unwrap();
+
+        policy.check("QuickFox1").unwrap();
+        policy.check(&swap(usdc)).unwrap();
+        assert!(matches!(
+            policy.check("Quick Fox"),
+            Err(PolicyViolation::NameMismatch(_))
+        ));
+        assert!(matches!(
+            policy.check(&"A".repeat(1_025)),
+            Err(PolicyViolation::NameTooLong { length: 1_025, .. })
+        ));
+        assert!(matches!(
+            policy.check(&swap(banned)),
+            Err(PolicyViolation::BannedAddress(address)) if address.address() == banned
+        ));
+        assert!(matches!(
+            policy.check(&swap(Address::repeat_byte(4))),
+            Err(PolicyViolation::TokenNotAllowed(_))
+        ));
+        assert!(matches!(
+            policy.check("swap:00"),
+            Err(PolicyViolation::Payload(_))
+        ));
+
+        assert!(TaskPolicy::new(
+            TaskPolicyRules {
+                name_pattern: Some("(".to_string()),
+                ..TaskPolicyRules::default()
+            },
+            &Services::default()
+        )
+        .is_err());
+    }
+}
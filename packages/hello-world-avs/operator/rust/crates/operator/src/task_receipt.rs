@@ -0,0 +1,210 @@
+use std::fmt::Write as _;
+
+use alloy::primitives::B256;
+use clap::ValueEnum;
+use serde::Serialize;
+use swap_manager_utils::SwapManager::ISwapManager::Task;
+
+use crate::swap_payload::SwapTaskPayload;
+use crate::task_export::ExportedTask;
+use crate::task_handler::TaskHandlers;
+
+/// Output format of `operator tasks show`
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReceiptFormat {
+    /// A line per field, for a terminal
+    #[default]
+    Text,
+    /// A JSON object, for scripts
+    Json,
+}
+
+/// The lifecycle of a task, from its creation to the confirmation of its response, as
+/// recorded in the task store and derived from it
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskReceipt {
+    /// The task as stored
+    #[serde(flatten)]
+    pub task: ExportedTask,
+    /// Swap the task name encodes, `None` for plain task names
+    pub payload: Option<SwapTaskPayload>,
+    /// Why the task name looks like a swap payload but doesn't decode
+    pub payload_error: Option<String>,
+    /// Hash of the response the operator signs for the task
+    pub response_hash: Option<B256>,
+    /// Why the operator computes no response to the task
+    pub response_error: Option<String>,
+    /// Blocks mined on top of the response block, the response block included, `None` if
+    /// unknown
+    pub confirmations: Option<u64>,
+}
+
+impl TaskReceipt {
+    /// Receipt of `task`, with the response computed by `handlers` and the confirmations
+    /// counted up to the chain head `head`
+    pub async fn new(task: ExportedTask, handlers: &TaskHandlers, head: Option<u64>) -> Self {
+        let (payload, payload_error) = match SwapTaskPayload::decode(&task.task_name) {
+            Ok(payload) => (payload, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let response = handlers
+            .response_hash(
+                task.task_index,
+                &Task {
+                    name: task.task_name.clone(),
+                    taskCreatedBlock: task.task_created_block,
+                },
+            )
+            .await;
+        let (response_hash, response_error) = match response {
+            Ok(hash) => (Some(hash), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let confirmations = task
+            .response_block
+            .zip(head)
+            .map(|(response_block, head)| (head + 1).saturating_sub(response_block));
+        Self {
+            task,
+            payload,
+            payload_error,
+            response_hash,
+            response_error,
+            confirmations,
+        }
+    }
+
+    /// The receipt rendered as `format`
+    pub fn render(&self, format: ReceiptFormat) -> Result<String, serde_json::Error> {
+        match format {
+            ReceiptFormat::Text => Ok(self.to_text()),
+            ReceiptFormat::Json => serde_json::to_string_pretty(self),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        fn opt(value: Option<impl ToString>) -> String {
+            value.map_or_else(|| "-".to_string(), |v| v.to_string())
+        }
+        let task = &self.task;
+        let mut lines = vec![
+            ("Task", task.task_index.to_string()),
+            ("Name", task.task_name.clone()),
+            ("Outcome", task.outcome.unwrap_or("in progress").to_string()),
+            ("", String::new()),
+            ("Created tx", opt(task.created_tx)),
+            ("Created block", opt(task.block_number)),
+            ("Reference block", task.task_created_block.to_string()),
+            ("Created at", opt(task.created_at)),
+            ("Seen at", task.seen_at.to_string()),
+        ];
+        match (&self.payload, &self.payload_error) {
+            (Some(payload), _) => lines.extend([
+                ("", String::new()),
+                ("Token in", payload.token_in.to_string()),
+                ("Token out", payload.token_out.to_string()),
+                ("Amount in", payload.amount_in.to_string()),
+                ("Min amount out", payload.min_amount_out.to_string()),
+                ("Slippage (bps)", payload.slippage_bps.to_string()),
+                ("Deadline", payload.deadline.to_string()),
+            ]),
+            (None, Some(error)) => lines.push(("Payload error", error.clone())),
+            (None, None) => {}
+        }
+        lines.extend([
+            ("", String::new()),
+            (
+                "Response hash",
+                match (&self.response_hash, &self.response_error) {
+                    (Some(hash), _) => hash.to_string(),
+                    (None, error) => format!("- ({})", opt(error.as_deref())),
+                },
+            ),
+            ("Response tx", opt(task.response_tx)),
+            ("Response block", opt(task.response_block)),
+            ("Responded at", opt(task.responded_at)),
+            ("Latency (s)", opt(task.latency_secs)),
+            ("Gas used", opt(task.gas_used)),
+            ("Confirmations", opt(self.confirmations)),
+            ("Confirmed", task.confirmed.to_string()),
+            ("", String::new()),
+            ("Attempts", task.attempts.to_string()),
+            ("Failure class", opt(task.failure_class.as_deref())),
+            ("Last error", opt(task.last_error.as_deref())),
+            ("Rejection", opt(task.rejection_reason.as_deref())),
+            ("Updated at", task.updated_at.to_string()),
+        ]);
+        let mut text = String::new();
+        for (label, value) in lines {
+            if label.is_empty() {
+                text.push('\n');
+            } else {
+                let _ = writeln!(text, "{:<16}{value}", format!("{label}:"));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    use crate::task_responder::task_response_hash;
+
+    #[tokio::test]
+    async fn test_receipt_decodes_the_payload_and_counts_confirmations() {
+        let name = SwapTaskPayload {
+            token_in: Address::repeat_byte(1),
+            token_out: Address::repeat_byte(2),
+            amount_in: U256::from(10u64).pow(U256::from(18u64)),
+            min_amount_out: U256::from(3_000_000_000u64),
+            slippage_bps: 50,
+            deadline: 1_700_000_000,
+        }
+        .encode();
+        let task = ExportedTask {
+            task_index: 3,
+            task_name: name.clone(),
+            task_created_block: 10,
+            block_number: Some(11),
+            created_tx: Some(B256::repeat_byte(1)),
+            outcome: Some("failed"),
+            response_tx: None,
+            response_block: Some(12),
+            confirmed: false,
+            gas_used: None,
+            failure_class: Some("nonce".to_string()),
+            rejection_reason: None,
+            last_error: Some("nonce too low".to_string()),
+            attempts: 2,
+            seen_at: 1_000,
+            created_at: None,
+            responded_at: None,
+            latency_secs: None,
+            updated_at: 1_010,
+        };
+
+        let receipt = TaskReceipt::new(task.clone(), &TaskHandlers::default(), Some(15)).await;
+        assert_eq!(receipt.payload.as_ref().unwrap().slippage_bps, 50);
+        assert_eq!(receipt.response_hash, Some(task_response_hash(&name)));
+        assert_eq!(receipt.confirmations, Some(4));
+        let text = receipt.render(ReceiptFormat::Text).unwrap();
+        assert!(text.contains("Last error:     nonce too low"));
+        assert!(text.contains("Slippage (bps): 50"));
+
+        let plain = TaskReceipt::new(
+            ExportedTask {
+                task_name: "swap:00".to_string(),
+                ..task
+            },
+            &TaskHandlers::default(),
+            None,
+        )
+        .await;
+        assert!(plain.payload_error.is_some());
+        assert_eq!(plain.confirmations, None);
+    }
+}
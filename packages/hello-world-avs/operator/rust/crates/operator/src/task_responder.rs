@@ -0,0 +1,487 @@
+use alloy::dyn_abi::DynSolValue;
+use alloy::{
+    primitives::{eip191_hash_message, keccak256, Address, Bytes, B256, U256},
+    providers::Provider,
+    rpc::types::TransactionReceipt,
+    sol_types::SolValue,
+};
+use async_trait::async_trait;
+use swap_manager_utils::SwapManager::{ISwapManager::Task, SwapManager};
+use tracing::{field, info, info_span, warn, Instrument};
+
+use crate::aggregator::{AggregatorClient, SignedTaskResponse};
+use crate::audit::SignedPayload;
+use crate::bls::BlsKey;
+use crate::control::TaskOutcome;
+use crate::error::{ChainError, OperatorError};
+use crate::key_pool::KeyPool;
+use crate::pending_tx::PendingTxWatcher;
+use crate::prometheus::{
+    record_gas_used, record_task_responded, record_tx_failure, time_rpc, PendingTxGuard,
+};
+use crate::quoting::{quote_hash, Quoter, SignedQuote, SwapQuote};
+use crate::services::Services;
+use crate::signature_scheme::sign_bls;
+use crate::signer::OperatorSigner;
+use crate::task_handler::TaskHandlers;
+use crate::task_listener::NewTask;
+use crate::webhook::WebhookEvent;
+
+const RESPOND_TO_TASK: &str = "respondToTask";
+
+/// Message the operator attests to for a task named `name`
+pub fn task_response_message(name: &str) -> String {
+    format!("Hello, {}", name)
+}
+
+/// Hash of the response message, signed with the EIP-191 prefix
+pub fn task_response_hash(name: &str) -> B256 {
+    keccak256(task_response_message(name).abi_encode_packed())
+}
+
+/// EIP-191 digest of the response message that the SwapManager verifies
+pub fn task_response_digest(name: &str) -> B256 {
+    eip191_hash_message(task_response_hash(name))
+}
+
+/// ABI encode `(address[] operators, bytes[] signatures, uint32 referenceBlock)` as
+/// expected by the `signature` argument of respondToTask
+pub fn encode_signature_data(
+    operators: &[Address],
+    signatures: &[Bytes],
+    reference_block: u64,
+) -> Bytes {
+    DynSolValue::Tuple(vec![
+        DynSolValue::Array(operators.iter().map(|a| DynSolValue::Address(*a)).collect()),
+        DynSolValue::Array(
+            signatures
+                .iter()
+                .map(|s| DynSolValue::Bytes(s.to_vec()))
+                .collect(),
+        ),
+        DynSolValue::Uint(U256::from(reference_block), 32),
+    ])
+    .abi_encode_params()
+    .into()
+}
+
+/// What a [`TaskResponder`] did with a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskResponse {
+    /// Outcome recorded for the task
+    pub outcome: TaskOutcome,
+    /// Hash of the response transaction, if one was mined
+    pub tx_hash: Option<B256>,
+    /// Block the response transaction was mined in
+    pub block_number: Option<u64>,
+    /// Gas used by the response transaction
+    pub gas_used: Option<u64>,
+}
+
+/// Handles the tasks the operator decided to respond to
+#[async_trait]
+pub trait TaskResponder: Send + Sync + std::fmt::Debug {
+    /// Operation name used by the retry logs and metrics
+    fn name(&self) -> &'static str;
+
+    /// Respond to `new_task`, retried on transient errors
+    async fn respond(&self, new_task: &NewTask) -> Result<TaskResponse, OperatorError>;
+}
+
+/// Signs tasks with the operator key and submits respondToTask to the SwapManager.
+///
+/// Every response is a transaction of its own. Unlike createNewTask, which the spammer packs
+/// into Multicall3 batches, the SwapManager files a response under `msg.sender` in
+/// `allTaskResponses` and offers no batch entry point: responses relayed through Multicall3
+/// would land under its address, leaving the operator slashable for tasks it answered
+#[derive(Debug)]
+pub struct SwapManagerResponder {
+    signer: OperatorSigner,
+    operator: Address,
+    keys: KeyPool,
+    swap_manager_address: Address,
+    watcher: PendingTxWatcher,
+    quoter: Option<Quoter>,
+    handlers: TaskHandlers,
+    bls_key: Option<BlsKey>,
+    services: Services,
+}
+
+impl SwapManagerResponder {
+    /// Create a responder sending transactions through `rpc_url` and `watcher`, auditing
+    /// and notifying them as set in `services`
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        signer: OperatorSigner,
+        swap_manager_address: Address,
+        watcher: PendingTxWatcher,
+    ) -> Result<Self, OperatorError> {
+        let signer = signer.services(services.clone());
+        Ok(Self {
+            operator: signer.address(),
+            keys: KeyPool::single(rpc_url, &signer)?,
+            signer,
+            swap_manager_address,
+            watcher: watcher.services(services.clone()),
+            quoter: None,
+            handlers: TaskHandlers::default(),
+            bls_key: None,
+            services: services.clone(),
+        })
+    }
+
+    /// Compute the signed responses with `handlers` instead of echoing every task name
+    pub fn handlers(mut self, handlers: TaskHandlers) -> Self {
+        self.handlers = handlers;
+        self
+    }
+
+    /// Submit the responses from the keys of `keys` in turn, through their private relay if
+    /// set, the signer still signs them
+    pub fn key_pool(mut self, keys: KeyPool) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Sign the responses submitted on chain with the BLS `key` rather than the ECDSA signer,
+    /// which still pays for the transactions and signs for an aggregator
+    pub fn bls_signing(mut self, key: BlsKey) -> Self {
+        self.bls_key = Some(key);
+        self
+    }
+
+    /// Quote the swap of swap tasks with `quoter`, the quote is signed along the responses
+    /// sent to an aggregator and logged otherwise
+    pub fn quoter(mut self, quoter: Quoter) -> Self {
+        self.quoter = Some(quoter);
+        self
+    }
+
+    /// Respond on behalf of `operator`, whose signing key in the stake registry is the
+    /// signer rather than the operator address itself
+    pub fn operator(mut self, operator: Address) -> Self {
+        self.operator = operator;
+        self
+    }
+
+    /// Address of the operator the responses are signed for
+    pub fn operator_address(&self) -> Address {
+        self.operator
+    }
+
+    /// Sign the digest of task `task_index` and encode it with the current block as
+    /// reference block
+    pub async fn sign_task(&self, task_index: u32, task: &Task) -> Result<Bytes, OperatorError> {
+        let span = info_span!("sign", reference_block = field::Empty);
+        async {
+            let response_hash = self.handlers.response_hash(task_index, task).await?;
+            let signature = self.sign_response(task_index, response_hash).await?;
+            let current_block = time_rpc(
+                "eth_blockNumber",
+                self.keys.signer_key().provider().get_block_number(),
+            )
+            .await?;
+            tracing::Span::current().record("reference_block", current_block);
+            Ok(encode_signature_data(
+                &[self.operator],
+                &[signature],
+                current_block,
+            ))
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Signature of `response_hash` in the scheme of the deployment, BLS if a key is set
+    async fn sign_response(
+        &self,
+        task_index: u32,
+        response_hash: B256,
+    ) -> Result<Bytes, OperatorError> {
+        match &self.bls_key {
+            Some(key) => {
+                self.services.audit_signature(
+                    self.signer.address(),
+                    SignedPayload::Bls,
+                    Some(task_index),
+                    response_hash,
+                );
+                Ok(sign_bls(key, response_hash))
+            }
+            None => Ok(self
+                .signer
+                .sign_task_message(task_index, response_hash.as_slice())
+                .await?
+                .as_bytes()
+                .into()),
+        }
+    }
+
+    /// Sign `new_task` for an aggregator instead of submitting the response ourselves
+    pub async fn signed_response(
+        &self,
+        new_task: &NewTask,
+    ) -> Result<SignedTaskResponse, OperatorError> {
+        let response_hash = self
+            .handlers
+            .response_hash(new_task.index, &new_task.task)
+            .await?;
+        let signature = self
+            .signer
+            .sign_task_message(new_task.index, response_hash.as_slice())
+            .instrument(info_span!("sign"))
+            .await?;
+        Ok(SignedTaskResponse {
+            task_index: new_task.index,
+            task_name: new_task.task.name.clone(),
+            task_created_block: new_task.task.taskCreatedBlock,
+            operator: self.signer.address(),
+            signature: signature.as_bytes().into(),
+            quote: self.signed_quote(new_task).await?,
+        })
+    }
+
+    /// Quote of the swap of `new_task` signed with the operator key, `None` without a
+    /// quoter, for tasks without a swap payload or if quoting failed
+    pub async fn signed_quote(
+        &self,
+        new_task: &NewTask,
+    ) -> Result<Option<SignedQuote>, OperatorError> {
+        let Some(quote) = self.quote(new_task).await else {
+            return Ok(None);
+        };
+        let signature = self
+            .signer
+            .sign_task_message(
+                new_task.index,
+                quote_hash(new_task.index, quote.amount_out, quote.fee).as_slice(),
+            )
+            .await?;
+        Ok(Some(SignedQuote {
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            signature: signature.as_bytes().into(),
+        }))
+    }
+
+    async fn quote(&self, new_task: &NewTask) -> Option<SwapQuote> {
+        let quoter = self.quoter.as_ref()?;
+        let span = info_span!("quote", amount_out = field::Empty, fee = field::Empty);
+        match quoter
+            .quote_task(&new_task.task.name)
+            .instrument(span.clone())
+            .await
+        {
+            Ok(Some(quote)) => {
+                span.record("amount_out", field::display(quote.amount_out));
+                span.record("fee", quote.fee);
+                info!(
+                    amount_out = %quote.amount_out,
+                    fee = quote.fee,
+                    gas_estimate = %quote.gas_estimate,
+                    "Quoted swap"
+                );
+                Some(quote)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                // The response stands on its own, a missing quote doesn't hold it back
+                warn!(error = %e, "Failed to quote the swap, responding without a quote");
+                None
+            }
+        }
+    }
+
+    /// Respond to `new_task`, returning the receipt of the successful response transaction
+    pub async fn submit_response(
+        &self,
+        new_task: &NewTask,
+    ) -> Result<TransactionReceipt, OperatorError> {
+        let _pending = PendingTxGuard::new();
+        match self.send_response(new_task).await {
+            Ok(receipt) => {
+                record_gas_used(RESPOND_TO_TASK, receipt.gas_used);
+                record_task_responded();
+                self.services.notify(WebhookEvent::ResponseSubmitted {
+                    task_index: new_task.index,
+                    tx_hash: receipt.transaction_hash,
+                });
+                Ok(receipt)
+            }
+            Err(e) => {
+                record_tx_failure(RESPOND_TO_TASK);
+                self.services.notify_tx_failure(RESPOND_TO_TASK, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Estimate and `eth_call` the response to `new_task` without sending it, returning the gas estimate
+    pub async fn simulate_response(&self, new_task: &NewTask) -> Result<u64, OperatorError> {
+        let signature_data = self.sign_task(new_task.index, &new_task.task).await?;
+        let key = self.keys.signer_key();
+        let swap_manager_contract = SwapManager::new(self.swap_manager_address, key.provider());
+        let tx = swap_manager_contract
+            .respondToTask(new_task.task.clone(), new_task.index, signature_data)
+            .from(key.address())
+            .into_transaction_request();
+        Ok(self
+            .watcher
+            .simulate(key.provider(), RESPOND_TO_TASK, tx)
+            .await?)
+    }
+
+    async fn send_response(&self, new_task: &NewTask) -> Result<TransactionReceipt, OperatorError> {
+        info!("Signing and responding to task");
+        // respondToTask has no room for the quote, it is only logged
+        self.quote(new_task).await;
+        let signature_data = self.sign_task(new_task.index, &new_task.task).await?;
+        let key = self.keys.next_key();
+        let swap_manager_contract = SwapManager::new(self.swap_manager_address, key.provider());
+
+        let nonce = key.nonces().next_nonce(key.provider()).await?;
+        let tx = swap_manager_contract
+            .respondToTask(new_task.task.clone(), new_task.index, signature_data)
+            .from(key.address())
+            .nonce(nonce)
+            .into_transaction_request();
+        let sent = match key.relay() {
+            Some(relay) => {
+                self.watcher
+                    .send_private(
+                        key.provider(),
+                        &relay.provider,
+                        relay.fallback_after,
+                        RESPOND_TO_TASK,
+                        tx,
+                    )
+                    .await
+            }
+            None => self.watcher.send(key.provider(), RESPOND_TO_TASK, tx).await,
+        };
+        let receipt = match sent {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                key.nonces().resync(key.provider()).await?;
+                return Err(e.into());
+            }
+        };
+        if !receipt.status() {
+            return Err(ChainError::Reverted {
+                method: RESPOND_TO_TASK,
+                tx_hash: receipt.transaction_hash,
+            }
+            .into());
+        }
+
+        info!(
+            tx_hash = %receipt.transaction_hash,
+            block_number = receipt.block_number,
+            "Responded to task"
+        );
+        Ok(receipt)
+    }
+}
+
+#[async_trait]
+impl TaskResponder for SwapManagerResponder {
+    fn name(&self) -> &'static str {
+        RESPOND_TO_TASK
+    }
+
+    async fn respond(&self, new_task: &NewTask) -> Result<TaskResponse, OperatorError> {
+        if self.watcher.is_dry_run() {
+            self.simulate_response(new_task).await?;
+            return Ok(TaskResponse {
+                outcome: TaskOutcome::Simulated,
+                tx_hash: None,
+                block_number: None,
+                gas_used: None,
+            });
+        }
+        let receipt = self.submit_response(new_task).await?;
+        Ok(TaskResponse {
+            outcome: TaskOutcome::Responded,
+            tx_hash: Some(receipt.transaction_hash),
+            block_number: receipt.block_number,
+            gas_used: Some(receipt.gas_used),
+        })
+    }
+}
+
+/// Hands signed responses to an aggregator, which submits them once the quorum is met
+#[derive(Debug)]
+pub struct AggregatorResponder {
+    responder: SwapManagerResponder,
+    client: AggregatorClient,
+}
+
+impl AggregatorResponder {
+    /// Sign with the key of `responder` and send to `client`
+    pub fn new(responder: SwapManagerResponder, client: AggregatorClient) -> Self {
+        Self { responder, client }
+    }
+}
+
+#[async_trait]
+impl TaskResponder for AggregatorResponder {
+    fn name(&self) -> &'static str {
+        "sendToAggregator"
+    }
+
+    async fn respond(&self, new_task: &NewTask) -> Result<TaskResponse, OperatorError> {
+        let response = self.responder.signed_response(new_task).await?;
+        let status = self.client.send(&response).await?;
+        info!(?status, "Sent task response to aggregator");
+        Ok(TaskResponse {
+            outcome: TaskOutcome::Aggregated,
+            tx_hash: None,
+            block_number: None,
+            gas_used: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_response_digest_of_any_name(name in any::<String>()) {
+            let hash = task_response_hash(&name);
+            prop_assert_eq!(hash, keccak256(format!("Hello, {name}")));
+            prop_assert_eq!(task_response_digest(&name), eip191_hash_message(hash));
+
+            let signer = PrivateKeySigner::from_bytes(&B256::repeat_byte(7)).unwrap();
+            let signature = signer.sign_message_sync(hash.as_slice()).unwrap();
+            prop_assert_eq!(
+                signature
+                    .recover_address_from_prehash(&task_response_digest(&name))
+                    .unwrap(),
+                signer.address()
+            );
+        }
+
+        #[test]
+        fn test_signature_data_round_trips(
+            signers in proptest::collection::vec(
+                (any::<[u8; 20]>(), proptest::collection::vec(any::<u8>(), 0..130)),
+                0..8,
+            ),
+            reference_block in any::<u32>(),
+        ) {
+            let (operators, signatures): (Vec<Address>, Vec<Bytes>) = signers
+                .into_iter()
+                .map(|(operator, signature)| (Address::from(operator), Bytes::from(signature)))
+                .unzip();
+            let data = encode_signature_data(&operators, &signatures, u64::from(reference_block));
+            let decoded =
+                <(Vec<Address>, Vec<Bytes>, u32)>::abi_decode_params(&data, true).unwrap();
+            prop_assert_eq!(decoded, (operators, signatures, reference_block));
+        }
+    }
+}
@@ -0,0 +1,861 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use alloy::{primitives::Address, providers::Provider, rpc::types::BlockNumberOrTag};
+use async_trait::async_trait;
+use eigensdk::common::SdkProvider;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::error::{BoxError, ChainError, ConfigError, OperatorError};
+use crate::health::SourceHealth;
+use crate::prometheus::{set_source_lag, time_rpc};
+use crate::retry::{retry, RetryConfig};
+use crate::services::Services;
+use crate::task_listener::{new_task_filter, NewTask, TaskListener, TASK_CHANNEL_CAPACITY};
+use crate::task_store::StoredTask;
+use crate::work_queue::TaskAcks;
+
+/// Largest block range requested in a single `eth_getLogs` call
+pub const MAX_POLL_BLOCK_RANGE: u64 = 2_000;
+
+/// A stream of tasks the operator should respond to
+#[async_trait]
+pub trait TaskSource: Send + std::fmt::Debug {
+    /// Wait for the next task, `None` once the source is exhausted.
+    ///
+    /// Cancel safe: dropping the future before it completes loses no task.
+    async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError>;
+
+    /// Progress reported to the health endpoints
+    fn health(&self) -> Arc<SourceHealth>;
+
+    /// Acknowledgements of the handled tasks, for sources delivering a task again until
+    /// it is acknowledged
+    fn acks(&self) -> Option<TaskAcks> {
+        None
+    }
+
+    /// Cursor told about the handled tasks, for sources persisting the block to resume from
+    /// so that it stays at or before the first task not handled yet
+    fn cursor(&self) -> Option<HandledCursor> {
+        None
+    }
+}
+
+/// How the operator discovers new tasks
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSourceKind {
+    /// WebSocket subscription, falling back to polling if it can't be established
+    #[default]
+    Auto,
+    /// WebSocket subscription only
+    Ws,
+    /// `eth_getLogs` polling only
+    Polling,
+}
+
+/// The `[source]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskSourceConfig {
+    /// Which source to use
+    pub kind: TaskSourceKind,
+    /// Seconds between two polls of the chain head
+    pub poll_interval_secs: u64,
+    /// File persisting the first block with a task not handled yet, so restarts resume
+    /// where they stopped
+    pub cursor_file: Option<PathBuf>,
+    /// Blocks the source may fall behind the head, after downtime or slow RPC calls, before
+    /// it catches up with concurrent `eth_getLogs` calls, never if 0
+    pub catch_up_lag_blocks: u64,
+    /// `eth_getLogs` calls of up to [`MAX_POLL_BLOCK_RANGE`] blocks in flight while catching up
+    pub catch_up_concurrency: usize,
+}
+
+impl Default for TaskSourceConfig {
+    fn default() -> Self {
+        Self {
+            kind: TaskSourceKind::Auto,
+            poll_interval_secs: 12,
+            cursor_file: None,
+            catch_up_lag_blocks: 10_000,
+            catch_up_concurrency: 4,
+        }
+    }
+}
+
+/// When and how fast a lagging source scans the blocks up to the head
+#[derive(Debug, Clone, Copy)]
+struct CatchUp {
+    lag_blocks: u64,
+    concurrency: usize,
+}
+
+impl CatchUp {
+    fn new(config: &TaskSourceConfig) -> Self {
+        Self {
+            lag_blocks: config.catch_up_lag_blocks,
+            concurrency: config.catch_up_concurrency.max(1),
+        }
+    }
+
+    /// Whether a source whose next block to scan is `next_block` lags too far behind `head`
+    fn lagging(&self, next_block: u64, head: u64) -> bool {
+        self.lag_blocks > 0 && head.saturating_sub(next_block) >= self.lag_blocks
+    }
+
+    /// Last block of the window scanned at once from `from_block` towards `head`
+    fn window_end(&self, from_block: u64, head: u64) -> u64 {
+        head.min(from_block + self.concurrency as u64 * MAX_POLL_BLOCK_RANGE - 1)
+    }
+
+    /// Tasks created between `from_block` and `to_block` inclusive, fetched by concurrent
+    /// `eth_getLogs` calls over ranges of [`MAX_POLL_BLOCK_RANGE`] blocks, in order
+    async fn fetch_tasks<P: Provider>(
+        &self,
+        provider: &P,
+        swap_manager_address: Address,
+        from_block: u64,
+        to_block: u64,
+        retry_config: &RetryConfig,
+    ) -> Result<Vec<NewTask>, ChainError> {
+        let chunks: Vec<Vec<NewTask>> = stream::iter(block_ranges(from_block, to_block))
+            .map(|(from, to)| fetch_tasks(provider, swap_manager_address, from, to, retry_config))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+}
+
+/// `from_block..=to_block` split into ranges of at most [`MAX_POLL_BLOCK_RANGE`] blocks
+pub(crate) fn block_ranges(from_block: u64, to_block: u64) -> Vec<(u64, u64)> {
+    (from_block..=to_block)
+        .step_by(MAX_POLL_BLOCK_RANGE as usize)
+        .map(|from| (from, to_block.min(from + MAX_POLL_BLOCK_RANGE - 1)))
+        .collect()
+}
+
+/// Log the progress of a catch up that scanned up to `to_block` out of `head`
+fn log_catch_up(from_block: u64, to_block: u64, head: u64, tasks: usize) {
+    let lag = head - to_block;
+    set_source_lag(lag, lag > 0);
+    if lag > 0 {
+        info!(
+            from_block,
+            to_block, head, lag, tasks, "Catching up on the chain head"
+        );
+    } else {
+        info!(head, tasks, "Caught up with the chain head");
+    }
+}
+
+/// Tasks received from a [`TaskListener`] WebSocket subscription
+#[derive(Debug)]
+pub struct WsTaskSource {
+    receiver: mpsc::Receiver<NewTask>,
+    listener: Option<JoinHandle<Result<(), ChainError>>>,
+    backlog: VecDeque<NewTask>,
+    last_index: Option<u32>,
+    health: Arc<SourceHealth>,
+}
+
+impl WsTaskSource {
+    /// Subscribe to new tasks, failing if the WebSocket connection can't be established
+    pub async fn connect(
+        ws_url: &str,
+        services: &Services,
+        swap_manager_address: Address,
+    ) -> Result<Self, ChainError> {
+        let (receiver, listener) = TaskListener::new(ws_url, services, swap_manager_address)
+            .spawn(TASK_CHANNEL_CAPACITY)
+            .await?;
+        Ok(Self {
+            receiver,
+            health: Arc::new(SourceHealth::ws(listener.abort_handle())),
+            listener: Some(listener),
+            backlog: VecDeque::new(),
+            last_index: None,
+        })
+    }
+
+    /// Replay the tasks created from `from_block` up to the head before the live ones,
+    /// so nothing emitted while the operator was down is missed. A lag of
+    /// `config.catch_up_lag_blocks` or more is scanned by concurrent calls
+    pub async fn backfill(
+        mut self,
+        rpc_url: &str,
+        services: &Services,
+        swap_manager_address: Address,
+        from_block: u64,
+        config: &TaskSourceConfig,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, OperatorError> {
+        let provider = services.http_provider(rpc_url)?;
+        let head = retry(retry_config, "eth_blockNumber", || {
+            time_rpc("eth_blockNumber", provider.get_block_number())
+        })
+        .await?;
+        let catch_up = CatchUp::new(config);
+        let catching_up = catch_up.lagging(from_block, head);
+        if catching_up {
+            info!(
+                from_block,
+                head,
+                concurrency = catch_up.concurrency,
+                "Catching up on the chain head before the live subscription"
+            );
+        }
+        let mut block = from_block;
+        while block <= head {
+            let to_block = if catching_up {
+                catch_up.window_end(block, head)
+            } else {
+                head.min(block + MAX_POLL_BLOCK_RANGE - 1)
+            };
+            self.backlog.extend(
+                catch_up
+                    .fetch_tasks(
+                        &provider,
+                        swap_manager_address,
+                        block,
+                        to_block,
+                        retry_config,
+                    )
+                    .await?,
+            );
+            self.health.set_synced_block(to_block);
+            if catching_up {
+                log_catch_up(from_block, to_block, head, self.backlog.len());
+            }
+            block = to_block + 1;
+        }
+        self.health.set_synced_block(head);
+        info!(
+            from_block,
+            to_block = head,
+            tasks = self.backlog.len(),
+            "Backfilled tasks created while offline"
+        );
+        Ok(self)
+    }
+
+    async fn next_live_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+        if let Some(task) = self.backlog.pop_front() {
+            return Ok(Some(task));
+        }
+        if let Some(task) = self.receiver.recv().await {
+            return Ok(Some(task));
+        }
+        // Awaited through a reference so a cancelled call can be resumed
+        match &mut self.listener {
+            Some(listener) => {
+                let result = listener.await;
+                self.listener = None;
+                result??;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl TaskSource for WsTaskSource {
+    async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+        // Task indexes only grow, so a task at or below the last one was already
+        // yielded by the backfill and the subscription overlapping it
+        while let Some(task) = self.next_live_task().await? {
+            if self.last_index.is_some_and(|last| task.index <= last) {
+                continue;
+            }
+            self.last_index = Some(task.index);
+            return Ok(Some(task));
+        }
+        Ok(None)
+    }
+
+    fn health(&self) -> Arc<SourceHealth> {
+        self.health.clone()
+    }
+}
+
+/// Next block to poll, optionally persisted to a file
+#[derive(Debug, Clone)]
+pub struct BlockCursor {
+    next_block: u64,
+    path: Option<PathBuf>,
+}
+
+impl BlockCursor {
+    /// Load the cursor from `path`, starting at `default_block` when it doesn't exist yet
+    pub fn load(path: Option<PathBuf>, default_block: u64) -> Result<Self, OperatorError> {
+        let next_block = match &path {
+            Some(path) if path.exists() => std::fs::read_to_string(path)
+                .map_err(|e| cursor_error(path, e))?
+                .trim()
+                .parse()
+                .map_err(|e| cursor_error(path, e))?,
+            _ => default_block,
+        };
+        Ok(Self { next_block, path })
+    }
+
+    /// First block that hasn't been processed yet
+    pub fn next_block(&self) -> u64 {
+        self.next_block
+    }
+
+    /// Mark every block before `next_block` as processed
+    pub fn advance(&mut self, next_block: u64) -> Result<(), OperatorError> {
+        self.next_block = next_block;
+        if let Some(path) = &self.path {
+            write_atomically(path, &next_block.to_string()).map_err(|e| cursor_error(path, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// [`BlockCursor`] persisted no further than the first fetched task not handled yet, so the
+/// tasks buffered or queued when the operator stops are read again on the next start
+#[derive(Debug, Clone)]
+pub struct HandledCursor {
+    state: Arc<Mutex<HandledState>>,
+}
+
+#[derive(Debug)]
+struct HandledState {
+    cursor: BlockCursor,
+    /// First block not fetched yet
+    fetched: u64,
+    /// Block and index of the fetched tasks not handled yet
+    unhandled: BTreeSet<(u64, u32)>,
+}
+
+impl HandledCursor {
+    /// Track the tasks fetched from the next block of `cursor` on
+    pub fn new(cursor: BlockCursor) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(HandledState {
+                fetched: cursor.next_block(),
+                cursor,
+                unhandled: BTreeSet::new(),
+            })),
+        }
+    }
+
+    /// First block whose tasks aren't all handled, as persisted
+    pub fn next_block(&self) -> u64 {
+        self.lock().cursor.next_block()
+    }
+
+    /// Record that the blocks up to `to_block` were fetched, yielding `tasks`
+    pub fn fetched(&self, to_block: u64, tasks: &[NewTask]) -> Result<(), OperatorError> {
+        let mut state = self.lock();
+        state.fetched = to_block + 1;
+        state.unhandled.extend(
+            tasks
+                .iter()
+                .filter_map(|task| Some((task.block_number?, task.index))),
+        );
+        state.persist()
+    }
+
+    /// Record that `task` was handled, a failure to persist only means it is read again
+    /// after a restart
+    pub fn handled(&self, task: &NewTask) {
+        let Some(block_number) = task.block_number else {
+            return;
+        };
+        let mut state = self.lock();
+        if state.unhandled.remove(&(block_number, task.index)) {
+            if let Err(e) = state.persist() {
+                warn!(error = %e, "Failed to advance the block cursor");
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HandledState> {
+        self.state.lock().expect("handled cursor lock poisoned")
+    }
+}
+
+impl HandledState {
+    fn persist(&mut self) -> Result<(), OperatorError> {
+        // Tasks of the same block may follow the first unhandled one, so only the blocks
+        // before it are done
+        let next_block = self
+            .unhandled
+            .first()
+            .map_or(self.fetched, |(block_number, _)| *block_number);
+        if next_block > self.cursor.next_block() {
+            self.cursor.advance(next_block)?;
+        }
+        Ok(())
+    }
+}
+
+fn cursor_error(path: &Path, error: impl Into<BoxError>) -> OperatorError {
+    OperatorError::Cursor {
+        path: path.to_path_buf(),
+        source: error.into(),
+    }
+}
+
+/// Replace the contents of `path` so that a crash leaves the old or the new ones
+pub(crate) fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Tasks discovered by polling `eth_getLogs` over successive block ranges
+#[derive(Debug)]
+pub struct PollingTaskSource {
+    provider: SdkProvider,
+    swap_manager_address: Address,
+    poll_interval: Duration,
+    /// First block not fetched yet, ahead of the persisted cursor while tasks are unhandled
+    next_block: u64,
+    cursor: HandledCursor,
+    pending: VecDeque<NewTask>,
+    retry: RetryConfig,
+    health: Arc<SourceHealth>,
+    catch_up: CatchUp,
+    catching_up: bool,
+}
+
+impl PollingTaskSource {
+    /// Start polling at `start_block` if given, else at the persisted cursor, or at the
+    /// current head for a fresh cursor
+    pub async fn new(
+        rpc_url: &str,
+        services: &Services,
+        swap_manager_address: Address,
+        config: &TaskSourceConfig,
+        retry_config: &RetryConfig,
+        start_block: Option<u64>,
+    ) -> Result<Self, OperatorError> {
+        let provider = services.http_provider(rpc_url)?;
+        let head = retry(retry_config, "eth_blockNumber", || {
+            time_rpc("eth_blockNumber", provider.get_block_number())
+        })
+        .await?;
+        let mut cursor = BlockCursor::load(config.cursor_file.clone(), head)?;
+        if let Some(start_block) = start_block {
+            cursor.next_block = start_block;
+        }
+        info!(
+            swap_manager = %services.named(swap_manager_address),
+            from_block = cursor.next_block(),
+            "Polling for new tasks"
+        );
+        Ok(Self {
+            provider,
+            swap_manager_address,
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+            next_block: cursor.next_block(),
+            cursor: HandledCursor::new(cursor),
+            pending: VecDeque::new(),
+            retry: retry_config.clone(),
+            health: Arc::new(SourceHealth::default()),
+            catch_up: CatchUp::new(config),
+            catching_up: false,
+        })
+    }
+
+    async fn poll(&mut self) -> Result<(), OperatorError> {
+        let provider = &self.provider;
+        let head = retry(&self.retry, "eth_blockNumber", || {
+            time_rpc("eth_blockNumber", provider.get_block_number())
+        })
+        .await?;
+        let from_block = self.next_block;
+        if head < from_block {
+            self.health.set_synced_block(head);
+            return Ok(());
+        }
+        // Enter catch up on a lag of catch_up_lag_blocks, and leave it once on the head
+        if !self.catching_up && self.catch_up.lagging(from_block, head) {
+            info!(
+                from_block,
+                head,
+                concurrency = self.catch_up.concurrency,
+                "Fell behind the chain head, catching up"
+            );
+            self.catching_up = true;
+        }
+        let to_block = if self.catching_up {
+            self.catch_up.window_end(from_block, head)
+        } else {
+            head.min(from_block + MAX_POLL_BLOCK_RANGE - 1)
+        };
+        let tasks = self
+            .catch_up
+            .fetch_tasks(
+                provider,
+                self.swap_manager_address,
+                from_block,
+                to_block,
+                &self.retry,
+            )
+            .await?;
+        let found = tasks.len();
+        self.cursor.fetched(to_block, &tasks)?;
+        self.next_block = to_block + 1;
+        self.pending.extend(tasks);
+        self.health.set_synced_block(to_block);
+        if self.catching_up {
+            log_catch_up(from_block, to_block, head, found);
+            self.catching_up = to_block < head;
+        } else {
+            set_source_lag(head - to_block, false);
+        }
+        Ok(())
+    }
+}
+
+/// Tasks created between `from_block` and `to_block` inclusive
+pub(crate) async fn fetch_tasks<P: Provider>(
+    provider: &P,
+    swap_manager_address: Address,
+    from_block: u64,
+    to_block: u64,
+    retry_config: &RetryConfig,
+) -> Result<Vec<NewTask>, ChainError> {
+    let filter = new_task_filter(swap_manager_address)
+        .from_block(BlockNumberOrTag::Number(from_block))
+        .to_block(BlockNumberOrTag::Number(to_block));
+    let logs = retry(retry_config, "eth_getLogs", || {
+        time_rpc("eth_getLogs", provider.get_logs(&filter))
+    })
+    .await?;
+    let mut tasks = Vec::with_capacity(logs.len());
+    for log in logs {
+        match NewTask::from_log(&log) {
+            Ok(task) => tasks.push(task),
+            Err(e) => warn!(error = %e, "Failed to decode NewTaskCreated log"),
+        }
+    }
+    Ok(tasks)
+}
+
+#[async_trait]
+impl TaskSource for PollingTaskSource {
+    async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+        loop {
+            if let Some(task) = self.pending.pop_front() {
+                return Ok(Some(task));
+            }
+            let before = self.next_block;
+            self.poll().await?;
+            if self.pending.is_empty() && self.next_block == before {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+    }
+
+    fn health(&self) -> Arc<SourceHealth> {
+        self.health.clone()
+    }
+    fn cursor(&self) -> Option<HandledCursor> {
+        Some(self.cursor.clone())
+    }
+}
+
+/// Tasks created in a fixed past block range, exhausted once the range is read
+#[derive(Debug)]
+pub struct HistoricalTaskSource {
+    provider: SdkProvider,
+    swap_manager_address: Address,
+    next_block: u64,
+    to_block: u64,
+    pending: VecDeque<NewTask>,
+    retry: RetryConfig,
+    health: Arc<SourceHealth>,
+}
+
+impl HistoricalTaskSource {
+    /// Read the tasks created between `from_block` and `to_block` inclusive
+    pub fn new(
+        rpc_url: &str,
+        services: &Services,
+        swap_manager_address: Address,
+        from_block: u64,
+        to_block: u64,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, ConfigError> {
+        info!(
+            swap_manager = %services.named(swap_manager_address),
+            from_block,
+            to_block,
+            "Replaying past tasks"
+        );
+        Ok(Self {
+            provider: services.http_provider(rpc_url)?,
+            swap_manager_address,
+            next_block: from_block,
+            to_block,
+            pending: VecDeque::new(),
+            retry: retry_config.clone(),
+            health: Arc::new(SourceHealth::default()),
+        })
+    }
+}
+
+#[async_trait]
+impl TaskSource for HistoricalTaskSource {
+    async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+        // Ranges are fetched one at a time so a long replay doesn't hold every task
+        while self.pending.is_empty() && self.next_block <= self.to_block {
+            let to_block = self
+                .to_block
+                .min(self.next_block + MAX_POLL_BLOCK_RANGE - 1);
+            let tasks = fetch_tasks(
+                &self.provider,
+                self.swap_manager_address,
+                self.next_block,
+                to_block,
+                &self.retry,
+            )
+            .await?;
+            self.pending.extend(tasks);
+            self.next_block = to_block + 1;
+            self.health.set_synced_block(to_block);
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    fn health(&self) -> Arc<SourceHealth> {
+        self.health.clone()
+    }
+}
+
+/// Tasks read back from the task store, exhausted once they are all delivered
+#[derive(Debug, Default)]
+pub struct StoredTaskSource {
+    pending: VecDeque<NewTask>,
+    health: Arc<SourceHealth>,
+}
+
+impl StoredTaskSource {
+    /// Deliver `tasks` in order
+    pub fn new(tasks: &[StoredTask]) -> Self {
+        Self {
+            pending: tasks.iter().map(StoredTask::new_task).collect(),
+            health: Arc::new(SourceHealth::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl TaskSource for StoredTaskSource {
+    async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+        Ok(self.pending.pop_front())
+    }
+
+    fn health(&self) -> Arc<SourceHealth> {
+        self.health.clone()
+    }
+}
+
+/// Build the task source selected by `config`, starting at `start_block` if given
+pub async fn connect_task_source(
+    rpc_url: &str,
+    ws_url: &str,
+    services: &Services,
+    swap_manager_address: Address,
+    config: &TaskSourceConfig,
+    retry_config: &RetryConfig,
+    start_block: Option<u64>,
+) -> Result<Box<dyn TaskSource>, OperatorError> {
+    let ws = match config.kind {
+        TaskSourceKind::Polling => None,
+        TaskSourceKind::Ws => {
+            Some(WsTaskSource::connect(ws_url, services, swap_manager_address).await?)
+        }
+        TaskSourceKind::Auto => {
+            match WsTaskSource::connect(ws_url, services, swap_manager_address).await {
+                Ok(source) => Some(source),
+                Err(e) => {
+                    warn!(error = %e, "WebSocket subscription unavailable, falling back to polling");
+                    None
+                }
+            }
+        }
+    };
+    match (ws, start_block) {
+        (Some(source), Some(start_block)) => Ok(Box::new(
+            source
+                .backfill(
+                    rpc_url,
+                    services,
+                    swap_manager_address,
+                    start_block,
+                    config,
+                    retry_config,
+                )
+                .await?,
+        )),
+        (Some(source), None) => Ok(Box::new(source)),
+        (None, _) => Ok(Box::new(
+            PollingTaskSource::new(
+                rpc_url,
+                services,
+                swap_manager_address,
+                config,
+                retry_config,
+                start_block,
+            )
+            .await?,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::LogData, rpc::types::Log, sol_types::SolEvent};
+    use serde_json::json;
+    use swap_manager_utils::SwapManager::{ISwapManager::Task, SwapManager::NewTaskCreated};
+
+    use super::*;
+    use crate::mockrpc::{MockReply, MockRpc};
+
+    fn task_log(swap_manager: Address, index: u32, block_number: u64) -> Log {
+        let event = NewTaskCreated {
+            taskIndex: index,
+            task: Task {
+                name: format!("Task{index}"),
+                taskCreatedBlock: block_number as u32,
+            },
+        };
+        let data: LogData = event.encode_log_data();
+        Log {
+            inner: alloy::primitives::Log {
+                address: swap_manager,
+                data,
+            },
+            block_number: Some(block_number),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unhandled_tasks_are_read_again_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("handled-cursor-{}", std::process::id()));
+        std::fs::write(&path, "10").unwrap();
+        let config = TaskSourceConfig {
+            kind: TaskSourceKind::Polling,
+            cursor_file: Some(path.clone()),
+            ..TaskSourceConfig::default()
+        };
+        let swap_manager = Address::repeat_byte(0x11);
+        let mock = MockRpc::start().await.unwrap();
+        mock.reply("eth_blockNumber", MockReply::Result(json!("0x14")));
+        let logs = [(0, 12), (1, 15), (2, 18)]
+            .map(|(index, block_number)| task_log(swap_manager, index, block_number));
+        mock.reply_once("eth_getLogs", MockReply::Result(json!(logs)));
+
+        let mut source = PollingTaskSource::new(
+            mock.url(),
+            &Services::default(),
+            swap_manager,
+            &config,
+            &RetryConfig::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        let cursor = source.cursor().unwrap();
+        let first = source.next_task().await.unwrap().unwrap();
+        let second = source.next_task().await.unwrap().unwrap();
+        assert_eq!((first.index, second.index), (0, 1));
+        // Blocks up to 20 were fetched, the cursor stays on the first unhandled task
+        assert_eq!(cursor.next_block(), 12);
+        // Only the first task is handled before stopping
+        cursor.handled(&first);
+        assert_eq!(cursor.next_block(), 15);
+        drop(source);
+
+        mock.reply("eth_getLogs", MockReply::Result(json!(logs[1..])));
+        let mut restarted = PollingTaskSource::new(
+            mock.url(),
+            &Services::default(),
+            swap_manager,
+            &config,
+            &RetryConfig::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        let cursor = restarted.cursor().unwrap();
+        let replayed = restarted.next_task().await.unwrap().unwrap();
+        assert_eq!(replayed.index, 1);
+        let from_blocks: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.method == "eth_getLogs")
+            .map(|call| call.params[0]["fromBlock"].clone())
+            .collect();
+        assert_eq!(from_blocks, [json!("0xa"), json!("0xf")]);
+
+        cursor.handled(&replayed);
+        assert_eq!(cursor.next_block(), 18);
+        cursor.handled(&restarted.next_task().await.unwrap().unwrap());
+        assert_eq!(cursor.next_block(), 21);
+        assert_eq!(
+            BlockCursor::load(Some(path.clone()), 0)
+                .unwrap()
+                .next_block(),
+            21
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_block_cursor_persists_progress() {
+        let path = std::env::temp_dir().join(format!("cursor-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut cursor = BlockCursor::load(Some(path.clone()), 10).unwrap();
+        assert_eq!(cursor.next_block(), 10);
+        cursor.advance(42).unwrap();
+
+        let reloaded = BlockCursor::load(Some(path.clone()), 10).unwrap();
+        assert_eq!(reloaded.next_block(), 42);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_catch_up_starts_on_lag_and_scans_windows_of_ranges() {
+        let catch_up = CatchUp::new(&TaskSourceConfig {
+            catch_up_lag_blocks: 1_000,
+            catch_up_concurrency: 3,
+            ..TaskSourceConfig::default()
+        });
+        assert!(!catch_up.lagging(100, 1_099));
+        assert!(catch_up.lagging(100, 1_100));
+        assert!(!catch_up.lagging(1_200, 1_100));
+        assert_eq!(catch_up.window_end(100, 100_000), 6_099);
+        assert_eq!(catch_up.window_end(100, 2_000), 2_000);
+        assert_eq!(
+            block_ranges(100, 4_500),
+            vec![(100, 2_099), (2_100, 4_099), (4_100, 4_500)]
+        );
+        assert_eq!(block_ranges(7, 7), vec![(7, 7)]);
+
+        let disabled = CatchUp::new(&TaskSourceConfig {
+            catch_up_lag_blocks: 0,
+            ..TaskSourceConfig::default()
+        });
+        assert!(!disabled.lagging(0, u64::MAX));
+    }
+}
@@ -0,0 +1,1449 @@
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, B256};
+use async_trait::async_trait;
+use serde::Deserialize;
+use sqlx::{
+    migrate::Migrator,
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    FromRow, PgPool, SqlitePool,
+};
+
+use swap_manager_utils::SwapManager::ISwapManager::Task;
+
+use crate::control::TaskOutcome;
+use crate::error::StoreError;
+use crate::slo::TaskLatency;
+use crate::task_export::ExportedTask;
+use crate::task_listener::NewTask;
+
+/// Database holding the task store
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    /// Local SQLite file at `store.path`
+    #[default]
+    Sqlite,
+    /// Postgres server at `store.url`, can be shared by several operators
+    Postgres,
+}
+
+/// The `[store]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    /// `sqlite` or `postgres` (`STORE_BACKEND`)
+    pub backend: StoreBackend,
+    /// SQLite database of seen tasks and the last processed block, disabled if unset (`STORE_PATH`)
+    pub path: Option<PathBuf>,
+    /// Postgres connection URL (`STORE_URL`)
+    pub url: Option<String>,
+    /// Size of the Postgres connection pool
+    pub max_connections: u32,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::default(),
+            path: None,
+            url: None,
+            max_connections: 5,
+        }
+    }
+}
+
+/// A task as recorded in the store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredTask {
+    /// Index of the task in the SwapManager
+    pub task_index: u32,
+    /// Name of the task
+    pub task_name: String,
+    /// Block the task was created in, from the task itself
+    pub task_created_block: u32,
+    /// Block the NewTaskCreated event was emitted in
+    pub block_number: Option<u64>,
+    /// Hash of the createNewTask transaction
+    pub created_tx: Option<B256>,
+    /// What happened to the task, `None` while it is being handled
+    pub outcome: Option<TaskOutcome>,
+    /// Hash of the response transaction
+    pub response_tx: Option<B256>,
+    /// Block the response transaction was mined in
+    pub response_block: Option<u64>,
+    /// Whether the response block got `reorg.confirmations` blocks on top of it
+    pub confirmed: bool,
+    /// Unix time of the last update
+    pub updated_at: i64,
+}
+
+impl StoredTask {
+    /// The task as delivered by a task source, to handle it again
+    pub fn new_task(&self) -> NewTask {
+        NewTask {
+            index: self.task_index,
+            task: Task {
+                name: self.task_name.clone(),
+                taskCreatedBlock: self.task_created_block,
+            },
+            block_number: self.block_number,
+            block_hash: None,
+            transaction_hash: self.created_tx,
+        }
+    }
+
+    /// Whether a restarted operator should leave the task alone
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self.outcome,
+            Some(
+                TaskOutcome::Responded
+                    | TaskOutcome::Aggregated
+                    | TaskOutcome::Skipped
+                    | TaskOutcome::Rejected
+                    | TaskOutcome::DeadLetter
+                    | TaskOutcome::Expired
+                    | TaskOutcome::Cancelled
+            )
+        )
+    }
+}
+
+/// Durable record of the tasks the operator saw and how far it got through the chain
+#[async_trait]
+pub trait TaskStore: Send + Sync + std::fmt::Debug {
+    /// Record a newly seen task, keeping the existing row if it was seen before
+    async fn record_seen(&self, task: &NewTask) -> Result<(), StoreError>;
+
+    /// Record what happened to task `task_index`, and where its response was mined for how
+    /// much gas
+    async fn record_outcome(
+        &self,
+        task_index: u32,
+        outcome: TaskOutcome,
+        response_tx: Option<B256>,
+        response_block: Option<u64>,
+        gas_used: Option<u64>,
+    ) -> Result<(), StoreError>;
+
+    /// Record the block timestamps of the creation and the response of task `task_index`
+    async fn record_latency(
+        &self,
+        task_index: u32,
+        created_at: u64,
+        responded_at: u64,
+    ) -> Result<(), StoreError>;
+
+    /// Record the error class, as given by [`failure_class`](crate::retry::failure_class), and
+    /// the message of the error the failed response to task `task_index` ran into
+    async fn record_failure(
+        &self,
+        task_index: u32,
+        class: &str,
+        error: &str,
+    ) -> Result<(), StoreError>;
+
+    /// Record why the operator refused to attest to task `task_index`
+    async fn record_rejection(&self, task_index: u32, reason: &str) -> Result<(), StoreError>;
+
+    /// Count a failed attempt at responding to task `task_index`, returning the attempts
+    /// so far and the unix time the task was first seen
+    async fn record_attempt(&self, task_index: u32) -> Result<(u32, i64), StoreError>;
+
+    /// Dead-lettered tasks, by index
+    async fn dead_letters(&self) -> Result<Vec<StoredTask>, StoreError>;
+
+    /// Tasks whose last attempt failed and that are to be attempted again, by index
+    async fn failed_tasks(&self) -> Result<Vec<StoredTask>, StoreError>;
+
+    /// Forget the outcome and the attempts of the dead-lettered task `task_index`, so it's
+    /// handled again, returning whether it was dead-lettered
+    async fn revive(&self, task_index: u32) -> Result<bool, StoreError>;
+
+    /// Mark a task not handled yet as cancelled so the operator leaves it alone, false if
+    /// the task is unknown or already handled
+    async fn cancel(&self, task_index: u32) -> Result<bool, StoreError>;
+
+    /// Latencies of the tasks created from `from` to before `to`, in unix seconds
+    async fn latencies(&self, from: i64, to: i64) -> Result<Vec<TaskLatency>, StoreError>;
+
+    /// Tasks first seen from `from` to before `to`, in unix seconds, by index
+    async fn export(&self, from: i64, to: i64) -> Result<Vec<ExportedTask>, StoreError>;
+
+    /// Write the `tasks` of another store's [`export`](TaskStore::export), replacing the
+    /// rows of the same index, and return the number written
+    async fn import(&self, tasks: &[ExportedTask]) -> Result<u64, StoreError>;
+
+    /// Look a task up by index
+    async fn task(&self, task_index: u32) -> Result<Option<StoredTask>, StoreError>;
+
+    /// Everything recorded about the handling of task `task_index`, as exported
+    async fn receipt(&self, task_index: u32) -> Result<Option<ExportedTask>, StoreError>;
+
+    /// First block whose tasks may not all be handled yet, `None` on a fresh store
+    async fn next_block(&self) -> Result<Option<u64>, StoreError>;
+
+    /// Mark the blocks before `next_block` as handled, never moving backwards
+    async fn set_next_block(&self, next_block: u64) -> Result<(), StoreError>;
+
+    /// Confirm the responses mined up to `block`, returning the indices of the tasks newly
+    /// confirmed
+    async fn confirm_responses(&self, block: u64) -> Result<Vec<u32>, StoreError>;
+
+    /// Forget the outcome and the confirmation of the tasks created or responded to from
+    /// `block` on, which a reorg dropped, and rewind the cursor to `block`. Returns the tasks
+    /// as they were
+    async fn invalidate_from(&self, block: u64) -> Result<Vec<StoredTask>, StoreError>;
+
+    /// Park `task` until [`unspill`](TaskStore::unspill) takes it back, returning whether
+    /// it wasn't parked already
+    async fn spill(&self, task: &NewTask) -> Result<bool, StoreError>;
+
+    /// Take back the parked task of the lowest index, `None` if none is parked
+    async fn unspill(&self) -> Result<Option<NewTask>, StoreError>;
+
+    /// Number of parked tasks
+    async fn spilled(&self) -> Result<u64, StoreError>;
+
+    /// Take or renew the lock `name` for `holder` until `ttl_secs` from now, `false` while
+    /// another holder's lock hasn't expired
+    async fn try_lock(&self, name: &str, holder: &str, ttl_secs: u64) -> Result<bool, StoreError>;
+
+    /// Release the lock `name` if `holder` holds it
+    async fn unlock(&self, name: &str, holder: &str) -> Result<(), StoreError>;
+
+    /// Hand the tasks and the cursor of the store's key over to the key `to`, keeping the
+    /// tasks `to` already has, and return the number of tasks moved. The rows of a store
+    /// holding a single key aren't keyed by it, so nothing moves
+    async fn transfer(&self, to: Address) -> Result<u64, StoreError>;
+
+    /// Wait for the pending writes and close the connections, called on shutdown
+    async fn close(&self);
+}
+
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("migrations/sqlite");
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("migrations/postgres");
+
+const TASK_COLUMNS: &str = "task_index, task_name, task_created_block, block_number, \
+    created_tx, outcome, response_tx, response_block, confirmed, updated_at";
+
+/// Columns of a `tasks` row, shared by both backends
+#[derive(FromRow)]
+struct TaskRow {
+    task_index: i64,
+    task_name: String,
+    task_created_block: i64,
+    block_number: Option<i64>,
+    created_tx: Option<String>,
+    outcome: Option<String>,
+    response_tx: Option<String>,
+    response_block: Option<i64>,
+    confirmed: bool,
+    updated_at: i64,
+}
+
+impl TryFrom<TaskRow> for StoredTask {
+    type Error = StoreError;
+
+    fn try_from(row: TaskRow) -> Result<Self, StoreError> {
+        Ok(Self {
+            task_index: row.task_index.try_into()?,
+            task_name: row.task_name,
+            task_created_block: row.task_created_block.try_into()?,
+            block_number: row.block_number.map(|block| block as u64),
+            created_tx: parse_hash(row.created_tx)?,
+            outcome: row
+                .outcome
+                .map(|outcome| TaskOutcome::from_str(&outcome))
+                .transpose()?,
+            response_tx: parse_hash(row.response_tx)?,
+            response_block: row.response_block.map(|block| block as u64),
+            confirmed: row.confirmed,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Columns of a measured task
+#[derive(FromRow)]
+struct LatencyRow {
+    task_index: i64,
+    created_at: i64,
+    responded_at: i64,
+}
+
+impl TryFrom<LatencyRow> for TaskLatency {
+    type Error = StoreError;
+
+    fn try_from(row: LatencyRow) -> Result<Self, StoreError> {
+        Ok(Self {
+            task_index: row.task_index.try_into()?,
+            created_at: row.created_at,
+            latency_secs: row.responded_at.saturating_sub(row.created_at).max(0) as u64,
+        })
+    }
+}
+
+const EXPORT_COLUMNS: &str = "task_index, task_name, task_created_block, block_number, \
+    created_tx, outcome, response_tx, response_block, confirmed, gas_used, failure_class, \
+    rejection_reason, last_error, CAST(attempts AS BIGINT) AS attempts, seen_at, created_at, responded_at, updated_at";
+
+/// Columns written by [`TaskStore::import`], in bind order
+const IMPORT_COLUMNS: &str = "task_index, task_name, task_created_block, block_number, \
+    created_tx, outcome, response_tx, response_block, confirmed, gas_used, failure_class, \
+    rejection_reason, last_error, attempts, seen_at, created_at, responded_at, updated_at";
+
+/// Columns of an exported `tasks` row
+#[derive(FromRow)]
+struct ExportRow {
+    #[sqlx(flatten)]
+    task: TaskRow,
+    gas_used: Option<i64>,
+    failure_class: Option<String>,
+    rejection_reason: Option<String>,
+    last_error: Option<String>,
+    attempts: i64,
+    seen_at: Option<i64>,
+    created_at: Option<i64>,
+    responded_at: Option<i64>,
+}
+
+impl TryFrom<ExportRow> for ExportedTask {
+    type Error = StoreError;
+
+    fn try_from(row: ExportRow) -> Result<Self, StoreError> {
+        let updated_at = row.task.updated_at;
+        let task = StoredTask::try_from(row.task)?;
+        let latency_secs =
+            row.created_at
+                .zip(row.responded_at)
+                .map(|(created_at, responded_at)| {
+                    responded_at.saturating_sub(created_at).max(0) as u64
+                });
+        Ok(Self {
+            task_index: task.task_index,
+            task_name: task.task_name,
+            task_created_block: task.task_created_block,
+            block_number: task.block_number,
+            created_tx: task.created_tx,
+            outcome: task.outcome.map(TaskOutcome::as_str),
+            response_tx: task.response_tx,
+            response_block: task.response_block,
+            confirmed: task.confirmed,
+            gas_used: row.gas_used.map(|gas| gas as u64),
+            failure_class: row.failure_class,
+            rejection_reason: row.rejection_reason,
+            last_error: row.last_error,
+            attempts: row.attempts.try_into()?,
+            seen_at: row.seen_at.unwrap_or(updated_at),
+            created_at: row.created_at,
+            responded_at: row.responded_at,
+            latency_secs,
+            updated_at,
+        })
+    }
+}
+
+const SPILLED_COLUMNS: &str =
+    "task_index, task_name, task_created_block, block_number, block_hash, created_tx";
+
+/// Columns of a `spilled_tasks` row, shared by both backends
+#[derive(FromRow)]
+struct SpilledRow {
+    task_index: i64,
+    task_name: String,
+    task_created_block: i64,
+    block_number: Option<i64>,
+    block_hash: Option<String>,
+    created_tx: Option<String>,
+}
+
+impl TryFrom<SpilledRow> for NewTask {
+    type Error = StoreError;
+
+    fn try_from(row: SpilledRow) -> Result<Self, StoreError> {
+        Ok(Self {
+            index: row.task_index.try_into()?,
+            task: Task {
+                name: row.task_name,
+                taskCreatedBlock: row.task_created_block.try_into()?,
+            },
+            block_number: row.block_number.map(|block| block as u64),
+            block_hash: parse_hash(row.block_hash)?,
+            transaction_hash: parse_hash(row.created_tx)?,
+        })
+    }
+}
+
+/// [`TaskStore`] in a local SQLite database
+#[derive(Debug, Clone)]
+pub struct SqliteTaskStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTaskStore {
+    /// Open the database at `path`, creating it and migrating its tables if needed
+    pub async fn open(path: &std::path::Path) -> Result<Self, StoreError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(|source| StoreError::Open {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Self::with_pool(pool).await
+    }
+
+    /// Store in a private in-memory database, for tests
+    pub async fn in_memory() -> Result<Self, StoreError> {
+        // A single connection, every connection would get its own database otherwise
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    async fn with_pool(pool: SqlitePool) -> Result<Self, StoreError> {
+        SQLITE_MIGRATIONS.run(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl TaskStore for SqliteTaskStore {
+    async fn record_seen(&self, task: &NewTask) -> Result<(), StoreError> {
+        sqlx::query(
+            "INSERT INTO tasks (task_index, task_name, task_created_block, block_number, created_tx, seen_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (task_index) DO NOTHING",
+        )
+        .bind(task.index)
+        .bind(&task.task.name)
+        .bind(task.task.taskCreatedBlock)
+        .bind(task.block_number.map(|block| block as i64))
+        .bind(task.transaction_hash.map(|hash| hash.to_string()))
+        .bind(now())
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_outcome(
+        &self,
+        task_index: u32,
+        outcome: TaskOutcome,
+        response_tx: Option<B256>,
+        response_block: Option<u64>,
+        gas_used: Option<u64>,
+    ) -> Result<(), StoreError> {
+        sqlx::query(
+            "UPDATE tasks SET outcome = ?, response_tx = COALESCE(?, response_tx),
+                 response_block = ?, confirmed = 0, gas_used = COALESCE(?, gas_used),
+                 updated_at = ?
+             WHERE task_index = ?",
+        )
+        .bind(outcome.as_str())
+        .bind(response_tx.map(|hash| hash.to_string()))
+        .bind(response_block.map(|block| block as i64))
+        .bind(gas_used.map(|gas| gas as i64))
+        .bind(now())
+        .bind(task_index)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_latency(
+        &self,
+        task_index: u32,
+        created_at: u64,
+        responded_at: u64,
+    ) -> Result<(), StoreError> {
+        sqlx::query("UPDATE tasks SET created_at = ?, responded_at = ? WHERE task_index = ?")
+            .bind(created_at as i64)
+            .bind(responded_at as i64)
+            .bind(task_index)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        task_index: u32,
+        class: &str,
+        error: &str,
+    ) -> Result<(), StoreError> {
+        sqlx::query("UPDATE tasks SET failure_class = ?, last_error = ? WHERE task_index = ?")
+            .bind(class)
+            .bind(error)
+            .bind(task_index)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_rejection(&self, task_index: u32, reason: &str) -> Result<(), StoreError> {
+        sqlx::query("UPDATE tasks SET rejection_reason = ? WHERE task_index = ?")
+            .bind(reason)
+            .bind(task_index)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_attempt(&self, task_index: u32) -> Result<(u32, i64), StoreError> {
+        let row: Option<(i64, i64)> = sqlx::query_as(
+            "UPDATE tasks SET attempts = attempts + 1, updated_at = ?
+             WHERE task_index = ?
+             RETURNING attempts, COALESCE(seen_at, updated_at)",
+        )
+        .bind(now())
+        .bind(task_index)
+        .fetch_optional(&self.pool)
+        .await?;
+        let (attempts, seen_at) = row.unwrap_or((1, now()));
+        Ok((attempts.try_into()?, seen_at))
+    }
+
+    async fn dead_letters(&self) -> Result<Vec<StoredTask>, StoreError> {
+        sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE outcome = ? ORDER BY task_index"
+        ))
+        .bind(TaskOutcome::DeadLetter.as_str())
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(StoredTask::try_from)
+        .collect()
+    }
+
+    async fn failed_tasks(&self) -> Result<Vec<StoredTask>, StoreError> {
+        sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE outcome = ? ORDER BY task_index"
+        ))
+        .bind(TaskOutcome::Failed.as_str())
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(StoredTask::try_from)
+        .collect()
+    }
+
+    async fn revive(&self, task_index: u32) -> Result<bool, StoreError> {
+        let revived = sqlx::query(
+            "UPDATE tasks SET outcome = NULL, attempts = 0, failure_class = NULL, last_error = NULL,
+             updated_at = ?
+             WHERE task_index = ? AND outcome = ?",
+        )
+        .bind(now())
+        .bind(task_index)
+        .bind(TaskOutcome::DeadLetter.as_str())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(revived > 0)
+    }
+
+    async fn cancel(&self, task_index: u32) -> Result<bool, StoreError> {
+        let cancelled = sqlx::query(
+            "UPDATE tasks SET outcome = ?, updated_at = ?
+             WHERE task_index = ? AND (outcome IS NULL OR outcome IN (?, ?, ?))",
+        )
+        .bind(TaskOutcome::Cancelled.as_str())
+        .bind(now())
+        .bind(task_index)
+        .bind(TaskOutcome::Paused.as_str())
+        .bind(TaskOutcome::Failed.as_str())
+        .bind(TaskOutcome::DeadLetter.as_str())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(cancelled > 0)
+    }
+
+    async fn latencies(&self, from: i64, to: i64) -> Result<Vec<TaskLatency>, StoreError> {
+        sqlx::query_as::<_, LatencyRow>(
+            "SELECT task_index, created_at, responded_at FROM tasks
+             WHERE created_at >= ? AND created_at < ? AND responded_at IS NOT NULL
+             ORDER BY task_index",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(TaskLatency::try_from)
+        .collect()
+    }
+
+    async fn export(&self, from: i64, to: i64) -> Result<Vec<ExportedTask>, StoreError> {
+        sqlx::query_as::<_, ExportRow>(&format!(
+            "SELECT {EXPORT_COLUMNS} FROM tasks WHERE seen_at >= ? AND seen_at < ?
+             ORDER BY task_index"
+        ))
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(ExportedTask::try_from)
+        .collect()
+    }
+
+    async fn import(&self, tasks: &[ExportedTask]) -> Result<u64, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        for task in tasks {
+            sqlx::query(&format!(
+                "INSERT OR REPLACE INTO tasks ({IMPORT_COLUMNS})
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            ))
+            .bind(task.task_index)
+            .bind(&task.task_name)
+            .bind(task.task_created_block)
+            .bind(task.block_number.map(|block| block as i64))
+            .bind(task.created_tx.map(|hash| hash.to_string()))
+            .bind(task.outcome)
+            .bind(task.response_tx.map(|hash| hash.to_string()))
+            .bind(task.response_block.map(|block| block as i64))
+            .bind(task.confirmed)
+            .bind(task.gas_used.map(|gas| gas as i64))
+            .bind(&task.failure_class)
+            .bind(&task.rejection_reason)
+            .bind(&task.last_error)
+            .bind(task.attempts)
+            .bind(task.seen_at)
+            .bind(task.created_at)
+            .bind(task.responded_at)
+            .bind(task.updated_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(tasks.len() as u64)
+    }
+
+    async fn task(&self, task_index: u32) -> Result<Option<StoredTask>, StoreError> {
+        sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE task_index = ?"
+        ))
+        .bind(task_index)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(StoredTask::try_from)
+        .transpose()
+    }
+
+    async fn receipt(&self, task_index: u32) -> Result<Option<ExportedTask>, StoreError> {
+        sqlx::query_as::<_, ExportRow>(&format!(
+            "SELECT {EXPORT_COLUMNS} FROM tasks WHERE task_index = ?"
+        ))
+        .bind(task_index)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(ExportedTask::try_from)
+        .transpose()
+    }
+
+    async fn next_block(&self) -> Result<Option<u64>, StoreError> {
+        let next_block: Option<i64> =
+            sqlx::query_scalar("SELECT next_block FROM cursor WHERE id = 0")
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(next_block.map(|block| block as u64))
+    }
+
+    async fn set_next_block(&self, next_block: u64) -> Result<(), StoreError> {
+        sqlx::query(
+            "INSERT INTO cursor (id, next_block) VALUES (0, ?)
+             ON CONFLICT (id) DO UPDATE SET next_block = MAX(next_block, excluded.next_block)",
+        )
+        .bind(next_block as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn confirm_responses(&self, block: u64) -> Result<Vec<u32>, StoreError> {
+        let confirmed: Vec<i64> = sqlx::query_scalar(
+            "UPDATE tasks SET confirmed = 1, updated_at = ?
+             WHERE confirmed = 0 AND response_block <= ?
+             RETURNING task_index",
+        )
+        .bind(now())
+        .bind(block as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut confirmed = confirmed
+            .into_iter()
+            .map(u32::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        confirmed.sort_unstable();
+        Ok(confirmed)
+    }
+
+    async fn invalidate_from(&self, block: u64) -> Result<Vec<StoredTask>, StoreError> {
+        let block = block as i64;
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE block_number >= ? OR response_block >= ?"
+        ))
+        .bind(block)
+        .bind(block)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query(
+            "UPDATE tasks SET outcome = NULL, response_block = NULL, responded_at = NULL,
+                 confirmed = 0, updated_at = ?
+             WHERE block_number >= ? OR response_block >= ?",
+        )
+        .bind(now())
+        .bind(block)
+        .bind(block)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE cursor SET next_block = MIN(next_block, ?)")
+            .bind(block)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        rows.into_iter().map(StoredTask::try_from).collect()
+    }
+
+    async fn spill(&self, task: &NewTask) -> Result<bool, StoreError> {
+        let spilled = sqlx::query(&format!(
+            "INSERT INTO spilled_tasks ({SPILLED_COLUMNS}, spilled_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (task_index) DO NOTHING"
+        ))
+        .bind(task.index)
+        .bind(&task.task.name)
+        .bind(task.task.taskCreatedBlock)
+        .bind(task.block_number.map(|block| block as i64))
+        .bind(task.block_hash.map(|hash| hash.to_string()))
+        .bind(task.transaction_hash.map(|hash| hash.to_string()))
+        .bind(now())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(spilled > 0)
+    }
+
+    async fn unspill(&self) -> Result<Option<NewTask>, StoreError> {
+        sqlx::query_as::<_, SpilledRow>(&format!(
+            "DELETE FROM spilled_tasks
+             WHERE task_index = (SELECT MIN(task_index) FROM spilled_tasks)
+             RETURNING {SPILLED_COLUMNS}"
+        ))
+        .fetch_optional(&self.pool)
+        .await?
+        .map(NewTask::try_from)
+        .transpose()
+    }
+
+    async fn spilled(&self) -> Result<u64, StoreError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM spilled_tasks")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count.try_into()?)
+    }
+
+    async fn try_lock(&self, name: &str, holder: &str, ttl_secs: u64) -> Result<bool, StoreError> {
+        let now = now();
+        let acquired: Option<String> = sqlx::query_scalar(
+            "INSERT INTO locks (name, holder, expires_at) VALUES (?, ?, ?)
+             ON CONFLICT (name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE locks.holder = excluded.holder OR locks.expires_at <= ?
+             RETURNING holder",
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(now + ttl_secs as i64)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(acquired.is_some())
+    }
+
+    async fn unlock(&self, name: &str, holder: &str) -> Result<(), StoreError> {
+        sqlx::query("DELETE FROM locks WHERE name = ? AND holder = ?")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer(&self, _to: Address) -> Result<u64, StoreError> {
+        Ok(0)
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+}
+
+/// [`TaskStore`] in a Postgres database, rows are keyed by operator so several
+/// operators can share it
+#[derive(Debug, Clone)]
+pub struct PostgresTaskStore {
+    pool: PgPool,
+    operator: String,
+}
+
+impl PostgresTaskStore {
+    /// Connect a pool of `max_connections` to `url` and run the pending migrations
+    pub async fn connect(
+        url: &str,
+        max_connections: u32,
+        operator: Address,
+    ) -> Result<Self, StoreError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await
+            .map_err(StoreError::Connect)?;
+        POSTGRES_MIGRATIONS.run(&pool).await?;
+        Ok(Self {
+            pool,
+            operator: operator.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl TaskStore for PostgresTaskStore {
+    async fn record_seen(&self, task: &NewTask) -> Result<(), StoreError> {
+        sqlx::query(
+            "INSERT INTO tasks (operator, task_index, task_name, task_created_block, block_number, created_tx, seen_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+             ON CONFLICT (operator, task_index) DO NOTHING",
+        )
+        .bind(&self.operator)
+        .bind(i64::from(task.index))
+        .bind(&task.task.name)
+        .bind(i64::from(task.task.taskCreatedBlock))
+        .bind(task.block_number.map(|block| block as i64))
+        .bind(task.transaction_hash.map(|hash| hash.to_string()))
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_outcome(
+        &self,
+        task_index: u32,
+        outcome: TaskOutcome,
+        response_tx: Option<B256>,
+        response_block: Option<u64>,
+        gas_used: Option<u64>,
+    ) -> Result<(), StoreError> {
+        sqlx::query(
+            "UPDATE tasks SET outcome = $1, response_tx = COALESCE($2, response_tx),
+                 response_block = $3, confirmed = FALSE, gas_used = COALESCE($4, gas_used),
+                 updated_at = $5
+             WHERE operator = $6 AND task_index = $7",
+        )
+        .bind(outcome.as_str())
+        .bind(response_tx.map(|hash| hash.to_string()))
+        .bind(response_block.map(|block| block as i64))
+        .bind(gas_used.map(|gas| gas as i64))
+        .bind(now())
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_latency(
+        &self,
+        task_index: u32,
+        created_at: u64,
+        responded_at: u64,
+    ) -> Result<(), StoreError> {
+        sqlx::query(
+            "UPDATE tasks SET created_at = $1, responded_at = $2
+             WHERE operator = $3 AND task_index = $4",
+        )
+        .bind(created_at as i64)
+        .bind(responded_at as i64)
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        task_index: u32,
+        class: &str,
+        error: &str,
+    ) -> Result<(), StoreError> {
+        sqlx::query(
+            "UPDATE tasks SET failure_class = $1, last_error = $2
+             WHERE operator = $3 AND task_index = $4",
+        )
+        .bind(class)
+        .bind(error)
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_rejection(&self, task_index: u32, reason: &str) -> Result<(), StoreError> {
+        sqlx::query(
+            "UPDATE tasks SET rejection_reason = $1 WHERE operator = $2 AND task_index = $3",
+        )
+        .bind(reason)
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_attempt(&self, task_index: u32) -> Result<(u32, i64), StoreError> {
+        let row: Option<(i32, i64)> = sqlx::query_as(
+            "UPDATE tasks SET attempts = attempts + 1, updated_at = $1
+             WHERE operator = $2 AND task_index = $3
+             RETURNING attempts, COALESCE(seen_at, updated_at)",
+        )
+        .bind(now())
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .fetch_optional(&self.pool)
+        .await?;
+        let (attempts, seen_at) = row.unwrap_or((1, now()));
+        Ok((attempts.try_into()?, seen_at))
+    }
+
+    async fn dead_letters(&self) -> Result<Vec<StoredTask>, StoreError> {
+        sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE operator = $1 AND outcome = $2
+             ORDER BY task_index"
+        ))
+        .bind(&self.operator)
+        .bind(TaskOutcome::DeadLetter.as_str())
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(StoredTask::try_from)
+        .collect()
+    }
+
+    async fn failed_tasks(&self) -> Result<Vec<StoredTask>, StoreError> {
+        sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE operator = $1 AND outcome = $2
+             ORDER BY task_index"
+        ))
+        .bind(&self.operator)
+        .bind(TaskOutcome::Failed.as_str())
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(StoredTask::try_from)
+        .collect()
+    }
+
+    async fn revive(&self, task_index: u32) -> Result<bool, StoreError> {
+        let revived = sqlx::query(
+            "UPDATE tasks SET outcome = NULL, attempts = 0, failure_class = NULL, last_error = NULL,
+             updated_at = $1
+             WHERE operator = $2 AND task_index = $3 AND outcome = $4",
+        )
+        .bind(now())
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .bind(TaskOutcome::DeadLetter.as_str())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(revived > 0)
+    }
+
+    async fn cancel(&self, task_index: u32) -> Result<bool, StoreError> {
+        let cancelled = sqlx::query(
+            "UPDATE tasks SET outcome = $1, updated_at = $2
+             WHERE operator = $3 AND task_index = $4
+                 AND (outcome IS NULL OR outcome IN ($5, $6, $7))",
+        )
+        .bind(TaskOutcome::Cancelled.as_str())
+        .bind(now())
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .bind(TaskOutcome::Paused.as_str())
+        .bind(TaskOutcome::Failed.as_str())
+        .bind(TaskOutcome::DeadLetter.as_str())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(cancelled > 0)
+    }
+
+    async fn latencies(&self, from: i64, to: i64) -> Result<Vec<TaskLatency>, StoreError> {
+        sqlx::query_as::<_, LatencyRow>(
+            "SELECT task_index, created_at, responded_at FROM tasks
+             WHERE operator = $1 AND created_at >= $2 AND created_at < $3
+                 AND responded_at IS NOT NULL
+             ORDER BY task_index",
+        )
+        .bind(&self.operator)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(TaskLatency::try_from)
+        .collect()
+    }
+
+    async fn export(&self, from: i64, to: i64) -> Result<Vec<ExportedTask>, StoreError> {
+        sqlx::query_as::<_, ExportRow>(&format!(
+            "SELECT {EXPORT_COLUMNS} FROM tasks
+             WHERE operator = $1 AND seen_at >= $2 AND seen_at < $3
+             ORDER BY task_index"
+        ))
+        .bind(&self.operator)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(ExportedTask::try_from)
+        .collect()
+    }
+
+    async fn import(&self, tasks: &[ExportedTask]) -> Result<u64, StoreError> {
+        let mut tx = self.pool.begin().await?;
+        for task in tasks {
+            sqlx::query(&format!(
+                "INSERT INTO tasks (operator, {IMPORT_COLUMNS})
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                     $18, $19)
+                 ON CONFLICT (operator, task_index) DO UPDATE SET
+                     task_name = EXCLUDED.task_name,
+                     task_created_block = EXCLUDED.task_created_block,
+                     block_number = EXCLUDED.block_number, created_tx = EXCLUDED.created_tx,
+                     outcome = EXCLUDED.outcome, response_tx = EXCLUDED.response_tx,
+                     response_block = EXCLUDED.response_block, confirmed = EXCLUDED.confirmed,
+                     gas_used = EXCLUDED.gas_used, failure_class = EXCLUDED.failure_class,
+                     rejection_reason = EXCLUDED.rejection_reason, last_error = EXCLUDED.last_error,
+                     attempts = EXCLUDED.attempts, seen_at = EXCLUDED.seen_at,
+                     created_at = EXCLUDED.created_at, responded_at = EXCLUDED.responded_at,
+                     updated_at = EXCLUDED.updated_at"
+            ))
+            .bind(&self.operator)
+            .bind(i64::from(task.task_index))
+            .bind(&task.task_name)
+            .bind(i64::from(task.task_created_block))
+            .bind(task.block_number.map(|block| block as i64))
+            .bind(task.created_tx.map(|hash| hash.to_string()))
+            .bind(task.outcome)
+            .bind(task.response_tx.map(|hash| hash.to_string()))
+            .bind(task.response_block.map(|block| block as i64))
+            .bind(task.confirmed)
+            .bind(task.gas_used.map(|gas| gas as i64))
+            .bind(&task.failure_class)
+            .bind(&task.rejection_reason)
+            .bind(&task.last_error)
+            .bind(i32::try_from(task.attempts)?)
+            .bind(task.seen_at)
+            .bind(task.created_at)
+            .bind(task.responded_at)
+            .bind(task.updated_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(tasks.len() as u64)
+    }
+
+    async fn task(&self, task_index: u32) -> Result<Option<StoredTask>, StoreError> {
+        sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE operator = $1 AND task_index = $2"
+        ))
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .fetch_optional(&self.pool)
+        .await?
+        .map(StoredTask::try_from)
+        .transpose()
+    }
+
+    async fn receipt(&self, task_index: u32) -> Result<Option<ExportedTask>, StoreError> {
+        sqlx::query_as::<_, ExportRow>(&format!(
+            "SELECT {EXPORT_COLUMNS} FROM tasks WHERE operator = $1 AND task_index = $2"
+        ))
+        .bind(&self.operator)
+        .bind(i64::from(task_index))
+        .fetch_optional(&self.pool)
+        .await?
+        .map(ExportedTask::try_from)
+        .transpose()
+    }
+
+    async fn next_block(&self) -> Result<Option<u64>, StoreError> {
+        let next_block: Option<i64> =
+            sqlx::query_scalar("SELECT next_block FROM cursors WHERE operator = $1")
+                .bind(&self.operator)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(next_block.map(|block| block as u64))
+    }
+
+    async fn set_next_block(&self, next_block: u64) -> Result<(), StoreError> {
+        sqlx::query(
+            "INSERT INTO cursors (operator, next_block) VALUES ($1, $2)
+             ON CONFLICT (operator) DO UPDATE SET next_block = GREATEST(cursors.next_block, excluded.next_block)",
+        )
+        .bind(&self.operator)
+        .bind(next_block as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn confirm_responses(&self, block: u64) -> Result<Vec<u32>, StoreError> {
+        let confirmed: Vec<i64> = sqlx::query_scalar(
+            "UPDATE tasks SET confirmed = TRUE, updated_at = $1
+             WHERE operator = $2 AND NOT confirmed AND response_block <= $3
+             RETURNING task_index",
+        )
+        .bind(now())
+        .bind(&self.operator)
+        .bind(block as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut confirmed = confirmed
+            .into_iter()
+            .map(u32::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        confirmed.sort_unstable();
+        Ok(confirmed)
+    }
+
+    async fn invalidate_from(&self, block: u64) -> Result<Vec<StoredTask>, StoreError> {
+        let block = block as i64;
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks
+             WHERE operator = $1 AND (block_number >= $2 OR response_block >= $2)"
+        ))
+        .bind(&self.operator)
+        .bind(block)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query(
+            "UPDATE tasks SET outcome = NULL, response_block = NULL, responded_at = NULL,
+                 confirmed = FALSE, updated_at = $1
+             WHERE operator = $2 AND (block_number >= $3 OR response_block >= $3)",
+        )
+        .bind(now())
+        .bind(&self.operator)
+        .bind(block)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE cursors SET next_block = LEAST(next_block, $1) WHERE operator = $2")
+            .bind(block)
+            .bind(&self.operator)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        rows.into_iter().map(StoredTask::try_from).collect()
+    }
+
+    async fn spill(&self, task: &NewTask) -> Result<bool, StoreError> {
+        let spilled = sqlx::query(&format!(
+            "INSERT INTO spilled_tasks (operator, {SPILLED_COLUMNS}, spilled_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             ON CONFLICT (operator, task_index) DO NOTHING"
+        ))
+        .bind(&self.operator)
+        .bind(i64::from(task.index))
+        .bind(&task.task.name)
+        .bind(i64::from(task.task.taskCreatedBlock))
+        .bind(task.block_number.map(|block| block as i64))
+        .bind(task.block_hash.map(|hash| hash.to_string()))
+        .bind(task.transaction_hash.map(|hash| hash.to_string()))
+        .bind(now())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(spilled > 0)
+    }
+
+    async fn unspill(&self) -> Result<Option<NewTask>, StoreError> {
+        sqlx::query_as::<_, SpilledRow>(&format!(
+            "DELETE FROM spilled_tasks
+             WHERE operator = $1 AND task_index =
+                 (SELECT MIN(task_index) FROM spilled_tasks WHERE operator = $1)
+             RETURNING {SPILLED_COLUMNS}"
+        ))
+        .bind(&self.operator)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(NewTask::try_from)
+        .transpose()
+    }
+
+    async fn spilled(&self) -> Result<u64, StoreError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM spilled_tasks WHERE operator = $1")
+                .bind(&self.operator)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count.try_into()?)
+    }
+
+    async fn try_lock(&self, name: &str, holder: &str, ttl_secs: u64) -> Result<bool, StoreError> {
+        let now = now();
+        let acquired: Option<String> = sqlx::query_scalar(
+            "INSERT INTO locks (operator, name, holder, expires_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (operator, name) DO UPDATE SET holder = EXCLUDED.holder, expires_at = EXCLUDED.expires_at
+             WHERE locks.holder = EXCLUDED.holder OR locks.expires_at <= $5
+             RETURNING holder",
+        )
+        .bind(&self.operator)
+        .bind(name)
+        .bind(holder)
+        .bind(now + ttl_secs as i64)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(acquired.is_some())
+    }
+
+    async fn unlock(&self, name: &str, holder: &str) -> Result<(), StoreError> {
+        sqlx::query("DELETE FROM locks WHERE operator = $1 AND name = $2 AND holder = $3")
+            .bind(&self.operator)
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer(&self, to: Address) -> Result<u64, StoreError> {
+        let to = to.to_string();
+        let mut tx = self.pool.begin().await?;
+        let moved = sqlx::query(
+            "UPDATE tasks SET operator = $2
+             WHERE operator = $1
+               AND task_index NOT IN (SELECT task_index FROM tasks WHERE operator = $2)",
+        )
+        .bind(&self.operator)
+        .bind(&to)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("DELETE FROM tasks WHERE operator = $1")
+            .bind(&self.operator)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO cursors (operator, next_block)
+             SELECT $2, next_block FROM cursors WHERE operator = $1
+             ON CONFLICT (operator) DO UPDATE
+             SET next_block = GREATEST(cursors.next_block, EXCLUDED.next_block)",
+        )
+        .bind(&self.operator)
+        .bind(&to)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            "INSERT INTO spilled_tasks (operator, {SPILLED_COLUMNS}, spilled_at)
+             SELECT $2, {SPILLED_COLUMNS}, spilled_at FROM spilled_tasks WHERE operator = $1
+             ON CONFLICT (operator, task_index) DO NOTHING"
+        ))
+        .bind(&self.operator)
+        .bind(&to)
+        .execute(&mut *tx)
+        .await?;
+        for table in ["cursors", "locks", "spilled_tasks"] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE operator = $1"))
+                .bind(&self.operator)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(moved)
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+}
+
+/// Open the store selected by `config` for `operator`, `None` if persistence is disabled
+pub async fn connect_task_store(
+    config: &StoreConfig,
+    operator: Address,
+) -> Result<Option<Box<dyn TaskStore>>, StoreError> {
+    match config.backend {
+        StoreBackend::Sqlite => match &config.path {
+            Some(path) => Ok(Some(Box::new(SqliteTaskStore::open(path).await?))),
+            None => Ok(None),
+        },
+        StoreBackend::Postgres => {
+            let url = config.url.as_deref().ok_or_else(|| {
+                StoreError::Invalid("store.url must be set for the postgres backend".to_string())
+            })?;
+            Ok(Some(Box::new(
+                PostgresTaskStore::connect(url, config.max_connections, operator).await?,
+            )))
+        }
+    }
+}
+
+fn parse_hash(hash: Option<String>) -> Result<Option<B256>, StoreError> {
+    hash.map(|hash| {
+        hash.parse().map_err(|_| StoreError::Corrupt {
+            what: "hash",
+            value: hash.clone(),
+        })
+    })
+    .transpose()
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swap_manager_utils::SwapManager::ISwapManager::Task;
+
+    #[tokio::test]
+    async fn test_sqlite_store_tracks_tasks_and_cursor() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        assert_eq!(store.next_block().await.unwrap(), None);
+
+        let new_task = NewTask {
+            index: 4,
+            task: Task {
+                name: "QuickFox1".to_string(),
+                taskCreatedBlock: 41,
+            },
+            block_number: Some(42),
+            block_hash: None,
+            transaction_hash: Some(B256::repeat_byte(1)),
+        };
+        store.record_seen(&new_task).await.unwrap();
+        store.record_seen(&new_task).await.unwrap();
+        assert!(!store.task(4).await.unwrap().unwrap().is_done());
+
+        store
+            .record_outcome(
+                4,
+                TaskOutcome::Responded,
+                Some(B256::repeat_byte(2)),
+                Some(43),
+                Some(61_234),
+            )
+            .await
+            .unwrap();
+        let stored = store.task(4).await.unwrap().unwrap();
+        assert!(stored.is_done());
+        assert_eq!(stored.block_number, Some(42));
+        assert_eq!(stored.created_tx, Some(B256::repeat_byte(1)));
+        assert_eq!(stored.response_tx, Some(B256::repeat_byte(2)));
+        assert!(!stored.confirmed);
+        assert_eq!(store.task(5).await.unwrap(), None);
+
+        store.record_latency(4, 1_000, 1_006).await.unwrap();
+        let latencies = store.latencies(1_000, 2_000).await.unwrap();
+        assert_eq!(latencies.len(), 1);
+        assert_eq!(latencies[0].latency_secs, 6);
+        assert!(store.latencies(0, 1_000).await.unwrap().is_empty());
+        let exported = store.export(0, i64::MAX).await.unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].gas_used, Some(61_234));
+        assert_eq!(exported[0].latency_secs, Some(6));
+        assert_eq!(exported[0].outcome, Some("responded"));
+        assert_eq!(exported[0].failure_class, None);
+        store
+            .record_failure(4, "nonce", "nonce too low")
+            .await
+            .unwrap();
+        let exported = store.export(0, i64::MAX).await.unwrap();
+        assert_eq!(exported[0].failure_class.as_deref(), Some("nonce"));
+        let receipt = store.receipt(4).await.unwrap().unwrap();
+        assert_eq!(receipt.last_error.as_deref(), Some("nonce too low"));
+        assert_eq!(receipt, exported[0]);
+        assert_eq!(store.receipt(5).await.unwrap(), None);
+        assert!(store.export(0, 1_000).await.unwrap().is_empty());
+
+        store.set_next_block(42).await.unwrap();
+        store.set_next_block(40).await.unwrap();
+        assert_eq!(store.next_block().await.unwrap(), Some(42));
+
+        assert!(store.confirm_responses(42).await.unwrap().is_empty());
+        assert_eq!(store.confirm_responses(43).await.unwrap(), vec![4]);
+        assert!(store.confirm_responses(44).await.unwrap().is_empty());
+        assert!(store.task(4).await.unwrap().unwrap().confirmed);
+
+        // A reorg dropping the response block only invalidates the response
+        let invalidated = store.invalidate_from(43).await.unwrap();
+        assert_eq!(invalidated.len(), 1);
+        assert_eq!(invalidated[0].response_block, Some(43));
+        let stored = store.task(4).await.unwrap().unwrap();
+        assert!(!stored.is_done());
+        assert!(!stored.confirmed);
+        assert_eq!(stored.response_tx, Some(B256::repeat_byte(2)));
+        assert_eq!(store.next_block().await.unwrap(), Some(42));
+        assert!(store.invalidate_from(44).await.unwrap().is_empty());
+
+        assert!(store.try_lock("task/4", "a", 60).await.unwrap());
+        assert!(store.try_lock("task/4", "a", 60).await.unwrap());
+        assert!(!store.try_lock("task/4", "b", 60).await.unwrap());
+        store.unlock("task/4", "b").await.unwrap();
+        assert!(!store.try_lock("task/4", "b", 60).await.unwrap());
+        store.unlock("task/4", "a").await.unwrap();
+        assert!(store.try_lock("task/4", "b", 0).await.unwrap());
+        // An expired lock is up for grabs
+        assert!(store.try_lock("task/4", "a", 60).await.unwrap());
+
+        assert_eq!(store.record_attempt(4).await.unwrap().0, 1);
+        assert_eq!(store.record_attempt(4).await.unwrap().0, 2);
+        assert!(!store.revive(4).await.unwrap());
+        store
+            .record_outcome(4, TaskOutcome::DeadLetter, None, None, None)
+            .await
+            .unwrap();
+        assert!(store.task(4).await.unwrap().unwrap().is_done());
+        let dead = store.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].task_index, 4);
+        assert!(store.revive(4).await.unwrap());
+        assert!(store.dead_letters().await.unwrap().is_empty());
+        assert_eq!(store.record_attempt(4).await.unwrap().0, 1);
+
+        // Revived, so not handled yet, then withdrawn
+        assert!(store.cancel(4).await.unwrap());
+        let cancelled = store.task(4).await.unwrap().unwrap();
+        assert_eq!(cancelled.outcome, Some(TaskOutcome::Cancelled));
+        assert!(cancelled.is_done());
+        assert!(!store.cancel(4).await.unwrap());
+        assert!(!store.cancel(99).await.unwrap());
+    }
+}
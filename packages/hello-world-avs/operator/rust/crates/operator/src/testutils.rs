@@ -0,0 +1,220 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{utils::parse_ether, Address, U256},
+    providers::Provider,
+};
+use eigensdk::common::get_provider;
+use swap_manager_utils::{Deployment, DeploymentError, Network, SwapManager::SwapManager};
+use testcontainers::{
+    core::{IntoContainerPort, Mount, WaitFor},
+    runners::AsyncRunner,
+    ContainerAsync, ContainerRequest, GenericImage, ImageExt,
+};
+use tokio::time::{self, Instant};
+use tracing::info;
+
+use crate::config::ContractAddresses;
+use crate::error::DevnetError;
+use crate::prometheus::time_rpc;
+
+/// Image running anvil
+pub const ANVIL_IMAGE: &str = "ghcr.io/foundry-rs/foundry";
+/// Tag of [`ANVIL_IMAGE`]
+pub const ANVIL_TAG: &str = "latest";
+/// Port anvil listens on inside the container
+const ANVIL_PORT: u16 = 8545;
+/// How often [`wait_for_responses`] checks the SwapManager
+const RESPONSE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Anvil state dump with the EigenLayer and SwapManager contracts deployed, built by
+/// `make build-anvil-state-with-deployed-contracts`
+pub fn default_anvil_state() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../../contracts/anvil/state.json")
+}
+
+/// How to start a [`Devnet`]
+#[derive(Debug, Clone)]
+pub struct DevnetConfig {
+    /// Chain to fork instead of loading `state`, its contracts come from the bundled deployment
+    pub fork_url: Option<String>,
+    /// Block to fork at, the latest if unset
+    pub fork_block_number: Option<u64>,
+    /// Anvil state dump to load when not forking
+    pub state: PathBuf,
+    /// Host port anvil is published on, a random free one if unset
+    pub host_port: Option<u16>,
+    /// Accounts given `balance` once anvil is up
+    pub fund: Vec<Address>,
+    /// Balance in wei of the funded accounts
+    pub balance: U256,
+}
+
+impl Default for DevnetConfig {
+    fn default() -> Self {
+        Self {
+            fork_url: None,
+            fork_block_number: None,
+            state: default_anvil_state(),
+            host_port: None,
+            fund: Vec::new(),
+            balance: parse_ether("100").expect("valid ether amount"),
+        }
+    }
+}
+
+/// Anvil container with the EigenLayer and SwapManager contracts, removed once dropped
+#[derive(Debug)]
+pub struct Devnet {
+    container: ContainerAsync<GenericImage>,
+    http_url: String,
+    ws_url: String,
+    deployment: Deployment,
+}
+
+impl Devnet {
+    /// Start anvil as set in `config` and fund the accounts of `config.fund`
+    pub async fn start(config: &DevnetConfig) -> Result<Self, DevnetError> {
+        let container = anvil_request(config).start().await?;
+        let port = container.get_host_port_ipv4(ANVIL_PORT.tcp()).await?;
+        let http_url = format!("http://localhost:{port}");
+        let ws_url = format!("ws://localhost:{port}");
+
+        let provider = get_provider(&http_url);
+        let chain_id = time_rpc("eth_chainId", provider.get_chain_id()).await?;
+        let deployment = match config.fork_url {
+            Some(_) => Deployment::bundled(chain_id)?,
+            None => Deployment::bundled(Network::Anvil.chain_id())?,
+        };
+        deployment.check_chain_id(chain_id)?;
+
+        let devnet = Self {
+            container,
+            http_url,
+            ws_url,
+            deployment,
+        };
+        for &address in &config.fund {
+            devnet.fund(address, config.balance).await?;
+        }
+        info!(http_url = %devnet.http_url, chain_id, "Devnet started");
+        Ok(devnet)
+    }
+
+    /// HTTP RPC endpoint
+    pub fn http_url(&self) -> &str {
+        &self.http_url
+    }
+
+    /// WebSocket RPC endpoint
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// Contracts deployed on the devnet
+    pub fn deployment(&self) -> &Deployment {
+        &self.deployment
+    }
+
+    /// Addresses of the contracts deployed on the devnet
+    pub fn contract_addresses(&self) -> Result<ContractAddresses, DeploymentError> {
+        ContractAddresses::from_deployment(&self.deployment)
+    }
+
+    /// Set the balance of `address` to `balance` wei
+    pub async fn fund(&self, address: Address, balance: U256) -> Result<(), DevnetError> {
+        fund_account(&self.http_url, address, balance).await
+    }
+
+    /// Stop and remove the container
+    pub async fn stop(self) -> Result<(), DevnetError> {
+        self.container.rm().await?;
+        Ok(())
+    }
+}
+
+/// Set the balance of `address` to `balance` wei on the anvil chain at `rpc_url`
+pub async fn fund_account(
+    rpc_url: &str,
+    address: Address,
+    balance: U256,
+) -> Result<(), DevnetError> {
+    get_provider(rpc_url)
+        .raw_request::<_, ()>("anvil_setBalance".into(), (address, balance))
+        .await?;
+    info!(%address, %balance, "Funded account");
+    Ok(())
+}
+
+/// Wait until the SwapManager at `swap_manager_address` marks every task of `tasks` as
+/// responded, failing with the ones left once `timeout` passes
+pub async fn wait_for_responses(
+    rpc_url: &str,
+    swap_manager_address: Address,
+    tasks: Range<u32>,
+    timeout: Duration,
+) -> Result<(), DevnetError> {
+    let provider = get_provider(rpc_url);
+    let swap_manager = SwapManager::new(swap_manager_address, &provider);
+    let deadline = Instant::now() + timeout;
+    let mut pending: Vec<u32> = tasks.collect();
+    loop {
+        let mut unresponded = Vec::new();
+        for task_index in pending {
+            if !swap_manager.taskWasResponded(task_index).call().await?._0 {
+                unresponded.push(task_index);
+            }
+        }
+        if unresponded.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(DevnetError::Unresponded { tasks: unresponded });
+        }
+        pending = unresponded;
+        time::sleep(RESPONSE_POLL_INTERVAL).await;
+    }
+}
+
+fn anvil_request(config: &DevnetConfig) -> ContainerRequest<GenericImage> {
+    let mut args = [
+        "--host",
+        "0.0.0.0",
+        "--port",
+        "8545",
+        "--base-fee",
+        "0",
+        "--gas-price",
+        "0",
+    ]
+    .map(String::from)
+    .to_vec();
+    if let Some(fork_url) = &config.fork_url {
+        args.extend(["--fork-url".to_string(), fork_url.clone()]);
+        if let Some(block) = config.fork_block_number {
+            args.extend(["--fork-block-number".to_string(), block.to_string()]);
+        }
+    } else {
+        args.extend(["--load-state".to_string(), "/state.json".to_string()]);
+    }
+
+    let mut request = GenericImage::new(ANVIL_IMAGE, ANVIL_TAG)
+        .with_entrypoint("anvil")
+        .with_wait_for(WaitFor::message_on_stdout("Listening on"))
+        .with_exposed_port(ANVIL_PORT.tcp())
+        .with_cmd(args);
+    if config.fork_url.is_none() {
+        request = request.with_mount(Mount::bind_mount(
+            config.state.to_string_lossy(),
+            "/state.json",
+        ));
+    }
+    if let Some(host_port) = config.host_port {
+        request = request.with_mapped_port(host_port, ANVIL_PORT.tcp());
+    }
+    request
+}
@@ -0,0 +1,562 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+    time::{Duration, Instant},
+};
+
+use alloy::providers::Provider;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, Paragraph},
+    DefaultTerminal, Frame,
+};
+use tonic::transport::Channel;
+
+use crate::control::proto::{
+    operator_control_client::OperatorControlClient, GetStatusRequest, ListRecentTasksRequest,
+    RecentTask, Status, TaskOutcome,
+};
+use crate::prometheus::{
+    PENDING_TXS, SIGNER_BALANCE, TASKS_CREATED, TASKS_RESPONDED, TASK_LATENCY, TX_FAILURES,
+};
+use crate::services::Services;
+
+/// Errors kept in the recent errors panel
+const MAX_ERRORS: usize = 100;
+/// Window the task rate is averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Recent tasks fetched per refresh to find the failed ones
+const RECENT_TASKS: u32 = 64;
+
+/// A series of a Prometheus text exposition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Metric name
+    pub name: String,
+    /// Label names and values, in exposition order
+    pub labels: Vec<(String, String)>,
+    /// Current value
+    pub value: f64,
+}
+
+impl Sample {
+    /// Value of the label `name`
+    pub fn label(&self, name: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _)| label == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Every series scraped from a `/metrics` endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics(Vec<Sample>);
+
+impl Metrics {
+    /// Series of the Prometheus text `text`, comments and malformed lines are skipped
+    pub fn parse(text: &str) -> Self {
+        Self(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(parse_sample)
+                .collect(),
+        )
+    }
+
+    /// Sum of the series of `name`, `None` if it isn't exported yet
+    pub fn total(&self, name: &str) -> Option<f64> {
+        self.0
+            .iter()
+            .filter(|sample| sample.name == name)
+            .map(|sample| sample.value)
+            .reduce(|a, b| a + b)
+    }
+
+    /// `quantile` of the summary `name`, as rendered by the exporter
+    pub fn quantile(&self, name: &str, quantile: &str) -> Option<f64> {
+        self.0
+            .iter()
+            .find(|sample| sample.name == name && sample.label("quantile") == Some(quantile))
+            .map(|sample| sample.value)
+            .filter(|value| value.is_finite())
+    }
+
+    /// Series of `name`
+    pub fn series<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Sample> + 'a {
+        self.0.iter().filter(move |sample| sample.name == name)
+    }
+
+    /// Values of the series of `name` by their `label`
+    pub fn by_label<'a>(
+        &'a self,
+        name: &'a str,
+        label: &'a str,
+    ) -> impl Iterator<Item = (&'a str, f64)> + 'a {
+        self.0
+            .iter()
+            .filter(move |sample| sample.name == name)
+            .filter_map(move |sample| Some((sample.label(label)?, sample.value)))
+    }
+}
+
+fn parse_sample(line: &str) -> Option<Sample> {
+    let (series, value) = line.rsplit_once(char::is_whitespace)?;
+    let value = value.parse().ok()?;
+    let (name, labels) = match series.trim_end().split_once('{') {
+        Some((name, labels)) => {
+            let labels = labels
+                .strip_suffix('}')?
+                .split(',')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (label, value) = pair.split_once('=')?;
+                    Some((label.to_string(), value.trim_matches('"').to_string()))
+                })
+                .collect::<Option<_>>()?;
+            (name, labels)
+        }
+        None => (series.trim_end(), Vec::new()),
+    };
+    Some(Sample {
+        name: name.to_string(),
+        labels,
+        value,
+    })
+}
+
+/// Everything read from the operator in one refresh, a source that failed holds its error
+#[derive(Debug, Default)]
+pub struct Poll {
+    /// Head block of the RPC endpoint
+    pub head_block: Option<Result<u64, String>>,
+    /// Scrape of the metrics endpoint, if one is watched
+    pub metrics: Option<Result<Metrics, String>>,
+    /// Status from the control API, if one is watched
+    pub status: Option<Result<Status, String>>,
+    /// Most recent tasks from the control API, newest first
+    pub recent_tasks: Vec<RecentTask>,
+}
+
+/// What `operator top` displays, updated from the successive [`Poll`]s
+#[derive(Debug)]
+pub struct TopState {
+    head_block: Option<u64>,
+    status: Option<Status>,
+    /// Tasks handled so far, sampled over the last [`RATE_WINDOW`]
+    handled: VecDeque<(Instant, f64)>,
+    latency: [Option<f64>; 3],
+    pending_txs: Option<f64>,
+    balance_eth: Option<f64>,
+    /// Failed transactions by method at the last scrape, `None` before the first one
+    failures: Option<Vec<(String, f64)>>,
+    failed_tasks: HashSet<(u32, i64)>,
+    errors: VecDeque<String>,
+    last_error: Option<String>,
+}
+
+impl Default for TopState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TopState {
+    /// State before the first refresh
+    pub fn new() -> Self {
+        Self {
+            head_block: None,
+            status: None,
+            handled: VecDeque::new(),
+            latency: [None; 3],
+            pending_txs: None,
+            balance_eth: None,
+            failures: None,
+            failed_tasks: HashSet::new(),
+            errors: VecDeque::new(),
+            last_error: None,
+        }
+    }
+
+    /// Fold the refresh `poll`, taken at `now`, into the state
+    pub fn record(&mut self, now: Instant, poll: Poll) {
+        match poll.head_block {
+            Some(Ok(block)) => self.head_block = Some(block),
+            Some(Err(e)) => self.push_error(format!("rpc: {e}")),
+            None => {}
+        }
+        match poll.status {
+            Some(Ok(status)) => self.status = Some(status),
+            Some(Err(e)) => self.push_error(format!("control: {e}")),
+            None => {}
+        }
+        let metrics = match poll.metrics {
+            Some(Ok(metrics)) => Some(metrics),
+            Some(Err(e)) => {
+                self.push_error(format!("metrics: {e}"));
+                None
+            }
+            None => None,
+        };
+        if let Some(metrics) = &metrics {
+            self.record_metrics(metrics);
+        }
+        let handled = self
+            .status
+            .as_ref()
+            .map(|status| status.tasks_seen as f64)
+            .or_else(|| {
+                let metrics = metrics.as_ref()?;
+                [TASKS_RESPONDED, TASKS_CREATED]
+                    .into_iter()
+                    .filter_map(|name| metrics.total(name))
+                    .reduce(|a, b| a + b)
+            });
+        if let Some(handled) = handled {
+            self.handled.push_back((now, handled));
+            while self
+                .handled
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) > RATE_WINDOW)
+            {
+                self.handled.pop_front();
+            }
+        }
+        // Oldest first, so the newest failure ends up at the top of the panel
+        for task in poll.recent_tasks.iter().rev() {
+            if task.outcome() != TaskOutcome::Failed {
+                continue;
+            }
+            let Some(task_index) = task.task_index else {
+                continue;
+            };
+            if self.failed_tasks.insert((task_index, task.timestamp)) {
+                self.push_error(format!("task {task_index} ({}) failed", task.task_name));
+            }
+        }
+    }
+
+    fn record_metrics(&mut self, metrics: &Metrics) {
+        self.latency = ["0.5", "0.95", "0.99"].map(|q| metrics.quantile(TASK_LATENCY, q));
+        self.pending_txs = metrics.total(PENDING_TXS);
+        self.balance_eth = metrics.total(SIGNER_BALANCE);
+        let failures: Vec<(String, f64)> = metrics
+            .by_label(TX_FAILURES, "method")
+            .map(|(method, count)| (method.to_string(), count))
+            .collect();
+        // The first scrape only sets the baseline
+        if let Some(known) = self.failures.replace(failures.clone()) {
+            for (method, count) in failures {
+                let before = known
+                    .iter()
+                    .find(|(known, _)| *known == method)
+                    .map_or(0.0, |(_, before)| *before);
+                if count > before {
+                    self.push_error(format!(
+                        "{method} failed {} more time(s)",
+                        (count - before) as u64
+                    ));
+                }
+            }
+        }
+    }
+
+    fn push_error(&mut self, message: String) {
+        // A source staying down would otherwise fill the panel with the same line
+        if self.last_error.as_ref() == Some(&message) {
+            return;
+        }
+        self.last_error = Some(message.clone());
+        let at = chrono::Local::now().format("%H:%M:%S");
+        self.errors.push_front(format!("{at} {message}"));
+        self.errors.truncate(MAX_ERRORS);
+    }
+
+    /// Tasks handled per minute over the last [`RATE_WINDOW`], `None` before two refreshes
+    pub fn tasks_per_min(&self) -> Option<f64> {
+        let ((first_at, first), (last_at, last)) = (self.handled.front()?, self.handled.back()?);
+        let elapsed = last_at.duration_since(*first_at).as_secs_f64();
+        (elapsed > 0.0).then(|| (last - first).max(0.0) * 60.0 / elapsed)
+    }
+
+    /// Errors seen so far, newest first
+    pub fn errors(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().map(String::as_str)
+    }
+}
+
+/// Where `operator top` reads the state of the operator from
+#[derive(Debug, Clone)]
+pub struct TopSources {
+    /// RPC endpoint the head block is read from
+    pub rpc_url: String,
+    /// Prometheus endpoint of the operator, such as `http://127.0.0.1:9090/metrics`
+    pub metrics_url: Option<String>,
+    /// gRPC control API of the operator, such as `http://127.0.0.1:50051`
+    pub control_url: Option<String>,
+}
+
+/// Reads the [`TopSources`], keeping the control API connection between refreshes
+#[derive(Debug)]
+struct Poller {
+    sources: TopSources,
+    services: Services,
+    http: reqwest::Client,
+    control: Option<OperatorControlClient<Channel>>,
+}
+
+impl Poller {
+    fn new(sources: TopSources, services: Services, timeout: Duration) -> Self {
+        Self {
+            sources,
+            services,
+            http: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .unwrap_or_default(),
+            control: None,
+        }
+    }
+
+    async fn poll(&mut self) -> Poll {
+        let provider = self.services.http_provider(&self.sources.rpc_url);
+        let head_block = async {
+            let provider = provider.map_err(|e| e.to_string())?;
+            provider.get_block_number().await.map_err(|e| e.to_string())
+        };
+        let metrics = async {
+            let url = self.sources.metrics_url.as_deref()?;
+            Some(self.scrape(url).await.map_err(|e| e.to_string()))
+        };
+        let (head_block, metrics) = tokio::join!(head_block, metrics);
+        let (status, recent_tasks) = match self.control().await {
+            Some(Ok(mut client)) => {
+                let status = client
+                    .get_status(GetStatusRequest {})
+                    .await
+                    .map(|response| response.into_inner())
+                    .map_err(|e| e.message().to_string());
+                let recent_tasks = client
+                    .list_recent_tasks(ListRecentTasksRequest {
+                        limit: RECENT_TASKS,
+                    })
+                    .await
+                    .map(|response| response.into_inner().tasks)
+                    .unwrap_or_default();
+                if status.is_err() {
+                    self.control = None;
+                }
+                (Some(status), recent_tasks)
+            }
+            Some(Err(e)) => (Some(Err(e)), Vec::new()),
+            None => (None, Vec::new()),
+        };
+        Poll {
+            head_block: Some(head_block),
+            metrics,
+            status,
+            recent_tasks,
+        }
+    }
+
+    async fn scrape(&self, url: &str) -> Result<Metrics, reqwest::Error> {
+        let text = self
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(Metrics::parse(&text))
+    }
+
+    /// Client of the control API, reconnecting after a failure
+    async fn control(&mut self) -> Option<Result<OperatorControlClient<Channel>, String>> {
+        let url = self.sources.control_url.clone()?;
+        if self.control.is_none() {
+            match OperatorControlClient::connect(url).await {
+                Ok(client) => self.control = Some(client),
+                Err(e) => return Some(Err(e.to_string())),
+            }
+        }
+        self.control.clone().map(Ok)
+    }
+}
+
+/// Render `state` on the whole terminal
+pub fn draw(frame: &mut Frame<'_>, state: &TopState) {
+    let [overview, latency, errors, help] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let status = state.status.as_ref();
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let field = |name: &'static str, value: String| {
+        Line::from(vec![
+            Span::from(format!("{name:<14}")).bold(),
+            Span::from(value),
+        ])
+    };
+    let paused = status.map(|status| {
+        if status.paused {
+            "paused".to_string()
+        } else {
+            "running".to_string()
+        }
+    });
+    let lines = vec![
+        field(
+            "Operator",
+            format!(
+                "{}  {}  up {}",
+                or_dash(status.map(|status| status.address.clone())),
+                or_dash(paused),
+                or_dash(status.map(|status| format_uptime(status.uptime_secs))),
+            ),
+        ),
+        field(
+            "Block",
+            or_dash(state.head_block.map(|block| block.to_string())),
+        ),
+        field(
+            "Tasks/min",
+            or_dash(state.tasks_per_min().map(|rate| format!("{rate:.1}"))),
+        ),
+        field(
+            "Pending txs",
+            or_dash(state.pending_txs.map(|count| format!("{count:.0}"))),
+        ),
+        field(
+            "Balance",
+            or_dash(state.balance_eth.map(|balance| format!("{balance:.4} ETH"))),
+        ),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" operator top ")),
+        overview,
+    );
+
+    let [p50, p95, p99] = state
+        .latency
+        .map(|secs| or_dash(secs.map(|secs| format!("{secs:.1}s"))));
+    frame.render_widget(
+        Paragraph::new(format!("p50 {p50}   p95 {p95}   p99 {p99}"))
+            .block(Block::bordered().title(" Response latency ")),
+        latency,
+    );
+
+    let items: Vec<ListItem<'_>> = state
+        .errors()
+        .map(|error| ListItem::new(error).style(Style::new().fg(Color::Red)))
+        .collect();
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" Recent errors ")),
+        errors,
+    );
+    frame.render_widget(Line::from("q or Esc to quit").dim(), help);
+}
+
+fn format_uptime(secs: u64) -> String {
+    format!("{}h{:02}m{:02}s", secs / 3_600, secs / 60 % 60, secs % 60)
+}
+
+fn is_quit(key: KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+}
+
+/// Show the live state of the operator behind `sources`, read through the RPC stack of
+/// `services` and refreshed every `interval`, until q, Esc or Ctrl-C is pressed
+pub async fn run(sources: TopSources, services: Services, interval: Duration) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_terminal(&mut terminal, sources, services, interval).await;
+    ratatui::restore();
+    result
+}
+
+async fn run_terminal(
+    terminal: &mut DefaultTerminal,
+    sources: TopSources,
+    services: Services,
+    interval: Duration,
+) -> io::Result<()> {
+    let mut poller = Poller::new(sources, services, interval.max(Duration::from_secs(1)));
+    let mut state = TopState::new();
+    let mut events = EventStream::new();
+    let mut refresh = tokio::time::interval(interval);
+    loop {
+        terminal.draw(|frame| draw(frame, &state))?;
+        tokio::select! {
+            _ = refresh.tick() => {
+                let poll = poller.poll().await;
+                state.record(Instant::now(), poll);
+            }
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if is_quit(key) => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_follows_the_scraped_metrics() {
+        let scrape = |responded: u64, failures: u64| {
+            Metrics::parse(&format!(
+                "# TYPE swap_manager_tasks_responded_total counter\n\
+                 swap_manager_tasks_responded_total {responded}\n\
+                 swap_manager_tx_failures_total{{method=\"respondToTask\"}} {failures}\n\
+                 swap_manager_task_latency_seconds{{quantile=\"0.5\"}} 4\n\
+                 swap_manager_task_latency_seconds{{quantile=\"0.99\"}} NaN\n\
+                 swap_manager_pending_txs 2\n"
+            ))
+        };
+        let metrics = scrape(10, 1);
+        assert_eq!(metrics.total(TASKS_RESPONDED), Some(10.0));
+        assert_eq!(metrics.quantile(TASK_LATENCY, "0.5"), Some(4.0));
+        assert_eq!(metrics.quantile(TASK_LATENCY, "0.99"), None);
+
+        let mut state = TopState::new();
+        let start = Instant::now();
+        state.record(
+            start,
+            Poll {
+                metrics: Some(Ok(metrics)),
+                ..Default::default()
+            },
+        );
+        assert_eq!(state.tasks_per_min(), None);
+        assert_eq!(state.errors().count(), 0);
+        state.record(
+            start + Duration::from_secs(30),
+            Poll {
+                metrics: Some(Ok(scrape(15, 3))),
+                ..Default::default()
+            },
+        );
+        assert_eq!(state.tasks_per_min(), Some(10.0));
+        assert_eq!(state.pending_txs, Some(2.0));
+        assert!(state
+            .errors()
+            .next()
+            .unwrap()
+            .ends_with("respondToTask failed 2 more time(s)"));
+    }
+}
@@ -0,0 +1,349 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use clap::ValueEnum;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+/// How the spammer spaces its task submissions
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrafficModel {
+    /// One task every `spammer.interval_ms` or `interval_secs`, adjustable through the control API
+    #[default]
+    Fixed,
+    /// Poisson arrivals averaging `rate_per_min`
+    Poisson,
+    /// `rate_per_min` during `burst_on_secs`, then nothing for `burst_off_secs`
+    Burst,
+    /// A rate rising from `ramp_from_per_min` to `ramp_to_per_min` over `ramp_secs`
+    Ramp,
+}
+
+/// Load curve settings, the `[spammer.traffic]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrafficConfig {
+    /// `fixed`, `poisson`, `burst` or `ramp`
+    pub model: TrafficModel,
+    /// Tasks per minute of the poisson model and of the bursts
+    pub rate_per_min: f64,
+    /// Seconds each burst lasts
+    pub burst_on_secs: u64,
+    /// Seconds of silence between two bursts
+    pub burst_off_secs: u64,
+    /// Tasks per minute the ramp starts at
+    pub ramp_from_per_min: f64,
+    /// Tasks per minute the ramp ends at and then stays at
+    pub ramp_to_per_min: f64,
+    /// Seconds the ramp takes to reach its final rate
+    pub ramp_secs: u64,
+    /// Seed making the poisson arrivals reproducible across runs
+    pub seed: Option<u64>,
+}
+
+impl Default for TrafficConfig {
+    fn default() -> Self {
+        Self {
+            model: TrafficModel::default(),
+            rate_per_min: 10.0,
+            burst_on_secs: 30,
+            burst_off_secs: 90,
+            ramp_from_per_min: 1.0,
+            ramp_to_per_min: 50.0,
+            ramp_secs: 600,
+            seed: None,
+        }
+    }
+}
+
+/// Picks the delay between two task submissions following a [`TrafficModel`]
+#[derive(Debug)]
+pub struct TrafficSchedule {
+    config: TrafficConfig,
+    rng: StdRng,
+}
+
+impl TrafficSchedule {
+    /// Schedule following the model of `config`
+    pub fn new(config: &TrafficConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            config: config.clone(),
+            rng,
+        }
+    }
+
+    /// Delay until the task after one submitted `elapsed` into the run, `interval` being
+    /// the current spam interval of the fixed model
+    pub fn next_delay(&mut self, elapsed: Duration, interval: Duration) -> Duration {
+        let config = &self.config;
+        match config.model {
+            TrafficModel::Fixed => interval,
+            TrafficModel::Poisson => {
+                // Exponentially distributed gaps, `1 - u` keeps the logarithm finite
+                let u: f64 = self.rng.random();
+                Duration::from_secs_f64(-(1.0 - u).ln() * 60.0 / config.rate_per_min)
+            }
+            TrafficModel::Burst => {
+                let cycle = Duration::from_secs(config.burst_on_secs + config.burst_off_secs);
+                let on = Duration::from_secs(config.burst_on_secs);
+                let into_cycle =
+                    Duration::from_nanos((elapsed.as_nanos() % cycle.as_nanos()) as u64);
+                let gap = per_min(config.rate_per_min);
+                if into_cycle + gap < on {
+                    gap
+                } else {
+                    cycle - into_cycle
+                }
+            }
+            TrafficModel::Ramp => {
+                let progress = (elapsed.as_secs_f64() / config.ramp_secs as f64).clamp(0.0, 1.0);
+                per_min(
+                    config.ramp_from_per_min
+                        + (config.ramp_to_per_min - config.ramp_from_per_min) * progress,
+                )
+            }
+        }
+    }
+}
+
+/// Gap between two tasks sent at `rate` tasks per minute
+fn per_min(rate: f64) -> Duration {
+    Duration::from_secs_f64(60.0 / rate)
+}
+
+/// Parse a spam interval such as `250ms`, `1.5s` or `6`, plain numbers being seconds
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, millis) = match value.strip_suffix("ms") {
+        Some(number) => (number, true),
+        None => (value.strip_suffix('s').unwrap_or(value), false),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid interval {value:?}, expected e.g. 250ms, 1.5s or 6"))?;
+    let interval = Duration::try_from_secs_f64(if millis { number / 1000.0 } else { number })
+        .map_err(|e| format!("invalid interval {value:?}: {e}"))?;
+    if interval < Duration::from_millis(1) {
+        return Err(format!("interval {value:?} must be at least 1ms"));
+    }
+    Ok(interval)
+}
+
+/// Day of the week a [`SpamWindow`] is open on
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    /// Monday
+    Mon,
+    /// Tuesday
+    Tue,
+    /// Wednesday
+    Wed,
+    /// Thursday
+    Thu,
+    /// Friday
+    Fri,
+    /// Saturday
+    Sat,
+    /// Sunday
+    Sun,
+}
+
+impl From<Day> for Weekday {
+    fn from(day: Day) -> Self {
+        match day {
+            Day::Mon => Weekday::Mon,
+            Day::Tue => Weekday::Tue,
+            Day::Wed => Weekday::Wed,
+            Day::Thu => Weekday::Thu,
+            Day::Fri => Weekday::Fri,
+            Day::Sat => Weekday::Sat,
+            Day::Sun => Weekday::Sun,
+        }
+    }
+}
+
+/// `HH:MM` time of day in UTC
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct TimeOfDay(NaiveTime);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        NaiveTime::parse_from_str(&value, "%H:%M")
+            .map(Self)
+            .map_err(|_| format!("invalid time of day {value:?}, expected HH:MM"))
+    }
+}
+
+/// UTC time range the spammer creates tasks in, one `[[spammer.windows]]` entry. A window
+/// ending before it starts runs past midnight into the next day, and one ending when it
+/// starts lasts the whole day.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpamWindow {
+    /// Days the window opens on, every day if empty
+    #[serde(default)]
+    pub days: Vec<Day>,
+    /// Time the window opens at
+    pub start: TimeOfDay,
+    /// Time the window closes at
+    pub end: TimeOfDay,
+}
+
+impl SpamWindow {
+    fn opens_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.iter().any(|&d| Weekday::from(d) == day)
+    }
+
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        let (day, time) = (at.weekday(), at.time());
+        let (start, end) = (self.start.0, self.end.0);
+        if start < end {
+            self.opens_on(day) && start <= time && time < end
+        } else {
+            (self.opens_on(day) && time >= start) || (self.opens_on(day.pred()) && time < end)
+        }
+    }
+}
+
+/// Time left at `now` until one of `windows` opens, `None` if one is open or there are
+/// none, in which case the spammer never pauses
+pub fn until_open(windows: &[SpamWindow], now: DateTime<Utc>) -> Option<Duration> {
+    if windows.is_empty() || windows.iter().any(|window| window.contains(now)) {
+        return None;
+    }
+    // Outside of every window the next one to open is the nearest start, at most a week away
+    (0..=7)
+        .filter_map(|days| now.date_naive().checked_add_days(chrono::Days::new(days)))
+        .flat_map(|date| {
+            windows
+                .iter()
+                .filter(move |window| window.opens_on(date.weekday()))
+                .map(move |window| date.and_time(window.start.0).and_utc())
+        })
+        .filter(|&start| start > now)
+        .min()
+        .and_then(|start| (start - now).to_std().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(6);
+
+    #[test]
+    fn test_models_space_tasks_along_their_curve() {
+        let mut fixed = TrafficSchedule::new(&TrafficConfig::default());
+        assert_eq!(
+            fixed.next_delay(Duration::from_secs(100), INTERVAL),
+            INTERVAL
+        );
+
+        let mut burst = TrafficSchedule::new(&TrafficConfig {
+            model: TrafficModel::Burst,
+            rate_per_min: 60.0,
+            burst_on_secs: 10,
+            burst_off_secs: 20,
+            ..Default::default()
+        });
+        assert_eq!(
+            burst.next_delay(Duration::from_secs(3), INTERVAL),
+            Duration::from_secs(1)
+        );
+        // The last task of a burst waits for the next one to start
+        assert_eq!(
+            burst.next_delay(Duration::from_secs(39), INTERVAL),
+            Duration::from_secs(21)
+        );
+
+        let mut ramp = TrafficSchedule::new(&TrafficConfig {
+            model: TrafficModel::Ramp,
+            ramp_from_per_min: 10.0,
+            ramp_to_per_min: 50.0,
+            ramp_secs: 600,
+            ..Default::default()
+        });
+        assert_eq!(
+            ramp.next_delay(Duration::ZERO, INTERVAL),
+            Duration::from_secs(6)
+        );
+        assert_eq!(
+            ramp.next_delay(Duration::from_secs(300), INTERVAL),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            ramp.next_delay(Duration::from_secs(3600), INTERVAL),
+            Duration::from_secs_f64(1.2)
+        );
+    }
+
+    #[test]
+    fn test_poisson_arrivals_average_the_configured_rate() {
+        let mut poisson = TrafficSchedule::new(&TrafficConfig {
+            model: TrafficModel::Poisson,
+            rate_per_min: 30.0,
+            seed: Some(7),
+            ..Default::default()
+        });
+        let total: Duration = (0..10_000)
+            .map(|_| poisson.next_delay(Duration::ZERO, INTERVAL))
+            .sum();
+        let mean = total.as_secs_f64() / 10_000.0;
+        assert!((1.9..2.1).contains(&mean), "mean gap {mean}");
+    }
+
+    #[test]
+    fn test_windows_pause_spamming_until_the_next_one_opens() {
+        #[derive(Deserialize)]
+        struct Spammer {
+            windows: Vec<SpamWindow>,
+        }
+        let Spammer { windows } = toml::from_str(
+            r#"
+            [[windows]]
+            days = ["mon", "tue", "wed", "thu", "fri"]
+            start = "09:00"
+            end = "17:00"
+
+            [[windows]]
+            days = ["sat"]
+            start = "22:00"
+            end = "02:00"
+            "#,
+        )
+        .unwrap();
+        let at = |time: &str| {
+            DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        // 2024-06-07 is a Friday
+        assert_eq!(until_open(&windows, at("2024-06-07T12:00:00Z")), None);
+        assert_eq!(
+            until_open(&windows, at("2024-06-07T17:00:00Z")),
+            Some(Duration::from_secs(29 * 3600))
+        );
+        assert_eq!(until_open(&windows, at("2024-06-09T01:30:00Z")), None);
+        assert_eq!(
+            until_open(&windows, at("2024-06-09T02:00:00Z")),
+            Some(Duration::from_secs(31 * 3600))
+        );
+        assert_eq!(until_open(&[], at("2024-06-09T02:00:00Z")), None);
+
+        assert_eq!(parse_interval("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_interval("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_interval("6"), Ok(Duration::from_secs(6)));
+        assert!(parse_interval("0ms").is_err());
+        assert!(parse_interval("soon").is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use alloy::primitives::{Address, B256};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{debug, warn};
+
+use crate::alerting::{Alerter, AlertsConfig, Severity};
+use crate::error::ConfigError;
+use crate::prometheus::record_webhook_delivery;
+use crate::retry::{retry, RetryConfig};
+
+/// Header holding `sha256=<hex HMAC-SHA256 of the body>` when a secret is set
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+/// Header holding the event name, also found in the `event` field of the body
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+/// Names of the events that can be subscribed to in `webhooks.events`
+pub const EVENT_NAMES: [&str; 7] = [
+    "task_created",
+    "response_submitted",
+    "tx_failure",
+    "challenge_raised",
+    "low_balance",
+    "responder_stalled",
+    "budget_exhausted",
+];
+
+/// Webhook notifications, the `[webhooks]` config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// URLs every event is POSTed to, notifications are off if empty (`WEBHOOK_URLS`,
+    /// comma separated)
+    pub urls: Vec<String>,
+    /// Key the bodies are signed with in [`SIGNATURE_HEADER`], unsigned if unset
+    /// (`WEBHOOK_SECRET`)
+    pub secret: Option<String>,
+    /// Events sent, out of [`EVENT_NAMES`], every event if empty
+    pub events: Vec<String>,
+    /// Seconds a single delivery attempt may take
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            secret: None,
+            events: Vec::new(),
+            timeout_secs: 5,
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Check that the events exist and deliveries can complete
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(event) = self
+            .events
+            .iter()
+            .find(|e| !EVENT_NAMES.contains(&e.as_str()))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "unknown webhooks.events entry {event}, expected one of {}",
+                EVENT_NAMES.join(", ")
+            )));
+        }
+        if self.secret.as_deref() == Some("") {
+            return Err(ConfigError::Invalid(
+                "webhooks.secret must not be empty".to_string(),
+            ));
+        }
+        if self.timeout_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "webhooks.timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Event of the task lifecycle, serialized as the body of the notification
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(
+    tag = "event",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum WebhookEvent {
+    /// Tasks were created in the transaction `tx_hash`
+    TaskCreated {
+        /// Names of the created tasks
+        task_names: Vec<String>,
+        /// Mined createNewTask or Multicall3 transaction
+        tx_hash: B256,
+    },
+    /// A response to task `task_index` was mined in `tx_hash`
+    ResponseSubmitted {
+        /// Index of the task responded to
+        task_index: u32,
+        /// Mined respondToTask transaction
+        tx_hash: B256,
+    },
+    /// A transaction calling `method` failed or reverted
+    TxFailure {
+        /// Contract method the transaction called
+        method: &'static str,
+        /// Why it failed
+        error: String,
+    },
+    /// The challenger found a faulty response of `operator`
+    ChallengeRaised {
+        /// Index of the task with the faulty response
+        task_index: u32,
+        /// Operator that responded
+        operator: Address,
+        /// Kind of fault, a `swap_manager_invalid_responses_total` label
+        fault: &'static str,
+    },
+    /// The balance of `address` dropped under the minimum
+    LowBalance {
+        /// Signer running low on funds
+        address: Address,
+        /// Current balance in ether
+        balance_eth: String,
+        /// Configured minimum in ether
+        min_balance_eth: String,
+    },
+    /// Task `task_index` has been handled for `stalled_secs` without a response going out
+    ResponderStalled {
+        /// Index of the task being handled
+        task_index: u32,
+        /// Seconds since the operator started handling it
+        stalled_secs: u64,
+    },
+    /// The fees spent over `window` reached the `[budget]` limit, transactions are held back
+    BudgetExhausted {
+        /// `hour` or `day`
+        window: &'static str,
+        /// Fees spent over the window in ether
+        spent_eth: String,
+        /// Limit of the window in ether
+        limit_eth: String,
+    },
+}
+
+impl WebhookEvent {
+    /// Name of the event, one of [`EVENT_NAMES`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TaskCreated { .. } => "task_created",
+            Self::ResponseSubmitted { .. } => "response_submitted",
+            Self::TxFailure { .. } => "tx_failure",
+            Self::ChallengeRaised { .. } => "challenge_raised",
+            Self::LowBalance { .. } => "low_balance",
+            Self::ResponderStalled { .. } => "responder_stalled",
+            Self::BudgetExhausted { .. } => "budget_exhausted",
+        }
+    }
+
+    /// How urgently the event needs a human
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::TaskCreated { .. } | Self::ResponseSubmitted { .. } => Severity::Info,
+            Self::TxFailure { .. } | Self::ChallengeRaised { .. } => Severity::Warning,
+            Self::LowBalance { .. }
+            | Self::ResponderStalled { .. }
+            | Self::BudgetExhausted { .. } => Severity::Critical,
+        }
+    }
+
+    /// One line description, the text of chat alerts
+    pub fn summary(&self) -> String {
+        match self {
+            Self::TaskCreated {
+                task_names,
+                tx_hash,
+            } => format!("Created {} task(s) in {tx_hash}", task_names.len()),
+            Self::ResponseSubmitted {
+                task_index,
+                tx_hash,
+            } => format!("Responded to task {task_index} in {tx_hash}"),
+            Self::TxFailure { method, error } => format!("{method} transaction failed: {error}"),
+            Self::ChallengeRaised {
+                task_index,
+                operator,
+                fault,
+            } => format!("Faulty response of {operator} to task {task_index}: {fault}"),
+            Self::LowBalance {
+                address,
+                balance_eth,
+                min_balance_eth,
+            } => format!(
+                "Signer {address} holds {balance_eth} ETH, below the minimum of {min_balance_eth} ETH"
+            ),
+            Self::ResponderStalled {
+                task_index,
+                stalled_secs,
+            } => format!("Task {task_index} has been handled for {stalled_secs}s without a response"),
+            Self::BudgetExhausted {
+                window,
+                spent_eth,
+                limit_eth,
+            } => format!(
+                "Spent {spent_eth} ETH in fees over the last {window}, the limit of {limit_eth} ETH, transactions are held back"
+            ),
+        }
+    }
+}
+
+/// Body of a notification: the event and the unix time it was sent at
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    at: i64,
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `body` keyed with `secret`, the value of
+/// [`SIGNATURE_HEADER`]
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether `signature` is the [`sign_payload`] signature of `body`, compared in constant time
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature
+        .strip_prefix("sha256=")
+        .and_then(|digest| hex::decode(digest).ok())
+    else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&digest).is_ok()
+}
+
+/// POSTs events to the configured webhooks, retrying failed deliveries
+pub struct Notifier {
+    client: reqwest::Client,
+    urls: Vec<String>,
+    secret: Option<String>,
+    events: Vec<String>,
+    retry: RetryConfig,
+}
+
+// Hand written to keep the secret out of the logs
+impl fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notifier")
+            .field("urls", &self.urls)
+            .field("signed", &self.secret.is_some())
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+impl Notifier {
+    /// Notifier for `config`, each delivery retried as set in `retry_config`
+    pub fn new(config: &WebhookConfig, retry_config: &RetryConfig) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()
+                .unwrap_or_default(),
+            urls: config.urls.clone(),
+            secret: config.secret.clone(),
+            events: config.events.clone(),
+            retry: retry_config.clone(),
+        }
+    }
+
+    /// Whether `event` is one of the subscribed events
+    pub fn wants(&self, event: &WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.iter().any(|name| name == event.name())
+    }
+
+    /// POST `event` to every webhook, failures are logged and counted
+    pub async fn deliver(&self, event: &WebhookEvent) {
+        let body = match serde_json::to_vec(&Payload {
+            event,
+            at: chrono::Utc::now().timestamp(),
+        }) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(event = event.name(), error = %e, "Failed to serialize the webhook event");
+                return;
+            }
+        };
+        let signature = self
+            .secret
+            .as_deref()
+            .map(|secret| sign_payload(secret, &body));
+        for url in &self.urls {
+            let sent = retry(&self.retry, "webhook", || async {
+                let mut request = self
+                    .client
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(EVENT_HEADER, event.name())
+                    .body(body.clone());
+                if let Some(signature) = &signature {
+                    request = request.header(SIGNATURE_HEADER, signature);
+                }
+                request.send().await?.error_for_status().map(|_| ())
+            })
+            .await;
+            match sent {
+                Ok(()) => {
+                    record_webhook_delivery(event.name(), "delivered");
+                    debug!(event = event.name(), url, "Webhook delivered");
+                }
+                Err(e) => {
+                    record_webhook_delivery(event.name(), "failed");
+                    warn!(event = event.name(), url, error = %e, "Webhook delivery failed");
+                }
+            }
+        }
+    }
+}
+
+/// Where the events of an operator go: the webhooks subscribed to them and the chat
+/// channels alerted at their severity. Nothing is sent by the default one
+#[derive(Debug, Clone, Default)]
+pub struct Notifications {
+    webhooks: Option<Arc<Notifier>>,
+    alerts: Option<Arc<Alerter>>,
+}
+
+impl Notifications {
+    /// Send to the webhooks of `webhooks` and the channels of `alerts`, each delivery retried
+    /// as set in `retry_config`
+    pub fn new(
+        webhooks: &WebhookConfig,
+        alerts: &AlertsConfig,
+        retry_config: &RetryConfig,
+    ) -> Self {
+        Self {
+            webhooks: (!webhooks.urls.is_empty())
+                .then(|| Arc::new(Notifier::new(webhooks, retry_config))),
+            alerts: (!alerts.channels.is_empty())
+                .then(|| Arc::new(Alerter::new(alerts, retry_config))),
+        }
+    }
+
+    /// Deliver `event` in the background to the webhooks subscribed to it and the chat
+    /// channels alerted at its severity
+    pub(crate) fn notify(&self, event: WebhookEvent) {
+        let notifier = self
+            .webhooks
+            .clone()
+            .filter(|notifier| notifier.wants(&event));
+        let alerter = self.alerts.clone();
+        if notifier.is_none() && alerter.is_none() {
+            return;
+        }
+        tokio::spawn(async move {
+            let deliveries = async {
+                if let Some(notifier) = notifier {
+                    notifier.deliver(&event).await;
+                }
+            };
+            let alerts = async {
+                if let Some(alerter) = alerter {
+                    alerter.send(&event).await;
+                }
+            };
+            tokio::join!(deliveries, alerts);
+        });
+    }
+
+    /// Notify that a transaction calling `method` failed with `error`
+    pub(crate) fn notify_tx_failure(&self, method: &'static str, error: &impl fmt::Display) {
+        self.notify(WebhookEvent::TxFailure {
+            method,
+            error: error.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads_are_signed_and_verified() {
+        let event = WebhookEvent::ResponseSubmitted {
+            task_index: 7,
+            tx_hash: B256::repeat_byte(1),
+        };
+        let body = serde_json::to_value(Payload {
+            event: &event,
+            at: 1_700_000_000,
+        })
+        .unwrap();
+        assert_eq!(body["event"], "response_submitted");
+        assert_eq!(body["taskIndex"], 7);
+        assert_eq!(body["at"], 1_700_000_000);
+
+        let body = body.to_string();
+        let signature = sign_payload("s3cret", body.as_bytes());
+        assert!(verify_signature("s3cret", body.as_bytes(), &signature));
+        assert!(!verify_signature("other", body.as_bytes(), &signature));
+        assert!(!verify_signature(
+            "s3cret",
+            body.replace('7', "8").as_bytes(),
+            &signature
+        ));
+        assert!(!verify_signature("s3cret", body.as_bytes(), "sha256=zz"));
+    }
+}
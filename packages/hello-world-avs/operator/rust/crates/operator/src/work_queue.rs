@@ -0,0 +1,678 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alloy::primitives::B256;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use futures::future::BoxFuture;
+use reqwest::Url;
+use serde::Deserialize;
+use swap_manager_utils::SwapManager::ISwapManager::Task;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tracing::{info, warn};
+
+use crate::error::{ConfigError, OperatorError, WorkQueueError};
+use crate::health::SourceHealth;
+use crate::prometheus::record_work_queue_task;
+use crate::retry::{retry, RetryConfig};
+use crate::shutdown::Shutdown;
+use crate::task_listener::NewTask;
+use crate::task_source::TaskSource;
+
+/// Port of a `redis://` URL without one
+const REDIS_DEFAULT_PORT: u16 = 6379;
+
+/// Milliseconds a worker blocks in XREADGROUP before checking for stale entries again
+const READ_BLOCK_MS: u64 = 5_000;
+
+/// Part a process plays in the work queue
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkQueueRole {
+    /// Listen for tasks and respond to them in the same process
+    #[default]
+    None,
+    /// Only listen for tasks, pushing them onto the stream
+    Listener,
+    /// Only respond, to the tasks pulled from the stream
+    Worker,
+}
+
+/// Redis stream shared by a task listener and its responder workers, the `[work_queue]`
+/// config section
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkQueueConfig {
+    /// Part of this process (`WORK_QUEUE_ROLE`)
+    pub role: WorkQueueRole,
+    /// `redis://[[user]:password@]host[:port][/db]` server holding the stream
+    /// (`WORK_QUEUE_URL`)
+    pub url: Option<String>,
+    /// Key of the stream
+    pub stream: String,
+    /// Consumer group the workers share, each task is delivered to one of them
+    pub group: String,
+    /// Name of this worker in the group, the host name and process ID if unset
+    /// (`WORK_QUEUE_CONSUMER`)
+    pub consumer: Option<String>,
+    /// Seconds a delivered task may stay unacknowledged before another worker claims it,
+    /// longer than responding with every retry takes
+    pub claim_idle_secs: u64,
+    /// Tasks read from the stream at once
+    pub batch_size: usize,
+    /// Approximate number of tasks the stream keeps, the oldest ones are trimmed
+    pub max_len: u64,
+}
+
+impl Default for WorkQueueConfig {
+    fn default() -> Self {
+        Self {
+            role: WorkQueueRole::default(),
+            url: None,
+            stream: "swap_manager:tasks".to_string(),
+            group: "responders".to_string(),
+            consumer: None,
+            claim_idle_secs: 300,
+            batch_size: 16,
+            max_len: 100_000,
+        }
+    }
+}
+
+impl WorkQueueConfig {
+    /// Name of this worker, `consumer` or `<host name>-<process ID>`
+    pub fn consumer(&self) -> String {
+        self.consumer.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "operator".to_string());
+            format!("{host}-{}", std::process::id())
+        })
+    }
+
+    /// Check that the stream can be reached and tasks are handed over
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.role == WorkQueueRole::None {
+            return Ok(());
+        }
+        let url = self.url.as_deref().ok_or_else(|| {
+            ConfigError::Invalid("work_queue.url must be set for a work queue role".to_string())
+        })?;
+        let parsed = Url::parse(url)
+            .map_err(|e| ConfigError::Invalid(format!("invalid work_queue.url {url}: {e}")))?;
+        if parsed.scheme() != "redis" || parsed.host_str().is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "work_queue.url must be a redis:// URL with a host, got {url}"
+            )));
+        }
+        if self.stream.is_empty() || self.group.is_empty() {
+            return Err(ConfigError::Invalid(
+                "work_queue.stream and work_queue.group must not be empty".to_string(),
+            ));
+        }
+        if self.claim_idle_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "work_queue.claim_idle_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.batch_size == 0 {
+            return Err(ConfigError::Invalid(
+                "work_queue.batch_size must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Reply to a Redis command, in the RESP2 protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>),
+}
+
+impl Reply {
+    fn into_string(self) -> Option<String> {
+        match self {
+            Self::Status(value) => Some(value),
+            Self::Bulk(Some(value)) => String::from_utf8(value).ok(),
+            _ => None,
+        }
+    }
+
+    /// Elements of an array, none for a nil array
+    fn into_array(self) -> Result<Vec<Reply>, WorkQueueError> {
+        match self {
+            Self::Array(elements) => Ok(elements.unwrap_or_default()),
+            reply => Err(WorkQueueError::Protocol(format!(
+                "expected an array, got {reply:?}"
+            ))),
+        }
+    }
+}
+
+/// Read one reply from `reader`, arrays recursively
+fn read_reply<R: AsyncBufRead + Unpin + Send>(
+    reader: &mut R,
+) -> BoxFuture<'_, Result<Reply, WorkQueueError>> {
+    Box::pin(async move {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(WorkQueueError::Protocol("connection closed".to_string()));
+        }
+        let line = line.trim_end_matches("\r\n");
+        let malformed = || WorkQueueError::Protocol(format!("malformed reply {line:?}"));
+        let (kind, value) = (line.get(..1).ok_or_else(malformed)?, &line[1..]);
+        let length = || value.parse::<i64>().map_err(|_| malformed());
+        Ok(match kind {
+            "+" => Reply::Status(value.to_string()),
+            "-" => Reply::Error(value.to_string()),
+            ":" => Reply::Integer(length()?),
+            "$" => match usize::try_from(length()?) {
+                Ok(len) => {
+                    let mut bulk = vec![0; len + 2];
+                    reader.read_exact(&mut bulk).await?;
+                    bulk.truncate(len);
+                    Reply::Bulk(Some(bulk))
+                }
+                Err(_) => Reply::Bulk(None),
+            },
+            "*" => match usize::try_from(length()?) {
+                Ok(len) => {
+                    let mut elements = Vec::with_capacity(len);
+                    for _ in 0..len {
+                        elements.push(read_reply(reader).await?);
+                    }
+                    Reply::Array(Some(elements))
+                }
+                Err(_) => Reply::Array(None),
+            },
+            _ => return Err(malformed()),
+        })
+    })
+}
+
+/// `args` as a RESP array of bulk strings
+fn encode_command(args: &[&str]) -> Vec<u8> {
+    let mut command = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        command.extend_from_slice(arg.as_bytes());
+        command.extend_from_slice(b"\r\n");
+    }
+    command
+}
+
+/// Connection to a Redis server, one command at a time
+#[derive(Debug)]
+struct RedisConnection {
+    stream: BufReader<TcpStream>,
+    /// A command was sent and its reply not read, which a cancelled call leaves behind
+    in_flight: bool,
+}
+
+impl RedisConnection {
+    /// Connect to the server at `url`, authenticating and selecting its database
+    async fn connect(url: &str) -> Result<Self, WorkQueueError> {
+        let url = Url::parse(url).map_err(|e| WorkQueueError::Protocol(e.to_string()))?;
+        let host = url.host_str().unwrap_or("localhost");
+        let stream = TcpStream::connect((host, url.port().unwrap_or(REDIS_DEFAULT_PORT))).await?;
+        let mut connection = Self {
+            stream: BufReader::new(stream),
+            in_flight: false,
+        };
+        match (url.username(), url.password()) {
+            (_, None) => {}
+            ("", Some(password)) => {
+                connection.command(&["AUTH", password]).await?;
+            }
+            (user, Some(password)) => {
+                connection.command(&["AUTH", user, password]).await?;
+            }
+        }
+        let db = url.path().trim_start_matches('/');
+        if !db.is_empty() {
+            connection.command(&["SELECT", db]).await?;
+        }
+        Ok(connection)
+    }
+
+    /// Send `args` and read the reply, error replies become [`WorkQueueError::Redis`]
+    async fn command(&mut self, args: &[&str]) -> Result<Reply, WorkQueueError> {
+        if self.in_flight {
+            return Err(WorkQueueError::Protocol(
+                "a previous command was interrupted".to_string(),
+            ));
+        }
+        self.in_flight = true;
+        self.stream
+            .get_mut()
+            .write_all(&encode_command(args))
+            .await?;
+        let reply = read_reply(&mut self.stream).await?;
+        self.in_flight = false;
+        match reply {
+            Reply::Error(error) => Err(WorkQueueError::Redis(error)),
+            reply => Ok(reply),
+        }
+    }
+
+    /// Create the consumer group of `stream` if it doesn't exist, starting at its oldest task
+    async fn create_group(&mut self, stream: &str, group: &str) -> Result<(), WorkQueueError> {
+        match self
+            .command(&["XGROUP", "CREATE", stream, group, "0", "MKSTREAM"])
+            .await
+        {
+            Err(WorkQueueError::Redis(error)) if error.starts_with("BUSYGROUP") => Ok(()),
+            created => created.map(|_| ()),
+        }
+    }
+}
+
+/// Connect to `url` with retries, creating the consumer group
+async fn connect_queue(
+    config: &WorkQueueConfig,
+    retry_config: &RetryConfig,
+) -> Result<RedisConnection, WorkQueueError> {
+    let url = config.url.as_deref().unwrap_or_default();
+    retry(retry_config, "redis_connect", || async {
+        let mut connection = RedisConnection::connect(url).await?;
+        connection
+            .create_group(&config.stream, &config.group)
+            .await?;
+        Ok(connection)
+    })
+    .await
+}
+
+/// ID and fields of a stream entry
+type StreamEntry = (String, HashMap<String, String>);
+
+/// Fields of the stream entry carrying `task`
+fn task_fields(task: &NewTask) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("index", task.index.to_string()),
+        ("name", task.task.name.clone()),
+        ("createdBlock", task.task.taskCreatedBlock.to_string()),
+    ];
+    fields.extend(
+        task.block_number
+            .map(|block| ("blockNumber", block.to_string())),
+    );
+    fields.extend(task.block_hash.map(|hash| ("blockHash", hash.to_string())));
+    fields.extend(
+        task.transaction_hash
+            .map(|hash| ("transactionHash", hash.to_string())),
+    );
+    fields
+}
+
+/// Task carried by the `fields` of a stream entry
+fn parse_task(fields: &HashMap<String, String>) -> Result<NewTask, String> {
+    fn field<'a>(fields: &'a HashMap<String, String>, name: &str) -> Result<&'a str, String> {
+        fields
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing {name}"))
+    }
+    fn optional<T: std::str::FromStr>(
+        fields: &HashMap<String, String>,
+        name: &str,
+    ) -> Result<Option<T>, String> {
+        fields
+            .get(name)
+            .map(|value| value.parse().map_err(|_| format!("invalid {name} {value}")))
+            .transpose()
+    }
+    Ok(NewTask {
+        index: field(fields, "index")?
+            .parse()
+            .map_err(|_| "invalid index".to_string())?,
+        task: Task {
+            name: field(fields, "name")?.to_string(),
+            taskCreatedBlock: field(fields, "createdBlock")?
+                .parse()
+                .map_err(|_| "invalid createdBlock".to_string())?,
+        },
+        block_number: optional(fields, "blockNumber")?,
+        block_hash: optional::<B256>(fields, "blockHash")?,
+        transaction_hash: optional::<B256>(fields, "transactionHash")?,
+    })
+}
+
+/// `(id, fields)` of the entries of an XREADGROUP or XAUTOCLAIM entry list, skipping the
+/// entries deleted since they were delivered
+fn parse_entries(entries: Vec<Reply>) -> Result<Vec<StreamEntry>, WorkQueueError> {
+    let mut parsed = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let mut entry = entry.into_array()?.into_iter();
+        let (Some(id), Some(fields)) = (entry.next(), entry.next()) else {
+            return Err(WorkQueueError::Protocol(
+                "malformed stream entry".to_string(),
+            ));
+        };
+        let id = id
+            .into_string()
+            .ok_or_else(|| WorkQueueError::Protocol("malformed stream entry id".to_string()))?;
+        if fields == Reply::Array(None) {
+            continue;
+        }
+        let mut values = HashMap::new();
+        let mut fields = fields.into_array()?.into_iter();
+        while let (Some(name), Some(value)) = (fields.next(), fields.next()) {
+            if let (Some(name), Some(value)) = (name.into_string(), value.into_string()) {
+                values.insert(name, value);
+            }
+        }
+        parsed.push((id, values));
+    }
+    Ok(parsed)
+}
+
+/// Push every task of `source` onto the stream until `shutdown` triggers or the source is
+/// exhausted, for the workers of the consumer group to respond to
+pub async fn publish_tasks(
+    mut source: Box<dyn TaskSource>,
+    config: &WorkQueueConfig,
+    retry_config: &RetryConfig,
+    shutdown: Shutdown,
+) -> Result<(), OperatorError> {
+    let mut connection = connect_queue(config, retry_config).await?;
+    let max_len = config.max_len.to_string();
+    info!(stream = %config.stream, group = %config.group, "Pushing tasks onto the work queue");
+    loop {
+        let new_task = tokio::select! {
+            _ = shutdown.triggered() => return Ok(()),
+            new_task = source.next_task() => match new_task? {
+                Some(new_task) => new_task,
+                None => return Ok(()),
+            },
+        };
+        let fields = task_fields(&new_task);
+        let mut args = vec!["XADD", &config.stream, "MAXLEN", "~", &max_len, "*"];
+        for (name, value) in &fields {
+            args.extend([*name, value.as_str()]);
+        }
+        // The workers skip a task pushed twice after a reconnect, a lost one would go unanswered
+        while let Err(e) = connection.command(&args).await {
+            warn!(task_index = new_task.index, error = %e, "Failed to push the task, reconnecting");
+            connection = connect_queue(config, retry_config).await?;
+        }
+        record_work_queue_task("enqueued");
+        info!(
+            task_index = new_task.index,
+            "Task pushed onto the work queue"
+        );
+    }
+}
+
+/// Acknowledges handled tasks so the consumer group stops redelivering them, a task
+/// redelivered after a worker responded but died before acknowledging it is skipped as
+/// already responded to
+#[derive(Debug, Clone)]
+pub struct TaskAcks {
+    config: Arc<WorkQueueConfig>,
+    retry: RetryConfig,
+    connection: Arc<tokio::sync::Mutex<Option<RedisConnection>>>,
+    /// Stream entries of the delivered tasks not acknowledged yet, by task index
+    delivered: Arc<Mutex<HashMap<u32, Vec<String>>>>,
+}
+
+impl TaskAcks {
+    fn delivered(&self, task_index: u32, id: String) {
+        self.delivered
+            .lock()
+            .expect("delivered entries lock poisoned")
+            .entry(task_index)
+            .or_default()
+            .push(id);
+    }
+
+    /// Acknowledge task `task_index` once handled. A failed acknowledgement only means the
+    /// task is delivered again, and skipped as already responded to
+    pub async fn ack(&self, task_index: u32) {
+        let Some(ids) = self
+            .delivered
+            .lock()
+            .expect("delivered entries lock poisoned")
+            .remove(&task_index)
+        else {
+            return;
+        };
+        let mut connection = self.connection.lock().await;
+        let mut args = vec!["XACK", &self.config.stream, &self.config.group];
+        args.extend(ids.iter().map(String::as_str));
+        let acked = match connection.as_mut() {
+            Some(redis) => redis.command(&args).await,
+            None => Err(WorkQueueError::Protocol("not connected".to_string())),
+        };
+        if let Err(e) = acked {
+            *connection = connect_queue(&self.config, &self.retry).await.ok();
+            let retried = match connection.as_mut() {
+                Some(redis) => redis.command(&args).await.map(|_| ()),
+                None => Err(e),
+            };
+            if let Err(e) = retried {
+                warn!(task_index, error = %e, "Failed to acknowledge the task");
+                return;
+            }
+        }
+        record_work_queue_task("acked");
+    }
+}
+
+/// Tasks pulled from the stream by one worker of the consumer group, at least once: a task
+/// is delivered again to some worker until it is acknowledged through [`TaskAcks`]
+#[derive(Debug)]
+pub struct WorkQueueSource {
+    config: Arc<WorkQueueConfig>,
+    consumer: String,
+    retry: RetryConfig,
+    connection: RedisConnection,
+    pending: VecDeque<NewTask>,
+    claimed_at: Option<Instant>,
+    acks: TaskAcks,
+    health: Arc<SourceHealth>,
+}
+
+impl WorkQueueSource {
+    /// Join the consumer group of the stream in `config` as a worker
+    pub async fn connect(
+        config: &WorkQueueConfig,
+        retry_config: &RetryConfig,
+    ) -> Result<Self, WorkQueueError> {
+        let connection = connect_queue(config, retry_config).await?;
+        let config = Arc::new(config.clone());
+        let consumer = config.consumer();
+        info!(stream = %config.stream, group = %config.group, %consumer, "Pulling tasks from the work queue");
+        Ok(Self {
+            acks: TaskAcks {
+                config: config.clone(),
+                retry: retry_config.clone(),
+                connection: Arc::new(tokio::sync::Mutex::new(None)),
+                delivered: Arc::default(),
+            },
+            config,
+            consumer,
+            retry: retry_config.clone(),
+            connection,
+            pending: VecDeque::new(),
+            claimed_at: None,
+            health: Arc::new(SourceHealth::default()),
+        })
+    }
+
+    /// Entries for this worker: the ones left unacknowledged by a worker for
+    /// `claim_idle_secs` first, then new ones, waiting a while for them
+    async fn fetch(&mut self) -> Result<Vec<StreamEntry>, WorkQueueError> {
+        let claim_every = Duration::from_secs(self.config.claim_idle_secs) / 2;
+        let count = self.config.batch_size.to_string();
+        let config = self.config.clone();
+        if self
+            .claimed_at
+            .map_or(true, |at| at.elapsed() >= claim_every)
+        {
+            let min_idle = (config.claim_idle_secs * 1_000).to_string();
+            let reply = self
+                .connection
+                .command(&[
+                    "XAUTOCLAIM",
+                    &config.stream,
+                    &config.group,
+                    &self.consumer,
+                    &min_idle,
+                    "0-0",
+                    "COUNT",
+                    &count,
+                ])
+                .await?;
+            let claimed = match reply.into_array()?.into_iter().nth(1) {
+                Some(entries) => parse_entries(entries.into_array()?)?,
+                None => Vec::new(),
+            };
+            // Claimed a full batch, more may be waiting
+            if claimed.len() < self.config.batch_size {
+                self.claimed_at = Some(Instant::now());
+            }
+            if !claimed.is_empty() {
+                info!(
+                    claimed = claimed.len(),
+                    "Claimed tasks left by another worker"
+                );
+                for _ in &claimed {
+                    record_work_queue_task("claimed");
+                }
+                return Ok(claimed);
+            }
+        }
+        let block = READ_BLOCK_MS.to_string();
+        let reply = self
+            .connection
+            .command(&[
+                "XREADGROUP",
+                "GROUP",
+                &config.group,
+                &self.consumer,
+                "COUNT",
+                &count,
+                "BLOCK",
+                &block,
+                "STREAMS",
+                &config.stream,
+                ">",
+            ])
+            .await?;
+        let mut entries = Vec::new();
+        for stream in reply.into_array()? {
+            if let Some(stream_entries) = stream.into_array()?.into_iter().nth(1) {
+                entries.extend(parse_entries(stream_entries.into_array()?)?);
+            }
+        }
+        for _ in &entries {
+            record_work_queue_task("delivered");
+        }
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl TaskSource for WorkQueueSource {
+    async fn next_task(&mut self) -> Result<Option<NewTask>, OperatorError> {
+        loop {
+            if let Some(task) = self.pending.pop_front() {
+                return Ok(Some(task));
+            }
+            let entries = match self.fetch().await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!(error = %e, "Failed to pull tasks, reconnecting");
+                    self.connection = connect_queue(&self.config, &self.retry).await?;
+                    continue;
+                }
+            };
+            for (id, fields) in entries {
+                match parse_task(&fields) {
+                    Ok(task) => {
+                        if let Some(block) = task.block_number {
+                            self.health.set_synced_block(block);
+                        }
+                        self.acks.delivered(task.index, id);
+                        self.pending.push_back(task);
+                    }
+                    Err(e) => {
+                        // Never deliverable, so it is dropped rather than redelivered forever
+                        warn!(id, error = e, "Dropping a malformed work queue entry");
+                        let args = ["XACK", &self.config.stream, &self.config.group, &id];
+                        if let Err(e) = self.connection.command(&args).await {
+                            warn!(id, error = %e, "Failed to drop the entry");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn health(&self) -> Arc<SourceHealth> {
+        self.health.clone()
+    }
+
+    fn acks(&self) -> Option<TaskAcks> {
+        Some(self.acks.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream_entries_carry_tasks() {
+        let task = NewTask {
+            index: 7,
+            task: Task {
+                name: "QuickFox1".to_string(),
+                taskCreatedBlock: 12,
+            },
+            block_number: Some(13),
+            block_hash: Some(B256::repeat_byte(1)),
+            transaction_hash: None,
+        };
+        let fields = task_fields(&task);
+        let fields: Vec<&str> = fields
+            .iter()
+            .flat_map(|(name, value)| [*name, value.as_str()])
+            .collect();
+        // An XREADGROUP reply holding the entry, as the server encodes it
+        let mut reply = b"*1\r\n*2\r\n$18\r\nswap_manager:tasks\r\n".to_vec();
+        reply.extend_from_slice(b"*1\r\n*2\r\n$15\r\n1700000000000-0\r\n");
+        reply.extend_from_slice(&encode_command(&fields));
+
+        let streams = read_reply(&mut &reply[..])
+            .await
+            .unwrap()
+            .into_array()
+            .unwrap();
+        let entries = streams[0].clone().into_array().unwrap().remove(1);
+        let parsed = parse_entries(entries.into_array().unwrap()).unwrap();
+        assert_eq!(parsed[0].0, "1700000000000-0");
+        let decoded = parse_task(&parsed[0].1).unwrap();
+        assert_eq!(decoded.index, 7);
+        assert_eq!(decoded.task.name, "QuickFox1");
+        assert_eq!(decoded.task.taskCreatedBlock, 12);
+        assert_eq!(decoded.block_hash, task.block_hash);
+        assert_eq!(decoded.transaction_hash, None);
+
+        let mut error = &b"-BUSYGROUP Consumer Group name already exists\r\n"[..];
+        assert!(matches!(
+            read_reply(&mut error).await.unwrap(),
+            Reply::Error(e) if e.starts_with("BUSYGROUP")
+        ));
+    }
+}
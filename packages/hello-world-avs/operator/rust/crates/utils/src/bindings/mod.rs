@@ -1,7 +1,8 @@
-#![allow(unused_imports, clippy::all, rustdoc::all)]
+#![allow(unused_imports, non_snake_case, clippy::all, rustdoc::all)]
 //! This module contains the sol! generated bindings for solidity contracts.
 //! This is autogenerated code.
 //! Do not manually edit these files.
 //! These files may be overwritten by the codegen system at any time.
 pub mod r#ecdsastakeregistry;
+#[path = "helloworldservicemanager.rs"]
 pub mod r#SwapManager;
@@ -1,26 +1,72 @@
 #![cfg(not(doctest))]
+#[rustfmt::skip]
 mod bindings;
 pub use bindings::*;
 
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use alloy::primitives::Address;
+use alloy::primitives::{hex::FromHexError, Address};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use thiserror::Error;
 
-#[derive(Deserialize, Debug)]
+/// Failure to find, read or use a deployment
+#[derive(Debug, Error)]
+pub enum DeploymentError {
+    #[error("failed to read {}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid {}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error(
+        "no bundled deployment for chain {chain_id} ({network}) in {}, deploy the contracts or \
+         pass a deployment file",
+        dir.display()
+    )]
+    NotBundled {
+        chain_id: u64,
+        network: String,
+        dir: PathBuf,
+    },
+    #[error("deployment is for chain {deployment} but the RPC endpoint serves chain {rpc}")]
+    ChainMismatch { deployment: u64, rpc: u64 },
+    #[error("invalid contract address")]
+    Address(#[from] FromHexError),
+    #[error("unknown network {0}")]
+    UnknownNetwork(String),
+    #[error("the deployment has no {0} address")]
+    MissingAddress(&'static str),
+    #[error("invalid deployment block number {0}")]
+    BlockNumber(String),
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct SwapManagerData {
     #[serde(rename = "lastUpdate")]
     pub last_update: LastUpdate,
     pub addresses: SwapManagerAddresses,
+    /// Scheme the service manager verifies the task responses with, `ecdsa` or `bls`,
+    /// ECDSA if unset
+    #[serde(rename = "signatureScheme", default)]
+    pub signature_scheme: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct LastUpdate {
     pub timestamp: String,
     pub block_number: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct SwapManagerAddresses {
     #[serde(rename = "proxyAdmin")]
     pub proxy_admin: String,
@@ -36,14 +82,14 @@ pub struct SwapManagerAddresses {
     pub token: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct EigenLayerData {
     #[serde(rename = "lastUpdate")]
     pub last_update: LastUpdate,
     pub addresses: EigenLayerAddresses,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct EigenLayerAddresses {
     #[serde(rename = "proxyAdmin")]
     pub proxy_admin: String,
@@ -69,45 +115,284 @@ pub struct EigenLayerAddresses {
     pub strategy_factory_impl: String,
     #[serde(rename = "strategyBeacon")]
     pub strategy_beacon: String,
+    #[serde(rename = "rewardsCoordinator", default)]
+    pub rewards_coordinator: Option<String>,
+}
+
+/// Networks the deployment registry knows by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Sepolia,
+    Holesky,
+    Anvil,
+}
+
+impl Network {
+    pub const ALL: [Network; 4] = [
+        Network::Mainnet,
+        Network::Sepolia,
+        Network::Holesky,
+        Network::Anvil,
+    ];
+
+    pub fn chain_id(self) -> u64 {
+        match self {
+            Network::Mainnet => 1,
+            Network::Sepolia => 11_155_111,
+            Network::Holesky => 17_000,
+            Network::Anvil => 31_337,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Sepolia => "sepolia",
+            Network::Holesky => "holesky",
+            Network::Anvil => "anvil",
+        }
+    }
+
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|network| network.chain_id() == chain_id)
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Network {
+    type Err = DeploymentError;
+
+    fn from_str(s: &str) -> Result<Self, DeploymentError> {
+        Self::ALL
+            .into_iter()
+            .find(|network| network.name() == s)
+            .ok_or_else(|| DeploymentError::UnknownNetwork(s.to_string()))
+    }
+}
+
+/// EigenLayer core and SwapManager contracts deployed on one chain
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    pub chain_id: u64,
+    /// Contents of `deployments/core/<chain id>.json`
+    pub core: EigenLayerData,
+    /// Contents of `deployments/swap-manager/<chain id>.json`
+    pub swap_manager: SwapManagerData,
+}
+
+impl Deployment {
+    /// The deployment the contract scripts wrote for `chain_id` under `contracts/deployments`
+    pub fn bundled(chain_id: u64) -> Result<Self, DeploymentError> {
+        let network = Network::from_chain_id(chain_id).map_or_else(
+            || "unknown network".to_string(),
+            |network| network.to_string(),
+        );
+        let core_path = deployment_path("core", chain_id);
+        let swap_manager_path = deployment_path("swap-manager", chain_id);
+        if !core_path.exists() || !swap_manager_path.exists() {
+            return Err(DeploymentError::NotBundled {
+                chain_id,
+                network,
+                dir: deployments_dir(),
+            });
+        }
+        Ok(Self {
+            chain_id,
+            core: read_json(&core_path)?,
+            swap_manager: read_json(&swap_manager_path)?,
+        })
+    }
+
+    /// A deployment file holding `chainId` and the `core` and `swapManager` deployments
+    pub fn from_file(path: &Path) -> Result<Self, DeploymentError> {
+        read_json(path)
+    }
+
+    /// Fail unless the deployment is for the chain `chain_id` the RPC endpoint serves
+    pub fn check_chain_id(&self, chain_id: u64) -> Result<(), DeploymentError> {
+        if self.chain_id != chain_id {
+            return Err(DeploymentError::ChainMismatch {
+                deployment: self.chain_id,
+                rpc: chain_id,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn network(&self) -> Option<Network> {
+        Network::from_chain_id(self.chain_id)
+    }
+
+    pub fn swap_manager_address(&self) -> Result<Address, DeploymentError> {
+        Ok(self
+            .swap_manager
+            .addresses
+            .swap_manager_service_manager
+            .parse()?)
+    }
+
+    pub fn stake_registry_address(&self) -> Result<Address, DeploymentError> {
+        Ok(self.swap_manager.addresses.stake_registry.parse()?)
+    }
+
+    pub fn delegation_manager_address(&self) -> Result<Address, DeploymentError> {
+        Ok(self.core.addresses.delegation_manager.parse()?)
+    }
+
+    pub fn avs_directory_address(&self) -> Result<Address, DeploymentError> {
+        Ok(self.core.addresses.avs_directory.parse()?)
+    }
+
+    pub fn rewards_coordinator_address(&self) -> Result<Address, DeploymentError> {
+        let address = self
+            .core
+            .addresses
+            .rewards_coordinator
+            .as_deref()
+            .ok_or(DeploymentError::MissingAddress("rewardsCoordinator"))?;
+        Ok(address.parse()?)
+    }
+
+    /// Block the EigenLayer core contracts were deployed in, the rewards coordinator
+    /// included, from `lastUpdate` of the core deployment
+    pub fn core_block(&self) -> Result<u64, DeploymentError> {
+        let block_number = &self.core.last_update.block_number;
+        block_number
+            .parse()
+            .map_err(|_| DeploymentError::BlockNumber(block_number.clone()))
+    }
+
+    /// `signatureScheme` of the swap manager deployment, if it names one
+    pub fn signature_scheme(&self) -> Option<&str> {
+        self.swap_manager.signature_scheme.as_deref()
+    }
 }
 
-pub fn get_anvil_eigenlayer_deployment_data() -> eyre::Result<EigenLayerData> {
-    let file_path = Path::new(&env!("CARGO_MANIFEST_DIR").to_string())
-        .join("../../../../contracts/deployments/core/31337.json");
-    let data = std::fs::read_to_string(file_path)?;
-    let el_parsed: EigenLayerData = serde_json::from_str(&data)?;
-    Ok(el_parsed)
+fn deployments_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../../contracts/deployments")
 }
 
-pub fn get_anvil_swap_manager_deployment_data() -> eyre::Result<SwapManagerData> {
-    let file_path = Path::new(&env!("CARGO_MANIFEST_DIR").to_string())
-        .join("../../../../contracts/deployments/swap-manager/31337.json");
-    let data = std::fs::read_to_string(file_path)?;
-    let parsed_data: SwapManagerData = serde_json::from_str(&data)?;
-    Ok(parsed_data)
+fn deployment_path(kind: &str, chain_id: u64) -> PathBuf {
+    deployments_dir()
+        .join(kind)
+        .join(format!("{chain_id}.json"))
 }
 
-pub fn get_swap_manager_service_manager() -> eyre::Result<Address> {
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, DeploymentError> {
+    let data = std::fs::read_to_string(path).map_err(|source| DeploymentError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    serde_json::from_str(&data).map_err(|source| DeploymentError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn get_anvil_eigenlayer_deployment_data() -> Result<EigenLayerData, DeploymentError> {
+    read_json(&deployment_path("core", Network::Anvil.chain_id()))
+}
+
+pub fn get_anvil_swap_manager_deployment_data() -> Result<SwapManagerData, DeploymentError> {
+    read_json(&deployment_path("swap-manager", Network::Anvil.chain_id()))
+}
+
+pub fn get_swap_manager_service_manager() -> Result<Address, DeploymentError> {
     let data = get_anvil_swap_manager_deployment_data()?;
     let swap_manager_contract_address: Address =
         data.addresses.swap_manager_service_manager.parse()?;
     Ok(swap_manager_contract_address)
 }
 
-pub fn get_stake_registry_address() -> eyre::Result<Address> {
+pub fn get_stake_registry_address() -> Result<Address, DeploymentError> {
     let data = get_anvil_swap_manager_deployment_data()?;
     let stake_registry_address: Address = data.addresses.stake_registry.parse()?;
     Ok(stake_registry_address)
 }
 
-pub fn get_delegation_manager_address() -> eyre::Result<Address> {
+pub fn get_delegation_manager_address() -> Result<Address, DeploymentError> {
     let data = get_anvil_eigenlayer_deployment_data()?;
     let delegation_manager_address: Address = data.addresses.delegation_manager.parse()?;
     Ok(delegation_manager_address)
 }
 
-pub fn get_avs_directory_address() -> eyre::Result<Address> {
+pub fn get_avs_directory_address() -> Result<Address, DeploymentError> {
     let data = get_anvil_eigenlayer_deployment_data()?;
     let avs_directory_address: Address = data.addresses.avs_directory.parse()?;
     Ok(avs_directory_address)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deployment_file_must_match_the_chain() {
+        let json = r#"{
+            "chainId": 17000,
+            "core": {
+                "lastUpdate": {"timestamp": "0", "block_number": "1234"},
+                "addresses": {
+                    "proxyAdmin": "0x0000000000000000000000000000000000000001",
+                    "delegationManager": "0x0000000000000000000000000000000000000002",
+                    "delegationManagerImpl": "0x0000000000000000000000000000000000000003",
+                    "avsDirectory": "0x0000000000000000000000000000000000000004",
+                    "avsDirectoryImpl": "0x0000000000000000000000000000000000000005",
+                    "strategyManager": "0x0000000000000000000000000000000000000006",
+                    "strategyManagerImpl": "0x0000000000000000000000000000000000000007",
+                    "eigenPodManager": "0x0000000000000000000000000000000000000008",
+                    "eigenPodManagerImpl": "0x0000000000000000000000000000000000000009",
+                    "strategyFactory": "0x000000000000000000000000000000000000000a",
+                    "strategyFactoryImpl": "0x000000000000000000000000000000000000000b",
+                    "strategyBeacon": "0x000000000000000000000000000000000000000c"
+                }
+            },
+            "swapManager": {
+                "lastUpdate": {"timestamp": "0", "block_number": "0"},
+                "addresses": {
+                    "proxyAdmin": "0x0000000000000000000000000000000000000011",
+                    "SwapManager": "0x0000000000000000000000000000000000000012",
+                    "SwapManagerImpl": "0x0000000000000000000000000000000000000013",
+                    "stakeRegistry": "0x0000000000000000000000000000000000000014",
+                    "stakeRegistryImpl": "0x0000000000000000000000000000000000000015",
+                    "strategy": "0x0000000000000000000000000000000000000016",
+                    "token": "0x0000000000000000000000000000000000000017"
+                }
+            }
+        }"#;
+        let deployment: Deployment = serde_json::from_str(json).unwrap();
+
+        assert_eq!(deployment.network(), Some(Network::Holesky));
+        assert_eq!(
+            deployment.swap_manager_address().unwrap(),
+            Address::with_last_byte(0x12)
+        );
+        assert_eq!(
+            deployment.avs_directory_address().unwrap(),
+            Address::with_last_byte(4)
+        );
+        assert_eq!(deployment.core_block().unwrap(), 1234);
+        assert!(matches!(
+            deployment.rewards_coordinator_address(),
+            Err(DeploymentError::MissingAddress("rewardsCoordinator"))
+        ));
+        assert!(deployment.check_chain_id(17_000).is_ok());
+        assert!(matches!(
+            deployment.check_chain_id(1),
+            Err(DeploymentError::ChainMismatch {
+                deployment: 17_000,
+                rpc: 1
+            })
+        ));
+        assert_eq!("sepolia".parse::<Network>().unwrap().chain_id(), 11_155_111);
+    }
+}